@@ -0,0 +1,67 @@
+//! Decides the KZG accumulator exposed by ezkl's aggregated proofs, with only `core` and the curve
+//! arithmetic: no allocation, io or threading, for embedded and enclave targets. Build it without
+//! the default `std` feature to get a `no_std` crate. ezkl re-exports it as
+//! `ezkl::pfsys::accumulator_decider`, where the std wrappers load proofs and keys and call into it.
+//!
+//! This is not a proof verifier on its own. The succinct part of the verification (checking the
+//! PLONK proof and its transcript) is done by the halo2 verifier, which requires std, and only
+//! attests that the accumulator was computed correctly. A device that only runs this crate has to
+//! trust whoever ran that part, and the accumulator it decides is only as good as that check.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(missing_docs, unsafe_code)]
+
+use halo2curves::bn256::{Bn256, Fq, Fr, G1Affine, G2Affine, G2Prepared, Gt};
+use halo2curves::ff::{Field, PrimeField};
+use halo2curves::group::Group;
+use halo2curves::pairing::{MillerLoopResult, MultiMillerLoop};
+use halo2curves::CurveAffine;
+
+/// Number of limbs each accumulator coordinate is split into in the aggregated proof instances.
+pub const ACCUMULATOR_LIMBS: usize = 4;
+/// Bit size of each accumulator limb.
+pub const ACCUMULATOR_LIMB_BITS: usize = 68;
+/// Number of instances holding the accumulator, at the start of an aggregated proof's instances.
+pub const ACCUMULATOR_INSTANCES: usize = 4 * ACCUMULATOR_LIMBS;
+
+/// Recomposes a base field element from its little endian limbs.
+fn limbs_to_fq(limbs: &[Fr]) -> Option<Fq> {
+    let shift = Fq::from(2).pow_vartime([ACCUMULATOR_LIMB_BITS as u64]);
+    let mut acc = Fq::ZERO;
+    for limb in limbs.iter().rev() {
+        let limb = Option::from(Fq::from_repr(limb.to_repr()))?;
+        acc = acc * shift + limb;
+    }
+    Some(acc)
+}
+
+/// Decodes the `(lhs, rhs)` KZG accumulator from the instances of an aggregated proof.
+pub fn decode_accumulator(instances: &[Fr]) -> Option<(G1Affine, G1Affine)> {
+    if instances.len() < ACCUMULATOR_INSTANCES {
+        return None;
+    }
+    let mut coordinates = instances[..ACCUMULATOR_INSTANCES].chunks_exact(ACCUMULATOR_LIMBS);
+    let mut next = || limbs_to_fq(coordinates.next()?);
+    let (lhs_x, lhs_y, rhs_x, rhs_y) = (next()?, next()?, next()?, next()?);
+    let lhs = Option::from(G1Affine::from_xy(lhs_x, lhs_y))?;
+    let rhs = Option::from(G1Affine::from_xy(rhs_x, rhs_y))?;
+    Some((lhs, rhs))
+}
+
+/// The KZG decider: checks `e(lhs, g2) == e(rhs, s·g2)`.
+pub fn check_accumulator(lhs: &G1Affine, rhs: &G1Affine, g2: &G2Affine, s_g2: &G2Affine) -> bool {
+    let g2 = G2Prepared::from(*g2);
+    let minus_s_g2 = G2Prepared::from(-*s_g2);
+    let result: Gt =
+        Bn256::multi_miller_loop(&[(lhs, &g2), (rhs, &minus_s_g2)]).final_exponentiation();
+    result.is_identity().into()
+}
+
+/// Decodes and decides the accumulator of an aggregated proof, returns false if the instances
+/// don't hold a valid accumulator.
+pub fn verify_accumulator(instances: &[Fr], g2: &G2Affine, s_g2: &G2Affine) -> bool {
+    match decode_accumulator(instances) {
+        Some((lhs, rhs)) => check_accumulator(&lhs, &rhs, g2, s_g2),
+        None => false,
+    }
+}
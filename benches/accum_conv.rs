@@ -154,6 +154,7 @@ fn runcnvrl(c: &mut Criterion) {
                         TranscriptType::EVM,
                         None,
                         None,
+                        None,
                     );
                     prover.unwrap();
                 });
@@ -123,6 +123,7 @@ fn rundot(c: &mut Criterion) {
                     TranscriptType::EVM,
                     None,
                     None,
+                    None,
                 );
                 prover.unwrap();
             });
@@ -127,6 +127,7 @@ fn runmatmul(c: &mut Criterion) {
                     TranscriptType::EVM,
                     None,
                     None,
+                    None,
                 );
                 prover.unwrap();
             });
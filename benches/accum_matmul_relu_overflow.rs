@@ -148,6 +148,7 @@ fn runmatmul(c: &mut Criterion) {
                     TranscriptType::EVM,
                     None,
                     None,
+                    None,
                 );
                 prover.unwrap();
             });
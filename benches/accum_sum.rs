@@ -119,6 +119,7 @@ fn runsum(c: &mut Criterion) {
                     TranscriptType::EVM,
                     None,
                     None,
+                    None,
                 );
                 prover.unwrap();
             });
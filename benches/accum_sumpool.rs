@@ -134,6 +134,7 @@ fn runsumpool(c: &mut Criterion) {
                         TranscriptType::EVM,
                         None,
                         None,
+                        None,
                     );
                     prover.unwrap();
                 });
@@ -0,0 +1,196 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ezkl::circuit::lookup::LookupOp;
+use ezkl::circuit::poly::PolyOp;
+use ezkl::circuit::table::Range;
+use ezkl::circuit::*;
+use ezkl::pfsys::create_proof_circuit;
+use ezkl::pfsys::TranscriptType;
+use ezkl::pfsys::{create_keys, srs::gen_srs};
+use ezkl::tensor::*;
+use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256::{Bn256, Fr};
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+
+const BITS: Range = (-32768, 32768);
+const K: usize = 17;
+const NUM_INNER_COLS: usize = 4;
+const CHANNELS: usize = 2;
+const IMAGE_SIZE: usize = 16;
+
+#[derive(Clone)]
+struct MyCircuit {
+    image: ValTensor<Fr>,
+    kernels: [ValTensor<Fr>; 2],
+    biases: [ValTensor<Fr>; 2],
+    order: AssignmentOrder,
+}
+
+// A ResNet basic block: conv -> relu -> conv -> add residual -> relu
+impl Circuit<Fr> for MyCircuit {
+    type Config = BaseConfig<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(cs: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let capacity = 1 << (K - 1);
+
+        let a = VarTensor::new_advice(cs, K, NUM_INNER_COLS, capacity);
+        let b = VarTensor::new_advice(cs, K, NUM_INNER_COLS, capacity);
+        let output = VarTensor::new_advice(cs, K, NUM_INNER_COLS, capacity);
+
+        let mut config =
+            BaseConfig::configure(cs, &[a.clone(), b.clone()], &output, CheckMode::UNSAFE);
+
+        config
+            .configure_lookup(cs, &b, &output, &a, BITS, K, &LookupOp::ReLU)
+            .unwrap();
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.layout_tables(&mut layouter).unwrap();
+        layouter.assign_region(
+            || "",
+            |region| {
+                let mut region = region::RegionCtx::new(region, 0, NUM_INNER_COLS);
+                region.set_assignment_order(self.order);
+                let conv = || PolyOp::Conv {
+                    padding: [(1, 1); 2],
+                    stride: (1, 1),
+                };
+
+                let x = config
+                    .layout(
+                        &mut region,
+                        &[
+                            self.image.clone(),
+                            self.kernels[0].clone(),
+                            self.biases[0].clone(),
+                        ],
+                        Box::new(conv()),
+                    )
+                    .unwrap()
+                    .unwrap();
+                let x = config
+                    .layout(&mut region, &[x], Box::new(LookupOp::ReLU))
+                    .unwrap()
+                    .unwrap();
+                let x = config
+                    .layout(
+                        &mut region,
+                        &[x, self.kernels[1].clone(), self.biases[1].clone()],
+                        Box::new(conv()),
+                    )
+                    .unwrap()
+                    .unwrap();
+                let x = config
+                    .layout(&mut region, &[x, self.image.clone()], Box::new(PolyOp::Add))
+                    .unwrap()
+                    .unwrap();
+                let _output = config
+                    .layout(&mut region, &[x], Box::new(LookupOp::ReLU))
+                    .unwrap();
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+fn runresnetblock(c: &mut Criterion) {
+    let mut group = c.benchmark_group("assignment_order");
+
+    let params = gen_srs::<KZGCommitmentScheme<_>>(K as u32);
+
+    let mut image =
+        Tensor::from((0..CHANNELS * IMAGE_SIZE * IMAGE_SIZE).map(|_| Value::known(Fr::from(1))));
+    image
+        .reshape(&[1, CHANNELS, IMAGE_SIZE, IMAGE_SIZE])
+        .unwrap();
+
+    let kernel = || {
+        let mut kernel = Tensor::from((0..CHANNELS * CHANNELS * 9).map(|_| Fr::from(1)));
+        kernel.reshape(&[CHANNELS, CHANNELS, 3, 3]).unwrap();
+        kernel.set_visibility(&ezkl::graph::Visibility::Private);
+        ValTensor::try_from(kernel).unwrap()
+    };
+    let bias = || {
+        let mut bias = Tensor::from((0..CHANNELS).map(|_| Fr::from(1)));
+        bias.set_visibility(&ezkl::graph::Visibility::Private);
+        ValTensor::try_from(bias).unwrap()
+    };
+
+    let circuit = MyCircuit {
+        image: ValTensor::from(image),
+        kernels: [kernel(), kernel()],
+        biases: [bias(), bias()],
+        order: AssignmentOrder::default(),
+    };
+
+    let pk = create_keys::<KZGCommitmentScheme<Bn256>, MyCircuit>(&circuit, &params, true).unwrap();
+
+    for (name, order) in [
+        ("row-major", AssignmentOrder::RowMajor),
+        ("column-major", AssignmentOrder::ColumnMajor),
+        ("auto", AssignmentOrder::Auto),
+    ] {
+        let circuit = MyCircuit {
+            order,
+            ..circuit.clone()
+        };
+
+        group.throughput(Throughput::Elements(
+            (CHANNELS * IMAGE_SIZE * IMAGE_SIZE) as u64,
+        ));
+        group.bench_with_input(BenchmarkId::new("prove", name), &name, |b, &_| {
+            b.iter(|| {
+                let prover = create_proof_circuit::<
+                    KZGCommitmentScheme<_>,
+                    MyCircuit,
+                    ProverSHPLONK<_>,
+                    VerifierSHPLONK<_>,
+                    SingleStrategy<_>,
+                    _,
+                    EvmTranscript<_, _, _, _>,
+                    EvmTranscript<_, _, _, _>,
+                >(
+                    circuit.clone(),
+                    vec![],
+                    &params,
+                    &pk,
+                    CheckMode::UNSAFE,
+                    ezkl::Commitments::KZG,
+                    TranscriptType::EVM,
+                    None,
+                    None,
+                    None,
+                );
+                prover.unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default().with_plots();
+  targets = runresnetblock
+}
+criterion_main!(benches);
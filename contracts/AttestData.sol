@@ -4,7 +4,7 @@ import './LoadInstances.sol';
 
 // This contract serves as a Data Attestation Verifier for the EZKL model.
 // It is designed to read and attest to instances of proofs generated from a specified circuit.
-// It is particularly constructed to read only int256 data from specified on-chain contracts' view functions.
+// It is particularly constructed to read only int256 or uint256 data from specified on-chain contracts' view functions.
 
 // Overview of the contract functionality:
 // 1. Initialization: Through the constructor, it sets up the contract calls that the EZKL model will read from.
@@ -25,6 +25,7 @@ contract DataAttestation is LoadInstances {
         address contractAddress;
         mapping(uint256 => bytes) callData;
         mapping(uint256 => uint256) decimals;
+        mapping(uint256 => bool) isUnsigned;
         uint callCount;
     }
     AccountCall[] public accountCalls;
@@ -50,11 +51,14 @@ contract DataAttestation is LoadInstances {
      * @dev Initialize the contract with account calls the EZKL model will read from.
      * @param _contractAddresses - The calls to all the contracts EZKL reads storage from.
      * @param _callData - The abi encoded function calls to make to the `contractAddress` that EZKL reads storage from.
+     * @param _decimals - The number of decimals the data returned from each call has.
+     * @param _isUnsigned - Whether each call returns a uint256 rather than an int256.
      */
     constructor(
         address[] memory _contractAddresses,
         bytes[][] memory _callData,
         uint256[][] memory _decimals,
+        bool[][] memory _isUnsigned,
         uint[] memory _scales,
         uint8 _instanceOffset,
         address _admin
@@ -63,7 +67,7 @@ contract DataAttestation is LoadInstances {
         for (uint i; i < _scales.length; i++) {
             scales.push(1 << _scales[i]);
         }
-        populateAccountCalls(_contractAddresses, _callData, _decimals);
+        populateAccountCalls(_contractAddresses, _callData, _decimals, _isUnsigned);
         instanceOffset = _instanceOffset;
     }
 
@@ -78,16 +82,18 @@ contract DataAttestation is LoadInstances {
     function updateAccountCalls(
         address[] memory _contractAddresses,
         bytes[][] memory _callData,
-        uint256[][] memory _decimals
+        uint256[][] memory _decimals,
+        bool[][] memory _isUnsigned
     ) external {
         require(msg.sender == admin, "Only admin can update instanceOffset");
-        populateAccountCalls(_contractAddresses, _callData, _decimals);
+        populateAccountCalls(_contractAddresses, _callData, _decimals, _isUnsigned);
     }
 
     function populateAccountCalls(
         address[] memory _contractAddresses,
         bytes[][] memory _callData,
-        uint256[][] memory _decimals
+        uint256[][] memory _decimals,
+        bool[][] memory _isUnsigned
     ) internal {
         require(
             _contractAddresses.length == _callData.length &&
@@ -98,6 +104,10 @@ contract DataAttestation is LoadInstances {
             _decimals.length == _contractAddresses.length,
             "Invalid number of decimals"
         );
+        require(
+            _isUnsigned.length == _contractAddresses.length,
+            "Invalid number of signedness flags"
+        );
         // fill in the accountCalls storage array
         uint counter = 0;
         for (uint256 i = 0; i < _contractAddresses.length; i++) {
@@ -107,6 +117,7 @@ contract DataAttestation is LoadInstances {
             for (uint256 j = 0; j < _callData[i].length; j++) {
                 accountCall.callData[j] = _callData[i][j];
                 accountCall.decimals[j] = 10 ** _decimals[i][j];
+                accountCall.isUnsigned[j] = _isUnsigned[i][j];
             }
             // count the total number of storage reads across all of the accounts
             counter += _callData[i].length;
@@ -168,19 +179,28 @@ contract DataAttestation is LoadInstances {
      * @param data - The data returned from the account calls.
      * @param decimals - The number of decimals the data returned from the account calls has (for floating point representation).
      * @param scale - The scale used to convert the floating point value into a fixed point value. 
+     * @param isUnsigned - Whether the data is a uint256 rather than an int256.
      */
     function quantizeData(
         bytes memory data,
         uint256 decimals,
-        uint256 scale
+        uint256 scale,
+        bool isUnsigned
     ) internal pure returns (int256 quantized_data) {
-        int x = abi.decode(data, (int256));
-        bool neg = x < 0;
-        if (neg) x = -x;
-        uint output = mulDiv(uint256(x), scale, decimals);
-        if (mulmod(uint256(x), scale, decimals) * 2 >= decimals) {
+        uint x;
+        bool neg;
+        if (isUnsigned) {
+            x = abi.decode(data, (uint256));
+        } else {
+            int signed = abi.decode(data, (int256));
+            neg = signed < 0;
+            x = uint256(neg ? -signed : signed);
+        }
+        uint output = mulDiv(x, scale, decimals);
+        if (mulmod(x, scale, decimals) * 2 >= decimals) {
             output += 1;
         }
+        require(output <= uint256(type(int256).max), "Quantized data overflows int256");
         quantized_data = neg ? -int256(output): int256(output);
     }
     /**
@@ -239,7 +259,8 @@ contract DataAttestation is LoadInstances {
                 int256 quantized_data = quantizeData(
                     returnData,
                     accountCalls[i].decimals[j],
-                    scale
+                    scale,
+                    accountCalls[i].isUnsigned[j]
                 );
                 uint256 field_element = toFieldElement(quantized_data);
                 require(
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-3.0
+
+pragma solidity ^0.8.17;
+
+/**
+ * @notice Records proofs posted as EIP-4844 blobs, so consumers can fetch a verified proof from the
+ * blob rather than from contract storage. The blob of the posting transaction holds the instances
+ * of the proof, one per field element, then the hash of the proof's bytes, then their length and
+ * the bytes themselves (see `ezkl::eth::proof_to_blob`). `postProof` verifies the proof it is given
+ * with the Halo2 verifier, checks against the blob's commitment, with the point evaluation
+ * precompile, that the blob holds its instances and its hash, and records the instances under the
+ * blob's versioned hash.
+ * @dev Generated by `ezkl create-evm-blob-verifier`, which sets NUM_INSTANCES from the circuit's settings.
+ * Needs a chain with the Cancun hardfork. The contract can't read the blob, so it doesn't check the
+ * bytes past the hash: consumers check them against it, as `ezkl::eth::blob_to_proof` does.
+ */
+contract EzklBlobVerifier {
+    uint256 internal constant NUM_INSTANCES = 1;
+
+    uint256 internal constant BLS_MODULUS =
+        52435875175126190479447740508185965837690552500527637822603658699938581184513;
+    uint256 internal constant FIELD_ELEMENTS_PER_BLOB = 4096;
+
+    /**
+     * @notice A primitive 4096th root of unity of the BLS12-381 scalar field, 7^((BLS_MODULUS - 1) / 4096)
+     */
+    uint256 internal constant ROOT_OF_UNITY =
+        39033254847818212395286706435128746857159659164139250548781411570340225835782;
+
+    address internal constant POINT_EVALUATION = address(0x0a);
+
+    /**
+     * @notice The verifier of the circuit's proofs
+     */
+    address public immutable verifier;
+
+    /**
+     * @notice Returns the versioned hash of the blob of the transaction at the index in its calldata,
+     * as the BLOBHASH opcode predates the compilers this contract targets
+     */
+    address public immutable blobHashReader;
+
+    /**
+     * @notice The hash of the instances of the proof in each posted blob
+     */
+    mapping(bytes32 => bytes32) public postedInstances;
+
+    event ProofPosted(bytes32 indexed versionedHash, uint256[] instances);
+
+    constructor(address verifier_) {
+        require(verifier_.code.length > 0, "EzklBlobVerifier: verifier is not a contract");
+        verifier = verifier_;
+        // PUSH1 0 CALLDATALOAD BLOBHASH PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN, behind its init code
+        bytes memory initCode = hex"600c600c600039600c6000f36000354960005260206000f3";
+        address reader;
+        assembly {
+            reader := create(0, add(initCode, 0x20), mload(initCode))
+        }
+        require(reader != address(0), "EzklBlobVerifier: could not deploy the blob hash reader");
+        blobHashReader = reader;
+    }
+
+    /**
+     * @notice The point the field element at `index` of a blob is the evaluation of its polynomial at,
+     * the roots of unity being in bit reversed order
+     */
+    function evaluationPoint(uint256 index) public view returns (uint256) {
+        uint256 exponent = 0;
+        for (uint256 bit = 0; bit < 12; bit++) {
+            exponent = (exponent << 1) | ((index >> bit) & 1);
+        }
+        (bool success, bytes memory result) = address(0x05).staticcall(
+            abi.encode(32, 32, 32, ROOT_OF_UNITY, exponent, BLS_MODULUS)
+        );
+        require(success, "EzklBlobVerifier: modexp failed");
+        return abi.decode(result, (uint256));
+    }
+
+    /**
+     * @notice Checks with the point evaluation precompile that the field element at `index` of the
+     * blob with `versionedHash` is `value`
+     */
+    function checkElement(
+        bytes32 versionedHash,
+        uint256 index,
+        uint256 value,
+        bytes calldata commitment,
+        bytes calldata kzgProof
+    ) internal view {
+        (bool success, bytes memory result) = POINT_EVALUATION.staticcall(
+            abi.encodePacked(versionedHash, evaluationPoint(index), value, commitment, kzgProof)
+        );
+        require(success && result.length == 64, "EzklBlobVerifier: the blob does not hold the proof");
+        (uint256 elements, uint256 modulus) = abi.decode(result, (uint256, uint256));
+        require(
+            elements == FIELD_ELEMENTS_PER_BLOB && modulus == BLS_MODULUS,
+            "EzklBlobVerifier: unexpected point evaluation result"
+        );
+    }
+
+    /**
+     * @notice The field element of a blob holding `proof`: its keccak256 hash, less its last byte so
+     * it stays below the BLS12-381 modulus
+     */
+    function proofHashElement(bytes calldata proof) public pure returns (uint256) {
+        return uint256(keccak256(proof)) >> 8;
+    }
+
+    /**
+     * @notice Verifies `proof` and records its instances, held with its hash by the first blob of
+     * the transaction, given the blob's KZG commitment and the KZG proofs of its first
+     * NUM_INSTANCES + 1 field elements
+     */
+    function postProof(
+        bytes calldata proof,
+        uint256[] calldata instances,
+        bytes calldata commitment,
+        bytes[] calldata kzgProofs
+    ) external returns (bytes32 versionedHash) {
+        require(
+            instances.length == NUM_INSTANCES && kzgProofs.length == NUM_INSTANCES + 1,
+            "EzklBlobVerifier: wrong number of instances"
+        );
+        (bool success, bytes memory result) = verifier.call(
+            abi.encodeWithSignature("verifyProof(bytes,uint256[])", proof, instances)
+        );
+        require(
+            success && result.length == 32 && abi.decode(result, (bool)),
+            "EzklBlobVerifier: invalid proof"
+        );
+
+        (success, result) = blobHashReader.staticcall(abi.encode(uint256(0)));
+        require(success, "EzklBlobVerifier: could not read the blob hash");
+        versionedHash = abi.decode(result, (bytes32));
+        require(versionedHash != bytes32(0), "EzklBlobVerifier: the transaction carries no blob");
+
+        for (uint256 i = 0; i < NUM_INSTANCES; i++) {
+            checkElement(versionedHash, i, instances[i], commitment, kzgProofs[i]);
+        }
+        checkElement(
+            versionedHash,
+            NUM_INSTANCES,
+            proofHashElement(proof),
+            commitment,
+            kzgProofs[NUM_INSTANCES]
+        );
+
+        postedInstances[versionedHash] = keccak256(abi.encodePacked(instances));
+        emit ProofPosted(versionedHash, instances);
+    }
+
+    /**
+     * @notice Whether the blob with `versionedHash` was posted with a valid proof of `instances`
+     */
+    function isPosted(
+        bytes32 versionedHash,
+        uint256[] calldata instances
+    ) external view returns (bool) {
+        bytes32 posted = postedInstances[versionedHash];
+        return posted != bytes32(0) && posted == keccak256(abi.encodePacked(instances));
+    }
+}
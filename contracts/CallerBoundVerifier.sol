@@ -0,0 +1,41 @@
+/**
+ * @notice Verifies proofs bound to a caller (gen-settings --bind-caller), whose last public instance
+ * is the address the proof was generated for, so that a proof generated for one account can't be
+ * replayed by another. It deploys the rendered verifier, renamed Halo2VerifierCore, and checks the
+ * bound address before forwarding proofs to it. Helpers rendered into the verifier (e.g validCount)
+ * are called on `verifier`.
+ * @dev `verifyProof` checks against `msg.sender`. Contracts relaying proofs on behalf of their own
+ * callers use `verifyProofFor`, which checks against the supplied `caller`.
+ */
+contract Halo2Verifier {
+    address public immutable verifier;
+
+    constructor() {
+        verifier = address(new Halo2VerifierCore());
+    }
+
+    function verifyProof(
+        bytes calldata proof,
+        uint256[] calldata instances
+    ) external returns (bool) {
+        return verifyProofFor(msg.sender, proof, instances);
+    }
+
+    function verifyProofFor(
+        address caller,
+        bytes calldata proof,
+        uint256[] calldata instances
+    ) public returns (bool) {
+        require(
+            instances.length > 0 &&
+                instances[instances.length - 1] == uint256(uint160(caller)),
+            "proof is bound to another caller"
+        );
+        // the verifier reads the proof at fixed calldata offsets, so it is called with the
+        // standard encoding
+        (bool success, bytes memory result) = verifier.call(
+            abi.encodeWithSignature("verifyProof(bytes,uint256[])", proof, instances)
+        );
+        return success && result.length == 32 && abi.decode(result, (bool));
+    }
+}
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: GPL-3.0
+
+pragma solidity ^0.8.17;
+
+/**
+ * @notice Quantizes on-chain fixed point values, integers with a number of decimals, to the fixed point representation of
+ * ezkl circuits: value / 10^decimals * 2^scale, rounded half away from zero, computed exactly.
+ * @dev `ezkl::fieldutils::quantize_float` computes the same in f64, so the two only agree when value / 10^decimals is exact
+ * in f64 and the quantized value is below 2^53. They differ e.g for values above 2^53, which f64 rounds, and for values just
+ * under a tie that aren't exact in f64, such as 2.499999999999999999 at scale 0 (the library returns 2, f64 rounds to 2.5 then 3).
+ * @dev Generated by `ezkl create-evm-quantizer`, which rewrites the constants below for the requested scale and bits.
+ */
+library EzklQuantizer {
+    /**
+     * @notice EZKL P value, quantized values are mapped to field elements modulo P
+     */
+    uint256 internal constant ORDER =
+        uint256(
+            0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
+        );
+
+    /**
+     * @notice 2^scale as a fraction, so negative scales stay integral
+     */
+    uint256 internal constant SCALE_NUMERATOR = 128;
+    uint256 internal constant SCALE_DENOMINATOR = 1;
+
+    /**
+     * @notice The range of quantized values, which take BITS bits
+     */
+    int256 internal constant MAX_VALUE = 170141183460469231731687303715884105727;
+    int256 internal constant MIN_VALUE = -170141183460469231731687303715884105728;
+
+    /**
+     * @notice The largest magnitude of a quantized value that fits in an int128, see `ezkl::fieldutils::felt_to_i128`
+     */
+    uint256 internal constant MAX_I128 = 170141183460469231731687303715884105727;
+
+    error QuantizationOverflow(int256 value, uint256 decimals);
+    error NotAFieldElement(uint256 felt);
+
+    /**
+     * @notice Calculates floor(x * y / denominator) with full precision. Throws if result overflows a uint256 or denominator == 0
+     * @dev Original credit to Remco Bloemen under MIT license (https://xn--2-umb.com/21/muldiv)
+     * with further edits by Uniswap Labs also under MIT license.
+     */
+    function mulDiv(
+        uint256 x,
+        uint256 y,
+        uint256 denominator
+    ) private pure returns (uint256 result) {
+        unchecked {
+            uint256 prod0; // Least significant 256 bits of the product
+            uint256 prod1; // Most significant 256 bits of the product
+            assembly {
+                let mm := mulmod(x, y, not(0))
+                prod0 := mul(x, y)
+                prod1 := sub(sub(mm, prod0), lt(mm, prod0))
+            }
+
+            // Handle non-overflow cases, 256 by 256 division.
+            if (prod1 == 0) {
+                return prod0 / denominator;
+            }
+
+            // Make sure the result is less than 2^256. Also prevents denominator == 0.
+            require(denominator > prod1, "Math: mulDiv overflow");
+
+            // Make division exact by subtracting the remainder from [prod1 prod0].
+            uint256 remainder;
+            assembly {
+                remainder := mulmod(x, y, denominator)
+                prod1 := sub(prod1, gt(remainder, prod0))
+                prod0 := sub(prod0, remainder)
+            }
+
+            // Factor powers of two out of denominator and compute largest power of two divisor of denominator. Always >= 1.
+            uint256 twos = denominator & (~denominator + 1);
+            assembly {
+                denominator := div(denominator, twos)
+                prod0 := div(prod0, twos)
+                twos := add(div(sub(0, twos), twos), 1)
+            }
+
+            // Shift in bits from prod1 into prod0.
+            prod0 |= prod1 * twos;
+
+            // Invert denominator mod 2^256 with Newton-Raphson, doubling the correct bits in each step.
+            uint256 inverse = (3 * denominator) ^ 2;
+            inverse *= 2 - denominator * inverse; // inverse mod 2^8
+            inverse *= 2 - denominator * inverse; // inverse mod 2^16
+            inverse *= 2 - denominator * inverse; // inverse mod 2^32
+            inverse *= 2 - denominator * inverse; // inverse mod 2^64
+            inverse *= 2 - denominator * inverse; // inverse mod 2^128
+            inverse *= 2 - denominator * inverse; // inverse mod 2^256
+
+            result = prod0 * inverse;
+            return result;
+        }
+    }
+
+    /**
+     * @notice x * y / denominator, rounded half away from zero (up, as x * y is non negative)
+     */
+    function mulDivRound(
+        uint256 x,
+        uint256 y,
+        uint256 denominator
+    ) private pure returns (uint256 result) {
+        result = mulDiv(x, y, denominator);
+        uint256 remainder = mulmod(x, y, denominator);
+        if (remainder >= denominator - remainder) {
+            result += 1;
+        }
+    }
+
+    function magnitude(int256 x) private pure returns (uint256) {
+        return x < 0 ? uint256(-(x + 1)) + 1 : uint256(x);
+    }
+
+    /**
+     * @notice Quantizes value / 10^decimals at the library's scale, reverting if the result doesn't fit in BITS bits
+     */
+    function quantize(
+        int256 value,
+        uint256 decimals
+    ) internal pure returns (int256) {
+        uint256 q = mulDivRound(
+            magnitude(value),
+            SCALE_NUMERATOR,
+            SCALE_DENOMINATOR * 10 ** decimals
+        );
+        if (value < 0) {
+            if (q > magnitude(MIN_VALUE)) revert QuantizationOverflow(value, decimals);
+            return -int256(q);
+        }
+        if (q > uint256(MAX_VALUE)) revert QuantizationOverflow(value, decimals);
+        return int256(q);
+    }
+
+    /**
+     * @notice The value with `decimals` decimals a quantized value at the library's scale stands for, rounded half away from zero
+     */
+    function dequantize(
+        int256 quantized,
+        uint256 decimals
+    ) internal pure returns (int256) {
+        uint256 v = mulDivRound(
+            magnitude(quantized),
+            SCALE_DENOMINATOR * 10 ** decimals,
+            SCALE_NUMERATOR
+        );
+        require(v <= uint256(type(int256).max), "EzklQuantizer: dequantized value overflows");
+        return quantized < 0 ? -int256(v) : int256(v);
+    }
+
+    /**
+     * @notice The field element standing for a quantized value, as public inputs of ezkl verifiers take them
+     */
+    function toFieldElement(int256 quantized) internal pure returns (uint256) {
+        uint256 m = magnitude(quantized);
+        require(m <= MAX_I128, "EzklQuantizer: value exceeds int128");
+        return quantized < 0 ? ORDER - m : m;
+    }
+
+    /**
+     * @notice The quantized value a field element stands for, the inverse of toFieldElement
+     */
+    function fromFieldElement(uint256 felt) internal pure returns (int256) {
+        if (felt >= ORDER) revert NotAFieldElement(felt);
+        return felt > MAX_I128 ? -int256(ORDER - felt) : int256(felt);
+    }
+}
@@ -0,0 +1,212 @@
+// SPDX-License-Identifier: GPL-3.0
+
+pragma solidity ^0.8.17;
+
+/**
+ * @notice The user operation of ERC-4337 v0.7 entry points
+ */
+struct PackedUserOperation {
+    address sender;
+    uint256 nonce;
+    bytes initCode;
+    bytes callData;
+    bytes32 accountGasLimits;
+    uint256 preVerificationGas;
+    bytes32 gasFees;
+    bytes paymasterAndData;
+    bytes signature;
+}
+
+interface IEntryPoint {
+    function depositTo(address account) external payable;
+
+    function withdrawTo(address payable withdrawAddress, uint256 withdrawAmount) external;
+
+    function addStake(uint32 unstakeDelaySec) external payable;
+
+    function unlockStake() external;
+
+    function withdrawStake(address payable withdrawAddress) external;
+}
+
+/**
+ * @notice The verifier of proofs bound to a caller, which wraps the verifier checking the proof alone
+ */
+interface ICallerBoundVerifier {
+    function verifier() external view returns (address);
+}
+
+/**
+ * @notice An ERC-4337 paymaster sponsoring the user operations that carry a valid proof of an ezkl
+ * model, gating a smart account action on an inference. The proof and its instances are the paymaster
+ * data of the operation, abi encoded as (bytes proof, uint256[] instances), and are verified by a
+ * Halo2Verifier during validation, with at most VERIFY_GAS_LIMIT gas so validation stays within its
+ * gas envelope. The public outputs of the model are passed to postOp, which emits them.
+ *
+ * A proof is only good for one operation: its last instance, the caller instance of circuits
+ * generated with --bind-caller, must be the binding of the operation's sender, nonce and callData
+ * (see operationBinding), set with `ezkl gen-witness --user-op`, so a proof copied from the mempool
+ * can't be attached to another operation. The paymaster also nullifies every proof it accepts.
+ * @dev Generated by `ezkl create-evm-paymaster`, which sets the constants below from the circuit's
+ * settings. Validation reads the verifier's code and precompiles and writes the nullifier to the
+ * paymaster's own storage, which ERC-7562 only allows a staked paymaster: call addStake before
+ * submitting operations to bundlers.
+ */
+contract EzklProofPaymaster {
+    /**
+     * @notice The number of instances of a proof, the index of its first public output and the number of outputs
+     */
+    uint256 internal constant NUM_INSTANCES = 1;
+    uint256 internal constant OUTPUT_OFFSET = 0;
+    uint256 internal constant OUTPUT_LEN = 1;
+
+    /**
+     * @notice The order of the scalar field of BN254, which operation bindings are reduced into
+     */
+    uint256 internal constant FIELD_ORDER =
+        21888242871839275222246405745257275088548364400416034343698204186575808495617;
+
+    /**
+     * @notice The most gas verifying a proof may take
+     */
+    uint256 internal constant VERIFY_GAS_LIMIT = 1500000;
+
+    /**
+     * @notice The paymaster data follows the paymaster and its validation and postOp gas limits
+     */
+    uint256 internal constant PAYMASTER_DATA_OFFSET = 52;
+    uint256 internal constant SIG_VALIDATION_FAILED = 1;
+
+    enum PostOpMode {
+        opSucceeded,
+        opReverted,
+        postOpReverted
+    }
+
+    address public immutable entryPoint;
+    address public immutable verifier;
+    address public owner;
+
+    /**
+     * @notice The hashes of the proofs already used to sponsor an operation
+     */
+    mapping(bytes32 => bool) public nullified;
+
+    event ProofValidated(
+        address indexed sender,
+        bytes32 indexed userOpHash,
+        uint256[] outputs
+    );
+
+    modifier onlyEntryPoint() {
+        require(msg.sender == entryPoint, "EzklProofPaymaster: caller is not the entry point");
+        _;
+    }
+
+    modifier onlyOwner() {
+        require(msg.sender == owner, "EzklProofPaymaster: caller is not the owner");
+        _;
+    }
+
+    /**
+     * @param verifier_ The verifier created for the circuit, which binds proofs to a caller. The
+     * paymaster checks the binding itself, against the operation, and calls the verifier it wraps.
+     */
+    constructor(address entryPoint_, address verifier_) {
+        require(verifier_.code.length > 0, "EzklProofPaymaster: verifier is not a contract");
+        entryPoint = entryPoint_;
+        verifier = ICallerBoundVerifier(verifier_).verifier();
+        owner = msg.sender;
+    }
+
+    /**
+     * @notice The value the last instance of a proof sponsoring `userOp` must hold
+     */
+    function operationBinding(PackedUserOperation calldata userOp) public pure returns (uint256) {
+        return
+            uint256(keccak256(abi.encode(userOp.sender, userOp.nonce, keccak256(userOp.callData)))) %
+            FIELD_ORDER;
+    }
+
+    /**
+     * @notice Sponsors the operation if its paymaster data holds a valid proof, returning its public
+     * outputs in the context passed to postOp
+     */
+    function validatePaymasterUserOp(
+        PackedUserOperation calldata userOp,
+        bytes32 userOpHash,
+        uint256
+    ) external onlyEntryPoint returns (bytes memory context, uint256 validationData) {
+        (bytes memory proof, uint256[] memory instances) = abi.decode(
+            userOp.paymasterAndData[PAYMASTER_DATA_OFFSET:],
+            (bytes, uint256[])
+        );
+        if (instances.length != NUM_INSTANCES) {
+            return ("", SIG_VALIDATION_FAILED);
+        }
+        if (instances[NUM_INSTANCES - 1] != operationBinding(userOp)) {
+            return ("", SIG_VALIDATION_FAILED);
+        }
+        bytes32 nullifier = keccak256(proof);
+        if (nullified[nullifier]) {
+            return ("", SIG_VALIDATION_FAILED);
+        }
+
+        (bool success, bytes memory result) = verifier.staticcall{gas: VERIFY_GAS_LIMIT}(
+            abi.encodeWithSignature("verifyProof(bytes,uint256[])", proof, instances)
+        );
+        if (!success || result.length != 32 || !abi.decode(result, (bool))) {
+            return ("", SIG_VALIDATION_FAILED);
+        }
+        nullified[nullifier] = true;
+
+        uint256[] memory outputs = new uint256[](OUTPUT_LEN);
+        for (uint256 i = 0; i < OUTPUT_LEN; i++) {
+            outputs[i] = instances[OUTPUT_OFFSET + i];
+        }
+        return (abi.encode(userOp.sender, userOpHash, outputs), 0);
+    }
+
+    /**
+     * @notice Emits the public outputs of the proof of a sponsored operation
+     */
+    function postOp(
+        PostOpMode,
+        bytes calldata context,
+        uint256,
+        uint256
+    ) external onlyEntryPoint {
+        (address sender, bytes32 userOpHash, uint256[] memory outputs) = abi.decode(
+            context,
+            (address, bytes32, uint256[])
+        );
+        emit ProofValidated(sender, userOpHash, outputs);
+    }
+
+    /**
+     * @notice Deposits to the entry point the gas of the operations the paymaster sponsors
+     */
+    function deposit() external payable {
+        IEntryPoint(entryPoint).depositTo{value: msg.value}(address(this));
+    }
+
+    function withdrawTo(address payable withdrawAddress, uint256 amount) external onlyOwner {
+        IEntryPoint(entryPoint).withdrawTo(withdrawAddress, amount);
+    }
+
+    /**
+     * @notice Stakes the paymaster in the entry point, which bundlers require of paymasters writing
+     * to their storage during validation
+     */
+    function addStake(uint32 unstakeDelaySec) external payable onlyOwner {
+        IEntryPoint(entryPoint).addStake{value: msg.value}(unstakeDelaySec);
+    }
+
+    function unlockStake() external onlyOwner {
+        IEntryPoint(entryPoint).unlockStake();
+    }
+
+    function withdrawStake(address payable withdrawAddress) external onlyOwner {
+        IEntryPoint(entryPoint).withdrawStake(withdrawAddress);
+    }
+}
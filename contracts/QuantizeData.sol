@@ -103,22 +103,36 @@ contract QuantizeData {
         }
     }
 
+    /**
+     * @param isUnsigned - Whether each of the data is a uint256 rather than an int256.
+     */
     function quantize_data(
         bytes[] memory data,
         uint256[] memory decimals,
-        uint256[] memory scales
+        uint256[] memory scales,
+        bool[] memory isUnsigned
     ) external pure returns (int256[] memory quantized_data) {
         quantized_data = new int256[](data.length);
         for (uint i; i < data.length; i++) {
-            int x = abi.decode(data[i], (int256));
-            bool neg = x < 0;
-            if (neg) x = -x;
+            uint x;
+            bool neg;
+            if (isUnsigned[i]) {
+                x = abi.decode(data[i], (uint256));
+            } else {
+                int signed = abi.decode(data[i], (int256));
+                neg = signed < 0;
+                x = uint256(neg ? -signed : signed);
+            }
             uint denom = 10 ** decimals[i];
             uint scale = 1 << scales[i];
-            uint output = mulDiv(uint256(x), scale, denom);
-            if (mulmod(uint256(x), scale, denom) * 2 >= denom) {
+            uint output = mulDiv(x, scale, denom);
+            if (mulmod(x, scale, denom) * 2 >= denom) {
                 output += 1;
             }
+            require(
+                output <= uint256(type(int256).max),
+                "quantized data overflows int256"
+            );
 
             quantized_data[i] = neg ? -int256(output) : int256(output);
         }
@@ -132,4 +146,13 @@ contract QuantizeData {
             output[i] = uint256(quantized_data[i] + int(ORDER)) % ORDER;
         }
     }
+
+    function to_field_element(
+        int256[] memory quantized_data
+    ) public pure returns (uint256[] memory output) {
+        output = new uint256[](quantized_data.length);
+        for (uint i; i < quantized_data.length; i++) {
+            output[i] = uint256(quantized_data[i] + int(ORDER)) % ORDER;
+        }
+    }
 }
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-3.0
+
+pragma solidity ^0.8.17;
+
+/**
+ * @notice A verifier whose address stays the same across model updates. It acts as the beacon of a
+ * Halo2Verifier rendered with its verifying key separate (create-evm-verifier --render-vk-seperately)
+ * and a Halo2VerifyingKey (create-evm-vk): proofs are verified by the verifier against the current
+ * verifying key, and the owner points the registry at the key of a retrained model, or a new verifier
+ * if the shape of the circuit changed, with `updateVerifyingKey`.
+ * @dev Every version stays in `versions`, so proofs generated before an update can still be verified
+ * against the key they were generated for with `verifyProofAt`, until the owner revokes the version
+ * with `revokeVersion`, e.g if its key leaked or its model was found unsound.
+ */
+contract EzklVerifierRegistry {
+    struct Version {
+        address verifier;
+        address vk;
+    }
+
+    address public owner;
+    Version[] public versions;
+    mapping(uint256 => bool) public revoked;
+
+    event VerifyingKeyUpdated(
+        uint256 indexed version,
+        address verifier,
+        address vk
+    );
+    event VersionRevoked(uint256 indexed version);
+    event OwnershipTransferred(
+        address indexed previousOwner,
+        address indexed newOwner
+    );
+
+    modifier onlyOwner() {
+        require(msg.sender == owner, "EzklVerifierRegistry: caller is not the owner");
+        _;
+    }
+
+    constructor(address verifier, address vk) {
+        owner = msg.sender;
+        emit OwnershipTransferred(address(0), msg.sender);
+        _push(verifier, vk);
+    }
+
+    /**
+     * @notice The version proofs are verified against by `verifyProof`
+     */
+    function currentVersion() public view returns (uint256) {
+        return versions.length - 1;
+    }
+
+    /**
+     * @notice Points the registry at a new verifying key, and verifier, which proofs are verified
+     * against from now on
+     */
+    function updateVerifyingKey(address verifier, address vk) external onlyOwner {
+        _push(verifier, vk);
+    }
+
+    /**
+     * @notice Stops proofs from verifying against `version`. The current version can't be revoked,
+     * point the registry at a new one first
+     */
+    function revokeVersion(uint256 version) external onlyOwner {
+        require(version < currentVersion(), "EzklVerifierRegistry: can't revoke the current version");
+        require(!revoked[version], "EzklVerifierRegistry: version is already revoked");
+        revoked[version] = true;
+        emit VersionRevoked(version);
+    }
+
+    function transferOwnership(address newOwner) external onlyOwner {
+        require(newOwner != address(0), "EzklVerifierRegistry: new owner is the zero address");
+        emit OwnershipTransferred(owner, newOwner);
+        owner = newOwner;
+    }
+
+    /**
+     * @notice Verifies a proof against the current verifying key, with the interface of a Halo2Verifier
+     */
+    function verifyProof(
+        bytes calldata proof,
+        uint256[] calldata instances
+    ) external view returns (bool) {
+        return verifyProofAt(currentVersion(), proof, instances);
+    }
+
+    /**
+     * @notice Verifies a proof against the verifying key of `version`, reverting if it is revoked
+     */
+    function verifyProofAt(
+        uint256 version,
+        bytes calldata proof,
+        uint256[] calldata instances
+    ) public view returns (bool) {
+        require(!revoked[version], "EzklVerifierRegistry: version is revoked");
+        Version memory v = versions[version];
+        (bool success, bytes memory result) = v.verifier.staticcall(
+            abi.encodeWithSignature(
+                "verifyProof(address,bytes,uint256[])",
+                v.vk,
+                proof,
+                instances
+            )
+        );
+        if (!success) {
+            // bubble up the revert of an invalid proof
+            assembly {
+                revert(add(result, 0x20), mload(result))
+            }
+        }
+        return abi.decode(result, (bool));
+    }
+
+    function _push(address verifier, address vk) internal {
+        require(verifier.code.length > 0, "EzklVerifierRegistry: verifier is not a contract");
+        require(vk.code.length > 0, "EzklVerifierRegistry: vk is not a contract");
+        versions.push(Version(verifier, vk));
+        emit VerifyingKeyUpdated(versions.length - 1, verifier, vk);
+    }
+}
@@ -0,0 +1,282 @@
+//! The types exchanged with the proving service, and the OpenAPI 3 document describing it.
+//!
+//! The json schema of each request and response type is derived from the type itself with
+//! [schemars], and each endpoint is an [Endpoint] naming the types it takes and returns. [openapi]
+//! assembles the document from them, so the document can't drift from the types the handlers
+//! (de)serialize, and client SDKs in other languages can be generated from it. The service serves
+//! the document at [OPENAPI_PATH].
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// The version of the OpenAPI specification the document follows.
+pub const OPENAPI_VERSION: &str = "3.0.3";
+
+/// The path the OpenAPI document is served at.
+pub const OPENAPI_PATH: &str = "/openapi.json";
+
+/// The input data to generate a witness from, see [crate::graph::GraphData].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WitnessSubmission {
+    /// The input data, in the format of `gen-witness --data`
+    #[schemars(with = "Map<String, Value>")]
+    pub data: crate::graph::GraphData,
+}
+
+/// A request to prove a witness, generated by an earlier job or supplied directly. Exactly one of
+/// `witness_job` and `witness` is set.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ProveRequest {
+    /// The witness generation job whose witness to prove
+    pub witness_job: Option<String>,
+    /// The witness to prove, in the format of `prove --witness`
+    #[schemars(with = "Option<Map<String, Value>>")]
+    pub witness: Option<crate::graph::GraphWitness>,
+}
+
+/// A proof to verify.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyRequest {
+    /// The proof, in the format written by `prove`
+    #[schemars(with = "Map<String, Value>")]
+    pub proof: Value,
+}
+
+/// The outcome of verifying a proof.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyResponse {
+    /// Whether the proof verified
+    pub verified: bool,
+    /// Why the proof could not be verified, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The state of a job in the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting for a worker
+    Queued,
+    /// Being run
+    Running,
+    /// Finished, its artifacts can be downloaded
+    Done,
+    /// Finished with an error
+    Failed,
+}
+
+/// The status of a witness generation or proving job.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct JobStatus {
+    /// The id of the job
+    pub id: String,
+    /// The state of the job
+    pub state: JobState,
+    /// Why the job failed, if it did
+    pub error: Option<String>,
+    /// The names of the artifacts the job produced, e.g `witness.json` or `proof.json`
+    pub artifacts: Vec<String>,
+}
+
+/// A request to remove the artifacts of every finished job of a model or tenant, see
+/// [crate::retention::purge]. At least one of `model` and `tenant` is set.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PurgeRequest {
+    /// Only purge the jobs of this model
+    pub model: Option<String>,
+    /// Only purge the jobs of this tenant
+    pub tenant: Option<String>,
+}
+
+/// The jobs whose artifacts were removed, by a purge or the retention policy.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PurgeReport {
+    /// The ids of the removed jobs
+    pub removed_jobs: Vec<String>,
+    /// The bytes freed on disk
+    pub freed_bytes: u64,
+}
+
+/// An artifact of a finished job, e.g a witness or a proof, as written by the cli.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct Artifact(Map<String, Value>);
+
+/// Adds the schema of a type to a [SchemaGenerator], returning a reference to it.
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// An endpoint of the service.
+#[derive(Clone, Debug)]
+pub struct Endpoint {
+    /// The http method, lowercase
+    pub method: &'static str,
+    /// The path, with `{param}` path parameters
+    pub path: &'static str,
+    /// What the endpoint does
+    pub summary: &'static str,
+    /// The schema of the json body, if any
+    pub request: Option<SchemaFn>,
+    /// The status code and schema of the json response
+    pub response: (u16, SchemaFn),
+}
+
+fn schema_of<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// The endpoints of the service.
+pub fn endpoints() -> Vec<Endpoint> {
+    vec![
+        Endpoint {
+            method: "post",
+            path: "/witness",
+            summary: "queues witness generation from input data",
+            request: Some(schema_of::<WitnessSubmission>),
+            response: (202, schema_of::<JobStatus>),
+        },
+        Endpoint {
+            method: "post",
+            path: "/prove",
+            summary: "queues proving a witness",
+            request: Some(schema_of::<ProveRequest>),
+            response: (202, schema_of::<JobStatus>),
+        },
+        Endpoint {
+            method: "post",
+            path: "/verify",
+            summary: "verifies a proof",
+            request: Some(schema_of::<VerifyRequest>),
+            response: (200, schema_of::<VerifyResponse>),
+        },
+        Endpoint {
+            method: "get",
+            path: "/jobs/{id}",
+            summary: "the status of a job",
+            request: None,
+            response: (200, schema_of::<JobStatus>),
+        },
+        Endpoint {
+            method: "get",
+            path: "/jobs/{id}/artifacts/{name}",
+            summary: "downloads an artifact of a finished job",
+            request: None,
+            response: (200, schema_of::<Artifact>),
+        },
+        Endpoint {
+            method: "post",
+            path: "/admin/purge",
+            summary: "removes the artifacts of every finished job of a model or tenant",
+            request: Some(schema_of::<PurgeRequest>),
+            response: (200, schema_of::<PurgeReport>),
+        },
+    ]
+}
+
+/// The names of the `{param}` path parameters of `path`.
+fn path_params(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter_map(|s| s.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+        .collect()
+}
+
+/// The OpenAPI document of the service, assembled from [endpoints].
+pub fn openapi() -> Value {
+    // the schemas of the types are collected in the components of the document, and referenced
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for endpoint in endpoints() {
+        let mut operation = Map::new();
+        operation.insert("summary".into(), endpoint.summary.into());
+
+        let params = path_params(endpoint.path)
+            .into_iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" }
+                })
+            })
+            .collect::<Vec<_>>();
+        if !params.is_empty() {
+            operation.insert("parameters".into(), params.into());
+        }
+
+        if let Some(schema) = endpoint.request {
+            operation.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": schema(&mut gen) } }
+                }),
+            );
+        }
+
+        let (status, schema) = endpoint.response;
+        operation.insert(
+            "responses".into(),
+            json!({
+                (status.to_string()): {
+                    "description": endpoint.summary,
+                    "content": { "application/json": { "schema": schema(&mut gen) } }
+                },
+                "default": { "description": "an error, with its message as plain text" }
+            }),
+        );
+
+        let item = paths
+            .entry(endpoint.path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(item) = item {
+            item.insert(endpoint.method.into(), operation.into());
+        }
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "ezkl proving service",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": gen.take_definitions() },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi() {
+        let doc = openapi();
+        assert_eq!(doc["openapi"], OPENAPI_VERSION);
+
+        // every referenced schema is defined
+        let text = doc.to_string();
+        for name in text.split("#/components/schemas/").skip(1) {
+            let name = name.split('"').next().unwrap();
+            assert!(doc["components"]["schemas"].get(name).is_some(), "{}", name);
+        }
+
+        let status = &doc["paths"]["/jobs/{id}"]["get"];
+        assert_eq!(status["parameters"][0]["name"], "id");
+
+        // the schemas follow the serialization of the types
+        let schemas = &doc["components"]["schemas"];
+        let state = serde_json::to_value(JobState::Done).unwrap();
+        assert!(schemas["JobState"].to_string().contains(&state.to_string()));
+        assert_eq!(
+            schemas["JobStatus"]["required"],
+            json!(["artifacts", "id", "state"])
+        );
+        assert_eq!(
+            schemas["JobStatus"]["properties"]["error"]["nullable"],
+            true
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! End-to-end benchmarks of a model, see `ezkl bench`.
+//!
+//! Each iteration runs setup, witness generation, proving and verification as the cli does, and
+//! [BenchSamples] records how long each stage took and the peak memory of the process during it.
+//! The [BenchReport] summarizes the samples alongside the size of the circuit and its proofs, so
+//! that settings can be compared without timing each command by hand.
+
+use crate::graph::GraphSettings;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tabled::settings::Style;
+use tabled::{Table, Tabled};
+
+/// Summary statistics of a set of samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// the mean of the samples
+    pub mean: f64,
+    /// the sample standard deviation, 0 for a single sample
+    pub stddev: f64,
+    /// the smallest sample
+    pub min: f64,
+    /// the largest sample
+    pub max: f64,
+}
+
+impl Stats {
+    /// The statistics of `samples`, all 0 when there are none.
+    pub fn new(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let stddev = if samples.len() > 1 {
+            (samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            mean,
+            stddev,
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// The size of a circuit, from its settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Tabled)]
+pub struct CircuitSize {
+    /// the log2 of the number of rows of the circuit
+    pub logrows: u32,
+    /// the number of rows the model takes up
+    #[tabled(rename = "rows")]
+    pub num_rows: usize,
+    /// the number of advice cells assigned
+    #[tabled(rename = "assignments")]
+    pub total_assignments: usize,
+    /// the number of constants
+    #[tabled(rename = "constants")]
+    pub total_const_size: usize,
+    /// the number of lookup tables
+    #[tabled(rename = "lookups")]
+    pub num_lookups: usize,
+    /// the number of range checks
+    #[tabled(rename = "range checks")]
+    pub num_range_checks: usize,
+    /// the number of dynamic lookups
+    #[tabled(rename = "dynamic lookups")]
+    pub num_dynamic_lookups: usize,
+    /// the number of shuffles
+    #[tabled(rename = "shuffles")]
+    pub num_shuffles: usize,
+}
+
+impl From<&GraphSettings> for CircuitSize {
+    fn from(settings: &GraphSettings) -> Self {
+        Self {
+            logrows: settings.run_args.logrows,
+            num_rows: settings.num_rows,
+            total_assignments: settings.total_assignments,
+            total_const_size: settings.total_const_size,
+            num_lookups: settings.required_lookups.len(),
+            num_range_checks: settings.required_range_checks.len(),
+            num_dynamic_lookups: settings.num_dynamic_lookups,
+            num_shuffles: settings.num_shuffles,
+        }
+    }
+}
+
+/// The timings and peak memory of a stage over every iteration.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StageReport {
+    /// the name of the stage
+    pub stage: String,
+    /// how long the stage took, in seconds
+    pub seconds: Stats,
+    /// the largest peak resident set size of the process during the stage in bytes, where the OS
+    /// reports it
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// The report of `ezkl bench`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// the number of times each stage ran
+    pub iterations: usize,
+    /// the size of the circuit
+    pub circuit: CircuitSize,
+    /// the size of the proofs, in bytes
+    pub proof_size_bytes: usize,
+    /// the stages, in the order they ran
+    pub stages: Vec<StageReport>,
+}
+
+#[derive(Tabled)]
+struct StageRow {
+    stage: String,
+    #[tabled(rename = "mean (s)")]
+    mean: String,
+    #[tabled(rename = "stddev (s)")]
+    stddev: String,
+    #[tabled(rename = "min (s)")]
+    min: String,
+    #[tabled(rename = "max (s)")]
+    max: String,
+    #[tabled(rename = "peak memory (MiB)")]
+    peak_memory: String,
+}
+
+impl BenchReport {
+    /// The report as tables of the stages and of the size of the circuit.
+    pub fn to_table(&self) -> String {
+        let rows = self.stages.iter().map(|stage| StageRow {
+            stage: stage.stage.clone(),
+            mean: format!("{:.3}", stage.seconds.mean),
+            stddev: format!("{:.3}", stage.seconds.stddev),
+            min: format!("{:.3}", stage.seconds.min),
+            max: format!("{:.3}", stage.seconds.max),
+            peak_memory: stage
+                .peak_rss_bytes
+                .map(|bytes| format!("{:.1}", bytes as f64 / (1024.0 * 1024.0)))
+                .unwrap_or_else(|| "-".to_string()),
+        });
+        let mut stages = Table::new(rows);
+        stages.with(Style::modern());
+
+        let mut circuit = Table::new(vec![self.circuit.clone()]);
+        circuit.with(Style::modern());
+
+        format!(
+            "{} iterations, proofs of {} bytes\n{}\n{}",
+            self.iterations, self.proof_size_bytes, stages, circuit
+        )
+    }
+}
+
+/// The samples of each stage, recorded as the iterations run.
+#[derive(Clone, Debug, Default)]
+pub struct BenchSamples {
+    stages: Vec<(String, Vec<f64>, Option<u64>)>,
+}
+
+impl BenchSamples {
+    /// Records that `stage` took `elapsed`, with the peak memory of the process since the peak
+    /// was last reset (see [crate::memory::reset_peak_rss]).
+    pub fn record(&mut self, stage: &str, elapsed: Duration) {
+        self.record_sample(stage, elapsed, crate::memory::peak_rss());
+    }
+
+    fn record_sample(&mut self, stage: &str, elapsed: Duration, peak_rss_bytes: Option<u64>) {
+        let index = match self.stages.iter().position(|(name, ..)| name == stage) {
+            Some(index) => index,
+            None => {
+                self.stages.push((stage.to_string(), vec![], None));
+                self.stages.len() - 1
+            }
+        };
+        let (_, seconds, peak) = &mut self.stages[index];
+        seconds.push(elapsed.as_secs_f64());
+        *peak = (*peak).max(peak_rss_bytes);
+    }
+
+    /// The report of the samples, over a circuit with `settings` whose proofs are
+    /// `proof_size_bytes` long.
+    pub fn report(&self, settings: &GraphSettings, proof_size_bytes: usize) -> BenchReport {
+        BenchReport {
+            iterations: self
+                .stages
+                .iter()
+                .map(|(_, seconds, _)| seconds.len())
+                .max()
+                .unwrap_or_default(),
+            circuit: settings.into(),
+            proof_size_bytes,
+            stages: self
+                .stages
+                .iter()
+                .map(|(stage, seconds, peak_rss_bytes)| StageReport {
+                    stage: stage.clone(),
+                    seconds: Stats::new(seconds),
+                    peak_rss_bytes: *peak_rss_bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_report() {
+        let stats = Stats::new(&[1.0, 2.0, 3.0]);
+        assert_eq!(stats.mean, 2.0);
+        assert_eq!(stats.stddev, 1.0);
+        assert_eq!((stats.min, stats.max), (1.0, 3.0));
+        assert_eq!(Stats::new(&[4.0]).stddev, 0.0);
+
+        let mut samples = BenchSamples::default();
+        for i in 1..=2 {
+            samples.record_sample("setup", Duration::from_secs(i), Some(i * 100));
+            samples.record_sample("prove", Duration::from_secs(2 * i), None);
+        }
+        let report = samples.report(&GraphSettings::default(), 100);
+        assert_eq!(report.iterations, 2);
+        assert_eq!(report.stages[0].stage, "setup");
+        assert_eq!(report.stages[0].seconds.mean, 1.5);
+        assert_eq!(report.stages[0].peak_rss_bytes, Some(200));
+        assert_eq!(report.stages[1].peak_rss_bytes, None);
+        assert!(report.to_table().contains("peak memory (MiB)"));
+    }
+}
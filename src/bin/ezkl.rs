@@ -5,11 +5,13 @@ use clap::Parser;
 #[cfg(not(target_arch = "wasm32"))]
 use colored_json::ToColoredJson;
 #[cfg(not(target_arch = "wasm32"))]
-use ezkl::commands::Cli;
+use ezkl::commands::{Cli, Commands};
 #[cfg(not(target_arch = "wasm32"))]
-use ezkl::execute::run;
+use ezkl::execute::{run, run_with_report};
 #[cfg(not(target_arch = "wasm32"))]
-use ezkl::logger::init_logger;
+use ezkl::logger::init_logger_to;
+#[cfg(not(target_arch = "wasm32"))]
+use ezkl::output::OutputFormat;
 #[cfg(not(target_arch = "wasm32"))]
 use log::{debug, error, info};
 #[cfg(not(any(target_arch = "wasm32", feature = "no-banner")))]
@@ -24,7 +26,15 @@ use std::error::Error;
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::parse();
-    init_logger();
+    // completions are piped to a file, so they're printed without the banner and logs
+    if let Commands::Completions { .. } = args.command {
+        print!("{}", run(args.command).await?);
+        return Ok(());
+    }
+    init_logger_to(match args.format {
+        OutputFormat::Human => env_logger::Target::Stdout,
+        OutputFormat::Json => env_logger::Target::Stderr,
+    });
     #[cfg(not(any(target_arch = "wasm32", feature = "no-banner")))]
     banner();
     #[cfg(feature = "icicle")]
@@ -34,6 +44,14 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
         info!("Running with CPU");
     }
     debug!("command: \n {}", &args.as_json()?.to_colored_json_auto()?);
+    if args.format == OutputFormat::Json {
+        let report = run_with_report(args.command).await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return match report.error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        };
+    }
     let res = run(args.command).await;
     match &res {
         Ok(_) => info!("succeeded"),
@@ -0,0 +1,181 @@
+use super::harness::{OpCircuit, OpCircuitParams};
+use super::lookup::LookupOp;
+use super::table::Range;
+use super::Op;
+use crate::fieldutils::{felt_to_i128, i128_to_felt};
+use crate::pfsys::create_keys;
+use crate::tensor::Tensor;
+use crate::EZKL_BUF_CAPACITY;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2curves::bn256::Bn256;
+use halo2curves::bn256::Fr as Fp;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// The logrows of every golden circuit.
+pub const GOLDEN_LOGROWS: u32 = 8;
+/// The range of the lookup table of every golden circuit.
+pub const GOLDEN_LOOKUP_RANGE: Range = (-64, 64);
+/// The inputs every op is evaluated on.
+pub const GOLDEN_INPUTS: [i128; 8] = [-63, -17, -3, -1, 0, 1, 17, 63];
+
+/// One canonical parametrization of every [LookupOp], in declaration order.
+pub fn golden_ops() -> Vec<LookupOp> {
+    let scale = super::utils::F32(128.0);
+    let a = super::utils::F32(0.5);
+    vec![
+        LookupOp::Abs,
+        LookupOp::Div {
+            denom: super::utils::F32(2.0),
+        },
+        LookupOp::Cast { scale },
+        LookupOp::ReLU,
+        LookupOp::Max { scale, a },
+        LookupOp::Min { scale, a },
+        LookupOp::Ceil { scale },
+        LookupOp::Floor { scale },
+        LookupOp::Round { scale },
+        LookupOp::RoundHalfToEven { scale },
+        LookupOp::Sqrt { scale },
+        LookupOp::Rsqrt { scale },
+        LookupOp::Recip {
+            input_scale: scale,
+            output_scale: scale,
+        },
+        LookupOp::LeakyReLU { slope: a },
+        LookupOp::Sigmoid { scale },
+        LookupOp::Ln { scale },
+        LookupOp::Exp { scale },
+        LookupOp::Cos { scale },
+        LookupOp::ACos { scale },
+        LookupOp::Cosh { scale },
+        LookupOp::ACosh { scale },
+        LookupOp::Sin { scale },
+        LookupOp::ASin { scale },
+        LookupOp::Sinh { scale },
+        LookupOp::ASinh { scale },
+        LookupOp::Tan { scale },
+        LookupOp::ATan { scale },
+        LookupOp::Tanh { scale },
+        LookupOp::ATanh { scale },
+        LookupOp::Erf { scale },
+        LookupOp::GreaterThan { a },
+        LookupOp::LessThan { a },
+        LookupOp::GreaterThanEqual { a },
+        LookupOp::LessThanEqual { a },
+        LookupOp::Sign,
+        LookupOp::KroneckerDelta,
+        LookupOp::Pow {
+            scale,
+            a: super::utils::F32(2.0),
+        },
+        LookupOp::HardSwish { scale },
+    ]
+}
+
+/// The file stem of an op's golden record, e.g `relu` or `round_half_to_even`.
+pub fn golden_name(op: &LookupOp) -> String {
+    let name = Op::<Fp>::as_string(op);
+    name.split('(').next().unwrap_or(&name).to_lowercase()
+}
+
+/// The expected behavior of a lookup op, pinned across releases.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoldenRecord {
+    /// The op laid out by the circuit
+    pub op: LookupOp,
+    /// The range of the op's lookup table
+    pub lookup_range: Range,
+    /// The logrows of the circuit
+    pub logrows: u32,
+    /// The quantized inputs the op is evaluated on
+    pub inputs: Vec<i128>,
+    /// The op's quantized outputs
+    pub outputs: Vec<i128>,
+    /// The SHA256 hash of the circuit's serialized verifying key
+    pub vk_hash: String,
+}
+
+impl GoldenRecord {
+    /// Load the record from a .json file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open golden record {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the record to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+}
+
+/// Builds the golden circuit of `op`, checks it is satisfied and records its outputs and the hash
+/// of its verifying key.
+///
+/// Keys are generated against an SRS from a fixed seed so the hash is reproducible, it must never
+/// be used for anything but these records.
+pub fn gen_golden(op: &LookupOp) -> Result<GoldenRecord, Box<dyn Error>> {
+    let inputs: Tensor<Fp> = GOLDEN_INPUTS.iter().map(|x| i128_to_felt(*x)).into();
+    let outputs = Op::<Fp>::f(op, &[inputs.clone()])?.output;
+
+    let circuit = OpCircuit {
+        op: Box::new(op.clone()),
+        inputs: vec![inputs.map(Value::known).into()],
+        params: OpCircuitParams {
+            logrows: GOLDEN_LOGROWS,
+            max_len: GOLDEN_INPUTS.len(),
+            lookup_range: GOLDEN_LOOKUP_RANGE,
+            lookups: vec![op.clone()],
+        },
+    };
+
+    MockProver::run(GOLDEN_LOGROWS, &circuit, vec![])?
+        .verify()
+        .map_err(|e| {
+            format!(
+                "golden circuit for {} is not satisfied: {:?}",
+                golden_name(op),
+                e
+            )
+        })?;
+
+    let params = ParamsKZG::<Bn256>::setup(GOLDEN_LOGROWS, StdRng::from_seed([0u8; 32]));
+    let pk = create_keys::<KZGCommitmentScheme<Bn256>, _>(&circuit, &params, false)?;
+    let mut vk_bytes = vec![];
+    pk.get_vk()
+        .write(&mut vk_bytes, halo2_proofs::SerdeFormat::RawBytes)?;
+
+    Ok(GoldenRecord {
+        op: op.clone(),
+        lookup_range: GOLDEN_LOOKUP_RANGE,
+        logrows: GOLDEN_LOGROWS,
+        inputs: GOLDEN_INPUTS.to_vec(),
+        outputs: outputs.iter().map(|x| felt_to_i128(*x)).collect(),
+        vk_hash: sha256::digest(vk_bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_names_are_unique() {
+        let ops = golden_ops();
+        let mut names: Vec<_> = ops.iter().map(golden_name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), ops.len());
+        assert_eq!(golden_name(&LookupOp::ReLU), "relu");
+    }
+}
@@ -0,0 +1,104 @@
+use super::lookup::LookupOp;
+use super::region::RegionCtx;
+use super::table::Range;
+use super::{BaseConfig, CheckMode, Op};
+use crate::tensor::{ValTensor, VarTensor};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error as PlonkError};
+use halo2curves::bn256::Fr as Fp;
+
+/// The shape of an [OpCircuit].
+#[derive(Clone, Debug, Default)]
+pub struct OpCircuitParams {
+    /// The logrows of the circuit
+    pub logrows: u32,
+    /// The most cells an input or output of the op can take
+    pub max_len: usize,
+    /// The range of the lookup tables
+    pub lookup_range: Range,
+    /// The lookups the layout of the op uses
+    pub lookups: Vec<LookupOp>,
+}
+
+/// A circuit laying out a single op on fixed inputs, to check the op in isolation: it backs the
+/// golden records (see [super::golden]), the soundness self-tests (see [super::soundness]) and the
+/// op tests.
+#[derive(Clone, Debug)]
+pub struct OpCircuit {
+    /// The op laid out by the circuit
+    pub op: Box<dyn Op<Fp>>,
+    /// The inputs of the op
+    pub inputs: Vec<ValTensor<Fp>>,
+    /// The shape of the circuit
+    pub params: OpCircuitParams,
+}
+
+impl Circuit<Fp> for OpCircuit {
+    type Config = BaseConfig<Fp>;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = OpCircuitParams;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn params(&self) -> Self::Params {
+        self.params.clone()
+    }
+
+    fn configure_with_params(cs: &mut ConstraintSystem<Fp>, params: Self::Params) -> Self::Config {
+        let [a, b, output, index] =
+            [(); 4].map(|_| VarTensor::new_advice(cs, params.logrows as usize, 1, params.max_len));
+        let mut config = BaseConfig::configure(cs, &[a.clone(), b], &output, CheckMode::UNSAFE);
+        for op in &params.lookups {
+            config
+                .configure_lookup(
+                    cs,
+                    &a,
+                    &output,
+                    &index,
+                    params.lookup_range,
+                    params.logrows as usize,
+                    op,
+                )
+                .unwrap();
+        }
+        config
+    }
+
+    fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {
+        unimplemented!("you should call configure_with_params instead")
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlonkError> {
+        self.layout(config, &mut layouter, |_, _| Ok(()))
+    }
+}
+
+impl OpCircuit {
+    /// Lays out the op, then passes its output to `then`, which can e.g tamper with the witness.
+    pub fn layout(
+        &self,
+        mut config: BaseConfig<Fp>,
+        layouter: &mut impl Layouter<Fp>,
+        then: impl Fn(&mut RegionCtx<Fp>, Option<ValTensor<Fp>>) -> Result<(), PlonkError>,
+    ) -> Result<(), PlonkError> {
+        config
+            .layout_tables(layouter)
+            .map_err(|_| PlonkError::Synthesis)?;
+        layouter.assign_region(
+            || "op",
+            |region| {
+                let mut region = RegionCtx::new(region, 0, 1);
+                let output = config
+                    .layout(&mut region, &self.inputs, self.op.clone())
+                    .map_err(|_| PlonkError::Synthesis)?;
+                then(&mut region, output)
+            },
+        )
+    }
+}
@@ -1,6 +1,17 @@
+/// Canonical circuits for each lookup op, whose outputs and keys are pinned across releases.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod golden;
+
+/// A circuit laying out a single op, to check ops in isolation.
+pub mod harness;
+
 ///
 pub mod modules;
 
+/// Soundness self-tests checking every op rejects tampered outputs.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod soundness;
+
 ///
 pub mod table;
 
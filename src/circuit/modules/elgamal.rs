@@ -0,0 +1,358 @@
+/*
+Hashed ElGamal over BN254 G1, laid out with the non-native ecc chip of halo2wrong. The shared point
+`r·pk` never leaves the circuit, only the public key, the ephemeral key `c1 = r·G` and the masked
+elements `c2_i = x_i + H(s_x, s_y, i)` are exposed as instances, where `s_x` and `s_y` are the
+coordinates of the shared point reduced into the scalar field and `H` is Poseidon.
+*/
+
+use halo2_proofs::circuit::{AssignedCell, Layouter, Value};
+use halo2_proofs::plonk::{Column, ConstraintSystem, Error, Instance};
+use halo2_wrong_ecc::{
+    integer::rns::Rns,
+    maingate::{
+        MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
+        RegionCtx,
+    },
+    BaseFieldEccChip, EccConfig,
+};
+use halo2curves::bn256::{Fq, Fr as Fp, G1Affine, G1};
+use halo2curves::ff::{Field, PrimeField};
+use halo2curves::group::prime::PrimeCurveAffine;
+use halo2curves::group::{Curve, Group};
+use halo2curves::CurveAffine;
+use rand::rngs::OsRng;
+use snark_verifier::pcs::kzg::LimbsEncodingInstructions;
+use snark_verifier::util::arithmetic::fe_to_limbs;
+
+use super::poseidon::spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH};
+use super::poseidon::{PoseidonChip, PoseidonConfig};
+use super::Module;
+use crate::tensor::{ValTensor, ValType};
+
+/// Number of limbs a base field coordinate is split into
+pub const LIMBS: usize = 4;
+/// Bit length of each limb
+pub const BITS: usize = 68;
+/// Window size of the scalar multiplications
+const WINDOW_SIZE: usize = 4;
+/// Rows budgeted by [num_rows] for the two scalar multiplications and the limb decompositions
+/// around them. Laying the chip out fails if they take more.
+const ECC_ROWS: usize = 1 << 16;
+
+/// Poseidon chip deriving the pad of an element from the shared point and the element's index
+type PadPoseidon = PoseidonChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, 3>;
+
+/// Number of instances exposed when sealing `len` elements: the limbs of the public key and of
+/// `c1`, followed by the masked elements
+pub fn num_instances(len: usize) -> usize {
+    4 * LIMBS + len
+}
+
+/// Number of rows taken to seal `len` elements. The range table alone needs `2^(BITS / LIMBS)`.
+pub fn num_rows(len: usize) -> usize {
+    std::cmp::max(
+        1 << (BITS / LIMBS),
+        ECC_ROWS + len * (PadPoseidon::num_rows(3) + 1),
+    )
+}
+
+/// The limbs the circuit exposes for `point`, `None` for the identity which has no coordinates.
+pub fn point_to_limbs(point: &G1Affine) -> Option<Vec<Fp>> {
+    let coordinates = Option::from(point.coordinates())?;
+    Some(
+        [*coordinates.x(), *coordinates.y()]
+            .into_iter()
+            .flat_map(fe_to_limbs::<Fq, Fp, LIMBS, BITS>)
+            .collect(),
+    )
+}
+
+/// The pads masking the first `len` elements, derived from the shared point `r·pk`. `None` if the
+/// shared point is the identity.
+pub fn pads(shared: &G1Affine, len: usize) -> Result<Option<Vec<Fp>>, Box<dyn std::error::Error>> {
+    let Some(limbs) = point_to_limbs(shared) else {
+        return Ok(None);
+    };
+    // the coordinates reduced into the scalar field, as the ecc chip keeps them next to the limbs
+    let shift = Fp::from_u128(1u128 << BITS);
+    let native = limbs
+        .chunks(LIMBS)
+        .map(|limbs| {
+            limbs
+                .iter()
+                .rev()
+                .fold(Fp::ZERO, |acc, limb| acc * shift + limb)
+        })
+        .collect::<Vec<_>>();
+
+    let pads = (0..len)
+        .map(|i| Ok(PadPoseidon::run(vec![native[0], native[1], Fp::from(i as u64)])?[0][0]))
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    Ok(Some(pads))
+}
+
+/// Configuration of the [ElGamalChip]
+#[derive(Clone, Debug)]
+pub struct ElGamalConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+    poseidon_config: PoseidonConfig<POSEIDON_WIDTH, POSEIDON_RATE>,
+}
+
+impl ElGamalConfig {
+    /// The instance column the chip exposes the ciphertext in, which the rest of the circuit
+    /// should share
+    pub fn instance(&self) -> Column<Instance> {
+        self.main_gate_config.instance()
+    }
+}
+
+/// Seals elements to a public key, constraining the exposed ciphertext to encrypt the assigned
+/// elements
+#[derive(Clone, Debug)]
+pub struct ElGamalChip {
+    config: ElGamalConfig,
+}
+
+impl ElGamalChip {
+    /// Creates the chip from its config
+    pub fn new(config: ElGamalConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configures the main gate, the range checks of the limbs and the Poseidon chip deriving
+    /// the pads
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> ElGamalConfig {
+        let main_gate_config = MainGate::<Fp>::configure(meta);
+        let range_config = RangeChip::<Fp>::configure(
+            meta,
+            &main_gate_config,
+            vec![BITS / LIMBS],
+            Rns::<Fq, Fp, LIMBS, BITS>::construct().overflow_lengths(),
+        );
+        let poseidon_config = PadPoseidon::configure_with_optional_instance(meta, None);
+
+        ElGamalConfig {
+            main_gate_config,
+            range_config,
+            poseidon_config,
+        }
+    }
+
+    /// Lays out the sealing of the flattened `elements` to `public_key` under `randomness` and
+    /// exposes the ciphertext at `instance_offset`, which is moved past it.
+    pub fn layout(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        elements: &[ValTensor<Fp>],
+        public_key: Value<G1Affine>,
+        randomness: Value<Fp>,
+        instance_offset: &mut usize,
+    ) -> Result<(), Error> {
+        let main_gate = MainGate::<Fp>::new(self.config.main_gate_config.clone());
+        let range_chip = RangeChip::<Fp>::new(self.config.range_config.clone());
+        range_chip.load_table(layouter)?;
+        let mut ecc_chip = BaseFieldEccChip::<G1Affine, LIMBS, BITS>::new(EccConfig::new(
+            self.config.range_config.clone(),
+            self.config.main_gate_config.clone(),
+        ));
+        // only keeps the incomplete additions of the multiplications away from exceptional cases
+        let aux_generator = G1::random(OsRng).to_affine();
+
+        let (limbs, shared) = layouter.assign_region(
+            || "elgamal",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                ecc_chip.assign_aux_generator(ctx, Value::known(aux_generator))?;
+                ecc_chip.assign_aux(ctx, WINDOW_SIZE, 1)?;
+
+                let generator = ecc_chip.assign_constant(ctx, G1Affine::generator())?;
+                let public_key = ecc_chip.assign_point(ctx, public_key)?;
+                let randomness = main_gate.assign_value(ctx, randomness)?;
+
+                let c1 = ecc_chip.mul(ctx, &generator, &randomness, WINDOW_SIZE)?;
+                let c1 = ecc_chip.normalize(ctx, &c1)?;
+                let shared = ecc_chip.mul(ctx, &public_key, &randomness, WINDOW_SIZE)?;
+                let shared = ecc_chip.normalize(ctx, &shared)?;
+
+                let mut limbs = ecc_chip.assign_ec_point_to_limbs(ctx, &public_key)?;
+                limbs.extend(ecc_chip.assign_ec_point_to_limbs(ctx, &c1)?);
+
+                if ctx.offset() > ECC_ROWS {
+                    log::error!(
+                        "the elgamal region takes {} rows, more than the {} budgeted by num_rows",
+                        ctx.offset(),
+                        ECC_ROWS
+                    );
+                    return Err(Error::Synthesis);
+                }
+
+                Ok((
+                    limbs,
+                    [shared.x().native().clone(), shared.y().native().clone()],
+                ))
+            },
+        )?;
+
+        let mut flattened = vec![];
+        for element in elements {
+            let inner = element.get_inner_tensor().map_err(|e| {
+                log::error!("sealed elements must be assigned values: {:?}", e);
+                Error::Synthesis
+            })?;
+            flattened.extend(inner.iter().cloned());
+        }
+
+        let pad_chip = PadPoseidon::new(self.config.poseidon_config.clone());
+        let pads = flattened
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                let message: ValTensor<Fp> = vec![
+                    ValType::from(shared[0].clone()),
+                    ValType::from(shared[1].clone()),
+                    ValType::Constant(Fp::from(i as u64)),
+                ]
+                .into();
+                match pad_chip.layout(layouter, &[message], 0)?.get_inner_tensor() {
+                    Ok(hash) => match &hash[0] {
+                        ValType::PrevAssigned(cell) => Ok(cell.clone()),
+                        _ => Err(Error::Synthesis),
+                    },
+                    Err(_) => Err(Error::Synthesis),
+                }
+            })
+            .collect::<Result<Vec<AssignedCell<Fp, Fp>>, Error>>()?;
+
+        let masked = layouter.assign_region(
+            || "elgamal mask",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                flattened
+                    .iter()
+                    .zip(pads.iter())
+                    .map(|(element, pad)| {
+                        let element = match element {
+                            ValType::PrevAssigned(cell) | ValType::AssignedConstant(cell, _) => {
+                                cell.clone()
+                            }
+                            ValType::Constant(c) => main_gate.assign_constant(ctx, *c)?,
+                            e => {
+                                log::error!("sealed elements must be assigned, got {:?}", e);
+                                return Err(Error::Synthesis);
+                            }
+                        };
+                        main_gate.add(ctx, &element, pad)
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )?;
+
+        for cell in limbs.into_iter().chain(masked) {
+            main_gate.expose_public(layouter.namespace(|| "sealed"), cell, *instance_offset)?;
+            *instance_offset += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::circuit::SimpleFloorPlanner;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::plonk::{Advice, Circuit};
+
+    #[derive(Clone, Debug, Default)]
+    struct SealCircuit {
+        elements: Vec<Fp>,
+        public_key: Value<G1Affine>,
+        randomness: Value<Fp>,
+    }
+
+    impl Circuit<Fp> for SealCircuit {
+        type Config = (ElGamalConfig, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                elements: self.elements.clone(),
+                ..Default::default()
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = meta.advice_column();
+            meta.enable_equality(advice);
+            (ElGamalChip::configure(meta), advice)
+        }
+
+        fn synthesize(
+            &self,
+            (config, advice): Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let cells = layouter.assign_region(
+                || "elements",
+                |mut region| {
+                    self.elements
+                        .iter()
+                        .enumerate()
+                        .map(|(i, x)| {
+                            region
+                                .assign_advice(|| "element", advice, i, || Value::known(*x))
+                                .map(ValType::PrevAssigned)
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+            ElGamalChip::new(config).layout(
+                &mut layouter,
+                &[cells.into()],
+                self.public_key,
+                self.randomness,
+                &mut 0,
+            )
+        }
+    }
+
+    #[test]
+    fn test_elgamal_constrains_ciphertext() {
+        let sk = Fp::random(OsRng);
+        let public_key = (G1Affine::generator() * sk).to_affine();
+        let randomness = Fp::random(OsRng);
+        let elements = vec![Fp::from(3), Fp::from(3), Fp::from(7)];
+
+        let c1 = (G1Affine::generator() * randomness).to_affine();
+        let shared = (public_key * randomness).to_affine();
+        let mut instances = point_to_limbs(&public_key).unwrap();
+        instances.extend(point_to_limbs(&c1).unwrap());
+        let pads = pads(&shared, elements.len()).unwrap().unwrap();
+        instances.extend(elements.iter().zip(pads).map(|(x, pad)| x + pad));
+        assert_eq!(instances.len(), num_instances(elements.len()));
+
+        let circuit = SealCircuit {
+            elements,
+            public_key: Value::known(public_key),
+            randomness: Value::known(randomness),
+        };
+        let k = (num_rows(circuit.elements.len()) as f64).log2().ceil() as u32 + 1;
+
+        let prover = MockProver::run(k, &circuit, vec![instances.clone()]).unwrap();
+        prover.assert_satisfied();
+
+        // a ciphertext that doesn't decrypt to the assigned elements is rejected
+        let mut tampered = instances;
+        *tampered.last_mut().unwrap() += Fp::ONE;
+        let prover = MockProver::run(k, &circuit, vec![tampered]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_identity_has_no_limbs() {
+        assert!(point_to_limbs(&G1Affine::identity()).is_none());
+        assert!(pads(&G1Affine::identity(), 1).unwrap().is_none());
+    }
+}
@@ -4,6 +4,9 @@ pub mod poseidon;
 ///
 pub mod polycommit;
 
+///
+pub mod elgamal;
+
 ///
 pub mod planner;
 use halo2_proofs::{
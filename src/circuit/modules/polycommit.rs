@@ -13,7 +13,7 @@ use halo2curves::group::prime::PrimeCurveAffine;
 use halo2curves::group::Curve;
 use halo2curves::CurveAffine;
 
-use crate::tensor::{Tensor, ValTensor, ValType, VarTensor};
+use crate::tensor::{AssignmentOrder, Tensor, ValTensor, ValType, VarTensor};
 
 use super::Module;
 
@@ -123,7 +123,11 @@ impl Module<Fp> for PolyCommitChip {
         assert_eq!(input.len(), 1);
         layouter.assign_region(
             || "PolyCommit",
-            |mut region| self.config.inputs.assign(&mut region, 0, &input[0]),
+            |mut region| {
+                self.config
+                    .inputs
+                    .assign(&mut region, 0, &input[0], AssignmentOrder::default())
+            },
         )
     }
 
@@ -26,7 +26,11 @@ use crate::{
     },
     tensor::{Tensor, TensorType, ValTensor, VarTensor},
 };
-use std::{collections::BTreeMap, error::Error, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    marker::PhantomData,
+};
 
 use super::{lookup::LookupOp, region::RegionCtx, Op};
 use halo2curves::ff::{Field, PrimeField};
@@ -526,8 +530,13 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
         // we borrow mutably twice so we need to do this dance
 
         let table = if !self.static_lookups.tables.contains_key(nl) {
-            // as all tables have the same input we see if there's another table who's input we can reuse
-            let table = if let Some(table) = self.static_lookups.tables.values().next() {
+            // tables over the same domain have the same input, so we see if there's another table who's input we can reuse
+            let table = if let Some(table) = self
+                .static_lookups
+                .tables
+                .values()
+                .find(|t| t.range == lookup_range)
+            {
                 Table::<F>::configure(
                     cs,
                     lookup_range,
@@ -915,17 +924,16 @@ impl<F: PrimeField + TensorType + PartialOrd> BaseConfig<F> {
 
     /// layout_tables must be called before layout.
     pub fn layout_tables(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), Box<dyn Error>> {
-        for (i, table) in self.static_lookups.tables.values_mut().enumerate() {
+        // the first table of each domain assigns the input it shares with the others
+        let mut assigned_inputs = HashSet::new();
+        for table in self.static_lookups.tables.values_mut() {
+            let preassigned_input = !assigned_inputs.insert(table.range);
             if !table.is_assigned {
                 debug!(
                     "laying out table for {}",
                     crate::circuit::ops::Op::<F>::as_string(&table.nonlinearity)
                 );
-                if i == 0 {
-                    table.layout(layouter, false)?;
-                } else {
-                    table.layout(layouter, true)?;
-                }
+                table.layout(layouter, preassigned_input)?;
             }
         }
         Ok(())
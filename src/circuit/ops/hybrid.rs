@@ -9,6 +9,15 @@ use halo2curves::ff::PrimeField;
 use serde::{Deserialize, Serialize};
 // import run args from model
 
+/// The log2 of `denom` when it's a power of two, in which case dividing by it is a shift.
+fn log2_denom(denom: f32) -> Option<u32> {
+    if denom.fract() != 0.0 || denom < 2.0 || denom >= 2_f32.powi(126) {
+        return None;
+    }
+    let denom = denom as u128;
+    denom.is_power_of_two().then(|| denom.trailing_zeros())
+}
+
 #[allow(missing_docs)]
 /// An enum representing the operations that consist of both lookups and arithmetic operations.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,6 +30,9 @@ pub enum HybridOp {
     Div {
         denom: utils::F32,
         use_range_check_for_int: bool,
+        /// Divide by powers of two with a shift (see [layouts::shift_div]) rather than a range checked division
+        #[serde(default)]
+        use_shift_for_pow2: bool,
     },
     ReduceMax {
         axes: Vec<usize>,
@@ -68,6 +80,22 @@ pub enum HybridOp {
         dim: usize,
         num_classes: usize,
     },
+    /// Samples a token from the top `k` logits (input 0) under randomness (input 1) in `[0, 2^randomness_bits)`
+    SampleTopK {
+        k: usize,
+        randomness_bits: u32,
+    },
+    /// Fused `softmax(c * Q K^T) V` over inputs `[Q, K, V]`. The raw scores are brought to the
+    /// softmax's input scale with a single division by `score_denom`, which folds in both the
+    /// score scaling `c` and the rebasing of the scores, and the softmax output feeds the second
+    /// einsum without being rebased.
+    Attention {
+        qk_equation: String,
+        av_equation: String,
+        score_denom: utils::F32,
+        scale: utils::F32,
+        axes: Vec<usize>,
+    },
 }
 
 impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
@@ -91,9 +119,16 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
         let res = match &self {
             HybridOp::ReduceMax { axes, .. } => tensor::ops::max_axes(&x, axes)?,
             HybridOp::ReduceMin { axes, .. } => tensor::ops::min_axes(&x, axes)?,
-            HybridOp::Div { denom, .. } => {
-                crate::tensor::ops::nonlinearities::const_div(&x, denom.0 as f64)
-            }
+            HybridOp::Div {
+                denom,
+                use_range_check_for_int,
+                use_shift_for_pow2,
+            } => match log2_denom(denom.0) {
+                Some(log_denom) if *use_range_check_for_int && *use_shift_for_pow2 => {
+                    crate::tensor::ops::nonlinearities::shift_div(&x, log_denom)
+                }
+                _ => crate::tensor::ops::nonlinearities::const_div(&x, denom.0 as f64),
+            },
             HybridOp::Recip {
                 input_scale,
                 output_scale,
@@ -118,6 +153,24 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             }
 
             HybridOp::TopK { dim, k, largest } => tensor::ops::topk_axes(&x, *k, *dim, *largest)?,
+            HybridOp::SampleTopK { k, .. } => {
+                let randomness = felt_to_i128(inputs[1][0]);
+                tensor::ops::sample_topk(&x, randomness, *k)?
+            }
+            HybridOp::Attention {
+                qk_equation,
+                av_equation,
+                score_denom,
+                scale,
+                axes,
+            } => {
+                let y = inputs[1].clone().map(|x| felt_to_i128(x));
+                let v = inputs[2].clone().map(|x| felt_to_i128(x));
+                let scores = tensor::ops::einsum(qk_equation, &[x, y])?;
+                let scores = tensor::ops::nonlinearities::const_div(&scores, score_denom.0 as f64);
+                let probs = tensor::ops::nonlinearities::softmax_axes(&scores, scale.into(), axes);
+                tensor::ops::einsum(av_equation, &[probs, v])?
+            }
             HybridOp::MaxPool2d {
                 padding,
                 stride,
@@ -178,6 +231,7 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             HybridOp::Div {
                 denom,
                 use_range_check_for_int,
+                ..
             } => format!(
                 "DIV (denom={}, use_range_check_for_int={})",
                 denom, use_range_check_for_int
@@ -219,6 +273,19 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             HybridOp::OneHot { dim, num_classes } => {
                 format!("ONEHOT (dim={}, num_classes={})", dim, num_classes)
             }
+            HybridOp::SampleTopK { k, randomness_bits } => {
+                format!("SAMPLETOPK (k={}, randomness_bits={})", k, randomness_bits)
+            }
+            HybridOp::Attention {
+                qk_equation,
+                av_equation,
+                score_denom,
+                scale,
+                axes,
+            } => format!(
+                "ATTENTION (qk={}, av={}, score_denom={}, scale={}, axes={:?})",
+                qk_equation, av_equation, score_denom, scale, axes
+            ),
         }
     }
 
@@ -274,9 +341,14 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             HybridOp::Div {
                 denom,
                 use_range_check_for_int,
-                ..
+                use_shift_for_pow2,
             } => {
-                if denom.0.fract() == 0.0 && *use_range_check_for_int {
+                if let (Some(log_denom), true) = (
+                    log2_denom(denom.0),
+                    *use_range_check_for_int && *use_shift_for_pow2,
+                ) {
+                    layouts::shift_div(config, region, values[..].try_into()?, log_denom)?
+                } else if denom.0.fract() == 0.0 && *use_range_check_for_int {
                     layouts::loop_div(
                         config,
                         region,
@@ -347,6 +419,25 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             HybridOp::OneHot { dim, num_classes } => {
                 layouts::one_hot_axis(config, region, values[..].try_into()?, *num_classes, *dim)?
             }
+            HybridOp::SampleTopK { k, randomness_bits } => {
+                layouts::sample_topk(config, region, values[..].try_into()?, *k, *randomness_bits)?
+            }
+            HybridOp::Attention {
+                qk_equation,
+                av_equation,
+                score_denom,
+                scale,
+                axes,
+            } => layouts::attention(
+                config,
+                region,
+                values[..].try_into()?,
+                qk_equation,
+                av_equation,
+                *score_denom,
+                *scale,
+                axes,
+            )?,
         }))
     }
 
@@ -358,8 +449,13 @@ impl<F: PrimeField + TensorType + PartialOrd> Op<F> for HybridOp {
             | HybridOp::LessEqual { .. }
             | HybridOp::ReduceArgMax { .. }
             | HybridOp::OneHot { .. }
+            | HybridOp::SampleTopK { .. }
             | HybridOp::ReduceArgMin { .. } => 0,
             HybridOp::Softmax { .. } => 2 * in_scales[0],
+            // the softmax output is at twice its input scale and is multiplied into V
+            HybridOp::Attention { scale, .. } => {
+                2 * multiplier_to_scale(scale.0 as f64) + in_scales[2]
+            }
             HybridOp::Recip { output_scale, .. } => multiplier_to_scale(output_scale.0 as f64),
             _ => in_scales[0],
         };
@@ -132,6 +132,109 @@ pub(crate) fn div<F: PrimeField + TensorType + PartialOrd>(
     Ok(claimed_output)
 }
 
+/// Div by 2^log_div layout, constraining the remainder with a bit decomposition rather than a range check lookup
+pub(crate) fn shift_div<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    value: &[ValTensor<F>; 1],
+    log_div: u32,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if log_div == 0 {
+        return Ok(value[0].clone());
+    }
+
+    let input = value[0].clone();
+    let input_dims = input.dims();
+
+    let div = 1_i128 << log_div;
+
+    let unknowns = || {
+        Tensor::new(
+            Some(&vec![Value::<F>::unknown(); input.len()]),
+            &[input.len()],
+        )
+    };
+
+    let is_assigned = !input.any_unknowns()?;
+
+    let mut claimed_output: ValTensor<F> = if is_assigned {
+        let input_evals = input.get_int_evals()?;
+        tensor::ops::nonlinearities::shift_div(&input_evals, log_div)
+            .iter()
+            .map(|x| Value::known(i128_to_felt(*x)))
+            .collect::<Tensor<Value<F>>>()
+            .into()
+    } else {
+        unknowns()?.into()
+    };
+    claimed_output.reshape(input_dims)?;
+    let claimed_output = region.assign(&config.custom_gates.output, &claimed_output)?;
+    region.increment(claimed_output.len());
+
+    let product = pairwise(
+        config,
+        region,
+        &[
+            claimed_output.clone(),
+            create_constant_tensor(i128_to_felt(div), 1),
+        ],
+        BaseOp::Mult,
+    )?;
+
+    let diff_with_input = pairwise(config, region, &[input.clone(), product], BaseOp::Sub)?;
+
+    // as halves are rounded up the remainder lies in [0, div)
+    let remainder = pairwise(
+        config,
+        region,
+        &[
+            diff_with_input,
+            create_constant_tensor(i128_to_felt(div / 2), 1),
+        ],
+        BaseOp::Add,
+    )?;
+
+    let remainder_evals = if is_assigned {
+        Some(remainder.get_int_evals()?)
+    } else {
+        None
+    };
+
+    // recompose the remainder from its log_div bits, most significant first
+    let mut recomposed: Option<ValTensor<F>> = None;
+    for i in (0..log_div).rev() {
+        let mut bit: ValTensor<F> = match &remainder_evals {
+            Some(evals) => evals
+                .iter()
+                .map(|r| Value::known(F::from(((r >> i) & 1) as u64)))
+                .collect::<Tensor<Value<F>>>()
+                .into(),
+            None => unknowns()?.into(),
+        };
+        bit.reshape(input_dims)?;
+        let bit = boolean_identity(config, region, &[bit], true)?;
+
+        recomposed = Some(match recomposed {
+            None => bit,
+            Some(acc) => {
+                let doubled = pairwise(
+                    config,
+                    region,
+                    &[acc, create_constant_tensor(F::from(2), 1)],
+                    BaseOp::Mult,
+                )?;
+                pairwise(config, region, &[doubled, bit], BaseOp::Add)?
+            }
+        });
+    }
+
+    if let Some(recomposed) = recomposed {
+        enforce_equality(config, region, &[remainder, recomposed])?;
+    }
+
+    Ok(claimed_output)
+}
+
 /// recip accumulated layout
 pub(crate) fn recip<F: PrimeField + TensorType + PartialOrd>(
     config: &BaseConfig<F>,
@@ -642,6 +745,77 @@ pub(crate) fn topk_axes<F: PrimeField + TensorType + PartialOrd>(
     Ok(output)
 }
 
+/// Proves that `token` was sampled from the top `k` logits under the given randomness, i.e that
+/// `logits[token]` is the `(randomness mod k)`-th largest logit. The randomness is decomposed as
+/// `randomness = q * k + j` with both `j` and `q` range checked, which makes `j` unique.
+pub(crate) fn sample_topk<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 2],
+    k: usize,
+    randomness_bits: u32,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let (mut logits, randomness) = (values[0].clone(), values[1].clone());
+    logits.flatten();
+    if randomness.len() != 1 {
+        return Err("sampling randomness must be a single element".into());
+    }
+    if k == 0 || k > logits.len() {
+        return Err(format!(
+            "cannot sample from the top {} of {} logits",
+            k,
+            logits.len()
+        )
+        .into());
+    }
+
+    let is_assigned = !logits.any_unknowns()? && !randomness.any_unknowns()?;
+
+    // this is safe because we later constrain it
+    let (quotient, remainder, token): (ValTensor<F>, ValTensor<F>, ValTensor<F>) = if is_assigned {
+        let r = randomness.get_int_evals()?[0];
+        let token = tensor::ops::sample_topk(&logits.get_int_evals()?, r, k)?;
+        let known = |x: i128| -> Result<ValTensor<F>, Box<dyn Error>> {
+            Ok(Tensor::new(Some(&[Value::known(i128_to_felt::<F>(x))]), &[1])?.into())
+        };
+        (
+            known(r.div_euclid(k as i128))?,
+            known(r.rem_euclid(k as i128))?,
+            known(token[0])?,
+        )
+    } else {
+        let unknown = || -> Result<ValTensor<F>, Box<dyn Error>> {
+            Ok(Tensor::new(Some(&[Value::<F>::unknown()]), &[1])?.into())
+        };
+        (unknown()?, unknown()?, unknown()?)
+    };
+
+    let quotient = region.assign(&config.custom_gates.inputs[0], &quotient)?;
+    let remainder = region.assign(&config.custom_gates.inputs[1], &remainder)?;
+    region.increment(1);
+    let token = region.assign(&config.custom_gates.output, &token)?;
+    region.increment(1);
+
+    // randomness = quotient * k + remainder
+    let k_tensor = create_constant_tensor(F::from(k as u64), 1);
+    let product = pairwise(config, region, &[quotient.clone(), k_tensor], BaseOp::Mult)?;
+    let recomposed = pairwise(config, region, &[product, remainder.clone()], BaseOp::Add)?;
+    enforce_equality(config, region, &[recomposed, randomness])?;
+
+    range_check(config, region, &[remainder.clone()], &(0, k as i128 - 1))?;
+    let max_quotient = ((1i128 << randomness_bits) - 1) / k as i128;
+    range_check(config, region, &[quotient], &(0, max_quotient))?;
+
+    // the candidates, sorted in descending order
+    let candidates = _select_topk(config, region, &[logits.clone()], k, true)?;
+    let sampled_val = select(config, region, &[candidates, remainder])?;
+
+    let claimed_val = select(config, region, &[logits, token.clone()])?;
+    enforce_equality(config, region, &[claimed_val, sampled_val])?;
+
+    Ok(token)
+}
+
 fn select<F: PrimeField + TensorType + PartialOrd>(
     config: &BaseConfig<F>,
     region: &mut RegionCtx<F>,
@@ -820,15 +994,40 @@ pub(crate) fn shuffles<F: PrimeField + TensorType + PartialOrd>(
         return Err("input and reference must be same length".into());
     }
 
-    let reference = region.assign_shuffle(&config.shuffles.references[0], &reference)?;
-    let reference_len = reference.len();
+    // every element of the shuffle is tagged with the shuffle index
+    let reference_tags = create_constant_tensor(F::from(shuffle_index as u64), reference.len());
+    let input_tags = create_constant_tensor(F::from(shuffle_index as u64), input.len());
+
+    tagged_shuffle(
+        config,
+        region,
+        &[input, input_tags],
+        &[reference, reference_tags],
+    )
+}
+
+/// Looks up each `(input[0], input[1])` pair in the table of `(reference[0], reference[1])` pairs
+/// laid out in the shuffle columns, the second element of each pair being a tag. Despite the name
+/// of the columns this is a (dynamic) lookup rather than a permutation argument: every input pair
+/// has to appear among the reference pairs, but reference pairs can be matched any number of
+/// times, or not at all. Returns the assigned `input[0]`.
+fn tagged_shuffle<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    input: &[ValTensor<F>; 2],
+    reference: &[ValTensor<F>; 2],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    if input[0].len() != input[1].len() || reference[0].len() != reference[1].len() {
+        return Err("shuffle values and tags must be same length".into());
+    }
 
-    // now create a vartensor of constants for the shuffle index
-    let index = create_constant_tensor(F::from(shuffle_index as u64), reference_len);
-    let index = region.assign_shuffle(&config.shuffles.references[1], &index)?;
+    let reference_len = reference[0].len();
+    region.assign_shuffle(&config.shuffles.references[0], &reference[0])?;
+    region.assign_shuffle(&config.shuffles.references[1], &reference[1])?;
 
-    let input = region.assign(&config.shuffles.inputs[0], &input)?;
-    region.assign(&config.shuffles.inputs[1], &index)?;
+    let input_len = input[0].len();
+    let assigned_input = region.assign(&config.shuffles.inputs[0], &input[0])?;
+    region.assign(&config.shuffles.inputs[1], &input[1])?;
 
     if !region.is_dummy() {
         (0..reference_len)
@@ -844,7 +1043,7 @@ pub(crate) fn shuffles<F: PrimeField + TensorType + PartialOrd>(
 
     if !region.is_dummy() {
         // Enable the selectors
-        (0..reference_len)
+        (0..input_len)
             .map(|i| {
                 let (x, y, z) =
                     config.custom_gates.inputs[0].cartesian_coord(region.linear_coord() + i);
@@ -863,9 +1062,70 @@ pub(crate) fn shuffles<F: PrimeField + TensorType + PartialOrd>(
 
     region.increment_shuffle_col_coord(reference_len);
     region.increment_shuffle_index(1);
-    region.increment(reference_len);
+    region.increment(input_len);
+
+    Ok(assigned_input)
+}
+
+/// Reads the element of the flattened `values[0]` at each of the constant `indices`, e.g for a
+/// gather with constant indices. Each element of the input is tagged with its (distinct) position
+/// and each output element with the position it reads, and every tagged output element is looked
+/// up among the tagged input elements, see [tagged_shuffle]. As the tags are distinct, an output
+/// element can only equal the input element at the position it reads. Indices can repeat or skip
+/// positions: the output is not a permutation of the input.
+///
+/// The tags of a rearrangement are `(shuffle_index + 1) * 2^32 + position`, so they are distinct
+/// from the tags of plain shuffles (their shuffle index) and of other rearrangements as long as
+/// positions and shuffle indices fit in 32 bits. The tags are constants, so this bound is checked
+/// when laying out rather than constrained in the circuit.
+pub(crate) fn rearrange<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+    indices: &Tensor<usize>,
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    const TAG_BOUND: u128 = 1 << 32;
+    let mut input = values[0].clone();
+    input.flatten();
+    if indices.iter().any(|i| *i >= input.len()) {
+        return Err("rearrange indices out of bounds".into());
+    }
+
+    // offset past the tags of plain shuffles, i.e their shuffle index, so the two can't collide
+    if input.len() as u128 > TAG_BOUND || region.shuffle_index() as u128 >= TAG_BOUND {
+        return Err(format!(
+            "rearrange tags must fit in 32 bits, got {} positions at shuffle index {}",
+            input.len(),
+            region.shuffle_index()
+        )
+        .into());
+    }
+    let offset = (region.shuffle_index() as u128 + 1) * TAG_BOUND;
+    let tag = |i: usize| ValType::Constant(F::from_u128(offset + i as u128));
+    let reference_tags: ValTensor<F> = Tensor::from((0..input.len()).map(tag)).into();
+    let output_tags: ValTensor<F> = Tensor::from(indices.iter().map(|i| tag(*i))).into();
+
+    // the output is assigned afresh rather than copied from the input
+    let output: ValTensor<F> = if input.any_unknowns()? {
+        Tensor::new(
+            Some(&vec![Value::<F>::unknown(); indices.len()]),
+            &[indices.len()],
+        )?
+    } else {
+        let evals = input.get_felt_evals()?;
+        Tensor::from(indices.iter().map(|i| Value::known(evals[*i])))
+    }
+    .into();
+
+    let mut output = tagged_shuffle(
+        config,
+        region,
+        &[output, output_tags],
+        &[input, reference_tags],
+    )?;
+    output.reshape(indices.dims())?;
 
-    Ok(input)
+    Ok(output)
 }
 
 /// One hot accumulated layout
@@ -1232,7 +1492,6 @@ pub(crate) fn linearize_nd_index<F: PrimeField + TensorType + PartialOrd>(
             const_offset += F::from(coord[i] as u64) * dim_multiplier[i];
         }
 
-
         let const_offset = create_constant_tensor(const_offset, 1);
 
         let mut results = vec![];
@@ -1256,7 +1515,7 @@ pub(crate) fn linearize_nd_index<F: PrimeField + TensorType + PartialOrd>(
                     .all(|x| *x < dims.iter().product::<usize>() as i128),
                 "res is greater than the product of the dims {} (coord={}, index_dim_multiplier={}, res={})",
                 dims.iter().product::<usize>(),
-                index_val.show(), 
+                index_val.show(),
                 index_dim_multiplier.show(),
                 res.show()
             );
@@ -1457,7 +1716,6 @@ pub(crate) fn scatter_nd<F: PrimeField + TensorType + PartialOrd>(
     region.increment(claimed_output.len());
     claimed_output.reshape(input.dims())?;
 
-
     // scatter elements is the inverse of gather elements
     let (gather_src, linear_index) =
         gather_nd(config, region, &[claimed_output.clone(), index.clone()], 0)?;
@@ -2108,6 +2366,34 @@ pub(crate) fn equals_zero<F: PrimeField + TensorType + PartialOrd>(
     Ok(output)
 }
 
+/// Counts the valid slots of a validity mask, which holds 1 for each valid slot of a tensor padded
+/// to a fixed number of slots and 0 for each padded one. The mask is constrained to be boolean and
+/// its valid slots to come before the padding, so the count alone tells which slots of the
+/// flattened tensor are valid.
+pub(crate) fn valid_count<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 1],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let mut mask = values[0].clone();
+    mask.flatten();
+    let len = mask.len();
+
+    let is_valid = boolean_identity(config, region, &[mask], true)?;
+    let is_padding = not(config, region, &[is_valid.clone()])?;
+
+    if len > 1 {
+        // a valid slot can't follow a padded one, ie. is_valid[i] * is_padding[i - 1] == 0
+        let next = slice(config, region, &[is_valid.clone()], &0, &1, &len)?;
+        let prev = slice(config, region, &[is_padding], &0, &0, &(len - 1))?;
+        let out_of_order = pairwise(config, region, &[next, prev], BaseOp::Mult)?;
+        let zero_tensor = create_zero_tensor(out_of_order.len());
+        enforce_equality(config, region, &[out_of_order, zero_tensor])?;
+    }
+
+    sum(config, region, &[is_valid])
+}
+
 /// Xor boolean operation
 pub(crate) fn xor<F: PrimeField + TensorType + PartialOrd>(
     config: &BaseConfig<F>,
@@ -3222,6 +3508,35 @@ pub(crate) fn softmax<F: PrimeField + TensorType + PartialOrd>(
     Ok(softmax)
 }
 
+/// Fused attention layout, `softmax(c * Q K^T) V`. The scores are divided down to the softmax's
+/// input scale with a single lookup (folding `c` into the divisor rather than scaling and then
+/// rebasing), and the softmax output goes straight into the second einsum, leaving a single
+/// rebase of the attention output to the caller.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn attention<F: PrimeField + TensorType + PartialOrd>(
+    config: &BaseConfig<F>,
+    region: &mut RegionCtx<F>,
+    values: &[ValTensor<F>; 3],
+    qk_equation: &str,
+    av_equation: &str,
+    score_denom: utils::F32,
+    scale: utils::F32,
+    axes: &[usize],
+) -> Result<ValTensor<F>, Box<dyn Error>> {
+    let [q, k, v] = values.clone();
+
+    let scores = einsum(config, region, &[q, k], qk_equation)?;
+    let scores = nonlinearity(
+        config,
+        region,
+        &[scores],
+        &LookupOp::Div { denom: score_denom },
+    )?;
+    let probs = softmax_axes(config, region, &[scores], scale, axes)?;
+
+    einsum(config, region, &[probs, v], av_equation)
+}
+
 /// Checks that the percent error between the expected public output and the actual output value
 /// is within the percent error expressed by the `tol` input, where `tol == 1.0` means the percent
 /// error tolerance is 1 percent.
@@ -18,6 +18,11 @@ pub enum PolyOp {
         batch_dims: usize,
         indices: Option<Tensor<usize>>,
     },
+    /// Reads the flat input element at each of the constant `indices`, laid out as a tagged
+    /// lookup in the shuffle columns, see [crate::RunArgs::shuffle_permutations]
+    Rearrange {
+        indices: Tensor<usize>,
+    },
     ScatterElements {
         dim: usize,
         constant_idx: Option<Tensor<usize>>,
@@ -101,6 +106,7 @@ impl<F: PrimeField + TensorType + PartialOrd + Serialize + for<'de> Deserialize<
         match &self {
             PolyOp::GatherElements { dim, .. } => format!("GATHERELEMENTS (dim={})", dim),
             PolyOp::GatherND { batch_dims, .. } => format!("GATHERND (batch_dims={})", batch_dims),
+            PolyOp::Rearrange { .. } => "REARRANGE".into(),
             PolyOp::ScatterElements { dim, .. } => format!("SCATTERELEMENTS (dim={})", dim),
             PolyOp::ScatterND { .. } => "SCATTERND".into(),
             PolyOp::MultiBroadcastTo { shape } => format!("MULTIBROADCASTTO (shape={:?})", shape),
@@ -239,6 +245,14 @@ impl<F: PrimeField + TensorType + PartialOrd + Serialize + for<'de> Deserialize<
                 };
                 tensor::ops::gather_nd(&x, &y, *batch_dims)
             }
+            PolyOp::Rearrange { indices } => {
+                // indexes the flattened input
+                let x = &inputs[0];
+                if indices.iter().any(|i| *i >= x.len()) {
+                    return Err(TensorError::DimMismatch("rearrange indices".to_string()));
+                }
+                Ok(indices.map(|i| x[i].clone()))
+            }
             PolyOp::ScatterElements { dim, constant_idx } => {
                 let x = inputs[0].clone();
 
@@ -331,6 +345,9 @@ impl<F: PrimeField + TensorType + PartialOrd + Serialize + for<'de> Deserialize<
                     layouts::gather_nd(config, region, values[..].try_into()?, *batch_dims)?.0
                 }
             }
+            PolyOp::Rearrange { indices } => {
+                layouts::rearrange(config, region, values[..].try_into()?, indices)?
+            }
             PolyOp::ScatterElements { dim, constant_idx } => {
                 if let Some(idx) = constant_idx {
                     tensor::ops::scatter(
@@ -1,15 +1,15 @@
 use crate::{
     circuit::table::Range,
-    tensor::{Tensor, TensorError, TensorType, ValTensor, ValType, VarTensor},
+    tensor::{AssignmentOrder, Tensor, TensorError, TensorType, ValTensor, ValType, VarTensor},
 };
 use halo2_proofs::{
-    circuit::Region,
-    plonk::{Error, Selector},
+    circuit::{AssignedCell, Region, Value},
+    plonk::{Advice, Column, Error, Selector},
 };
 use halo2curves::ff::PrimeField;
 use std::{
     cell::RefCell,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc, Mutex,
@@ -80,6 +80,33 @@ impl ShuffleIndex {
     }
 }
 
+/// Widens the input range of each lookup in `ranges` to cover its range in `other`
+fn merge_lookup_input_ranges(
+    ranges: &mut HashMap<LookupOp, Range>,
+    other: &HashMap<LookupOp, Range>,
+) {
+    for (op, (min, max)) in other {
+        let range = ranges.entry(op.clone()).or_insert((*min, *max));
+        *range = (range.0.min(*min), range.1.max(*max));
+    }
+}
+
+/// The rows, constants and lookups used by a dummy region, see [RegionCtx::usage]
+#[derive(Clone, Debug, Default)]
+pub struct RegionUsage {
+    row: usize,
+    linear_coord: usize,
+    total_constants: usize,
+    dynamic_lookup_index: DynamicLookupIndex,
+    shuffle_index: ShuffleIndex,
+    used_lookups: HashSet<LookupOp>,
+    used_range_checks: HashSet<Range>,
+    lookup_input_ranges: HashMap<LookupOp, Range>,
+    max_lookup_inputs: i128,
+    min_lookup_inputs: i128,
+    max_range_size: i128,
+}
+
 /// Region error
 #[derive(Debug, thiserror::Error)]
 pub enum RegionError {
@@ -130,10 +157,12 @@ pub struct RegionCtx<'a, F: PrimeField + TensorType + PartialOrd> {
     shuffle_index: ShuffleIndex,
     used_lookups: HashSet<LookupOp>,
     used_range_checks: HashSet<Range>,
+    lookup_input_ranges: HashMap<LookupOp, Range>,
     max_lookup_inputs: i128,
     min_lookup_inputs: i128,
     max_range_size: i128,
     throw_range_check_error: bool,
+    assignment_order: AssignmentOrder,
 }
 
 impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
@@ -167,6 +196,21 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         self.throw_range_check_error
     }
 
+    ///
+    pub fn num_inner_cols(&self) -> usize {
+        self.num_inner_cols
+    }
+
+    /// The order in which the cells of assigned tensors are written
+    pub fn assignment_order(&self) -> AssignmentOrder {
+        self.assignment_order
+    }
+
+    /// Sets the order in which the cells of assigned tensors are written
+    pub fn set_assignment_order(&mut self, order: AssignmentOrder) {
+        self.assignment_order = order;
+    }
+
     /// Create a new region context
     pub fn new(region: Region<'a, F>, row: usize, num_inner_cols: usize) -> RegionCtx<'a, F> {
         let region = Some(RefCell::new(region));
@@ -182,10 +226,12 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
             shuffle_index: ShuffleIndex::default(),
             used_lookups: HashSet::new(),
             used_range_checks: HashSet::new(),
+            lookup_input_ranges: HashMap::new(),
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
             throw_range_check_error: false,
+            assignment_order: AssignmentOrder::default(),
         }
     }
     /// Create a new region context from a wrapped region
@@ -207,10 +253,12 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
             shuffle_index,
             used_lookups: HashSet::new(),
             used_range_checks: HashSet::new(),
+            lookup_input_ranges: HashMap::new(),
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
             throw_range_check_error: false,
+            assignment_order: AssignmentOrder::default(),
         }
     }
 
@@ -233,10 +281,12 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
             shuffle_index: ShuffleIndex::default(),
             used_lookups: HashSet::new(),
             used_range_checks: HashSet::new(),
+            lookup_input_ranges: HashMap::new(),
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
             throw_range_check_error,
+            assignment_order: AssignmentOrder::default(),
         }
     }
 
@@ -259,10 +309,12 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
             shuffle_index: ShuffleIndex::default(),
             used_lookups: HashSet::new(),
             used_range_checks: HashSet::new(),
+            lookup_input_ranges: HashMap::new(),
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
             throw_range_check_error,
+            assignment_order: AssignmentOrder::default(),
         }
     }
 
@@ -317,6 +369,7 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         let min_lookup_inputs = AtomicInt::new(self.min_lookup_inputs());
         let lookups = Arc::new(Mutex::new(self.used_lookups.clone()));
         let range_checks = Arc::new(Mutex::new(self.used_range_checks.clone()));
+        let lookup_input_ranges = Arc::new(Mutex::new(self.lookup_input_ranges.clone()));
         let dynamic_lookup_index = Arc::new(Mutex::new(self.dynamic_lookup_index.clone()));
         let shuffle_index = Arc::new(Mutex::new(self.shuffle_index.clone()));
 
@@ -356,6 +409,9 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
                 // update the range checks
                 let mut range_checks = range_checks.lock().unwrap();
                 range_checks.extend(local_reg.used_range_checks());
+                // update the per lookup input ranges
+                let mut lookup_input_ranges = lookup_input_ranges.lock().unwrap();
+                merge_lookup_input_ranges(&mut lookup_input_ranges, &local_reg.lookup_input_ranges);
                 // update the dynamic lookup index
                 let mut dynamic_lookup_index = dynamic_lookup_index.lock().unwrap();
                 dynamic_lookup_index.update(&local_reg.dynamic_lookup_index);
@@ -388,6 +444,20 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
             .map_err(|e| {
                 RegionError::from(format!("dummy_loop: failed to get range checks: {:?}", e))
             })?;
+        self.lookup_input_ranges = Arc::try_unwrap(lookup_input_ranges)
+            .map_err(|e| {
+                RegionError::from(format!(
+                    "dummy_loop: failed to get lookup input ranges: {:?}",
+                    e
+                ))
+            })?
+            .into_inner()
+            .map_err(|e| {
+                RegionError::from(format!(
+                    "dummy_loop: failed to get lookup input ranges: {:?}",
+                    e
+                ))
+            })?;
         self.dynamic_lookup_index = Arc::try_unwrap(dynamic_lookup_index)
             .map_err(|e| {
                 RegionError::from(format!(
@@ -414,16 +484,22 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         Ok(())
     }
 
-    /// Update the max and min from inputs
-    pub fn update_max_min_lookup_inputs(
-        &mut self,
-        inputs: &[ValTensor<F>],
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    /// The min and max of the inputs, widened to include 0
+    fn int_range(inputs: &[ValTensor<F>]) -> Result<Range, Box<dyn std::error::Error>> {
         let (mut min, mut max) = (0, 0);
         for i in inputs {
             max = max.max(i.get_int_evals()?.into_iter().max().unwrap_or_default());
             min = min.min(i.get_int_evals()?.into_iter().min().unwrap_or_default());
         }
+        Ok((min, max))
+    }
+
+    /// Update the max and min from inputs
+    pub fn update_max_min_lookup_inputs(
+        &mut self,
+        inputs: &[ValTensor<F>],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (min, max) = Self::int_range(inputs)?;
         self.max_lookup_inputs = self.max_lookup_inputs.max(max);
         self.min_lookup_inputs = self.min_lookup_inputs.min(min);
         Ok(())
@@ -455,8 +531,15 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         lookup: LookupOp,
         inputs: &[ValTensor<F>],
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let range = Self::int_range(inputs)?;
+        merge_lookup_input_ranges(
+            &mut self.lookup_input_ranges,
+            &HashMap::from([(lookup.clone(), range)]),
+        );
         self.used_lookups.insert(lookup);
-        self.update_max_min_lookup_inputs(inputs)
+        self.max_lookup_inputs = self.max_lookup_inputs.max(range.1);
+        self.min_lookup_inputs = self.min_lookup_inputs.min(range.0);
+        Ok(())
     }
 
     /// add used range check
@@ -510,6 +593,11 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         self.used_range_checks.clone()
     }
 
+    /// the min and max inputs of each used lookup
+    pub fn lookup_input_ranges(&self) -> HashMap<LookupOp, Range> {
+        self.lookup_input_ranges.clone()
+    }
+
     /// max lookup inputs
     pub fn max_lookup_inputs(&self) -> i128 {
         self.max_lookup_inputs
@@ -525,6 +613,41 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
         self.max_range_size
     }
 
+    /// The usage of the region, which unlike the region itself can be sent across threads.
+    pub fn usage(&self) -> RegionUsage {
+        RegionUsage {
+            row: self.row,
+            linear_coord: self.linear_coord,
+            total_constants: self.total_constants,
+            dynamic_lookup_index: self.dynamic_lookup_index.clone(),
+            shuffle_index: self.shuffle_index.clone(),
+            used_lookups: self.used_lookups.clone(),
+            used_range_checks: self.used_range_checks.clone(),
+            lookup_input_ranges: self.lookup_input_ranges.clone(),
+            max_lookup_inputs: self.max_lookup_inputs,
+            min_lookup_inputs: self.min_lookup_inputs,
+            max_range_size: self.max_range_size,
+        }
+    }
+
+    /// Adds the usage of a dummy region that started at row 0, e.g one an independent operation
+    /// was laid out in, as if it had been laid out in this region.
+    pub fn add_usage(&mut self, usage: &RegionUsage) {
+        self.row += usage.row;
+        self.linear_coord += usage.linear_coord;
+        self.total_constants += usage.total_constants;
+        self.dynamic_lookup_index
+            .update(&usage.dynamic_lookup_index);
+        self.shuffle_index.update(&usage.shuffle_index);
+        self.used_lookups.extend(usage.used_lookups.iter().cloned());
+        self.used_range_checks
+            .extend(usage.used_range_checks.iter().copied());
+        merge_lookup_input_ranges(&mut self.lookup_input_ranges, &usage.lookup_input_ranges);
+        self.max_lookup_inputs = self.max_lookup_inputs.max(usage.max_lookup_inputs);
+        self.min_lookup_inputs = self.min_lookup_inputs.min(usage.min_lookup_inputs);
+        self.max_range_size = self.max_range_size.max(usage.max_range_size);
+    }
+
     /// Assign a constant value
     pub fn assign_constant(&mut self, var: &VarTensor, value: F) -> Result<ValType<F>, Error> {
         self.total_constants += 1;
@@ -543,12 +666,34 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
     ) -> Result<ValTensor<F>, Error> {
         self.total_constants += values.num_constants();
         if let Some(region) = &self.region {
-            var.assign(&mut region.borrow_mut(), self.linear_coord, values)
+            var.assign(
+                &mut region.borrow_mut(),
+                self.linear_coord,
+                values,
+                self.assignment_order,
+            )
         } else {
             Ok(values.clone())
         }
     }
 
+    /// Overwrites the value of an assigned advice cell, leaving the constraints on it untouched.
+    /// Only meant for soundness tests, which check that tampered witnesses are rejected (see
+    /// [crate::circuit::soundness]).
+    pub fn overwrite(&mut self, cell: &AssignedCell<F, F>, value: Value<F>) -> Result<(), Error> {
+        if let Some(region) = &self.region {
+            let column =
+                Column::<Advice>::try_from(cell.cell().column).map_err(|_| Error::Synthesis)?;
+            region.borrow_mut().assign_advice(
+                || "overwrite",
+                column,
+                cell.cell().row_offset,
+                || value,
+            )?;
+        }
+        Ok(())
+    }
+
     ///
     pub fn combined_dynamic_shuffle_coord(&self) -> usize {
         self.dynamic_lookup_col_coord() + self.shuffle_col_coord()
@@ -566,6 +711,7 @@ impl<'a, F: PrimeField + TensorType + PartialOrd> RegionCtx<'a, F> {
                 &mut region.borrow_mut(),
                 self.combined_dynamic_shuffle_coord(),
                 values,
+                self.assignment_order,
             )
         } else {
             Ok(values.clone())
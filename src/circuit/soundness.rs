@@ -0,0 +1,248 @@
+//! Soundness self-tests of circuit ops, run by `ezkl self-test`.
+//!
+//! The ops checked are those of [soundness_cases]: the elementwise arithmetic, sums, products
+//! and einsums, the division by a constant and every lookup op. Other ops, e.g. convolutions,
+//! gathers or the hybrid comparisons, aren't covered.
+//!
+//! An op is laid out on fixed inputs and the honest witness is checked to satisfy the circuit.
+//! Then, one at a time, each assigned cell of the op's output is overwritten with a wrong value,
+//! and the mock prover must reject every such witness. An op accepting a tampered output is
+//! under-constrained: a prover could claim any output for it.
+//!
+//! Custom [Op] implementations can be checked with [check_soundness], passing the lookups their
+//! layout uses.
+
+use super::golden::{golden_ops, GOLDEN_INPUTS, GOLDEN_LOOKUP_RANGE};
+use super::harness::{OpCircuit, OpCircuitParams};
+use super::hybrid::HybridOp;
+use super::lookup::LookupOp;
+use super::poly::PolyOp;
+use super::utils::F32;
+use super::{BaseConfig, Op};
+use crate::fieldutils::i128_to_felt;
+use crate::tensor::{Tensor, ValType};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error as PlonkError};
+use halo2curves::bn256::Fr as Fp;
+use halo2curves::ff::Field;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The logrows of every soundness circuit.
+pub const SOUNDNESS_LOGROWS: u32 = 10;
+/// The most cells an input or output of a soundness circuit can take.
+const SOUNDNESS_MAX_LEN: usize = 64;
+
+/// An op, the quantized inputs it is checked on and the lookups its layout uses.
+#[derive(Clone, Debug)]
+pub struct SoundnessCase {
+    /// The op under test
+    pub op: Box<dyn Op<Fp>>,
+    /// The quantized inputs the op is laid out on
+    pub inputs: Vec<Tensor<i128>>,
+    /// The lookups the layout of the op uses
+    pub lookups: Vec<LookupOp>,
+}
+
+/// The outcome of the soundness self-test of an op.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SoundnessReport {
+    /// The op under test
+    pub op: String,
+    /// The number of output cells tampered with
+    pub tampered_cells: usize,
+    /// The flat indices of the output cells whose tampered witness was accepted
+    pub accepted: Vec<usize>,
+}
+
+impl SoundnessReport {
+    /// Whether every tampered witness was rejected
+    pub fn is_sound(&self) -> bool {
+        self.accepted.is_empty()
+    }
+}
+
+/// The cases `ezkl self-test` runs: the arithmetic ops that constrain their outputs, the lookup
+/// free rescaling by a power of two and every lookup op of the golden records (see
+/// [super::golden]).
+pub fn soundness_cases() -> Vec<SoundnessCase> {
+    let x = Tensor::new(Some(&GOLDEN_INPUTS[..]), &[GOLDEN_INPUTS.len()]).unwrap();
+    let y = x.map(|v| 3 - v / 8);
+    let mut matrix = x.clone();
+    matrix.reshape(&[2, 4]).unwrap();
+    let mut weights = y.clone();
+    weights.reshape(&[4, 2]).unwrap();
+
+    let poly = |op: PolyOp, inputs: Vec<Tensor<i128>>| SoundnessCase {
+        op: Box::new(op),
+        inputs,
+        lookups: vec![],
+    };
+    let mut cases = vec![
+        poly(PolyOp::Add, vec![x.clone(), y.clone()]),
+        poly(PolyOp::Sub, vec![x.clone(), y.clone()]),
+        poly(PolyOp::Mult, vec![x.clone(), y.clone()]),
+        poly(PolyOp::Neg, vec![x.clone()]),
+        poly(PolyOp::Pow(2), vec![x.clone()]),
+        poly(PolyOp::Sum { axes: vec![0] }, vec![x.clone()]),
+        poly(
+            PolyOp::Prod {
+                axes: vec![0],
+                len_prod: 4,
+            },
+            vec![y
+                .clone()
+                .map(|v| v.clamp(-3, 3))
+                .get_slice(&[0..4])
+                .unwrap()],
+        ),
+        poly(
+            PolyOp::Einsum {
+                equation: "ij,jk->ik".to_string(),
+            },
+            vec![matrix, weights],
+        ),
+        SoundnessCase {
+            op: Box::new(HybridOp::Div {
+                denom: F32(8.0),
+                use_range_check_for_int: true,
+                use_shift_for_pow2: true,
+            }),
+            inputs: vec![x.clone()],
+            lookups: vec![],
+        },
+    ];
+    cases.extend(golden_ops().into_iter().map(|op| SoundnessCase {
+        op: Box::new(op.clone()),
+        inputs: vec![x.clone()],
+        lookups: vec![op],
+    }));
+    cases
+}
+
+/// An [OpCircuit], optionally with one output cell tampered with.
+#[derive(Clone, Debug)]
+struct SoundnessCircuit {
+    circuit: OpCircuit,
+    /// the flat index of the output cell to tamper with
+    tamper: Option<usize>,
+    /// set once the cell to tamper with was found and overwritten
+    tampered: Arc<AtomicBool>,
+}
+
+impl Circuit<Fp> for SoundnessCircuit {
+    type Config = BaseConfig<Fp>;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = OpCircuitParams;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn params(&self) -> Self::Params {
+        self.circuit.params()
+    }
+
+    fn configure_with_params(cs: &mut ConstraintSystem<Fp>, params: Self::Params) -> Self::Config {
+        OpCircuit::configure_with_params(cs, params)
+    }
+
+    fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {
+        unimplemented!("you should call configure_with_params instead")
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), PlonkError> {
+        self.circuit
+            .layout(config, &mut layouter, |region, output| {
+                let Some(idx) = self.tamper else {
+                    return Ok(());
+                };
+                let cell = output
+                    .and_then(|o| o.get_inner_tensor().ok().cloned())
+                    .map(|o| o.get_flat_index(idx));
+                if let Some(ValType::PrevAssigned(cell)) = cell {
+                    region.overwrite(&cell, cell.value().copied() + Value::known(Fp::ONE))?;
+                    self.tampered.store(true, Ordering::Relaxed);
+                }
+                Ok(())
+            })
+    }
+}
+
+/// Lays out `case`, checks its honest witness satisfies the circuit, and then tampers with each
+/// cell of its output in turn, recording the tampered witnesses the mock prover accepts.
+pub fn check_soundness(case: &SoundnessCase) -> Result<SoundnessReport, Box<dyn Error>> {
+    let name = case.op.as_string();
+    let felts = case
+        .inputs
+        .iter()
+        .map(|t| t.map(i128_to_felt::<Fp>))
+        .collect::<Vec<_>>();
+    let output_len = case.op.f(&felts)?.output.len();
+    if case.inputs.iter().any(|t| t.len() > SOUNDNESS_MAX_LEN) || output_len > SOUNDNESS_MAX_LEN {
+        return Err(format!(
+            "the inputs and output of {} can be at most {} long",
+            name, SOUNDNESS_MAX_LEN
+        )
+        .into());
+    }
+
+    let circuit = |tamper| SoundnessCircuit {
+        circuit: OpCircuit {
+            op: case.op.clone(),
+            inputs: felts.iter().map(|t| t.map(Value::known).into()).collect(),
+            params: OpCircuitParams {
+                logrows: SOUNDNESS_LOGROWS,
+                max_len: SOUNDNESS_MAX_LEN,
+                lookup_range: GOLDEN_LOOKUP_RANGE,
+                lookups: case.lookups.clone(),
+            },
+        },
+        tamper,
+        tampered: Arc::new(AtomicBool::new(false)),
+    };
+
+    MockProver::run(SOUNDNESS_LOGROWS, &circuit(None), vec![])?
+        .verify()
+        .map_err(|e| format!("the honest witness of {} is rejected: {:?}", name, e))?;
+
+    let mut report = SoundnessReport {
+        op: name,
+        tampered_cells: 0,
+        accepted: vec![],
+    };
+    for idx in 0..output_len {
+        let circuit = circuit(Some(idx));
+        let prover = MockProver::run(SOUNDNESS_LOGROWS, &circuit, vec![])?;
+        // constants and cells the op passes through from its inputs can't be tampered with alone
+        if !circuit.tampered.load(Ordering::Relaxed) {
+            continue;
+        }
+        report.tampered_cells += 1;
+        if prover.verify().is_ok() {
+            report.accepted.push(idx);
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tampered_outputs_are_rejected() {
+        for case in soundness_cases().iter() {
+            let report = check_soundness(case).unwrap();
+            assert!(report.tampered_cells > 0, "{:?}", report);
+            assert!(report.is_sound(), "{:?}", report);
+        }
+    }
+}
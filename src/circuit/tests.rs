@@ -354,6 +354,7 @@ mod matmul_col_ultra_overflow_double_col {
             crate::pfsys::TranscriptType::EVM,
             None,
             None,
+            None,
         );
 
         assert!(prover.is_ok());
@@ -471,6 +472,7 @@ mod matmul_col_ultra_overflow {
             crate::pfsys::TranscriptType::EVM,
             None,
             None,
+            None,
         );
 
         assert!(prover.is_ok());
@@ -771,6 +773,78 @@ mod sum {
     }
 }
 
+#[cfg(test)]
+mod valid_count {
+    use super::*;
+
+    const K: usize = 8;
+    const LEN: usize = 4;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: PrimeField + TensorType + PartialOrd> {
+        inputs: [ValTensor<F>; 1],
+        _marker: PhantomData<F>,
+    }
+
+    impl Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, 1, LEN);
+            let b = VarTensor::new_advice(cs, K, 1, LEN);
+            let output = VarTensor::new_advice(cs, K, 1, LEN);
+
+            Self::Config::configure(cs, &[a, b], &output, CheckMode::SAFE)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter
+                .assign_region(
+                    || "",
+                    |region| {
+                        let mut region = RegionCtx::new(region, 0, 1);
+                        layouts::valid_count(&config, &mut region, &self.inputs)
+                            .map_err(|_| Error::Synthesis)
+                    },
+                )
+                .unwrap();
+            Ok(())
+        }
+    }
+
+    fn circuit(values: [u64; LEN]) -> MyCircuit<F> {
+        let a = Tensor::from(values.iter().map(|v| Value::known(F::from(*v))));
+        MyCircuit::<F> {
+            inputs: [ValTensor::from(a)],
+            _marker: PhantomData,
+        }
+    }
+
+    #[test]
+    fn validcountcircuit() {
+        let prover = MockProver::run(K as u32, &circuit([1, 1, 0, 0]), vec![]).unwrap();
+        prover.assert_satisfied();
+
+        // a valid slot after the padding is rejected
+        let prover = MockProver::run(K as u32, &circuit([1, 0, 1, 0]), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // values that aren't a validity mask are rejected
+        let prover = MockProver::run(K as u32, &circuit([3, 5, 0, 0]), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
 #[cfg(test)]
 mod sum_col_overflow_double_col {
     use super::*;
@@ -1275,6 +1349,7 @@ mod conv_col_ultra_overflow {
             crate::pfsys::TranscriptType::EVM,
             None,
             None,
+            None,
         );
 
         assert!(prover.is_ok());
@@ -1428,6 +1503,7 @@ mod conv_relu_col_ultra_overflow {
             // use safe mode to verify that the proof is correct
             None,
             None,
+            None,
         );
 
         assert!(prover.is_ok());
@@ -1842,6 +1918,102 @@ mod shuffle {
     }
 }
 
+#[cfg(test)]
+mod rearrange {
+    use super::*;
+    use crate::tensor::ValType;
+
+    const K: usize = 6;
+    const LEN: usize = 6;
+
+    #[derive(Clone)]
+    struct MyCircuit<F: PrimeField + TensorType + PartialOrd> {
+        input: [ValTensor<F>; 1],
+        indices: Tensor<usize>,
+        // overwrites the first output cell with a wrong value
+        tamper: bool,
+        _marker: PhantomData<F>,
+    }
+
+    impl Circuit<F> for MyCircuit<F> {
+        type Config = BaseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = TestParams;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(cs: &mut ConstraintSystem<F>) -> Self::Config {
+            let a = VarTensor::new_advice(cs, K, 2, LEN);
+            let b = VarTensor::new_advice(cs, K, 2, LEN);
+            let c: VarTensor = VarTensor::new_advice(cs, K, 2, LEN);
+
+            let d = VarTensor::new_advice(cs, K, 1, LEN);
+            let e = VarTensor::new_advice(cs, K, 1, LEN);
+
+            let _constant = VarTensor::constant_cols(cs, K, 2 * LEN, false);
+
+            let mut config =
+                Self::Config::configure(cs, &[a.clone(), b.clone()], &c, CheckMode::SAFE);
+            config
+                .configure_shuffles(cs, &[a.clone(), b.clone()], &[d.clone(), e.clone()])
+                .unwrap();
+            config
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "",
+                |region| {
+                    let mut region = RegionCtx::new(region, 0, 1);
+                    let output =
+                        layouts::rearrange(&config, &mut region, &self.input, &self.indices)
+                            .map_err(|_| Error::Synthesis)?;
+                    if self.tamper {
+                        let cell = output
+                            .get_inner_tensor()
+                            .map_err(|_| Error::Synthesis)?
+                            .get_flat_index(0);
+                        if let ValType::PrevAssigned(cell) = cell {
+                            region
+                                .overwrite(&cell, cell.value().copied() + Value::known(F::ONE))?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn rearrangecircuit() {
+        let input = ValTensor::from(Tensor::from(
+            (0..LEN).map(|i| Value::known(F::from(i as u64 * 7 + 1))),
+        ));
+        // indices can repeat and skip positions
+        let indices = Tensor::new(Some(&[2, 0, 5, 5, 3]), &[5]).unwrap();
+
+        let circuit = |tamper| MyCircuit::<F> {
+            input: [input.clone()],
+            indices: indices.clone(),
+            tamper,
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(K as u32, &circuit(false), vec![]).unwrap();
+        prover.assert_satisfied();
+
+        // an output element differing from the input element at the position it reads is rejected
+        let prover = MockProver::run(K as u32, &circuit(true), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
+
 #[cfg(test)]
 mod add_with_overflow {
     use super::*;
@@ -2341,6 +2513,7 @@ mod matmul_relu {
 #[cfg(test)]
 mod relu {
     use super::*;
+    use crate::circuit::harness::{OpCircuit, OpCircuitParams};
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner, Value},
         dev::MockProver,
@@ -2410,6 +2583,26 @@ mod relu {
         let prover = MockProver::run(4_u32, &circuit, vec![]).unwrap();
         prover.assert_satisfied();
     }
+
+    #[test]
+    fn relucircuit_harness() {
+        let input: Tensor<Value<F>> =
+            Tensor::new(Some(&[Value::<F>::known(F::from(1_u64)); 4]), &[4]).unwrap();
+
+        let circuit = OpCircuit {
+            op: Box::new(LookupOp::ReLU),
+            inputs: vec![ValTensor::from(input)],
+            params: OpCircuitParams {
+                logrows: 4,
+                max_len: 3,
+                lookup_range: (-6, 6),
+                lookups: vec![LookupOp::ReLU],
+            },
+        };
+
+        let prover = MockProver::run(4_u32, &circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+    }
 }
 
 #[cfg(test)]
@@ -2528,6 +2721,7 @@ mod lookup_ultra_overflow {
             crate::pfsys::TranscriptType::EVM,
             None,
             None,
+            None,
         );
 
         assert!(prover.is_ok());
@@ -1,3 +1,4 @@
+use crate::eth::TargetChain;
 use clap::{Parser, Subcommand};
 #[cfg(not(target_arch = "wasm32"))]
 use ethers::types::H160;
@@ -13,12 +14,16 @@ use std::path::PathBuf;
 use std::{error::Error, str::FromStr};
 use tosubcommand::{ToFlags, ToSubcommand};
 
-use crate::{pfsys::ProofType, Commitments, RunArgs};
+use crate::{pfsys::ProofType, Accelerator, Commitments, RunArgs};
 
 use crate::circuit::CheckMode;
+use crate::graph::model::ReportSort;
 #[cfg(not(target_arch = "wasm32"))]
+use crate::graph::templates::TemplateTask;
 use crate::graph::TestDataSource;
+use crate::output::OutputFormat;
 use crate::pfsys::TranscriptType;
+use crate::wasm_contracts::ContractTarget;
 
 /// The default path to the .json data file
 pub const DEFAULT_DATA: &str = "input.json";
@@ -44,6 +49,10 @@ pub const DEFAULT_PROOF: &str = "proof.json";
 pub const DEFAULT_PROOF_AGGREGATED: &str = "proof_aggr.json";
 /// Default for whether to split proofs
 pub const DEFAULT_SPLIT: &str = "false";
+/// Default for whether to aggregate into a universal verifier
+pub const DEFAULT_UNIVERSAL: &str = "false";
+/// Default for whether the aggregate verifier forwards the instances of the proofs it aggregates
+pub const DEFAULT_FORWARD_INSTANCES: &str = "false";
 /// Default verifier abi
 pub const DEFAULT_VERIFIER_ABI: &str = "verifier_abi.json";
 /// Default verifier abi for aggregated proofs
@@ -56,20 +65,64 @@ pub const DEFAULT_SOL_CODE: &str = "evm_deploy.sol";
 pub const DEFAULT_SOL_CODE_AGGREGATED: &str = "evm_deploy_aggr.sol";
 /// Default solidity code for data attestation
 pub const DEFAULT_SOL_CODE_DA: &str = "evm_deploy_da.sol";
+/// Default solidity code for the quantization library
+pub const DEFAULT_SOL_CODE_QUANTIZER: &str = "EzklQuantizer.sol";
+/// Default scale of the quantization library
+pub const DEFAULT_QUANTIZER_SCALE: &str = "7";
+/// Default bits of the values the quantization library produces
+pub const DEFAULT_QUANTIZER_BITS: &str = "128";
 /// Default contract address
 pub const DEFAULT_CONTRACT_ADDRESS: &str = "contract.address";
 /// Default contract address for data attestation
 pub const DEFAULT_CONTRACT_ADDRESS_DA: &str = "contract_da.address";
 /// Default contract address for vk
 pub const DEFAULT_CONTRACT_ADDRESS_VK: &str = "contract_vk.address";
+/// Default contract address for the verifier registry
+pub const DEFAULT_CONTRACT_ADDRESS_REGISTRY: &str = "contract_registry.address";
+/// Default solidity code for the verifier registry
+pub const DEFAULT_SOL_CODE_REGISTRY: &str = "evm_deploy_registry.sol";
+/// Default verifier abi for the verifier registry
+pub const DEFAULT_VERIFIER_REGISTRY_ABI: &str = "verifier_registry_abi.json";
+/// Default contract address for the paymaster
+pub const DEFAULT_CONTRACT_ADDRESS_PAYMASTER: &str = "contract_paymaster.address";
+/// Default solidity code for the paymaster
+pub const DEFAULT_SOL_CODE_PAYMASTER: &str = "evm_deploy_paymaster.sol";
+/// Default paymaster abi
+pub const DEFAULT_PAYMASTER_ABI: &str = "paymaster_abi.json";
+/// Default gas the paymaster may spend verifying a proof
+pub const DEFAULT_PAYMASTER_VERIFY_GAS_LIMIT: &str = "1500000";
+/// Default ERC-4337 entry point, the canonical v0.7 deployment
+pub const DEFAULT_ENTRY_POINT: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+/// Default contract address for the blob verifier
+pub const DEFAULT_CONTRACT_ADDRESS_BLOB: &str = "contract_blob.address";
+/// Default solidity code for the blob verifier
+pub const DEFAULT_SOL_CODE_BLOB: &str = "evm_deploy_blob.sol";
+/// Default blob verifier abi
+pub const DEFAULT_VERIFIER_BLOB_ABI: &str = "verifier_blob_abi.json";
+/// Default path to the EIP-4844 KZG trusted setup
+pub const DEFAULT_TRUSTED_SETUP: &str = "trusted_setup.txt";
+/// Default output directory for the npm verifier package
+pub const DEFAULT_NPM_PACKAGE_DIR: &str = "verifier-npm";
+/// Default name of the npm verifier package
+pub const DEFAULT_NPM_PACKAGE_NAME: &str = "ezkl-model-verifier";
+/// Default output directory for the verifier contract of non-EVM chains
+pub const DEFAULT_WASM_CONTRACT_DIR: &str = "verifier-contract";
+/// Default name of the verifier contract crate
+pub const DEFAULT_WASM_CONTRACT_NAME: &str = "ezkl-verifier";
+/// Default smart contract platform of the verifier contract
+pub const DEFAULT_CONTRACT_TARGET: &str = "cosmwasm";
 /// Default check mode
 pub const DEFAULT_CHECKMODE: &str = "safe";
 /// Default calibration target
 pub const DEFAULT_CALIBRATION_TARGET: &str = "resources";
 /// Default logrows for aggregated proofs
 pub const DEFAULT_AGGREGATED_LOGROWS: &str = "23";
+/// Default number of proofs aggregated by each node of an aggregation tree
+pub const DEFAULT_AGGREGATION_FAN_IN: &str = "4";
 /// Default optimizer runs
 pub const DEFAULT_OPTIMIZER_RUNS: &str = "1";
+/// Default name of the verifier contract
+pub const DEFAULT_VERIFIER_CONTRACT_NAME: &str = "Halo2Verifier";
 /// Default fuzz runs
 pub const DEFAULT_FUZZ_RUNS: &str = "10";
 /// Default calibration file
@@ -80,6 +133,8 @@ pub const DEFAULT_LOOKUP_SAFETY_MARGIN: &str = "2";
 pub const DEFAULT_DISABLE_SELECTOR_COMPRESSION: &str = "false";
 /// Default render vk seperately
 pub const DEFAULT_RENDER_VK_SEPERATELY: &str = "false";
+/// Default chain verifiers are generated for
+pub const DEFAULT_TARGET_CHAIN: &str = "ethereum";
 /// Default VK sol path
 pub const DEFAULT_VK_SOL: &str = "vk.sol";
 /// Default VK abi path
@@ -90,8 +145,72 @@ pub const DEFAULT_SCALE_REBASE_MULTIPLIERS: &str = "1,2,10";
 pub const DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION: &str = "false";
 /// Default only check for range check rebase
 pub const DEFAULT_ONLY_RANGE_CHECK_REBASE: &str = "false";
+/// Default bias correction
+pub const DEFAULT_BIAS_CORRECTION: &str = "false";
+/// Default max number of lookup domains
+pub const DEFAULT_MAX_LOOKUP_DOMAINS: &str = "1";
+/// Default golden records directory
+pub const DEFAULT_GOLDEN_DIR: &str = "tests/golden";
+/// Default golden check
+pub const DEFAULT_GOLDEN_CHECK: &str = "false";
+/// Default path of the fixed point reference vectors
+pub const DEFAULT_FIELD_VECTORS: &str = "tests/fieldutils_vectors.json";
+/// Default environment lockfile
+pub const DEFAULT_ENV_LOCK: &str = "ezkl-env.lock";
+/// Default directory of the proving service's jobs
+pub const DEFAULT_JOBS_DIR: &str = "jobs";
+/// Default address of the proving service
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:8080";
+/// Default number of the proving service's workers
+pub const DEFAULT_SERVE_WORKERS: &str = "1";
+/// Default number of jobs waiting for the proving service's workers
+pub const DEFAULT_SERVE_MAX_QUEUE: &str = "64";
+/// Default number of iterations of a benchmark
+pub const DEFAULT_BENCH_ITERATIONS: &str = "5";
+/// Default directory of the artifacts of a benchmark
+pub const DEFAULT_BENCH_DIR: &str = "bench";
+/// Default order of the nodes of a model report
+pub const DEFAULT_INSPECT_SORT: &str = "idx";
+/// Default config of a pipeline
+pub const DEFAULT_PIPELINE: &str = "pipeline.toml";
+/// Default of rerunning every stage of a pipeline
+pub const DEFAULT_PIPELINE_FORCE: &str = "false";
+/// Default range inputs are sampled from when fuzzing
+pub const DEFAULT_FUZZ_RANGE: &str = "-1->1";
+/// Default number of inputs to fuzz
+pub const DEFAULT_FUZZ_ITERATIONS: &str = "100";
+/// Default directory the failing inputs of fuzzing are saved to
+pub const DEFAULT_FUZZ_DIR: &str = "fuzz";
+/// Default format of the results of commands
+pub const DEFAULT_OUTPUT_FORMAT: &str = "human";
+/// Default journal of commands to replay
+pub const DEFAULT_JOURNAL: &str = "journal.json";
+/// Default directory for batches of proofs
+pub const DEFAULT_PROOF_DIR: &str = "proofs";
+/// Default scrubbing of the witness after proving
+pub const DEFAULT_SCRUB: &str = "false";
+/// Default memory trimming while proving
+pub const DEFAULT_TRIM_MEMORY: &str = "false";
+/// Default force loading keys from stale settings
+pub const DEFAULT_FORCE: &str = "false";
+/// Default resuming from a checkpoint
+pub const DEFAULT_RESUME: &str = "false";
+/// Default direction of compress-proof
+pub const DEFAULT_DECOMPRESS: &str = "false";
+/// Default size of the chunks artifacts are transferred in, 64 MiB
+pub const DEFAULT_CHUNK_SIZE: &str = "67108864";
 /// Default commitment
 pub const DEFAULT_COMMITMENT: &str = "kzg";
+/// Default for whether to write a timing report next to the proof
+pub const DEFAULT_PROFILE: &str = "false";
+/// Default for whether to generate an SRS from publicly known toxic waste
+pub const DEFAULT_UNSAFE_SRS: &str = "false";
+/// Default number of input features of a template model
+pub const DEFAULT_TEMPLATE_FEATURES: &str = "4";
+/// Default number of hidden units of an mlp template model
+pub const DEFAULT_TEMPLATE_HIDDEN_SIZE: &str = "8";
+/// Default seed of the placeholder weights and example data of a template model
+pub const DEFAULT_TEMPLATE_SEED: &str = "0";
 
 #[cfg(feature = "python-bindings")]
 /// Converts TranscriptType into a PyObject (Required for TranscriptType to be compatible with Python)
@@ -100,6 +219,7 @@ impl IntoPy<PyObject> for TranscriptType {
         match self {
             TranscriptType::Poseidon => "poseidon".to_object(py),
             TranscriptType::EVM => "evm".to_object(py),
+            TranscriptType::Blake2b => "blake2b".to_object(py),
         }
     }
 }
@@ -111,7 +231,8 @@ impl<'source> FromPyObject<'source> for TranscriptType {
         let strval = trystr.to_string();
         match strval.to_lowercase().as_str() {
             "poseidon" => Ok(TranscriptType::Poseidon),
-            "evm" => Ok(TranscriptType::EVM),
+            "evm" | "keccak" => Ok(TranscriptType::EVM),
+            "blake2b" => Ok(TranscriptType::Blake2b),
             _ => Err(PyValueError::new_err("Invalid value for TranscriptType")),
         }
     }
@@ -178,6 +299,62 @@ impl From<&str> for CalibrationTarget {
     }
 }
 
+/// The shells `ezkl completions` generates completions for
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(
+    clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize, PartialOrd,
+)]
+pub enum CompletionShell {
+    /// Bourne Again SHell
+    Bash,
+    /// Elvish shell
+    Elvish,
+    /// Friendly Interactive SHell
+    Fish,
+    /// PowerShell
+    #[value(name = "powershell")]
+    PowerShell,
+    /// Z SHell
+    Zsh,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::fmt::Display for CompletionShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CompletionShell::Bash => "bash",
+                CompletionShell::Elvish => "elvish",
+                CompletionShell::Fish => "fish",
+                CompletionShell::PowerShell => "powershell",
+                CompletionShell::Zsh => "zsh",
+            }
+        )
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToFlags for CompletionShell {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<CompletionShell> for clap_complete::Shell {
+    fn from(shell: CompletionShell) -> Self {
+        match shell {
+            CompletionShell::Bash => clap_complete::Shell::Bash,
+            CompletionShell::Elvish => clap_complete::Shell::Elvish,
+            CompletionShell::Fish => clap_complete::Shell::Fish,
+            CompletionShell::PowerShell => clap_complete::Shell::PowerShell,
+            CompletionShell::Zsh => clap_complete::Shell::Zsh,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
 /// wrapper for H160 to make it easy to parse into flag vals
@@ -208,6 +385,50 @@ impl From<&str> for H160Flag {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
+/// An ERC-4337 user operation, parsed from `<sender>:<nonce>:<call data>` with the nonce in decimal
+/// and the call data in hex
+pub struct UserOpFlag {
+    /// The account sending the operation
+    pub sender: H160,
+    /// The nonce of the operation, including its key
+    pub nonce: ethers::types::U256,
+    /// The call the entry point makes to the account
+    pub call_data: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ToFlags for UserOpFlag {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!(
+            "{:#x}:{}:0x{}",
+            self.sender,
+            self.nonce,
+            hex::encode(&self.call_data)
+        )]
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FromStr for UserOpFlag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let [sender, nonce, call_data] = parts[..] else {
+            return Err("expected <sender>:<nonce>:<call data>".to_string());
+        };
+        Ok(Self {
+            sender: H160::from_str(sender).map_err(|e| format!("invalid sender: {}", e))?,
+            nonce: ethers::types::U256::from_dec_str(nonce)
+                .map_err(|e| format!("invalid nonce: {}", e))?,
+            call_data: hex::decode(call_data.trim_start_matches("0x"))
+                .map_err(|e| format!("invalid call data: {}", e))?,
+        })
+    }
+}
+
 #[cfg(feature = "python-bindings")]
 /// Converts CalibrationTarget into a PyObject (Required for CalibrationTarget to be compatible with Python)
 impl IntoPy<PyObject> for CalibrationTarget {
@@ -261,6 +482,10 @@ pub struct Cli {
     #[command(subcommand)]
     #[allow(missing_docs)]
     pub command: Commands,
+    /// How the results of the command are printed: human readable logs, or a json object with its status, timing, artifacts and result, with the logs on stderr
+    #[arg(long, global = true, default_value = DEFAULT_OUTPUT_FORMAT, value_enum)]
+    #[serde(default)]
+    pub format: OutputFormat,
 }
 
 impl Cli {
@@ -296,6 +521,22 @@ pub enum Commands {
         args: RunArgs,
     },
 
+    /// Loads a model and prints the estimated cost of each node: its op, output shapes and scales, the rows, advice cells and constants it takes up, and the lookups and range checks it needs, as a table, or as json with `--format json`
+    Inspect {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL)]
+        model: PathBuf,
+        /// proving arguments
+        #[clap(flatten)]
+        args: RunArgs,
+        /// What to sort the nodes by, the costliest first: idx, rows, assignments, constants or lookups
+        #[arg(long, default_value = DEFAULT_INSPECT_SORT, value_enum)]
+        sort_by: ReportSort,
+        /// Only print the first this many nodes
+        #[arg(long)]
+        top: Option<usize>,
+    },
+
     /// Generates the witness from an input file.
     GenWitness {
         /// The path to the .json data file
@@ -313,6 +554,21 @@ pub enum Commands {
         /// Path to the srs file (optional - solely used to generate kzg commits)
         #[arg(short = 'P', long)]
         srs_path: Option<PathBuf>,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+        /// Path to a hex encoded (compressed G1) public key, e.g of a committee or timelock service, to seal the outputs to for a delayed reveal. Required by circuits generated with --seal-outputs, and only accepted by them
+        #[arg(long)]
+        seal_outputs_to: Option<PathBuf>,
+        /// The address proofs are bound to, required if the circuit was generated with --bind-caller
+        #[arg(long, conflicts_with = "user_op")]
+        caller: Option<H160Flag>,
+        /// The ERC-4337 user operation proofs are bound to instead of a caller, as `<sender>:<nonce>:<call data>`, for the paymaster of create-evm-paymaster
+        #[arg(long)]
+        user_op: Option<UserOpFlag>,
+        /// Also write a self-contained assignment to this path, to be proven elsewhere with prove --from-assignment
+        #[arg(long)]
+        full_assignment: Option<PathBuf>,
     },
 
     /// Produces the proving hyperparameters, from run-args
@@ -328,6 +584,33 @@ pub enum Commands {
         args: RunArgs,
     },
 
+    /// Generates a small .onnx model for a common task, example input data and the circuit settings it is best proven with, so simple formulas can be proven without exporting a model from pytorch. The weights are seeded placeholders, to be replaced by the trained values
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "template", arg_required_else_help = true)]
+    Template {
+        /// The task the model performs: linear-regression, logistic, mlp or anomaly-score
+        #[arg(long)]
+        task: TemplateTask,
+        /// The number of input features
+        #[arg(long, default_value = DEFAULT_TEMPLATE_FEATURES)]
+        num_features: usize,
+        /// The number of hidden units, for the mlp task
+        #[arg(long, default_value = DEFAULT_TEMPLATE_HIDDEN_SIZE)]
+        hidden_size: usize,
+        /// The seed the placeholder weights and example data are drawn from
+        #[arg(long, default_value = DEFAULT_TEMPLATE_SEED)]
+        seed: u64,
+        /// The path to write the .onnx model file to
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL)]
+        model: PathBuf,
+        /// The path to write the example input data .json file to
+        #[arg(short = 'D', long, default_value = DEFAULT_DATA)]
+        data: PathBuf,
+        /// The path to write the circuit settings .json file to
+        #[arg(short = 'O', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+    },
+
     /// Calibrates the proving scale, lookup bits and logrows from a circuit settings file.
     #[cfg(not(target_arch = "wasm32"))]
     CalibrateSettings {
@@ -363,24 +646,289 @@ pub enum Commands {
         // whether to only range check rebases (instead of trying both range check and lookup)
         #[arg(long, default_value = DEFAULT_ONLY_RANGE_CHECK_REBASE)]
         only_range_check_rebase: bool,
+        /// Path to a .json spec mapping the columns of a .csv or .parquet calibration dataset (passed as --data) to the model inputs, along with their normalization
+        #[arg(long)]
+        dataset_spec: Option<PathBuf>,
+        /// Whether to correct the quantized biases of conv and dense layers with the mean error of their outputs over the calibration data
+        #[arg(long, default_value = DEFAULT_BIAS_CORRECTION)]
+        bias_correction: bool,
+        /// The max number of domains to give lookup tables, fitted to the inputs of their ops over the calibration data. 1 keeps every table over the same lookup range, more can shrink the tables of ops with narrow inputs
+        #[arg(long, default_value = DEFAULT_MAX_LOOKUP_DOMAINS)]
+        max_lookup_domains: usize,
+    },
+
+    /// Upgrades a circuit settings file produced by an older release to the current schema.
+    #[cfg(not(target_arch = "wasm32"))]
+    Migrate {
+        /// The path to the circuit settings .json file to migrate
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to write the migrated settings to, overwrites the input file if unset
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Reports the format versions of a proof and its circuit settings and the range of releases that can verify them, failing when this binary can't.
+    #[cfg(not(target_arch = "wasm32"))]
+    CompatCheck {
+        /// The path to the proof file (generated using the prove command)
+        #[arg(long, visible_alias = "proof", default_value = DEFAULT_PROOF)]
+        proof_path: PathBuf,
+        /// The path to the circuit settings .json file the proof was generated with
+        #[arg(short = 'S', long, visible_alias = "settings", default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+    },
+
+    /// Builds a tiny circuit per lookup op and writes (or checks) golden records of its outputs and verifying key hash, to catch changes in op semantics across releases.
+    #[cfg(not(target_arch = "wasm32"))]
+    GenGolden {
+        /// The directory holding one .json golden record per op
+        #[arg(short = 'O', long, default_value = DEFAULT_GOLDEN_DIR)]
+        output_dir: PathBuf,
+        /// Check the records in the directory instead of overwriting them, fails on any difference
+        #[arg(long, default_value = DEFAULT_GOLDEN_CHECK)]
+        check: bool,
+    },
+
+    /// Lays out the arithmetic and lookup ops in a tiny circuit each and tampers with every cell of their output in turn, failing if the mock prover accepts a tampered witness, to catch under-constrained ops. Convolutions, gathers and the hybrid comparisons aren't checked.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "self-test")]
+    SelfTest {
+        /// Only check the ops whose name starts with this, e.g. `RELU`
+        #[arg(long)]
+        op: Option<String>,
+    },
+
+    /// Prints the completions of ezkl for a shell, e.g `ezkl completions bash > /etc/bash_completion.d/ezkl`
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "completions", arg_required_else_help = true)]
+    Completions {
+        /// The shell to generate completions for
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+
+    /// Runs a model from its .onnx file to a verified proof from a single .toml or .json pipeline config: generates settings, calibrates them if configured, compiles the circuit, runs setup, generates the witness, proves and verifies. Artifacts are written to the work directory of the pipeline, and stages whose arguments and input files haven't changed since they last ran are skipped.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "run")]
+    Run {
+        /// The path to the pipeline config, a .toml file or a .json file
+        #[arg(short = 'C', long, default_value = DEFAULT_PIPELINE)]
+        config: PathBuf,
+        /// Whether to rerun every stage, even those whose artifacts are up to date
+        #[arg(long, default_value = DEFAULT_PIPELINE_FORCE)]
+        force: bool,
+    },
+
+    /// Benchmarks a model end to end: compiles its circuit, then runs setup, witness generation, proving and verification `--iterations` times, reporting the mean and standard deviation of the time each stage took, its peak memory, the proof size and the size of the circuit.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "bench")]
+    Bench {
+        /// The path to the .onnx model file
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL)]
+        model: PathBuf,
+        /// The path to the circuit settings .json file (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to the .json data file to generate witnesses from
+        #[arg(short = 'D', long, default_value = DEFAULT_DATA)]
+        data: PathBuf,
+        /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// The number of times each stage is run
+        #[arg(long, default_value = DEFAULT_BENCH_ITERATIONS)]
+        iterations: usize,
+        /// The directory the compiled circuit, keys, witness and proof are written to
+        #[arg(long, default_value = DEFAULT_BENCH_DIR)]
+        work_dir: PathBuf,
+        /// The path to also write the .json report to
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Runs a proving service for a model, loading its compiled circuit, proving key and SRS once and serving witness generation, proving and verification over HTTP. Witness generation and proving are queued as jobs, run by `--workers` workers, whose status and artifacts are fetched from `/jobs/{id}`. The OpenAPI document of the service is served at `/openapi.json`.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "serve")]
+    Serve {
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT)]
+        compiled_circuit: PathBuf,
+        /// The path to the proving key file (generated using the setup command)
+        #[arg(long, default_value = DEFAULT_PK)]
+        pk_path: PathBuf,
+        /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+        /// The address to listen on
+        #[arg(long, default_value = DEFAULT_SERVE_ADDR)]
+        addr: std::net::SocketAddr,
+        /// The directory holding one directory per job
+        #[arg(long, default_value = DEFAULT_JOBS_DIR)]
+        jobs_dir: PathBuf,
+        /// The number of jobs run at once
+        #[arg(long, default_value = DEFAULT_SERVE_WORKERS)]
+        workers: usize,
+        /// The number of jobs that can wait for a worker, requests beyond it get a 503
+        #[arg(long, default_value = DEFAULT_SERVE_MAX_QUEUE)]
+        max_queue: usize,
+        /// Run sanity checks during proving (safe or unsafe)
+        #[arg(long, default_value = DEFAULT_CHECKMODE)]
+        check_mode: CheckMode,
+        /// The name of the model, recorded with each job so its jobs can be purged. Defaults to the file stem of the compiled circuit
+        #[arg(long)]
+        model: Option<String>,
+        /// Remove finished jobs older than this many seconds
+        #[arg(long)]
+        max_age: Option<u64>,
+        /// Remove the oldest finished jobs while the jobs directory is larger than this many bytes
+        #[arg(long)]
+        max_bytes: Option<u64>,
+        /// A file holding the bearer token of the admin endpoints. The token is otherwise read from $EZKL_ADMIN_TOKEN, and the admin endpoints are disabled without either
+        #[arg(long)]
+        admin_token_file: Option<PathBuf>,
+    },
+
+    /// Removes the artifacts of the proving service's finished jobs that are older than `--max-age` or, oldest first, don't fit in `--max-bytes`. With `--model` or `--tenant`, instead removes every finished job of that model or tenant.
+    #[cfg(not(target_arch = "wasm32"))]
+    GcJobs {
+        /// The directory holding one directory per job
+        #[arg(long, default_value = DEFAULT_JOBS_DIR)]
+        jobs_dir: PathBuf,
+        /// Remove finished jobs older than this many seconds
+        #[arg(long)]
+        max_age: Option<u64>,
+        /// Remove the oldest finished jobs while the jobs directory is larger than this many bytes
+        #[arg(long)]
+        max_bytes: Option<u64>,
+        /// Purge the finished jobs of this model
+        #[arg(long)]
+        model: Option<String>,
+        /// Purge the finished jobs of this tenant
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+
+    /// Writes reference vectors of the fixed point conversions of `ezkl::fieldutils` (quantizing floats and converting them to field elements and back), to check implementations in other languages against.
+    #[cfg(not(target_arch = "wasm32"))]
+    GenFieldVectors {
+        /// The path to write the .json reference vectors to
+        #[arg(short = 'O', long, default_value = DEFAULT_FIELD_VECTORS)]
+        output: PathBuf,
+    },
+
+    /// Writes a lockfile of everything affecting proof bytes: the ezkl version, hashes of the settings and SRS, the features ezkl was built with and the host profile
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "export-env")]
+    ExportEnv {
+        /// The path to the circuit settings .json file to lock, skipped if it doesn't exist
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to the SRS file to lock, defaults to the cached SRS for the settings' logrows
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// The path to write the lockfile to
+        #[arg(short = 'O', long, default_value = DEFAULT_ENV_LOCK)]
+        output: PathBuf,
+    },
+
+    /// Checks the current environment against a lockfile written by export-env, failing if keys and proofs produced here wouldn't be interoperable with the locked environment
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "check-env")]
+    CheckEnv {
+        /// The path to the lockfile
+        #[arg(short = 'L', long, default_value = DEFAULT_ENV_LOCK)]
+        lock: PathBuf,
+        /// The path to the circuit settings .json file to check
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to the SRS file to check, defaults to the cached SRS for the settings' logrows
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+    },
+
+    /// Decrypts the outputs of a witness sealed with `gen-witness --seal-outputs-to`. With hashed outputs the result can be checked against the hash committed to in the proof
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "unseal-outputs", arg_required_else_help = true)]
+    UnsealOutputs {
+        /// The path to the witness .json file with sealed outputs
+        #[arg(short = 'W', long, default_value = DEFAULT_WITNESS)]
+        witness: PathBuf,
+        /// Path to the hex encoded secret key matching the public key the outputs were sealed to
+        #[arg(long)]
+        secret_key: PathBuf,
+        /// Path to write the unsealed outputs .json file to
+        #[arg(short = 'O', long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Compares two witnesses element-wise and reports where they first diverge.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "witness-diff", arg_required_else_help = true)]
+    WitnessDiff {
+        /// The path to the first witness .json file
+        #[arg(long)]
+        left: PathBuf,
+        /// The path to the second witness .json file
+        #[arg(long)]
+        right: PathBuf,
+        /// The path to the compiled model file, used to map divergences back to graph nodes (optional)
+        #[arg(short = 'M', long)]
+        compiled_circuit: Option<PathBuf>,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+    },
+
+    /// Compares two settings files field by field, or settings with the header of a key generated from them, and explains which fields differ and whether they require new keys.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "diff-settings", arg_required_else_help = true)]
+    DiffSettings {
+        /// The path to the first settings .json file
+        #[arg(long)]
+        left: PathBuf,
+        /// The path to the second settings .json file
+        #[arg(
+            long,
+            required_unless_present = "key_path",
+            conflicts_with = "key_path"
+        )]
+        right: Option<PathBuf>,
+        /// The path to a proving or verifying key, to compare the first settings with the settings it was generated from
+        #[arg(long)]
+        key_path: Option<PathBuf>,
+    },
+
+    /// Re-runs the commands recorded to a journal (set EZKL_JOURNAL to record one), checking each step finds the journaled inputs and reproduces the journaled outputs. Proofs are only reproducible when proven with --seed
+    #[cfg(not(target_arch = "wasm32"))]
+    Replay {
+        /// The path to the journal
+        #[arg(default_value = DEFAULT_JOURNAL)]
+        journal: PathBuf,
     },
 
     /// Generates a dummy SRS
     #[command(name = "gen-srs", arg_required_else_help = true)]
     GenSrs {
-        /// The path to output the generated SRS
+        /// The path to output the generated SRS, if None will save to $EZKL_REPO_PATH/srs, where get-srs caches downloaded SRS
         #[arg(long)]
-        srs_path: PathBuf,
+        srs_path: Option<PathBuf>,
         /// number of logrows to use for srs
         #[arg(long)]
         logrows: usize,
         /// commitment used
         #[arg(long, default_value = DEFAULT_COMMITMENT)]
         commitment: Commitments,
+        /// DEV ONLY: generate a KZG SRS from fixed, publicly known toxic waste, so it is the same on every run. Anyone can forge proofs against it
+        #[arg(long = "unsafe", default_value = DEFAULT_UNSAFE_SRS)]
+        unsafe_setup: bool,
     },
 
     #[cfg(not(target_arch = "wasm32"))]
-    /// Gets an SRS from a circuit settings file.
+    /// Gets the public SRS for a circuit settings file (or --logrows). Downloads are checked against the published hash and resume if interrupted, and a larger cached SRS is trimmed down instead when available.
     #[command(name = "get-srs")]
     GetSrs {
         /// The path to output the desired srs file, if set to None will save to $EZKL_REPO_PATH/srs
@@ -396,6 +944,51 @@ pub enum Commands {
         #[arg(long, default_value = None)]
         commitment: Option<Commitments>,
     },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Downloads an .onnx model over https, checking it against a pinned SHA256 hash. Downloads are cached in $EZKL_REPO_PATH/models
+    #[command(name = "get-model")]
+    GetModel {
+        /// The https url to download the model from
+        #[arg(long)]
+        url: String,
+        /// The expected SHA256 hash (hex) of the model file
+        #[arg(long)]
+        sha256: String,
+        /// The path to save the .onnx model file to
+        #[arg(short = 'M', long, default_value = DEFAULT_MODEL)]
+        model: PathBuf,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Downloads an artifact (e.g a proving key or SRS) over http in chunks, resuming an interrupted download and retrying failed chunks
+    #[command(name = "fetch-artifact")]
+    FetchArtifact {
+        /// The url to download the artifact from, the server should support range requests
+        #[arg(long)]
+        url: String,
+        /// The path to save the artifact to
+        #[arg(short = 'O', long)]
+        output: PathBuf,
+        /// The expected SHA256 hash (hex) of the artifact, it is only saved if it matches
+        #[arg(long)]
+        sha256: Option<String>,
+        /// The size of the chunks to download, in bytes
+        #[arg(long, default_value = DEFAULT_CHUNK_SIZE)]
+        chunk_size: u64,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Uploads an artifact (e.g a proving key or SRS) over http in chunks, to an endpoint accepting resumable uploads. An interrupted upload resumes where it stopped
+    #[command(name = "publish-artifact")]
+    PublishArtifact {
+        /// The path to the artifact to upload
+        #[arg(long)]
+        path: PathBuf,
+        /// The url to upload the artifact to
+        #[arg(long)]
+        url: String,
+        /// The size of the chunks to upload, in bytes
+        #[arg(long, default_value = DEFAULT_CHUNK_SIZE)]
+        chunk_size: u64,
+    },
     /// Loads model and input and runs mock prover (for testing)
     Mock {
         /// The path to the .json witness file (generated using the gen-witness command)
@@ -404,6 +997,33 @@ pub enum Commands {
         /// The path to the compiled model file (generated using the compile-circuit command)
         #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT)]
         model: PathBuf,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+    },
+
+    /// Samples random inputs from the given ranges and runs the forward pass and mock prover on each, reporting the inputs that fail range checks, overflow lookup tables or don't satisfy constraints. Failing inputs are saved as .json data files to `--output-dir`, along with the report.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[command(name = "fuzz")]
+    Fuzz {
+        /// The path to the compiled model file (generated using the compile-circuit command)
+        #[arg(short = 'M', long, default_value = DEFAULT_COMPILED_CIRCUIT)]
+        compiled_circuit: PathBuf,
+        /// The range each input is sampled from, e.g `-1->1`, or a comma separated range per input. Integer inputs are sampled from the integers of their range and boolean inputs from both values
+        #[arg(long, value_parser = crate::parse_key_val::<f64, f64>, value_delimiter = ',', allow_hyphen_values = true, default_value = DEFAULT_FUZZ_RANGE)]
+        ranges: Vec<(f64, f64)>,
+        /// The number of inputs to sample
+        #[arg(long, default_value = DEFAULT_FUZZ_ITERATIONS)]
+        iterations: usize,
+        /// The seed to sample inputs with, a random one if unset. The seed is reported so failures can be reproduced
+        #[arg(long)]
+        seed: Option<u64>,
+        /// The directory failing inputs and the report are written to
+        #[arg(short = 'O', long, default_value = DEFAULT_FUZZ_DIR)]
+        output_dir: PathBuf,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
     },
 
     /// Mock aggregate proofs
@@ -417,6 +1037,9 @@ pub enum Commands {
         /// whether the accumulated are segments of a larger proof
         #[arg(long, default_value = DEFAULT_SPLIT)]
         split_proofs: bool,
+        /// aggregate into a universal verifier, whose verifying key verifies proofs of any model with the same circuit shape and which exposes the vk hash of each aggregated proof after their instances
+        #[arg(long, default_value = DEFAULT_UNIVERSAL)]
+        universal: bool,
     },
 
     /// setup aggregation circuit :)
@@ -439,6 +1062,9 @@ pub enum Commands {
         /// whether the accumulated are segments of a larger proof
         #[arg(long, default_value = DEFAULT_SPLIT)]
         split_proofs: bool,
+        /// aggregate into a universal verifier, whose verifying key verifies proofs of any model with the same circuit shape and which exposes the vk hash of each aggregated proof after their instances
+        #[arg(long, default_value = DEFAULT_UNIVERSAL)]
+        universal: bool,
         /// compress selectors
         #[arg(long, default_value = DEFAULT_DISABLE_SELECTOR_COMPRESSION)]
         disable_selector_compression: bool,
@@ -448,7 +1074,7 @@ pub enum Commands {
     },
     /// Aggregates proofs :)
     Aggregate {
-        /// The path to the snarks to aggregate over (generated using the prove command with the --proof-type=for-aggr flag)
+        /// The path to the snarks to aggregate over (generated using the prove command with the --proof-type=for-aggr flag), which can be proofs of distinct circuits. The layout of each snark's instances among the aggregate's is saved along with the proof
         #[arg(long, default_value = DEFAULT_PROOF, value_delimiter = ',', allow_hyphen_values = true)]
         aggregation_snarks: Vec<PathBuf>,
         /// The path to load the desired proving key file (generated using the setup-aggregate command)
@@ -477,10 +1103,49 @@ pub enum Commands {
         /// whether the accumulated proofs are segments of a larger circuit
         #[arg(long, default_value = DEFAULT_SPLIT)]
         split_proofs: bool,
+        /// aggregate into a universal verifier, whose verifying key verifies proofs of any model with the same circuit shape and which exposes the vk hash of each aggregated proof after their instances
+        #[arg(long, default_value = DEFAULT_UNIVERSAL)]
+        universal: bool,
         /// commitment used
         #[arg(long, default_value = DEFAULT_COMMITMENT)]
         commitment: Commitments,
     },
+    /// Recursively aggregates many proofs into one, `fan-in` proofs at a time, keeping memory bounded. The keys of each layer are generated on the fly and the verifying key of the final proof is saved to `vk-path`
+    AggregateTree {
+        /// The path to the snarks to aggregate over (generated using the prove command with the --proof-type=for-aggr flag)
+        #[arg(long, default_value = DEFAULT_PROOF, value_delimiter = ',', allow_hyphen_values = true)]
+        aggregation_snarks: Vec<PathBuf>,
+        /// The path to output the proof file to
+        #[arg(long, default_value = DEFAULT_PROOF_AGGREGATED)]
+        proof_path: PathBuf,
+        /// The path to save the verification key of the final proof to
+        #[arg(long, default_value = DEFAULT_VK_AGGREGATED)]
+        vk_path: PathBuf,
+        /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// the transcript of the final proof, intermediate proofs always use poseidon
+        #[arg(
+            long,
+            require_equals = true,
+            num_args = 0..=1,
+            default_value_t = TranscriptType::default(),
+            value_enum
+        )]
+        transcript: TranscriptType,
+        /// logrows used for the aggregation circuits
+        #[arg(long, default_value = DEFAULT_AGGREGATED_LOGROWS)]
+        logrows: u32,
+        /// the number of proofs aggregated by each node of the tree
+        #[arg(long, default_value = DEFAULT_AGGREGATION_FAN_IN)]
+        fan_in: usize,
+        /// run sanity checks during calculations (safe or unsafe)
+        #[arg(long, default_value = DEFAULT_CHECKMODE)]
+        check_mode: CheckMode,
+        /// compress selectors
+        #[arg(long, default_value = DEFAULT_DISABLE_SELECTOR_COMPRESSION)]
+        disable_selector_compression: bool,
+    },
     /// Compiles a circuit from onnx to a simplified graph (einsum + other ops) and parameters as sets of field elements
     CompileCircuit {
         /// The path to the .onnx model file
@@ -492,6 +1157,9 @@ pub enum Commands {
         /// The path to load circuit settings .json file from (generated using the gen-settings command)
         #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
         settings_path: PathBuf,
+        /// Path to a key (32 bytes, raw or hex encoded) to encrypt the compiled circuit with, so the model's weights aren't stored in the clear
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
     },
     /// Creates pk and vk
     Setup {
@@ -513,6 +1181,18 @@ pub enum Commands {
         /// compress selectors
         #[arg(long, default_value = DEFAULT_DISABLE_SELECTOR_COMPRESSION)]
         disable_selector_compression: bool,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+        /// The commitment scheme (kzg or ipa), must match the commitment of the circuit settings, which is picked with gen-settings --commitment. IPA doesn't require a trusted setup.
+        #[arg(long, default_value = None)]
+        commitment: Option<Commitments>,
+        /// The directory to checkpoint the verifying key to before the proving key is generated, and the proving key to before the keys are written, so that a crashed setup can be resumed with --resume
+        #[arg(long)]
+        checkpoint_dir: Option<PathBuf>,
+        /// Resume from the checkpoint in --checkpoint-dir, skipping the stages it completed if its inputs haven't changed
+        #[arg(long, default_value = DEFAULT_RESUME, requires = "checkpoint_dir")]
+        resume: bool,
     },
     #[cfg(not(target_arch = "wasm32"))]
     /// Deploys a test contact that the data attester reads from and creates a data attestation formatted input.json file that contains call data information
@@ -563,6 +1243,20 @@ pub enum Commands {
         #[arg(short = 'W', long, default_value = DEFAULT_WITNESS)]
         witness_path: PathBuf,
     },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Compresses a proof, compressing the points of its transcript and storing repeated instances once. Compressed proofs are decompressed as they are loaded, so every command takes them
+    #[command(name = "compress-proof")]
+    CompressProof {
+        /// The path to the proof file
+        #[arg(short = 'P', long, default_value = DEFAULT_PROOF)]
+        proof_path: PathBuf,
+        /// The path to write the compressed proof to, defaults to overwriting the proof
+        #[arg(long)]
+        output_path: Option<PathBuf>,
+        /// Decompress the proof instead, e.g for tooling that reads proof files directly
+        #[arg(long, default_value = DEFAULT_DECOMPRESS)]
+        decompress: bool,
+    },
 
     #[cfg(not(target_arch = "wasm32"))]
     /// Loads model, data, and creates proof
@@ -576,7 +1270,7 @@ pub enum Commands {
         /// The path to load the desired proving key file (generated using the setup command)
         #[arg(long, default_value = DEFAULT_PK)]
         pk_path: PathBuf,
-        /// The path to output the proof file to
+        /// The path to output the proof file to, CBOR encoded if it ends in `.cbor` and JSON otherwise
         #[arg(long, default_value = DEFAULT_PROOF)]
         proof_path: PathBuf,
         /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs
@@ -593,6 +1287,93 @@ pub enum Commands {
         /// run sanity checks during calculations (safe or unsafe)
         #[arg(long, default_value = DEFAULT_CHECKMODE)]
         check_mode: CheckMode,
+        /// Path to the key (32 bytes, raw or hex encoded) the compiled circuit is encrypted with
+        #[arg(long)]
+        encryption_key: Option<PathBuf>,
+        /// The commitment scheme (kzg or ipa), must match the commitment of the circuit settings, which is picked with gen-settings --commitment. IPA doesn't require a trusted setup.
+        #[arg(long, default_value = None)]
+        commitment: Option<Commitments>,
+        /// The transcript hash: evm (keccak) for on-chain verification, poseidon for recursion or blake2b. Defaults to evm for single proofs and poseidon for proofs to aggregate
+        #[arg(long, value_enum)]
+        transcript: Option<TranscriptType>,
+        /// Hardware the prover offloads its MSMs and FFTs to (cpu or cuda). cuda needs ezkl built with the icicle feature and ENABLE_ICICLE_GPU set, which the prover reads itself. Defaults to whatever ENABLE_ICICLE_GPU selects
+        #[arg(long)]
+        accelerator: Option<Accelerator>,
+        /// Path to the running state of a streaming aggregation (created if missing). The proof is generated for aggregation and pushed into the state, which aggregates every layer of the tree that fills up. Use finalize-aggregation to produce the final proof
+        #[arg(long)]
+        aggregation_state: Option<PathBuf>,
+        /// the number of proofs aggregated together, defaults to 4 when creating the aggregation state and must match it afterwards
+        #[arg(long, requires = "aggregation_state")]
+        fan_in: Option<usize>,
+        /// logrows of the aggregation circuits, defaults to 23 when creating the aggregation state and must match it afterwards
+        #[arg(long, requires = "aggregation_state")]
+        aggregation_logrows: Option<u32>,
+        /// The path to the SRS of the aggregation circuits, defaults to $EZKL_REPO_PATH/srs/kzg{logrows}.srs when creating the aggregation state and must match it afterwards
+        #[arg(long, requires = "aggregation_state")]
+        aggregation_srs_path: Option<PathBuf>,
+        /// Directory of .json witness files to prove in a batch, reusing the loaded proving key and SRS. Replaces --witness and --proof-path
+        #[arg(long)]
+        witness_dir: Option<PathBuf>,
+        /// Directory to write the proof of each witness of a batch to, along with a manifest.json listing them
+        #[arg(long, default_value = DEFAULT_PROOF_DIR)]
+        proof_dir: PathBuf,
+        /// Prove an assignment written by gen-witness --full-assignment instead of the witness
+        #[arg(long, conflicts_with = "witness_dir")]
+        from_assignment: Option<PathBuf>,
+        /// Zeroize the witness in memory and shred the witness file(s) once the proof is written
+        #[arg(long, default_value = DEFAULT_SCRUB)]
+        scrub: bool,
+        /// Seed the rng blinding the proof, so the same witness always yields the same proof bytes.
+        /// FOR TESTS ONLY: a seeded proof is not zero knowledge
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Prove on a quarter of the cpus (unless num_threads is set) and release the heap memory freed after loading the proving key and SRS back to the OS. This trims the memory around the prover but not its own peak (the extended domain evaluations), so it doesn't make circuits that run out of memory provable
+        #[arg(long, default_value = DEFAULT_TRIM_MEMORY, conflicts_with = "witness_dir")]
+        trim_memory: bool,
+        /// Write a report of the time each stage of proving took, the peak memory usage, the number of instances and the proof size next to the proof, as <proof>.profile.json
+        #[arg(long, default_value = DEFAULT_PROFILE, conflicts_with = "witness_dir")]
+        profile: bool,
+        /// The original onnx model to run in onnxruntime alongside proving, as a trusted execution. No proof is emitted if the outputs of the circuit aren't within the tolerance of its outputs. Needs ezkl built with the onnxruntime feature
+        #[arg(long, conflicts_with = "witness_dir")]
+        shadow_model: Option<PathBuf>,
+        /// Prove with a proving key generated from different circuit settings than the compiled circuit's, warning instead of failing
+        #[arg(long, default_value = DEFAULT_FORCE)]
+        force: bool,
+        /// The directory to checkpoint the proof to before it is checked against the shadow model and written, so that a crashed run can be resumed with --resume
+        #[arg(long, conflicts_with = "witness_dir")]
+        checkpoint_dir: Option<PathBuf>,
+        /// Resume from the checkpoint in --checkpoint-dir, skipping the stages it completed if its inputs haven't changed
+        #[arg(long, default_value = DEFAULT_RESUME, requires = "checkpoint_dir")]
+        resume: bool,
+    },
+    /// Produces the final proof over every proof pushed into an aggregation state (see prove --aggregation-state)
+    FinalizeAggregation {
+        /// Path to the running state of the streaming aggregation
+        #[arg(long)]
+        state_path: PathBuf,
+        /// The path to output the proof file to
+        #[arg(long, default_value = DEFAULT_PROOF_AGGREGATED)]
+        proof_path: PathBuf,
+        /// The path to save the verification key of the final proof to
+        #[arg(long, default_value = DEFAULT_VK_AGGREGATED)]
+        vk_path: PathBuf,
+        /// The path to SRS, must match the SRS the aggregation state was created with. Defaults to the stored one
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        #[arg(
+            long,
+            require_equals = true,
+            num_args = 0..=1,
+            default_value_t = TranscriptType::default(),
+            value_enum
+        )]
+        transcript: TranscriptType,
+        /// run sanity checks during calculations (safe or unsafe)
+        #[arg(long, default_value = DEFAULT_CHECKMODE)]
+        check_mode: CheckMode,
+        /// compress selectors
+        #[arg(long, default_value = DEFAULT_DISABLE_SELECTOR_COMPRESSION)]
+        disable_selector_compression: bool,
     },
     #[cfg(not(target_arch = "wasm32"))]
     /// Creates an Evm verifier for a single proof
@@ -616,8 +1397,59 @@ pub enum Commands {
         /// Whether the verifier key should be rendered as a separate contract.
         /// We recommend disabling selector compression if this is enabled.
         /// To save the verifier key as a separate contract, set this to true and then call the create-evm-vk command.        
+        /// Verifiers over the EIP-170 contract size limit are split this way automatically, with the verifier key written next to the verifier as <sol_code_path>.vk.sol.
         #[arg(long, default_value = DEFAULT_RENDER_VK_SEPERATELY)]
         render_vk_seperately: bool,
+        /// The path to a .json list of view calls returning commitments posted on-chain, e.g. `[{"segment": "input_hash_0", "address": "...", "call_data": "..."}]`.
+        /// The verifier makes each call at verification time and rejects proofs whose instance segment doesn't match the returned value.
+        #[arg(long)]
+        commitments_path: Option<PathBuf>,
+        /// The chain the verifier is deployed to, adapting it to the chain's precompiles: ethereum, arbitrum, optimism or zksync
+        #[arg(long, default_value = DEFAULT_TARGET_CHAIN)]
+        target_chain: TargetChain,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates an npm package that verifies proofs for this model in the browser, with TS types for its public instances
+    #[command(name = "create-npm-verifier")]
+    CreateNpmVerifier {
+        /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// The path to load circuit settings .json file from (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to load the desired verification key file
+        #[arg(long, default_value = DEFAULT_VK)]
+        vk_path: PathBuf,
+        /// The directory to write the package to
+        #[arg(short = 'O', long, default_value = DEFAULT_NPM_PACKAGE_DIR)]
+        output_dir: PathBuf,
+        /// The name of the npm package
+        #[arg(long, default_value = DEFAULT_NPM_PACKAGE_NAME)]
+        package_name: String,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates a CosmWasm contract crate that verifies proofs for this model on non-EVM chains, taking proofs and instances as the Solidity verifier does
+    #[command(name = "create-wasm-verifier")]
+    CreateWasmVerifier {
+        /// The path to SRS, if None will use $EZKL_REPO_PATH/srs/kzg{logrows}.srs. Only its verifier params are bundled in the contract
+        #[arg(long)]
+        srs_path: Option<PathBuf>,
+        /// The path to load circuit settings .json file from (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to load the desired verification key file
+        #[arg(long, default_value = DEFAULT_VK)]
+        vk_path: PathBuf,
+        /// The directory to write the contract crate to
+        #[arg(short = 'O', long, default_value = DEFAULT_WASM_CONTRACT_DIR)]
+        output_dir: PathBuf,
+        /// The name of the contract crate
+        #[arg(long, default_value = DEFAULT_WASM_CONTRACT_NAME)]
+        crate_name: String,
+        /// The smart contract platform of the chain
+        #[arg(long, default_value = DEFAULT_CONTRACT_TARGET, value_enum)]
+        target: ContractTarget,
     },
     #[cfg(not(target_arch = "wasm32"))]
     /// Creates an Evm verifier for a single proof
@@ -660,6 +1492,20 @@ pub enum Commands {
         #[arg(short = 'D', long, default_value = DEFAULT_DATA)]
         data: PathBuf,
     },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates a Solidity library quantizing on-chain fixed point values as ezkl does at a given scale, for contracts computing the public inputs of a verifier
+    #[command(name = "create-evm-quantizer")]
+    CreateEvmQuantizer {
+        /// The path to output the Solidity code
+        #[arg(long, default_value = DEFAULT_SOL_CODE_QUANTIZER)]
+        sol_code_path: PathBuf,
+        /// The scale to quantize at, the input_scale of the circuit's settings
+        #[arg(long, default_value = DEFAULT_QUANTIZER_SCALE, allow_hyphen_values = true)]
+        scale: crate::Scale,
+        /// The bits quantized values must fit in, quantizing reverts for values that don't
+        #[arg(long, default_value = DEFAULT_QUANTIZER_BITS)]
+        bits: u32,
+    },
 
     #[cfg(not(target_arch = "wasm32"))]
     /// Creates an Evm verifier for an aggregate proof
@@ -686,8 +1532,12 @@ pub enum Commands {
         /// Whether the verifier key should be rendered as a separate contract.
         /// We recommend disabling selector compression if this is enabled.
         /// To save the verifier key as a separate contract, set this to true and then call the create-evm-vk command.        
+        /// Verifiers over the EIP-170 contract size limit are split this way automatically, with the verifier key written next to the verifier as <sol_code_path>.vk.sol.
         #[arg(long, default_value = DEFAULT_RENDER_VK_SEPERATELY)]
         render_vk_seperately: bool,
+        /// Adds `innerInstances`, returning the public instances of each aggregated proof, and `verifyProofAndEmit`, which verifies the aggregate and emits them, to the verifier
+        #[arg(long, default_value = DEFAULT_FORWARD_INSTANCES)]
+        forward_instances: bool,
     },
     /// Verifies a proof, returning accept or reject
     Verify {
@@ -697,6 +1547,9 @@ pub enum Commands {
         /// The path to the proof file (generated using the prove command)
         #[arg(long, default_value = DEFAULT_PROOF)]
         proof_path: PathBuf,
+        /// Directory of .json or .cbor proofs to verify in parallel, reusing the loaded verification key and SRS, reporting the result of each and failing if any proof fails. Replaces --proof-path
+        #[arg(long)]
+        proof_dir: Option<PathBuf>,
         /// The path to the verification key file (generated using the setup command)
         #[arg(long, default_value = DEFAULT_VK)]
         vk_path: PathBuf,
@@ -706,6 +1559,12 @@ pub enum Commands {
         /// Reduce SRS logrows to the number of instances rather than the number of logrows used for proofs (only works if the srs were generated in the same ceremony)
         #[arg(long, default_value = DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION)]
         reduced_srs: bool,
+        /// The commitment scheme (kzg or ipa), must match the commitment of the circuit settings, which is picked with gen-settings --commitment. IPA doesn't require a trusted setup.
+        #[arg(long, default_value = None)]
+        commitment: Option<Commitments>,
+        /// Verify with a verification key generated from different circuit settings, warning instead of failing
+        #[arg(long, default_value = DEFAULT_FORCE)]
+        force: bool,
     },
     /// Verifies an aggregate proof, returning accept or reject
     VerifyAggr {
@@ -743,9 +1602,12 @@ pub enum Commands {
         /// The optimizer runs to set on the verifier. Lower values optimize for deployment cost, while higher values optimize for gas cost.
         #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
         optimizer_runs: usize,
-        /// Private secp256K1 key in hex format, 64 chars, no 0x prefix, of the account signing transactions. If None the private key will be generated by Anvil
-        #[arg(short = 'P', long)]
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
         private_key: Option<String>,
+        /// Deploy with CREATE2 through the deterministic deployment proxy, salted with the hash of this verification key, at the address given by predict-evm-address
+        #[arg(long)]
+        create2_vk_path: Option<PathBuf>,
     },
     #[cfg(not(target_arch = "wasm32"))]
     /// Deploys an evm verifier that is generated by ezkl
@@ -762,11 +1624,205 @@ pub enum Commands {
         /// The optimizer runs to set on the verifier. Lower values optimize for deployment cost, while higher values optimize for gas cost.
         #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
         optimizer_runs: usize,
-        /// Private secp256K1 key in hex format, 64 chars, no 0x prefix, of the account signing transactions. If None the private key will be generated by Anvil
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+        /// Deploy with CREATE2 through the deterministic deployment proxy, salted with the hash of this verification key, at the address given by predict-evm-address
+        #[arg(long)]
+        create2_vk_path: Option<PathBuf>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates a verifier registry, a verifier whose address stays the same across model updates, forwarding proofs to a verifier rendered with --render-vk-seperately and the verifying key it is pointed at
+    #[command(name = "create-evm-registry")]
+    CreateEvmRegistry {
+        /// The path to output the Solidity code
+        #[arg(long, default_value = DEFAULT_SOL_CODE_REGISTRY)]
+        sol_code_path: PathBuf,
+        /// The path to output the Solidity verifier registry ABI
+        #[arg(long, default_value = DEFAULT_VERIFIER_REGISTRY_ABI)]
+        abi_path: PathBuf,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Deploys a verifier registry, owned by the deployer, pointed at a deployed verifier and verifying key
+    #[command(name = "deploy-evm-registry")]
+    DeployEvmRegistry {
+        /// The path to the Solidity code (generated using the create-evm-registry command)
+        #[arg(long, default_value = DEFAULT_SOL_CODE_REGISTRY)]
+        sol_code_path: PathBuf,
+        /// The address of the verifier, rendered with --render-vk-seperately
+        #[arg(long)]
+        addr_verifier: H160Flag,
+        /// The address of the verifying key (deployed using the deploy-evm-vk command)
+        #[arg(long)]
+        addr_vk: H160Flag,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        #[arg(long, default_value = DEFAULT_CONTRACT_ADDRESS_REGISTRY)]
+        /// The path to output the contract address
+        addr_path: PathBuf,
+        /// The optimizer runs to set on the registry. Lower values optimize for deployment cost, while higher values optimize for gas cost.
+        #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
+        optimizer_runs: usize,
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Points a verifier registry at the verifying key of a retrained model, so proofs of the new model verify at the same address
+    #[command(name = "update-evm-vk")]
+    UpdateEvmVk {
+        /// The address of the verifier registry (deployed using the deploy-evm-registry command)
+        #[arg(long)]
+        addr_registry: H160Flag,
+        /// The address of the new verifying key (deployed using the deploy-evm-vk command)
+        #[arg(long)]
+        addr_vk: H160Flag,
+        /// The address of a new verifier, if the shape of the circuit changed. If None the registry keeps its verifier
+        #[arg(long)]
+        addr_verifier: Option<H160Flag>,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        /// The signer of transactions, the owner of the registry: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Revokes a past version of a verifier registry, so proofs no longer verify against its verifying key
+    #[command(name = "revoke-evm-vk")]
+    RevokeEvmVk {
+        /// The address of the verifier registry (deployed using the deploy-evm-registry command)
+        #[arg(long)]
+        addr_registry: H160Flag,
+        /// The version to revoke, as returned by update-evm-vk. The current version can't be revoked
+        #[arg(long)]
+        version: u64,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        /// The signer of transactions, the owner of the registry: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates an ERC-4337 paymaster sponsoring the user operations that carry a valid proof, verified by a verifier created with create-evm-verifier
+    #[command(name = "create-evm-paymaster")]
+    CreateEvmPaymaster {
+        /// The path to load circuit settings .json file from (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to output the Solidity code
+        #[arg(long, default_value = DEFAULT_SOL_CODE_PAYMASTER)]
+        sol_code_path: PathBuf,
+        /// The path to output the Solidity paymaster ABI
+        #[arg(long, default_value = DEFAULT_PAYMASTER_ABI)]
+        abi_path: PathBuf,
+        /// The most gas verifying a proof may take during validation, which has to fit in the paymaster verification gas limit of user operations
+        #[arg(long, default_value = DEFAULT_PAYMASTER_VERIFY_GAS_LIMIT)]
+        verify_gas_limit: u64,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Deploys an ERC-4337 paymaster generated by create-evm-paymaster
+    #[command(name = "deploy-evm-paymaster")]
+    DeployEvmPaymaster {
+        /// The path to the Solidity code (generated using the create-evm-paymaster command)
+        #[arg(long, default_value = DEFAULT_SOL_CODE_PAYMASTER)]
+        sol_code_path: PathBuf,
+        /// The address of the verifier
+        #[arg(long)]
+        addr_verifier: H160Flag,
+        /// The address of the ERC-4337 entry point
+        #[arg(long, default_value = DEFAULT_ENTRY_POINT)]
+        entry_point: H160Flag,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        #[arg(long, default_value = DEFAULT_CONTRACT_ADDRESS_PAYMASTER)]
+        /// The path to output the contract address
+        addr_path: PathBuf,
+        /// The optimizer runs to set on the paymaster. Lower values optimize for deployment cost, while higher values optimize for gas cost.
+        #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
+        optimizer_runs: usize,
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Creates a contract recording proofs posted as EIP-4844 blobs, which verifies a posted proof and checks its instances and hash against the blob's commitment
+    #[command(name = "create-evm-blob-verifier")]
+    CreateEvmBlobVerifier {
+        /// The path to load circuit settings .json file from (generated using the gen-settings command)
+        #[arg(short = 'S', long, default_value = DEFAULT_SETTINGS)]
+        settings_path: PathBuf,
+        /// The path to output the Solidity code
+        #[arg(long, default_value = DEFAULT_SOL_CODE_BLOB)]
+        sol_code_path: PathBuf,
+        /// The path to output the Solidity blob verifier ABI
+        #[arg(long, default_value = DEFAULT_VERIFIER_BLOB_ABI)]
+        abi_path: PathBuf,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Deploys a blob verifier generated by create-evm-blob-verifier, on a chain with the Cancun hardfork
+    #[command(name = "deploy-evm-blob-verifier")]
+    DeployEvmBlobVerifier {
+        /// The path to the Solidity code (generated using the create-evm-blob-verifier command)
+        #[arg(long, default_value = DEFAULT_SOL_CODE_BLOB)]
+        sol_code_path: PathBuf,
+        /// The address of the verifier of the proofs
+        #[arg(long)]
+        addr_verifier: H160Flag,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        #[arg(long, default_value = DEFAULT_CONTRACT_ADDRESS_BLOB)]
+        /// The path to output the contract address
+        addr_path: PathBuf,
+        /// The optimizer runs to set on the blob verifier. Lower values optimize for deployment cost, while higher values optimize for gas cost.
+        #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
+        optimizer_runs: usize,
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
+        private_key: Option<String>,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Posts a proof as an EIP-4844 blob to a blob verifier, printing the versioned hash of the blob. Needs ezkl built with the blobs feature
+    #[command(name = "post-proof-blob")]
+    PostProofBlob {
+        /// The path to the proof
+        #[arg(long, default_value = DEFAULT_PROOF)]
+        proof_path: PathBuf,
+        /// The address of the blob verifier
+        #[arg(long)]
+        addr_verifier: H160Flag,
+        /// The path to the EIP-4844 KZG trusted setup, in the format of the consensus specs
+        #[arg(long, default_value = DEFAULT_TRUSTED_SETUP)]
+        trusted_setup_path: PathBuf,
+        /// RPC URL for an Ethereum node, if None will use Anvil but WON'T persist state
+        #[arg(short = 'U', long)]
+        rpc_url: Option<String>,
+        /// The private secp256K1 key, in hex format, 64 chars, no 0x prefix, signing the blob transaction. Blob transactions can't be signed by external signers. If None the private key will be generated by Anvil
         #[arg(short = 'P', long)]
         private_key: Option<String>,
     },
     #[cfg(not(target_arch = "wasm32"))]
+    /// Predicts, offline, the address an evm verifier or verifier key is deployed to with --create2-vk-path
+    #[command(name = "predict-evm-address")]
+    PredictEvmAddress {
+        /// The path to the Solidity code (generated using the create-evm-verifier or create-evm-vk command)
+        #[arg(long, default_value = DEFAULT_SOL_CODE)]
+        sol_code_path: PathBuf,
+        /// The path to the verification key salting the deployment
+        #[arg(long, default_value = DEFAULT_VK)]
+        vk_path: PathBuf,
+        /// The optimizer runs the contract is deployed with
+        #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
+        optimizer_runs: usize,
+        /// The contract to deploy, Halo2Verifier or Halo2VerifyingKey
+        #[arg(long, default_value = DEFAULT_VERIFIER_CONTRACT_NAME)]
+        contract_name: String,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
     /// Deploys an evm verifier that allows for data attestation
     #[command(name = "deploy-evm-da")]
     DeployEvmDataAttestation {
@@ -788,8 +1844,8 @@ pub enum Commands {
         /// The optimizer runs to set on the verifier. (Lower values optimize for deployment, while higher values optimize for execution)
         #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
         optimizer_runs: usize,
-        /// Private secp256K1 key in hex format, 64 chars, no 0x prefix, of the account signing transactions. If None the private key will be generated by Anvil
-        #[arg(short = 'P', long)]
+        /// The signer of transactions: a private secp256K1 key in hex format, 64 chars, no 0x prefix, `ledger[:<index>]`, `aws-kms:<key id>` or `rpc[:<address>]` for an account held by the node. If None the private key will be generated by Anvil
+        #[arg(short = 'P', long, visible_alias = "signer")]
         private_key: Option<String>,
     },
     #[cfg(not(target_arch = "wasm32"))]
@@ -812,4 +1868,21 @@ pub enum Commands {
         #[arg(long)]
         addr_vk: Option<H160Flag>,
     },
+    #[cfg(not(target_arch = "wasm32"))]
+    /// Deploys a verifier to a local Evm executor and estimates the gas of verifying a proof with it, broken down into calldata and execution
+    #[command(name = "estimate-gas")]
+    EstimateGas {
+        /// The path to a sample proof (generated using the prove command)
+        #[arg(long, default_value = DEFAULT_PROOF)]
+        proof_path: PathBuf,
+        /// The path to the Solidity code of the verifier (generated using the create-evm-verifier command)
+        #[arg(long, default_value = DEFAULT_SOL_CODE)]
+        sol_code_path: PathBuf,
+        /// The path to the Solidity code of the verifier key, if it is rendered separately (generated using the create-evm-vk command)
+        #[arg(long)]
+        vk_sol_path: Option<PathBuf>,
+        /// The optimizer runs to set on the verifier. Lower values optimize for deployment cost, while higher values optimize for gas cost.
+        #[arg(long, default_value = DEFAULT_OPTIMIZER_RUNS)]
+        optimizer_runs: usize,
+    },
 }
@@ -0,0 +1,244 @@
+//! Whether this binary can verify an artifact, checked by `ezkl compat-check`.
+//!
+//! Proofs and settings record the version of their format (see [SNARK_FORMAT_VERSION] and
+//! [SETTINGS_SCHEMA_VERSION]). Older formats are migrated on load, so a binary reads every format
+//! version up to the one it writes. [COMPATIBILITY] names, for every format version, the first
+//! release reading it, from which the range of releases able to verify an artifact follows.
+
+use crate::graph::migrate::{settings_schema_version, SETTINGS_SCHEMA_VERSION};
+use crate::pfsys::compress::COMPRESSED_SNARK_FORMAT_VERSION;
+#[cfg(doc)]
+use crate::pfsys::SNARK_FORMAT_VERSION;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The kinds of versioned artifacts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Artifact {
+    /// A proof, versioned by its `format_version`
+    Proof,
+    /// Circuit settings, versioned by their `schema_version`
+    Settings,
+}
+
+impl std::fmt::Display for Artifact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Artifact::Proof => write!(f, "proof format"),
+            Artifact::Settings => write!(f, "settings schema"),
+        }
+    }
+}
+
+/// The first release reading a format version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Since {
+    /// The format predates versioning, so every release reads it
+    Always,
+    /// The format was introduced by this release
+    Release(&'static str),
+    /// The format is introduced by the release this binary is built for. Pin it to that release
+    /// when tagging it.
+    Unreleased,
+}
+
+impl Since {
+    /// The later of two first releases.
+    fn later(self, other: Since) -> Since {
+        match (self, other) {
+            (Since::Unreleased, _) | (_, Since::Unreleased) => Since::Unreleased,
+            (Since::Always, s) | (s, Since::Always) => s,
+            (Since::Release(a), Since::Release(b)) => {
+                if parse_release(a) >= parse_release(b) {
+                    self
+                } else {
+                    other
+                }
+            }
+        }
+    }
+
+    /// The range of releases reading a format introduced by this release.
+    fn range(self) -> String {
+        match self {
+            Since::Always => "*".to_string(),
+            Since::Release(release) => format!(">={}", release),
+            Since::Unreleased => match env!("CARGO_PKG_VERSION") {
+                "0.0.0" => "unreleased".to_string(),
+                release => format!(">={}", release),
+            },
+        }
+    }
+}
+
+/// The first release reading each format version of each artifact. Append an entry whenever a
+/// format version is bumped.
+pub const COMPATIBILITY: &[(Artifact, u32, Since)] = &[
+    (Artifact::Proof, 1, Since::Always),
+    (Artifact::Proof, 2, Since::Unreleased),
+    (Artifact::Proof, 3, Since::Unreleased),
+    (Artifact::Settings, 0, Since::Always),
+    (Artifact::Settings, 1, Since::Unreleased),
+    (Artifact::Settings, 2, Since::Unreleased),
+];
+
+/// Parses a `major.minor.patch` release, ignoring a leading `v` and any pre-release suffix.
+fn parse_release(release: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = release
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map(|p| p.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// The newest version of `artifact` this binary reads.
+pub fn supported_version(artifact: Artifact) -> u32 {
+    match artifact {
+        Artifact::Proof => COMPRESSED_SNARK_FORMAT_VERSION,
+        Artifact::Settings => SETTINGS_SCHEMA_VERSION,
+    }
+}
+
+/// Checks that this binary reads `version` of `artifact`.
+pub fn check_version(artifact: Artifact, version: u32) -> Result<(), String> {
+    if version > supported_version(artifact) {
+        return Err(format!(
+            "{} version {} is newer than this binary reads (up to version {}), upgrade ezkl to verify it",
+            artifact,
+            version,
+            supported_version(artifact)
+        ));
+    }
+    Ok(())
+}
+
+/// The first release reading `version` of `artifact`.
+pub fn first_release(artifact: Artifact, version: u32) -> Result<Since, String> {
+    check_version(artifact, version)?;
+    COMPATIBILITY
+        .iter()
+        .find(|(a, v, _)| *a == artifact && *v == version)
+        .map(|(_, _, since)| *since)
+        .ok_or_else(|| format!("unknown {} version {}", artifact, version))
+}
+
+/// The format versions of a proof and its settings, the releases reading them and the newest
+/// versions this binary reads.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompatReport {
+    /// The format version of the proof
+    pub proof_format_version: u32,
+    /// The schema version of the settings
+    pub settings_schema_version: u32,
+    /// The version of ezkl that wrote the settings, if they record it
+    pub settings_written_by: Option<String>,
+    /// The releases reading the proof format, e.g. `>=10.1.0`, `*` for every release
+    pub proof_readable_by: String,
+    /// The releases reading the settings schema
+    pub settings_readable_by: String,
+    /// The releases able to verify the proof against the settings
+    pub verifiable_by: String,
+    /// The newest proof format version this binary reads
+    pub supported_proof_format_version: u32,
+    /// The newest settings schema version this binary reads
+    pub supported_settings_schema_version: u32,
+}
+
+impl CompatReport {
+    /// Reads the format versions of a serialized proof and settings, failing when they are newer
+    /// than this binary reads.
+    pub fn new(proof: &Value, settings: &Value) -> Result<Self, Box<dyn Error>> {
+        // proofs predating versioning are version 1
+        let proof_format_version = proof
+            .get("format_version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1) as u32;
+        let settings_schema_version = settings_schema_version(settings);
+
+        let proof_since = first_release(Artifact::Proof, proof_format_version)?;
+        let settings_since = first_release(Artifact::Settings, settings_schema_version)?;
+
+        Ok(CompatReport {
+            proof_format_version,
+            settings_schema_version,
+            settings_written_by: settings
+                .get("version")
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(String::from),
+            proof_readable_by: proof_since.range(),
+            settings_readable_by: settings_since.range(),
+            verifiable_by: proof_since.later(settings_since).range(),
+            supported_proof_format_version: supported_version(Artifact::Proof),
+            supported_settings_schema_version: supported_version(Artifact::Settings),
+        })
+    }
+}
+
+/// Reads a proof as json, whether it is serialized as json or cbor, without interpreting it.
+pub fn read_proof_value(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let is_json = reader
+        .fill_buf()?
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .map_or(true, |b| *b == b'{');
+    Ok(if is_json {
+        serde_json::from_reader(reader)?
+    } else {
+        ciborium::from_reader(reader)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compat_report() {
+        // every format this binary writes is read back
+        assert!(check_version(Artifact::Proof, supported_version(Artifact::Proof)).is_ok());
+        assert!(check_version(Artifact::Settings, SETTINGS_SCHEMA_VERSION).is_ok());
+        // and the table covers every format version this binary reads
+        for version in 1..=supported_version(Artifact::Proof) {
+            assert!(first_release(Artifact::Proof, version).is_ok());
+        }
+        for version in 0..=SETTINGS_SCHEMA_VERSION {
+            assert!(first_release(Artifact::Settings, version).is_ok());
+        }
+
+        assert_eq!(
+            Since::Release("10.1.0").later(Since::Release("v9.4.2")),
+            Since::Release("10.1.0")
+        );
+        assert_eq!(
+            Since::Always.later(Since::Release("9.4.2")).range(),
+            ">=9.4.2"
+        );
+        assert_eq!(Since::Always.later(Since::Always).range(), "*");
+
+        let report = CompatReport::new(&json!({}), &json!({})).unwrap();
+        assert_eq!(report.proof_format_version, 1);
+        assert_eq!(report.settings_schema_version, 0);
+        assert_eq!(report.settings_written_by, None);
+        assert_eq!(report.verifiable_by, "*");
+
+        let report = CompatReport::new(
+            &json!({"format_version": 3}),
+            &json!({"schema_version": 1, "version": "1.2.3"}),
+        )
+        .unwrap();
+        assert_eq!(report.proof_format_version, 3);
+        assert_eq!(report.settings_schema_version, 1);
+        assert_eq!(report.settings_written_by.as_deref(), Some("1.2.3"));
+        assert_eq!(report.verifiable_by, Since::Unreleased.range());
+
+        let err = CompatReport::new(&json!({"format_version": 99}), &json!({})).unwrap_err();
+        assert!(err.to_string().contains("proof format version 99"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Lockfiles pinning everything that affects the bytes of keys and proofs.
+//!
+//! `ezkl export-env` records the lock of the current environment, and `ezkl check-env` compares
+//! another environment against it, so teams can certify that two setups produce interoperable
+//! artifacts before exchanging them.
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// The compiled in features that change the circuits, keys or proofs ezkl produces.
+pub fn proof_features() -> Vec<String> {
+    let features = [
+        ("mv-lookup", cfg!(feature = "mv-lookup")),
+        ("det-prove", cfg!(feature = "det-prove")),
+        ("icicle", cfg!(feature = "icicle")),
+    ];
+    features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// The platform ezkl was built for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HostProfile {
+    /// The target operating system
+    pub os: String,
+    /// The target architecture
+    pub arch: String,
+    /// The size of a pointer in bits
+    pub pointer_width: usize,
+}
+
+impl HostProfile {
+    /// The profile of the running binary.
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            pointer_width: usize::BITS as usize,
+        }
+    }
+}
+
+/// A lockfile of a proving environment.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnvLock {
+    /// The ezkl version
+    pub version: String,
+    /// The SHA256 hash of the circuit settings file, if one was locked
+    pub settings_hash: Option<String>,
+    /// The SHA256 hash of the SRS file, if one was locked
+    pub srs_hash: Option<String>,
+    /// The compiled in features affecting proofs, see [proof_features]
+    pub features: Vec<String>,
+    /// The platform of the binary
+    pub host: HostProfile,
+}
+
+impl EnvLock {
+    /// The lock of the running binary for the given settings and SRS hashes.
+    pub fn current(settings_hash: Option<String>, srs_hash: Option<String>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            settings_hash,
+            srs_hash,
+            features: proof_features(),
+            host: HostProfile::current(),
+        }
+    }
+
+    /// Load the lock from a .json file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open lockfile {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*crate::EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the lock to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer =
+            BufWriter::with_capacity(*crate::EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Compares `other` against the lock. Returns the differences that break interoperability,
+    /// and those in the host profile, which only matter for bit-for-bit reproducibility.
+    pub fn diff(&self, other: &EnvLock) -> (Vec<String>, Vec<String>) {
+        let mut breaking = vec![];
+        let mut show = |field: &str, locked: &dyn std::fmt::Debug, found: &dyn std::fmt::Debug| {
+            breaking.push(format!("{}: locked {:?}, found {:?}", field, locked, found))
+        };
+        if self.version != other.version {
+            show("version", &self.version, &other.version);
+        }
+        // only compare artifacts that were locked
+        if self.settings_hash.is_some() && self.settings_hash != other.settings_hash {
+            show("settings_hash", &self.settings_hash, &other.settings_hash);
+        }
+        if self.srs_hash.is_some() && self.srs_hash != other.srs_hash {
+            show("srs_hash", &self.srs_hash, &other.srs_hash);
+        }
+        if self.features != other.features {
+            show("features", &self.features, &other.features);
+        }
+
+        let mut host = vec![];
+        if self.host != other.host {
+            host.push(format!(
+                "host: locked {:?}, found {:?}",
+                self.host, other.host
+            ));
+        }
+        (breaking, host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_lock_diff() {
+        let lock = EnvLock::current(Some("abc".into()), None);
+        let (breaking, host) = lock.diff(&EnvLock::current(Some("abc".into()), Some("srs".into())));
+        assert!(breaking.is_empty() && host.is_empty());
+
+        let mut other = EnvLock::current(Some("def".into()), None);
+        other.host.arch = "riscv64".into();
+        let (breaking, host) = lock.diff(&other);
+        assert_eq!(breaking.len(), 1);
+        assert!(breaking[0].starts_with("settings_hash"));
+        assert_eq!(host.len(), 1);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::graph::input::{CallsToAccount, FileSourceInner, GraphData};
+use crate::graph::input::{
+    AccountStateProof, BlockStateProof, CallsToAccount, ContractCall, FileSourceInner, GraphData,
+    OnChainSource, StorageSlotProof, StorageSlots,
+};
 use crate::graph::modules::POSEIDON_INSTANCES;
 use crate::graph::DataSource;
 #[cfg(not(target_arch = "wasm32"))]
@@ -15,6 +18,8 @@ use ethers::prelude::ContractInstance;
 use ethers::prelude::Wallet;
 use ethers::providers::Middleware;
 use ethers::providers::{Http, Provider};
+#[cfg(not(target_arch = "wasm32"))]
+use ethers::providers::{StreamExt, Ws};
 use ethers::signers::Signer;
 use ethers::solc::{CompilerInput, Solc};
 use ethers::types::transaction::eip2718::TypedTransaction;
@@ -24,20 +29,25 @@ use ethers::types::U256;
 use ethers::types::{Bytes, I256};
 #[cfg(not(target_arch = "wasm32"))]
 use ethers::{
-    prelude::{LocalWallet, Wallet},
+    prelude::LocalWallet,
     utils::{Anvil, AnvilInstance},
 };
 use halo2_solidity_verifier::encode_calldata;
 use halo2curves::bn256::{Fr, G1Affine};
 use halo2curves::group::ff::PrimeField;
 use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Duration;
 use std::{convert::TryFrom, sync::Arc};
 
 /// A local ethers-rs based client
+#[cfg(not(target_arch = "wasm32"))]
+pub type EthersClient = Arc<EthClient>;
+/// A local ethers-rs based client
+#[cfg(target_arch = "wasm32")]
 pub type EthersClient = Arc<SignerMiddleware<Provider<Http>, Wallet<SigningKey>>>;
 
 // Generate contract bindings OUTSIDE the functions so they are part of library
@@ -47,6 +57,12 @@ abigen!(QuantizeData, "./abis/QuantizeData.json");
 
 const TESTREADS_SOL: &str = include_str!("../contracts/TestReads.sol");
 const QUANTIZE_DATA_SOL: &str = include_str!("../contracts/QuantizeData.sol");
+const QUANTIZER_SOL: &str = include_str!("../contracts/EzklQuantizer.sol");
+const PAYMASTER_SOL: &str = include_str!("../contracts/ProofPaymaster.sol");
+const BLOB_VERIFIER_SOL: &str = include_str!("../contracts/BlobVerifier.sol");
+const CALLER_BOUND_VERIFIER_SOL: &str = include_str!("../contracts/CallerBoundVerifier.sol");
+/// The Solidity code of the `EzklVerifierRegistry`, see [deploy_verifier_registry]
+pub const VERIFIER_REGISTRY_SOL: &str = include_str!("../contracts/VerifierRegistry.sol");
 const ATTESTDATA_SOL: &str = include_str!("../contracts/AttestData.sol");
 const LOADINSTANCES_SOL: &str = include_str!("../contracts/LoadInstances.sol");
 
@@ -74,32 +90,341 @@ pub async fn setup_eth_backend(
     let chain_id = provider.get_chainid().await?.as_u64();
     info!("using chain {}", chain_id);
 
-    // Instantiate the wallet
-    let wallet: LocalWallet;
-    if let Some(private_key) = private_key {
-        debug!("using private key {}", private_key);
-        // Sanity checks for private_key
-        let private_key_format_error =
-            "Private key must be in hex format, 64 chars, without 0x prefix";
-        if private_key.len() != 64 {
-            return Err(private_key_format_error.into());
-        }
-        let private_key_buffer = hex::decode(private_key)?;
-        let signing_key = SigningKey::from_slice(&private_key_buffer)?;
-        wallet = LocalWallet::from(signing_key);
-    } else {
-        wallet = anvil.keys()[0].clone().into();
-    }
+    // Instantiate the signer
+    let signer = match private_key {
+        Some(source) => {
+            let source = source.parse::<SignerSource>()?;
+            EthSigner::connect(&source, &provider, chain_id).await?
+        }
+        None => EthSigner::Local(LocalWallet::from(anvil.keys()[0].clone())),
+    };
+    info!("signing transactions as {:#x}", signer.address());
 
     // Instantiate the client with the signer
-    let client = Arc::new(SignerMiddleware::new(
-        provider,
-        wallet.with_chain_id(chain_id),
-    ));
+    let client = Arc::new(EthClient::new(provider, signer.with_chain_id(chain_id)));
 
     Ok((anvil, client))
 }
 
+/// Where the transactions of deployments are signed, parsed from the `--private-key` (or
+/// `--signer`) flag of the deploy commands:
+///
+/// - a secp256k1 private key, in hex, 64 chars, without 0x prefix;
+/// - `ledger` or `ledger:<index>`, the account at `index` of the Ledger Live derivation path of a
+///   connected Ledger, with the `ledger` feature;
+/// - `aws-kms:<key id>`, a secp256k1 key held in AWS KMS, with the region and credentials of the
+///   environment, with the `aws-kms` feature;
+/// - `rpc` or `rpc:<address>`, an account held by the node (its first account by default), to
+///   which transactions are sent unsigned with `eth_sendTransaction`, as for keys kept in clef or
+///   a custody provider's node.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignerSource {
+    /// A secp256k1 private key, in hex
+    PrivateKey(String),
+    /// The index of an account of a Ledger
+    Ledger(usize),
+    /// The id of a key in AWS KMS
+    AwsKms(String),
+    /// An account held by the node
+    Rpc(Option<H160>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl std::str::FromStr for SignerSource {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+        match (kind, arg) {
+            ("ledger", None) => Ok(SignerSource::Ledger(0)),
+            ("ledger", Some(index)) => Ok(SignerSource::Ledger(index.parse()?)),
+            ("aws-kms", Some(key_id)) if !key_id.is_empty() => {
+                Ok(SignerSource::AwsKms(key_id.to_string()))
+            }
+            ("aws-kms", _) => Err("aws-kms signers need a key id, as in aws-kms:<key id>".into()),
+            ("rpc", None) => Ok(SignerSource::Rpc(None)),
+            ("rpc", Some(address)) => Ok(SignerSource::Rpc(Some(address.parse()?))),
+            (key, None) if key.len() == 64 && hex::decode(key).is_ok() => {
+                Ok(SignerSource::PrivateKey(key.to_string()))
+            }
+            _ => Err(
+                "Private key must be in hex format, 64 chars, without 0x prefix, or be one of ledger[:<index>], aws-kms:<key id> or rpc[:<address>]"
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// The errors of an [EthSigner].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, thiserror::Error)]
+pub enum EthSignerError {
+    /// A local wallet failed to sign
+    #[error(transparent)]
+    Wallet(#[from] ethers::signers::WalletError),
+    /// A Ledger failed to sign
+    #[cfg(feature = "ledger")]
+    #[error(transparent)]
+    Ledger(#[from] ethers::signers::LedgerError),
+    /// AWS KMS failed to sign
+    #[cfg(feature = "aws-kms")]
+    #[error(transparent)]
+    Aws(#[from] ethers::signers::AwsSignerError),
+    /// The node failed to sign
+    #[error(transparent)]
+    Rpc(#[from] ethers::providers::ProviderError),
+    /// The signer can't sign this kind of payload
+    #[error("{0} signers can't sign {1}")]
+    Unsupported(&'static str, &'static str),
+}
+
+/// An account held by the node, signing messages with its `eth_sign` method. Its transactions are
+/// sent to the node unsigned, see [EthClient].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct RpcSigner {
+    provider: Provider<Http>,
+    address: H160,
+    chain_id: u64,
+}
+
+/// The signers of deployments, see [SignerSource].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub enum EthSigner {
+    /// A private key held in memory
+    Local(LocalWallet),
+    /// A Ledger
+    #[cfg(feature = "ledger")]
+    Ledger(ethers::signers::Ledger),
+    /// A key held in AWS KMS
+    #[cfg(feature = "aws-kms")]
+    Aws(ethers::signers::AwsSigner),
+    /// An account held by the node
+    Rpc(RpcSigner),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EthSigner {
+    /// Connects to the signer of `source` for `chain_id`.
+    pub async fn connect(
+        source: &SignerSource,
+        provider: &Provider<Http>,
+        chain_id: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(match source {
+            SignerSource::PrivateKey(private_key) => {
+                let signing_key = SigningKey::from_slice(&hex::decode(private_key)?)?;
+                EthSigner::Local(LocalWallet::from(signing_key))
+            }
+            #[cfg(feature = "ledger")]
+            SignerSource::Ledger(index) => {
+                let path = ethers::signers::HDPath::LedgerLive(*index);
+                EthSigner::Ledger(ethers::signers::Ledger::new(path, chain_id).await?)
+            }
+            #[cfg(not(feature = "ledger"))]
+            SignerSource::Ledger(_) => {
+                return Err("ledger signers need ezkl built with the ledger feature".into())
+            }
+            #[cfg(feature = "aws-kms")]
+            SignerSource::AwsKms(key_id) => {
+                let kms = rusoto_kms::KmsClient::new(rusoto_core::Region::default());
+                EthSigner::Aws(ethers::signers::AwsSigner::new(kms, key_id, chain_id).await?)
+            }
+            #[cfg(not(feature = "aws-kms"))]
+            SignerSource::AwsKms(_) => {
+                return Err("aws-kms signers need ezkl built with the aws-kms feature".into())
+            }
+            SignerSource::Rpc(address) => {
+                let address = match address {
+                    Some(address) => *address,
+                    None => *provider
+                        .get_accounts()
+                        .await?
+                        .first()
+                        .ok_or("the node holds no accounts to sign with")?,
+                };
+                EthSigner::Rpc(RpcSigner {
+                    provider: provider.clone(),
+                    address,
+                    chain_id,
+                })
+            }
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait::async_trait]
+impl Signer for EthSigner {
+    type Error = EthSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<ethers::types::Signature, Self::Error> {
+        Ok(match self {
+            EthSigner::Local(wallet) => wallet.sign_message(message).await?,
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => ledger.sign_message(message).await?,
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => aws.sign_message(message).await?,
+            EthSigner::Rpc(rpc) => {
+                let message = Bytes::from(message.as_ref().to_vec());
+                rpc.provider.sign(message, &rpc.address).await?
+            }
+        })
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+    ) -> Result<ethers::types::Signature, Self::Error> {
+        Ok(match self {
+            EthSigner::Local(wallet) => wallet.sign_transaction(tx).await?,
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => ledger.sign_transaction(tx).await?,
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => aws.sign_transaction(tx).await?,
+            // the node signs the transactions it is sent, see [EthClient]
+            EthSigner::Rpc(_) => return Err(EthSignerError::Unsupported("rpc", "transactions")),
+        })
+    }
+
+    async fn sign_typed_data<T: ethers::types::transaction::eip712::Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<ethers::types::Signature, Self::Error> {
+        Ok(match self {
+            EthSigner::Local(wallet) => wallet.sign_typed_data(payload).await?,
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => ledger.sign_typed_data(payload).await?,
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => aws.sign_typed_data(payload).await?,
+            EthSigner::Rpc(_) => return Err(EthSignerError::Unsupported("rpc", "typed data")),
+        })
+    }
+
+    fn address(&self) -> H160 {
+        match self {
+            EthSigner::Local(wallet) => wallet.address(),
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => ledger.address(),
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => aws.address(),
+            EthSigner::Rpc(rpc) => rpc.address,
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            EthSigner::Local(wallet) => wallet.chain_id(),
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => ledger.chain_id(),
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => aws.chain_id(),
+            EthSigner::Rpc(rpc) => rpc.chain_id,
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            EthSigner::Local(wallet) => EthSigner::Local(wallet.with_chain_id(chain_id)),
+            #[cfg(feature = "ledger")]
+            EthSigner::Ledger(ledger) => EthSigner::Ledger(ledger.with_chain_id(chain_id)),
+            #[cfg(feature = "aws-kms")]
+            EthSigner::Aws(aws) => EthSigner::Aws(aws.with_chain_id(chain_id)),
+            EthSigner::Rpc(rpc) => EthSigner::Rpc(RpcSigner {
+                chain_id: chain_id.into(),
+                ..rpc
+            }),
+        }
+    }
+}
+
+/// A client signing with an [EthSigner]. Transactions of [EthSigner::Rpc] accounts are sent to the
+/// node unsigned with `eth_sendTransaction`, for the node to sign them, the others are signed
+/// locally and sent raw.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct EthClient(SignerMiddleware<Provider<Http>, EthSigner>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EthClient {
+    /// A client for `provider` signing with `signer`
+    pub fn new(provider: Provider<Http>, signer: EthSigner) -> Self {
+        EthClient(SignerMiddleware::new(provider, signer))
+    }
+
+    /// The address transactions are sent from
+    pub fn address(&self) -> H160 {
+        self.0.address()
+    }
+
+    /// The signer of the client
+    pub fn signer(&self) -> &EthSigner {
+        self.0.signer()
+    }
+}
+
+/// The errors of an [EthClient].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct EthClientError(
+    #[from] ethers::middleware::signer::SignerMiddlewareError<Provider<Http>, EthSigner>,
+);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ethers::providers::MiddlewareError for EthClientError {
+    type Inner = ethers::middleware::signer::SignerMiddlewareError<Provider<Http>, EthSigner>;
+
+    fn from_err(e: Self::Inner) -> Self {
+        EthClientError(e)
+    }
+
+    fn as_inner(&self) -> Option<&Self::Inner> {
+        Some(&self.0)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait::async_trait]
+impl Middleware for EthClient {
+    type Error = EthClientError;
+    type Provider = Http;
+    type Inner = SignerMiddleware<Provider<Http>, EthSigner>;
+
+    fn inner(&self) -> &Self::Inner {
+        &self.0
+    }
+
+    async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
+        &self,
+        tx: T,
+        block: Option<ethers::types::BlockId>,
+    ) -> Result<ethers::providers::PendingTransaction<'_, Http>, Self::Error> {
+        if !matches!(self.signer(), EthSigner::Rpc(_)) {
+            return Ok(self.0.send_transaction(tx, block).await?);
+        }
+        let mut tx = tx.into();
+        tx.set_from(self.address());
+        self.0
+            .provider()
+            .send_transaction(tx, block)
+            .await
+            .map_err(|e| {
+                EthClientError(
+                    ethers::middleware::signer::SignerMiddlewareError::MiddlewareError(e),
+                )
+            })
+    }
+}
+
 ///
 pub async fn deploy_contract_via_solidity(
     sol_code_path: PathBuf,
@@ -122,6 +447,206 @@ pub async fn deploy_contract_via_solidity(
     Ok(addr)
 }
 
+/// Deploys the `EzklVerifierRegistry` of [VERIFIER_REGISTRY_SOL], a verifier whose address stays the same
+/// across model updates, pointed at a verifier rendered with its verifying key separate and the
+/// verifying key contract.
+pub async fn deploy_verifier_registry(
+    sol_code_path: PathBuf,
+    rpc_url: Option<&str>,
+    runs: usize,
+    private_key: Option<&str>,
+    verifier: H160,
+    vk: H160,
+) -> Result<ethers::types::Address, Box<dyn Error>> {
+    // anvil instance must be alive at least until the factory completes the deploy
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let (abi, bytecode, runtime_bytecode) =
+        get_contract_artifacts(sol_code_path, "EzklVerifierRegistry", runs)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client)?;
+    let contract = factory.deploy((verifier, vk))?.send().await?;
+    let addr = contract.address();
+
+    drop(anvil);
+    Ok(addr)
+}
+
+/// Points the verifier registry at `registry` at a new verifying key contract `vk`, and verifier
+/// if `verifier` is set (else the registry keeps its current verifier), returning the version of
+/// the registry proofs are now verified against. Only the owner of the registry can update it.
+pub async fn update_registry_vk(
+    registry: H160,
+    verifier: Option<H160>,
+    vk: H160,
+    rpc_url: Option<&str>,
+    private_key: Option<&str>,
+) -> Result<u64, Box<dyn Error>> {
+    use ethers::abi::{ParamType, Token};
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let from = client.address();
+    let call = |data: Vec<u8>| -> TypedTransaction {
+        TransactionRequest::default()
+            .to(registry)
+            .from(from)
+            .data(data)
+            .into()
+    };
+    let current_version = |client: EthersClient| async move {
+        let data = ethers::utils::id("currentVersion()").to_vec();
+        let result = client.call(&call(data), None).await?;
+        let version = ethers::abi::decode(&[ParamType::Uint(256)], &result)?
+            .pop()
+            .and_then(|t| t.into_uint())
+            .ok_or("malformed registry version")?;
+        Ok::<_, Box<dyn Error>>(version)
+    };
+
+    let verifier = match verifier {
+        Some(verifier) => verifier,
+        None => {
+            let version = current_version(client.clone()).await?;
+            let data = [
+                &ethers::utils::id("versions(uint256)")[..],
+                &ethers::abi::encode(&[Token::Uint(version)]),
+            ]
+            .concat();
+            let result = client.call(&call(data), None).await?;
+            ethers::abi::decode(&[ParamType::Address, ParamType::Address], &result)?
+                .swap_remove(0)
+                .into_address()
+                .ok_or("malformed registry version")?
+        }
+    };
+
+    let data = [
+        &ethers::utils::id("updateVerifyingKey(address,address)")[..],
+        &ethers::abi::encode(&[Token::Address(verifier), Token::Address(vk)]),
+    ]
+    .concat();
+    let receipt = client
+        .send_transaction(call(data), None)
+        .await?
+        .await?
+        .ok_or("the update of the registry was dropped")?;
+    if receipt.status != Some(1.into()) {
+        return Err(format!(
+            "the update of the registry reverted in transaction {:#x}",
+            receipt.transaction_hash
+        )
+        .into());
+    }
+    let version = current_version(client).await?.as_u64();
+
+    drop(anvil);
+    Ok(version)
+}
+
+/// Revokes `version` of the verifier registry at `registry`, so proofs no longer verify against
+/// it with `verifyProofAt`. The current version can't be revoked. Only the owner of the registry
+/// can revoke versions.
+pub async fn revoke_registry_version(
+    registry: H160,
+    version: u64,
+    rpc_url: Option<&str>,
+    private_key: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    use ethers::abi::Token;
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let data = [
+        &ethers::utils::id("revokeVersion(uint256)")[..],
+        &ethers::abi::encode(&[Token::Uint(version.into())]),
+    ]
+    .concat();
+    let tx: TypedTransaction = TransactionRequest::default()
+        .to(registry)
+        .from(client.address())
+        .data(data)
+        .into();
+    // surface the registry's revert reason, e.g for the current version
+    client.call(&tx, None).await?;
+    let receipt = client
+        .send_transaction(tx, None)
+        .await?
+        .await?
+        .ok_or("the revocation was dropped")?;
+    if receipt.status != Some(1.into()) {
+        return Err(format!(
+            "the revocation reverted in transaction {:#x}",
+            receipt.transaction_hash
+        )
+        .into());
+    }
+
+    drop(anvil);
+    Ok(())
+}
+
+/// The deterministic deployment proxy, at the same address on most EVM chains and on anvil,
+/// which deploys the init code past the 32 byte salt of its calldata with CREATE2.
+pub const CREATE2_DEPLOYER: H160 = H160([
+    0x4e, 0x59, 0xb4, 0x48, 0x47, 0xb3, 0x79, 0x57, 0x85, 0x88, 0x92, 0x0c, 0xa7, 0x8f, 0xbf, 0x26,
+    0xc0, 0xb4, 0x95, 0x6c,
+]);
+
+/// The CREATE2 salt of contracts verifying proofs for the serialized verification key
+/// `vk_bytes`: its keccak256 hash.
+pub fn vk_salt(vk_bytes: &[u8]) -> [u8; 32] {
+    ethers::utils::keccak256(vk_bytes)
+}
+
+/// The address the [CREATE2_DEPLOYER] deploys `init_code` salted with `salt` to, which only
+/// depends on the two so it is the same on every chain.
+pub fn create2_address(salt: [u8; 32], init_code: &[u8]) -> H160 {
+    ethers::utils::get_create2_address(CREATE2_DEPLOYER, salt, init_code)
+}
+
+/// Deploys a contract through the [CREATE2_DEPLOYER] at the address predicted by
+/// [create2_address]. A contract already deployed at that address is reused.
+pub async fn deploy_contract_via_create2(
+    sol_code_path: PathBuf,
+    rpc_url: Option<&str>,
+    runs: usize,
+    private_key: Option<&str>,
+    contract_name: &str,
+    salt: [u8; 32],
+) -> Result<ethers::types::Address, Box<dyn Error>> {
+    // anvil instance must be alive at least until the deployment completes
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let (_, bytecode, _) = get_contract_artifacts(sol_code_path, contract_name, runs)?;
+    let addr = create2_address(salt, &bytecode);
+
+    if !client.get_code(addr, None).await?.is_empty() {
+        info!("{} is already deployed at {:#?}", contract_name, addr);
+        drop(anvil);
+        return Ok(addr);
+    }
+    if client.get_code(CREATE2_DEPLOYER, None).await?.is_empty() {
+        return Err(format!(
+            "the CREATE2 deployer {:#?} isn't deployed on this chain",
+            CREATE2_DEPLOYER
+        )
+        .into());
+    }
+
+    let tx = TransactionRequest::new()
+        .to(CREATE2_DEPLOYER)
+        .data([salt.as_slice(), bytecode.as_ref()].concat());
+    let receipt = client
+        .send_transaction(tx, None)
+        .await?
+        .await?
+        .ok_or("the deployment transaction was dropped")?;
+    if receipt.status != Some(1.into()) || client.get_code(addr, None).await?.is_empty() {
+        return Err(format!("the CREATE2 deployment of {} failed", contract_name).into());
+    }
+
+    drop(anvil);
+    Ok(addr)
+}
+
 ///
 pub async fn deploy_da_verifier_via_solidity(
     settings_path: PathBuf,
@@ -136,6 +661,10 @@ pub async fn deploy_da_verifier_via_solidity(
     let input = GraphData::from_path(input)?;
 
     let settings = GraphSettings::load(&settings_path)?;
+    // the attested instances are located assuming every output shares a visibility
+    if !settings.run_args.output_destinations.is_empty() {
+        return Err("data attestation doesn't support per-output destinations".into());
+    }
 
     let mut scales: Vec<u32> = vec![];
     // The data that will be stored in the test contracts that will eventually be read from.
@@ -171,7 +700,8 @@ pub async fn deploy_da_verifier_via_solidity(
     let mut instance_idx = 0;
     let mut contract_instance_offset = 0;
 
-    if let DataSource::OnChain(source) = input.input_data {
+    if let DataSource::OnChain(mut source) = input.input_data {
+        source.resolve_contract_calls().await?;
         if settings.run_args.input_visibility.is_hashed_public() {
             // set scales 1.0
             scales.extend(vec![0; instance_shapes[instance_idx]]);
@@ -196,7 +726,8 @@ pub async fn deploy_da_verifier_via_solidity(
         }
     }
 
-    if let Some(DataSource::OnChain(source)) = input.output_data {
+    if let Some(DataSource::OnChain(mut source)) = input.output_data {
+        source.resolve_contract_calls().await?;
         if settings.run_args.output_visibility.is_hashed_public() {
             // set scales 1.0
             scales.extend(vec![0; instance_shapes[instance_idx]]);
@@ -213,7 +744,7 @@ pub async fn deploy_da_verifier_via_solidity(
         }
     }
 
-    let (contract_addresses, call_data, decimals) = if !calls_to_accounts.is_empty() {
+    let (contract_addresses, call_data, decimals, is_unsigned) = if !calls_to_accounts.is_empty() {
         parse_calls_to_accounts(calls_to_accounts)?
     } else {
         return Err("Data source for either input_data or output_data must be OnChain".into());
@@ -232,6 +763,7 @@ pub async fn deploy_da_verifier_via_solidity(
             contract_addresses,
             call_data,
             decimals,
+            is_unsigned,
             scales,
             contract_instance_offset as u32,
             client.address(),
@@ -243,7 +775,7 @@ pub async fn deploy_da_verifier_via_solidity(
     Ok(contract.address())
 }
 
-type ParsedCallsToAccount = (Vec<H160>, Vec<Vec<Bytes>>, Vec<Vec<U256>>);
+type ParsedCallsToAccount = (Vec<H160>, Vec<Vec<Bytes>>, Vec<Vec<U256>>, Vec<Vec<bool>>);
 
 fn parse_calls_to_accounts(
     calls_to_accounts: Vec<CallsToAccount>,
@@ -251,6 +783,7 @@ fn parse_calls_to_accounts(
     let mut contract_addresses = vec![];
     let mut call_data = vec![];
     let mut decimals: Vec<Vec<U256>> = vec![];
+    let mut is_unsigned = vec![];
     for (i, val) in calls_to_accounts.iter().enumerate() {
         let contract_address_bytes = hex::decode(val.address.clone())?;
         let contract_address = H160::from_slice(&contract_address_bytes);
@@ -262,8 +795,9 @@ fn parse_calls_to_accounts(
             call_data[i].push(ethers::types::Bytes::from(call_data_bytes));
             decimals[i].push(ethers::types::U256::from_dec_str(&decimal.to_string())?);
         }
+        is_unsigned.push(val.unsigned_calls().collect());
     }
-    Ok((contract_addresses, call_data, decimals))
+    Ok((contract_addresses, call_data, decimals, is_unsigned))
 }
 
 pub async fn update_account_calls(
@@ -276,19 +810,21 @@ pub async fn update_account_calls(
     // The data that will be stored in the test contracts that will eventually be read from.
     let mut calls_to_accounts = vec![];
 
-    if let DataSource::OnChain(source) = input.input_data {
+    if let DataSource::OnChain(mut source) = input.input_data {
+        source.resolve_contract_calls().await?;
         for call in source.calls {
             calls_to_accounts.push(call);
         }
     }
 
-    if let Some(DataSource::OnChain(source)) = input.output_data {
+    if let Some(DataSource::OnChain(mut source)) = input.output_data {
+        source.resolve_contract_calls().await?;
         for call in source.calls {
             calls_to_accounts.push(call);
         }
     }
 
-    let (contract_addresses, call_data, decimals) = if !calls_to_accounts.is_empty() {
+    let (contract_addresses, call_data, decimals, is_unsigned) = if !calls_to_accounts.is_empty() {
         parse_calls_to_accounts(calls_to_accounts)?
     } else {
         return Err("Data source for either input_data or output_data must be OnChain".into());
@@ -303,6 +839,7 @@ pub async fn update_account_calls(
             contract_addresses.clone(),
             call_data.clone(),
             decimals.clone(),
+            is_unsigned.clone(),
         )
         .send()
         .await?;
@@ -310,7 +847,10 @@ pub async fn update_account_calls(
     // Instantiate a different wallet
     let wallet: LocalWallet = anvil.keys()[1].clone().into();
 
-    let client = Arc::new(client.with_signer(wallet.with_chain_id(anvil.chain_id())));
+    let client = Arc::new(EthClient::new(
+        client.provider().clone(),
+        EthSigner::Local(wallet.with_chain_id(anvil.chain_id())),
+    ));
 
     // update contract signer with non admin account
     let contract = DataAttestation::new(addr, client.clone());
@@ -318,7 +858,7 @@ pub async fn update_account_calls(
     // call to update_account_calls should fail
 
     if (contract
-        .update_account_calls(contract_addresses, call_data, decimals)
+        .update_account_calls(contract_addresses, call_data, decimals, is_unsigned)
         .send()
         .await)
         .is_err()
@@ -331,6 +871,107 @@ pub async fn update_account_calls(
     Ok(())
 }
 
+/// The base gas cost of any transaction
+const TX_BASE_GAS: u64 = 21_000;
+
+/// The gas verifying a proof with a generated verifier costs, see [estimate_verify_gas].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GasEstimate {
+    /// The gas used deploying the verifier, and its verifying key if rendered separately
+    pub deployment: u64,
+    /// The size of the verify calldata, in bytes
+    pub calldata_bytes: usize,
+    /// The base cost of the verify transaction
+    pub intrinsic: u64,
+    /// The gas charged for the verify calldata
+    pub calldata: u64,
+    /// The gas spent executing the verifier
+    pub execution: u64,
+    /// The total gas of the verify transaction
+    pub total: u64,
+}
+
+/// The gas charged for transaction calldata, 4 per zero byte and 16 per nonzero byte (EIP-2028)
+pub fn calldata_gas(data: &[u8]) -> u64 {
+    data.iter().map(|b| if *b == 0 { 4 } else { 16 }).sum()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn deploy_measuring_gas(
+    sol_code_path: PathBuf,
+    contract_name: &str,
+    runs: usize,
+    client: EthersClient,
+) -> Result<(ethers::types::Address, u64), Box<dyn Error>> {
+    let (abi, bytecode, runtime_bytecode) =
+        get_contract_artifacts(sol_code_path, contract_name, runs)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client)?;
+    let (contract, receipt) = factory.deploy(())?.send_with_receipt().await?;
+    let gas = receipt
+        .gas_used
+        .ok_or("the deployment receipt has no gas used")?;
+    Ok((contract.address(), gas.as_u64()))
+}
+
+/// Deploys the verifier at `sol_code_path`, preceded by its verifying key at `vk_sol_path` if it
+/// was rendered separately, to a local anvil instance and estimates the gas of verifying `proof`
+/// with it, broken down into the calldata and the execution of the verifier.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn estimate_verify_gas(
+    proof: Snark<Fr, G1Affine>,
+    sol_code_path: PathBuf,
+    vk_sol_path: Option<PathBuf>,
+    runs: usize,
+) -> Result<GasEstimate, Box<dyn Error>> {
+    // the contracts are deployed to and called on the same anvil instance, which has to be kept
+    // alive until the end
+    let (anvil, client) = setup_eth_backend(None, None).await?;
+
+    let mut deployment = 0;
+    let mut addr_vk = None;
+    if let Some(vk_sol_path) = vk_sol_path {
+        let (addr, gas) =
+            deploy_measuring_gas(vk_sol_path, "Halo2VerifyingKey", runs, client.clone()).await?;
+        addr_vk = Some(addr);
+        deployment += gas;
+    }
+    let (addr, gas) =
+        deploy_measuring_gas(sol_code_path, "Halo2Verifier", runs, client.clone()).await?;
+    deployment += gas;
+
+    let encoded = encode_calldata(
+        addr_vk.map(|addr| addr.0),
+        &proof.proof,
+        &proof.instances.concat(),
+    );
+    let calldata_bytes = encoded.len();
+    let calldata = calldata_gas(&encoded);
+    let tx: TypedTransaction = TransactionRequest::default()
+        .to(addr)
+        .from(client.address())
+        .data(encoded)
+        .into();
+
+    let result = client
+        .call(&tx, None)
+        .await
+        .map_err(|_| EvmVerificationError::SolidityExecution)?;
+    if result.to_vec().last() != Some(&1u8) {
+        return Err(Box::new(EvmVerificationError::InvalidProof));
+    }
+    let total = client.estimate_gas(&tx, None).await?.as_u64();
+
+    drop(anvil);
+    Ok(GasEstimate {
+        deployment,
+        calldata_bytes,
+        intrinsic: TX_BASE_GAS,
+        calldata,
+        execution: total.saturating_sub(TX_BASE_GAS + calldata),
+        total,
+    })
+}
+
 /// Verify a proof using a Solidity verifier contract
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn verify_proof_via_solidity(
@@ -560,69 +1201,990 @@ pub async fn test_on_chain_data<M: 'static + Middleware>(
     let calls_to_account = CallsToAccount {
         call_data: calldata,
         address: hex::encode(contract.address().as_bytes()),
+        is_unsigned: vec![],
     };
     info!("calls_to_account: {:#?}", calls_to_account);
     Ok(vec![calls_to_account])
 }
 
-/// Reads on-chain inputs, returning the raw encoded data returned from making all the calls in on_chain_input_data
+/// On-chain reads a quorum of rpcs agreed on, see [read_on_chain_inputs_with_quorum]
 #[cfg(not(target_arch = "wasm32"))]
-pub async fn read_on_chain_inputs<M: 'static + Middleware>(
-    client: Arc<M>,
-    address: H160,
-    data: &Vec<CallsToAccount>,
-) -> Result<(Vec<Bytes>, Vec<u8>), Box<dyn Error>> {
-    // Iterate over all on-chain inputs
-    let mut fetched_inputs = vec![];
-    let mut decimals = vec![];
-    for on_chain_data in data {
-        // Construct the address
-        let contract_address_bytes = hex::decode(on_chain_data.address.clone())?;
-        let contract_address = H160::from_slice(&contract_address_bytes);
-        for (call_data, decimal) in &on_chain_data.call_data {
-            let call_data_bytes = hex::decode(call_data.clone())?;
-            let tx: TypedTransaction = TransactionRequest::default()
-                .to(contract_address)
-                .from(address)
-                .data(call_data_bytes)
-                .into();
-            debug!("transaction {:#?}", tx);
+pub struct QuorumRead {
+    /// The last rpc of the quorum
+    pub rpc: String,
+    /// A client connected to `rpc`, unless it is a websocket rpc
+    pub client: Option<EthersClient>,
+    /// The block the reads were pinned to
+    pub block_number: ethers::types::U64,
+    /// The raw encoded data returned by the calls, and their decimals
+    pub inputs: (Vec<Bytes>, Vec<u8>),
+}
 
-            let result = client.call(&tx, None).await?;
-            debug!("return data {:#?}", result);
-            fetched_inputs.push(result);
-            decimals.push(*decimal);
+/// Whether `rpc` is a websocket rpc, which is read through a [Ws] provider.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_ws_rpc(rpc: &str) -> bool {
+    rpc.starts_with("ws://") || rpc.starts_with("wss://")
+}
+
+/// Waits for the chain behind `provider` to reach `block`, subscribing to its new blocks.
+#[cfg(not(target_arch = "wasm32"))]
+async fn wait_for_block(
+    provider: &Provider<Ws>,
+    block: ethers::types::U64,
+) -> Result<(), Box<dyn Error>> {
+    if provider.get_block_number().await? >= block {
+        return Ok(());
+    }
+    info!("waiting for block {}", block);
+    let mut blocks = provider.subscribe_blocks().await?;
+    while let Some(head) = blocks.next().await {
+        if head.number.is_some_and(|n| n >= block) {
+            return Ok(());
         }
     }
-    Ok((fetched_inputs, decimals))
+    Err("the subscription to new blocks ended".into())
 }
 
-///
+/// Reads `data` from `rpc` at `block`, or at its latest block, retrying up to `retries` times
+/// with exponential backoff starting at `backoff`.
 #[cfg(not(target_arch = "wasm32"))]
-pub async fn evm_quantize<M: 'static + Middleware>(
-    client: Arc<M>,
-    scales: Vec<crate::Scale>,
-    data: &(Vec<ethers::types::Bytes>, Vec<u8>),
-) -> Result<Vec<Fr>, Box<dyn Error>> {
-    // save the sol to a tmp file
-    let mut sol_path = std::env::temp_dir();
-    sol_path.push("quantizedata.sol");
-    std::fs::write(&sol_path, QUANTIZE_DATA_SOL)?;
-
-    let (abi, bytecode, runtime_bytecode) = get_contract_artifacts(sol_path, "QuantizeData", 0)?;
-    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client.clone())?;
+async fn read_on_chain_inputs_with_retries(
+    rpc: &str,
+    data: &Vec<CallsToAccount>,
+    block: Option<ethers::types::U64>,
+    retries: usize,
+    backoff: Duration,
+) -> Result<QuorumRead, Box<dyn Error>> {
+    // the http client is set up once, only the reads are retried
+    let client = if is_ws_rpc(rpc) {
+        None
+    } else {
+        Some(setup_eth_backend(Some(rpc), None).await?.1)
+    };
+    let mut attempt = 0;
+    loop {
+        let read = async {
+            let Some(client) = &client else {
+                let provider = Provider::<Ws>::connect(rpc).await?;
+                let block_number = match block {
+                    Some(block) => {
+                        wait_for_block(&provider, block).await?;
+                        block
+                    }
+                    None => provider.get_block_number().await?,
+                };
+                let inputs = read_on_chain_inputs(
+                    Arc::new(provider),
+                    H160::zero(),
+                    data,
+                    Some(block_number.into()),
+                )
+                .await?;
+                return Ok(QuorumRead {
+                    rpc: rpc.to_string(),
+                    client: None,
+                    block_number,
+                    inputs,
+                });
+            };
+            let block_number = match block {
+                Some(block) => block,
+                None => client.get_block_number().await?,
+            };
+            let inputs = read_on_chain_inputs(
+                client.clone(),
+                client.address(),
+                data,
+                Some(block_number.into()),
+            )
+            .await?;
+            Ok::<_, Box<dyn Error>>(QuorumRead {
+                rpc: rpc.to_string(),
+                client: Some(client.clone()),
+                block_number,
+                inputs,
+            })
+        }
+        .await;
+        let err = match read {
+            Ok(read) => return Ok(read),
+            Err(e) => e.to_string(),
+        };
+        if attempt >= retries {
+            return Err(err.into());
+        }
+        attempt += 1;
+        warn!(
+            "reading from rpc {} failed ({}), retry {}/{}",
+            rpc, err, attempt, retries
+        );
+        tokio::time::sleep(backoff * (1_u32 << (attempt - 1).min(8))).await;
+    }
+}
 
-    let contract = factory.deploy(())?.send().await?;
+/// Reads `data` from `rpcs` in order until `quorum` of them read the same values, failing over
+/// from any rpc that still fails after `retries` retries, waited for with exponential backoff
+/// starting at `backoff`. The reads are pinned to `block`, or else to the block the first rpc to
+/// respond is at, so rpcs lagging behind it fail over too. Rpcs can be http or websocket rpcs.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn read_on_chain_inputs_with_quorum(
+    rpcs: &[&str],
+    data: &Vec<CallsToAccount>,
+    block: Option<ethers::types::U64>,
+    quorum: usize,
+    retries: usize,
+    backoff: Duration,
+) -> Result<QuorumRead, Box<dyn Error>> {
+    let quorum = quorum.max(1);
+    if rpcs.len() < quorum {
+        return Err(format!(
+            "a quorum of {} rpcs was requested but only {} are configured",
+            quorum,
+            rpcs.len()
+        )
+        .into());
+    }
 
-    let contract = QuantizeData::new(contract.address(), client.clone());
+    let mut block = block;
+    let mut reads: Vec<QuorumRead> = vec![];
+    for rpc in rpcs {
+        let read = match read_on_chain_inputs_with_retries(rpc, data, block, retries, backoff).await
+        {
+            Ok(read) => read,
+            Err(e) => {
+                warn!("failing over from rpc {}: {}", rpc, e);
+                continue;
+            }
+        };
+        block = Some(read.block_number);
+        let agreeing = reads.iter().filter(|r| r.inputs == read.inputs).count() + 1;
+        if agreeing <= reads.len() {
+            warn!(
+                "rpc {} disagrees with {} other rpcs on the on-chain reads at block {}",
+                rpc,
+                reads.len() + 1 - agreeing,
+                read.block_number
+            );
+        }
+        if agreeing >= quorum {
+            debug!("{} rpcs agreed on the on-chain reads", agreeing);
+            return Ok(read);
+        }
+        reads.push(read);
+    }
 
-    let fetched_inputs = data.0.clone();
-    let decimals = data.1.clone();
+    Err(format!(
+        "fewer than {} of the {} rpcs agreed on the on-chain reads",
+        quorum,
+        rpcs.len()
+    )
+    .into())
+}
 
-    let fetched_inputs = fetched_inputs
-        .iter()
-        .map(|x| Result::<_, std::convert::Infallible>::Ok(ethers::types::Bytes::from(x.to_vec())))
-        .collect::<Result<Vec<Bytes>, _>>()?;
+/// Splits `bytes` into nibbles, most significant first.
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Walks the Merkle-Patricia `proof` of `key` down from `root`, returning the value stored at the
+/// key, or `None` when the proof shows there is none.
+fn verify_trie_proof(
+    root: ethers::types::H256,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    use ethers::utils::rlp::Rlp;
+
+    let path = nibbles(&ethers::utils::keccak256(key));
+    let mut nodes = proof.iter();
+    let mut next_node = |hash: &[u8]| -> Result<Vec<u8>, Box<dyn Error>> {
+        let node = nodes.next().ok_or("the trie proof ends before the key")?;
+        if ethers::utils::keccak256(node).as_slice() != hash {
+            return Err("a node of the trie proof does not match its hash".into());
+        }
+        Ok(node.clone())
+    };
+
+    let mut node = next_node(root.as_bytes())?;
+    let mut pos = 0;
+    loop {
+        let rlp = Rlp::new(&node);
+        let child = match rlp.item_count()? {
+            // branch node
+            17 => {
+                if pos == path.len() {
+                    let value = rlp.at(16)?.data()?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                }
+                pos += 1;
+                rlp.at(path[pos - 1] as usize)?
+            }
+            // extension or leaf node, with a hex prefix encoded path
+            2 => {
+                let encoded = rlp.at(0)?.data()?;
+                let flag = encoded.first().ok_or("a trie node has an empty path")? >> 4;
+                let partial = &nibbles(encoded)[if flag & 1 == 1 { 1 } else { 2 }..];
+                if !path[pos..].starts_with(partial) {
+                    return Ok(None);
+                }
+                pos += partial.len();
+                if flag & 2 == 2 {
+                    if pos != path.len() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(rlp.at(1)?.data()?.to_vec()));
+                }
+                rlp.at(1)?
+            }
+            _ => return Err("malformed trie node".into()),
+        };
+        // children shorter than a hash are inlined in their parent
+        node = if child.is_list() {
+            child.as_raw().to_vec()
+        } else {
+            let hash = child.data()?;
+            if hash.is_empty() {
+                return Ok(None);
+            }
+            next_node(hash)?
+        };
+    }
+}
+
+/// Checks the EIP-1186 proofs of every account of `proof` against its state root, and the proofs
+/// of their storage slots against their storage roots. The state root itself is only as trusted
+/// as the rpcs it was read from, third parties check it against the block's header.
+pub fn verify_block_state_proof(proof: &BlockStateProof) -> Result<(), Box<dyn Error>> {
+    use ethers::types::H256;
+    use ethers::utils::keccak256;
+    use ethers::utils::rlp::{Rlp, RlpStream, NULL_RLP};
+
+    let parse_u256 = |value: &str| {
+        U256::from_str_radix(value.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("{} is not a hex encoded integer", value))
+    };
+    let parse_nodes = |nodes: &[String]| {
+        nodes
+            .iter()
+            .map(|n| hex::decode(n.trim_start_matches("0x")))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let empty_root = H256(keccak256(NULL_RLP));
+    let empty_code = H256(keccak256([0u8; 0]));
+
+    let state_root: H256 = proof.state_root.parse()?;
+    for account in &proof.accounts {
+        let address: H160 = account.address.parse()?;
+        let balance = parse_u256(&account.balance)?;
+        let storage_hash: H256 = account.storage_hash.parse()?;
+        let code_hash: H256 = account.code_hash.parse()?;
+        let mut expected = RlpStream::new_list(4);
+        expected
+            .append(&account.nonce)
+            .append(&balance)
+            .append(&storage_hash)
+            .append(&code_hash);
+        let is_empty = account.nonce == 0
+            && balance.is_zero()
+            && storage_hash == empty_root
+            && code_hash == empty_code;
+        match verify_trie_proof(
+            state_root,
+            address.as_bytes(),
+            &parse_nodes(&account.account_proof)?,
+        )? {
+            Some(value) if value == expected.out().to_vec() => {}
+            None if is_empty => {}
+            _ => {
+                return Err(format!(
+                    "the proof of account {} does not match the state root {}",
+                    account.address, proof.state_root
+                )
+                .into())
+            }
+        }
+
+        for slot in &account.storage {
+            let mut key = [0u8; 32];
+            parse_u256(&slot.key)?.to_big_endian(&mut key);
+            let value = parse_u256(&slot.value)?;
+            let proven = match verify_trie_proof(storage_hash, &key, &parse_nodes(&slot.proof)?)? {
+                Some(stored) => Rlp::new(&stored).as_val::<U256>()?,
+                None => U256::zero(),
+            };
+            if proven != value {
+                return Err(format!(
+                    "the proof of slot {} of account {} does not match its storage root",
+                    slot.key, account.address
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The storage slots `calls` read at `block`, from their access lists (`eth_createAccessList`),
+/// along with the `extra` slots.
+#[cfg(not(target_arch = "wasm32"))]
+async fn storage_slots_read<M: 'static + Middleware>(
+    client: &M,
+    calls: &[CallsToAccount],
+    extra: &[StorageSlots],
+    block: ethers::types::BlockId,
+) -> Result<std::collections::BTreeMap<H160, Vec<ethers::types::H256>>, Box<dyn Error>> {
+    let mut slots: std::collections::BTreeMap<H160, Vec<ethers::types::H256>> = Default::default();
+    let mut add = |address: H160, key: ethers::types::H256| {
+        let keys = slots.entry(address).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    };
+    for account in calls {
+        let address: H160 = account.address.parse()?;
+        for (call_data, _) in &account.call_data {
+            let tx: TypedTransaction = TransactionRequest::default()
+                .to(address)
+                .data(hex::decode(call_data.trim_start_matches("0x"))?)
+                .into();
+            let accessed = client.create_access_list(&tx, Some(block)).await?;
+            for item in accessed.access_list.0 {
+                for key in item.storage_keys {
+                    add(item.address, key);
+                }
+            }
+        }
+    }
+    for account in extra {
+        let address: H160 = account.address.parse()?;
+        for key in &account.slots {
+            let bytes = hex::decode(key.trim_start_matches("0x"))?;
+            if bytes.len() > 32 {
+                return Err(format!("storage key {} is longer than 32 bytes", key).into());
+            }
+            let mut padded = [0u8; 32];
+            padded[32 - bytes.len()..].copy_from_slice(&bytes);
+            add(address, ethers::types::H256(padded));
+        }
+    }
+    Ok(slots)
+}
+
+/// Fetches the EIP-1186 proofs at `block` of the storage slots `calls` read and of the `extra`
+/// slots, along with the header they are proven against, and checks them against its state root.
+#[cfg(not(target_arch = "wasm32"))]
+async fn get_block_state_proof<M: 'static + Middleware>(
+    client: &M,
+    calls: &[CallsToAccount],
+    extra: &[StorageSlots],
+    block: ethers::types::U64,
+) -> Result<BlockStateProof, Box<dyn Error>> {
+    let block_id = ethers::types::BlockId::from(block);
+    let header = client
+        .get_block(block_id)
+        .await?
+        .ok_or_else(|| format!("block {} not found", block))?;
+
+    let mut accounts = vec![];
+    for (address, keys) in storage_slots_read(client, calls, extra, block_id).await? {
+        let proof = client.get_proof(address, keys, Some(block_id)).await?;
+        let to_hex = |b: &Bytes| format!("0x{}", hex::encode(b));
+        accounts.push(AccountStateProof {
+            address: format!("{:#x}", proof.address),
+            balance: format!("{:#x}", proof.balance),
+            nonce: proof.nonce.as_u64(),
+            code_hash: format!("{:#x}", proof.code_hash),
+            storage_hash: format!("{:#x}", proof.storage_hash),
+            account_proof: proof.account_proof.iter().map(to_hex).collect(),
+            storage: proof
+                .storage_proof
+                .iter()
+                .map(|slot| StorageSlotProof {
+                    key: format!("{:#x}", slot.key),
+                    value: format!("{:#x}", slot.value),
+                    proof: slot.proof.iter().map(to_hex).collect(),
+                })
+                .collect(),
+        });
+    }
+
+    let proof = BlockStateProof {
+        block_number: block.as_u64(),
+        block_hash: format!(
+            "{:#x}",
+            header
+                .hash
+                .ok_or_else(|| format!("block {} has no hash", block))?
+        ),
+        state_root: format!("{:#x}", header.state_root),
+        accounts,
+    };
+    verify_block_state_proof(&proof)?;
+    Ok(proof)
+}
+
+/// Fetches the storage proofs of `calls` from `rpc`, see [get_block_state_proof], retrying up to
+/// `retries` times with exponential backoff starting at `backoff`.
+#[cfg(not(target_arch = "wasm32"))]
+async fn get_block_state_proof_with_retries(
+    rpc: &str,
+    calls: &[CallsToAccount],
+    extra: &[StorageSlots],
+    block: ethers::types::U64,
+    retries: usize,
+    backoff: Duration,
+) -> Result<BlockStateProof, Box<dyn Error>> {
+    let mut attempt = 0;
+    loop {
+        let proof = if is_ws_rpc(rpc) {
+            match Provider::<Ws>::connect(rpc).await {
+                Ok(provider) => get_block_state_proof(&provider, calls, extra, block).await,
+                Err(e) => Err(e.into()),
+            }
+        } else {
+            match Provider::<Http>::try_from(rpc) {
+                Ok(provider) => get_block_state_proof(&provider, calls, extra, block).await,
+                Err(e) => Err(e.into()),
+            }
+        };
+        let err = match proof {
+            Ok(proof) => return Ok(proof),
+            Err(e) => e.to_string(),
+        };
+        if attempt >= retries {
+            return Err(err.into());
+        }
+        attempt += 1;
+        warn!(
+            "fetching storage proofs from rpc {} failed ({}), retry {}/{}",
+            rpc, err, attempt, retries
+        );
+        tokio::time::sleep(backoff * (1_u32 << (attempt - 1).min(8))).await;
+    }
+}
+
+/// Fetches the EIP-1186 proofs of the storage slots the calls of `source` read, at the block its
+/// reads are pinned to, from its rpcs in order until `quorum` of them return the same proofs (see
+/// [read_on_chain_inputs_with_quorum]). The proofs are recorded in the source's snapshot, and
+/// replayed from it without querying the rpcs when the source replays its snapshot.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn fetch_block_state_proof(
+    source: &OnChainSource,
+) -> Result<BlockStateProof, Box<dyn Error>> {
+    use crate::graph::input::OnChainSnapshot;
+
+    let snapshot_path = source.snapshot.as_ref().map(PathBuf::from);
+    if let Some(path) = snapshot_path
+        .as_ref()
+        .filter(|p| source.replay_snapshot && p.exists())
+    {
+        let proof = OnChainSnapshot::from_path(path)?
+            .state_proof
+            .ok_or_else(|| {
+                format!(
+                    "on-chain snapshot {} records no storage proofs, delete it to read them again",
+                    path.display()
+                )
+            })?;
+        verify_block_state_proof(&proof)?;
+        return Ok(proof);
+    }
+
+    let block: ethers::types::U64 = source
+        .block_number
+        .ok_or("storage proofs need the on-chain reads pinned to a block, set `block_number`")?
+        .into();
+    let calls = source.encoded_calls()?;
+    let rpcs = source.rpcs();
+    let quorum = source.quorum.max(1);
+    if rpcs.len() < quorum {
+        return Err(format!(
+            "a quorum of {} rpcs was requested but only {} are configured",
+            quorum,
+            rpcs.len()
+        )
+        .into());
+    }
+
+    let mut proofs: Vec<BlockStateProof> = vec![];
+    for rpc in &rpcs {
+        let proof = match get_block_state_proof_with_retries(
+            rpc,
+            &calls,
+            &source.storage_proofs,
+            block,
+            source.retries,
+            Duration::from_millis(source.retry_backoff_ms),
+        )
+        .await
+        {
+            Ok(proof) => proof,
+            Err(e) => {
+                warn!("failing over from rpc {}: {}", rpc, e);
+                continue;
+            }
+        };
+        let agreeing = proofs.iter().filter(|p| **p == proof).count() + 1;
+        if agreeing <= proofs.len() {
+            warn!(
+                "rpc {} disagrees with {} other rpcs on the storage proofs at block {}",
+                rpc,
+                proofs.len() + 1 - agreeing,
+                block
+            );
+        }
+        if agreeing >= quorum {
+            debug!("{} rpcs agreed on the storage proofs", agreeing);
+            if let Some(path) = snapshot_path.as_ref().filter(|p| p.exists()) {
+                let mut snapshot = OnChainSnapshot::from_path(path)?;
+                snapshot.state_proof = Some(proof.clone());
+                snapshot.save(path)?;
+            }
+            return Ok(proof);
+        }
+        proofs.push(proof);
+    }
+
+    Err(format!(
+        "fewer than {} of the {} rpcs agreed on the storage proofs",
+        quorum,
+        rpcs.len()
+    )
+    .into())
+}
+
+/// The address Multicall3 is deployed at on most chains, see <https://github.com/mds1/multicall>
+pub const MULTICALL3_ADDRESS: &str = "cA11bde05977b3631167028862bE2a173976CA11";
+
+/// Encodes the calldata of Multicall3's `aggregate3` making `calls`, none of which may fail.
+fn encode_aggregate3(calls: &[(H160, Vec<u8>)]) -> Vec<u8> {
+    use ethers::abi::Token;
+    let calls = calls
+        .iter()
+        .map(|(target, data)| {
+            Token::Tuple(vec![
+                Token::Address(*target),
+                Token::Bool(false),
+                Token::Bytes(data.clone()),
+            ])
+        })
+        .collect();
+    [
+        &ethers::utils::id("aggregate3((address,bool,bytes)[])")[..],
+        &ethers::abi::encode(&[Token::Array(calls)]),
+    ]
+    .concat()
+}
+
+/// Decodes the data returned by `aggregate3` making `num_calls` calls.
+fn decode_aggregate3(data: &[u8], num_calls: usize) -> Result<Vec<Bytes>, Box<dyn Error>> {
+    use ethers::abi::{ParamType, Token};
+    let result = ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes]);
+    let mut decoded = ethers::abi::decode(&[ParamType::Array(Box::new(result))], data)?;
+    let Some(Token::Array(results)) = decoded.pop() else {
+        return Err("malformed multicall return data".into());
+    };
+    if results.len() != num_calls {
+        return Err(format!(
+            "multicall returned {} results for {} calls",
+            results.len(),
+            num_calls
+        )
+        .into());
+    }
+    results
+        .into_iter()
+        .map(|result| match result {
+            Token::Tuple(fields) => match &fields[..] {
+                [Token::Bool(true), Token::Bytes(data)] => Ok(Bytes::from(data.clone())),
+                _ => Err("a multicall call failed".into()),
+            },
+            _ => Err("malformed multicall return data".into()),
+        })
+        .collect()
+}
+
+/// Reads on-chain inputs, returning the raw encoded data returned from making all the calls in on_chain_input_data.
+/// The calls are batched into a single `eth_call` through Multicall3, so they read the same block in one round trip,
+/// falling back to making them one by one on chains without Multicall3, on nodes without state overrides or if any of
+/// them fails. Multicall3's code is run from `address` through a state override, so the calls see `address` as their
+/// `msg.sender` as they would if made one by one. The reads are pinned to `block`, or else to the latest block.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn read_on_chain_inputs<M: 'static + Middleware>(
+    client: Arc<M>,
+    address: H160,
+    data: &Vec<CallsToAccount>,
+    block: Option<ethers::types::BlockId>,
+) -> Result<(Vec<Bytes>, Vec<u8>), Box<dyn Error>> {
+    // Iterate over all on-chain inputs
+    let mut calls = vec![];
+    let mut decimals = vec![];
+    for on_chain_data in data {
+        // Construct the address
+        let contract_address_bytes = hex::decode(on_chain_data.address.clone())?;
+        let contract_address = H160::from_slice(&contract_address_bytes);
+        for (call_data, decimal) in &on_chain_data.call_data {
+            calls.push((contract_address, hex::decode(call_data.clone())?));
+            decimals.push(*decimal);
+        }
+    }
+
+    // the calls made one by one after a failed multicall read the same block
+    let block = match block {
+        Some(block) => block,
+        None => client.get_block_number().await?.into(),
+    };
+
+    if calls.len() > 1 {
+        let batched: Result<Vec<Bytes>, Box<dyn Error>> = async {
+            use ethers::providers::call_raw::{spoof, RawCall};
+            let multicall = H160::from_slice(&hex::decode(MULTICALL3_ADDRESS)?);
+            let code = client.get_code(multicall, Some(block)).await?;
+            if code.is_empty() {
+                return Err("Multicall3 isn't deployed".into());
+            }
+            let mut state = spoof::state();
+            state.account(address).code(code);
+            let tx: TypedTransaction = TransactionRequest::default()
+                .to(address)
+                .from(address)
+                .data(encode_aggregate3(&calls))
+                .into();
+            let result = client
+                .provider()
+                .call_raw(&tx)
+                .block(block)
+                .state(&state)
+                .await?;
+            decode_aggregate3(&result, calls.len())
+        }
+        .await;
+        match batched {
+            Ok(fetched_inputs) => {
+                debug!("read {} on-chain inputs with multicall", calls.len());
+                return Ok((fetched_inputs, decimals));
+            }
+            Err(e) => debug!("multicall failed ({}), making the calls one by one", e),
+        }
+    }
+
+    let mut fetched_inputs = vec![];
+    for (contract_address, call_data) in calls {
+        let tx: TypedTransaction = TransactionRequest::default()
+            .to(contract_address)
+            .from(address)
+            .data(call_data)
+            .into();
+        debug!("transaction {:#?}", tx);
+
+        let result = client.call(&tx, Some(block)).await?;
+        debug!("return data {:#?}", result);
+        fetched_inputs.push(result);
+    }
+    Ok((fetched_inputs, decimals))
+}
+
+/// ERC-165 interface id of ERC-721
+const ERC721_INTERFACE_ID: [u8; 4] = [0x80, 0xac, 0x58, 0xcd];
+
+/// The return type of the views of ERC-20 and ERC-721 tokens, by canonical signature, and whether
+/// they return an amount of the token, whose decimals are the token's.
+fn standard_view(signature: &str) -> Option<(ethers::abi::ParamType, bool)> {
+    use ethers::abi::ParamType;
+    match signature {
+        "totalSupply()" | "balanceOf(address)" | "allowance(address,address)" => {
+            Some((ParamType::Uint(256), true))
+        }
+        "decimals()" => Some((ParamType::Uint(8), false)),
+        _ => None,
+    }
+}
+
+/// A [ContractCall] ABI encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedContractCall {
+    /// The canonical signature of the function, e.g `balanceOf(address)`
+    pub signature: String,
+    /// The selector of the function followed by the encoded arguments
+    pub call_data: Vec<u8>,
+    /// The type of the value returned
+    pub returns: ethers::abi::ParamType,
+}
+
+/// ABI encodes a call specified by its signature, inferring its return type for the standard
+/// token views and checking it can be quantized.
+pub fn encode_contract_call(call: &ContractCall) -> Result<EncodedContractCall, Box<dyn Error>> {
+    use ethers::abi::param_type::Reader;
+    use ethers::abi::token::{LenientTokenizer, Tokenizer};
+    use ethers::abi::ParamType;
+
+    let malformed = || format!("{} is not a function signature", call.signature);
+    let signature = call.signature.replace(' ', "");
+    let (name, rest) = signature.split_once('(').ok_or_else(malformed)?;
+    let (inputs, returns) = rest.split_once(')').ok_or_else(malformed)?;
+    let read_types = |types: &str| -> Result<Vec<ParamType>, Box<dyn Error>> {
+        if types.is_empty() {
+            return Ok(vec![]);
+        }
+        types.split(',').map(|t| Ok(Reader::read(t)?)).collect()
+    };
+
+    let inputs = read_types(inputs)?;
+    let canonical = format!(
+        "{}({})",
+        name,
+        inputs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let returns = if returns.is_empty() {
+        standard_view(&canonical)
+            .map(|(returns, _)| returns)
+            .ok_or_else(|| {
+                format!(
+                    "can't infer the return type of {}, append it to the signature, e.g `{}(int256)`",
+                    canonical, canonical
+                )
+            })?
+    } else {
+        let returns = returns
+            .strip_prefix('(')
+            .and_then(|r| r.strip_suffix(')'))
+            .ok_or_else(malformed)?;
+        match read_types(returns)?.as_slice() {
+            [returns] => returns.clone(),
+            _ => return Err(format!("{} must return a single value", canonical).into()),
+        }
+    };
+    if !matches!(
+        returns,
+        ParamType::Int(_) | ParamType::Uint(_) | ParamType::Bool
+    ) {
+        return Err(format!(
+            "{} returns {}, only integers and bools can be quantized",
+            canonical, returns
+        )
+        .into());
+    }
+
+    if call.args.len() != inputs.len() {
+        return Err(format!(
+            "{} takes {} arguments but {} were given",
+            canonical,
+            inputs.len(),
+            call.args.len()
+        )
+        .into());
+    }
+    let tokens = inputs
+        .iter()
+        .zip(&call.args)
+        .map(|(kind, arg)| LenientTokenizer::tokenize(kind, arg))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut call_data = ethers::utils::id(&canonical).to_vec();
+    call_data.extend(ethers::abi::encode(&tokens));
+    Ok(EncodedContractCall {
+        signature: canonical,
+        call_data,
+        returns,
+    })
+}
+
+/// The decimals of the amounts of a token: none for ERC-721 tokens, which count tokens and are
+/// recognized through ERC-165, and the `decimals()` of ERC-20 tokens.
+async fn token_decimals<M: Middleware>(
+    client: &M,
+    token: H160,
+    block: Option<ethers::types::BlockId>,
+) -> Result<u8, Box<dyn Error>> {
+    use ethers::abi::Token;
+    let call = |data: Vec<u8>| -> TypedTransaction {
+        TransactionRequest::default().to(token).data(data).into()
+    };
+
+    let mut supports_erc721 = ethers::utils::id("supportsInterface(bytes4)").to_vec();
+    supports_erc721.extend(ethers::abi::encode(&[Token::FixedBytes(
+        ERC721_INTERFACE_ID.to_vec(),
+    )]));
+    // tokens without ERC-165 revert, and are taken to be ERC-20 tokens
+    if let Ok(result) = client.call(&call(supports_erc721), block).await {
+        if result.len() == 32 && result[31] == 1 {
+            return Ok(0);
+        }
+    }
+
+    let result = client
+        .call(&call(ethers::utils::id("decimals()").to_vec()), block)
+        .await
+        .map_err(|e| format!("could not read the decimals of token {:#x}: {}", token, e))?;
+    if result.len() != 32 || result[..31].iter().any(|b| *b != 0) {
+        return Err(format!("token {:#x} returned malformed decimals", token).into());
+    }
+    Ok(result[31])
+}
+
+/// Reads the decimals of `token` (see [token_decimals]) at the block the reads of `source` are
+/// pinned to, from its rpcs in order until `quorum` of them agree, failing over from any rpc that
+/// still fails after the source's retries (see [read_on_chain_inputs_with_quorum]).
+async fn token_decimals_with_quorum(
+    source: &OnChainSource,
+    token: H160,
+) -> Result<u8, Box<dyn Error>> {
+    let block = source
+        .block_number
+        .map(|b| ethers::types::BlockId::from(ethers::types::U64::from(b)));
+    let rpcs = source.rpcs();
+    let quorum = source.quorum.max(1);
+    if rpcs.len() < quorum {
+        return Err(format!(
+            "a quorum of {} rpcs was requested but only {} are configured",
+            quorum,
+            rpcs.len()
+        )
+        .into());
+    }
+    let backoff = Duration::from_millis(source.retry_backoff_ms);
+
+    let mut reads: Vec<u8> = vec![];
+    for rpc in &rpcs {
+        let mut attempt = 0;
+        let read = loop {
+            let read = async {
+                if is_ws_rpc(rpc) {
+                    let provider = Provider::<Ws>::connect(*rpc).await?;
+                    token_decimals(&provider, token, block).await
+                } else {
+                    let provider = Provider::<Http>::try_from(*rpc)?;
+                    token_decimals(&provider, token, block).await
+                }
+            }
+            .await
+            .map_err(|e| e.to_string());
+            match read {
+                Ok(decimals) => break Ok(decimals),
+                Err(e) if attempt >= source.retries => break Err(e),
+                Err(e) => {
+                    attempt += 1;
+                    warn!(
+                        "reading decimals from rpc {} failed ({}), retry {}/{}",
+                        rpc, e, attempt, source.retries
+                    );
+                    tokio::time::sleep(backoff * (1_u32 << (attempt - 1).min(8))).await;
+                }
+            }
+        };
+        let decimals = match read {
+            Ok(decimals) => decimals,
+            Err(e) => {
+                warn!("failing over from rpc {}: {}", rpc, e);
+                continue;
+            }
+        };
+        let agreeing = reads.iter().filter(|d| **d == decimals).count() + 1;
+        if agreeing <= reads.len() {
+            warn!(
+                "rpc {} disagrees with {} other rpcs on the decimals of token {:#x}",
+                rpc,
+                reads.len() + 1 - agreeing,
+                token
+            );
+        }
+        if agreeing >= quorum {
+            return Ok(decimals);
+        }
+        reads.push(decimals);
+    }
+
+    Err(format!(
+        "fewer than {} of the {} rpcs agreed on the decimals of token {:#x}",
+        quorum,
+        rpcs.len(),
+        token
+    )
+    .into())
+}
+
+/// The most decimals a call can have, `10 ** 78` overflows a `uint256`.
+const MAX_DECIMALS: u8 = 77;
+
+/// Resolves the `contract_calls` of `source`, specified by their signature, into ABI encoded
+/// [CallsToAccount], one per call. Calls without decimals must be standard token views, whose
+/// decimals are read from the token through the rpcs of the source (see
+/// [token_decimals_with_quorum]) for amounts and are none otherwise. Calls returning unsigned
+/// integers are marked as such (see [CallsToAccount::is_unsigned]), so they are quantized as
+/// `uint256`.
+pub async fn resolve_contract_calls(
+    source: &OnChainSource,
+) -> Result<Vec<CallsToAccount>, Box<dyn Error>> {
+    let mut resolved = vec![];
+    for call in &source.contract_calls {
+        let encoded = encode_contract_call(call)?;
+        let address = call.address.parse::<H160>()?;
+        let decimals = match (call.decimals, standard_view(&encoded.signature)) {
+            (Some(decimals), _) => decimals,
+            (None, Some((_, true))) => token_decimals_with_quorum(source, address).await?,
+            (None, Some((_, false))) => 0,
+            (None, None) => {
+                return Err(format!(
+                    "can't infer the decimals of {} on {}, set them",
+                    encoded.signature, call.address
+                )
+                .into())
+            }
+        };
+        if decimals > MAX_DECIMALS {
+            return Err(format!(
+                "{} on {} has {} decimals, more than 10 ** decimals can hold",
+                encoded.signature, call.address, decimals
+            )
+            .into());
+        }
+        debug!(
+            "resolved {} on {:#x} returning {} with {} decimals",
+            encoded.signature, address, encoded.returns, decimals
+        );
+        // the quantizing contracts decode the result as the type it is returned as
+        let is_unsigned = matches!(encoded.returns, ethers::abi::ParamType::Uint(_));
+        resolved.push(CallsToAccount {
+            call_data: vec![(hex::encode(encoded.call_data), decimals)],
+            address: hex::encode(address),
+            is_unsigned: vec![is_unsigned],
+        });
+    }
+    Ok(resolved)
+}
+
+/// Quantizes the raw encoded results of on-chain calls and their decimals at `scales` with the
+/// QuantizeData contract, decoding those whose `is_unsigned` flag is set as `uint256`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn evm_quantize<M: 'static + Middleware>(
+    client: Arc<M>,
+    scales: Vec<crate::Scale>,
+    data: &(Vec<ethers::types::Bytes>, Vec<u8>),
+    is_unsigned: &[bool],
+) -> Result<Vec<Fr>, Box<dyn Error>> {
+    // save the sol to a tmp file
+    let mut sol_path = std::env::temp_dir();
+    sol_path.push("quantizedata.sol");
+    std::fs::write(&sol_path, QUANTIZE_DATA_SOL)?;
+
+    let (abi, bytecode, runtime_bytecode) = get_contract_artifacts(sol_path, "QuantizeData", 0)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client.clone())?;
+
+    let contract = factory.deploy(())?.send().await?;
+
+    let contract = QuantizeData::new(contract.address(), client.clone());
+
+    let fetched_inputs = data.0.clone();
+    let decimals = data.1.clone();
+
+    let fetched_inputs = fetched_inputs
+        .iter()
+        .map(|x| Result::<_, std::convert::Infallible>::Ok(ethers::types::Bytes::from(x.to_vec())))
+        .collect::<Result<Vec<Bytes>, _>>()?;
 
     let decimals = decimals
         .iter()
@@ -639,11 +2201,18 @@ pub async fn evm_quantize<M: 'static + Middleware>(
     info!("fetched_inputs: {:#?}", fetched_inputs);
 
     let results = contract
-        .quantize_data(fetched_inputs, decimals, scales)
+        .quantize_data(fetched_inputs, decimals, scales, is_unsigned.to_vec())
         .call()
         .await?;
 
-    let felts = contract.to_field_element(results.clone()).call().await?;
+    // to_field_element keeps its int128[] overload for existing callers
+    let felts: Vec<U256> = contract
+        .method_hash(
+            ethers::utils::id("to_field_element(int256[])"),
+            results.clone(),
+        )?
+        .call()
+        .await?;
     info!("evm quantization contract results: {:#?}", felts,);
 
     let results = felts
@@ -654,6 +2223,508 @@ pub async fn evm_quantize<M: 'static + Middleware>(
     Ok(results.to_vec())
 }
 
+/// Sets the values of the constants of a Solidity template, each found by its declaration up to
+/// the `=`.
+fn set_sol_constants(
+    template: &str,
+    constants: &[(&str, String)],
+) -> Result<String, Box<dyn Error>> {
+    let mut sol = template.to_string();
+    for (constant, value) in constants {
+        let start = sol
+            .find(constant)
+            .ok_or_else(|| format!("the template lacks `{}`", constant))?
+            + constant.len();
+        let end = start + sol[start..].find(';').ok_or("unterminated constant")?;
+        sol.replace_range(start..end, value);
+    }
+    Ok(sol)
+}
+
+/// Generates `EzklProofPaymaster`, an ERC-4337 paymaster sponsoring the user operations carrying a
+/// valid proof of the circuit of `settings`, verified by a Halo2Verifier with at most
+/// `verify_gas_limit` gas, and exposing the public outputs of the model to postOp. The proof of an
+/// operation is bound to it through the caller instance (see [user_operation_binding]), so the
+/// circuit must be generated with --bind-caller.
+pub fn paymaster_sol(
+    settings: &GraphSettings,
+    verify_gas_limit: u64,
+) -> Result<String, Box<dyn Error>> {
+    let run_args = &settings.run_args;
+    if !run_args.bind_caller {
+        return Err("the paymaster binds proofs to user operations through the caller instance, generate the settings with --bind-caller".into());
+    }
+    let instance_sizes = settings
+        .model_instance_shapes
+        .iter()
+        .map(|shape| shape.iter().product::<usize>())
+        .collect::<Vec<_>>();
+    // the model's public inputs come first, then its public outputs
+    let num_inputs = if run_args.input_visibility.is_public() {
+        settings.model_input_scales.len()
+    } else {
+        0
+    };
+    let output_offset = instance_sizes[..num_inputs.min(instance_sizes.len())]
+        .iter()
+        .sum::<usize>();
+    let output_len = if run_args.output_visibility.is_public() {
+        instance_sizes[num_inputs.min(instance_sizes.len())..]
+            .iter()
+            .sum::<usize>()
+    } else {
+        0
+    };
+    let num_instances = settings.total_instances().iter().sum::<usize>();
+
+    set_sol_constants(
+        PAYMASTER_SOL,
+        &[
+            (
+                "uint256 internal constant NUM_INSTANCES = ",
+                num_instances.to_string(),
+            ),
+            (
+                "uint256 internal constant OUTPUT_OFFSET = ",
+                output_offset.to_string(),
+            ),
+            (
+                "uint256 internal constant OUTPUT_LEN = ",
+                output_len.to_string(),
+            ),
+            (
+                "uint256 internal constant VERIFY_GAS_LIMIT = ",
+                verify_gas_limit.to_string(),
+            ),
+        ],
+    )
+}
+
+/// Deploys the paymaster of [paymaster_sol] for the ERC-4337 entry point at `entry_point`,
+/// verifying proofs with the verifier at `verifier`.
+pub async fn deploy_paymaster_via_solidity(
+    sol_code_path: PathBuf,
+    rpc_url: Option<&str>,
+    runs: usize,
+    private_key: Option<&str>,
+    entry_point: H160,
+    verifier: H160,
+) -> Result<ethers::types::Address, Box<dyn Error>> {
+    // anvil instance must be alive at least until the factory completes the deploy
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let (abi, bytecode, runtime_bytecode) =
+        get_contract_artifacts(sol_code_path, "EzklProofPaymaster", runs)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client)?;
+    let contract = factory.deploy((entry_point, verifier))?.send().await?;
+    let addr = contract.address();
+
+    drop(anvil);
+    Ok(addr)
+}
+
+/// The value the caller instance of a proof sponsoring the user operation of `sender` with `nonce`
+/// and `call_data` must hold for the paymaster of [paymaster_sol]: the keccak256 of
+/// `abi.encode(sender, nonce, keccak256(call_data))`, reduced into the field.
+pub fn user_operation_binding(sender: &H160, nonce: U256, call_data: &[u8]) -> Fr {
+    use ethers::abi::Token;
+    let hash = ethers::utils::keccak256(ethers::abi::encode(&[
+        Token::Address(*sender),
+        Token::Uint(nonce),
+        Token::FixedBytes(ethers::utils::keccak256(call_data).to_vec()),
+    ]));
+    let order = U256::from_dec_str(
+        "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+    )
+    .unwrap();
+    let mut repr = [0u8; 32];
+    (U256::from_big_endian(&hash) % order).to_little_endian(&mut repr);
+    // the reduced hash is below the order so it is always in the field
+    Fr::from_repr(repr).unwrap()
+}
+
+/// The paymaster data of a user operation sponsored by the paymaster of [paymaster_sol] with
+/// `proof`, which follows the paymaster and its gas limits in `paymasterAndData`.
+pub fn paymaster_data(proof: &Snark<Fr, G1Affine>) -> Vec<u8> {
+    use ethers::abi::Token;
+    let instances = proof
+        .instances
+        .iter()
+        .flatten()
+        .map(|i| Token::Uint(U256::from_little_endian(i.to_repr().as_ref())))
+        .collect();
+    ethers::abi::encode(&[Token::Bytes(proof.proof.clone()), Token::Array(instances)])
+}
+
+/// The number of field elements of an EIP-4844 blob
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+/// The size of an EIP-4844 blob, in bytes
+pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * 32;
+/// The BLS12-381 scalar field modulus, which the field elements of blobs are reduced by
+const BLS_MODULUS: &str =
+    "52435875175126190479447740508185965837690552500527637822603658699938581184513";
+/// A primitive 4096th root of unity of the BLS12-381 scalar field, 7^((BLS_MODULUS - 1) / 4096)
+const BLOB_ROOT_OF_UNITY: &str =
+    "39033254847818212395286706435128746857159659164139250548781411570340225835782";
+
+/// The field element of a blob holding `proof`, which the blob verifier checks the posted proof
+/// against: its keccak256 hash, less its last byte so it stays below the BLS12-381 modulus.
+pub fn proof_hash_element(proof: &[u8]) -> [u8; 32] {
+    let mut element = [0u8; 32];
+    element[1..].copy_from_slice(&ethers::utils::keccak256(proof)[..31]);
+    element
+}
+
+/// Lays a proof out in an EIP-4844 blob: its instances, one per field element (BN254 elements are
+/// smaller than the BLS12-381 modulus), then the hash of the proof (see [proof_hash_element]),
+/// then the length of the proof, then the bytes of the proof, 31 to a field element so every
+/// element stays below the modulus.
+pub fn proof_to_blob(instances: &[Fr], proof: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let num_elements = instances.len() + 2 + proof.len().div_ceil(31);
+    if num_elements > FIELD_ELEMENTS_PER_BLOB {
+        return Err(format!(
+            "the proof takes {} field elements, more than the {} of a blob",
+            num_elements, FIELD_ELEMENTS_PER_BLOB
+        )
+        .into());
+    }
+    let mut blob = Vec::with_capacity(BYTES_PER_BLOB);
+    for instance in instances {
+        blob.extend(instance.to_repr().as_ref().iter().rev());
+    }
+    blob.extend(proof_hash_element(proof));
+    let mut len = [0u8; 32];
+    U256::from(proof.len()).to_big_endian(&mut len);
+    blob.extend(len);
+    for chunk in proof.chunks(31) {
+        blob.push(0);
+        blob.extend(chunk);
+        blob.extend(std::iter::repeat(0).take(31 - chunk.len()));
+    }
+    blob.resize(BYTES_PER_BLOB, 0);
+    Ok(blob)
+}
+
+/// Reads the instances and the bytes of a proof out of a blob laid out by [proof_to_blob], checking
+/// the bytes against the hash the blob verifier checked.
+pub fn blob_to_proof(
+    blob: &[u8],
+    num_instances: usize,
+) -> Result<(Vec<Fr>, Vec<u8>), Box<dyn Error>> {
+    if blob.len() != BYTES_PER_BLOB || num_instances + 1 >= FIELD_ELEMENTS_PER_BLOB {
+        return Err("not a blob of a proof".into());
+    }
+    let mut elements = blob.chunks(32);
+    let instances = elements
+        .by_ref()
+        .take(num_instances)
+        .map(|element| {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            repr.as_mut()
+                .copy_from_slice(&element.iter().rev().copied().collect::<Vec<_>>());
+            Option::from(Fr::from_repr(repr)).ok_or("an instance isn't a field element")
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let hash = elements.next().ok_or("not a blob of a proof")?.to_vec();
+    let len = U256::from_big_endian(elements.next().ok_or("not a blob of a proof")?);
+    if len > U256::from(elements.len() * 31) {
+        return Err("the proof is longer than the blob".into());
+    }
+    let mut proof = elements.flat_map(|e| e[1..].to_vec()).collect::<Vec<_>>();
+    proof.truncate(len.as_usize());
+    if hash != proof_hash_element(&proof) {
+        return Err("the proof doesn't match its hash in the blob".into());
+    }
+    Ok((instances, proof))
+}
+
+/// The point the field element at `index` of a blob is the evaluation of the blob's polynomial
+/// at: the 4096th roots of unity, in bit reversed order.
+pub fn blob_evaluation_point(index: usize) -> U256 {
+    let modulus = U256::from_dec_str(BLS_MODULUS).unwrap();
+    let mul_mod = |a: U256, b: U256| {
+        U256::try_from(a.full_mul(b) % ethers::types::U512::from(modulus)).unwrap()
+    };
+    let exponent = (index as u32).reverse_bits() >> (32 - FIELD_ELEMENTS_PER_BLOB.ilog2());
+    let mut base = U256::from_dec_str(BLOB_ROOT_OF_UNITY).unwrap();
+    let mut point = U256::one();
+    for bit in 0..FIELD_ELEMENTS_PER_BLOB.ilog2() {
+        if exponent >> bit & 1 == 1 {
+            point = mul_mod(point, base);
+        }
+        base = mul_mod(base, base);
+    }
+    point
+}
+
+/// Generates `EzklBlobVerifier`, which verifies the proofs of the circuit of `settings` posted as
+/// EIP-4844 blobs by `post_proof_blob` and records their instances, checking the instances and
+/// the hash of the proof against the blob's commitment.
+pub fn blob_verifier_sol(settings: &GraphSettings) -> Result<String, Box<dyn Error>> {
+    let num_instances = settings.total_instances().iter().sum::<usize>();
+    set_sol_constants(
+        BLOB_VERIFIER_SOL,
+        &[(
+            "uint256 internal constant NUM_INSTANCES = ",
+            num_instances.to_string(),
+        )],
+    )
+}
+
+/// Deploys the blob verifier of [blob_verifier_sol], verifying proofs with the verifier at
+/// `verifier`.
+pub async fn deploy_blob_verifier_via_solidity(
+    sol_code_path: PathBuf,
+    rpc_url: Option<&str>,
+    runs: usize,
+    private_key: Option<&str>,
+    verifier: H160,
+) -> Result<ethers::types::Address, Box<dyn Error>> {
+    // anvil instance must be alive at least until the factory completes the deploy
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+
+    let (abi, bytecode, runtime_bytecode) =
+        get_contract_artifacts(sol_code_path, "EzklBlobVerifier", runs)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client)?;
+    let contract = factory.deploy(verifier)?.send().await?;
+    let addr = contract.address();
+
+    drop(anvil);
+    Ok(addr)
+}
+
+/// Posts `proof` as an EIP-4844 blob, laid out by [proof_to_blob], in a transaction calling
+/// `postProof` on the `EzklBlobVerifier` at `verifier`, returning the blob's versioned hash. The
+/// KZG commitment and proofs of the blob are computed with the trusted setup of Ethereum's KZG
+/// ceremony at `trusted_setup_path`. Blob transactions are signed with a private key.
+#[cfg(feature = "blobs")]
+pub async fn post_proof_blob(
+    proof: Snark<Fr, G1Affine>,
+    verifier: H160,
+    trusted_setup_path: &Path,
+    rpc_url: Option<&str>,
+    private_key: Option<&str>,
+) -> Result<ethers::types::H256, Box<dyn Error>> {
+    use ethers::abi::Token;
+    use ethers::utils::rlp::RlpStream;
+    let kzg_err = |e: c_kzg::Error| format!("kzg: {:?}", e);
+
+    let instances = proof
+        .instances
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    let blob_bytes = proof_to_blob(&instances, &proof.proof)?;
+    let settings =
+        c_kzg::KzgSettings::load_trusted_setup_file(trusted_setup_path).map_err(kzg_err)?;
+    let blob = c_kzg::Blob::from_bytes(&blob_bytes).map_err(kzg_err)?;
+    let commitment = c_kzg::KzgCommitment::blob_to_kzg_commitment(&blob, &settings)
+        .map_err(kzg_err)?
+        .to_bytes();
+    let blob_proof = c_kzg::KzgProof::compute_blob_kzg_proof(&blob, &commitment, &settings)
+        .map_err(kzg_err)?
+        .to_bytes();
+    let mut versioned_hash =
+        ethers::types::H256::from_slice(&hex::decode(sha256::digest(&commitment[..]))?);
+    versioned_hash.0[0] = 0x01;
+
+    // the kzg proofs that the first field elements of the blob are the instances and the hash
+    let elements = instances
+        .iter()
+        .map(|i| {
+            i.to_repr()
+                .as_ref()
+                .iter()
+                .rev()
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .chain(std::iter::once(proof_hash_element(&proof.proof).to_vec()));
+    let mut kzg_proofs = vec![];
+    for (i, element) in elements.enumerate() {
+        let mut point = [0u8; 32];
+        blob_evaluation_point(i).to_big_endian(&mut point);
+        let point = c_kzg::Bytes32::from_bytes(&point).map_err(kzg_err)?;
+        let (kzg_proof, value) =
+            c_kzg::KzgProof::compute_kzg_proof(&blob, &point, &settings).map_err(kzg_err)?;
+        if value[..] != element[..] {
+            return Err(format!("field element {} of the blob isn't the posted one", i).into());
+        }
+        kzg_proofs.push(Token::Bytes(kzg_proof.to_bytes()[..].to_vec()));
+    }
+    let calldata = [
+        &ethers::utils::id("postProof(bytes,uint256[],bytes,bytes[])")[..],
+        &ethers::abi::encode(&[
+            Token::Bytes(proof.proof.clone()),
+            Token::Array(
+                instances
+                    .iter()
+                    .map(|i| Token::Uint(U256::from_little_endian(i.to_repr().as_ref())))
+                    .collect(),
+            ),
+            Token::Bytes(commitment[..].to_vec()),
+            Token::Array(kzg_proofs),
+        ]),
+    ]
+    .concat();
+
+    let (anvil, client) = setup_eth_backend(rpc_url, private_key).await?;
+    let EthSigner::Local(wallet) = client.signer() else {
+        return Err("blob transactions can only be signed with a private key".into());
+    };
+    let chain_id = client.get_chainid().await?;
+    let nonce = client
+        .get_transaction_count(
+            client.address(),
+            Some(ethers::types::BlockNumber::Pending.into()),
+        )
+        .await?;
+    let (max_fee, max_priority_fee) = client.estimate_eip1559_fees(None).await?;
+    let blob_base_fee: U256 = client.provider().request("eth_blobBaseFee", ()).await?;
+    let max_fee_per_blob_gas = (blob_base_fee * 2).max(U256::one());
+    // the blob can't be simulated, so the gas limit covers verifying the proof, with room to
+    // spare, and the point evaluations, which cost 50000 gas each
+    let gas_limit = U256::from(3_000_000 + 60_000 * (instances.len() + 1));
+
+    let append_fields = |rlp: &mut RlpStream| {
+        rlp.append(&chain_id);
+        rlp.append(&nonce);
+        rlp.append(&max_priority_fee);
+        rlp.append(&max_fee);
+        rlp.append(&gas_limit);
+        rlp.append(&verifier);
+        rlp.append(&U256::zero());
+        rlp.append(&Bytes::from(calldata.clone()));
+        rlp.begin_list(0);
+        rlp.append(&max_fee_per_blob_gas);
+        rlp.begin_list(1);
+        rlp.append(&versioned_hash);
+    };
+    let mut unsigned = RlpStream::new_list(11);
+    append_fields(&mut unsigned);
+    let sighash = ethers::utils::keccak256([&[0x03], &unsigned.out()[..]].concat());
+    let signature = wallet.sign_hash(sighash.into())?;
+
+    let mut signed = RlpStream::new_list(14);
+    append_fields(&mut signed);
+    signed.append(&(signature.v - 27));
+    signed.append(&signature.r);
+    signed.append(&signature.s);
+
+    // the network form of the transaction carries the blob, its commitment and its proof
+    let mut wrapper = RlpStream::new_list(4);
+    wrapper.append_raw(&signed.out(), 1);
+    wrapper.begin_list(1);
+    wrapper.append(&Bytes::from(blob_bytes));
+    wrapper.begin_list(1);
+    wrapper.append(&Bytes::from(commitment[..].to_vec()));
+    wrapper.begin_list(1);
+    wrapper.append(&Bytes::from(blob_proof[..].to_vec()));
+    let raw = Bytes::from([&[0x03], &wrapper.out()[..]].concat());
+
+    let receipt = client
+        .send_raw_transaction(raw)
+        .await?
+        .await?
+        .ok_or("the blob transaction was dropped")?;
+    if receipt.status != Some(1.into()) {
+        return Err(format!(
+            "posting the proof reverted in transaction {:#x}",
+            receipt.transaction_hash
+        )
+        .into());
+    }
+    info!(
+        "posted the proof in blob {:#x} in transaction {:#x}",
+        versioned_hash, receipt.transaction_hash
+    );
+
+    drop(anvil);
+    Ok(versioned_hash)
+}
+
+/// Generates a standalone Solidity library, `EzklQuantizer`, quantizing on-chain fixed point
+/// values at `scale` as [crate::fieldutils::quantize_float] does, and reverting for quantized
+/// values that don't fit in `bits` bits. The library computes exactly, so it only agrees with
+/// [crate::fieldutils::quantize_float] where f64 is exact, see the library's doc.
+pub fn quantizer_sol(scale: crate::Scale, bits: u32) -> Result<String, Box<dyn Error>> {
+    if !(2..=128).contains(&bits) {
+        return Err(format!("quantized values take 2 to 128 bits, not {}", bits).into());
+    }
+    if !(-128..=128).contains(&scale) {
+        return Err(format!("the scale must lie in [-128, 128], not {}", scale).into());
+    }
+    let pow2 = |exp: u32| (U256::one() << exp).to_string();
+    let (numerator, denominator) = if scale >= 0 {
+        (pow2(scale as u32), pow2(0))
+    } else {
+        (pow2(0), pow2(scale.unsigned_abs()))
+    };
+    let max_value = (U256::one() << (bits - 1)) - 1;
+    let min_value = format!("-{}", pow2(bits - 1));
+
+    let sol = set_sol_constants(
+        QUANTIZER_SOL,
+        &[
+            ("uint256 internal constant SCALE_NUMERATOR = ", numerator),
+            (
+                "uint256 internal constant SCALE_DENOMINATOR = ",
+                denominator,
+            ),
+            (
+                "int256 internal constant MAX_VALUE = ",
+                max_value.to_string(),
+            ),
+            ("int256 internal constant MIN_VALUE = ", min_value),
+        ],
+    )?;
+    Ok(sol.replacen(
+        " * @notice Quantizes on-chain fixed point values",
+        &format!(
+            " * @notice Generated for scale {} and {} bit values.\n * @notice Quantizes on-chain fixed point values",
+            scale, bits
+        ),
+        1,
+    ))
+}
+
+/// Quantizes `values`, integers and their decimals, with the library of [quantizer_sol] deployed
+/// behind a harness contract on `rpc_url` (or a local anvil), to check it against
+/// [crate::fieldutils::quantize_float]. Values the library reverts on are `None`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn quantize_via_solidity(
+    scale: crate::Scale,
+    bits: u32,
+    values: &[(i128, u32)],
+    rpc_url: Option<&str>,
+) -> Result<Vec<Option<i128>>, Box<dyn Error>> {
+    let harness = r#"
+contract EzklQuantizerHarness {
+    function quantize(int256 value, uint256 decimals) external pure returns (int256) {
+        return EzklQuantizer.quantize(value, decimals);
+    }
+}
+"#;
+    let mut sol_path = std::env::temp_dir();
+    sol_path.push("ezklquantizerharness.sol");
+    std::fs::write(&sol_path, quantizer_sol(scale, bits)? + harness)?;
+
+    let (_anvil, client) = setup_eth_backend(rpc_url, None).await?;
+    let (abi, bytecode, runtime_bytecode) =
+        get_contract_artifacts(sol_path, "EzklQuantizerHarness", 0)?;
+    let factory = get_sol_contract_factory(abi, bytecode, runtime_bytecode, client)?;
+    let contract = factory.deploy(())?.send().await?;
+
+    let mut quantized = vec![];
+    for (value, decimals) in values {
+        let call =
+            contract.method::<_, I256>("quantize", (I256::from(*value), U256::from(*decimals)))?;
+        quantized.push(call.call().await.ok().map(|q| q.as_i128()));
+    }
+    Ok(quantized)
+}
+
 /// Generates the contract factory for a solidity verifier, optionally compiling the code with optimizer runs set on the Solc compiler.
 fn get_sol_contract_factory<M: 'static + Middleware>(
     abi: Contract,
@@ -708,6 +2779,31 @@ pub fn get_contract_artifacts(
     Ok((abi, bytecode, runtime_bytecode))
 }
 
+/// The largest runtime bytecode a contract can deploy, in bytes, see EIP-170
+pub const EIP170_CONTRACT_SIZE_LIMIT: usize = 0x6000;
+
+/// Fails if the runtime bytecode of the contract `name`, of `size` bytes, is over the EIP-170
+/// contract size limit.
+pub fn check_contract_size(name: &str, size: usize) -> Result<(), Box<dyn Error>> {
+    if size > EIP170_CONTRACT_SIZE_LIMIT {
+        return Err(format!(
+            "the {} is {} bytes, over the {} byte EIP-170 contract size limit, so it can't be deployed: try fewer instances or fewer columns",
+            name, size, EIP170_CONTRACT_SIZE_LIMIT
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Where the verifying key contract split out of a verifier at `sol_code_path` (with its abi at
+/// `abi_path`) is written, e.g `evm_deploy.vk.sol` and `verifier_abi.vk.json`.
+pub fn separate_vk_paths(sol_code_path: &Path, abi_path: &Path) -> (PathBuf, PathBuf) {
+    (
+        sol_code_path.with_extension("vk.sol"),
+        abi_path.with_extension("vk.json"),
+    )
+}
+
 /// Sets the constants stored in the da verifier
 pub fn fix_da_sol(
     input_data: Option<Vec<CallsToAccount>>,
@@ -747,3 +2843,680 @@ pub fn fix_da_sol(
 
     Ok(contract)
 }
+
+/// Encodes an address as the instance the evm verifier compares with `uint256(uint160(caller))`.
+pub fn address_to_field(address: &H160) -> Fr {
+    let mut repr = [0u8; 32];
+    // field elements are little endian, addresses big endian
+    for (i, byte) in address.as_bytes().iter().rev().enumerate() {
+        repr[i] = *byte;
+    }
+    // an address is 160 bits so it is always in the field
+    Fr::from_repr(repr).unwrap()
+}
+
+/// Adds helpers interpreting the count of valid slots marked by the validity mask (see
+/// [crate::RunArgs::mask_output]) to a rendered Halo2Verifier. `offset` is the index of the count
+/// in the public instances. The valid slots are the first `count` slots of the flattened padded
+/// outputs.
+pub fn valid_count_sol(verifier: &str, offset: usize) -> Result<String, Box<dyn Error>> {
+    let contract_end = verifier
+        .rfind('}')
+        .ok_or("could not find the end of the verifier contract")?;
+
+    let helpers = format!(
+        r#"
+    function validCount(uint256[] calldata instances) public pure returns (uint256) {{
+        require(instances.length > {offset}, "missing the count of valid slots");
+        return instances[{offset}];
+    }}
+
+    function isValidSlot(
+        uint256[] calldata instances,
+        uint256 slot
+    ) external pure returns (bool) {{
+        return slot < validCount(instances);
+    }}
+"#
+    );
+
+    Ok([
+        &verifier[..contract_end],
+        &helpers,
+        &verifier[contract_end..],
+    ]
+    .concat())
+}
+
+/// Binds a rendered Halo2Verifier to the caller, so that a proof generated for one account can't
+/// be replayed by another: the verifier is renamed `Halo2VerifierCore` and wrapped by the
+/// `Halo2Verifier` of contracts/CallerBoundVerifier.sol, which checks that the last instance is the
+/// caller before forwarding the proof to the core.
+pub fn bind_caller_sol(verifier: &str) -> Result<String, Box<dyn Error>> {
+    const CONTRACT: &str = "contract Halo2Verifier {";
+    if verifier.matches(CONTRACT).count() != 1 {
+        return Err("could not find the Halo2Verifier contract in the verifier".into());
+    }
+    let signature_start = verifier
+        .find("function verifyProof(")
+        .ok_or("could not find verifyProof in the verifier")?;
+    let params_end = signature_start
+        + verifier[signature_start..]
+            .find(')')
+            .ok_or("could not parse the verifyProof signature")?;
+
+    let mut wrapper = CALLER_BOUND_VERIFIER_SOL.to_string();
+    // a verifier rendered with a separate vk takes the vk address first
+    if verifier[signature_start..params_end].contains("address") {
+        wrapper = wrapper
+            .replace("bytes calldata proof", "address vk, bytes calldata proof")
+            .replace("(msg.sender, proof", "(msg.sender, vk, proof")
+            .replace(
+                "\"verifyProof(bytes,uint256[])\", proof",
+                "\"verifyProof(address,bytes,uint256[])\", vk, proof",
+            );
+    }
+
+    Ok(format!(
+        "{}\n{}",
+        verifier.replace(CONTRACT, "contract Halo2VerifierCore {"),
+        wrapper
+    ))
+}
+
+/// Makes a rendered aggregate Halo2Verifier forward the public instances of the proofs it
+/// aggregates, so that downstream contracts can consume model outputs directly. `inner` is the
+/// range of the aggregate's instances holding each inner proof's instances, in the order they were
+/// aggregated (see [crate::pfsys::evm::aggregation_kzg::AggregationCircuit::inner_layout]).
+///
+/// `innerInstances` returns the instances of an inner proof, and the added `verifyProofAndEmit`
+/// verifies the aggregate with an external self call and then emits an
+/// `InnerInstances` event for each inner proof.
+pub fn forward_instances_sol(
+    verifier: &str,
+    inner: &[std::ops::Range<usize>],
+) -> Result<String, Box<dyn Error>> {
+    if inner.is_empty() {
+        return Err("the aggregate verifier has no inner proofs to forward".into());
+    }
+    let signature_start = verifier
+        .find("function verifyProof(")
+        .ok_or("could not find verifyProof in the verifier")?;
+    let params_end = signature_start
+        + verifier[signature_start..]
+            .find(')')
+            .ok_or("could not parse the verifyProof signature")?;
+    let contract_end = verifier
+        .rfind('}')
+        .ok_or("could not find the end of the verifier contract")?;
+
+    let separate_vk = verifier[signature_start..params_end].contains("address");
+    let (vk_param, vk_arg, vk_type) = if separate_vk {
+        ("address vk, ", "vk, ", "address,")
+    } else {
+        ("", "", "")
+    };
+
+    let num_proofs = inner.len();
+    let starts = inner
+        .iter()
+        .map(|r| r.start.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ends = inner
+        .iter()
+        .map(|r| r.end.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let helpers = format!(
+        r#"
+    event InnerInstances(uint256 indexed proofIndex, uint256[] instances);
+
+    function innerProofCount() public pure returns (uint256) {{
+        return {num_proofs};
+    }}
+
+    function innerInstances(
+        uint256[] calldata instances,
+        uint256 proofIndex
+    ) public pure returns (uint256[] memory) {{
+        uint256[{num_proofs}] memory starts = [uint256({starts})];
+        uint256[{num_proofs}] memory ends = [uint256({ends})];
+        require(proofIndex < {num_proofs}, "no such inner proof");
+        require(instances.length >= ends[proofIndex], "missing inner proof instances");
+        return instances[starts[proofIndex]:ends[proofIndex]];
+    }}
+
+    function verifyProofAndEmit(
+        {vk_param}bytes calldata proof,
+        uint256[] calldata instances
+    ) external returns (bool) {{
+        (bool success, bytes memory result) = address(this).call(
+            abi.encodeWithSignature("verifyProof({vk_type}bytes,uint256[])", {vk_arg}proof, instances)
+        );
+        require(
+            success && result.length == 32 && abi.decode(result, (bool)),
+            "invalid aggregate proof"
+        );
+        for (uint256 i = 0; i < {num_proofs}; i++) {{
+            emit InnerInstances(i, innerInstances(instances, i));
+        }}
+        return true;
+    }}
+"#
+    );
+
+    Ok([
+        &verifier[..contract_end],
+        &helpers,
+        &verifier[contract_end..],
+    ]
+    .concat())
+}
+
+/// A view call returning a commitment posted on-chain, such as the Poseidon hash of inputs or
+/// outputs with [crate::graph::Visibility::Hashed] visibility, which the verifier compares with
+/// the instance segment named `segment` (see [crate::graph::GraphSettings::instance_layout]).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CommitmentCall {
+    /// The single instance segment attested to, e.g. `input_hash_0` or `output_hash_0`
+    pub segment: String,
+    /// The hex encoded address of the contract to call
+    pub address: String,
+    /// The hex encoded abi encoded view call, returning a uint256
+    pub call_data: String,
+}
+
+impl CommitmentCall {
+    /// Loads the commitment calls from a json file
+    pub fn load(path: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let file = std::fs::File::open(path).map_err(|e| {
+            format!(
+                "failed to open commitment calls at {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// The address and call data of the call
+    pub fn parse(&self) -> Result<(H160, Bytes), Box<dyn Error>> {
+        let address = hex::decode(&self.address)?;
+        if address.len() != 20 {
+            return Err(format!("invalid address {}", self.address).into());
+        }
+        Ok((
+            H160::from_slice(&address),
+            Bytes::from(hex::decode(&self.call_data)?),
+        ))
+    }
+}
+
+/// Makes a rendered Halo2Verifier attest that instances match commitments posted on-chain.
+/// `commitments` holds the index of the attested instance, and the contract and view call
+/// returning its expected value. At verification time `verifyProof` makes each view call and
+/// rejects proofs whose instance doesn't match the returned uint256.
+///
+/// Unlike the [DataAttestation] contract, which quantizes raw on-chain data for public inputs and
+/// outputs, the returned values are compared as field elements, so commitments to hashed inputs and
+/// outputs can be attested to by the verifier itself.
+pub fn attest_commitments_sol(
+    verifier: &str,
+    commitments: &[(usize, H160, Bytes)],
+) -> Result<String, Box<dyn Error>> {
+    if commitments.is_empty() {
+        return Err("no commitments to attest".into());
+    }
+    let signature_start = verifier
+        .find("function verifyProof(")
+        .ok_or("could not find verifyProof in the verifier")?;
+    let params_end = signature_start
+        + verifier[signature_start..]
+            .find(')')
+            .ok_or("could not find the verifyProof parameters")?;
+    // the attestations read the instances by the name verifyProof gives them
+    if !verifier[signature_start..params_end].contains("uint256[] calldata instances") {
+        return Err(
+            "verifyProof doesn't take its instances as `uint256[] calldata instances`".into(),
+        );
+    }
+    let body_start = params_end
+        + verifier[params_end..]
+            .find('{')
+            .ok_or("could not find the verifyProof body")?
+        + 1;
+    let contract_end = verifier
+        .rfind('}')
+        .ok_or("could not find the end of the verifier contract")?;
+
+    let num_instances = commitments.iter().map(|(i, _, _)| i + 1).max().unwrap_or(0);
+    let calls = commitments
+        .iter()
+        .map(|(instance, address, call_data)| {
+            format!(
+                "\n        attestCommitment({}, hex\"{}\", instances[{}]);",
+                ethers::utils::to_checksum(address, None),
+                hex::encode(call_data),
+                instance
+            )
+        })
+        .collect::<String>();
+
+    let check = r#"
+        attestCommitments(instances);"#;
+
+    let helpers = format!(
+        r#"
+    function attestCommitment(
+        address target,
+        bytes memory data,
+        uint256 instance
+    ) internal view {{
+        require(target.code.length > 0, "Address: call to non-contract");
+        (bool success, bytes memory result) = target.staticcall(data);
+        require(success && result.length == 32, "commitment call failed");
+        require(abi.decode(result, (uint256)) == instance, "commitment does not match");
+    }}
+
+    function attestCommitments(uint256[] calldata instances) internal view {{
+        require(instances.length >= {num_instances}, "missing committed instances");{calls}
+    }}
+"#
+    );
+
+    Ok([
+        &verifier[..body_start],
+        check,
+        &verifier[body_start..contract_end],
+        &helpers,
+        &verifier[contract_end..],
+    ]
+    .concat())
+}
+
+/// The chain a verifier is generated for. Calldata pricing and precompiles differ across L2s, so
+/// [TargetChain::patch_verifier] adapts a rendered Halo2Verifier to the chain:
+///
+/// - `ethereum`, `arbitrum` and `optimism` leave it as is. Arbitrum and OP-stack chains have every
+///   precompile the verifier calls and compress the calldata of their batches themselves.
+/// - `zksync` replaces calls to the modexp precompile, which zkSync Era doesn't have, with the
+///   equivalent exponentiation in assembly. The verifier is deployed compiled with zksolc, which
+///   [TargetChain::check_build] checks it compiles with.
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub enum TargetChain {
+    /// Ethereum mainnet and other chains with its precompiles and calldata pricing
+    #[default]
+    Ethereum,
+    /// Arbitrum One and Nova
+    Arbitrum,
+    /// OP-stack chains
+    Optimism,
+    /// zkSync Era
+    ZkSync,
+}
+
+impl std::fmt::Display for TargetChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetChain::Ethereum => write!(f, "ethereum"),
+            TargetChain::Arbitrum => write!(f, "arbitrum"),
+            TargetChain::Optimism => write!(f, "optimism"),
+            TargetChain::ZkSync => write!(f, "zksync"),
+        }
+    }
+}
+
+impl tosubcommand::ToFlags for TargetChain {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl std::str::FromStr for TargetChain {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ethereum" => Ok(TargetChain::Ethereum),
+            "arbitrum" => Ok(TargetChain::Arbitrum),
+            "optimism" => Ok(TargetChain::Optimism),
+            "zksync" => Ok(TargetChain::ZkSync),
+            _ => Err(format!(
+                "invalid target chain {}, expected one of ethereum, arbitrum, optimism, zksync",
+                s
+            )),
+        }
+    }
+}
+
+impl TargetChain {
+    /// Adapts a rendered Halo2Verifier to the chain, see [TargetChain].
+    pub fn patch_verifier(&self, verifier: &str) -> Result<String, Box<dyn Error>> {
+        match self {
+            TargetChain::Ethereum | TargetChain::Arbitrum | TargetChain::Optimism => {
+                Ok(verifier.to_string())
+            }
+            TargetChain::ZkSync => replace_modexp_sol(verifier),
+        }
+    }
+
+    /// Checks a verifier at `sol_code_path` patched for the chain builds with the compiler the
+    /// chain deploys with. zkSync Era runs EraVM bytecode compiled by zksolc rather than EVM
+    /// bytecode, so solc accepting the verifier says little.
+    pub fn check_build(&self, sol_code_path: &Path) -> Result<(), Box<dyn Error>> {
+        if *self != TargetChain::ZkSync {
+            return Ok(());
+        }
+        let output = std::process::Command::new("zksolc")
+            .arg("--bin")
+            .arg(sol_code_path)
+            .output()
+            .map_err(|_| "zksolc not found, zksync verifiers are compiled with zksolc")?;
+        if !output.status.success() {
+            return Err(format!(
+                "zksolc failed to compile the verifier: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the calls a rendered Halo2Verifier makes to the modexp precompile (0x05) with an
+/// assembly function reading the same input and writing the same output.
+fn replace_modexp_sol(verifier: &str) -> Result<String, Box<dyn Error>> {
+    const MODEXP_CALL: &str = "staticcall(gas(), 0x05, ";
+    let mut patched = String::new();
+    let mut rest = verifier;
+    while let Some(start) = rest.find(MODEXP_CALL) {
+        let args_start = start + MODEXP_CALL.len();
+        let args_end = args_start
+            + rest[args_start..]
+                .find(')')
+                .ok_or("could not parse a call to the modexp precompile")?;
+        let args = rest[args_start..args_end].split(", ").collect::<Vec<_>>();
+        let [input, "0xc0", output, "0x20"] = args[..] else {
+            return Err(format!(
+                "unexpected call to the modexp precompile: {}",
+                &rest[start..=args_end]
+            )
+            .into());
+        };
+        patched.push_str(&rest[..start]);
+        patched.push_str(&format!("zksync_modexp({}, {})", input, output));
+        rest = &rest[args_end + 1..];
+    }
+    patched.push_str(rest);
+    if patched == verifier {
+        return Ok(verifier.to_string());
+    }
+
+    let signature_start = patched
+        .find("function verifyProof(")
+        .ok_or("could not find verifyProof in the verifier")?;
+    let assembly_start = signature_start
+        + patched[signature_start..]
+            .find("assembly")
+            .ok_or("could not find the assembly of verifyProof")?;
+    let block_start = assembly_start
+        + patched[assembly_start..]
+            .find('{')
+            .ok_or("could not find the assembly block of verifyProof")?
+        + 1;
+
+    let function = r#"
+            // the modexp precompile on the inputs it is called with: the lengths of the base,
+            // exponent and modulus, each 32 bytes, followed by the three
+            function zksync_modexp(in_mptr, out_mptr) -> ret {
+                let base := mload(add(in_mptr, 0x60))
+                let exponent := mload(add(in_mptr, 0x80))
+                let modulus := mload(add(in_mptr, 0xa0))
+                let result := 1
+                base := mod(base, modulus)
+                for {} gt(exponent, 0) {} {
+                    if and(exponent, 1) {
+                        result := mulmod(result, base, modulus)
+                    }
+                    base := mulmod(base, base, modulus)
+                    exponent := shr(1, exponent)
+                }
+                mstore(out_mptr, result)
+                ret := 1
+            }
+"#;
+
+    Ok([&patched[..block_start], function, &patched[block_start..]].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_chains() {
+        let verifier = "contract Halo2Verifier {\n    function verifyProof(bytes calldata proof, uint256[] calldata instances) public returns (bool) {\n        assembly (\"memory-safe\") {\n            success := and(success, staticcall(gas(), 0x05, gp_mptr, 0xc0, gp_mptr, 0x20))\n        }\n    }\n}\n";
+        let patched = TargetChain::ZkSync.patch_verifier(verifier).unwrap();
+        assert!(patched.contains("success := and(success, zksync_modexp(gp_mptr, gp_mptr))"));
+        assert!(patched.contains("function zksync_modexp(in_mptr, out_mptr) -> ret"));
+        assert_eq!(
+            TargetChain::Ethereum.patch_verifier(verifier).unwrap(),
+            verifier
+        );
+        assert_eq!(
+            TargetChain::Optimism.patch_verifier(verifier).unwrap(),
+            verifier
+        );
+        assert_eq!("zkSync".parse::<TargetChain>(), Ok(TargetChain::ZkSync));
+        assert!("ethereun".parse::<TargetChain>().is_err());
+    }
+
+    #[test]
+    fn test_signer_sources() {
+        let key = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+        assert_eq!(
+            key.parse::<SignerSource>().unwrap(),
+            SignerSource::PrivateKey(key.to_string())
+        );
+        assert_eq!(
+            "ledger".parse::<SignerSource>().unwrap(),
+            SignerSource::Ledger(0)
+        );
+        assert_eq!(
+            "ledger:2".parse::<SignerSource>().unwrap(),
+            SignerSource::Ledger(2)
+        );
+        assert_eq!(
+            "aws-kms:my-key".parse::<SignerSource>().unwrap(),
+            SignerSource::AwsKms("my-key".to_string())
+        );
+        assert_eq!(
+            "rpc".parse::<SignerSource>().unwrap(),
+            SignerSource::Rpc(None)
+        );
+        assert!("aws-kms".parse::<SignerSource>().is_err());
+        assert!(format!("0x{}", key).parse::<SignerSource>().is_err());
+    }
+
+    #[test]
+    fn test_multicall_encoding() {
+        use ethers::abi::Token;
+        let target = H160::repeat_byte(1);
+        let calldata = encode_aggregate3(&[(target, vec![0xf2, 0xa7]), (target, vec![])]);
+        assert_eq!(
+            calldata[..4],
+            ethers::utils::id("aggregate3((address,bool,bytes)[])")
+        );
+
+        let result = |success, data: &[u8]| {
+            Token::Tuple(vec![Token::Bool(success), Token::Bytes(data.to_vec())])
+        };
+        let returned = ethers::abi::encode(&[Token::Array(vec![
+            result(true, &[1]),
+            result(true, &[2, 3]),
+        ])]);
+        let decoded = decode_aggregate3(&returned, 2).unwrap();
+        assert_eq!(decoded, vec![Bytes::from(vec![1]), Bytes::from(vec![2, 3])]);
+        assert!(decode_aggregate3(&returned, 3).is_err());
+
+        let failed = ethers::abi::encode(&[Token::Array(vec![result(false, &[])])]);
+        assert!(decode_aggregate3(&failed, 1).is_err());
+        // chains without multicall return no data
+        assert!(decode_aggregate3(&[], 2).is_err());
+    }
+
+    #[test]
+    fn test_encode_contract_call() {
+        use ethers::abi::ParamType;
+        let call = |signature: &str, args: &[&str]| ContractCall {
+            address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+            signature: signature.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            decimals: None,
+        };
+
+        let holder = "0x000000000000000000000000000000000000dEaD";
+        let encoded = encode_contract_call(&call("balanceOf(address)", &[holder])).unwrap();
+        assert_eq!(encoded.returns, ParamType::Uint(256));
+        assert_eq!(hex::encode(&encoded.call_data[..4]), "70a08231");
+        assert_eq!(encoded.call_data[4..].len(), 32);
+        assert_eq!(encoded.call_data[35], 0xad);
+
+        // types are canonicalized and the return type of non standard views is stated
+        let encoded = encode_contract_call(&call("getRate(uint)(int)", &["7"])).unwrap();
+        assert_eq!(encoded.signature, "getRate(uint256)");
+        assert_eq!(encoded.returns, ParamType::Int(256));
+        assert!(encode_contract_call(&call("latestAnswer()", &[])).is_err());
+        assert!(encode_contract_call(&call("name()(string)", &[])).is_err());
+        assert!(encode_contract_call(&call("balanceOf(address)", &[])).is_err());
+    }
+
+    #[test]
+    fn test_proof_blobs() {
+        let instances = vec![Fr::from(3), -Fr::from(5)];
+        let proof = (0..100).map(|i| i as u8).collect::<Vec<_>>();
+        let blob = proof_to_blob(&instances, &proof).unwrap();
+        assert_eq!(blob.len(), BYTES_PER_BLOB);
+        // every field element is below the BLS12-381 modulus
+        let modulus = U256::from_dec_str(BLS_MODULUS).unwrap();
+        assert!(blob.chunks(32).all(|e| U256::from_big_endian(e) < modulus));
+        assert_eq!(blob_to_proof(&blob, 2).unwrap(), (instances, proof));
+        // the bytes of the proof, past the instances, its hash and its length, are checked
+        let mut tampered = blob.clone();
+        tampered[4 * 32 + 1] ^= 1;
+        assert!(blob_to_proof(&tampered, 2).is_err());
+        assert!(proof_to_blob(&[], &[0; BYTES_PER_BLOB]).is_err());
+
+        // the roots of unity are in bit reversed order: 1, -1, then the 4th roots
+        assert_eq!(blob_evaluation_point(0), U256::one());
+        assert_eq!(blob_evaluation_point(1), modulus - 1);
+        let square =
+            |a: U256| U256::try_from(a.full_mul(a) % ethers::types::U512::from(modulus)).unwrap();
+        assert_eq!(square(blob_evaluation_point(2)), modulus - 1);
+    }
+
+    #[test]
+    fn test_calldata_gas() {
+        assert_eq!(calldata_gas(&[]), 0);
+        assert_eq!(calldata_gas(&[0, 0, 1, 255]), 4 + 4 + 16 + 16);
+    }
+
+    #[test]
+    fn test_create2_address() {
+        // the deployer is the well known address of the deterministic deployment proxy
+        let salt = [0u8; 32];
+        let expected = ethers::utils::get_create2_address(
+            "0x4e59b44847b379578588920cA78FbF26c0B4956C"
+                .parse::<H160>()
+                .unwrap(),
+            salt,
+            [0xdeu8, 0xad, 0xbe, 0xef],
+        );
+        assert_eq!(create2_address(salt, &[0xde, 0xad, 0xbe, 0xef]), expected);
+        assert_eq!(vk_salt(b"vk"), vk_salt(b"vk"));
+        assert_ne!(vk_salt(b"vk"), vk_salt(b"other vk"));
+    }
+
+    #[test]
+    fn test_attest_commitments_sol() {
+        let verifier = "contract Halo2Verifier {\n    function verifyProof(bytes calldata proof, uint256[] calldata instances) public returns (bool) {\n        return true;\n    }\n}\n";
+        let call = CommitmentCall {
+            segment: "input_hash_0".to_string(),
+            address: "b794f5ea0ba39494ce839613fffba74279579268".to_string(),
+            call_data: "f2a75fe4".to_string(),
+        };
+        let (address, call_data) = call.parse().unwrap();
+        let attested =
+            attest_commitments_sol(verifier, &[(2, address, call_data.clone())]).unwrap();
+        assert!(attested.contains("attestCommitments(instances);\n        return true;"));
+        assert!(attested.contains(
+            "attestCommitment(0xb794F5eA0ba39494cE839613fffBA74279579268, hex\"f2a75fe4\", instances[2]);"
+        ));
+        assert!(attested.contains("instances.length >= 3"));
+        assert!(attest_commitments_sol(verifier, &[]).is_err());
+        let renamed = verifier.replace("instances)", "pubInputs)");
+        assert!(attest_commitments_sol(&renamed, &[(2, address, Bytes::new())]).is_err());
+
+        // the attested verifier compiles
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("verifier.sol");
+        let verifier = format!(
+            "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\n{}",
+            verifier.replace("return true;", "return proof.length > 0;")
+        );
+        std::fs::write(
+            &path,
+            attest_commitments_sol(&verifier, &[(2, address, call_data)]).unwrap(),
+        )
+        .unwrap();
+        let (abi, _, runtime_bytecode) = get_contract_artifacts(path, "Halo2Verifier", 0).unwrap();
+        assert!(abi.function("verifyProof").is_ok());
+        assert!(!runtime_bytecode.is_empty());
+    }
+
+    #[test]
+    fn test_forward_instances_sol() {
+        let dir = tempfile::tempdir().unwrap();
+        let verifiers = [
+            "function verifyProof(bytes calldata proof, uint256[] calldata instances) public returns (bool)",
+            "function verifyProof(address vk, bytes calldata proof, uint256[] calldata instances) public returns (bool)",
+        ];
+        for (i, signature) in verifiers.iter().enumerate() {
+            let verifier = format!(
+                "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\ncontract Halo2Verifier {{\n    {} {{\n        return proof.length > 0 && instances.length > 0;\n    }}\n}}\n",
+                signature
+            );
+            let forwarded = forward_instances_sol(&verifier, &[12..14, 14..17]).unwrap();
+            let path = dir.path().join(format!("verifier_{}.sol", i));
+            std::fs::write(&path, forwarded).unwrap();
+            let (abi, _, runtime_bytecode) =
+                get_contract_artifacts(path, "Halo2Verifier", 0).unwrap();
+            for function in ["innerProofCount", "innerInstances", "verifyProofAndEmit"] {
+                assert!(abi.function(function).is_ok(), "{} is missing", function);
+            }
+            assert!(abi.event("InnerInstances").is_ok());
+            assert!(!runtime_bytecode.is_empty());
+        }
+        assert!(forward_instances_sol(verifiers[0], &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_contract_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let runs = crate::commands::DEFAULT_OPTIMIZER_RUNS.parse().unwrap();
+        // a contract returning a constant of `len` bytes, which is embedded in its bytecode
+        let size = |len: usize| {
+            let path = dir.path().join(format!("blob_{}.sol", len));
+            let contract = format!(
+                "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\ncontract Blob {{\n    function blob() external pure returns (bytes memory) {{\n        return hex\"{}\";\n    }}\n}}\n",
+                "ab".repeat(len)
+            );
+            std::fs::write(&path, contract).unwrap();
+            get_contract_artifacts(path, "Blob", runs).unwrap().2.len()
+        };
+        assert!(check_contract_size("blob", size(1024)).is_ok());
+        assert!(check_contract_size("blob", size(EIP170_CONTRACT_SIZE_LIMIT)).is_err());
+        assert!(check_contract_size("blob", EIP170_CONTRACT_SIZE_LIMIT).is_ok());
+    }
+}
@@ -3,32 +3,50 @@ use crate::circuit::CheckMode;
 use crate::commands::CalibrationTarget;
 use crate::commands::Commands;
 #[cfg(not(target_arch = "wasm32"))]
-use crate::commands::H160Flag;
+use crate::commands::{H160Flag, UserOpFlag};
+use crate::commands::{DEFAULT_AGGREGATED_LOGROWS, DEFAULT_AGGREGATION_FAN_IN};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::eth::{deploy_contract_via_solidity, deploy_da_verifier_via_solidity};
 #[cfg(not(target_arch = "wasm32"))]
 #[allow(unused_imports)]
 use crate::eth::{fix_da_sol, get_contract_artifacts, verify_proof_via_solidity};
+use crate::graph::assignment::Assignment;
+use crate::graph::dataset::{load_dataset, DatasetSpec};
+use crate::graph::encryption::ModelKey;
 use crate::graph::input::GraphData;
+use crate::graph::scrub::{scrub_witness, shred_file};
+use crate::graph::templates::{Template, TemplateTask};
 use crate::graph::{GraphCircuit, GraphSettings, GraphWitness, Model};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::graph::{TestDataSource, TestSources};
-use crate::pfsys::evm::aggregation_kzg::{AggregationCircuit, PoseidonTranscript};
+use crate::journal::{command_name, journal_path, Journal, Recording, Watch};
+use crate::output::CommandReport;
+use crate::pfsys::aggregation_tree::AggregationState;
+use crate::pfsys::batch::{
+    list_proofs, list_witnesses, BatchItem, BatchManifest, VerificationReport,
+};
 #[cfg(not(target_arch = "wasm32"))]
+use crate::pfsys::checkpoint::{fingerprint, Checkpoint};
+use crate::pfsys::evm::aggregation_kzg::{AggregationCircuit, PoseidonTranscript};
+use crate::pfsys::key_header::KeyParams;
+use crate::pfsys::profile::ProofReport;
 use crate::pfsys::{
-    create_keys, load_pk, load_vk, save_params, save_pk, Snark, StrategyType, TranscriptType,
+    create_keys, create_pk, create_vk, load_pk, load_vk, save_params, save_pk, Snark, StrategyType,
+    TranscriptType,
 };
 use crate::pfsys::{
-    create_proof_circuit, swap_proof_commitments_polycommit, verify_proof_circuit, ProofSplitCommit,
+    create_proof_circuit, create_single_proof, swap_proof_commitments_polycommit,
+    verify_proof_circuit, ProofSplitCommit,
 };
 use crate::pfsys::{save_vk, srs::*};
 use crate::tensor::TensorError;
-use crate::{Commitments, RunArgs};
+use crate::wasm_contracts::ContractTarget;
+use crate::{Accelerator, Commitments, RunArgs};
 #[cfg(unix)]
 use gag::Gag;
 use halo2_proofs::dev::VerifyFailure;
-use halo2_proofs::plonk::{self, Circuit};
-use halo2_proofs::poly::commitment::{CommitmentScheme, Params};
+use halo2_proofs::plonk::{self, Circuit, VerifyingKey};
+use halo2_proofs::poly::commitment::CommitmentScheme;
 use halo2_proofs::poly::commitment::{ParamsProver, Verifier};
 use halo2_proofs::poly::ipa::commitment::{IPACommitmentScheme, ParamsIPA};
 use halo2_proofs::poly::ipa::multiopen::{ProverIPA, VerifierIPA};
@@ -41,7 +59,7 @@ use halo2_proofs::poly::kzg::{
     commitment::ParamsKZG, strategy::SingleStrategy as KZGSingleStrategy,
 };
 use halo2_proofs::poly::VerificationStrategy;
-use halo2_proofs::transcript::{EncodedChallenge, TranscriptReadBuffer};
+use halo2_proofs::transcript::{Blake2bRead, Challenge255, EncodedChallenge, TranscriptReadBuffer};
 #[cfg(not(target_arch = "wasm32"))]
 use halo2_solidity_verifier;
 use halo2curves::bn256::{Bn256, Fr, G1Affine};
@@ -55,9 +73,11 @@ use itertools::Itertools;
 #[cfg(not(target_arch = "wasm32"))]
 use log::debug;
 use log::{info, trace, warn};
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::pcs::kzg::KzgSuccinctVerifyingKey;
 use snark_verifier::system::halo2::compile;
 use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
 use snark_verifier::system::halo2::Config;
@@ -74,11 +94,10 @@ use std::sync::OnceLock;
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::EZKL_BUF_CAPACITY;
-#[cfg(not(target_arch = "wasm32"))]
-use std::io::BufWriter;
 use std::time::Duration;
 use tabled::Tabled;
 use thiserror::Error;
+use tosubcommand::ToFlags;
 
 #[cfg(not(target_arch = "wasm32"))]
 static _SOLC_REQUIREMENT: OnceLock<bool> = OnceLock::new();
@@ -120,6 +139,9 @@ lazy_static! {
     /// The path to the ezkl related data (SRS)
     pub static ref EZKL_SRS_REPO_PATH: String = format!("{}/srs", *EZKL_REPO_PATH);
 
+    /// The path to cached models downloaded with get-model
+    pub static ref EZKL_MODELS_REPO_PATH: String = format!("{}/models", *EZKL_REPO_PATH);
+
 }
 
 /// A wrapper for tensor related errors.
@@ -143,6 +165,42 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
     // set working dir
     std::env::set_current_dir(WORKING_DIR.as_path())?;
 
+    match (command, journal_path()) {
+        (Commands::Replay { journal }, _) => replay(journal).await,
+        (command, Some(journal)) => {
+            let recording = Recording::start(&command)?;
+            let res = run_command(command).await?;
+            Journal::append(&journal, recording.finish()?)?;
+            Ok(res)
+        }
+        (command, None) => run_command(command).await,
+    }
+}
+
+/// Run an ezkl command, reporting whether it succeeded, how long it took, the files it wrote and
+/// its result
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_with_report(command: Commands) -> CommandReport {
+    let name = command_name(&command);
+    if let Err(e) = std::env::set_current_dir(WORKING_DIR.as_path()) {
+        return CommandReport::new(name, Duration::ZERO, vec![], Err(e.to_string()));
+    }
+    // the artifacts are only hashed when journaled, by [run]
+    let watch = Watch::start(&command);
+    let start = Instant::now();
+    let result = run(command).await.map_err(|e| e.to_string());
+    let elapsed = start.elapsed();
+    let artifacts = match watch.and_then(|watch| watch.finish()) {
+        Ok(artifacts) => artifacts,
+        Err(e) => {
+            warn!("failed to list the artifacts of {}: {}", name, e);
+            vec![]
+        }
+    };
+    CommandReport::new(name, elapsed, artifacts, result)
+}
+
+async fn run_command(command: Commands) -> Result<String, Box<dyn Error>> {
     match command {
         #[cfg(feature = "empty-cmd")]
         Commands::Empty => Ok(String::new()),
@@ -150,7 +208,13 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             srs_path,
             logrows,
             commitment,
-        } => gen_srs_cmd(srs_path, logrows as u32, commitment),
+            unsafe_setup,
+        } => gen_srs_cmd(
+            get_srs_path(logrows as u32, srs_path, commitment),
+            logrows as u32,
+            commitment,
+            unsafe_setup,
+        ),
         #[cfg(not(target_arch = "wasm32"))]
         Commands::GetSrs {
             srs_path,
@@ -158,13 +222,167 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             logrows,
             commitment,
         } => get_srs_cmd(srs_path, settings_path, logrows, commitment).await,
+        Commands::GetModel { url, sha256, model } => get_model_cmd(url, sha256, model).await,
+        Commands::FetchArtifact {
+            url,
+            output,
+            sha256,
+            chunk_size,
+        } => fetch_artifact(url, output, sha256, chunk_size).await,
+        Commands::PublishArtifact {
+            path,
+            url,
+            chunk_size,
+        } => publish_artifact(path, url, chunk_size).await,
         Commands::Table { model, args } => table(model, args),
+        Commands::Inspect {
+            model,
+            args,
+            sort_by,
+            top,
+        } => inspect(model, args, sort_by, top),
+        Commands::Template {
+            task,
+            num_features,
+            hidden_size,
+            seed,
+            model,
+            data,
+            settings_path,
+        } => template(
+            task,
+            num_features,
+            hidden_size,
+            seed,
+            model,
+            data,
+            settings_path,
+        ),
         Commands::GenSettings {
             model,
             settings_path,
             args,
         } => gen_circuit_settings(model, settings_path, args),
         #[cfg(not(target_arch = "wasm32"))]
+        Commands::Migrate {
+            settings_path,
+            output,
+        } => migrate(settings_path, output),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::CompatCheck {
+            proof_path,
+            settings_path,
+        } => compat_check(proof_path, settings_path),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::GenGolden { output_dir, check } => gen_golden(output_dir, check),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::SelfTest { op } => self_test(op),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Completions { shell } => completions(shell),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Run { config, force } => {
+            let report = run_pipeline(config, force).await?;
+            Ok(serde_json::to_string_pretty(&report)?)
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Bench {
+            model,
+            settings_path,
+            data,
+            srs_path,
+            iterations,
+            work_dir,
+            output,
+        } => {
+            bench(
+                model,
+                settings_path,
+                data,
+                srs_path,
+                iterations,
+                work_dir,
+                output,
+            )
+            .await
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Serve {
+            compiled_circuit,
+            pk_path,
+            srs_path,
+            encryption_key,
+            addr,
+            jobs_dir,
+            workers,
+            max_queue,
+            check_mode,
+            model,
+            max_age,
+            max_bytes,
+            admin_token_file,
+        } => {
+            serve(
+                compiled_circuit,
+                pk_path,
+                srs_path,
+                encryption_key,
+                crate::serve::ServeConfig {
+                    addr,
+                    jobs_dir,
+                    model,
+                    workers,
+                    max_queue,
+                    check_mode,
+                    retention: crate::retention::RetentionPolicy { max_age, max_bytes },
+                    admin_token: crate::serve::read_admin_token(admin_token_file)?,
+                },
+            )
+            .await
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::GcJobs {
+            jobs_dir,
+            max_age,
+            max_bytes,
+            model,
+            tenant,
+        } => gc_jobs(jobs_dir, max_age, max_bytes, model, tenant),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::GenFieldVectors { output } => gen_field_vectors(output),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::ExportEnv {
+            settings_path,
+            srs_path,
+            output,
+        } => export_env(settings_path, srs_path, output),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::CheckEnv {
+            lock,
+            settings_path,
+            srs_path,
+        } => check_env(lock, settings_path, srs_path),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::UnsealOutputs {
+            witness,
+            secret_key,
+            output,
+        } => unseal_outputs(witness, secret_key, output),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::WitnessDiff {
+            left,
+            right,
+            compiled_circuit,
+            encryption_key,
+        } => witness_diff(left, right, compiled_circuit, encryption_key),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::DiffSettings {
+            left,
+            right,
+            key_path,
+        } => diff_settings(left, right, key_path),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Replay { .. } => Err("replays can't be nested".into()),
+        #[cfg(not(target_arch = "wasm32"))]
         Commands::CalibrateSettings {
             model,
             settings_path,
@@ -175,6 +393,9 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             scale_rebase_multiplier,
             max_logrows,
             only_range_check_rebase,
+            dataset_spec,
+            bias_correction,
+            max_lookup_domains,
         } => calibrate(
             model,
             data,
@@ -185,6 +406,9 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             scale_rebase_multiplier,
             only_range_check_rebase,
             max_logrows,
+            dataset_spec,
+            bias_correction,
+            max_lookup_domains,
         )
         .map(|e| serde_json::to_string(&e).unwrap()),
         Commands::GenWitness {
@@ -193,10 +417,49 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             output,
             vk_path,
             srs_path,
-        } => gen_witness(compiled_circuit, data, Some(output), vk_path, srs_path)
+            encryption_key,
+            seal_outputs_to,
+            caller,
+            user_op,
+            full_assignment,
+        } => gen_witness(
+            compiled_circuit,
+            data,
+            Some(output),
+            vk_path,
+            srs_path,
+            encryption_key,
+            seal_outputs_to,
+            caller,
+            user_op,
+            full_assignment,
+        )
+        .await
+        .map(|e| serde_json::to_string(&e).unwrap()),
+        Commands::Mock {
+            model,
+            witness,
+            encryption_key,
+        } => mock(model, witness, encryption_key),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::Fuzz {
+            compiled_circuit,
+            ranges,
+            iterations,
+            seed,
+            output_dir,
+            encryption_key,
+        } => {
+            fuzz(
+                compiled_circuit,
+                ranges,
+                iterations,
+                seed,
+                output_dir,
+                encryption_key,
+            )
             .await
-            .map(|e| serde_json::to_string(&e).unwrap()),
-        Commands::Mock { model, witness } => mock(model, witness),
+        }
         #[cfg(not(target_arch = "wasm32"))]
         Commands::CreateEvmVerifier {
             vk_path,
@@ -205,6 +468,8 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             sol_code_path,
             abi_path,
             render_vk_seperately,
+            commitments_path,
+            target_chain,
         } => create_evm_verifier(
             vk_path,
             srs_path,
@@ -212,6 +477,30 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             sol_code_path,
             abi_path,
             render_vk_seperately,
+            commitments_path,
+            target_chain,
+        ),
+        Commands::CreateNpmVerifier {
+            srs_path,
+            settings_path,
+            vk_path,
+            output_dir,
+            package_name,
+        } => create_npm_verifier(srs_path, settings_path, vk_path, output_dir, package_name),
+        Commands::CreateWasmVerifier {
+            srs_path,
+            settings_path,
+            vk_path,
+            output_dir,
+            crate_name,
+            target,
+        } => create_wasm_verifier(
+            srs_path,
+            settings_path,
+            vk_path,
+            output_dir,
+            crate_name,
+            target,
         ),
         Commands::CreateEvmVK {
             vk_path,
@@ -228,6 +517,12 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             data,
         } => create_evm_data_attestation(settings_path, sol_code_path, abi_path, data),
         #[cfg(not(target_arch = "wasm32"))]
+        Commands::CreateEvmQuantizer {
+            sol_code_path,
+            scale,
+            bits,
+        } => create_evm_quantizer(sol_code_path, scale, bits),
+        #[cfg(not(target_arch = "wasm32"))]
         Commands::CreateEvmVerifierAggr {
             vk_path,
             srs_path,
@@ -236,6 +531,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             aggregation_settings,
             logrows,
             render_vk_seperately,
+            forward_instances,
         } => create_evm_aggregate_verifier(
             vk_path,
             srs_path,
@@ -244,12 +540,14 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             aggregation_settings,
             logrows,
             render_vk_seperately,
+            forward_instances,
         ),
         Commands::CompileCircuit {
             model,
             compiled_circuit,
             settings_path,
-        } => compile_circuit(model, compiled_circuit, settings_path),
+            encryption_key,
+        } => compile_circuit(model, compiled_circuit, settings_path, encryption_key),
         Commands::Setup {
             compiled_circuit,
             srs_path,
@@ -257,6 +555,10 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             pk_path,
             witness,
             disable_selector_compression,
+            encryption_key,
+            commitment,
+            checkpoint_dir,
+            resume,
         } => setup(
             compiled_circuit,
             srs_path,
@@ -264,6 +566,10 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             pk_path,
             witness,
             disable_selector_compression,
+            encryption_key,
+            commitment,
+            checkpoint_dir,
+            resume,
         ),
         #[cfg(not(target_arch = "wasm32"))]
         Commands::SetupTestEvmData {
@@ -297,6 +603,12 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
         } => swap_proof_commitments_cmd(proof_path, witness_path)
             .map(|e| serde_json::to_string(&e).unwrap()),
         #[cfg(not(target_arch = "wasm32"))]
+        Commands::CompressProof {
+            proof_path,
+            output_path,
+            decompress,
+        } => compress_proof(proof_path, output_path, decompress),
+        #[cfg(not(target_arch = "wasm32"))]
         Commands::Prove {
             witness,
             compiled_circuit,
@@ -305,21 +617,127 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             srs_path,
             proof_type,
             check_mode,
-        } => prove(
-            witness,
-            compiled_circuit,
-            pk_path,
-            Some(proof_path),
+            encryption_key,
+            commitment,
+            transcript,
+            accelerator,
+            aggregation_state,
+            fan_in,
+            aggregation_logrows,
+            aggregation_srs_path,
+            witness_dir,
+            proof_dir,
+            from_assignment,
+            scrub,
+            seed,
+            trim_memory,
+            profile,
+            shadow_model,
+            force,
+            checkpoint_dir,
+            resume,
+        } => match (witness_dir, aggregation_state) {
+            (Some(_), Some(_)) => {
+                Err("--witness-dir can't be combined with --aggregation-state".into())
+            }
+            (Some(witness_dir), None) => prove_batch(
+                witness_dir,
+                compiled_circuit,
+                pk_path,
+                proof_dir,
+                srs_path,
+                proof_type,
+                check_mode,
+                encryption_key,
+                commitment,
+                transcript,
+                accelerator,
+                scrub,
+                seed,
+                force,
+            )
+            .map(|e| serde_json::to_string(&e).unwrap()),
+            (None, None) => prove(
+                witness,
+                compiled_circuit,
+                pk_path,
+                Some(proof_path),
+                srs_path,
+                proof_type,
+                check_mode,
+                encryption_key,
+                commitment,
+                transcript,
+                accelerator,
+                from_assignment.clone(),
+                scrub,
+                seed,
+                trim_memory,
+                profile,
+                shadow_model,
+                checkpoint_dir,
+                resume,
+                force,
+            )
+            .map(|e| serde_json::to_string(&e).unwrap()),
+            (None, Some(state_path)) => prove(
+                witness,
+                compiled_circuit,
+                pk_path,
+                Some(proof_path),
+                srs_path,
+                ProofType::ForAggr,
+                check_mode,
+                encryption_key,
+                commitment,
+                transcript,
+                accelerator,
+                from_assignment,
+                scrub,
+                seed,
+                trim_memory,
+                profile,
+                shadow_model,
+                checkpoint_dir,
+                resume,
+                force,
+            )
+            .and_then(|snark| {
+                push_to_aggregation(
+                    snark,
+                    state_path,
+                    fan_in,
+                    aggregation_logrows,
+                    aggregation_srs_path,
+                    check_mode,
+                )
+            })
+            .map(|e| serde_json::to_string(&e).unwrap()),
+        },
+        Commands::FinalizeAggregation {
+            state_path,
+            proof_path,
+            vk_path,
             srs_path,
-            proof_type,
+            transcript,
+            check_mode,
+            disable_selector_compression,
+        } => finalize_aggregation(
+            state_path,
+            proof_path,
+            vk_path,
+            srs_path,
+            transcript,
             check_mode,
+            disable_selector_compression,
         )
         .map(|e| serde_json::to_string(&e).unwrap()),
         Commands::MockAggregate {
             aggregation_snarks,
             logrows,
             split_proofs,
-        } => mock_aggregate(aggregation_snarks, logrows, split_proofs),
+            universal,
+        } => mock_aggregate(aggregation_snarks, logrows, split_proofs, universal),
         Commands::SetupAggregate {
             sample_snarks,
             vk_path,
@@ -327,6 +745,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             srs_path,
             logrows,
             split_proofs,
+            universal,
             disable_selector_compression,
             commitment,
         } => setup_aggregate(
@@ -336,6 +755,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             srs_path,
             logrows,
             split_proofs,
+            universal,
             disable_selector_compression,
             commitment,
         ),
@@ -348,6 +768,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             logrows,
             check_mode,
             split_proofs,
+            universal,
             commitment,
         } => aggregate(
             proof_path,
@@ -358,17 +779,67 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             logrows,
             check_mode,
             split_proofs,
+            universal,
             commitment,
         )
         .map(|e| serde_json::to_string(&e).unwrap()),
+        Commands::AggregateTree {
+            aggregation_snarks,
+            proof_path,
+            vk_path,
+            srs_path,
+            transcript,
+            logrows,
+            fan_in,
+            check_mode,
+            disable_selector_compression,
+        } => aggregate_tree(
+            aggregation_snarks,
+            proof_path,
+            vk_path,
+            srs_path,
+            transcript,
+            logrows,
+            fan_in,
+            check_mode,
+            disable_selector_compression,
+        )
+        .map(|e| serde_json::to_string(&e).unwrap()),
         Commands::Verify {
             proof_path,
+            proof_dir,
             settings_path,
             vk_path,
             srs_path,
             reduced_srs,
-        } => verify(proof_path, settings_path, vk_path, srs_path, reduced_srs)
+            commitment,
+            force,
+        } => match proof_dir {
+            Some(proof_dir) => verify_batch(
+                proof_dir,
+                settings_path,
+                vk_path,
+                srs_path,
+                reduced_srs,
+                commitment,
+                force,
+            )
+            .and_then(|report| {
+                // the report is logged by verify_batch, failures exit nonzero
+                report.check()?;
+                Ok(serde_json::to_string(&report)?)
+            }),
+            None => verify(
+                proof_path,
+                settings_path,
+                vk_path,
+                srs_path,
+                reduced_srs,
+                commitment,
+                force,
+            )
             .map(|e| serde_json::to_string(&e).unwrap()),
+        },
         Commands::VerifyAggr {
             proof_path,
             vk_path,
@@ -392,6 +863,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             addr_path,
             optimizer_runs,
             private_key,
+            create2_vk_path,
         } => {
             deploy_evm(
                 sol_code_path,
@@ -400,6 +872,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
                 optimizer_runs,
                 private_key,
                 "Halo2Verifier",
+                create2_vk_path,
             )
             .await
         }
@@ -410,6 +883,7 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             addr_path,
             optimizer_runs,
             private_key,
+            create2_vk_path,
         } => {
             deploy_evm(
                 sol_code_path,
@@ -418,23 +892,72 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
                 optimizer_runs,
                 private_key,
                 "Halo2VerifyingKey",
+                create2_vk_path,
             )
             .await
         }
         #[cfg(not(target_arch = "wasm32"))]
-        Commands::DeployEvmDataAttestation {
-            data,
+        Commands::CreateEvmRegistry {
+            sol_code_path,
+            abi_path,
+        } => create_evm_registry(sol_code_path, abi_path),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::DeployEvmRegistry {
+            sol_code_path,
+            addr_verifier,
+            addr_vk,
+            rpc_url,
+            addr_path,
+            optimizer_runs,
+            private_key,
+        } => {
+            deploy_evm_registry(
+                sol_code_path,
+                addr_verifier,
+                addr_vk,
+                rpc_url,
+                addr_path,
+                optimizer_runs,
+                private_key,
+            )
+            .await
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::UpdateEvmVk {
+            addr_registry,
+            addr_vk,
+            addr_verifier,
+            rpc_url,
+            private_key,
+        } => update_evm_vk(addr_registry, addr_vk, addr_verifier, rpc_url, private_key).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::RevokeEvmVk {
+            addr_registry,
+            version,
+            rpc_url,
+            private_key,
+        } => revoke_evm_vk(addr_registry, version, rpc_url, private_key).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::CreateEvmPaymaster {
             settings_path,
             sol_code_path,
+            abi_path,
+            verify_gas_limit,
+        } => create_evm_paymaster(settings_path, sol_code_path, abi_path, verify_gas_limit),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::DeployEvmPaymaster {
+            sol_code_path,
+            addr_verifier,
+            entry_point,
             rpc_url,
             addr_path,
             optimizer_runs,
             private_key,
         } => {
-            deploy_da_evm(
-                data,
-                settings_path,
+            deploy_evm_paymaster(
                 sol_code_path,
+                addr_verifier,
+                entry_point,
                 rpc_url,
                 addr_path,
                 optimizer_runs,
@@ -443,29 +966,106 @@ pub async fn run(command: Commands) -> Result<String, Box<dyn Error>> {
             .await
         }
         #[cfg(not(target_arch = "wasm32"))]
-        Commands::VerifyEvm {
-            proof_path,
+        Commands::CreateEvmBlobVerifier {
+            settings_path,
+            sol_code_path,
+            abi_path,
+        } => create_evm_blob_verifier(settings_path, sol_code_path, abi_path),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::DeployEvmBlobVerifier {
+            sol_code_path,
             addr_verifier,
             rpc_url,
-            addr_da,
-            addr_vk,
-        } => verify_evm(proof_path, addr_verifier, rpc_url, addr_da, addr_vk).await,
-    }
-}
-
-/// Get the srs path
-pub fn get_srs_path(logrows: u32, srs_path: Option<PathBuf>, commitment: Commitments) -> PathBuf {
-    if let Some(srs_path) = srs_path {
-        srs_path
-    } else {
-        if !Path::new(&*EZKL_SRS_REPO_PATH).exists() {
-            std::fs::create_dir_all(&*EZKL_SRS_REPO_PATH).unwrap();
+            addr_path,
+            optimizer_runs,
+            private_key,
+        } => {
+            deploy_evm_blob_verifier(
+                sol_code_path,
+                addr_verifier,
+                rpc_url,
+                addr_path,
+                optimizer_runs,
+                private_key,
+            )
+            .await
         }
-        match commitment {
-            Commitments::KZG => Path::new(&*EZKL_SRS_REPO_PATH).join(format!("kzg{}.srs", logrows)),
-            Commitments::IPA => Path::new(&*EZKL_SRS_REPO_PATH).join(format!("ipa{}.srs", logrows)),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::PostProofBlob {
+            proof_path,
+            addr_verifier,
+            trusted_setup_path,
+            rpc_url,
+            private_key,
+        } => {
+            post_proof_blob(
+                proof_path,
+                addr_verifier,
+                trusted_setup_path,
+                rpc_url,
+                private_key,
+            )
+            .await
         }
-    }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::PredictEvmAddress {
+            sol_code_path,
+            vk_path,
+            optimizer_runs,
+            contract_name,
+        } => predict_evm_address(sol_code_path, vk_path, optimizer_runs, &contract_name),
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::DeployEvmDataAttestation {
+            data,
+            settings_path,
+            sol_code_path,
+            rpc_url,
+            addr_path,
+            optimizer_runs,
+            private_key,
+        } => {
+            deploy_da_evm(
+                data,
+                settings_path,
+                sol_code_path,
+                rpc_url,
+                addr_path,
+                optimizer_runs,
+                private_key,
+            )
+            .await
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::VerifyEvm {
+            proof_path,
+            addr_verifier,
+            rpc_url,
+            addr_da,
+            addr_vk,
+        } => verify_evm(proof_path, addr_verifier, rpc_url, addr_da, addr_vk).await,
+        #[cfg(not(target_arch = "wasm32"))]
+        Commands::EstimateGas {
+            proof_path,
+            sol_code_path,
+            vk_sol_path,
+            optimizer_runs,
+        } => estimate_gas(proof_path, sol_code_path, vk_sol_path, optimizer_runs).await,
+    }
+}
+
+/// Get the srs path
+pub fn get_srs_path(logrows: u32, srs_path: Option<PathBuf>, commitment: Commitments) -> PathBuf {
+    if let Some(srs_path) = srs_path {
+        srs_path
+    } else {
+        if !Path::new(&*EZKL_SRS_REPO_PATH).exists() {
+            std::fs::create_dir_all(&*EZKL_SRS_REPO_PATH).unwrap();
+        }
+        match commitment {
+            Commitments::KZG => Path::new(&*EZKL_SRS_REPO_PATH).join(format!("kzg{}.srs", logrows)),
+            Commitments::IPA => Path::new(&*EZKL_SRS_REPO_PATH).join(format!("ipa{}.srs", logrows)),
+        }
+    }
 }
 
 fn srs_exists_check(logrows: u32, srs_path: Option<PathBuf>, commitment: Commitments) -> bool {
@@ -476,12 +1076,27 @@ pub(crate) fn gen_srs_cmd(
     srs_path: PathBuf,
     logrows: u32,
     commitment: Commitments,
+    unsafe_setup: bool,
 ) -> Result<String, Box<dyn Error>> {
+    // an srs left over at the path must not keep the flag of an unsafe one
+    clear_unsafe_srs_flag(&srs_path)?;
     match commitment {
+        Commitments::KZG if unsafe_setup => {
+            let params = gen_unsafe_srs(logrows);
+            save_params::<KZGCommitmentScheme<Bn256>>(&srs_path, &params)?;
+            flag_unsafe_srs(&srs_path)?;
+            warn!(
+                "generated an UNSAFE SRS at {} from publicly known toxic waste, only use it for local development",
+                srs_path.display()
+            );
+        }
         Commitments::KZG => {
             let params = gen_srs::<KZGCommitmentScheme<Bn256>>(logrows);
             save_params::<KZGCommitmentScheme<Bn256>>(&srs_path, &params)?;
         }
+        Commitments::IPA if unsafe_setup => {
+            return Err("IPA SRS have no toxic waste, generate them without --unsafe".into());
+        }
         Commitments::IPA => {
             let params = gen_srs::<IPACommitmentScheme<G1Affine>>(logrows);
             save_params::<IPACommitmentScheme<G1Affine>>(&srs_path, &params)?;
@@ -490,24 +1105,39 @@ pub(crate) fn gen_srs_cmd(
     Ok(String::new())
 }
 
+/// Downloads the SRS at `uri` to `path`, see [crate::transfer::download]. The file is only moved
+/// into place once its hash matches `expected_hash`.
 #[cfg(not(target_arch = "wasm32"))]
-async fn fetch_srs(uri: &str) -> Result<Vec<u8>, Box<dyn Error>> {
-    let pb = {
-        let pb = init_spinner();
-        pb.set_message("Downloading SRS (this may take a while) ...");
-        pb
-    };
-    let client = reqwest::Client::new();
-    // wasm doesn't require it to be mutable
-    #[allow(unused_mut)]
-    let mut resp = client.get(uri).body(vec![]).send().await?;
-    let mut buf = vec![];
-    while let Some(chunk) = resp.chunk().await? {
-        buf.extend(chunk.to_vec());
-    }
+async fn download_srs(uri: &str, path: &Path, expected_hash: &str) -> Result<(), Box<dyn Error>> {
+    crate::transfer::download(
+        uri,
+        path,
+        Some(expected_hash),
+        crate::transfer::DEFAULT_CHUNK_SIZE,
+    )
+    .await?;
+    Ok(())
+}
 
-    pb.finish_with_message("SRS downloaded.");
-    Ok(std::mem::take(&mut buf))
+/// Trims the smallest cached public KZG SRS with more than `2^k` rows down to `2^k` rows. Public
+/// SRS files are themselves trimmed from the same ceremony, so the result matches the published
+/// hash for `k`.
+#[cfg(not(target_arch = "wasm32"))]
+fn trim_cached_srs(k: u32, path: &Path) -> Result<bool, Box<dyn Error>> {
+    let cached = (k + 1..=crate::graph::MAX_PUBLIC_SRS)
+        .map(|j| get_srs_path(j, None, Commitments::KZG))
+        .find(|p| p.exists() && !is_unsafe_srs(p));
+    let Some(cached) = cached else {
+        return Ok(false);
+    };
+    info!(
+        "trimming cached SRS at {} to {} logrows",
+        cached.display(),
+        k
+    );
+    let params = load_srs_prover_trimmed::<KZGCommitmentScheme<Bn256>>(cached, k)?;
+    save_params::<KZGCommitmentScheme<Bn256>>(&path.to_path_buf(), &params)?;
+    Ok(true)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -555,6 +1185,41 @@ fn check_srs_hash(
     Ok(hash)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn get_model_cmd(
+    url: String,
+    sha256: String,
+    model: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    let cached = Model::fetch_onnx(&url, &sha256).await?;
+    std::fs::copy(&cached, &model)?;
+    info!("model saved to {}", model.display());
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn fetch_artifact(
+    url: String,
+    output: PathBuf,
+    sha256: Option<String>,
+    chunk_size: u64,
+) -> Result<String, Box<dyn Error>> {
+    let hash = crate::transfer::download(&url, &output, sha256.as_deref(), chunk_size).await?;
+    info!("artifact saved to {} (sha256 {})", output.display(), hash);
+    Ok(hash)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn publish_artifact(
+    path: PathBuf,
+    url: String,
+    chunk_size: u64,
+) -> Result<String, Box<dyn Error>> {
+    let hash = crate::transfer::upload(&path, &url, chunk_size).await?;
+    info!("{} uploaded to {} (sha256 {})", path.display(), url, hash);
+    Ok(hash)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) async fn get_srs_cmd(
     srs_path: Option<PathBuf>,
@@ -592,31 +1257,29 @@ pub(crate) async fn get_srs_cmd(
         return Err(err_string.into());
     };
 
+    let path = get_srs_path(k, srs_path.clone(), commitment);
+    if is_unsafe_srs(&path) {
+        return Err(format!(
+            "the SRS at {} was generated with gen-srs --unsafe, remove it to get the public SRS",
+            path.display()
+        )
+        .into());
+    }
+
     if !srs_exists_check(k, srs_path.clone(), commitment) {
         if matches!(commitment, Commitments::KZG) {
-            info!("SRS does not exist, downloading...");
-            let srs_uri = format!("{}{}", PUBLIC_SRS_URL, k);
-            let mut reader = Cursor::new(fetch_srs(&srs_uri).await?);
-            // check the SRS
-            #[cfg(not(target_arch = "wasm32"))]
-            let pb = init_spinner();
-            #[cfg(not(target_arch = "wasm32"))]
-            pb.set_message("Validating SRS (this may take a while) ...");
-            let params = ParamsKZG::<Bn256>::read(&mut reader)?;
-            #[cfg(not(target_arch = "wasm32"))]
-            pb.finish_with_message("SRS validated.");
-
-            info!("Saving SRS to disk...");
-            let mut file = std::fs::File::create(get_srs_path(k, srs_path.clone(), commitment))?;
-            let mut buffer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, &mut file);
-            params.write(&mut buffer)?;
-
-            info!("Saved SRS to disk.");
-
-            info!("SRS downloaded");
+            let path = get_srs_path(k, srs_path.clone(), commitment);
+            if !trim_cached_srs(k, &path)? {
+                info!("SRS does not exist, downloading...");
+                let expected_hash = crate::srs_sha::PUBLIC_SRS_SHA256_HASHES
+                    .get(&k)
+                    .ok_or(format!("SRS (k={}) hash not found in public set", k))?;
+                download_srs(&format!("{}{}", PUBLIC_SRS_URL, k), &path, expected_hash).await?;
+                info!("SRS downloaded");
+            }
         } else {
             let path = get_srs_path(k, srs_path.clone(), commitment);
-            gen_srs_cmd(path, k, commitment)?;
+            gen_srs_cmd(path, k, commitment, false)?;
         }
     } else {
         info!("SRS already exists at that path");
@@ -629,29 +1292,78 @@ pub(crate) async fn get_srs_cmd(
     Ok(String::new())
 }
 
+pub(crate) fn template(
+    task: TemplateTask,
+    num_features: usize,
+    hidden_size: usize,
+    seed: u64,
+    model: PathBuf,
+    data: PathBuf,
+    settings_path: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    let template = Template::new(task, num_features, hidden_size, seed)?;
+    template.save(&model, &data)?;
+    gen_circuit_settings(model.clone(), settings_path, template.run_args.clone())?;
+    info!(
+        "wrote a {} model to {} and example data to {}, its settings are those of gen-settings {}",
+        task,
+        model.display(),
+        data.display(),
+        template.run_args.to_flags().join(" ")
+    );
+    Ok(String::new())
+}
+
 pub(crate) fn table(model: PathBuf, run_args: RunArgs) -> Result<String, Box<dyn Error>> {
     let model = Model::from_run_args(&run_args, &model)?;
     info!("\n {}", model.table_nodes());
     Ok(String::new())
 }
 
+pub(crate) fn inspect(
+    model: PathBuf,
+    run_args: RunArgs,
+    sort_by: crate::graph::model::ReportSort,
+    top: Option<usize>,
+) -> Result<String, Box<dyn Error>> {
+    let model = Model::from_run_args(&run_args, &model)?;
+    let mut reports = model.report(&run_args)?;
+    sort_by.sort(&mut reports);
+    if let Some(top) = top {
+        reports.truncate(top);
+    }
+
+    // the json is the result of the command, see `--format json`
+    let mut table = tabled::Table::new(&reports);
+    table.with(tabled::settings::Style::modern());
+    info!("\n{}", table);
+    Ok(serde_json::to_string_pretty(&reports)?)
+}
+
 pub(crate) async fn gen_witness(
     compiled_circuit_path: PathBuf,
     data: PathBuf,
     output: Option<PathBuf>,
     vk_path: Option<PathBuf>,
     srs_path: Option<PathBuf>,
+    encryption_key: Option<PathBuf>,
+    seal_outputs_to: Option<PathBuf>,
+    caller: Option<H160Flag>,
+    user_op: Option<UserOpFlag>,
+    full_assignment: Option<PathBuf>,
 ) -> Result<GraphWitness, Box<dyn Error>> {
+    let _warnings = crate::warnings::scope();
     // these aren't real values so the sanity checks are mostly meaningless
 
-    let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
-    let data = GraphData::from_path(data)?;
+    let mut circuit = load_compiled_circuit(compiled_circuit_path, encryption_key)?;
+    let data = GraphData::from_path_with_limits(data, &circuit.json_limits()?)?;
     let settings = circuit.settings().clone();
 
     let vk = if let Some(vk) = vk_path {
         Some(load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(
             vk,
             settings.clone(),
+            false,
         )?)
     } else {
         None
@@ -665,50 +1377,58 @@ pub(crate) async fn gen_witness(
     // if any of the settings have kzg visibility then we need to load the srs
 
     let start_time = Instant::now();
-    let witness = if settings.module_requires_polycommit() {
-        if get_srs_path(
-            settings.run_args.logrows,
-            srs_path.clone(),
-            settings.run_args.commitment,
-        )
-        .exists()
-        {
-            match settings.run_args.commitment {
-                Commitments::KZG => {
-                    let srs: ParamsKZG<Bn256> = load_params_prover::<KZGCommitmentScheme<Bn256>>(
-                        srs_path.clone(),
-                        settings.run_args.logrows,
-                        settings.run_args.commitment,
-                    )?;
-                    circuit.forward::<KZGCommitmentScheme<_>>(
-                        &mut input,
-                        vk.as_ref(),
-                        Some(&srs),
-                        false,
-                    )?
-                }
-                Commitments::IPA => {
-                    let srs: ParamsIPA<G1Affine> =
-                        load_params_prover::<IPACommitmentScheme<G1Affine>>(
+    let num_threads = settings.run_args.num_threads;
+    let mut witness = crate::threads::install(num_threads, "witness generation", || {
+        Ok(if settings.module_requires_polycommit() {
+            if get_srs_path(
+                settings.run_args.logrows,
+                srs_path.clone(),
+                settings.run_args.commitment,
+            )
+            .exists()
+            {
+                match settings.run_args.commitment {
+                    Commitments::KZG => {
+                        let srs: ParamsKZG<Bn256> = load_params_prover::<KZGCommitmentScheme<Bn256>>(
                             srs_path.clone(),
                             settings.run_args.logrows,
                             settings.run_args.commitment,
                         )?;
-                    circuit.forward::<IPACommitmentScheme<_>>(
-                        &mut input,
-                        vk.as_ref(),
-                        Some(&srs),
-                        false,
-                    )?
+                        circuit.forward::<KZGCommitmentScheme<_>>(
+                            &mut input,
+                            vk.as_ref(),
+                            Some(&srs),
+                            false,
+                        )?
+                    }
+                    Commitments::IPA => {
+                        let srs: ParamsIPA<G1Affine> =
+                            load_params_prover::<IPACommitmentScheme<G1Affine>>(
+                                srs_path.clone(),
+                                settings.run_args.logrows,
+                                settings.run_args.commitment,
+                            )?;
+                        circuit.forward::<IPACommitmentScheme<_>>(
+                            &mut input,
+                            vk.as_ref(),
+                            Some(&srs),
+                            false,
+                        )?
+                    }
                 }
+            } else {
+                warn!("SRS for poly commit does not exist (will be ignored)");
+                circuit.forward::<KZGCommitmentScheme<Bn256>>(
+                    &mut input,
+                    vk.as_ref(),
+                    None,
+                    false,
+                )?
             }
         } else {
-            warn!("SRS for poly commit does not exist (will be ignored)");
             circuit.forward::<KZGCommitmentScheme<Bn256>>(&mut input, vk.as_ref(), None, false)?
-        }
-    } else {
-        circuit.forward::<KZGCommitmentScheme<Bn256>>(&mut input, vk.as_ref(), None, false)?
-    };
+        })
+    })?;
 
     // print each variable tuple (symbol, value) as symbol=value
     trace!(
@@ -723,10 +1443,61 @@ pub(crate) async fn gen_witness(
         start_time.elapsed()
     );
 
+    if seal_outputs_to.is_some() != settings.run_args.seal_outputs {
+        return Err(if settings.run_args.seal_outputs {
+            "the circuit seals its outputs, set a public key with --seal-outputs-to"
+        } else {
+            "the circuit doesn't constrain sealed outputs, generate its settings with --seal-outputs"
+        }
+        .into());
+    }
+    if let Some(key_path) = seal_outputs_to {
+        let public_key =
+            crate::graph::sealed::public_key_from_hex(&std::fs::read_to_string(&key_path)?)?;
+        witness.sealed_outputs = Some(crate::graph::sealed::seal(
+            &witness.outputs,
+            &public_key,
+            rand::rngs::OsRng,
+        )?);
+    }
+
+    witness.expected_outputs = data.expected_outputs.clone();
+    if let crate::graph::DataSource::OnChain(source) = &data.input_data {
+        if source.prove_storage || !source.storage_proofs.is_empty() {
+            witness.state_proof = Some(crate::eth::fetch_block_state_proof(source).await?);
+        }
+    }
+    if let Some(report) = witness.check_expected_outputs(&settings) {
+        if report.passed() {
+            info!("{}", report);
+        } else {
+            warn!("{}", report);
+        }
+    }
+
+    if let Some(caller) = caller {
+        witness.caller = Some(crate::eth::address_to_field(&caller.into()));
+    } else if let Some(op) = user_op {
+        witness.caller = Some(crate::eth::user_operation_binding(
+            &op.sender,
+            op.nonce,
+            &op.call_data,
+        ));
+    } else if settings.run_args.bind_caller {
+        warn!("the circuit binds proofs to a caller but none was set, proving will fail");
+    }
+
     if let Some(output_path) = output {
         witness.save(output_path)?;
     }
 
+    if let Some(assignment_path) = full_assignment {
+        circuit.load_graph_witness(&witness)?;
+        let instances = circuit.prepare_public_inputs(&witness)?;
+        Assignment::new(circuit.settings(), witness.clone(), instances).save(&assignment_path)?;
+        info!("wrote assignment to {}", assignment_path.display());
+    }
+
     // print the witness in debug
     debug!("witness: \n {}", witness.as_json()?.to_colored_json_auto()?);
 
@@ -739,97 +1510,808 @@ pub(crate) fn gen_circuit_settings(
     params_output: PathBuf,
     run_args: RunArgs,
 ) -> Result<String, Box<dyn Error>> {
+    let _warnings = crate::warnings::scope();
     let circuit = GraphCircuit::from_run_args(&run_args, &model_path)?;
     let params = circuit.settings();
     params.save(&params_output)?;
     Ok(String::new())
 }
 
-// not for wasm targets
 #[cfg(not(target_arch = "wasm32"))]
-pub(crate) fn init_spinner() -> ProgressBar {
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.set_draw_target(indicatif::ProgressDrawTarget::stdout());
-    pb.enable_steady_tick(Duration::from_millis(200));
-    pb.set_style(
-        ProgressStyle::with_template("[{elapsed_precise}] {spinner:.blue} {msg}")
-            .unwrap()
-            .tick_strings(&[
-                "------ - ✨ ",
-                "------ - ⏳ ",
-                "------ - 🌎 ",
-                "------ - 🔎 ",
-                "------ - 🥹 ",
-                "------ - 🫠 ",
-                "------ - 👾 ",
-            ]),
+pub(crate) fn migrate(
+    settings_path: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    let reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, File::open(&settings_path)?);
+    let value: serde_json::Value = serde_json::from_reader(reader)?;
+    let from_version = crate::graph::migrate::settings_schema_version(&value);
+
+    let settings = GraphSettings::from_value(value)?;
+    let output = output.unwrap_or(settings_path);
+    settings.save(&output)?;
+
+    let msg = format!(
+        "migrated settings from schema v{} to v{}",
+        from_version,
+        crate::graph::SETTINGS_SCHEMA_VERSION
     );
-    pb
+    info!("{}", msg);
+    Ok(msg)
 }
 
-// not for wasm targets
 #[cfg(not(target_arch = "wasm32"))]
-pub(crate) fn init_bar(len: u64) -> ProgressBar {
-    let pb = ProgressBar::new(len);
-    pb.set_draw_target(indicatif::ProgressDrawTarget::stdout());
-    pb.enable_steady_tick(Duration::from_millis(200));
-    let sty = ProgressStyle::with_template(
-        "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-    )
-    .unwrap()
-    .progress_chars("##-");
-    pb.set_style(sty);
-    pb
-}
+pub(crate) fn compat_check(
+    proof_path: PathBuf,
+    settings_path: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    use crate::compat::{read_proof_value, CompatReport};
 
-#[cfg(not(target_arch = "wasm32"))]
-use colored_json::ToColoredJson;
+    let proof = read_proof_value(&proof_path)?;
+    let reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, File::open(&settings_path)?);
+    let settings: serde_json::Value = serde_json::from_reader(reader)?;
 
-#[derive(Debug, Clone, Tabled)]
-/// Accuracy tearsheet
-pub struct AccuracyResults {
-    mean_error: f32,
-    median_error: f32,
-    max_error: f32,
-    min_error: f32,
-    mean_abs_error: f32,
-    median_abs_error: f32,
-    max_abs_error: f32,
-    min_abs_error: f32,
-    mean_squared_error: f32,
-    mean_percent_error: f32,
-    mean_abs_percent_error: f32,
+    let report = CompatReport::new(&proof, &settings)?;
+    info!(
+        "proof format v{} with settings schema v{} can be verified by releases {}, including this binary (reads up to proof format v{} and settings schema v{})",
+        report.proof_format_version,
+        report.settings_schema_version,
+        report.verifiable_by,
+        report.supported_proof_format_version,
+        report.supported_settings_schema_version
+    );
+    Ok(serde_json::to_string_pretty(&report)?)
 }
 
-impl AccuracyResults {
-    /// Create a new accuracy results struct
-    pub fn new(
-        mut original_preds: Vec<crate::tensor::Tensor<f32>>,
-        mut calibrated_preds: Vec<crate::tensor::Tensor<f32>>,
-    ) -> Result<Self, Box<dyn Error>> {
-        let mut errors = vec![];
-        let mut abs_errors = vec![];
-        let mut squared_errors = vec![];
-        let mut percentage_errors = vec![];
-        let mut abs_percentage_errors = vec![];
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn completions(
+    shell: crate::commands::CompletionShell,
+) -> Result<String, Box<dyn Error>> {
+    use clap::CommandFactory;
+
+    let mut script = vec![];
+    clap_complete::generate(
+        clap_complete::Shell::from(shell),
+        &mut crate::commands::Cli::command(),
+        "ezkl",
+        &mut script,
+    );
+    Ok(String::from_utf8(script)?)
+}
 
-        for (original, calibrated) in original_preds.iter_mut().zip(calibrated_preds.iter_mut()) {
-            original.flatten();
-            calibrated.flatten();
-            let error = (original.clone() - calibrated.clone())?;
-            let abs_error = error.map(|x| x.abs());
-            let squared_error = error.map(|x| x.powi(2));
-            let percentage_error = error.enum_map(|i, x| Ok::<_, TensorError>(x / original[i]))?;
-            let abs_percentage_error = percentage_error.map(|x| x.abs());
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn run_pipeline(
+    config_path: PathBuf,
+    force: bool,
+) -> Result<crate::pipeline::PipelineReport, Box<dyn Error>> {
+    use crate::pipeline::{PipelineCache, PipelineConfig, PipelineReport, StageKind, StageOutcome};
+
+    let config = PipelineConfig::load(&config_path)?;
+    std::fs::create_dir_all(&config.work_dir)?;
+    let cache_path = config.cache_path();
+    let mut cache = PipelineCache::load(&cache_path)?;
+
+    let mut outcomes = vec![];
+    for mut stage in config.stages()? {
+        if stage.kind == StageKind::Setup && config.srs_path.is_none() {
+            stage = stage.with_default_srs(&GraphSettings::load(&config.settings_path())?);
+        }
+        let fingerprint = stage.fingerprint()?;
+        if !force && cache.is_fresh(&stage, &fingerprint) {
+            info!("{}: up to date, skipping", stage.kind);
+            outcomes.push(StageOutcome {
+                stage: stage.kind,
+                cached: true,
+                seconds: 0.0,
+            });
+            continue;
+        }
 
-            errors.extend(error);
-            abs_errors.extend(abs_error);
-            squared_errors.extend(squared_error);
-            percentage_errors.extend(percentage_error);
-            abs_percentage_errors.extend(abs_percentage_error);
+        info!("{}: running", stage.kind);
+        cache.forget(&stage);
+        cache.save(&cache_path)?;
+        let start = Instant::now();
+        match stage.kind {
+            StageKind::GenSettings => {
+                gen_circuit_settings(
+                    config.model.clone(),
+                    config.generated_settings_path(),
+                    config.run_args()?,
+                )?;
+            }
+            StageKind::Calibrate => {
+                let calibration = config
+                    .calibration
+                    .clone()
+                    .ok_or("the pipeline has no calibration")?;
+                std::fs::copy(config.generated_settings_path(), config.settings_path())?;
+                calibrate(
+                    config.model.clone(),
+                    config.data.clone(),
+                    config.settings_path(),
+                    calibration.target()?,
+                    calibration.lookup_safety_margin,
+                    calibration.scales,
+                    calibration.scale_rebase_multiplier,
+                    calibration.only_range_check_rebase,
+                    calibration.max_logrows,
+                    None,
+                    calibration.bias_correction,
+                    calibration.max_lookup_domains,
+                )?;
+            }
+            StageKind::Compile => {
+                compile_circuit(
+                    config.model.clone(),
+                    config.compiled_circuit_path(),
+                    config.settings_path(),
+                    None,
+                )?;
+            }
+            StageKind::Setup => {
+                setup(
+                    config.compiled_circuit_path(),
+                    config.srs_path.clone(),
+                    config.vk_path(),
+                    config.pk_path(),
+                    None,
+                    false,
+                    None,
+                    None,
+                    None,
+                    false,
+                )?;
+            }
+            StageKind::Witness => {
+                gen_witness(
+                    config.compiled_circuit_path(),
+                    config.data.clone(),
+                    Some(config.witness_path()),
+                    Some(config.vk_path()),
+                    config.srs_path.clone(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+                .await?;
+            }
+            StageKind::Prove => {
+                let settings = GraphSettings::load(&config.settings_path())?;
+                prove(
+                    config.witness_path(),
+                    config.compiled_circuit_path(),
+                    config.pk_path(),
+                    Some(config.proof_path()),
+                    config.srs_path.clone(),
+                    ProofType::Single,
+                    settings.run_args.check_mode,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    None,
+                    false,
+                    false,
+                    None,
+                    None,
+                    false,
+                    false,
+                )?;
+            }
+            // always runs once the proof is up to date, below
+            StageKind::Verify => {}
         }
+        outcomes.push(StageOutcome {
+            stage: stage.kind,
+            cached: false,
+            seconds: start.elapsed().as_secs_f64(),
+        });
+        cache.record(&stage, fingerprint);
+        cache.save(&cache_path)?;
+    }
 
-        let mean_percent_error =
+    info!("{}: running", StageKind::Verify);
+    let start = Instant::now();
+    let verified = verify(
+        config.proof_path(),
+        config.settings_path(),
+        config.vk_path(),
+        config.srs_path.clone(),
+        false,
+        None,
+        false,
+    )?;
+    if !verified {
+        return Err(format!("the proof {} did not verify", config.proof_path().display()).into());
+    }
+    outcomes.push(StageOutcome {
+        stage: StageKind::Verify,
+        cached: false,
+        seconds: start.elapsed().as_secs_f64(),
+    });
+
+    let report = PipelineReport {
+        stages: outcomes,
+        proof_path: config.proof_path(),
+    };
+    info!(
+        "ran {} of {} stages of {}, the proof verified",
+        report.stages.iter().filter(|s| !s.cached).count(),
+        report.stages.len(),
+        config_path.display()
+    );
+    Ok(report)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn bench(
+    model: PathBuf,
+    settings_path: PathBuf,
+    data: PathBuf,
+    srs_path: Option<PathBuf>,
+    iterations: usize,
+    work_dir: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    if iterations == 0 {
+        return Err("benchmarking needs at least one iteration".into());
+    }
+    std::fs::create_dir_all(&work_dir)?;
+    let compiled_circuit = work_dir.join("model.compiled");
+    let vk_path = work_dir.join("vk.key");
+    let pk_path = work_dir.join("pk.key");
+    let witness_path = work_dir.join("witness.json");
+    let proof_path = work_dir.join("proof.json");
+
+    compile_circuit(model, compiled_circuit.clone(), settings_path.clone(), None)?;
+    let settings = GraphSettings::load(&settings_path)?;
+
+    let mut samples = crate::bench::BenchSamples::default();
+    let mut proof_size_bytes = 0;
+    for i in 0..iterations {
+        info!("benchmark iteration {}/{}", i + 1, iterations);
+
+        crate::memory::reset_peak_rss();
+        let start = Instant::now();
+        setup(
+            compiled_circuit.clone(),
+            srs_path.clone(),
+            vk_path.clone(),
+            pk_path.clone(),
+            None,
+            false,
+            None,
+            None,
+            None,
+            false,
+        )?;
+        samples.record("setup", start.elapsed());
+
+        crate::memory::reset_peak_rss();
+        let start = Instant::now();
+        gen_witness(
+            compiled_circuit.clone(),
+            data.clone(),
+            Some(witness_path.clone()),
+            Some(vk_path.clone()),
+            srs_path.clone(),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+        samples.record("witness", start.elapsed());
+
+        crate::memory::reset_peak_rss();
+        let start = Instant::now();
+        let snark = prove(
+            witness_path.clone(),
+            compiled_circuit.clone(),
+            pk_path.clone(),
+            Some(proof_path.clone()),
+            srs_path.clone(),
+            ProofType::Single,
+            settings.check_mode,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            false,
+            false,
+            None,
+            None,
+            false,
+            false,
+        )?;
+        samples.record("prove", start.elapsed());
+        proof_size_bytes = snark.proof.len();
+
+        crate::memory::reset_peak_rss();
+        let start = Instant::now();
+        let verified = verify(
+            proof_path.clone(),
+            settings_path.clone(),
+            vk_path.clone(),
+            srs_path.clone(),
+            false,
+            None,
+            false,
+        )?;
+        samples.record("verify", start.elapsed());
+        if !verified {
+            return Err("the benchmarked proof did not verify".into());
+        }
+    }
+
+    let report = samples.report(&settings, proof_size_bytes);
+    let json = serde_json::to_string_pretty(&report)?;
+    if let Some(output) = output {
+        std::fs::write(output, &json)?;
+    }
+    info!("\n{}", report.to_table());
+    Ok(json)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn serve(
+    compiled_circuit: PathBuf,
+    pk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    encryption_key: Option<PathBuf>,
+    mut config: crate::serve::ServeConfig,
+) -> Result<String, Box<dyn Error>> {
+    if config.model.is_none() {
+        config.model = compiled_circuit
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string());
+    }
+    let circuit = load_compiled_circuit(compiled_circuit, encryption_key)?;
+    let settings = circuit.settings().clone();
+    if settings.run_args.commitment != Commitments::KZG {
+        return Err("the proving service only supports KZG commitments".into());
+    }
+
+    info!("loading the proving key and SRS");
+    let pk = load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(pk_path, circuit.params(), false)?;
+    let params = load_params_prover::<KZGCommitmentScheme<Bn256>>(
+        srs_path,
+        settings.run_args.logrows,
+        Commitments::KZG,
+    )?;
+
+    let model = crate::serve::LoadedModel {
+        circuit,
+        pk,
+        params,
+    };
+    crate::serve::serve(model, config).await?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn gc_jobs(
+    jobs_dir: PathBuf,
+    max_age: Option<u64>,
+    max_bytes: Option<u64>,
+    model: Option<String>,
+    tenant: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    use crate::retention::{collect_garbage, purge, RetentionPolicy};
+
+    let report = if model.is_some() || tenant.is_some() {
+        purge(&jobs_dir, &crate::api::PurgeRequest { model, tenant })?
+    } else {
+        collect_garbage(&jobs_dir, &RetentionPolicy { max_age, max_bytes })?
+    };
+    info!(
+        "removed {} jobs, freeing {} bytes",
+        report.removed_jobs.len(),
+        report.freed_bytes
+    );
+    Ok(serde_json::to_string(&report)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn gen_field_vectors(output: PathBuf) -> Result<String, Box<dyn Error>> {
+    let vectors = crate::fieldutils::reference_vectors()?;
+    std::fs::write(&output, serde_json::to_string_pretty(&vectors)?)?;
+    let msg = format!(
+        "wrote {} reference vectors to {}",
+        vectors.len(),
+        output.display()
+    );
+    info!("{}", msg);
+    Ok(msg)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn gen_golden(output_dir: PathBuf, check: bool) -> Result<String, Box<dyn Error>> {
+    use crate::circuit::golden::{self, GoldenRecord};
+
+    if !check {
+        std::fs::create_dir_all(&output_dir)?;
+    }
+
+    let ops = golden::golden_ops();
+    let pb = init_bar(ops.len() as u64);
+    let mut mismatches = vec![];
+    for op in ops {
+        let name = golden::golden_name(&op);
+        pb.set_message(name.clone());
+        let record = golden::gen_golden(&op)?;
+        let path = output_dir.join(format!("{}.json", name));
+        if check {
+            match GoldenRecord::load(&path) {
+                Ok(expected) if expected == record => {}
+                Ok(expected) => {
+                    if expected.outputs != record.outputs {
+                        mismatches.push(format!("{}: outputs changed", name));
+                    } else {
+                        mismatches.push(format!("{}: verifying key changed", name));
+                    }
+                }
+                Err(e) => mismatches.push(format!("{}: {}", name, e)),
+            }
+        } else {
+            record.save(&path)?;
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    if !mismatches.is_empty() {
+        return Err(format!(
+            "golden records in {} don't match:\n{}",
+            output_dir.display(),
+            mismatches.join("\n")
+        )
+        .into());
+    }
+    let msg = if check {
+        format!("golden records in {} match", output_dir.display())
+    } else {
+        format!("wrote golden records to {}", output_dir.display())
+    };
+    info!("{}", msg);
+    Ok(msg)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn self_test(op: Option<String>) -> Result<String, Box<dyn Error>> {
+    use crate::circuit::soundness::{check_soundness, soundness_cases};
+
+    let cases = soundness_cases()
+        .into_iter()
+        .filter(|c| match &op {
+            Some(op) => c.op.as_string().starts_with(op.as_str()),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    if cases.is_empty() {
+        return Err(format!("no op matches {}", op.unwrap_or_default()).into());
+    }
+
+    let pb = init_bar(cases.len() as u64);
+    let mut unsound = vec![];
+    for case in &cases {
+        pb.set_message(case.op.as_string());
+        let report = check_soundness(case)?;
+        if !report.is_sound() {
+            unsound.push(format!(
+                "{}: tampered output cells {:?} are accepted",
+                report.op, report.accepted
+            ));
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    if !unsound.is_empty() {
+        return Err(format!("under-constrained ops:\n{}", unsound.join("\n")).into());
+    }
+    let msg = format!("all {} ops reject tampered outputs", cases.len());
+    info!("{}", msg);
+    Ok(msg)
+}
+
+/// The lock of the current environment, hashing the settings and SRS when they exist.
+#[cfg(not(target_arch = "wasm32"))]
+fn current_env_lock(
+    settings_path: &PathBuf,
+    srs_path: Option<PathBuf>,
+) -> Result<crate::env_lock::EnvLock, Box<dyn Error>> {
+    let (settings_hash, srs_path) = if settings_path.exists() {
+        let settings = GraphSettings::load(settings_path)?;
+        let srs_path = get_srs_path(
+            settings.run_args.logrows,
+            srs_path,
+            settings.run_args.commitment,
+        );
+        (Some(get_file_hash(settings_path)?), Some(srs_path))
+    } else {
+        (None, srs_path)
+    };
+    let srs_hash = match srs_path {
+        Some(p) if p.exists() => Some(get_file_hash(&p)?),
+        _ => None,
+    };
+    Ok(crate::env_lock::EnvLock::current(settings_hash, srs_hash))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn export_env(
+    settings_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    output: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    let lock = current_env_lock(&settings_path, srs_path)?;
+    if lock.settings_hash.is_none() {
+        warn!(
+            "no settings at {}, they won't be locked",
+            settings_path.display()
+        );
+    }
+    if lock.srs_hash.is_none() {
+        warn!("no SRS found, it won't be locked");
+    }
+    lock.save(&output)?;
+    info!("wrote environment lock to {}", output.display());
+    Ok(serde_json::to_string_pretty(&lock)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn check_env(
+    lock: PathBuf,
+    settings_path: PathBuf,
+    srs_path: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    let locked = crate::env_lock::EnvLock::load(&lock)?;
+    let current = current_env_lock(&settings_path, srs_path)?;
+    let (breaking, host) = locked.diff(&current);
+    for difference in &host {
+        warn!("{}", difference);
+    }
+    if !breaking.is_empty() {
+        return Err(format!(
+            "environment doesn't match {}:\n{}",
+            lock.display(),
+            breaking.join("\n")
+        )
+        .into());
+    }
+    let msg = format!("environment matches {}", lock.display());
+    info!("{}", msg);
+    Ok(msg)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn unseal_outputs(
+    witness: PathBuf,
+    secret_key: PathBuf,
+    output: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    let witness = GraphWitness::from_path(witness)?;
+    let sealed = witness
+        .sealed_outputs
+        .ok_or("the witness has no sealed outputs, generate it with --seal-outputs-to")?;
+    let secret_key =
+        crate::graph::sealed::secret_key_from_hex(&std::fs::read_to_string(&secret_key)?)?;
+    let outputs = sealed.unseal(&secret_key)?;
+
+    let json = serde_json::to_string(&outputs)?;
+    if let Some(output) = output {
+        std::fs::write(&output, &json)?;
+    }
+    info!("unsealed {} outputs", outputs.len());
+    Ok(json)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn replay(journal_path: PathBuf) -> Result<String, Box<dyn Error>> {
+    let journal = Journal::load(&journal_path)?;
+    if journal.version != env!("CARGO_PKG_VERSION") {
+        warn!(
+            "the journal was recorded with ezkl {}, this is ezkl {}",
+            journal.version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    let num_steps = journal.entries.len();
+    for (i, entry) in journal.entries.into_iter().enumerate() {
+        let name = command_name(&entry.command);
+        info!("replaying step {} of {}: {}", i + 1, num_steps, name);
+        let step = |e: Box<dyn Error>| format!("step {} ({}): {}", i + 1, name, e);
+        entry.check_inputs().map_err(step)?;
+        run_command(entry.command.clone()).await.map_err(step)?;
+        entry.check_outputs().map_err(step)?;
+    }
+    let msg = format!(
+        "replayed {} steps of {}, every output was reproduced",
+        num_steps,
+        journal_path.display()
+    );
+    info!("{}", msg);
+    Ok(msg)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn witness_diff(
+    left: PathBuf,
+    right: PathBuf,
+    compiled_circuit: Option<PathBuf>,
+    encryption_key: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    let left_witness = GraphWitness::from_path(left.clone())?;
+    let right_witness = GraphWitness::from_path(right.clone())?;
+    let circuit = compiled_circuit
+        .map(|path| load_compiled_circuit(path, encryption_key))
+        .transpose()?;
+
+    let divergences = crate::graph::witness_diff::diff_witnesses(
+        &left_witness,
+        &right_witness,
+        circuit.as_ref().map(|c| c.model()),
+    );
+
+    match divergences.first() {
+        None => info!("{} and {} are identical", left.display(), right.display()),
+        Some(first) => warn!(
+            "{} and {} diverge at {} elements, first divergence: {}",
+            left.display(),
+            right.display(),
+            divergences.len(),
+            first
+        ),
+    }
+
+    Ok(serde_json::to_string_pretty(&divergences)?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn diff_settings(
+    left: PathBuf,
+    right: Option<PathBuf>,
+    key_path: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    use crate::graph::settings_diff::{diff_key_header, diff_settings};
+
+    let left_settings = GraphSettings::load(&left)?;
+    let (differences, other) = match (right, key_path) {
+        (Some(right), _) => (
+            diff_settings(&left_settings, &GraphSettings::load(&right)?)?,
+            right,
+        ),
+        (None, Some(key_path)) => {
+            let mut reader = std::io::BufReader::new(File::open(&key_path)?);
+            match crate::pfsys::key_header::KeyHeader::read(&mut reader)? {
+                Some(header) => (diff_key_header(&left_settings, &header), key_path),
+                None => {
+                    return Err(format!(
+                        "{} has no header, so the settings it was generated from are unknown",
+                        key_path.display()
+                    )
+                    .into())
+                }
+            }
+        }
+        (None, None) => return Err("pass the settings or key to compare with".into()),
+    };
+
+    if differences.is_empty() {
+        info!("{} and {} agree", left.display(), other.display());
+    }
+    for difference in &differences {
+        if difference.requires_new_keys {
+            warn!("{}", difference);
+        } else {
+            info!("{}", difference);
+        }
+    }
+    if differences.iter().any(|d| d.requires_new_keys) {
+        warn!(
+            "keys generated from {} can't be used with {}, regenerate them with `ezkl setup`",
+            other.display(),
+            left.display()
+        );
+    }
+
+    Ok(serde_json::to_string_pretty(&differences)?)
+}
+
+// not for wasm targets
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn init_spinner() -> ProgressBar {
+    let pb = indicatif::ProgressBar::new_spinner();
+    pb.set_draw_target(indicatif::ProgressDrawTarget::stdout());
+    pb.enable_steady_tick(Duration::from_millis(200));
+    pb.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {spinner:.blue} {msg}")
+            .unwrap()
+            .tick_strings(&[
+                "------ - ✨ ",
+                "------ - ⏳ ",
+                "------ - 🌎 ",
+                "------ - 🔎 ",
+                "------ - 🥹 ",
+                "------ - 🫠 ",
+                "------ - 👾 ",
+            ]),
+    );
+    pb
+}
+
+// not for wasm targets
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn init_bar(len: u64) -> ProgressBar {
+    let pb = ProgressBar::new(len);
+    pb.set_draw_target(indicatif::ProgressDrawTarget::stdout());
+    pb.enable_steady_tick(Duration::from_millis(200));
+    let sty = ProgressStyle::with_template(
+        "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
+    )
+    .unwrap()
+    .progress_chars("##-");
+    pb.set_style(sty);
+    pb
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+use colored_json::ToColoredJson;
+
+#[derive(Debug, Clone, Tabled)]
+/// Accuracy tearsheet
+pub struct AccuracyResults {
+    mean_error: f32,
+    median_error: f32,
+    max_error: f32,
+    min_error: f32,
+    mean_abs_error: f32,
+    median_abs_error: f32,
+    max_abs_error: f32,
+    min_abs_error: f32,
+    mean_squared_error: f32,
+    mean_percent_error: f32,
+    mean_abs_percent_error: f32,
+}
+
+impl AccuracyResults {
+    /// Create a new accuracy results struct
+    pub fn new(
+        mut original_preds: Vec<crate::tensor::Tensor<f32>>,
+        mut calibrated_preds: Vec<crate::tensor::Tensor<f32>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut errors = vec![];
+        let mut abs_errors = vec![];
+        let mut squared_errors = vec![];
+        let mut percentage_errors = vec![];
+        let mut abs_percentage_errors = vec![];
+
+        for (original, calibrated) in original_preds.iter_mut().zip(calibrated_preds.iter_mut()) {
+            original.flatten();
+            calibrated.flatten();
+            let error = (original.clone() - calibrated.clone())?;
+            let abs_error = error.map(|x| x.abs());
+            let squared_error = error.map(|x| x.powi(2));
+            let percentage_error = error.enum_map(|i, x| Ok::<_, TensorError>(x / original[i]))?;
+            let abs_percentage_error = percentage_error.map(|x| x.abs());
+
+            errors.extend(error);
+            abs_errors.extend(abs_error);
+            squared_errors.extend(squared_error);
+            percentage_errors.extend(percentage_error);
+            abs_percentage_errors.extend(abs_percentage_error);
+        }
+
+        let mean_percent_error =
             percentage_errors.iter().sum::<f32>() / percentage_errors.len() as f32;
         let mean_abs_percent_error =
             abs_percentage_errors.iter().sum::<f32>() / abs_percentage_errors.len() as f32;
@@ -873,13 +2355,53 @@ impl AccuracyResults {
     }
 }
 
-/// Calibrate the circuit parameters to a given a dataset
+/// Calibrate the circuit parameters to a given a dataset, either a .json data file or, if a
+/// dataset spec is passed, a .csv / .parquet file
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn calibrate(
+    model_path: PathBuf,
+    data: PathBuf,
+    settings_path: PathBuf,
+    target: CalibrationTarget,
+    lookup_safety_margin: i128,
+    scales: Option<Vec<crate::Scale>>,
+    scale_rebase_multiplier: Vec<u32>,
+    only_range_check_rebase: bool,
+    max_logrows: Option<u32>,
+    dataset_spec: Option<PathBuf>,
+    bias_correction: bool,
+    max_lookup_domains: usize,
+) -> Result<GraphSettings, Box<dyn Error>> {
+    let data = match dataset_spec {
+        Some(spec) => {
+            let spec = DatasetSpec::from_path(&spec)?;
+            load_dataset(&data, &spec)?
+        }
+        None => GraphData::from_path(data)?,
+    };
+    calibrate_with_dataset(
+        model_path,
+        data,
+        settings_path,
+        target,
+        lookup_safety_margin,
+        scales,
+        scale_rebase_multiplier,
+        only_range_check_rebase,
+        max_logrows,
+        bias_correction,
+        max_lookup_domains,
+    )
+}
+
+/// Calibrate the circuit parameters to an in memory dataset
 #[cfg(not(target_arch = "wasm32"))]
 #[allow(trivial_casts)]
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn calibrate(
+pub(crate) fn calibrate_with_dataset(
     model_path: PathBuf,
-    data: PathBuf,
+    data: GraphData,
     settings_path: PathBuf,
     target: CalibrationTarget,
     lookup_safety_margin: i128,
@@ -887,11 +2409,13 @@ pub(crate) fn calibrate(
     scale_rebase_multiplier: Vec<u32>,
     only_range_check_rebase: bool,
     max_logrows: Option<u32>,
+    bias_correction: bool,
+    max_lookup_domains: usize,
 ) -> Result<GraphSettings, Box<dyn Error>> {
     use std::collections::HashMap;
     use tabled::Table;
 
-    let data = GraphData::from_path(data)?;
+    let _warnings = crate::warnings::scope();
     // load the pre-generated settings
     let settings = GraphSettings::load(&settings_path)?;
     // now retrieve the run args
@@ -1074,6 +2598,23 @@ pub(crate) fn calibrate(
         );
 
         if res.is_ok() {
+            if max_lookup_domains > 1 {
+                // the min and max inputs of each op over all the calibration batches
+                let mut lookup_input_ranges: HashMap<_, crate::circuit::table::Range> =
+                    HashMap::new();
+                for (op, (min, max)) in result.iter().flat_map(|x| x.lookup_input_ranges.iter()) {
+                    let range = lookup_input_ranges
+                        .entry(op.clone())
+                        .or_insert((*min, *max));
+                    *range = (range.0.min(*min), range.1.max(*max));
+                }
+                circuit.assign_lookup_domains(
+                    &lookup_input_ranges.into_iter().collect::<Vec<_>>(),
+                    max_lookup_domains,
+                    lookup_safety_margin,
+                );
+            }
+
             let new_settings = circuit.settings().clone();
 
             let found_run_args = RunArgs {
@@ -1095,6 +2636,7 @@ pub(crate) fn calibrate(
                 num_rows: new_settings.num_rows,
                 total_assignments: new_settings.total_assignments,
                 total_const_size: new_settings.total_const_size,
+                model_hash: new_settings.model_hash,
                 ..settings.clone()
             };
 
@@ -1234,6 +2776,18 @@ pub(crate) fn calibrate(
         best_params.run_args.logrows = reduction;
     }
 
+    // the corrections are found for the chosen scales, any stale ones are dropped
+    best_params.bias_corrections = if bias_correction {
+        info!("running bias correction...");
+        crate::graph::bias_correction::find_bias_corrections(
+            &best_params.run_args,
+            &model_path,
+            &chunks,
+        )?
+    } else {
+        Default::default()
+    };
+
     best_params.save(&settings_path)?;
 
     debug!("Saved parameters.");
@@ -1244,12 +2798,20 @@ pub(crate) fn calibrate(
 pub(crate) fn mock(
     compiled_circuit_path: PathBuf,
     data_path: PathBuf,
+    encryption_key: Option<PathBuf>,
 ) -> Result<String, Box<dyn Error>> {
     // mock should catch any issues by default so we set it to safe
-    let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
+    let mut circuit = load_compiled_circuit(compiled_circuit_path, encryption_key)?;
 
     let data = GraphWitness::from_path(data_path)?;
 
+    if let Some(report) = data.check_expected_outputs(circuit.settings()) {
+        if !report.passed() {
+            return Err(report.to_string().into());
+        }
+        info!("{}", report);
+    }
+
     circuit.load_graph_witness(&data)?;
 
     let public_inputs = circuit.prepare_public_inputs(&data)?;
@@ -1268,6 +2830,121 @@ pub(crate) fn mock(
     Ok(String::new())
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn fuzz(
+    compiled_circuit_path: PathBuf,
+    ranges: Vec<(f64, f64)>,
+    iterations: usize,
+    seed: Option<u64>,
+    output_dir: PathBuf,
+    encryption_key: Option<PathBuf>,
+) -> Result<String, Box<dyn Error>> {
+    use crate::fuzz::{
+        check_input, input_ranges, sample_inputs, FuzzFailure, FuzzFailureKind, FuzzReport,
+    };
+    use rand::SeedableRng;
+
+    let mut circuit = load_compiled_circuit(compiled_circuit_path, encryption_key)?;
+    if circuit.settings().module_requires_polycommit() {
+        return Err("fuzzing circuits with polycommit visibility isn't supported".into());
+    }
+    let shapes = circuit.model().graph.input_shapes()?;
+    let types = circuit.model().graph.get_input_types()?;
+    let ranges = input_ranges(&ranges, &types)?;
+    let seed = seed.unwrap_or_else(rand::random);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    std::fs::create_dir_all(&output_dir)?;
+    info!("fuzzing {} inputs with seed {}", iterations, seed);
+
+    let mut failures = vec![];
+    let pb = init_bar(iterations as u64);
+    for iteration in 0..iterations {
+        pb.inc(1);
+        let data = GraphData::new(crate::graph::DataSource::from(sample_inputs(
+            &mut rng, &shapes, &types, &ranges,
+        )));
+        // saved before it runs so that an input crashing the process isn't lost, and removed if
+        // it passes
+        let input_path = output_dir.join(format!("input_{}.json", iteration));
+        data.save(input_path.clone())?;
+
+        let failure = match circuit.load_graph_input(&data).await {
+            Err(e) => Some((FuzzFailureKind::Error, vec![e.to_string()])),
+            Ok(mut input) => std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                check_input(&mut circuit, &mut input)
+            }))
+            .unwrap_or_else(|panic| {
+                Some((
+                    FuzzFailureKind::Panic,
+                    vec![crate::threads::panic_message(&*panic)],
+                ))
+            }),
+        };
+        let Some((kind, messages)) = failure else {
+            std::fs::remove_file(&input_path)?;
+            continue;
+        };
+        pb.suspend(|| warn!("input {} ({}): {}", iteration, kind, messages.join(", ")));
+        failures.push(FuzzFailure {
+            iteration,
+            kind,
+            messages,
+            input_path,
+        });
+    }
+    pb.finish_and_clear();
+
+    let report = FuzzReport {
+        seed,
+        iterations,
+        failures,
+    };
+    let json = serde_json::to_string_pretty(&report)?;
+    let report_path = output_dir.join("report.json");
+    std::fs::write(&report_path, &json)?;
+    if !report.failures.is_empty() {
+        return Err(format!(
+            "{} of {} inputs failed with seed {}, see {}",
+            report.failures.len(),
+            iterations,
+            seed,
+            report_path.display()
+        )
+        .into());
+    }
+    info!("all {} inputs passed with seed {}", iterations, seed);
+    Ok(json)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn estimate_gas(
+    proof_path: PathBuf,
+    sol_code_path: PathBuf,
+    vk_sol_path: Option<PathBuf>,
+    optimizer_runs: usize,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+
+    let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    if proof.transcript_type != TranscriptType::EVM {
+        return Err(format!(
+            "evm verifiers only verify proofs with an evm transcript, got {}",
+            proof.transcript_type
+        )
+        .into());
+    }
+
+    let estimate =
+        crate::eth::estimate_verify_gas(proof, sol_code_path, vk_sol_path, optimizer_runs).await?;
+    info!(
+        "verification costs {} gas: {} for {} bytes of calldata, {} executing the verifier and {} for the transaction itself",
+        estimate.total, estimate.calldata, estimate.calldata_bytes, estimate.execution, estimate.intrinsic
+    );
+    info!("deploying the verifier costs {} gas", estimate.deployment);
+
+    Ok(serde_json::to_string_pretty(&estimate)?)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn create_evm_verifier(
     vk_path: PathBuf,
@@ -1276,6 +2953,8 @@ pub(crate) fn create_evm_verifier(
     sol_code_path: PathBuf,
     abi_path: PathBuf,
     render_vk_seperately: bool,
+    commitments_path: Option<PathBuf>,
+    target_chain: crate::eth::TargetChain,
 ) -> Result<String, Box<dyn Error>> {
     check_solc_requirement();
     let circuit_settings = GraphSettings::load(&settings_path)?;
@@ -1287,8 +2966,39 @@ pub(crate) fn create_evm_verifier(
 
     let num_instance = circuit_settings.total_instances();
     let num_instance: usize = num_instance.iter().sum::<usize>();
+    let bind_caller = circuit_settings.run_args.bind_caller;
+    let valid_count_offset = circuit_settings
+        .instance_layout()
+        .into_iter()
+        .find(|s| s.name == "valid_count")
+        .map(|s| s.offset);
+    // locate the instance each on-chain commitment attests to
+    let commitments = match commitments_path {
+        Some(path) => {
+            let layout = circuit_settings.instance_layout();
+            crate::eth::CommitmentCall::load(&path)?
+                .iter()
+                .map(|call| {
+                    let segment = layout
+                        .iter()
+                        .find(|s| s.name == call.segment)
+                        .ok_or_else(|| format!("the proofs have no {} instances", call.segment))?;
+                    if segment.len() != 1 {
+                        return Err(format!(
+                            "{} is not a commitment, only single instances can be attested to",
+                            call.segment
+                        )
+                        .into());
+                    }
+                    let (address, call_data) = call.parse()?;
+                    Ok((segment.offset, address, call_data))
+                })
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+        }
+        None => vec![],
+    };
 
-    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, circuit_settings)?;
+    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, circuit_settings, false)?;
     trace!("params computed");
 
     let generator = halo2_solidity_verifier::SolidityGenerator::new(
@@ -1297,19 +3007,149 @@ pub(crate) fn create_evm_verifier(
         halo2_solidity_verifier::BatchOpenScheme::Bdfg21,
         num_instance,
     );
+    write_evm_verifier(
+        &generator,
+        render_vk_seperately,
+        |mut verifier_solidity| {
+            if let Some(offset) = valid_count_offset {
+                verifier_solidity = crate::eth::valid_count_sol(&verifier_solidity, offset)?;
+            }
+            if !commitments.is_empty() {
+                verifier_solidity =
+                    crate::eth::attest_commitments_sol(&verifier_solidity, &commitments)?;
+            }
+            verifier_solidity = target_chain.patch_verifier(&verifier_solidity)?;
+            // wraps the patched verifier, so it has to come last
+            if bind_caller {
+                verifier_solidity = crate::eth::bind_caller_sol(&verifier_solidity)?;
+            }
+            Ok(verifier_solidity)
+        },
+        sol_code_path.clone(),
+        abi_path,
+    )?;
+    target_chain.check_build(&sol_code_path)?;
+
+    Ok(String::new())
+}
+
+/// Writes the verifier rendered by `generator`, patched by `patch`, to `sol_code_path` and its abi
+/// to `abi_path`. A verifier whose bytecode exceeds the EIP-170 contract size limit can't be
+/// deployed, so unless the verifying key is already rendered separately it is rendered again with
+/// the verifying key as its own contract, written next to the verifier (see
+/// [crate::eth::separate_vk_paths]) to be deployed first with `deploy-evm-vk`. Fails if either
+/// contract is still over the limit. The limit applies to
+/// the verifier itself, i.e to the `Halo2VerifierCore` of a verifier bound to its caller (see
+/// [crate::eth::bind_caller_sol]).
+#[cfg(not(target_arch = "wasm32"))]
+fn write_evm_verifier(
+    generator: &halo2_solidity_verifier::SolidityGenerator<'_>,
+    render_vk_seperately: bool,
+    patch: impl Fn(String) -> Result<String, Box<dyn Error>>,
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+) -> Result<(), Box<dyn Error>> {
     let verifier_solidity = if render_vk_seperately {
         generator.render_separately()?.0 // ignore the rendered vk for now and generate it in create_evm_vk
     } else {
         generator.render()?
     };
+    // the contracts are sized as deploy-evm-verifier compiles them by default
+    let runs: usize = crate::commands::DEFAULT_OPTIMIZER_RUNS.parse()?;
+    // the abi of the deployed contract and the runtime bytecode of the verifier
+    let write = |verifier_solidity: String| -> Result<_, Box<dyn Error>> {
+        let verifier_solidity = patch(verifier_solidity)?;
+        File::create(sol_code_path.clone())?.write_all(verifier_solidity.as_bytes())?;
+        let (abi, _, runtime_bytecode) =
+            get_contract_artifacts(sol_code_path.clone(), "Halo2Verifier", runs)?;
+        if verifier_solidity.contains("contract Halo2VerifierCore {") {
+            let (_, _, core_bytecode) =
+                get_contract_artifacts(sol_code_path.clone(), "Halo2VerifierCore", runs)?;
+            return Ok((abi, core_bytecode));
+        }
+        Ok((abi, runtime_bytecode))
+    };
+    let (mut abi, mut runtime_bytecode) = write(verifier_solidity)?;
+
+    if !render_vk_seperately && runtime_bytecode.len() > crate::eth::EIP170_CONTRACT_SIZE_LIMIT {
+        let (verifier_solidity, vk_solidity) = generator.render_separately()?;
+        let (vk_sol_path, vk_abi_path) = crate::eth::separate_vk_paths(&sol_code_path, &abi_path);
+        File::create(&vk_sol_path)?.write_all(vk_solidity.as_bytes())?;
+        let (vk_abi, _, vk_bytecode) =
+            get_contract_artifacts(vk_sol_path.clone(), "Halo2VerifyingKey", runs)?;
+        crate::eth::check_contract_size("verifying key", vk_bytecode.len())?;
+        serde_json::to_writer(std::fs::File::create(vk_abi_path)?, &vk_abi)?;
+
+        (abi, runtime_bytecode) = write(verifier_solidity)?;
+        warn!(
+            "the verifier exceeds the {} byte contract size limit, so its verifying key was split into {}: deploy it with deploy-evm-vk and pass its address to the verifier with --addr-vk",
+            crate::eth::EIP170_CONTRACT_SIZE_LIMIT,
+            vk_sol_path.display()
+        );
+    }
+    crate::eth::check_contract_size("verifier", runtime_bytecode.len())?;
 
-    File::create(sol_code_path.clone())?.write_all(verifier_solidity.as_bytes())?;
-
-    // fetch abi of the contract
-    let (abi, _, _) = get_contract_artifacts(sol_code_path, "Halo2Verifier", 0)?;
     // save abi to file
     serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_npm_verifier(
+    srs_path: Option<PathBuf>,
+    settings_path: PathBuf,
+    vk_path: PathBuf,
+    output_dir: PathBuf,
+    package_name: String,
+) -> Result<String, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    let srs_path = get_srs_path(
+        settings.run_args.logrows,
+        srs_path,
+        settings.run_args.commitment,
+    );
+    crate::npm::create_package(
+        &output_dir,
+        &package_name,
+        &settings_path,
+        &vk_path,
+        &srs_path,
+    )?;
+    info!("npm verifier package written to {}", output_dir.display());
+    Ok(String::new())
+}
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_wasm_verifier(
+    srs_path: Option<PathBuf>,
+    settings_path: PathBuf,
+    vk_path: PathBuf,
+    output_dir: PathBuf,
+    crate_name: String,
+    target: ContractTarget,
+) -> Result<String, Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path)?;
+    if settings.run_args.commitment != Commitments::KZG {
+        return Err("verifier contracts only support KZG commitments".into());
+    }
+    let srs_path = get_srs_path(
+        settings.run_args.logrows,
+        srs_path,
+        settings.run_args.commitment,
+    );
+    crate::wasm_contracts::create_contract(
+        target,
+        &output_dir,
+        &crate_name,
+        &settings_path,
+        &vk_path,
+        &srs_path,
+    )?;
+    info!(
+        "{} verifier contract written to {}",
+        target,
+        output_dir.display()
+    );
     Ok(String::new())
 }
 
@@ -1332,7 +3172,7 @@ pub(crate) fn create_evm_vk(
     let num_instance = circuit_settings.total_instances();
     let num_instance: usize = num_instance.iter().sum::<usize>();
 
-    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, circuit_settings)?;
+    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, circuit_settings, false)?;
     trace!("params computed");
 
     let generator = halo2_solidity_verifier::SolidityGenerator::new(
@@ -1347,7 +3187,12 @@ pub(crate) fn create_evm_vk(
     File::create(sol_code_path.clone())?.write_all(vk_solidity.as_bytes())?;
 
     // fetch abi of the contract
-    let (abi, _, _) = get_contract_artifacts(sol_code_path, "Halo2VerifyingKey", 0)?;
+    let (abi, _, runtime_bytecode) = get_contract_artifacts(
+        sol_code_path,
+        "Halo2VerifyingKey",
+        crate::commands::DEFAULT_OPTIMIZER_RUNS.parse()?,
+    )?;
+    crate::eth::check_contract_size("verifying key", runtime_bytecode.len())?;
     // save abi to file
     serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
 
@@ -1376,8 +3221,12 @@ pub(crate) fn create_evm_data_attestation(
         if visibility.output.is_private() {
             return Err("private output data on chain is not supported on chain".into());
         }
+        if !settings.run_args.output_destinations.is_empty() {
+            return Err("on-chain output data doesn't support per-output destinations".into());
+        }
+        // the contract only depends on the number of calls, so their decimals needn't be resolved
         let mut on_chain_output_data = vec![];
-        for call in source.calls {
+        for call in source.encoded_calls()? {
             on_chain_output_data.push(call);
         }
         Some(on_chain_output_data)
@@ -1389,8 +3238,9 @@ pub(crate) fn create_evm_data_attestation(
         if visibility.input.is_private() {
             return Err("private input data on chain is not supported on chain".into());
         }
+        // the contract only depends on the number of calls, so their decimals needn't be resolved
         let mut on_chain_input_data = vec![];
-        for call in source.calls {
+        for call in source.encoded_calls()? {
             on_chain_input_data.push(call);
         }
         Some(on_chain_input_data)
@@ -1398,19 +3248,241 @@ pub(crate) fn create_evm_data_attestation(
         None
     };
 
-    if input_data.is_some() || output_data.is_some() {
-        let output = fix_da_sol(input_data, output_data)?;
-        let mut f = File::create(_sol_code_path.clone())?;
-        let _ = f.write(output.as_bytes());
-        // fetch abi of the contract
-        let (abi, _, _) = get_contract_artifacts(_sol_code_path, "DataAttestation", 0)?;
-        // save abi to file
-        serde_json::to_writer(std::fs::File::create(_abi_path)?, &abi)?;
-    } else {
-        return Err(
-            "Neither input or output data source is on-chain. Atleast one must be on chain.".into(),
-        );
-    }
+    if input_data.is_some() || output_data.is_some() {
+        let output = fix_da_sol(input_data, output_data)?;
+        let mut f = File::create(_sol_code_path.clone())?;
+        let _ = f.write(output.as_bytes());
+        // fetch abi of the contract
+        let (abi, _, _) = get_contract_artifacts(_sol_code_path, "DataAttestation", 0)?;
+        // save abi to file
+        serde_json::to_writer(std::fs::File::create(_abi_path)?, &abi)?;
+    } else {
+        return Err(
+            "Neither input or output data source is on-chain. Atleast one must be on chain.".into(),
+        );
+    }
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_evm_quantizer(
+    sol_code_path: PathBuf,
+    scale: crate::Scale,
+    bits: u32,
+) -> Result<String, Box<dyn Error>> {
+    let sol = crate::eth::quantizer_sol(scale, bits)?;
+    std::fs::write(&sol_code_path, sol)?;
+    info!(
+        "wrote a quantization library for scale {} and {} bits to {}",
+        scale,
+        bits,
+        sol_code_path.display()
+    );
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_evm_paymaster(
+    settings_path: PathBuf,
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+    verify_gas_limit: u64,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let settings = GraphSettings::load(&settings_path)?;
+    std::fs::write(
+        &sol_code_path,
+        crate::eth::paymaster_sol(&settings, verify_gas_limit)?,
+    )?;
+    // fetch abi of the contract
+    let (abi, _, _) = get_contract_artifacts(sol_code_path, "EzklProofPaymaster", 0)?;
+    // save abi to file
+    serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn deploy_evm_paymaster(
+    sol_code_path: PathBuf,
+    addr_verifier: H160Flag,
+    entry_point: H160Flag,
+    rpc_url: Option<String>,
+    addr_path: PathBuf,
+    runs: usize,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let contract_address = crate::eth::deploy_paymaster_via_solidity(
+        sol_code_path,
+        rpc_url.as_deref(),
+        runs,
+        private_key.as_deref(),
+        entry_point.into(),
+        addr_verifier.into(),
+    )
+    .await?;
+
+    info!("Contract deployed at: {:#?}", contract_address);
+
+    let mut f = File::create(addr_path)?;
+    write!(f, "{:#?}", contract_address)?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_evm_blob_verifier(
+    settings_path: PathBuf,
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let settings = GraphSettings::load(&settings_path)?;
+    std::fs::write(&sol_code_path, crate::eth::blob_verifier_sol(&settings)?)?;
+    // fetch abi of the contract
+    let (abi, _, _) = get_contract_artifacts(sol_code_path, "EzklBlobVerifier", 0)?;
+    // save abi to file
+    serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn deploy_evm_blob_verifier(
+    sol_code_path: PathBuf,
+    addr_verifier: H160Flag,
+    rpc_url: Option<String>,
+    addr_path: PathBuf,
+    runs: usize,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let contract_address = crate::eth::deploy_blob_verifier_via_solidity(
+        sol_code_path,
+        rpc_url.as_deref(),
+        runs,
+        private_key.as_deref(),
+        addr_verifier.into(),
+    )
+    .await?;
+
+    info!("Contract deployed at: {:#?}", contract_address);
+
+    let mut f = File::create(addr_path)?;
+    write!(f, "{:#?}", contract_address)?;
+    Ok(String::new())
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "blobs")))]
+pub(crate) async fn post_proof_blob(
+    _proof_path: PathBuf,
+    _addr_verifier: H160Flag,
+    _trusted_setup_path: PathBuf,
+    _rpc_url: Option<String>,
+    _private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    Err("posting proofs as blobs needs ezkl built with the blobs feature".into())
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "blobs"))]
+pub(crate) async fn post_proof_blob(
+    proof_path: PathBuf,
+    addr_verifier: H160Flag,
+    trusted_setup_path: PathBuf,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    let versioned_hash = crate::eth::post_proof_blob(
+        proof,
+        addr_verifier.into(),
+        &trusted_setup_path,
+        rpc_url.as_deref(),
+        private_key.as_deref(),
+    )
+    .await?;
+    info!(
+        "proof posted in the blob with versioned hash {:#?}",
+        versioned_hash
+    );
+    Ok(format!("{:#?}", versioned_hash))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn create_evm_registry(
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    std::fs::write(&sol_code_path, crate::eth::VERIFIER_REGISTRY_SOL)?;
+    // fetch abi of the contract
+    let (abi, _, _) = get_contract_artifacts(sol_code_path, "EzklVerifierRegistry", 0)?;
+    // save abi to file
+    serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn deploy_evm_registry(
+    sol_code_path: PathBuf,
+    addr_verifier: H160Flag,
+    addr_vk: H160Flag,
+    rpc_url: Option<String>,
+    addr_path: PathBuf,
+    runs: usize,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let contract_address = crate::eth::deploy_verifier_registry(
+        sol_code_path,
+        rpc_url.as_deref(),
+        runs,
+        private_key.as_deref(),
+        addr_verifier.into(),
+        addr_vk.into(),
+    )
+    .await?;
+
+    info!("Contract deployed at: {:#?}", contract_address);
+
+    let mut f = File::create(addr_path)?;
+    write!(f, "{:#?}", contract_address)?;
+    Ok(String::new())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn update_evm_vk(
+    addr_registry: H160Flag,
+    addr_vk: H160Flag,
+    addr_verifier: Option<H160Flag>,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    let version = crate::eth::update_registry_vk(
+        addr_registry.into(),
+        addr_verifier.map(Into::into),
+        addr_vk.into(),
+        rpc_url.as_deref(),
+        private_key.as_deref(),
+    )
+    .await?;
+    info!("the registry verifies proofs against version {}", version);
+    Ok(version.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn revoke_evm_vk(
+    addr_registry: H160Flag,
+    version: u64,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<String, Box<dyn Error>> {
+    crate::eth::revoke_registry_version(
+        addr_registry.into(),
+        version,
+        rpc_url.as_deref(),
+        private_key.as_deref(),
+    )
+    .await?;
+    info!("version {} of the registry is revoked", version);
     Ok(String::new())
 }
 
@@ -1450,16 +3522,32 @@ pub(crate) async fn deploy_evm(
     runs: usize,
     private_key: Option<String>,
     contract_name: &str,
+    create2_vk_path: Option<PathBuf>,
 ) -> Result<String, Box<dyn Error>> {
     check_solc_requirement();
-    let contract_address = deploy_contract_via_solidity(
-        sol_code_path,
-        rpc_url.as_deref(),
-        runs,
-        private_key.as_deref(),
-        contract_name,
-    )
-    .await?;
+    let contract_address = match create2_vk_path {
+        Some(vk_path) => {
+            crate::eth::deploy_contract_via_create2(
+                sol_code_path,
+                rpc_url.as_deref(),
+                runs,
+                private_key.as_deref(),
+                contract_name,
+                crate::eth::vk_salt(&std::fs::read(vk_path)?),
+            )
+            .await?
+        }
+        None => {
+            deploy_contract_via_solidity(
+                sol_code_path,
+                rpc_url.as_deref(),
+                runs,
+                private_key.as_deref(),
+                contract_name,
+            )
+            .await?
+        }
+    };
 
     info!("Contract deployed at: {:#?}", contract_address);
 
@@ -1468,6 +3556,23 @@ pub(crate) async fn deploy_evm(
     Ok(String::new())
 }
 
+/// The address `deploy-evm-verifier --create2-vk-path` and `deploy-evm-vk --create2-vk-path`
+/// deploy the contract to, on any chain.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn predict_evm_address(
+    sol_code_path: PathBuf,
+    vk_path: PathBuf,
+    runs: usize,
+    contract_name: &str,
+) -> Result<String, Box<dyn Error>> {
+    check_solc_requirement();
+    let (_, bytecode, _) = get_contract_artifacts(sol_code_path, contract_name, runs)?;
+    let salt = crate::eth::vk_salt(&std::fs::read(vk_path)?);
+    let address = crate::eth::create2_address(salt, &bytecode);
+    info!("{} will be deployed at: {:#?}", contract_name, address);
+    Ok(format!("{:#?}", address))
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) async fn verify_evm(
     proof_path: PathBuf,
@@ -1480,6 +3585,13 @@ pub(crate) async fn verify_evm(
     check_solc_requirement();
 
     let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    if proof.transcript_type != TranscriptType::EVM {
+        return Err(format!(
+            "evm verifiers only verify proofs with an evm transcript, got {}",
+            proof.transcript_type
+        )
+        .into());
+    }
 
     let result = if let Some(addr_da) = addr_da {
         verify_proof_with_data_attestation(
@@ -1518,6 +3630,7 @@ pub(crate) fn create_evm_aggregate_verifier(
     circuit_settings: Vec<PathBuf>,
     logrows: u32,
     render_vk_seperately: bool,
+    forward_instances: bool,
 ) -> Result<String, Box<dyn Error>> {
     check_solc_requirement();
     let srs_path = get_srs_path(logrows, srs_path, Commitments::KZG);
@@ -1530,16 +3643,23 @@ pub(crate) fn create_evm_aggregate_verifier(
         settings.push(s);
     }
 
-    let num_instance: usize = settings
+    // the instances of the inner proofs follow the accumulator, in the order they were aggregated
+    let mut offset = AggregationCircuit::num_instance(0)[0];
+    let inner = settings
         .iter()
-        .map(|s| s.total_instances().iter().sum::<usize>())
-        .sum();
+        .map(|s| {
+            let range = offset..offset + s.total_instances().iter().sum::<usize>();
+            offset = range.end;
+            range
+        })
+        .collect_vec();
+    let num_instance: usize = inner.iter().map(|r| r.len()).sum();
 
     let num_instance = AggregationCircuit::num_instance(num_instance);
     assert_eq!(num_instance.len(), 1);
     let num_instance = num_instance[0];
 
-    let agg_vk = load_vk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(vk_path, ())?;
+    let agg_vk = load_vk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(vk_path, (), false)?;
 
     let mut generator = halo2_solidity_verifier::SolidityGenerator::new(
         &params,
@@ -1556,18 +3676,16 @@ pub(crate) fn create_evm_aggregate_verifier(
 
     generator = generator.set_acc_encoding(Some(acc_encoding));
 
-    let verifier_solidity = if render_vk_seperately {
-        generator.render_separately()?.0 // ignore the rendered vk for now and generate it in create_evm_vk
-    } else {
-        generator.render()?
-    };
-
-    File::create(sol_code_path.clone())?.write_all(verifier_solidity.as_bytes())?;
-
-    // fetch abi of the contract
-    let (abi, _, _) = get_contract_artifacts(sol_code_path, "Halo2Verifier", 0)?;
-    // save abi to file
-    serde_json::to_writer(std::fs::File::create(abi_path)?, &abi)?;
+    write_evm_verifier(
+        &generator,
+        render_vk_seperately,
+        |verifier_solidity| match forward_instances {
+            true => crate::eth::forward_instances_sol(&verifier_solidity, &inner),
+            false => Ok(verifier_solidity),
+        },
+        sol_code_path,
+        abi_path,
+    )?;
 
     Ok(String::new())
 }
@@ -1576,13 +3694,49 @@ pub(crate) fn compile_circuit(
     model_path: PathBuf,
     compiled_circuit: PathBuf,
     settings_path: PathBuf,
+    encryption_key: Option<PathBuf>,
 ) -> Result<String, Box<dyn Error>> {
     let settings = GraphSettings::load(&settings_path)?;
     let circuit = GraphCircuit::from_settings(&settings, &model_path, CheckMode::UNSAFE)?;
-    circuit.save(compiled_circuit)?;
+    match encryption_key {
+        Some(key_path) => {
+            let key = ModelKey::from_file(&key_path)?;
+            circuit.save_encrypted(compiled_circuit, &key)?;
+        }
+        None => circuit.save(compiled_circuit)?,
+    }
     Ok(String::new())
 }
 
+/// Loads a compiled circuit, decrypting it in memory if a key is passed.
+fn load_compiled_circuit(
+    compiled_circuit: PathBuf,
+    encryption_key: Option<PathBuf>,
+) -> Result<GraphCircuit, Box<dyn Error>> {
+    let key = encryption_key
+        .map(|path| ModelKey::from_file(&path))
+        .transpose()?;
+    GraphCircuit::load_with_key(compiled_circuit, key.as_ref())
+}
+
+/// Checks the commitment passed to setup, prove or verify against the one of the circuit
+/// settings. Keys, SRS and proofs of one scheme can't be used with the other, so the scheme is
+/// picked once with gen-settings --commitment and can only be restated afterwards.
+fn check_commitment(
+    settings: &GraphSettings,
+    commitment: Option<Commitments>,
+) -> Result<(), Box<dyn Error>> {
+    match commitment {
+        Some(commitment) if commitment != settings.run_args.commitment => Err(format!(
+            "--commitment {} doesn't match the {} commitment of the circuit settings, pick it with gen-settings --commitment",
+            commitment, settings.run_args.commitment
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn setup(
     compiled_circuit: PathBuf,
     srs_path: Option<PathBuf>,
@@ -1590,10 +3744,16 @@ pub(crate) fn setup(
     pk_path: PathBuf,
     witness: Option<PathBuf>,
     disable_selector_compression: bool,
+    encryption_key: Option<PathBuf>,
+    commitment: Option<Commitments>,
+    checkpoint_dir: Option<PathBuf>,
+    resume: bool,
 ) -> Result<String, Box<dyn Error>> {
     // these aren't real values so the sanity checks are mostly meaningless
 
-    let mut circuit = GraphCircuit::load(compiled_circuit)?;
+    let inputs = [Some(compiled_circuit.clone()), witness.clone()];
+    let mut circuit = load_compiled_circuit(compiled_circuit, encryption_key)?;
+    check_commitment(circuit.settings(), commitment)?;
 
     if let Some(witness) = witness {
         let data = GraphWitness::from_path(witness)?;
@@ -1601,18 +3761,35 @@ pub(crate) fn setup(
     }
 
     let logrows = circuit.settings().run_args.logrows;
+    let commitment = circuit.settings().run_args.commitment;
+
+    let mut checkpoint = match checkpoint_dir {
+        Some(dir) => {
+            let files = inputs.iter().flatten().map(|p| p.as_path()).collect_vec();
+            let srs = get_srs_path(logrows, srs_path.clone(), commitment);
+            let args = serde_json::json!({
+                "logrows": logrows,
+                "commitment": commitment,
+                "disable_selector_compression": disable_selector_compression,
+            });
+            let fingerprint = fingerprint(&files, &[srs.as_path()], args)?;
+            Some(Checkpoint::open(&dir, "setup", fingerprint, resume)?)
+        }
+        None => None,
+    };
 
-    let pk = match circuit.settings().run_args.commitment {
+    let pk = match commitment {
         Commitments::KZG => {
             let params = load_params_prover::<KZGCommitmentScheme<Bn256>>(
                 srs_path,
                 logrows,
                 Commitments::KZG,
             )?;
-            create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(
+            setup_keys::<KZGCommitmentScheme<Bn256>>(
                 &circuit,
                 &params,
                 disable_selector_compression,
+                checkpoint.as_mut(),
             )?
         }
         Commitments::IPA => {
@@ -1621,18 +3798,61 @@ pub(crate) fn setup(
                 logrows,
                 Commitments::IPA,
             )?;
-            create_keys::<IPACommitmentScheme<G1Affine>, GraphCircuit>(
+            setup_keys::<IPACommitmentScheme<G1Affine>>(
                 &circuit,
                 &params,
                 disable_selector_compression,
+                checkpoint.as_mut(),
             )?
         }
     };
-    save_vk::<G1Affine>(&vk_path, pk.get_vk())?;
-    save_pk::<G1Affine>(&pk_path, &pk)?;
+    save_vk::<G1Affine>(&vk_path, pk.get_vk(), circuit.settings())?;
+    save_pk::<G1Affine>(&pk_path, &pk, circuit.settings())?;
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish()?;
+    }
     Ok(String::new())
 }
 
+/// Generates the keys of a circuit, keeping each in `checkpoint` once generated so that a resumed
+/// setup loads them rather than generating them again. halo2 computes the fixed and permutation
+/// polynomials of the proving key within a single call, so the proving key is kept whole.
+fn setup_keys<Scheme: CommitmentScheme<Scalar = Fr, Curve = G1Affine>>(
+    circuit: &GraphCircuit,
+    params: &Scheme::ParamsProver,
+    disable_selector_compression: bool,
+    mut checkpoint: Option<&mut Checkpoint>,
+) -> Result<plonk::ProvingKey<G1Affine>, Box<dyn Error>> {
+    const VK_STAGE: &str = "vk";
+    const PK_STAGE: &str = "pk";
+    if let Some(checkpoint) = checkpoint.as_deref() {
+        if checkpoint.is_complete(PK_STAGE) {
+            let path = checkpoint.artifact(PK_STAGE);
+            return load_pk::<Scheme, GraphCircuit>(path, circuit.params(), false);
+        }
+    }
+    let vk = match checkpoint.as_deref_mut() {
+        Some(checkpoint) if checkpoint.is_complete(VK_STAGE) => {
+            load_vk::<Scheme, GraphCircuit>(checkpoint.artifact(VK_STAGE), circuit.params(), false)?
+        }
+        checkpoint => {
+            let vk =
+                create_vk::<Scheme, GraphCircuit>(circuit, params, disable_selector_compression)?;
+            if let Some(checkpoint) = checkpoint {
+                save_vk::<G1Affine>(&checkpoint.artifact(VK_STAGE), &vk, circuit.settings())?;
+                checkpoint.complete(VK_STAGE)?;
+            }
+            vk
+        }
+    };
+    let pk = create_pk::<Scheme, GraphCircuit>(circuit, params, vk)?;
+    if let Some(checkpoint) = checkpoint {
+        save_pk::<G1Affine>(&checkpoint.artifact(PK_STAGE), &pk, circuit.settings())?;
+        checkpoint.complete(PK_STAGE)?;
+    }
+    Ok(pk)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) async fn setup_test_evm_witness(
     data_path: PathBuf,
@@ -1696,43 +3916,256 @@ pub(crate) fn prove(
     srs_path: Option<PathBuf>,
     proof_type: ProofType,
     check_mode: CheckMode,
+    encryption_key: Option<PathBuf>,
+    commitment: Option<Commitments>,
+    transcript: Option<TranscriptType>,
+    accelerator: Option<Accelerator>,
+    assignment_path: Option<PathBuf>,
+    scrub: bool,
+    seed: Option<u64>,
+    trim_memory: bool,
+    profile: bool,
+    shadow_model: Option<PathBuf>,
+    checkpoint_dir: Option<PathBuf>,
+    resume: bool,
+    force: bool,
 ) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
-    let data = GraphWitness::from_path(data_path)?;
-    let mut circuit = GraphCircuit::load(compiled_circuit_path)?;
+    let _warnings = crate::warnings::scope();
+    if shadow_model.is_some() && !cfg!(feature = "onnxruntime") {
+        return Err("prove --shadow-model needs ezkl built with the onnxruntime feature".into());
+    }
+    if trim_memory {
+        info!("trimming memory while proving, this will be slower");
+    }
+    let profiled = profile.then(crate::pfsys::profile::scope);
+    crate::pfsys::profile::start("witness loading");
+    let witness_start = Instant::now();
+    let mut circuit = load_compiled_circuit(compiled_circuit_path.clone(), encryption_key)?;
+    check_commitment(circuit.settings(), commitment)?;
+
+    let (mut data, assigned_instances, data_path) = match assignment_path {
+        Some(path) => {
+            let assignment = Assignment::load(&path)?;
+            assignment.check(circuit.settings())?;
+            (assignment.witness, Some(assignment.instances), path)
+        }
+        None => (GraphWitness::from_path(data_path.clone())?, None, data_path),
+    };
 
     circuit.load_graph_witness(&data)?;
+    if scrub {
+        circuit.scrub();
+    }
 
     let pretty_public_inputs = circuit.pretty_public_inputs(&data)?;
     let public_inputs = circuit.prepare_public_inputs(&data)?;
+    if assigned_instances.is_some_and(|instances| instances != public_inputs) {
+        return Err(
+            "the public instances of the assignment don't match those of its witness".into(),
+        );
+    }
+    crate::pfsys::profile::record("witness loading", witness_start.elapsed());
 
     let circuit_settings = circuit.settings().clone();
+    let logrows = circuit_settings.run_args.logrows;
 
     let strategy: StrategyType = proof_type.into();
-    let transcript: TranscriptType = proof_type.into();
-    let proof_split_commits: Option<ProofSplitCommit> = data.into();
+    let transcript: TranscriptType = transcript.unwrap_or(proof_type.into());
+    if strategy == StrategyType::Accum && transcript != TranscriptType::Poseidon {
+        return Err("proofs for aggregation must use the poseidon transcript".into());
+    }
+    let proof_split_commits: Option<ProofSplitCommit> = (&data).into();
+
+    if let Some(accelerator) = accelerator {
+        crate::pfsys::check_accelerator(accelerator)?;
+    }
 
     let commitment = circuit_settings.run_args.commitment;
-    // creates and verifies the proof
-    let mut snark = match commitment {
+    let mut num_threads = circuit_settings.run_args.num_threads;
+    if trim_memory {
+        num_threads = Some(crate::memory::trimmed_threads(num_threads));
+    }
+    let settings_hash = circuit_settings.settings_hash();
+    let mut checkpoint = match checkpoint_dir {
+        Some(dir) => {
+            let srs = get_srs_path(logrows, srs_path.clone(), commitment);
+            let args = serde_json::json!({
+                "proof_type": proof_type,
+                "check_mode": check_mode,
+                "commitment": commitment,
+                "transcript": transcript,
+                "seed": seed,
+            });
+            let fingerprint = fingerprint(
+                &[compiled_circuit_path.as_path(), data_path.as_path()],
+                &[pk_path.as_path(), srs.as_path()],
+                args,
+            )?;
+            Some(Checkpoint::open(&dir, "prove", fingerprint, resume)?)
+        }
+        None => None,
+    };
+    const PROOF_STAGE: &str = "proof";
+    let resumed = match &checkpoint {
+        Some(checkpoint) if checkpoint.is_complete(PROOF_STAGE) => {
+            let path = checkpoint.artifact(PROOF_STAGE);
+            Some(match commitment {
+                Commitments::KZG => Snark::load::<KZGCommitmentScheme<Bn256>>(&path)?,
+                Commitments::IPA => Snark::load::<IPACommitmentScheme<G1Affine>>(&path)?,
+            })
+        }
+        _ => None,
+    };
+    // runs the original model alongside proving, so that a proof of outputs materially different
+    // from a trusted execution is never emitted
+    let shadow = match shadow_model {
+        Some(model_path) => {
+            let settings = circuit_settings.clone();
+            let input_shapes = circuit.model().graph.input_shapes()?;
+            let mut witness = data.clone();
+            Some(std::thread::spawn(move || {
+                let report = crate::graph::expected::shadow_outputs(
+                    &settings,
+                    input_shapes,
+                    &witness,
+                    &model_path,
+                )
+                .map_err(|e| e.to_string());
+                if scrub {
+                    scrub_witness(&mut witness);
+                }
+                report
+            }))
+        }
+        None => None,
+    };
+    // creates and verifies the proof, unless it was already created before resuming
+    let mut snark = match resumed {
+        Some(snark) => snark,
+        None => {
+            let snark = crate::threads::install(num_threads, "proving", move || {
+                create_proof_from_keys(
+                    circuit,
+                    public_inputs,
+                    pk_path,
+                    srs_path,
+                    strategy,
+                    check_mode,
+                    transcript,
+                    proof_split_commits,
+                    seed,
+                    trim_memory,
+                    force,
+                )
+            })?;
+            if let Some(checkpoint) = &mut checkpoint {
+                snark.save(&checkpoint.artifact(PROOF_STAGE))?;
+                checkpoint.complete(PROOF_STAGE)?;
+            }
+            snark
+        }
+    };
+
+    if let Some(peak) = trim_memory.then(crate::memory::peak_rss).flatten() {
+        info!("peak memory usage: {} MiB", peak >> 20);
+    }
+
+    let shadow_check = match shadow {
+        Some(shadow) => shadow
+            .join()
+            .map_err(|_| "the shadow model panicked".to_string())
+            .and_then(|report| report.map_err(|e| format!("failed to run the shadow model: {}", e)))
+            .and_then(|report| {
+                if !report.passed() {
+                    return Err(format!(
+                        "refusing to emit a proof whose outputs differ from the shadow model: {}",
+                        report
+                    ));
+                }
+                info!("shadow model: {}", report);
+                Ok(())
+            }),
+        None => Ok(()),
+    };
+    // the witness is scrubbed whether or not the proof is emitted
+    if scrub && shadow_check.is_err() {
+        scrub_witness(&mut data);
+        shred_file(&data_path)?;
+        info!("scrubbed witness {}", data_path.display());
+    }
+    shadow_check?;
+
+    snark.pretty_public_inputs = pretty_public_inputs;
+    snark.settings_hash = settings_hash;
+    snark.warnings = data.warnings.clone();
+    snark.warnings.extend(crate::warnings::take());
+
+    if let Some(proof_path) = proof_path {
+        snark.save(&proof_path)?;
+        if let Some(profiled) = &profiled {
+            let report = ProofReport::new(&snark, logrows, profiled, crate::memory::peak_rss());
+            let report_path = ProofReport::path_for(&proof_path);
+            report.save(&report_path)?;
+            info!("wrote proving report to {}", report_path.display());
+        }
+    }
+
+    if scrub {
+        scrub_witness(&mut data);
+        shred_file(&data_path)?;
+        info!("scrubbed witness {}", data_path.display());
+    }
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish()?;
+    }
+
+    Ok(snark)
+}
+
+/// Loads the proving key and SRS of a circuit and proves it.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+fn create_proof_from_keys(
+    circuit: GraphCircuit,
+    public_inputs: Vec<Fr>,
+    pk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    strategy: StrategyType,
+    check_mode: CheckMode,
+    transcript: TranscriptType,
+    proof_split_commits: Option<ProofSplitCommit>,
+    seed: Option<u64>,
+    trim_memory: bool,
+    force: bool,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let commitment = circuit.settings().run_args.commitment;
+    let logrows = circuit.settings().run_args.logrows;
+    match commitment {
         Commitments::KZG => {
-            let pk =
-                load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(pk_path, circuit.params())?;
+            crate::pfsys::profile::start("key loading");
+            let keys_start = Instant::now();
+            let pk = load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(
+                pk_path,
+                circuit.params(),
+                force,
+            )?;
 
             let params = load_params_prover::<KZGCommitmentScheme<Bn256>>(
                 srs_path,
-                circuit_settings.run_args.logrows,
+                logrows,
                 Commitments::KZG,
             )?;
+            crate::pfsys::profile::record("key loading", keys_start.elapsed());
+            if trim_memory {
+                crate::memory::trim_heap();
+            }
             match strategy {
-                StrategyType::Single => create_proof_circuit::<
+                StrategyType::Single => create_single_proof::<
                     KZGCommitmentScheme<Bn256>,
                     _,
                     ProverSHPLONK<_>,
                     VerifierSHPLONK<_>,
                     KZGSingleStrategy<_>,
-                    _,
-                    EvmTranscript<_, _, _, _>,
-                    EvmTranscript<_, _, _, _>,
                 >(
                     circuit,
                     vec![public_inputs],
@@ -1742,7 +4175,7 @@ pub(crate) fn prove(
                     commitment,
                     transcript,
                     proof_split_commits,
-                    None,
+                    seed,
                 ),
                 StrategyType::Accum => {
                     let protocol = Some(compile(
@@ -1770,29 +4203,36 @@ pub(crate) fn prove(
                         transcript,
                         proof_split_commits,
                         protocol,
+                        seed,
                     )
                 }
             }
         }
         Commitments::IPA => {
-            let pk =
-                load_pk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(pk_path, circuit.params())?;
+            crate::pfsys::profile::start("key loading");
+            let keys_start = Instant::now();
+            let pk = load_pk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(
+                pk_path,
+                circuit.params(),
+                force,
+            )?;
 
             let params = load_params_prover::<IPACommitmentScheme<G1Affine>>(
                 srs_path,
-                circuit_settings.run_args.logrows,
+                logrows,
                 Commitments::IPA,
             )?;
+            crate::pfsys::profile::record("key loading", keys_start.elapsed());
+            if trim_memory {
+                crate::memory::trim_heap();
+            }
             match strategy {
-                StrategyType::Single => create_proof_circuit::<
+                StrategyType::Single => create_single_proof::<
                     IPACommitmentScheme<G1Affine>,
                     _,
                     ProverIPA<_>,
                     VerifierIPA<_>,
                     IPASingleStrategy<_>,
-                    _,
-                    EvmTranscript<_, _, _, _>,
-                    EvmTranscript<_, _, _, _>,
                 >(
                     circuit,
                     vec![public_inputs],
@@ -1802,7 +4242,7 @@ pub(crate) fn prove(
                     commitment,
                     transcript,
                     proof_split_commits,
-                    None,
+                    seed,
                 ),
                 StrategyType::Accum => {
                     let protocol = Some(compile(
@@ -1829,19 +4269,163 @@ pub(crate) fn prove(
                         transcript,
                         proof_split_commits,
                         protocol,
+                        seed,
                     )
                 }
             }
         }
-    }?;
+    }
+}
 
-    snark.pretty_public_inputs = pretty_public_inputs;
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prove_batch(
+    witness_dir: PathBuf,
+    compiled_circuit_path: PathBuf,
+    pk_path: PathBuf,
+    proof_dir: PathBuf,
+    srs_path: Option<PathBuf>,
+    proof_type: ProofType,
+    check_mode: CheckMode,
+    encryption_key: Option<PathBuf>,
+    commitment: Option<Commitments>,
+    transcript: Option<TranscriptType>,
+    accelerator: Option<Accelerator>,
+    scrub: bool,
+    seed: Option<u64>,
+    force: bool,
+) -> Result<BatchManifest, Box<dyn Error>> {
+    if proof_type != ProofType::Single {
+        return Err("batches can only be proven with --proof-type=single".into());
+    }
+    let witnesses = list_witnesses(&witness_dir)?;
+    if witnesses.is_empty() {
+        return Err(format!("no .json witnesses found in {}", witness_dir.display()).into());
+    }
+    std::fs::create_dir_all(&proof_dir)?;
+    if proof_dir.canonicalize()? == witness_dir.canonicalize()? {
+        return Err("proofs can't be written to the witness dir".into());
+    }
+    let proof_paths = crate::pfsys::batch::proof_paths(&proof_dir, &witnesses);
 
-    if let Some(proof_path) = proof_path {
-        snark.save(&proof_path)?;
+    let circuit = load_compiled_circuit(compiled_circuit_path, encryption_key)?;
+    check_commitment(circuit.settings(), commitment)?;
+    let settings = circuit.settings().clone();
+    let settings_hash = settings.settings_hash();
+    let transcript: TranscriptType = transcript.unwrap_or(proof_type.into());
+    if let Some(accelerator) = accelerator {
+        crate::pfsys::check_accelerator(accelerator)?;
     }
 
-    Ok(snark)
+    // loads a witness into its own copy of the circuit
+    let prepare = |(path, proof_path): (PathBuf, PathBuf)| -> Result<_, Box<dyn Error>> {
+        let mut data = GraphWitness::from_path(path.clone())?;
+        let mut circuit = circuit.clone();
+        circuit.load_graph_witness(&data)?;
+        if scrub {
+            circuit.scrub();
+        }
+        let pretty_public_inputs = circuit.pretty_public_inputs(&data)?;
+        let instances = vec![circuit.prepare_public_inputs(&data)?];
+        let split = (&data).into();
+        let warnings = std::mem::take(&mut data.warnings);
+        if scrub {
+            scrub_witness(&mut data);
+        }
+        Ok(BatchItem {
+            circuit,
+            instances,
+            split,
+            context: (path, proof_path, pretty_public_inputs, warnings),
+        })
+    };
+    // writes each proof next to the others, named after its witness
+    let finish = |mut snark: Snark<Fr, G1Affine>,
+                  (path, proof_path, pretty_public_inputs, warnings): (
+        PathBuf,
+        PathBuf,
+        Option<crate::pfsys::PrettyElements>,
+        Vec<crate::warnings::Warning>,
+    )|
+     -> Result<PathBuf, Box<dyn Error>> {
+        snark.pretty_public_inputs = pretty_public_inputs;
+        snark.settings_hash = settings_hash.clone();
+        snark.warnings = warnings;
+        snark.save(&proof_path)?;
+        if scrub {
+            shred_file(&path)?;
+        }
+        Ok(proof_path)
+    };
+
+    let commitment = settings.run_args.commitment;
+    let logrows = settings.run_args.logrows;
+    let jobs = witnesses
+        .iter()
+        .cloned()
+        .zip(proof_paths)
+        .collect::<Vec<_>>();
+    let results =
+        crate::threads::install(settings.run_args.num_threads, "batch proving", move || {
+            match commitment {
+                Commitments::KZG => {
+                    let pk = load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(
+                        pk_path, settings, force,
+                    )?;
+                    let params = load_params_prover::<KZGCommitmentScheme<Bn256>>(
+                        srs_path,
+                        logrows,
+                        Commitments::KZG,
+                    )?;
+                    Ok(crate::pfsys::batch::prove_batch::<
+                        KZGCommitmentScheme<Bn256>,
+                        _,
+                        ProverSHPLONK<_>,
+                        VerifierSHPLONK<_>,
+                        KZGSingleStrategy<_>,
+                        _,
+                        _,
+                        _,
+                    >(
+                        jobs, prepare, finish, &params, &pk, check_mode, commitment, transcript,
+                        seed,
+                    ))
+                }
+                Commitments::IPA => {
+                    let pk = load_pk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(
+                        pk_path, settings, force,
+                    )?;
+                    let params = load_params_prover::<IPACommitmentScheme<G1Affine>>(
+                        srs_path,
+                        logrows,
+                        Commitments::IPA,
+                    )?;
+                    Ok(crate::pfsys::batch::prove_batch::<
+                        IPACommitmentScheme<G1Affine>,
+                        _,
+                        ProverIPA<_>,
+                        VerifierIPA<_>,
+                        IPASingleStrategy<_>,
+                        _,
+                        _,
+                        _,
+                    >(
+                        jobs, prepare, finish, &params, &pk, check_mode, commitment, transcript,
+                        seed,
+                    ))
+                }
+            }
+        })?;
+
+    let manifest = BatchManifest::new(witnesses, results);
+    manifest.save(&proof_dir.join("manifest.json"))?;
+    info!(
+        "proved {} of {} witnesses, manifest written to {}",
+        manifest.entries.len() - manifest.num_failed(),
+        manifest.entries.len(),
+        proof_dir.join("manifest.json").display()
+    );
+    Ok(manifest)
 }
 
 pub(crate) fn swap_proof_commitments_cmd(
@@ -1866,10 +4450,49 @@ pub(crate) fn swap_proof_commitments_cmd(
     Ok(snark_new)
 }
 
+pub(crate) fn compress_proof(
+    proof_path: PathBuf,
+    output_path: Option<PathBuf>,
+    decompress: bool,
+) -> Result<String, Box<dyn Error>> {
+    // proofs are decompressed as they are loaded
+    let mut snark = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+    let original_size = std::fs::metadata(&proof_path)?.len();
+    if !decompress {
+        snark.compress();
+    }
+    let output_path = output_path.unwrap_or(proof_path);
+    snark.save(&output_path)?;
+    info!(
+        "proof went from {} to {} bytes",
+        original_size,
+        std::fs::metadata(&output_path)?.len()
+    );
+    Ok(String::new())
+}
+
+/// The aggregation circuit over `snarks`, a universal verifier (see
+/// [AggregationCircuit::universal]) if `universal` is set.
+fn aggregation_circuit(
+    svk: &KzgSuccinctVerifyingKey<G1Affine>,
+    snarks: Vec<Snark<Fr, G1Affine>>,
+    split_proofs: bool,
+    universal: bool,
+) -> Result<AggregationCircuit, Box<dyn Error>> {
+    if !universal {
+        return Ok(AggregationCircuit::new(svk, snarks, split_proofs)?);
+    }
+    if split_proofs {
+        return Err("split proofs can't be aggregated into a universal verifier".into());
+    }
+    Ok(AggregationCircuit::universal(svk, snarks)?)
+}
+
 pub(crate) fn mock_aggregate(
     aggregation_snarks: Vec<PathBuf>,
     logrows: u32,
     split_proofs: bool,
+    universal: bool,
 ) -> Result<String, Box<dyn Error>> {
     let mut snarks = vec![];
     for proof_path in aggregation_snarks.iter() {
@@ -1892,7 +4515,12 @@ pub(crate) fn mock_aggregate(
         pb
     };
 
-    let circuit = AggregationCircuit::new(&G1Affine::generator().into(), snarks, split_proofs)?;
+    let circuit = aggregation_circuit(
+        &G1Affine::generator().into(),
+        snarks,
+        split_proofs,
+        universal,
+    )?;
 
     let prover = halo2_proofs::dev::MockProver::run(logrows, &circuit, vec![circuit.instances()])
         .map_err(Box::<dyn Error>::from)?;
@@ -1911,6 +4539,7 @@ pub(crate) fn setup_aggregate(
     srs_path: Option<PathBuf>,
     logrows: u32,
     split_proofs: bool,
+    universal: bool,
     disable_selector_compression: bool,
     commitment: Commitments,
 ) -> Result<String, Box<dyn Error>> {
@@ -1928,7 +4557,12 @@ pub(crate) fn setup_aggregate(
         }
     }
 
-    let circuit = AggregationCircuit::new(&G1Affine::generator().into(), snarks, split_proofs)?;
+    let circuit = aggregation_circuit(
+        &G1Affine::generator().into(),
+        snarks,
+        split_proofs,
+        universal,
+    )?;
 
     let pk = match commitment {
         Commitments::KZG => {
@@ -1957,8 +4591,8 @@ pub(crate) fn setup_aggregate(
             )?
         }
     };
-    save_vk::<G1Affine>(&vk_path, pk.get_vk())?;
-    save_pk::<G1Affine>(&pk_path, &pk)?;
+    save_vk::<G1Affine>(&vk_path, pk.get_vk(), &())?;
+    save_pk::<G1Affine>(&pk_path, &pk, &())?;
 
     Ok(String::new())
 }
@@ -1973,6 +4607,7 @@ pub(crate) fn aggregate(
     logrows: u32,
     check_mode: CheckMode,
     split_proofs: bool,
+    universal: bool,
     commitment: Commitments,
 ) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
     let mut snarks = vec![];
@@ -1998,19 +4633,21 @@ pub(crate) fn aggregate(
     };
 
     let now = Instant::now();
+    let inner_proofs = AggregationCircuit::inner_layout(&snarks);
 
-    let snark = match commitment {
+    let mut snark = match commitment {
         Commitments::KZG => {
-            let pk = load_pk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(pk_path, ())?;
+            let pk = load_pk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(pk_path, (), false)?;
             let params: ParamsKZG<Bn256> = load_params_prover::<KZGCommitmentScheme<_>>(
                 srs_path.clone(),
                 logrows,
                 Commitments::KZG,
             )?;
-            let circuit = AggregationCircuit::new(
+            let circuit = aggregation_circuit(
                 &ParamsProver::<G1Affine>::get_g(&params)[0].into(),
                 snarks,
                 split_proofs,
+                universal,
             )?;
             let public_inputs = circuit.instances();
             match transcript {
@@ -2033,7 +4670,11 @@ pub(crate) fn aggregate(
                     transcript,
                     None,
                     None,
+                    None,
                 ),
+                TranscriptType::Blake2b => {
+                    Err("aggregated proofs are only proven with evm or poseidon transcripts".into())
+                }
                 TranscriptType::Poseidon => {
                     let protocol = Some(compile(
                         &params,
@@ -2060,21 +4701,23 @@ pub(crate) fn aggregate(
                         transcript,
                         None,
                         protocol,
+                        None,
                     )
                 }
             }
         }
         Commitments::IPA => {
-            let pk = load_pk::<IPACommitmentScheme<_>, AggregationCircuit>(pk_path, ())?;
+            let pk = load_pk::<IPACommitmentScheme<_>, AggregationCircuit>(pk_path, (), false)?;
             let params: ParamsIPA<_> = load_params_prover::<IPACommitmentScheme<_>>(
                 srs_path.clone(),
                 logrows,
                 Commitments::IPA,
             )?;
-            let circuit = AggregationCircuit::new(
+            let circuit = aggregation_circuit(
                 &ParamsProver::<G1Affine>::get_g(&params)[0].into(),
                 snarks,
                 split_proofs,
+                universal,
             )?;
             let public_inputs = circuit.instances();
 
@@ -2098,7 +4741,11 @@ pub(crate) fn aggregate(
                     transcript,
                     None,
                     None,
+                    None,
                 ),
+                TranscriptType::Blake2b => {
+                    Err("aggregated proofs are only proven with evm or poseidon transcripts".into())
+                }
                 TranscriptType::Poseidon => {
                     let protocol = Some(compile(
                         &params,
@@ -2125,12 +4772,14 @@ pub(crate) fn aggregate(
                         transcript,
                         None,
                         protocol,
+                        None,
                     )
                 }
             }
         }
     }?;
     // the K used for the aggregation circuit
+    snark.inner_proofs = Some(inner_proofs);
 
     let elapsed = now.elapsed();
     info!(
@@ -2146,20 +4795,160 @@ pub(crate) fn aggregate(
     Ok(snark)
 }
 
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn aggregate_tree(
+    aggregation_snarks: Vec<PathBuf>,
+    proof_path: PathBuf,
+    vk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    transcript: TranscriptType,
+    logrows: u32,
+    fan_in: usize,
+    check_mode: CheckMode,
+    disable_selector_compression: bool,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let snarks = aggregation_snarks
+        .iter()
+        .map(|path| {
+            Snark::load::<KZGCommitmentScheme<Bn256>>(path).map_err(|_| {
+                format!(
+                    "invalid sample commitment type for aggregation at {}, must be KZG",
+                    path.display()
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let pb = {
+        let pb = init_spinner();
+        pb.set_message(format!(
+            "Aggregating {} proofs with a fan-in of {} (may take a while)...",
+            snarks.len(),
+            fan_in
+        ));
+        pb
+    };
+
+    let now = Instant::now();
+    let params: ParamsKZG<Bn256> =
+        load_params_prover::<KZGCommitmentScheme<_>>(srs_path, logrows, Commitments::KZG)?;
+    let (snark, vk) = crate::pfsys::aggregation_tree::aggregate_tree(
+        &params,
+        snarks,
+        fan_in,
+        transcript,
+        check_mode,
+        disable_selector_compression,
+    )?;
+
+    let elapsed = now.elapsed();
+    info!(
+        "Aggregation tree took {}.{}",
+        elapsed.as_secs(),
+        elapsed.subsec_millis()
+    );
+    snark.save(&proof_path)?;
+    save_vk::<G1Affine>(&vk_path, &vk, &())?;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pb.finish_with_message("Done.");
+
+    Ok(snark)
+}
+
+/// Pushes a proof generated for aggregation into the aggregation state at `state_path`, creating
+/// the state if it doesn't exist yet.
+pub(crate) fn push_to_aggregation(
+    snark: Snark<Fr, G1Affine>,
+    state_path: PathBuf,
+    fan_in: Option<usize>,
+    logrows: Option<u32>,
+    srs_path: Option<PathBuf>,
+    check_mode: CheckMode,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let mut state = if state_path.exists() {
+        let state = AggregationState::load(&state_path)?;
+        state.check(fan_in, logrows, srs_path.as_ref())?;
+        state
+    } else {
+        let fan_in = match fan_in {
+            Some(fan_in) => fan_in,
+            None => DEFAULT_AGGREGATION_FAN_IN.parse()?,
+        };
+        let logrows = match logrows {
+            Some(logrows) => logrows,
+            None => DEFAULT_AGGREGATED_LOGROWS.parse()?,
+        };
+        let srs_path = get_srs_path(logrows, srs_path, Commitments::KZG);
+        AggregationState::new(state_path.clone(), fan_in, logrows, srs_path)?
+    };
+
+    let params: ParamsKZG<Bn256> = load_params_prover::<KZGCommitmentScheme<_>>(
+        Some(state.srs_path.clone()),
+        state.logrows,
+        Commitments::KZG,
+    )?;
+    state.push(&params, snark.clone(), check_mode, false)?;
+    state.save()?;
+
+    info!(
+        "pushed proof {} into the aggregation state at {}",
+        state.num_proofs,
+        state_path.display()
+    );
+    Ok(snark)
+}
+
+pub(crate) fn finalize_aggregation(
+    state_path: PathBuf,
+    proof_path: PathBuf,
+    vk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    transcript: TranscriptType,
+    check_mode: CheckMode,
+    disable_selector_compression: bool,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let state = AggregationState::load(&state_path)?;
+    state.check(None, None, srs_path.as_ref())?;
+    let num_proofs = state.num_proofs;
+    let params: ParamsKZG<Bn256> = load_params_prover::<KZGCommitmentScheme<_>>(
+        Some(state.srs_path.clone()),
+        state.logrows,
+        Commitments::KZG,
+    )?;
+
+    let (snark, vk) = state.finalize(
+        &params,
+        transcript,
+        check_mode,
+        disable_selector_compression,
+    )?;
+    snark.save(&proof_path)?;
+    save_vk::<G1Affine>(&vk_path, &vk, &())?;
+
+    info!("produced the final proof over {} proofs", num_proofs);
+    Ok(snark)
+}
+
 pub(crate) fn verify(
     proof_path: PathBuf,
     settings_path: PathBuf,
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
     reduced_srs: bool,
+    commitment: Option<Commitments>,
+    force: bool,
 ) -> Result<bool, Box<dyn Error>> {
     let circuit_settings = GraphSettings::load(&settings_path)?;
+    check_commitment(&circuit_settings, commitment)?;
 
     let logrows = circuit_settings.run_args.logrows;
 
     match circuit_settings.run_args.commitment {
         Commitments::KZG => {
             let proof = Snark::load::<KZGCommitmentScheme<Bn256>>(&proof_path)?;
+            circuit_settings.check_model_hash(&proof.instances.concat())?;
             let params: ParamsKZG<Bn256> = if reduced_srs {
                 // only need G_0 for the verification with shplonk
                 load_params_verifier::<KZGCommitmentScheme<Bn256>>(srs_path, 1, Commitments::KZG)?
@@ -2171,60 +4960,113 @@ pub(crate) fn verify(
                 )?
             };
             match proof.transcript_type {
-                TranscriptType::EVM => {
-                    verify_commitment::<
-                        KZGCommitmentScheme<Bn256>,
-                        VerifierSHPLONK<'_, Bn256>,
-                        _,
-                        KZGSingleStrategy<_>,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        KZGCommitmentScheme<Bn256>,
-                        VerifierSHPLONK<'_, Bn256>,
-                        _,
-                        KZGSingleStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
+                TranscriptType::EVM => verify_commitment::<
+                    KZGCommitmentScheme<Bn256>,
+                    VerifierSHPLONK<'_, Bn256>,
+                    _,
+                    KZGSingleStrategy<_>,
+                    EvmTranscript<G1Affine, _, _, _>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
+                TranscriptType::Poseidon => verify_commitment::<
+                    KZGCommitmentScheme<Bn256>,
+                    VerifierSHPLONK<'_, Bn256>,
+                    _,
+                    KZGSingleStrategy<_>,
+                    PoseidonTranscript<NativeLoader, _>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
+                TranscriptType::Blake2b => verify_commitment::<
+                    KZGCommitmentScheme<Bn256>,
+                    VerifierSHPLONK<'_, Bn256>,
+                    _,
+                    KZGSingleStrategy<_>,
+                    Blake2bRead<_, _, Challenge255<_>>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
             }
         }
         Commitments::IPA => {
             let proof = Snark::load::<IPACommitmentScheme<G1Affine>>(&proof_path)?;
+            circuit_settings.check_model_hash(&proof.instances.concat())?;
             let params: ParamsIPA<_> = load_params_verifier::<IPACommitmentScheme<G1Affine>>(
                 srs_path,
                 logrows,
                 Commitments::IPA,
             )?;
             match proof.transcript_type {
-                TranscriptType::EVM => {
-                    verify_commitment::<
-                        IPACommitmentScheme<G1Affine>,
-                        VerifierIPA<_>,
-                        _,
-                        IPASingleStrategy<_>,
-                        EvmTranscript<G1Affine, _, _, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
-                TranscriptType::Poseidon => {
-                    verify_commitment::<
-                        IPACommitmentScheme<G1Affine>,
-                        VerifierIPA<_>,
-                        _,
-                        IPASingleStrategy<_>,
-                        PoseidonTranscript<NativeLoader, _>,
-                        GraphCircuit,
-                        _,
-                    >(proof_path, circuit_settings, vk_path, &params, logrows)
-                }
+                TranscriptType::EVM => verify_commitment::<
+                    IPACommitmentScheme<G1Affine>,
+                    VerifierIPA<_>,
+                    _,
+                    IPASingleStrategy<_>,
+                    EvmTranscript<G1Affine, _, _, _>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
+                TranscriptType::Poseidon => verify_commitment::<
+                    IPACommitmentScheme<G1Affine>,
+                    VerifierIPA<_>,
+                    _,
+                    IPASingleStrategy<_>,
+                    PoseidonTranscript<NativeLoader, _>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
+                TranscriptType::Blake2b => verify_commitment::<
+                    IPACommitmentScheme<G1Affine>,
+                    VerifierIPA<_>,
+                    _,
+                    IPASingleStrategy<_>,
+                    Blake2bRead<_, _, Challenge255<_>>,
+                    GraphCircuit,
+                    _,
+                >(
+                    proof_path,
+                    circuit_settings,
+                    vk_path,
+                    &params,
+                    logrows,
+                    force,
+                ),
             }
         }
     }
@@ -2238,13 +5080,14 @@ fn verify_commitment<
     Strategy: VerificationStrategy<'a, Scheme, V>,
     TR: TranscriptReadBuffer<Cursor<Vec<u8>>, Scheme::Curve, E>,
     C: Circuit<<Scheme as CommitmentScheme>::Scalar, Params = Params>,
-    Params,
+    Params: KeyParams,
 >(
     proof_path: PathBuf,
     settings: Params,
     vk_path: PathBuf,
     params: &'a Scheme::ParamsVerifier,
     logrows: u32,
+    force: bool,
 ) -> Result<bool, Box<dyn Error>>
 where
     Scheme::Scalar: FromUniformBytes<64>
@@ -2256,9 +5099,10 @@ where
     Scheme::ParamsVerifier: 'a,
 {
     let proof = Snark::load::<Scheme>(&proof_path)?;
+    proof.check_settings(&settings)?;
 
     let strategy = Strategy::new(params);
-    let vk = load_vk::<Scheme, C>(vk_path, settings)?;
+    let vk = load_vk::<Scheme, C>(vk_path, settings, force)?;
     let now = Instant::now();
 
     let result =
@@ -2274,6 +5118,135 @@ where
     result.map_err(|e: plonk::Error| e.into()).map(|_| true)
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn verify_batch(
+    proof_dir: PathBuf,
+    settings_path: PathBuf,
+    vk_path: PathBuf,
+    srs_path: Option<PathBuf>,
+    reduced_srs: bool,
+    commitment: Option<Commitments>,
+    force: bool,
+) -> Result<VerificationReport, Box<dyn Error>> {
+    let proofs = list_proofs(&proof_dir)?;
+    if proofs.is_empty() {
+        return Err(format!("no .json or .cbor proofs found in {}", proof_dir.display()).into());
+    }
+    let circuit_settings = GraphSettings::load(&settings_path)?;
+    check_commitment(&circuit_settings, commitment)?;
+    let logrows = circuit_settings.run_args.logrows;
+
+    let jobs = proofs.clone();
+    let results = crate::threads::install(
+        circuit_settings.run_args.num_threads,
+        "batch verifying",
+        move || match circuit_settings.run_args.commitment {
+            Commitments::KZG => {
+                let params: ParamsKZG<Bn256> = if reduced_srs {
+                    // only need G_0 for the verification with shplonk
+                    load_params_verifier::<KZGCommitmentScheme<Bn256>>(
+                        srs_path,
+                        1,
+                        Commitments::KZG,
+                    )?
+                } else {
+                    load_params_verifier::<KZGCommitmentScheme<Bn256>>(
+                        srs_path,
+                        logrows,
+                        Commitments::KZG,
+                    )?
+                };
+                let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(
+                    vk_path,
+                    circuit_settings.clone(),
+                    force,
+                )?;
+                Ok(verify_proofs::<
+                    KZGCommitmentScheme<Bn256>,
+                    VerifierSHPLONK<_>,
+                    KZGSingleStrategy<_>,
+                >(&jobs, &circuit_settings, &params, &vk))
+            }
+            Commitments::IPA => {
+                let params: ParamsIPA<_> = load_params_verifier::<IPACommitmentScheme<G1Affine>>(
+                    srs_path,
+                    logrows,
+                    Commitments::IPA,
+                )?;
+                let vk = load_vk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(
+                    vk_path,
+                    circuit_settings.clone(),
+                    force,
+                )?;
+                Ok(verify_proofs::<
+                    IPACommitmentScheme<G1Affine>,
+                    VerifierIPA<_>,
+                    IPASingleStrategy<_>,
+                >(&jobs, &circuit_settings, &params, &vk))
+            }
+        },
+    )?;
+
+    let report = VerificationReport::new(proofs, results);
+    info!(
+        "verified {} of {} proofs",
+        report.entries.len() - report.num_failed(),
+        report.entries.len()
+    );
+    Ok(report)
+}
+
+/// Loads and checks each proof against the settings, then verifies those that load with
+/// [crate::pfsys::batch::verify_batch]. Returns one result per proof.
+#[cfg(not(target_arch = "wasm32"))]
+fn verify_proofs<
+    'a,
+    Scheme: CommitmentScheme<Scalar = Fr, Curve = G1Affine>,
+    V: Verifier<'a, Scheme>,
+    Strategy: VerificationStrategy<'a, Scheme, V>,
+>(
+    proofs: &[PathBuf],
+    settings: &GraphSettings,
+    params: &'a Scheme::ParamsVerifier,
+    vk: &VerifyingKey<G1Affine>,
+) -> Vec<Result<(), String>>
+where
+    Scheme::ParamsVerifier: Sync,
+{
+    let load = |path: &PathBuf| -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+        let proof = Snark::load::<Scheme>(path)?;
+        proof.check_settings(settings)?;
+        settings.check_model_hash(&proof.instances.concat())?;
+        Ok(proof)
+    };
+    // the error is flattened to a string as boxed errors can't be sent across threads
+    let loaded = proofs
+        .par_iter()
+        .map(|path| load(path).map_err(|e| e.to_string()))
+        .collect::<Vec<_>>();
+
+    let mut results = vec![Ok(()); proofs.len()];
+    let mut indices = vec![];
+    let mut snarks = vec![];
+    for (i, res) in loaded.into_iter().enumerate() {
+        match res {
+            Ok(snark) => {
+                indices.push(i);
+                snarks.push(snark);
+            }
+            Err(e) => results[i] = Err(e),
+        }
+    }
+
+    let orig_n = 1 << settings.run_args.logrows;
+    let verified =
+        crate::pfsys::batch::verify_batch::<Scheme, V, Strategy>(&snarks, params, vk, orig_n);
+    for (i, res) in indices.into_iter().zip(verified) {
+        results[i] = res;
+    }
+    results
+}
+
 pub(crate) fn verify_aggr(
     proof_path: PathBuf,
     vk_path: PathBuf,
@@ -2295,16 +5268,18 @@ pub(crate) fn verify_aggr(
                     Commitments::KZG,
                 )?
             };
-            match proof.transcript_type {
-                TranscriptType::EVM => verify_commitment::<
-                    KZGCommitmentScheme<Bn256>,
-                    VerifierSHPLONK<'_, Bn256>,
-                    _,
-                    KZGSingleStrategy<_>,
-                    EvmTranscript<_, _, _, _>,
-                    AggregationCircuit,
-                    _,
-                >(proof_path, (), vk_path, &params, logrows),
+            let verified = match proof.transcript_type {
+                TranscriptType::EVM => {
+                    verify_commitment::<
+                        KZGCommitmentScheme<Bn256>,
+                        VerifierSHPLONK<'_, Bn256>,
+                        _,
+                        KZGSingleStrategy<_>,
+                        EvmTranscript<_, _, _, _>,
+                        AggregationCircuit,
+                        _,
+                    >(proof_path, (), vk_path, &params, logrows, false)
+                }
                 TranscriptType::Poseidon => {
                     verify_commitment::<
                         KZGCommitmentScheme<Bn256>,
@@ -2314,9 +5289,26 @@ pub(crate) fn verify_aggr(
                         PoseidonTranscript<NativeLoader, _>,
                         AggregationCircuit,
                         _,
-                    >(proof_path, (), vk_path, &params, logrows)
+                    >(proof_path, (), vk_path, &params, logrows, false)
                 }
+                TranscriptType::Blake2b => {
+                    Err("aggregated proofs are only proven with evm or poseidon transcripts".into())
+                }
+            }?;
+
+            // the proof only attests to the accumulator of the aggregated proofs, which still
+            // needs to be decided for the inner proofs to be verified
+            let accumulator_verified = proof.instances.first().is_some_and(|instances| {
+                crate::pfsys::accumulator_decider::verify_accumulator(
+                    instances,
+                    &params.g2(),
+                    &params.s_g2(),
+                )
+            });
+            if !accumulator_verified {
+                log::error!("the accumulator of the aggregated proof is invalid");
             }
+            Ok(verified && accumulator_verified)
         }
         Commitments::IPA => {
             let proof = Snark::load::<IPACommitmentScheme<G1Affine>>(&proof_path)?;
@@ -2326,15 +5318,17 @@ pub(crate) fn verify_aggr(
                 Commitments::IPA,
             )?;
             match proof.transcript_type {
-                TranscriptType::EVM => verify_commitment::<
-                    IPACommitmentScheme<G1Affine>,
-                    VerifierIPA<_>,
-                    _,
-                    IPASingleStrategy<_>,
-                    EvmTranscript<_, _, _, _>,
-                    AggregationCircuit,
-                    _,
-                >(proof_path, (), vk_path, &params, logrows),
+                TranscriptType::EVM => {
+                    verify_commitment::<
+                        IPACommitmentScheme<G1Affine>,
+                        VerifierIPA<_>,
+                        _,
+                        IPASingleStrategy<_>,
+                        EvmTranscript<_, _, _, _>,
+                        AggregationCircuit,
+                        _,
+                    >(proof_path, (), vk_path, &params, logrows, false)
+                }
                 TranscriptType::Poseidon => {
                     verify_commitment::<
                         IPACommitmentScheme<G1Affine>,
@@ -2344,7 +5338,10 @@ pub(crate) fn verify_aggr(
                         PoseidonTranscript<NativeLoader, _>,
                         AggregationCircuit,
                         _,
-                    >(proof_path, (), vk_path, &params, logrows)
+                    >(proof_path, (), vk_path, &params, logrows, false)
+                }
+                TranscriptType::Blake2b => {
+                    Err("aggregated proofs are only proven with evm or poseidon transcripts".into())
                 }
             }
         }
@@ -2356,14 +5353,12 @@ pub(crate) fn load_params_verifier<Scheme: CommitmentScheme>(
     srs_path: Option<PathBuf>,
     logrows: u32,
     commitment: Commitments,
-) -> Result<Scheme::ParamsVerifier, Box<dyn Error>> {
+) -> Result<Scheme::ParamsVerifier, Box<dyn Error>>
+where
+    Scheme::Curve: SerdeObject,
+{
     let srs_path = get_srs_path(logrows, srs_path, commitment);
-    let mut params = load_srs_verifier::<Scheme>(srs_path)?;
-    info!("downsizing params to {} logrows", logrows);
-    if logrows < params.k() {
-        params.downsize(logrows);
-    }
-    Ok(params)
+    load_srs_verifier_trimmed::<Scheme>(srs_path, logrows)
 }
 
 /// helper function for load_params
@@ -2371,12 +5366,10 @@ pub(crate) fn load_params_prover<Scheme: CommitmentScheme>(
     srs_path: Option<PathBuf>,
     logrows: u32,
     commitment: Commitments,
-) -> Result<Scheme::ParamsProver, Box<dyn Error>> {
+) -> Result<Scheme::ParamsProver, Box<dyn Error>>
+where
+    Scheme::Curve: SerdeObject,
+{
     let srs_path = get_srs_path(logrows, srs_path, commitment);
-    let mut params = load_srs_prover::<Scheme>(srs_path)?;
-    info!("downsizing params to {} logrows", logrows);
-    if logrows < params.k() {
-        params.downsize(logrows);
-    }
-    Ok(params)
+    load_srs_prover_trimmed::<Scheme>(srs_path, logrows)
 }
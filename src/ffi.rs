@@ -0,0 +1,263 @@
+//! A stable C ABI for host applications embedding the `ezkl` cdylib.
+//!
+//! Hosts register callbacks receiving log lines and the progress of proving (see
+//! [crate::pfsys::profile::Progress]) instead of having them written to the terminal, and run
+//! commands with [ezkl_run]. Callbacks may be called from any thread, including several at once,
+//! and are passed back the `user_data` pointer they were registered with.
+//!
+//! The signatures here only change along with [EZKL_FFI_VERSION].
+#![allow(unsafe_code)]
+
+use crate::commands::Commands;
+use crate::pfsys::profile::{self, Progress};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{AssertUnwindSafe, UnwindSafe};
+use std::sync::{OnceLock, RwLock};
+
+/// The version of the C ABI, bumped whenever a signature changes.
+pub const EZKL_FFI_VERSION: u32 = 1;
+
+/// Receives a log line: its level (1 error, 2 warn, 3 info, 4 debug, 5 trace), the module it was
+/// logged from and the message. The strings are only valid for the duration of the call.
+pub type EzklLogCallback = extern "C" fn(
+    level: c_int,
+    target: *const c_char,
+    message: *const c_char,
+    user_data: *mut c_void,
+);
+
+/// Receives the progress of proving: the name of the stage, whether it started (0) or finished
+/// (1), and for finished stages how long they took in seconds. The name is only valid for the
+/// duration of the call.
+pub type EzklProgressCallback =
+    extern "C" fn(stage: *const c_char, event: c_int, seconds: f64, user_data: *mut c_void);
+
+/// The pointer a host registered a callback with, handed back to it untouched.
+#[derive(Clone, Copy, Debug)]
+struct UserData(*mut c_void);
+
+// SAFETY: the host registering a callback vouches for its user data being usable from the
+// threads the callback is called on, see the module docs
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    // a method rather than the field, so closures capture the whole of `UserData`
+    fn get(self) -> *mut c_void {
+        self.0
+    }
+}
+
+static LOG_CALLBACK: RwLock<Option<(EzklLogCallback, UserData)>> = RwLock::new(None);
+static LOGGER_INSTALLED: OnceLock<bool> = OnceLock::new();
+
+/// Forwards log records to the registered [EzklLogCallback].
+#[derive(Debug)]
+struct CallbackLogger;
+
+impl Log for CallbackLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        matches!(LOG_CALLBACK.read(), Ok(callback) if callback.is_some())
+    }
+
+    fn log(&self, record: &Record) {
+        let Ok(callback) = LOG_CALLBACK.read() else {
+            return;
+        };
+        if let Some((callback, user_data)) = *callback {
+            let target = to_c_string(record.target());
+            let message = to_c_string(&record.args().to_string());
+            callback(
+                record.level() as c_int,
+                target.as_ptr(),
+                message.as_ptr(),
+                user_data.get(),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Converts `s` for C, dropping any interior nul bytes.
+fn to_c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// The version of the C ABI the library implements, see [EZKL_FFI_VERSION].
+#[no_mangle]
+pub extern "C" fn ezkl_ffi_version() -> u32 {
+    EZKL_FFI_VERSION
+}
+
+/// Sends log lines up to `max_level` (as in [EzklLogCallback]) to `callback` instead of the
+/// terminal, replacing any callback registered before. A null `callback` drops log lines.
+///
+/// Returns 0, or -1 when another logger was installed in the process before the first
+/// registration, in which case log lines keep going to it.
+#[no_mangle]
+pub extern "C" fn ezkl_register_log_callback(
+    callback: Option<EzklLogCallback>,
+    max_level: c_int,
+    user_data: *mut c_void,
+) -> c_int {
+    match LOG_CALLBACK.write() {
+        Ok(mut current) => *current = callback.map(|cb| (cb, UserData(user_data))),
+        Err(_) => return -1,
+    }
+    // the logger can only be installed once, later registrations only swap the callback
+    if !*LOGGER_INSTALLED.get_or_init(|| log::set_logger(&CallbackLogger).is_ok()) {
+        return -1;
+    }
+    log::set_max_level(match max_level {
+        i if i <= 0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    });
+    0
+}
+
+/// Sends the progress of proving to `callback`, replacing any callback registered before. A null
+/// `callback` stops progress events. Returns 0.
+#[no_mangle]
+pub extern "C" fn ezkl_register_progress_callback(
+    callback: Option<EzklProgressCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let user_data = UserData(user_data);
+    profile::set_listener(callback.map(|callback| -> profile::Listener {
+        Box::new(move |progress: Progress<'_>| {
+            let (stage, event, seconds) = match progress {
+                Progress::Started(stage) => (stage, 0, 0.0),
+                Progress::Finished(phase) => (phase.name.as_str(), 1, phase.seconds),
+            };
+            let stage = to_c_string(stage);
+            callback(stage.as_ptr(), event, seconds, user_data.get());
+        })
+    }));
+    0
+}
+
+/// Runs the command serialized as json in `command_json`, as recorded in journals (see
+/// [crate::journal]), e.g. `{"Prove": {...}}`. Unless `output` is null, it is set to the output
+/// of the command, to be released with [ezkl_free_string]. Errors are logged.
+///
+/// Returns 0 when the command succeeds, -1 when `command_json` isn't a command, 1 when the
+/// command fails and 2 when it panics.
+///
+/// # Safety
+///
+/// `command_json` must be a valid nul terminated string, and `output` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ezkl_run(command_json: *const c_char, output: *mut *mut c_char) -> c_int {
+    // unwinding into the host is undefined behavior
+    catch_panic(AssertUnwindSafe(|| run(command_json, output)))
+}
+
+/// Runs `f`, turning a panic into the error code 2 rather than letting it unwind across the C ABI.
+fn catch_panic(f: impl FnOnce() -> c_int + UnwindSafe) -> c_int {
+    std::panic::catch_unwind(f).unwrap_or_else(|panic| {
+        log::error!("panicked: {}", crate::threads::panic_message(&*panic));
+        2
+    })
+}
+
+/// The body of [ezkl_run].
+unsafe fn run(command_json: *const c_char, output: *mut *mut c_char) -> c_int {
+    if command_json.is_null() {
+        return -1;
+    }
+    let command: Commands = match CStr::from_ptr(command_json)
+        .to_str()
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(json).map_err(|e| e.to_string()))
+    {
+        Ok(command) => command,
+        Err(e) => {
+            log::error!("invalid command: {}", e);
+            return -1;
+        }
+    };
+
+    let res = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(crate::execute::run(command)),
+        Err(e) => Err(e.into()),
+    };
+    match res {
+        Ok(res) => {
+            if !output.is_null() {
+                *output = to_c_string(&res).into_raw();
+            }
+            0
+        }
+        Err(e) => {
+            log::error!("failed: {}", e);
+            1
+        }
+    }
+}
+
+/// Releases a string returned by [ezkl_run].
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by [ezkl_run] that wasn't released yet.
+#[no_mangle]
+pub unsafe extern "C" fn ezkl_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    static EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn count_events(stage: *const c_char, _: c_int, _: f64, user_data: *mut c_void) {
+        assert!(user_data.is_null());
+        let stage = unsafe { CStr::from_ptr(stage) };
+        if stage.to_str() == Ok("ffi test") {
+            EVENTS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_progress_callback() {
+        assert_eq!(
+            ezkl_register_progress_callback(Some(count_events), std::ptr::null_mut()),
+            0
+        );
+        profile::start("ffi test");
+        profile::record("ffi test", Duration::from_secs(1));
+        ezkl_register_progress_callback(None, std::ptr::null_mut());
+        profile::start("ffi test");
+        assert_eq!(EVENTS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_run_errors() {
+        assert_eq!(catch_panic(|| panic!("in a command")), 2);
+        assert_eq!(catch_panic(|| 1), 1);
+
+        let invalid = CString::new("{\"NotACommand\": {}}").unwrap();
+        assert_eq!(
+            unsafe { ezkl_run(invalid.as_ptr(), std::ptr::null_mut()) },
+            -1
+        );
+        assert_eq!(
+            unsafe { ezkl_run(std::ptr::null(), std::ptr::null_mut()) },
+            -1
+        );
+    }
+}
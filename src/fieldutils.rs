@@ -1,6 +1,20 @@
+//! Conversions between integers, fixed point numbers and field elements.
+//!
+//! Floats are represented in circuits as fixed point numbers: a float `x` at scale `s` is the
+//! integer `round(x * 2^s)`, rounding halfway cases away from zero, and negative integers are
+//! represented as their additive inverse in the field. Integrations in other languages must
+//! reproduce these conversions exactly for their inputs to match the circuit.
+//!
+//! The functions in this module are a stable API: their signatures and results only change
+//! with a major release. The results are pinned by reference vectors, generated with
+//! `ezkl gen-field-vectors` into `tests/fieldutils_vectors.json`, which implementations in
+//! other languages can be checked against.
+
+use crate::tensor::TensorError;
 use halo2_proofs::arithmetic::Field;
-/// Utilities for converting from Halo2 PrimeField types to integers (and vice-versa).
+use halo2curves::bn256::Fr;
 use halo2curves::ff::PrimeField;
+use serde::{Deserialize, Serialize};
 
 /// Converts an i32 to a PrimeField element.
 pub fn i32_to_felt<F: PrimeField>(x: i32) -> F {
@@ -65,6 +79,99 @@ pub fn felt_to_i128<F: PrimeField + PartialOrd + Field>(x: F) -> i128 {
     }
 }
 
+/// Converts a scale (log base 2) to a fixed point multiplier.
+pub fn scale_to_multiplier(scale: crate::Scale) -> f64 {
+    f64::powf(2., scale as f64)
+}
+
+/// Converts a fixed point multiplier to the nearest scale (log base 2).
+pub fn multiplier_to_scale(mult: f64) -> crate::Scale {
+    mult.log2().round() as crate::Scale
+}
+
+/// Quantizes a float to an integer using a fixed point representation.
+/// Arguments
+///
+/// * `elem` - the float to quantize.
+/// * `shift` - offset used in the fixed point representation.
+/// * `scale` - `2^scale` used in the fixed point representation.
+pub fn quantize_float(elem: &f64, shift: f64, scale: crate::Scale) -> Result<i128, TensorError> {
+    let mult = scale_to_multiplier(scale);
+    let max_value = ((i128::MAX as f64 - shift) / mult).round(); // the maximum value that can be represented w/o sig bit truncation
+
+    if *elem > max_value {
+        return Err(TensorError::SigBitTruncationError);
+    }
+
+    // we parallelize the quantization process as it seems to be quite slow at times
+    let scaled = (mult * *elem + shift).round() as i128;
+
+    Ok(scaled)
+}
+
+/// Dequantizes a field element to a f64 using a fixed point representation.
+/// Arguments
+/// * `felt` - the field element to dequantize.
+/// * `scale` - `2^scale` used in the fixed point representation.
+/// * `shift` - offset used in the fixed point representation.
+pub fn dequantize<F: PrimeField + PartialOrd + Field>(
+    felt: F,
+    scale: crate::Scale,
+    shift: f64,
+) -> f64 {
+    let int_rep = felt_to_i128(felt);
+    let multiplier = scale_to_multiplier(scale);
+    int_rep as f64 / multiplier - shift
+}
+
+/// A float quantized at a scale, with each step of the conversion, to check implementations in
+/// other languages against.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceVector {
+    /// the float
+    pub float: f64,
+    /// the scale it is quantized at
+    pub scale: crate::Scale,
+    /// [scale_to_multiplier] of the scale
+    pub multiplier: f64,
+    /// [quantize_float] of the float, without a shift
+    pub quantized: i128,
+    /// [i128_to_felt] of the quantized integer, as field elements are serialized in witnesses
+    /// (little endian hex)
+    pub felt: Fr,
+    /// the field element as a `0x` prefixed big endian hex string
+    pub felt_big_endian: String,
+    /// [dequantize] of the field element, without a shift
+    pub dequantized: f64,
+}
+
+/// The reference vectors of the fixed point conversions, over floats and scales covering
+/// negative values, halfway cases and negative scales.
+pub fn reference_vectors() -> Result<Vec<ReferenceVector>, TensorError> {
+    let floats = [
+        0.0, 1.0, -1.0, 0.5, -0.5, 0.1, -0.1, 3.3, -2.6, 0.001, 123.456, -1024.75,
+    ];
+    let scales = [-2, 0, 1, 7, 13, 20];
+
+    let mut vectors = vec![];
+    for scale in scales {
+        for float in floats {
+            let quantized = quantize_float(&float, 0.0, scale)?;
+            let felt: Fr = i128_to_felt(quantized);
+            vectors.push(ReferenceVector {
+                float,
+                scale,
+                multiplier: scale_to_multiplier(scale),
+                quantized,
+                felt,
+                felt_big_endian: format!("{:?}", felt),
+                dequantized: dequantize(felt, scale, 0.0),
+            });
+        }
+    }
+    Ok(vectors)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -86,6 +193,13 @@ mod test {
         assert_eq!(res, F::from(131072));
     }
 
+    #[test]
+    fn test_reference_vectors() {
+        let expected: Vec<ReferenceVector> =
+            serde_json::from_str(include_str!("../tests/fieldutils_vectors.json")).unwrap();
+        assert_eq!(reference_vectors().unwrap(), expected);
+    }
+
     #[test]
     fn felttoi32() {
         for x in -(2i32.pow(16))..(2i32.pow(16)) {
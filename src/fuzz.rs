@@ -0,0 +1,241 @@
+//! Randomized testing of a compiled circuit, see `ezkl fuzz`.
+//!
+//! Each iteration samples inputs uniformly from the ranges given for them, integers and booleans
+//! for the inputs of those types, runs the forward pass and the mock prover on them, and records a
+//! [FuzzFailure] when either fails or panics, when lookup inputs fall outside of the tables of the
+//! circuit or when constraints aren't satisfied. Inputs are saved before they run, and kept when
+//! they fail, so that they can be reproduced with `gen-witness` and `mock` even if they crash the
+//! process, and the seed is reported so that the whole run can.
+
+use crate::circuit::InputType;
+use crate::graph::GraphCircuit;
+use crate::tensor::Tensor;
+use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+use halo2curves::bn256::{Bn256, Fr as Fp};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// How an input of a fuzzing run failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FuzzFailureKind {
+    /// the forward pass failed, e.g a range check was violated
+    Forward,
+    /// lookup inputs fell outside of the lookup range or the domain of a table
+    LookupOverflow,
+    /// the mock prover found unsatisfied constraints
+    Constraint,
+    /// loading the input or the witness failed, or the mock prover couldn't run
+    Error,
+    /// the forward pass or the mock prover panicked
+    Panic,
+}
+
+impl std::fmt::Display for FuzzFailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FuzzFailureKind::Forward => "forward pass failed",
+                FuzzFailureKind::LookupOverflow => "lookup overflow",
+                FuzzFailureKind::Constraint => "constraint failure",
+                FuzzFailureKind::Error => "error",
+                FuzzFailureKind::Panic => "panic",
+            }
+        )
+    }
+}
+
+/// An input that failed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FuzzFailure {
+    /// the iteration the input was sampled at
+    pub iteration: usize,
+    /// how the input failed
+    pub kind: FuzzFailureKind,
+    /// why the input failed
+    pub messages: Vec<String>,
+    /// the .json data file the input was saved to
+    pub input_path: PathBuf,
+}
+
+/// The report of `ezkl fuzz`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FuzzReport {
+    /// the seed the inputs were sampled with
+    pub seed: u64,
+    /// the number of inputs sampled
+    pub iterations: usize,
+    /// the inputs that failed, in the order they were sampled
+    pub failures: Vec<FuzzFailure>,
+}
+
+/// The range of each input of the given `types`, a single range being used for every input. The
+/// ranges of integer inputs must hold an integer, booleans are sampled from both values whatever
+/// their range.
+pub fn input_ranges(
+    ranges: &[(f64, f64)],
+    types: &[InputType],
+) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+    if let Some((min, max)) = ranges
+        .iter()
+        .find(|(min, max)| min.is_nan() || max.is_nan() || min > max)
+    {
+        return Err(format!("invalid input range {}->{}", min, max).into());
+    }
+    let ranges = match ranges.len() {
+        1 => vec![ranges[0]; types.len()],
+        n if n == types.len() => ranges.to_vec(),
+        n => {
+            return Err(format!(
+                "the model has {} inputs but {} input ranges were given",
+                types.len(),
+                n
+            )
+            .into())
+        }
+    };
+    for (i, ((min, max), input_type)) in ranges.iter().zip(types).enumerate() {
+        if input_type.is_integer() && *input_type != InputType::Bool && min.ceil() > max.floor() {
+            return Err(format!(
+                "input {} is an integer but its range {}->{} holds none",
+                i, min, max
+            )
+            .into());
+        }
+    }
+    Ok(ranges)
+}
+
+/// Samples inputs of the given `shapes` and `types` uniformly from their `ranges`, see
+/// [input_ranges].
+pub fn sample_inputs(
+    rng: &mut StdRng,
+    shapes: &[Vec<usize>],
+    types: &[InputType],
+    ranges: &[(f64, f64)],
+) -> Vec<Vec<f64>> {
+    shapes
+        .iter()
+        .zip(types)
+        .zip(ranges)
+        .map(|((shape, input_type), (min, max))| {
+            (0..shape.iter().product::<usize>())
+                .map(|_| match input_type {
+                    InputType::Bool => rng.gen_bool(0.5) as u8 as f64,
+                    InputType::Int | InputType::TDim => {
+                        rng.gen_range(min.ceil() as i64..=max.floor() as i64) as f64
+                    }
+                    _ => rng.gen_range(*min..=*max),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Runs the forward pass and the mock prover on `input`, returning how it failed if it did.
+pub fn check_input(
+    circuit: &mut GraphCircuit,
+    input: &mut [Tensor<Fp>],
+) -> Option<(FuzzFailureKind, Vec<String>)> {
+    // only the warnings of this input are checked
+    let _warnings = crate::warnings::scope();
+    let witness = match circuit.forward::<KZGCommitmentScheme<Bn256>>(input, None, None, true) {
+        Ok(witness) => witness,
+        Err(e) => return Some((FuzzFailureKind::Forward, vec![e.to_string()])),
+    };
+    let overflows = witness
+        .warnings
+        .iter()
+        .filter(|w| w.kind == crate::warnings::WarningKind::Clamped)
+        .map(|w| w.message.clone())
+        .collect::<Vec<_>>();
+    if !overflows.is_empty() {
+        return Some((FuzzFailureKind::LookupOverflow, overflows));
+    }
+
+    let prover = circuit
+        .load_graph_witness(&witness)
+        .and_then(|_| circuit.prepare_public_inputs(&witness))
+        .and_then(|public_inputs| {
+            Ok(halo2_proofs::dev::MockProver::run(
+                circuit.settings().run_args.logrows,
+                &*circuit,
+                vec![public_inputs],
+            )?)
+        });
+    match prover {
+        Err(e) => Some((FuzzFailureKind::Error, vec![e.to_string()])),
+        Ok(prover) => prover.verify().err().map(|errors| {
+            (
+                FuzzFailureKind::Constraint,
+                errors.iter().map(|e| e.to_string()).collect(),
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::input::GraphData;
+    use crate::graph::templates::{Template, TemplateTask};
+    use crate::graph::DataSource;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sample_inputs() {
+        let floats = vec![InputType::F32; 3];
+        assert_eq!(input_ranges(&[(-1.0, 1.0)], &floats[..2]).unwrap().len(), 2);
+        assert!(input_ranges(&[(-1.0, 1.0), (0.0, 1.0)], &floats).is_err());
+        assert!(input_ranges(&[(1.0, -1.0)], &floats[..1]).is_err());
+        assert!(input_ranges(&[(0.2, 0.8)], &[InputType::Int]).is_err());
+        assert!(input_ranges(&[(0.2, 0.8)], &[InputType::Bool]).is_ok());
+
+        let shapes = vec![vec![2, 3], vec![4]];
+        let types = [InputType::F32, InputType::F32];
+        let ranges = input_ranges(&[(-1.0, 1.0), (5.0, 5.0)], &types).unwrap();
+        let inputs = sample_inputs(&mut StdRng::seed_from_u64(0), &shapes, &types, &ranges);
+        assert_eq!(inputs[0].len(), 6);
+        assert!(inputs[0].iter().all(|x| (-1.0..=1.0).contains(x)));
+        assert_eq!(inputs[1], vec![5.0; 4]);
+        assert_eq!(
+            inputs,
+            sample_inputs(&mut StdRng::seed_from_u64(0), &shapes, &types, &ranges)
+        );
+
+        // integers and booleans are sampled as such
+        let types = [InputType::Int, InputType::Bool];
+        let ranges = input_ranges(&[(-3.5, 3.5), (0.0, 1.0)], &types).unwrap();
+        let inputs = sample_inputs(&mut StdRng::seed_from_u64(0), &shapes, &types, &ranges);
+        assert!(inputs[0].iter().all(|x| x.fract() == 0.0 && x.abs() <= 3.0));
+        assert!(inputs[1].iter().all(|x| *x == 0.0 || *x == 1.0));
+    }
+
+    #[test]
+    fn test_check_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("network.onnx");
+        let template = Template::new(TemplateTask::LinearRegression, 3, 4, 0).unwrap();
+        template
+            .save(&model_path, &dir.path().join("input.json"))
+            .unwrap();
+        let mut circuit = GraphCircuit::from_run_args(&template.run_args, &model_path).unwrap();
+
+        let shapes = circuit.model().graph.input_shapes().unwrap();
+        let types = circuit.model().graph.get_input_types().unwrap();
+        let ranges = input_ranges(&[(-1.0, 1.0)], &types).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..3 {
+            let data = GraphData::new(DataSource::from(sample_inputs(
+                &mut rng, &shapes, &types, &ranges,
+            )));
+            let mut input = circuit.load_graph_from_file_exclusively(&data).unwrap();
+            assert_eq!(check_input(&mut circuit, &mut input), None);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Self-contained witness assignments, to generate the witness and the proof on separate machines.
+//!
+//! The data owner runs `gen-witness --full-assignment` next to their data and ships the resulting
+//! assignment to the prover, which proves it with `prove --from-assignment`. The assignment pins
+//! the circuit it was generated for and its public instances, so the prover can check it was
+//! handed the right one without ever seeing the input data.
+
+use super::{GraphSettings, GraphWitness};
+use crate::pfsys::key_header::KeyParams;
+use crate::EZKL_BUF_CAPACITY;
+use halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Version of the assignment format, bumped when fields are removed or change meaning.
+pub const ASSIGNMENT_VERSION: u32 = 1;
+
+/// A witness along with everything needed to prove it, bar the compiled circuit and keys.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Assignment {
+    /// The version of the assignment format
+    pub version: u32,
+    /// The ezkl version the assignment was generated with
+    pub ezkl_version: String,
+    /// The logrows of the circuit the assignment was generated for
+    pub logrows: u32,
+    /// The hash of the settings of the circuit, see [KeyParams]
+    pub settings_hash: Option<String>,
+    /// The public instances of the proof
+    pub instances: Vec<Fp>,
+    /// The witness, with every module result the circuit needs
+    pub witness: GraphWitness,
+}
+
+impl Assignment {
+    /// The assignment of `witness` to the circuit with the given settings and public instances.
+    pub fn new(settings: &GraphSettings, witness: GraphWitness, instances: Vec<Fp>) -> Self {
+        Self {
+            version: ASSIGNMENT_VERSION,
+            ezkl_version: env!("CARGO_PKG_VERSION").to_string(),
+            logrows: settings.run_args.logrows,
+            settings_hash: settings.settings_hash(),
+            instances,
+            witness,
+        }
+    }
+
+    /// Checks the assignment can be proven by the circuit with the given settings.
+    pub fn check(&self, settings: &GraphSettings) -> Result<(), Box<dyn Error>> {
+        if self.version > ASSIGNMENT_VERSION {
+            return Err(format!(
+                "the assignment was written by a newer ezkl ({}), upgrade ezkl to prove it",
+                self.ezkl_version
+            )
+            .into());
+        }
+        if self.logrows != settings.run_args.logrows {
+            return Err(format!(
+                "the assignment was generated for logrows {} but the circuit has logrows {}",
+                self.logrows, settings.run_args.logrows
+            )
+            .into());
+        }
+        if let (Some(expected), Some(found)) = (settings.settings_hash(), &self.settings_hash) {
+            if &expected != found {
+                return Err("the assignment was generated for a different circuit".into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the assignment from a .json file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open assignment {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the assignment to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer(writer, &self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignment_check() {
+        let settings = GraphSettings::default();
+        let witness = GraphWitness::new(vec![vec![Fp::from(1)]], vec![vec![Fp::from(2)]]);
+        let assignment = Assignment::new(&settings, witness, vec![Fp::from(2)]);
+        assert!(assignment.check(&settings).is_ok());
+
+        let mut other = settings.clone();
+        other.run_args.logrows += 1;
+        assert!(assignment.check(&other).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("assignment.json");
+        assignment.save(&path).unwrap();
+        let loaded = Assignment::load(&path).unwrap();
+        assert_eq!(loaded.instances, assignment.instances);
+        assert_eq!(loaded.witness.inputs, assignment.witness.inputs);
+    }
+}
@@ -0,0 +1,196 @@
+use super::input::GraphData;
+use super::model::{Model, NodeType};
+use super::node::SupportedOp;
+use super::{scale_to_multiplier, GraphCircuit};
+use crate::circuit::poly::PolyOp;
+use crate::RunArgs;
+use log::{debug, warn};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// A bias constant and the node that adds it to the output of a linear layer.
+#[derive(Clone, Debug)]
+struct BiasTarget {
+    /// The constant node holding the bias
+    bias: usize,
+    /// The node whose output the bias is added to
+    consumer: usize,
+    /// The dims of the bias, padded so that they broadcast against the consumer's output from the
+    /// right
+    dims: Vec<usize>,
+}
+
+/// Looks through rescaling wrappers to the underlying op.
+fn inner_op(op: &SupportedOp) -> &SupportedOp {
+    match op {
+        SupportedOp::RebaseScale(r) => inner_op(&r.inner),
+        SupportedOp::Rescaled(r) => inner_op(&r.inner),
+        op => op,
+    }
+}
+
+/// Finds the biases of convolutions and of dense layers (an einsum followed by the addition of a
+/// constant). Constants shared with other nodes are left alone, as correcting them for one layer
+/// would skew the others.
+fn bias_targets(model: &Model) -> Vec<BiasTarget> {
+    let nodes = &model.graph.nodes;
+    let is_constant = |idx: usize| nodes.get(&idx).map_or(false, |n| n.is_constant());
+    let is_einsum = |idx: usize| match nodes.get(&idx) {
+        Some(NodeType::Node(n)) => matches!(
+            inner_op(&n.opkind),
+            SupportedOp::Linear(PolyOp::Einsum { .. })
+        ),
+        _ => false,
+    };
+
+    let mut targets = vec![];
+    for (idx, node) in nodes {
+        let NodeType::Node(n) = node else {
+            continue;
+        };
+        let (bias, channel_axis) = match inner_op(&n.opkind) {
+            SupportedOp::Linear(PolyOp::Conv { .. })
+                if n.inputs.len() == 3 && is_constant(n.inputs[2].0) =>
+            {
+                (n.inputs[2].0, Some(1))
+            }
+            SupportedOp::Linear(PolyOp::Add) if n.inputs.len() == 2 => {
+                let (a, b) = (n.inputs[0].0, n.inputs[1].0);
+                if is_einsum(a) && is_constant(b) {
+                    (b, None)
+                } else if is_constant(a) && is_einsum(b) {
+                    (a, None)
+                } else {
+                    continue;
+                }
+            }
+            _ => continue,
+        };
+
+        let Some(NodeType::Node(bias_node)) = nodes.get(&bias) else {
+            continue;
+        };
+        if bias_node.num_uses != 1 {
+            debug!("skipping bias correction of shared constant {}", bias);
+            continue;
+        }
+        let mut dims = bias_node.out_dims.clone();
+        if let Some(axis) = channel_axis {
+            // conv biases are per output channel
+            dims.extend(vec![1; n.out_dims.len().saturating_sub(axis + 1)]);
+        }
+        targets.push(BiasTarget {
+            bias,
+            consumer: *idx,
+            dims,
+        });
+    }
+    targets
+}
+
+/// Maps a flat index into the consumer's output to the bias element that was added to it.
+fn bias_index(out_dims: &[usize], bias_dims: &[usize], flat: usize) -> usize {
+    let mut rem = flat;
+    let mut idx = 0;
+    let mut stride = 1;
+    for (from_right, dim) in out_dims.iter().rev().enumerate() {
+        let coord = rem % dim;
+        rem /= dim;
+        if from_right < bias_dims.len() {
+            let bias_dim = bias_dims[bias_dims.len() - 1 - from_right];
+            if bias_dim > 1 {
+                idx += coord * stride;
+                stride *= bias_dim;
+            }
+        }
+    }
+    idx
+}
+
+/// Runs the float and quantized models over the calibration data and, for every bias, returns the
+/// mean error (float - dequantized) of the outputs it is added to. Adding these to the biases
+/// removes the systematic shift quantization introduces in each layer's outputs.
+pub fn find_bias_corrections(
+    run_args: &RunArgs,
+    model_path: &std::path::Path,
+    chunks: &[GraphData],
+) -> Result<BTreeMap<usize, Vec<f32>>, Box<dyn Error>> {
+    let mut circuit = GraphCircuit::from_run_args(run_args, model_path)?;
+    let targets = bias_targets(circuit.model());
+    if targets.is_empty() {
+        warn!("bias correction found no biases to correct");
+        return Ok(BTreeMap::new());
+    }
+    let consumers: Vec<usize> = targets.iter().map(|t| t.consumer).collect();
+
+    let float_outputs = Model::run_onnx_node_predictions(
+        run_args,
+        model_path,
+        chunks,
+        circuit.model().graph.input_shapes()?,
+        Some(&consumers),
+    )?;
+
+    // running sums of the errors and the number of elements behind each bias element
+    let mut stats: Vec<(Vec<f64>, Vec<usize>)> = targets
+        .iter()
+        .map(|t| {
+            let len = t.dims.iter().product();
+            (vec![0.0; len], vec![0; len])
+        })
+        .collect();
+
+    for (chunk, float_chunk) in chunks.iter().zip(float_outputs) {
+        let inputs = circuit.load_graph_from_file_exclusively(chunk)?;
+        let quantized = circuit
+            .model()
+            .forward_nodes(&inputs, run_args, &consumers)?;
+
+        for ((target, (sums, counts)), (float, quant)) in targets
+            .iter()
+            .zip(stats.iter_mut())
+            .zip(float_chunk.iter().zip(quantized.iter()))
+        {
+            if float.len() != quant.len() {
+                continue;
+            }
+            let scale = circuit.model().graph.nodes[&target.consumer].out_scales()[0];
+            let multiplier = scale_to_multiplier(scale);
+            for (i, (f, q)) in float.iter().zip(quant.iter()).enumerate() {
+                let j = bias_index(quant.dims(), &target.dims, i);
+                sums[j] += *f as f64 - *q as f64 / multiplier;
+                counts[j] += 1;
+            }
+        }
+    }
+
+    Ok(targets
+        .iter()
+        .zip(stats)
+        .filter(|(_, (_, counts))| counts.iter().all(|c| *c > 0))
+        .map(|(target, (sums, counts))| {
+            let deltas = sums
+                .iter()
+                .zip(counts)
+                .map(|(s, c)| (s / c as f64) as f32)
+                .collect();
+            (target.bias, deltas)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bias_index() {
+        // conv output [N, C, H, W] with a per channel bias
+        let out_dims = [2, 3, 2, 2];
+        let flat = 12 + 2 * 4 + 2;
+        assert_eq!(bias_index(&out_dims, &[3, 1, 1], flat), 2);
+        // dense output [N, F] with a bias broadcast over the batch
+        assert_eq!(bias_index(&[2, 3], &[3], 4), 1);
+        assert_eq!(bias_index(&[2, 3], &[1, 3], 5), 2);
+    }
+}
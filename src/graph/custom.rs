@@ -0,0 +1,498 @@
+use super::GraphError;
+use crate::circuit::hybrid::HybridOp;
+use crate::circuit::region::RegionCtx;
+use crate::circuit::{BaseConfig, ForwardResult, Op};
+use crate::tensor::{Tensor, TensorError, ValTensor};
+use halo2curves::bn256::Fr as Fp;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// The value of an attribute of a custom onnx node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CustomAttribute {
+    ///
+    Int(i64),
+    ///
+    Float(f32),
+    ///
+    Ints(Vec<i64>),
+    ///
+    Floats(Vec<f32>),
+    ///
+    String(String),
+}
+
+/// The attributes of a custom onnx node, keyed by name.
+pub type CustomAttributes = BTreeMap<String, CustomAttribute>;
+
+/// Supplies the implementation of an onnx op ezkl doesn't know about, e.g an op from a custom
+/// domain, so downstream crates can map it to their own constraints.
+pub trait GraphOpFactory: Send + Sync {
+    /// The output shape of the op for the given input shapes.
+    fn output_shape(
+        &self,
+        input_shapes: &[Vec<usize>],
+        attributes: &CustomAttributes,
+    ) -> Result<Vec<usize>, Box<dyn Error>>;
+
+    /// Builds the circuit op, which provides the quantized forward pass, the layout and the output scale.
+    fn build(&self, attributes: &CustomAttributes) -> Result<Box<dyn Op<Fp>>, Box<dyn Error>>;
+
+    /// Floating point forward pass, used when running the onnx model itself (e.g during calibration).
+    fn eval_float(
+        &self,
+        _inputs: &[Tensor<f32>],
+        _attributes: &CustomAttributes,
+    ) -> Result<Tensor<f32>, Box<dyn Error>> {
+        Err("floating point evaluation is not supported by this custom op".into())
+    }
+}
+
+type Registry = RwLock<HashMap<String, Arc<dyn GraphOpFactory>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut builtins: HashMap<String, Arc<dyn GraphOpFactory>> = HashMap::new();
+        builtins.insert(SAMPLE_TOPK_OP.to_string(), Arc::new(SampleTopKFactory));
+        RwLock::new(builtins)
+    })
+}
+
+/// The op type of the onnx node proving a token was sampled from the top k logits, see [SampleTopKFactory].
+pub const SAMPLE_TOPK_OP: &str = "SampleTopK";
+
+/// The default number of bits of the randomness a [SAMPLE_TOPK_OP] node samples under.
+pub const DEFAULT_RANDOMNESS_BITS: u32 = 16;
+
+/// Built in factory for [SAMPLE_TOPK_OP] nodes, which take the logits of a decoder and a
+/// randomness value and output the index of the `(randomness mod k)`-th largest logit. Making the
+/// randomness input hashed or public commits to it, so the proof attests that the emitted token
+/// was sampled from the top k candidates rather than picked freely.
+/// Attributes are `k` and optionally `randomness_bits` (defaults to [DEFAULT_RANDOMNESS_BITS]).
+/// The randomness is used as quantized, so it should be an integer input at scale 0.
+pub struct SampleTopKFactory;
+
+impl SampleTopKFactory {
+    fn params(attributes: &CustomAttributes) -> Result<(usize, u32), Box<dyn Error>> {
+        let k = match attributes.get("k") {
+            Some(CustomAttribute::Int(k)) if *k > 0 => *k as usize,
+            _ => return Err(format!("{} requires a positive k attribute", SAMPLE_TOPK_OP).into()),
+        };
+        let randomness_bits = match attributes.get("randomness_bits") {
+            Some(CustomAttribute::Int(bits)) if *bits > 0 && *bits < 64 => *bits as u32,
+            None => DEFAULT_RANDOMNESS_BITS,
+            _ => return Err(format!("invalid randomness_bits for {}", SAMPLE_TOPK_OP).into()),
+        };
+        Ok((k, randomness_bits))
+    }
+}
+
+impl GraphOpFactory for SampleTopKFactory {
+    fn output_shape(
+        &self,
+        input_shapes: &[Vec<usize>],
+        attributes: &CustomAttributes,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let (k, _) = Self::params(attributes)?;
+        if input_shapes.len() != 2 {
+            return Err(format!("{} takes logits and randomness", SAMPLE_TOPK_OP).into());
+        }
+        let num_logits = input_shapes[0].iter().product::<usize>();
+        if k > num_logits {
+            return Err(
+                format!("cannot sample from the top {} of {} logits", k, num_logits).into(),
+            );
+        }
+        Ok(vec![1])
+    }
+
+    fn build(&self, attributes: &CustomAttributes) -> Result<Box<dyn Op<Fp>>, Box<dyn Error>> {
+        let (k, randomness_bits) = Self::params(attributes)?;
+        Ok(Box::new(HybridOp::SampleTopK { k, randomness_bits }))
+    }
+
+    fn eval_float(
+        &self,
+        inputs: &[Tensor<f32>],
+        attributes: &CustomAttributes,
+    ) -> Result<Tensor<f32>, Box<dyn Error>> {
+        let (k, _) = Self::params(attributes)?;
+        let mut candidates = inputs[0].iter().cloned().collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        let randomness = inputs[1][0].round() as i128;
+        let selected = candidates[randomness.rem_euclid(k as i128) as usize];
+        let token = inputs[0]
+            .iter()
+            .position(|x| *x == selected)
+            .ok_or("sampled logit not found")?;
+        Ok(Tensor::new(Some(&[token as f32]), &[1])?)
+    }
+}
+
+/// Registers `factory` for onnx nodes whose op type is `name`, replacing any previous registration.
+pub fn register_custom_op(name: &str, factory: Box<dyn GraphOpFactory>) {
+    if let Ok(mut registry) = registry().write() {
+        registry.insert(name.to_string(), Arc::from(factory));
+    }
+}
+
+/// Returns the factory registered for `name`.
+pub fn get_custom_op(name: &str) -> Option<Arc<dyn GraphOpFactory>> {
+    registry().read().ok()?.get(name).cloned()
+}
+
+/// Names of all registered custom ops.
+pub fn registered_custom_ops() -> Vec<String> {
+    registry()
+        .read()
+        .map(|r| r.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// A node backed by a registered [GraphOpFactory]. Only the op type and attributes are
+/// serialized, the op itself is rebuilt from the registry, so custom ops need to be registered
+/// before a compiled circuit that uses them is loaded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomOp {
+    /// The onnx op type the factory is registered under
+    pub name: String,
+    /// The attributes of the onnx node
+    pub attributes: CustomAttributes,
+    #[serde(skip)]
+    inner: OnceLock<Box<dyn Op<Fp>>>,
+}
+
+impl CustomOp {
+    ///
+    pub fn new(name: String, attributes: CustomAttributes) -> Self {
+        Self {
+            name,
+            attributes,
+            inner: OnceLock::new(),
+        }
+    }
+
+    fn op(&self) -> Result<&dyn Op<Fp>, Box<dyn Error>> {
+        if let Some(op) = self.inner.get() {
+            return Ok(op.as_ref());
+        }
+        let factory =
+            get_custom_op(&self.name).ok_or(GraphError::MissingCustomOp(self.name.clone()))?;
+        let op = factory.build(&self.attributes)?;
+        Ok(self.inner.get_or_init(|| op).as_ref())
+    }
+}
+
+impl Op<Fp> for CustomOp {
+    fn f(&self, x: &[Tensor<Fp>]) -> Result<ForwardResult<Fp>, TensorError> {
+        self.op()
+            .map_err(|e| {
+                log::error!("{}", e);
+                TensorError::WrongMethod
+            })?
+            .f(x)
+    }
+
+    fn as_string(&self) -> String {
+        format!("CUSTOM({})", self.name)
+    }
+
+    fn layout(
+        &self,
+        config: &mut BaseConfig<Fp>,
+        region: &mut RegionCtx<Fp>,
+        values: &[ValTensor<Fp>],
+    ) -> Result<Option<ValTensor<Fp>>, Box<dyn Error>> {
+        self.op()?.layout(config, region, values)
+    }
+
+    fn out_scale(&self, in_scales: Vec<crate::Scale>) -> Result<crate::Scale, Box<dyn Error>> {
+        self.op()?.out_scale(in_scales)
+    }
+
+    fn requires_homogenous_input_scales(&self) -> Vec<usize> {
+        self.op()
+            .map(|op| op.requires_homogenous_input_scales())
+            .unwrap_or_default()
+    }
+
+    fn clone_dyn(&self) -> Box<dyn Op<Fp>> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Registers the custom ops with tract's onnx parser, so they survive loading as [tract::CustomTypedOp] nodes.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub(crate) fn register_tract_parsers(onnx: &mut tract_onnx::Onnx) {
+    for name in registered_custom_ops() {
+        onnx.op_register.0.insert(name, tract::parse_custom_op);
+    }
+}
+
+/// Tract ops standing in for custom nodes between parsing and [super::utilities::new_op_from_onnx].
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub(crate) mod tract {
+    use super::{get_custom_op, CustomAttribute, CustomAttributes, GraphOpFactory};
+    use std::sync::Arc;
+    use tract_onnx::model::ParsingContext;
+    use tract_onnx::pb::attribute_proto::AttributeType;
+    use tract_onnx::pb::NodeProto;
+    use tract_onnx::tract_hir::internal::*;
+    use tract_onnx::tract_hir::ops::expandable::{expand, Expansion};
+
+    fn factory(name: &str) -> TractResult<Arc<dyn GraphOpFactory>> {
+        get_custom_op(name).ok_or_else(|| format_err!("custom op {} is not registered", name))
+    }
+
+    fn attributes_from_proto(node: &NodeProto) -> CustomAttributes {
+        node.attribute
+            .iter()
+            .filter_map(|attr| {
+                let value = match AttributeType::try_from(attr.r#type).ok()? {
+                    AttributeType::Int => CustomAttribute::Int(attr.i),
+                    AttributeType::Float => CustomAttribute::Float(attr.f),
+                    AttributeType::Ints => CustomAttribute::Ints(attr.ints.clone()),
+                    AttributeType::Floats => CustomAttribute::Floats(attr.floats.clone()),
+                    AttributeType::String => {
+                        CustomAttribute::String(String::from_utf8_lossy(&attr.s).to_string())
+                    }
+                    _ => return None,
+                };
+                Some((attr.name.clone(), value))
+            })
+            .collect()
+    }
+
+    pub(crate) fn parse_custom_op(
+        _ctx: &ParsingContext,
+        node: &NodeProto,
+    ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+        Ok((
+            expand(CustomExpansion {
+                name: node.op_type.clone(),
+                attributes: attributes_from_proto(node),
+            }),
+            vec![],
+        ))
+    }
+
+    fn hash_custom(name: &str, attributes: &CustomAttributes, state: &mut dyn std::hash::Hasher) {
+        state.write(name.as_bytes());
+        state.write(format!("{:?}", attributes).as_bytes());
+    }
+
+    #[derive(Debug, Clone)]
+    struct CustomExpansion {
+        name: String,
+        attributes: CustomAttributes,
+    }
+
+    impl std::hash::Hash for CustomExpansion {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            hash_custom(&self.name, &self.attributes, state)
+        }
+    }
+
+    impl Expansion for CustomExpansion {
+        fn name(&self) -> Cow<str> {
+            self.name.clone().into()
+        }
+
+        fn rules<'r, 'p: 'r, 's: 'r>(
+            &'s self,
+            s: &mut Solver<'r>,
+            inputs: &'p [TensorProxy],
+            outputs: &'p [TensorProxy],
+        ) -> InferenceResult {
+            check_output_arity(outputs, 1)?;
+            let factory = factory(&self.name)?;
+            s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+            s.given_all(inputs.iter().map(|i| &i.shape), move |s, shapes| {
+                let shapes = shapes
+                    .iter()
+                    .map(|shape| shape.iter().map(|d| d.to_usize()).collect())
+                    .collect::<TractResult<Vec<Vec<usize>>>>()?;
+                let output_shape = factory
+                    .output_shape(&shapes, &self.attributes)
+                    .map_err(|e| format_err!("{}", e))?;
+                s.equals(
+                    &outputs[0].shape,
+                    ShapeFactoid::from(
+                        output_shape
+                            .into_iter()
+                            .map(TDim::from)
+                            .collect::<TVec<_>>(),
+                    ),
+                )
+            })
+        }
+
+        fn wire(
+            &self,
+            prefix: &str,
+            model: &mut TypedModel,
+            inputs: &[OutletId],
+        ) -> TractResult<TVec<OutletId>> {
+            let facts = inputs
+                .iter()
+                .map(|i| model.outlet_fact(*i).cloned())
+                .collect::<TractResult<Vec<_>>>()?;
+            let shapes = facts
+                .iter()
+                .map(|f| {
+                    f.shape.as_concrete().map(|s| s.to_vec()).ok_or_else(|| {
+                        format_err!("custom op {} needs concrete input shapes", self.name)
+                    })
+                })
+                .collect::<TractResult<Vec<_>>>()?;
+            let output_shape = factory(&self.name)?
+                .output_shape(&shapes, &self.attributes)
+                .map_err(|e| format_err!("{}", e))?;
+
+            let op = CustomTypedOp {
+                name: self.name.clone(),
+                attributes: self.attributes.clone(),
+                output_fact: TypedFact::dt_shape(facts[0].datum_type, output_shape),
+            };
+            model.wire_node(prefix, op, inputs)
+        }
+    }
+
+    /// A custom node in a typed tract model.
+    #[derive(Debug, Clone)]
+    pub(crate) struct CustomTypedOp {
+        pub(crate) name: String,
+        pub(crate) attributes: CustomAttributes,
+        output_fact: TypedFact,
+    }
+
+    impl std::hash::Hash for CustomTypedOp {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            hash_custom(&self.name, &self.attributes, state)
+        }
+    }
+
+    impl Op for CustomTypedOp {
+        fn name(&self) -> Cow<str> {
+            self.name.clone().into()
+        }
+
+        op_as_typed_op!();
+    }
+
+    impl EvalOp for CustomTypedOp {
+        fn is_stateless(&self) -> bool {
+            true
+        }
+
+        fn eval(&self, inputs: TVec<TValue>) -> TractResult<TVec<TValue>> {
+            let inputs = inputs
+                .iter()
+                .map(|t| {
+                    let t = t.cast_to::<f32>()?;
+                    Ok(crate::tensor::Tensor::new(
+                        Some(t.as_slice::<f32>()?),
+                        t.shape(),
+                    )?)
+                })
+                .collect::<TractResult<Vec<_>>>()?;
+            let output = factory(&self.name)?
+                .eval_float(&inputs, &self.attributes)
+                .map_err(|e| format_err!("{}", e))?;
+            let output = Tensor::from_shape(output.dims(), &output)?
+                .cast_to_dt(self.output_fact.datum_type)?
+                .into_owned();
+            Ok(tvec!(output.into_tvalue()))
+        }
+    }
+
+    impl TypedOp for CustomTypedOp {
+        fn output_facts(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+            Ok(tvec!(self.output_fact.clone()))
+        }
+
+        as_op!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::poly::PolyOp;
+    use crate::fieldutils::i128_to_felt;
+
+    struct NegFactory;
+
+    impl GraphOpFactory for NegFactory {
+        fn output_shape(
+            &self,
+            input_shapes: &[Vec<usize>],
+            _attributes: &CustomAttributes,
+        ) -> Result<Vec<usize>, Box<dyn Error>> {
+            Ok(input_shapes[0].clone())
+        }
+
+        fn build(&self, _attributes: &CustomAttributes) -> Result<Box<dyn Op<Fp>>, Box<dyn Error>> {
+            Ok(Box::new(PolyOp::Neg))
+        }
+    }
+
+    #[test]
+    fn test_custom_op_registry() {
+        let unknown = CustomOp::new("TestUnregistered".to_string(), CustomAttributes::new());
+        assert!(unknown.out_scale(vec![7]).is_err());
+
+        register_custom_op("TestNeg", Box::new(NegFactory));
+        assert!(registered_custom_ops().contains(&"TestNeg".to_string()));
+        assert!(registered_custom_ops().contains(&SAMPLE_TOPK_OP.to_string()));
+
+        let op = CustomOp::new("TestNeg".to_string(), CustomAttributes::new());
+        let input = Tensor::new(Some(&[1, -2, 3]), &[3])
+            .unwrap()
+            .map(i128_to_felt::<Fp>);
+        let expected = Tensor::new(Some(&[-1, 2, -3]), &[3])
+            .unwrap()
+            .map(i128_to_felt::<Fp>);
+        assert_eq!(op.f(&[input.clone()]).unwrap().output, expected);
+        assert_eq!(op.out_scale(vec![7]).unwrap(), 7);
+
+        // only the name and attributes are serialized, the op is rebuilt from the registry
+        let restored: CustomOp =
+            serde_json::from_str(&serde_json::to_string(&op).unwrap()).unwrap();
+        assert_eq!(restored.f(&[input]).unwrap().output, expected);
+    }
+
+    #[test]
+    fn test_sample_topk_factory() {
+        let factory = get_custom_op(SAMPLE_TOPK_OP).unwrap();
+        let mut attributes = CustomAttributes::new();
+        assert!(factory
+            .output_shape(&[vec![5], vec![1]], &attributes)
+            .is_err());
+        attributes.insert("k".to_string(), CustomAttribute::Int(3));
+        assert_eq!(
+            factory
+                .output_shape(&[vec![5], vec![1]], &attributes)
+                .unwrap(),
+            vec![1]
+        );
+        assert!(factory
+            .output_shape(&[vec![2], vec![1]], &attributes)
+            .is_err());
+
+        let logits = Tensor::new(Some(&[0.1, 0.9, 0.3, 0.7, 0.5]), &[5]).unwrap();
+        // randomness 4 picks the second largest of the top 3 logits, at index 3
+        let randomness = Tensor::new(Some(&[4.0]), &[1]).unwrap();
+        let token = factory
+            .eval_float(&[logits, randomness], &attributes)
+            .unwrap();
+        assert_eq!(token[0], 3.0);
+    }
+}
@@ -0,0 +1,285 @@
+use super::input::{FileSourceInner, GraphData};
+use super::GraphError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Normalization applied to a column before it is fed to the model. This needs to match the
+/// preprocessing the model was trained with.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Normalization {
+    /// Values are used as is
+    #[default]
+    None,
+    /// `(x - mean) / std`
+    ZScore {
+        ///
+        mean: f64,
+        ///
+        std: f64,
+    },
+    /// `(x - min) / (max - min)`
+    MinMax {
+        ///
+        min: f64,
+        ///
+        max: f64,
+    },
+}
+
+impl Normalization {
+    /// Applies the normalization to a single value.
+    pub fn apply(&self, x: f64) -> f64 {
+        match self {
+            Normalization::None => x,
+            Normalization::ZScore { mean, std } => (x - mean) / std,
+            Normalization::MinMax { min, max } => (x - min) / (max - min),
+        }
+    }
+}
+
+/// A column of the dataset and the normalization applied to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    /// The column name, as it appears in the csv header or the parquet schema
+    pub name: String,
+    ///
+    #[serde(default)]
+    pub normalization: Normalization,
+}
+
+/// Maps the columns of a tabular dataset to the inputs of a model. Each row of the dataset is one
+/// sample, and input `i` of a sample is made of the columns in `inputs[i]`, in order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatasetSpec {
+    /// The columns of each model input
+    pub inputs: Vec<Vec<ColumnSpec>>,
+}
+
+impl DatasetSpec {
+    /// Loads a spec from a .json file.
+    pub fn from_path(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open dataset spec at {}: {}", path.display(), e))?;
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Builds the model inputs from a table of named columns, with the samples concatenated
+    /// along each input (as expected by [GraphData::split_into_batches]).
+    pub fn to_graph_data(
+        &self,
+        columns: &HashMap<String, Vec<f64>>,
+    ) -> Result<GraphData, GraphError> {
+        let num_rows = columns.values().map(|c| c.len()).next().unwrap_or(0);
+
+        let mut input_data = vec![vec![]; self.inputs.len()];
+        for row in 0..num_rows {
+            for (input, specs) in input_data.iter_mut().zip(&self.inputs) {
+                for spec in specs {
+                    let column = columns.get(&spec.name).ok_or(GraphError::Dataset(format!(
+                        "column {} is missing from the dataset",
+                        spec.name
+                    )))?;
+                    input.push(FileSourceInner::Float(
+                        spec.normalization.apply(column[row]),
+                    ));
+                }
+            }
+        }
+
+        Ok(GraphData::new(input_data.into()))
+    }
+}
+
+/// Parses csv data with a header row into named columns. Only the columns in `keep` are parsed,
+/// and they must all be numeric (booleans map to 0 / 1).
+pub fn parse_csv(data: &str, keep: &[&str]) -> Result<HashMap<String, Vec<f64>>, GraphError> {
+    let mut lines = data.lines().filter(|l| !l.trim().is_empty());
+    let header = lines
+        .next()
+        .ok_or(GraphError::Dataset("the csv file is empty".to_string()))?;
+    let header = split_csv_line(header);
+
+    let indices = keep
+        .iter()
+        .map(|name| {
+            header
+                .iter()
+                .position(|h| h == name)
+                .map(|idx| (name.to_string(), idx))
+                .ok_or(GraphError::Dataset(format!(
+                    "column {} is missing from the csv header",
+                    name
+                )))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut columns: HashMap<String, Vec<f64>> = HashMap::new();
+    for (row, line) in lines.enumerate() {
+        let fields = split_csv_line(line);
+        if fields.len() != header.len() {
+            return Err(GraphError::Dataset(format!(
+                "row {} has {} fields, expected {}",
+                row + 1,
+                fields.len(),
+                header.len()
+            )));
+        }
+        for (name, idx) in &indices {
+            let value = parse_value(&fields[*idx]).ok_or(GraphError::Dataset(format!(
+                "row {} has a non numeric value for column {}: {}",
+                row + 1,
+                name,
+                fields[*idx]
+            )))?;
+            columns.entry(name.clone()).or_default().push(value);
+        }
+    }
+    Ok(columns)
+}
+
+/// Splits a csv line on commas, honouring double quoted fields (with `""` as an escaped quote).
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn parse_value(field: &str) -> Option<f64> {
+    match field.to_lowercase().as_str() {
+        "true" => Some(1.0),
+        "false" => Some(0.0),
+        f => f.parse().ok(),
+    }
+}
+
+/// Reads the named numeric columns of a parquet file.
+#[cfg(feature = "parquet")]
+fn read_parquet(path: &Path, keep: &[&str]) -> Result<HashMap<String, Vec<f64>>, GraphError> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    let err = |e: parquet::errors::ParquetError| GraphError::Dataset(e.to_string());
+    let file = std::fs::File::open(path).map_err(|e| GraphError::Dataset(e.to_string()))?;
+    let reader = SerializedFileReader::new(file).map_err(err)?;
+
+    let mut columns: HashMap<String, Vec<f64>> = HashMap::new();
+    for (row_idx, row) in reader.get_row_iter(None).map_err(err)?.enumerate() {
+        let row = row.map_err(err)?;
+        for (name, field) in row.get_column_iter() {
+            if !keep.contains(&name.as_str()) {
+                continue;
+            }
+            let value = match field {
+                Field::Bool(b) => *b as u8 as f64,
+                Field::Byte(v) => *v as f64,
+                Field::Short(v) => *v as f64,
+                Field::Int(v) => *v as f64,
+                Field::Long(v) => *v as f64,
+                Field::UByte(v) => *v as f64,
+                Field::UShort(v) => *v as f64,
+                Field::UInt(v) => *v as f64,
+                Field::ULong(v) => *v as f64,
+                Field::Float(v) => *v as f64,
+                Field::Double(v) => *v,
+                f => {
+                    return Err(GraphError::Dataset(format!(
+                        "row {} has a non numeric value for column {}: {}",
+                        row_idx + 1,
+                        name,
+                        f
+                    )))
+                }
+            };
+            columns.entry(name.clone()).or_default().push(value);
+        }
+    }
+    Ok(columns)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn read_parquet(_: &Path, _: &[&str]) -> Result<HashMap<String, Vec<f64>>, GraphError> {
+    Err(GraphError::Dataset(
+        "parquet support is disabled, rebuild ezkl with the `parquet` feature".to_string(),
+    ))
+}
+
+/// Loads a .csv or .parquet dataset into model inputs, following the column mapping and
+/// normalization of `spec`.
+pub fn load_dataset(path: &Path, spec: &DatasetSpec) -> Result<GraphData, GraphError> {
+    let keep = spec
+        .inputs
+        .iter()
+        .flatten()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+
+    let columns = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => {
+            let data = std::fs::read_to_string(path).map_err(|e| {
+                GraphError::Dataset(format!("failed to read {}: {}", path.display(), e))
+            })?;
+            parse_csv(&data, &keep)?
+        }
+        Some("parquet") => read_parquet(path, &keep)?,
+        _ => {
+            return Err(GraphError::Dataset(format!(
+                "unsupported dataset format for {}, expected a .csv or .parquet file",
+                path.display()
+            )))
+        }
+    };
+
+    spec.to_graph_data(&columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_to_graph_data() {
+        let csv = "id,age,\"income, usd\",label\n1,20,1000,true\n2,40,3000,false\n";
+        let spec = DatasetSpec {
+            inputs: vec![vec![
+                ColumnSpec {
+                    name: "age".to_string(),
+                    normalization: Normalization::ZScore {
+                        mean: 30.0,
+                        std: 10.0,
+                    },
+                },
+                ColumnSpec {
+                    name: "income, usd".to_string(),
+                    normalization: Normalization::MinMax {
+                        min: 1000.0,
+                        max: 3000.0,
+                    },
+                },
+            ]],
+        };
+        let keep = ["age", "income, usd"];
+        let columns = parse_csv(csv, &keep).unwrap();
+        let data = spec.to_graph_data(&columns).unwrap();
+
+        let expected: Vec<FileSourceInner> = [-1.0, 0.0, 1.0, 1.0]
+            .into_iter()
+            .map(FileSourceInner::Float)
+            .collect();
+        assert_eq!(data.input_data, vec![expected].into());
+    }
+}
@@ -0,0 +1,129 @@
+//! Lookup table domains picked per op during calibration.
+//!
+//! By default every lookup table covers the same inputs, [crate::RunArgs::lookup_range], so a
+//! single layer with wide inputs forces every table to be as wide. Calibration can instead give
+//! each op a domain fitting the inputs it saw. As tables over the same domain share their input
+//! columns, ops are grouped into a few domain classes rather than each getting its own, trading
+//! slightly wider tables for some ops against the input columns every extra domain adds.
+
+use super::GraphCircuit;
+use crate::circuit::lookup::LookupOp;
+use crate::circuit::table::Range;
+
+/// The smallest range covering all of `ranges`
+fn union(ranges: impl Iterator<Item = Range>) -> Range {
+    ranges
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+        .unwrap_or((0, 0))
+}
+
+/// The table area of a class of `num_ops` tables over `domain`: a column of outputs per op and
+/// the column of inputs they share, each as long as the domain.
+fn class_cost(domain: Range, num_ops: usize) -> i128 {
+    (domain.1 - domain.0).abs() * (num_ops as i128 + 1)
+}
+
+/// Groups `ranges` into at most `max_classes` classes minimizing the total table area, where the
+/// domain of a class is the union of its ranges. Ties go to the fewest classes.
+///
+/// Returns the class of each range and the domain of each class.
+pub fn domain_classes(ranges: &[Range], max_classes: usize) -> (Vec<usize>, Vec<Range>) {
+    let n = ranges.len();
+    if n == 0 {
+        return (vec![], vec![]);
+    }
+    let max_classes = max_classes.clamp(1, n);
+
+    // classes are contiguous runs of the ranges ordered by width
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by_key(|i| (ranges[*i].1 - ranges[*i].0).abs());
+    let run_domain = |start: usize, end: usize| union(order[start..end].iter().map(|i| ranges[*i]));
+
+    // best[c][j] is the least area of the first j ranges in c classes, and where the last starts
+    let mut best = vec![vec![(i128::MAX, 0); n + 1]; max_classes + 1];
+    best[0][0] = (0, 0);
+    for c in 1..=max_classes {
+        for j in c..=n {
+            for i in (c - 1)..j {
+                let (prev, _) = best[c - 1][i];
+                if prev == i128::MAX {
+                    continue;
+                }
+                let cost = prev + class_cost(run_domain(i, j), j - i);
+                if cost < best[c][j].0 {
+                    best[c][j] = (cost, i);
+                }
+            }
+        }
+    }
+
+    let mut c = (1..=max_classes).min_by_key(|c| best[*c][n].0).unwrap_or(1);
+    let mut classes = vec![0; n];
+    let mut domains = vec![(0, 0); c];
+    let mut end = n;
+    while c > 0 {
+        let start = best[c][end].1;
+        domains[c - 1] = run_domain(start, end);
+        for i in &order[start..end] {
+            classes[*i] = c - 1;
+        }
+        end = start;
+        c -= 1;
+    }
+
+    (classes, domains)
+}
+
+impl GraphCircuit {
+    /// Gives the required lookups domains fitting their inputs over the calibration data,
+    /// `lookup_input_ranges`, widened by the `lookup_safety_margin`, grouped into at most
+    /// `max_domains` classes. Ops whose class covers the whole lookup range, or that saw no
+    /// inputs, keep sharing the lookup range.
+    pub fn assign_lookup_domains(
+        &mut self,
+        lookup_input_ranges: &[(LookupOp, Range)],
+        max_domains: usize,
+        lookup_safety_margin: i128,
+    ) {
+        let settings = self.settings_mut();
+        let lookup_range = settings.run_args.lookup_range;
+
+        let (ops, ranges): (Vec<&LookupOp>, Vec<Range>) = settings
+            .required_lookups
+            .iter()
+            .filter_map(|l| lookup_input_ranges.iter().find(|(op, _)| op == &l.op))
+            .map(|(op, (min, max))| (op, (lookup_safety_margin * min, lookup_safety_margin * max)))
+            .unzip();
+        let (classes, domains) = domain_classes(&ranges, max_domains);
+
+        for lookup in settings.required_lookups.iter_mut() {
+            lookup.domain = ops
+                .iter()
+                .position(|op| *op == &lookup.op)
+                .map(|i| domains[classes[i]])
+                .filter(|domain| *domain != lookup_range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_classes() {
+        let ranges = [(-10, 10), (-1000, 1000), (-8, 10), (-10, 6)];
+
+        // a single class is the shared lookup range
+        let (classes, domains) = domain_classes(&ranges, 1);
+        assert_eq!(classes, vec![0; 4]);
+        assert_eq!(domains, vec![(-1000, 1000)]);
+
+        // the wide op gets a domain of its own, the narrow ones share theirs
+        let (classes, domains) = domain_classes(&ranges, 3);
+        assert_eq!(classes, vec![0, 1, 0, 0]);
+        assert_eq!(domains, vec![(-10, 10), (-1000, 1000)]);
+
+        assert_eq!(domain_classes(&[], 3), (vec![], vec![]));
+    }
+}
@@ -0,0 +1,130 @@
+use super::GraphError;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use std::path::PathBuf;
+
+/// Prefix of encrypted model files, also authenticated as associated data.
+pub const ENCRYPTED_MAGIC: &[u8; 8] = b"EZKLENC1";
+/// Length of the AES-256 key in bytes.
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// A user provided AES-256-GCM key used to encrypt serialized models and compiled circuits at rest.
+#[derive(Clone)]
+pub struct ModelKey([u8; KEY_LEN]);
+
+impl std::fmt::Debug for ModelKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print the key material
+        write!(f, "ModelKey(..)")
+    }
+}
+
+impl ModelKey {
+    ///
+    pub fn new(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parses a hex encoded key (e.g generated with `openssl rand -hex 32`).
+    pub fn from_hex(hex_key: &str) -> Result<Self, GraphError> {
+        let bytes = hex::decode(hex_key.trim().trim_start_matches("0x"))
+            .map_err(|e| GraphError::Encryption(format!("invalid hex key: {}", e)))?;
+        Self::try_from(bytes.as_slice())
+    }
+
+    /// Loads a key from a file holding either the hex encoded key or the raw key bytes.
+    pub fn from_file(path: &PathBuf) -> Result<Self, GraphError> {
+        let bytes = std::fs::read(path).map_err(|e| {
+            GraphError::Encryption(format!("failed to read key file {}: {}", path.display(), e))
+        })?;
+        if bytes.len() == KEY_LEN {
+            return Self::try_from(bytes.as_slice());
+        }
+        Self::from_hex(&String::from_utf8_lossy(&bytes))
+    }
+}
+
+impl TryFrom<&[u8]> for ModelKey {
+    type Error = GraphError;
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key: [u8; KEY_LEN] = bytes.try_into().map_err(|_| {
+            GraphError::Encryption(format!(
+                "expected a {} byte key, got {} bytes",
+                KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self(key))
+    }
+}
+
+/// Returns true if `data` was produced by [encrypt].
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+/// Encrypts `plaintext` under a fresh random nonce. The output is laid out as
+/// `magic || nonce || tag || ciphertext`.
+pub fn encrypt(plaintext: &[u8], key: &ModelKey) -> Result<Vec<u8>, GraphError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    openssl::rand::rand_bytes(&mut nonce).map_err(|e| GraphError::Encryption(e.to_string()))?;
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key.0,
+        Some(&nonce),
+        ENCRYPTED_MAGIC,
+        plaintext,
+        &mut tag,
+    )
+    .map_err(|e| GraphError::Encryption(e.to_string()))?;
+
+    let mut out =
+        Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + TAG_LEN + ciphertext.len());
+    out.extend_from_slice(ENCRYPTED_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&tag);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypts data produced by [encrypt], failing if it was tampered with or the key is wrong.
+pub fn decrypt(data: &[u8], key: &ModelKey) -> Result<Vec<u8>, GraphError> {
+    if !is_encrypted(data) || data.len() < ENCRYPTED_MAGIC.len() + NONCE_LEN + TAG_LEN {
+        return Err(GraphError::Encryption(
+            "data is not an encrypted ezkl model".to_string(),
+        ));
+    }
+    let (nonce, rest) = data[ENCRYPTED_MAGIC.len()..].split_at(NONCE_LEN);
+    let (tag, ciphertext) = rest.split_at(TAG_LEN);
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        &key.0,
+        Some(nonce),
+        ENCRYPTED_MAGIC,
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| {
+        GraphError::Encryption("wrong key or the encrypted model was modified".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let key = ModelKey::new([7u8; KEY_LEN]);
+        let plaintext = b"model weights".to_vec();
+
+        let encrypted = encrypt(&plaintext, &key).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(decrypt(&encrypted, &key).unwrap(), plaintext);
+
+        let wrong_key = ModelKey::new([8u8; KEY_LEN]);
+        assert!(decrypt(&encrypted, &wrong_key).is_err());
+    }
+}
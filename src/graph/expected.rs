@@ -0,0 +1,289 @@
+//! Acceptance checks of a circuit's outputs against reference outputs of the original model.
+//!
+//! Input data can carry the outputs the float model is expected to produce, e.g those of the
+//! training framework. `gen-witness` keeps them in the witness and reports whether the quantized
+//! outputs match them, and `mock` fails when they don't, so a model owner can check the circuit
+//! reproduces their model before generating real proofs.
+
+use super::{scale_to_multiplier, GraphSettings, GraphWitness};
+use crate::circuit::Tolerance;
+use crate::fieldutils::felt_to_f64;
+use serde::{Deserialize, Serialize};
+
+/// How the outputs of a single output tensor compare to the expected ones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OutputComparison {
+    /// The index of the output tensor
+    pub output: usize,
+    /// The largest absolute error between the outputs and the expected outputs
+    pub max_error: f64,
+    /// The number of elements outside of the tolerance
+    pub num_failed: usize,
+    /// The number of elements compared
+    pub len: usize,
+}
+
+/// The result of comparing every output of a witness to the expected outputs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AcceptanceReport {
+    /// One comparison per output tensor
+    pub outputs: Vec<OutputComparison>,
+    /// Why the outputs could not be compared, e.g a mismatched number of elements
+    pub errors: Vec<String>,
+}
+
+impl AcceptanceReport {
+    /// Whether every output is within the tolerance of the expected outputs
+    pub fn passed(&self) -> bool {
+        self.errors.is_empty() && self.outputs.iter().all(|o| o.num_failed == 0)
+    }
+}
+
+impl std::fmt::Display for AcceptanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = if self.passed() { "passed" } else { "failed" };
+        write!(f, "expected outputs check {}", status)?;
+        for o in &self.outputs {
+            write!(
+                f,
+                "; output {}: {}/{} within tolerance, max error {:e}",
+                o.output,
+                o.len - o.num_failed,
+                o.len,
+                o.max_error
+            )?;
+        }
+        for e in &self.errors {
+            write!(f, "; {}", e)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the outputs of `witness` to the `expected` ones.
+///
+/// An element passes when it is within `tolerance.val` percent of the expected value, or within
+/// one unit of the output's fixed point scale, as quantization alone can't do better than that.
+pub fn compare_outputs(
+    witness: &GraphWitness,
+    expected: &[Vec<f64>],
+    output_scales: &[crate::Scale],
+    tolerance: &Tolerance,
+) -> AcceptanceReport {
+    let mut report = AcceptanceReport::default();
+    if expected.len() != witness.outputs.len() {
+        report.errors.push(format!(
+            "expected {} outputs but the circuit has {}",
+            expected.len(),
+            witness.outputs.len()
+        ));
+    }
+
+    for (i, (outputs, expected)) in witness.outputs.iter().zip(expected).enumerate() {
+        if outputs.len() != expected.len() {
+            report.errors.push(format!(
+                "expected {} elements for output {} but the circuit has {}",
+                expected.len(),
+                i,
+                outputs.len()
+            ));
+            continue;
+        }
+        let multiplier = output_scales
+            .get(i)
+            .map_or(1.0, |s| scale_to_multiplier(*s));
+        let mut comparison = OutputComparison {
+            output: i,
+            max_error: 0.0,
+            num_failed: 0,
+            len: outputs.len(),
+        };
+        for (output, expected) in outputs.iter().zip(expected) {
+            let error = (felt_to_f64(*output) / multiplier - expected).abs();
+            let allowed = (tolerance.val as f64 / 100.0 * expected.abs()).max(1.0 / multiplier);
+            comparison.max_error = comparison.max_error.max(error);
+            if error > allowed || error.is_nan() {
+                comparison.num_failed += 1;
+            }
+        }
+        report.outputs.push(comparison);
+    }
+    report
+}
+
+impl GraphWitness {
+    /// Compares the outputs to the expected outputs, if the input data had any, within the
+    /// tolerance of the circuit.
+    pub fn check_expected_outputs(&self, settings: &GraphSettings) -> Option<AcceptanceReport> {
+        let expected = self.expected_outputs.as_ref()?;
+        Some(compare_outputs(
+            self,
+            expected,
+            &settings.model_output_scales,
+            &settings.run_args.tolerance,
+        ))
+    }
+}
+
+/// Runs the original onnx model at `model_path` in onnxruntime on the inputs of `witness`, and
+/// compares the outputs of the witness to its outputs within the tolerance of the circuit. This is
+/// the trusted execution `prove --shadow-model` checks a proof against before emitting it, so it
+/// deliberately runs outside of the tract frontend the circuit was built with.
+///
+/// The inputs are dequantized from the witness, so the float model sees the same values as the
+/// circuit, up to the precision of the input scales.
+#[cfg(all(feature = "onnxruntime", not(target_arch = "wasm32")))]
+pub fn shadow_outputs(
+    settings: &GraphSettings,
+    input_shapes: Vec<Vec<usize>>,
+    witness: &GraphWitness,
+    model_path: &std::path::Path,
+) -> Result<AcceptanceReport, Box<dyn std::error::Error>> {
+    use ndarray::{ArrayD, CowArray, IxDyn};
+    use ort::tensor::TensorElementDataType;
+    use ort::{Environment, SessionBuilder, Value};
+
+    enum Input {
+        F32(CowArray<'static, f32, IxDyn>),
+        I64(CowArray<'static, i64, IxDyn>),
+    }
+
+    let environment = Environment::builder()
+        .with_name("ezkl-shadow-model")
+        .build()?
+        .into_arc();
+    let session = SessionBuilder::new(&environment)?.with_model_from_file(model_path)?;
+    if session.inputs.len() != witness.inputs.len() {
+        return Err(format!(
+            "the shadow model takes {} inputs but the witness has {}",
+            session.inputs.len(),
+            witness.inputs.len()
+        )
+        .into());
+    }
+
+    let mut inputs = vec![];
+    for (i, (input, shape)) in witness.inputs.iter().zip(input_shapes).enumerate() {
+        let multiplier = settings
+            .model_input_scales
+            .get(i)
+            .map_or(1.0, |s| scale_to_multiplier(*s));
+        let values = input.iter().map(|x| felt_to_f64(*x) / multiplier);
+        let shape = IxDyn(&shape);
+        inputs.push(match session.inputs[i].input_type {
+            TensorElementDataType::Float32 => Input::F32(CowArray::from(ArrayD::from_shape_vec(
+                shape,
+                values.map(|x| x as f32).collect(),
+            )?)),
+            TensorElementDataType::Int64 => Input::I64(CowArray::from(ArrayD::from_shape_vec(
+                shape,
+                values.map(|x| x.round() as i64).collect(),
+            )?)),
+            ty => {
+                return Err(
+                    format!("the shadow model takes an unsupported input type {:?}", ty).into(),
+                )
+            }
+        });
+    }
+    let values = inputs
+        .iter()
+        .map(|input| match input {
+            Input::F32(array) => Value::from_array(session.allocator(), array),
+            Input::I64(array) => Value::from_array(session.allocator(), array),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let expected = session
+        .run(values)?
+        .iter()
+        .map(|output| -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+            Ok(match output.try_extract::<f32>() {
+                Ok(tensor) => tensor.view().iter().map(|x| *x as f64).collect(),
+                Err(_) => {
+                    let tensor = output.try_extract::<i64>()?;
+                    let values = tensor.view().iter().map(|x| *x as f64).collect();
+                    values
+                }
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if expected.is_empty() {
+        return Err("the shadow model produced no outputs".into());
+    }
+
+    Ok(compare_outputs(
+        witness,
+        &expected,
+        &settings.model_output_scales,
+        &settings.run_args.tolerance,
+    ))
+}
+
+/// Without the `onnxruntime` feature there is no trusted execution to check against.
+#[cfg(all(
+    feature = "onnx",
+    not(feature = "onnxruntime"),
+    not(target_arch = "wasm32")
+))]
+pub fn shadow_outputs(
+    _settings: &GraphSettings,
+    _input_shapes: Vec<Vec<usize>>,
+    _witness: &GraphWitness,
+    _model_path: &std::path::Path,
+) -> Result<AcceptanceReport, Box<dyn std::error::Error>> {
+    Err("the shadow model needs ezkl built with the onnxruntime feature".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::Fr as Fp;
+
+    #[test]
+    fn test_compare_outputs() {
+        // outputs at scale 1, i.e 1.0 and 1.5
+        let witness = GraphWitness::new(vec![], vec![vec![Fp::from(2), Fp::from(3)]]);
+        let tolerance = Tolerance::default();
+
+        let report = compare_outputs(&witness, &[vec![1.1, 1.4]], &[1], &tolerance);
+        assert!(report.passed(), "{}", report);
+
+        let report = compare_outputs(&witness, &[vec![1.0, 2.5]], &[1], &tolerance);
+        assert!(!report.passed());
+        assert_eq!(report.outputs[0].num_failed, 1);
+
+        let tolerance = Tolerance {
+            val: 50.0,
+            ..Default::default()
+        };
+        assert!(compare_outputs(&witness, &[vec![1.0, 2.5]], &[1], &tolerance).passed());
+        assert!(!compare_outputs(&witness, &[vec![1.0]], &[1], &tolerance).passed());
+    }
+
+    #[cfg(feature = "onnxruntime")]
+    #[test]
+    fn test_shadow_outputs() {
+        use crate::fieldutils::i128_to_felt;
+
+        let model_path = std::path::Path::new("examples/onnx/1l_relu/network.onnx");
+        let settings = GraphSettings {
+            model_input_scales: vec![0],
+            model_output_scales: vec![0],
+            ..Default::default()
+        };
+        let felts = |xs: &[i128]| {
+            xs.iter()
+                .map(|x| i128_to_felt::<Fp>(*x))
+                .collect::<Vec<_>>()
+        };
+
+        let witness = GraphWitness::new(vec![felts(&[-1, 2, 3])], vec![felts(&[0, 2, 3])]);
+        let report = shadow_outputs(&settings, vec![vec![1, 3]], &witness, model_path).unwrap();
+        assert!(report.passed(), "{}", report);
+
+        let witness = GraphWitness::new(vec![felts(&[-1, 2, 3])], vec![felts(&[0, 2, 5])]);
+        let report = shadow_outputs(&settings, vec![vec![1, 3]], &witness, model_path).unwrap();
+        assert_eq!(report.outputs[0].num_failed, 1);
+    }
+}
@@ -0,0 +1,297 @@
+use super::model::NodeType;
+use super::node::{Node, Outlet, RebaseScale, SupportedOp};
+use super::scale_to_multiplier;
+use crate::circuit::hybrid::HybridOp;
+use crate::circuit::poly::PolyOp;
+use crate::circuit::Op;
+use log::debug;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Looks through the rebasing of an op's output, which fusion makes redundant. Rescaled inputs
+/// change the op's semantics so are not looked through.
+fn unwrap_rebase(op: &SupportedOp) -> &SupportedOp {
+    match op {
+        SupportedOp::RebaseScale(r) => unwrap_rebase(&r.inner),
+        op => op,
+    }
+}
+
+/// Returns the node at `idx` if only a single op consumes it, so it can be folded into that op.
+fn single_use<'a>(
+    nodes: &'a BTreeMap<usize, NodeType>,
+    outputs: &[usize],
+    outlet: Outlet,
+) -> Option<&'a Node> {
+    match nodes.get(&outlet.0) {
+        Some(NodeType::Node(n))
+            if n.num_uses == 1 && outlet.1 == 0 && !outputs.contains(&n.idx) =>
+        {
+            Some(n)
+        }
+        _ => None,
+    }
+}
+
+fn einsum_equation(node: &Node) -> Option<&str> {
+    match unwrap_rebase(&node.opkind) {
+        SupportedOp::Linear(PolyOp::Einsum { equation }) if node.inputs.len() == 2 => {
+            Some(equation)
+        }
+        _ => None,
+    }
+}
+
+/// Swaps the operands of a two operand einsum equation.
+fn swap_operands(equation: &str) -> Option<String> {
+    let (operands, output) = equation.split_once("->")?;
+    let (a, b) = operands.split_once(',')?;
+    Some(format!("{},{}->{}", b, a, output))
+}
+
+/// An attention block matched in the graph.
+#[derive(Debug)]
+struct AttentionMatch {
+    /// `[Q, K, V]`
+    inputs: Vec<Outlet>,
+    qk_equation: String,
+    av_equation: String,
+    /// the factor the scores are multiplied by before the softmax
+    score_factor: f64,
+    scale: crate::circuit::utils::F32,
+    axes: Vec<usize>,
+    /// the scores, scaling and softmax nodes subsumed by the fused op
+    subsumed: Vec<usize>,
+    /// the constant the scores were scaled by, if any
+    factor_constant: Option<usize>,
+}
+
+/// Matches `einsum(softmax(scaling(einsum(Q, K))), V)` ending at `idx`, where the scaling is
+/// either absent, a multiplication by a scalar constant or a power of two change of scale.
+fn match_attention(
+    nodes: &BTreeMap<usize, NodeType>,
+    outputs: &[usize],
+    idx: usize,
+) -> Option<AttentionMatch> {
+    let Some(NodeType::Node(av)) = nodes.get(&idx) else {
+        return None;
+    };
+    let av_equation = einsum_equation(av)?;
+
+    let (pos, softmax, scale, axes) = (0..2).find_map(|pos| {
+        let softmax = single_use(nodes, outputs, av.inputs[pos])?;
+        match unwrap_rebase(&softmax.opkind) {
+            SupportedOp::Hybrid(HybridOp::Softmax { scale, axes })
+                if axes.as_slice() == [softmax.out_dims.len() - 1] =>
+            {
+                Some((pos, softmax, *scale, axes.clone()))
+            }
+            _ => None,
+        }
+    })?;
+    let av_equation = if pos == 0 {
+        av_equation.to_string()
+    } else {
+        swap_operands(av_equation)?
+    };
+    let v = av.inputs[1 - pos];
+
+    let mut subsumed = vec![softmax.idx];
+    let mut factor_constant = None;
+    let scaled = single_use(nodes, outputs, *softmax.inputs.first()?)?;
+    let (scores, score_factor) = match unwrap_rebase(&scaled.opkind) {
+        SupportedOp::Linear(PolyOp::Einsum { .. }) => (scaled, 1.0),
+        SupportedOp::Linear(PolyOp::Mult) if scaled.inputs.len() == 2 => {
+            let constant = scaled.inputs.iter().position(
+                |i| matches!(nodes.get(&i.0), Some(NodeType::Node(n)) if n.is_constant()),
+            )?;
+            let Some(NodeType::Node(c)) = nodes.get(&scaled.inputs[constant].0) else {
+                return None;
+            };
+            let SupportedOp::Constant(c) = &c.opkind else {
+                return None;
+            };
+            if c.raw_values.len() != 1 {
+                return None;
+            }
+            factor_constant = Some(scaled.inputs[constant].0);
+            subsumed.push(scaled.idx);
+            let scores = single_use(nodes, outputs, scaled.inputs[1 - constant])?;
+            (scores, c.raw_values[0] as f64)
+        }
+        SupportedOp::Linear(PolyOp::Identity {
+            out_scale: Some(out_scale),
+        }) if scaled.inputs.len() == 1 => {
+            subsumed.push(scaled.idx);
+            let scores = single_use(nodes, outputs, scaled.inputs[0])?;
+            (scores, scale_to_multiplier(scores.out_scale - out_scale))
+        }
+        _ => return None,
+    };
+    let qk_equation = einsum_equation(scores)?.to_string();
+    subsumed.push(scores.idx);
+
+    Some(AttentionMatch {
+        inputs: vec![scores.inputs[0], scores.inputs[1], v],
+        qk_equation,
+        av_equation,
+        score_factor,
+        scale,
+        axes,
+        subsumed,
+        factor_constant,
+    })
+}
+
+/// Replaces attention blocks with a single [HybridOp::Attention], returning the number of blocks
+/// fused. Fused nodes keep the index and output scale of the final einsum, so their consumers are
+/// unaffected. `outputs` are the graph's output nodes, which are never subsumed.
+pub fn fuse_attention(
+    nodes: &mut BTreeMap<usize, NodeType>,
+    outputs: &[usize],
+    div_rebasing: bool,
+    shift_rebasing: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let mut num_fused = 0;
+    let candidates: Vec<usize> = nodes.keys().copied().collect();
+    for idx in candidates {
+        let Some(m) = match_attention(nodes, outputs, idx) else {
+            continue;
+        };
+        let in_scales = m
+            .inputs
+            .iter()
+            .map(|(i, slot)| Ok(nodes.get(i).ok_or("input not found")?.out_scales()[*slot]))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        // the raw scores are at the sum of the scales of Q and K
+        let score_multiplier = scale_to_multiplier(in_scales[0] + in_scales[1]);
+        let score_denom = score_multiplier / (m.score_factor * m.scale.0 as f64);
+        let op = SupportedOp::Hybrid(HybridOp::Attention {
+            qk_equation: m.qk_equation,
+            av_equation: m.av_equation,
+            score_denom: (score_denom as f32).into(),
+            scale: m.scale,
+            axes: m.axes,
+        });
+
+        let NodeType::Node(av) = &nodes[&idx] else {
+            continue;
+        };
+        // rebase to the scale of the unfused block, which consumers were built against
+        let target = av.out_scale;
+        let op_out_scale = op.out_scale(in_scales.clone())?;
+        let opkind = if op_out_scale > target {
+            RebaseScale::rebase(op, target, op_out_scale, 1, div_rebasing, shift_rebasing)
+        } else {
+            RebaseScale::rebase_up(op, target, op_out_scale, div_rebasing, shift_rebasing)
+        };
+        if opkind.out_scale(in_scales)? != target {
+            debug!(
+                "skipping attention fusion at {}, scales can't be matched",
+                idx
+            );
+            continue;
+        }
+
+        let fused = Node {
+            idx,
+            opkind,
+            inputs: m.inputs,
+            out_dims: av.out_dims.clone(),
+            out_scale: target,
+            num_uses: av.num_uses,
+        };
+        for subsumed in &m.subsumed {
+            nodes.remove(subsumed);
+        }
+        if let Some(c) = m.factor_constant.and_then(|c| nodes.get_mut(&c)) {
+            c.decrement_use();
+        }
+        nodes.insert(idx, NodeType::Node(fused));
+        num_fused += 1;
+    }
+    Ok(num_fused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::ops::{Input, InputType};
+
+    fn node(idx: usize, opkind: SupportedOp, inputs: &[usize], out_dims: &[usize]) -> NodeType {
+        let in_scales = vec![7; inputs.len()];
+        NodeType::Node(Node {
+            idx,
+            out_scale: opkind.out_scale(in_scales).unwrap(),
+            opkind,
+            inputs: inputs.iter().map(|i| (*i, 0)).collect(),
+            out_dims: out_dims.to_vec(),
+            num_uses: 1,
+        })
+    }
+
+    #[test]
+    fn test_fuse_attention() {
+        let input = || {
+            SupportedOp::Input(Input {
+                scale: 7,
+                datum_type: InputType::F32,
+            })
+        };
+        let einsum = |equation: &str| {
+            SupportedOp::Linear(PolyOp::Einsum {
+                equation: equation.into(),
+            })
+        };
+        let mut nodes = BTreeMap::new();
+        for (idx, n) in [
+            node(0, input(), &[], &[4, 8]),
+            node(1, input(), &[], &[4, 8]),
+            node(2, input(), &[], &[4, 8]),
+            node(3, einsum("ik,jk->ij"), &[0, 1], &[4, 4]),
+            node(
+                4,
+                SupportedOp::Linear(PolyOp::Identity {
+                    out_scale: Some(15),
+                }),
+                &[3],
+                &[4, 4],
+            ),
+            node(
+                5,
+                SupportedOp::Hybrid(HybridOp::Softmax {
+                    scale: 128.0.into(),
+                    axes: vec![1],
+                }),
+                &[4],
+                &[4, 4],
+            ),
+            node(6, einsum("jk,ij->ik"), &[2, 5], &[4, 8]),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            nodes.insert(idx, n);
+        }
+
+        assert_eq!(fuse_attention(&mut nodes, &[6], false, false).unwrap(), 1);
+        assert_eq!(nodes.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2, 6]);
+        let NodeType::Node(fused) = &nodes[&6] else {
+            panic!("expected a node");
+        };
+        assert_eq!(fused.inputs, vec![(0, 0), (1, 0), (2, 0)]);
+        match unwrap_rebase(&fused.opkind) {
+            SupportedOp::Hybrid(HybridOp::Attention {
+                av_equation,
+                score_denom,
+                ..
+            }) => {
+                assert_eq!(av_equation, "ij,jk->ik");
+                // scores at scale 14, scaled by 2^-1 and brought to scale 7
+                assert_eq!(score_denom.0, 256.0);
+            }
+            op => panic!("expected attention, got {}", op.as_string()),
+        }
+    }
+}
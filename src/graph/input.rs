@@ -1,12 +1,15 @@
 use super::quantize_float;
+use super::sanitize::{check_json, read_json, JsonLimits};
 use super::GraphError;
 use crate::circuit::InputType;
 use crate::fieldutils::i128_to_felt;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
 use crate::tensor::Tensor;
 use crate::EZKL_BUF_CAPACITY;
 use halo2curves::bn256::Fr as Fp;
 #[cfg(not(target_arch = "wasm32"))]
+use num::ToPrimitive;
+#[cfg(not(target_arch = "wasm32"))]
 use postgres::{Client, NoTls};
 #[cfg(feature = "python-bindings")]
 use pyo3::prelude::*;
@@ -18,17 +21,14 @@ use serde::ser::SerializeStruct;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::io::BufReader;
 use std::io::BufWriter;
-use std::io::Read;
 use std::panic::UnwindSafe;
 #[cfg(not(target_arch = "wasm32"))]
 use std::thread;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::tract_core::{
     tract_data::{prelude::Tensor as TractTensor, TVec},
     value::TValue,
 };
-#[cfg(not(target_arch = "wasm32"))]
-use tract_onnx::tract_hir::tract_num_traits::ToPrimitive;
 
 type Decimals = u8;
 type Call = String;
@@ -157,6 +157,13 @@ impl FileSourceInner {
     }
 }
 
+/// Milliseconds waited before the first retry of an rpc, unless the source sets its own
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+
+fn default_retry_backoff_ms() -> u64 {
+    DEFAULT_RETRY_BACKOFF_MS
+}
+
 /// Inner elements of inputs/outputs coming from on-chain
 #[derive(Clone, Debug, Deserialize, Serialize, Default, PartialOrd, PartialEq)]
 pub struct OnChainSource {
@@ -164,12 +171,190 @@ pub struct OnChainSource {
     pub calls: Vec<CallsToAccount>,
     /// RPC url
     pub rpc: RPCUrl,
+    /// RPC urls tried in order after `rpc`, when it fails or to reach the `quorum`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_rpcs: Vec<RPCUrl>,
+    /// Number of times each rpc is retried before failing over to the next
+    #[serde(default)]
+    pub retries: usize,
+    /// Milliseconds waited before the first retry of an rpc, doubling with every further retry
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Block number the reads are pinned to, so they can be reproduced. Reads are made at the
+    /// latest block when unset, and rpcs behind a pinned block wait for it when they are
+    /// websocket rpcs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// Opt-in: record in the witness the EIP-1186 proofs, at the pinned block, of the storage slots
+    /// the calls read (see [BlockStateProof]), so third parties can check the reads. The slots are
+    /// taken from the access lists of the calls
+    #[serde(default)]
+    pub prove_storage: bool,
+    /// Storage slots proven along with the ones the calls read, e.g slots read through other
+    /// contracts' code the access lists miss
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_proofs: Vec<StorageSlots>,
+    /// Number of rpcs that must read the same values for them to be trusted, at least 1
+    #[serde(default)]
+    pub quorum: usize,
+    /// Local file the on-chain reads are snapshotted to whenever they are fetched from the rpc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    /// Opt-in: replay the reads from `snapshot` (if it exists) instead of querying the rpc
+    #[serde(default)]
+    pub replay_snapshot: bool,
+    /// Calls specified by their function's signature and arguments, ABI encoded and read after
+    /// `calls`, see [ContractCall]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contract_calls: Vec<ContractCall>,
 }
 
 impl OnChainSource {
     /// Create a new OnChainSource
     pub fn new(calls: Vec<CallsToAccount>, rpc: RPCUrl) -> Self {
-        OnChainSource { calls, rpc }
+        OnChainSource {
+            calls,
+            rpc,
+            fallback_rpcs: vec![],
+            retries: 0,
+            retry_backoff_ms: DEFAULT_RETRY_BACKOFF_MS,
+            block_number: None,
+            prove_storage: false,
+            storage_proofs: vec![],
+            quorum: 1,
+            snapshot: None,
+            replay_snapshot: false,
+            contract_calls: vec![],
+        }
+    }
+
+    /// The rpcs to read from, in the order they are tried
+    pub fn rpcs(&self) -> Vec<&str> {
+        std::iter::once(&self.rpc)
+            .chain(self.fallback_rpcs.iter())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// ABI encodes the `contract_calls` into `calls`, inferring their decimals from the rpcs where
+    /// they aren't set (see [crate::eth::resolve_contract_calls]).
+    #[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
+    pub async fn resolve_contract_calls(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.contract_calls.is_empty() {
+            return Ok(());
+        }
+        let resolved = crate::eth::resolve_contract_calls(self).await?;
+        self.calls.extend(resolved);
+        self.contract_calls.clear();
+        Ok(())
+    }
+
+    /// All the calls of the source, with the `contract_calls` ABI encoded but their decimals left
+    /// at 0, for uses that only depend on the calls made and not on how they're scaled.
+    #[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
+    pub fn encoded_calls(&self) -> Result<Vec<CallsToAccount>, Box<dyn std::error::Error>> {
+        let mut calls = self.calls.clone();
+        for call in &self.contract_calls {
+            let encoded = crate::eth::encode_contract_call(call)?;
+            calls.push(CallsToAccount {
+                call_data: vec![(hex::encode(encoded.call_data), 0)],
+                address: call.address.trim_start_matches("0x").to_lowercase(),
+                is_unsigned: vec![],
+            });
+        }
+        Ok(calls)
+    }
+}
+
+/// A single read recorded in an [OnChainSnapshot]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SnapshotCall {
+    /// Address of the contract that was called
+    pub address: String,
+    /// ABI encoded call
+    pub call_data: Call,
+    /// keccak256 of the address bytes followed by the call data bytes
+    pub call_hash: String,
+    /// Number of decimals for f32 conversion
+    pub decimals: Decimals,
+    /// Whether the call returns an unsigned integer
+    #[serde(default)]
+    pub is_unsigned: bool,
+    /// Hex encoded return data
+    pub result: String,
+}
+
+/// On-chain reads recorded at a fixed block, so that a failed run can be retried without
+/// hitting the rpc again.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct OnChainSnapshot {
+    /// RPC url the reads were made against
+    pub rpc: RPCUrl,
+    /// Block number all reads were pinned to
+    pub block_number: u64,
+    /// The reads, in the order they were made
+    pub calls: Vec<SnapshotCall>,
+    /// The scales the reads were quantized at
+    pub scales: Vec<crate::Scale>,
+    /// The reads quantized by the QuantizeData contract
+    pub quantized: Vec<Fp>,
+    /// The storage proofs of the reads, when the source records them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_proof: Option<BlockStateProof>,
+}
+
+#[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
+impl OnChainSnapshot {
+    /// Hash identifying a call, recorded for auditability and used to check a snapshot matches a source
+    pub fn call_hash(address: &str, call_data: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut bytes = hex::decode(address)?;
+        bytes.extend(hex::decode(call_data)?);
+        Ok(hex::encode(ethers::utils::keccak256(bytes)))
+    }
+
+    /// Checks the snapshot records exactly the calls of `calls`, quantized at `scales`.
+    pub fn check(
+        &self,
+        calls: &[CallsToAccount],
+        scales: &[crate::Scale],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut expected = vec![];
+        for account in calls {
+            for (call_data, _) in &account.call_data {
+                expected.push(Self::call_hash(&account.address, call_data)?);
+            }
+        }
+        let recorded = self
+            .calls
+            .iter()
+            .map(|c| c.call_hash.clone())
+            .collect::<Vec<_>>();
+        if expected != recorded {
+            return Err("on-chain snapshot does not match the calls of the data source".into());
+        }
+        if self.scales != scales {
+            return Err(format!(
+                "on-chain snapshot was quantized at scales {:?}, expected {:?}",
+                self.scales, scales
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Load a snapshot from a json file
+    pub fn from_path(path: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save a snapshot to a json file
+    pub fn save(&self, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        let writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer(writer, self)?;
+        Ok(())
     }
 }
 
@@ -277,7 +462,7 @@ impl PostgresSource {
 }
 
 impl OnChainSource {
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
     /// Create dummy local on-chain data to test the OnChain data source
     pub async fn test_from_file_data(
         data: &FileSource,
@@ -304,7 +489,8 @@ impl OnChainSource {
 
         let calls_to_accounts = test_on_chain_data(client.clone(), data).await?;
         debug!("Calls to accounts: {:?}", calls_to_accounts);
-        let inputs = read_on_chain_inputs(client.clone(), address, &calls_to_accounts).await?;
+        let inputs =
+            read_on_chain_inputs(client.clone(), address, &calls_to_accounts, None).await?;
         debug!("Inputs: {:?}", inputs);
 
         let mut quantized_evm_inputs = vec![];
@@ -319,6 +505,7 @@ impl OnChainSource {
                         inputs.0[prev..i.len()].to_vec(),
                         inputs.1[prev..i.len()].to_vec(),
                     ),
+                    &vec![false; i.len()],
                 )
                 .await?,
             );
@@ -357,6 +544,95 @@ pub struct CallsToAccount {
     pub call_data: Vec<(Call, Decimals)>,
     /// Address of the contract to read the data from.
     pub address: String,
+    /// Whether each call returns an unsigned integer, which is then decoded as a `uint256`
+    /// rather than an `int256` when quantized. Calls past its end are signed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub is_unsigned: Vec<bool>,
+}
+
+impl CallsToAccount {
+    /// Whether each of the calls returns an unsigned integer, see [CallsToAccount::is_unsigned].
+    pub fn unsigned_calls(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.call_data.len()).map(|i| self.is_unsigned.get(i).copied().unwrap_or(false))
+    }
+}
+
+/// A view call to read on-chain data from, specified by the function's signature and arguments
+/// rather than its ABI encoded call data. The return type and decimals of the views of ERC-20
+/// and ERC-721 tokens (`balanceOf`, `totalSupply`, `allowance` and `decimals`) are inferred,
+/// other calls have to state them.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialOrd, PartialEq)]
+pub struct ContractCall {
+    /// Address of the contract to read the data from.
+    pub address: String,
+    /// Signature of the function, e.g `balanceOf(address)`, followed by its return type for
+    /// functions other than the standard token views, e.g `latestAnswer()(int256)`. Only
+    /// elementary argument types are supported, and the function must return an integer or bool.
+    pub signature: String,
+    /// Arguments of the call, parsed according to their type in the signature, e.g
+    /// `0x5FbDB2315678afecb367f032d93F642f64180aa3` for an address
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Number of decimals of the value returned. Read from the token for ERC-20 amounts, and none
+    /// for ERC-721 counts, when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<Decimals>,
+}
+
+/// Storage slots of an account backing on-chain reads, to be proven with an EIP-1186 proof.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, PartialOrd, PartialEq)]
+pub struct StorageSlots {
+    /// Address of the account, hex encoded
+    pub address: String,
+    /// The storage keys to prove, hex encoded
+    pub slots: Vec<String>,
+}
+
+/// The EIP-1186 proof of a storage slot, hex encoded as returned by `eth_getProof`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StorageSlotProof {
+    /// The storage key
+    pub key: String,
+    /// The value stored at the key
+    pub value: String,
+    /// The Merkle-Patricia proof of the value against the account's storage root
+    pub proof: Vec<String>,
+}
+
+/// The EIP-1186 proof of an account and some of its storage slots, hex encoded as returned by
+/// `eth_getProof`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AccountStateProof {
+    /// Address of the account
+    pub address: String,
+    /// Balance of the account
+    pub balance: String,
+    /// Nonce of the account
+    pub nonce: u64,
+    /// Hash of the account's code
+    pub code_hash: String,
+    /// Root of the account's storage trie
+    pub storage_hash: String,
+    /// The Merkle-Patricia proof of the account against the block's state root
+    pub account_proof: Vec<String>,
+    /// The proofs of the requested storage slots
+    pub storage: Vec<StorageSlotProof>,
+}
+
+/// EIP-1186 proofs of the storage backing on-chain reads, against the state root of the block the
+/// reads were pinned to. They are checked against the state root when fetched (see
+/// [crate::eth::verify_block_state_proof]) and let third parties check the reads against the
+/// block's header, the circuit doesn't verify them (yet).
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BlockStateProof {
+    /// The block the proofs are against
+    pub block_number: u64,
+    /// Hash of the block
+    pub block_hash: String,
+    /// State root of the block
+    pub state_root: String,
+    /// The proofs of each account
+    pub accounts: Vec<AccountStateProof>,
 }
 /// Enum that defines source of the inputs/outputs to the EZKL model
 #[derive(Clone, Debug, Serialize, PartialOrd, PartialEq)]
@@ -447,13 +723,17 @@ pub struct GraphData {
     pub input_data: DataSource,
     /// Outputs of the model / computational graph (can be empty vectors if outputs are coming from on-chain).
     pub output_data: Option<DataSource>,
+    /// The outputs the original model is expected to produce, to check the circuit against, see
+    /// [super::expected]
+    #[serde(default)]
+    pub expected_outputs: Option<Vec<Vec<f64>>>,
 }
 
 impl UnwindSafe for GraphData {}
 
 impl GraphData {
     // not wasm
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     /// Convert the input data to tract data
     pub fn to_tract_data(
         &self,
@@ -488,17 +768,32 @@ impl GraphData {
         GraphData {
             input_data,
             output_data: None,
+            expected_outputs: None,
         }
     }
 
-    /// Load the model input from a file
+    /// Load the model input from a file, within the default [JsonLimits]
     pub fn from_path(path: std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let reader = std::fs::File::open(path)?;
-        let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, reader);
-        let mut buf = String::new();
-        reader.read_to_string(&mut buf)?;
-        let graph_input = serde_json::from_str(&buf)?;
-        Ok(graph_input)
+        Self::from_path_with_limits(path, &JsonLimits::default())
+    }
+
+    /// Load the model input from a file, rejecting files exceeding the `limits`
+    pub fn from_path_with_limits(
+        path: std::path::PathBuf,
+        limits: &JsonLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let bytes = read_json(&path, limits)?;
+        Self::from_untrusted(&bytes, limits)
+    }
+
+    /// Parse untrusted model input, rejecting payloads exceeding the `limits` or repeating keys
+    /// before they are deserialized, see [super::sanitize]
+    pub fn from_untrusted(
+        bytes: &[u8],
+        limits: &JsonLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        check_json(bytes, limits)?;
+        Ok(serde_json::from_slice(bytes)?)
     }
 
     /// Save the model input to a file
@@ -520,11 +815,11 @@ impl GraphData {
         let iterable = match self {
             GraphData {
                 input_data: DataSource::File(data),
-                output_data: _,
+                ..
             } => data.clone(),
             GraphData {
                 input_data: DataSource::OnChain(_),
-                output_data: _,
+                ..
             } => {
                 return Err(Box::new(GraphError::InvalidDims(
                     0,
@@ -534,14 +829,18 @@ impl GraphData {
             #[cfg(not(target_arch = "wasm32"))]
             GraphData {
                 input_data: DataSource::DB(data),
-                output_data: _,
+                ..
             } => data.fetch_and_format_as_file()?,
         };
 
         for (i, shape) in input_shapes.iter().enumerate() {
             // ensure the input is evenly divisible by batch_size
-            let input_size = shape.clone().iter().product::<usize>();
-            let input = &iterable[i];
+            let shape = super::normalize_dims(shape, &format!("input {}", i))?;
+            let input_size = shape.iter().product::<usize>();
+            let input = iterable.get(i).ok_or(GraphError::InvalidDims(
+                i,
+                format!("missing calibration data for input {}", i),
+            ))?;
             if input.len() % input_size != 0 {
                 return Err(Box::new(GraphError::InvalidDims(
                     0,
@@ -597,6 +896,7 @@ impl ToPyObject for CallsToAccount {
         let dict = PyDict::new(py);
         dict.set_item("account", &self.address).unwrap();
         dict.set_item("call_data", &self.call_data).unwrap();
+        dict.set_item("is_unsigned", &self.is_unsigned).unwrap();
         dict.to_object(py)
     }
 }
@@ -645,6 +945,11 @@ impl Serialize for GraphData {
         let mut state = serializer.serialize_struct("GraphData", 4)?;
         state.serialize_field("input_data", &self.input_data)?;
         state.serialize_field("output_data", &self.output_data)?;
+        if let Some(expected_outputs) = &self.expected_outputs {
+            state.serialize_field("expected_outputs", expected_outputs)?;
+        } else {
+            state.skip_field("expected_outputs")?;
+        }
         state.end()
     }
 }
@@ -671,6 +976,30 @@ mod tests {
         assert_eq!(expect, source);
     }
 
+    #[test]
+    fn test_on_chain_source_rpcs() {
+        // sources predating fail-over read from their single rpc
+        let json = r#"{"calls": [], "rpc": "http://a"}"#;
+        let source: OnChainSource = serde_json::from_str(json).unwrap();
+        assert_eq!(source.rpcs(), vec!["http://a"]);
+        assert_eq!((source.retries, source.quorum), (0, 0));
+
+        let json =
+            r#"{"calls": [], "rpc": "http://a", "fallback_rpcs": ["http://b"], "quorum": 2}"#;
+        let source: OnChainSource = serde_json::from_str(json).unwrap();
+        assert_eq!(source.rpcs(), vec!["http://a", "http://b"]);
+        assert_eq!(source.retry_backoff_ms, DEFAULT_RETRY_BACKOFF_MS);
+        assert_eq!(source.block_number, None);
+
+        assert!(!source.prove_storage);
+
+        let json = r#"{"calls": [], "rpc": "wss://a", "block_number": 19000000, "prove_storage": true, "storage_proofs": [{"address": "00", "slots": ["00"]}]}"#;
+        let source: OnChainSource = serde_json::from_str(json).unwrap();
+        assert_eq!(source.block_number, Some(19000000));
+        assert!(source.prove_storage);
+        assert_eq!(source.storage_proofs[0].slots, vec!["00"]);
+    }
+
     #[test]
     // this is for backwards compatibility with the old format
     fn test_graph_input_serialization_round_trip() {
@@ -0,0 +1,133 @@
+use super::{
+    GraphError, GraphSettings, ASSUMED_BLINDING_FACTORS, MAX_LOOKUP_ABS, MAX_NUM_LOOKUP_COLS,
+    MAX_PUBLIC_SRS, MIN_LOGROWS,
+};
+use halo2curves::bn256::Fr;
+use halo2curves::ff::PrimeField;
+use serde::{Deserialize, Serialize};
+
+/// Einsum equations (which back matmuls, contractions and reductions) label each axis with an
+/// ascii letter, so tensors cannot have more axes than there are letters.
+pub const MAX_TENSOR_RANK: usize = 52;
+
+/// Witness values are represented as `i128` before being mapped into the field.
+pub const MAX_VALUE_BITS: u32 = i128::BITS - 1;
+
+/// The hard limits of the circuits ezkl can generate, derived from the constants used throughout
+/// the compiler, so integrators can validate models before submitting them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limits {
+    /// The smallest supported circuit size (log2 of the number of rows)
+    pub min_logrows: u32,
+    /// The largest supported circuit size (log2 of the number of rows), bounded by the public SRS
+    pub max_logrows: u32,
+    /// The maximum number of axes of any tensor in the graph
+    pub max_tensor_rank: usize,
+    /// The maximum number of bits of a quantized value (excluding the sign)
+    pub max_value_bits: u32,
+    /// The number of bits of the scalar field
+    pub field_bits: u32,
+    /// The largest absolute value a lookup table input can take
+    pub max_lookup_abs: i128,
+    /// The maximum number of columns a lookup table can be split across
+    pub max_lookup_cols: usize,
+    /// The largest scale (log2 of the fixed point multiplier) at which a unit value is still representable
+    pub max_scale: crate::Scale,
+    /// The number of rows reserved for blinding at the bottom of every column
+    pub blinding_rows: usize,
+}
+
+impl Limits {
+    /// The maximum number of public elements (inputs, outputs, params and hashes combined) that
+    /// fit in the instance column at a given `logrows`.
+    pub fn max_instance_len(&self, logrows: u32) -> usize {
+        let logrows = logrows.min(self.max_logrows);
+        (1usize << logrows).saturating_sub(self.blinding_rows)
+    }
+
+    /// Checks compiled settings against the limits, returning the first violation found.
+    pub fn check(&self, settings: &GraphSettings) -> Result<(), GraphError> {
+        let logrows = settings.run_args.logrows;
+        if logrows < self.min_logrows || logrows > self.max_logrows {
+            return Err(GraphError::LimitExceeded(format!(
+                "logrows {} is outside of [{}, {}]",
+                logrows, self.min_logrows, self.max_logrows
+            )));
+        }
+
+        let scales = settings
+            .model_input_scales
+            .iter()
+            .chain(settings.model_output_scales.iter())
+            .chain([settings.run_args.input_scale, settings.run_args.param_scale].iter());
+        for scale in scales {
+            if *scale > self.max_scale {
+                return Err(GraphError::LimitExceeded(format!(
+                    "scale {} exceeds the max scale {}",
+                    scale, self.max_scale
+                )));
+            }
+        }
+
+        for shape in &settings.model_instance_shapes {
+            if shape.len() > self.max_tensor_rank {
+                return Err(GraphError::LimitExceeded(format!(
+                    "instance of shape {:?} exceeds the max rank {}",
+                    shape, self.max_tensor_rank
+                )));
+            }
+        }
+
+        let num_instances = settings.total_instances().iter().sum::<usize>();
+        let max_instances = self.max_instance_len(logrows);
+        if num_instances > max_instances {
+            return Err(GraphError::LimitExceeded(format!(
+                "{} public elements do not fit in the {} available instance rows at logrows {}",
+                num_instances, max_instances, logrows
+            )));
+        }
+
+        let (min_lookup, max_lookup) = settings.run_args.lookup_range;
+        if (max_lookup - min_lookup).abs() > self.max_lookup_abs {
+            return Err(GraphError::LimitExceeded(format!(
+                "lookup range [{}, {}] exceeds the max lookup size {}",
+                min_lookup, max_lookup, self.max_lookup_abs
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the limits of the circuits supported by this release.
+pub fn limits() -> Limits {
+    Limits {
+        min_logrows: MIN_LOGROWS,
+        max_logrows: MAX_PUBLIC_SRS,
+        max_tensor_rank: MAX_TENSOR_RANK,
+        max_value_bits: MAX_VALUE_BITS,
+        field_bits: Fr::NUM_BITS,
+        max_lookup_abs: MAX_LOOKUP_ABS,
+        max_lookup_cols: MAX_NUM_LOOKUP_COLS,
+        max_scale: MAX_VALUE_BITS as crate::Scale - 1,
+        // the instance column reserves the blinding factors plus one row, see [crate::tensor::VarTensor::max_rows]
+        blinding_rows: ASSUMED_BLINDING_FACTORS + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings_within_limits() {
+        let limits = limits();
+        let mut settings = GraphSettings::default();
+        settings.run_args.logrows = limits.min_logrows;
+        assert!(limits.check(&settings).is_ok());
+
+        settings.model_instance_shapes =
+            vec![vec![limits.max_instance_len(limits.min_logrows) + 1]];
+        assert!(limits.check(&settings).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use super::{GraphError, GraphSettings};
+use log::debug;
+use serde_json::Value;
+
+/// The schema version of [GraphSettings] written by this release.
+/// Bump it and append a step to [MIGRATIONS] whenever the layout of the settings changes.
+pub const SETTINGS_SCHEMA_VERSION: u32 = 2;
+
+type Migration = fn(&mut Value) -> Result<(), GraphError>;
+
+/// `MIGRATIONS[i]` upgrades serialized settings from schema `i` to schema `i + 1`.
+const MIGRATIONS: [Migration; SETTINGS_SCHEMA_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
+
+/// Settings written before schema versioning was introduced may be missing fields that have since
+/// been added to [GraphSettings] and [crate::RunArgs]. These are filled in with their defaults.
+fn migrate_v0_to_v1(settings: &mut Value) -> Result<(), GraphError> {
+    let defaults = serde_json::to_value(GraphSettings::default())
+        .map_err(|e| GraphError::SettingsMigration(e.to_string()))?;
+
+    fill_missing_fields(settings, &defaults);
+    if let (Some(run_args), Some(default_run_args)) =
+        (settings.get_mut("run_args"), defaults.get("run_args"))
+    {
+        fill_missing_fields(run_args, default_run_args);
+    }
+    Ok(())
+}
+
+/// The required lookups became [super::RequiredLookup]s, which can carry a domain of their own.
+/// Existing lookups keep sharing the lookup range.
+fn migrate_v1_to_v2(settings: &mut Value) -> Result<(), GraphError> {
+    if let Some(Value::Array(lookups)) = settings.get_mut("required_lookups") {
+        for lookup in lookups.iter_mut() {
+            *lookup = serde_json::json!({ "op": lookup.take() });
+        }
+    }
+    Ok(())
+}
+
+/// Inserts the top level fields of `defaults` that are absent from `value`.
+fn fill_missing_fields(value: &mut Value, defaults: &Value) {
+    if let (Value::Object(value), Value::Object(defaults)) = (value, defaults) {
+        for (key, default) in defaults {
+            if !value.contains_key(key) {
+                value.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
+
+/// Returns the schema version of serialized settings, settings predating versioning are version 0.
+pub fn settings_schema_version(settings: &Value) -> u32 {
+    settings
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32
+}
+
+/// Upgrades serialized [GraphSettings] to [SETTINGS_SCHEMA_VERSION].
+/// Fails if the settings were written by a newer release than this one.
+pub fn migrate_settings(mut settings: Value) -> Result<Value, GraphError> {
+    if !settings.is_object() {
+        return Err(GraphError::SettingsMigration(
+            "settings must be a json object".to_string(),
+        ));
+    }
+
+    let version = settings_schema_version(&settings);
+    if version > SETTINGS_SCHEMA_VERSION {
+        return Err(GraphError::UnsupportedSchemaVersion(
+            version,
+            SETTINGS_SCHEMA_VERSION,
+        ));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        debug!("migrating settings from schema v{} to v{}", i, i + 1);
+        migration(&mut settings)?;
+        settings["schema_version"] = Value::from(i as u32 + 1);
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_unversioned_settings() {
+        let mut legacy = serde_json::to_value(GraphSettings::default()).unwrap();
+        let obj = legacy.as_object_mut().unwrap();
+        obj.remove("schema_version");
+        obj.remove("num_blinding_factors");
+        obj.remove("timestamp");
+        obj["run_args"]
+            .as_object_mut()
+            .unwrap()
+            .remove("commitment");
+
+        assert_eq!(settings_schema_version(&legacy), 0);
+
+        let migrated = migrate_settings(legacy).unwrap();
+        assert_eq!(settings_schema_version(&migrated), SETTINGS_SCHEMA_VERSION);
+
+        let settings: GraphSettings = serde_json::from_value(migrated).unwrap();
+        assert_eq!(settings.run_args, GraphSettings::default().run_args);
+    }
+
+    #[test]
+    fn test_reject_newer_settings() {
+        let mut settings = serde_json::to_value(GraphSettings::default()).unwrap();
+        settings["schema_version"] = Value::from(SETTINGS_SCHEMA_VERSION + 1);
+        assert!(migrate_settings(settings).is_err());
+    }
+}
@@ -1,15 +1,64 @@
+/// Self-contained witness assignments, to prove on another machine than the witness is generated on.
+pub mod assignment;
+/// Correction of quantized biases from calibration data statistics.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub mod bias_correction;
+/// Registry of user supplied implementations for custom onnx ops.
+pub mod custom;
+/// Loading of tabular (csv / parquet) calibration datasets.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dataset;
+/// Per op lookup table domains, chosen during calibration.
+pub mod domains;
+/// Encryption of serialized models at rest.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod encryption;
+/// Acceptance checks of the outputs against reference outputs of the original model.
+pub mod expected;
+/// Fusion of attention blocks into a single macro-op.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fusion;
 /// Representations of a computational graph's inputs.
 pub mod input;
+/// Hard limits of the generated circuits
+pub mod limits;
+/// Versioned migrations for serialized settings.
+pub mod migrate;
 /// Crate for defining a computational graph and building a ZK-circuit from it.
 pub mod model;
 /// Representations of a computational graph's modules.
 pub mod modules;
 /// Inner elements of a computational graph that represent a single operation / constraints.
 pub mod node;
+/// Rewriting of newer opset nodes into the form the loader expects.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub mod opset;
+/// Proof-carrying responses for inference APIs.
+pub mod response;
+/// Limits on untrusted json inputs and witnesses.
+pub mod sanitize;
+/// Erasure of private witness material once it has been proven.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scrub;
+/// Encryption of model outputs to an external key, for delayed reveals.
+pub mod sealed;
+/// Field-wise comparison of settings, telling which differences need new keys.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod settings_diff;
+/// Gathers with constant indices laid out in the shuffle columns.
+pub mod shuffle;
+/// Witness generation over streams of inputs.
+pub mod stream;
+/// Small ready-made models for common tasks, with example data and run args.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub mod templates;
 /// Helper functions
 pub mod utilities;
 /// Representations of a computational graph's variables.
 pub mod vars;
+/// Element-wise comparison of witnesses, to track down nondeterminism.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod witness_diff;
 #[cfg(not(target_arch = "wasm32"))]
 use colored_json::ToColoredJson;
 #[cfg(unix)]
@@ -18,9 +67,10 @@ use halo2_proofs::plonk::VerifyingKey;
 use halo2_proofs::poly::commitment::CommitmentScheme;
 pub use input::DataSource;
 use itertools::Itertools;
+pub use migrate::SETTINGS_SCHEMA_VERSION;
 use tosubcommand::ToFlags;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
 use self::input::OnChainSource;
 use self::input::{FileSource, GraphData};
 use self::modules::{GraphModules, ModuleConfigs, ModuleForwardResult, ModuleSizes};
@@ -28,7 +78,8 @@ use crate::circuit::lookup::LookupOp;
 use crate::circuit::modules::ModulePlanner;
 use crate::circuit::table::{num_cols_required, Range, Table, RESERVED_BLINDING_ROWS_PAD};
 use crate::circuit::{CheckMode, InputType};
-use crate::fieldutils::felt_to_f64;
+use crate::fieldutils::{felt_to_f64, felt_to_i128};
+use crate::pfsys::key_header::KeyParams;
 use crate::pfsys::PrettyElements;
 use crate::tensor::{Tensor, ValTensor};
 use crate::{RunArgs, EZKL_BUF_CAPACITY};
@@ -52,6 +103,7 @@ use pyo3::types::PyDict;
 #[cfg(feature = "python-bindings")]
 use pyo3::ToPyObject;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::ops::Deref;
 use thiserror::Error;
 pub use utilities::*;
@@ -137,6 +189,38 @@ pub enum GraphError {
     /// Missing results
     #[error("missing results")]
     MissingResults,
+    /// Settings were written by a newer release
+    #[error(
+        "settings schema version {0} is newer than the supported version {1}, please upgrade ezkl"
+    )]
+    UnsupportedSchemaVersion(u32, u32),
+    /// Settings could not be migrated to the current schema
+    #[error("failed to migrate settings: {0}")]
+    SettingsMigration(String),
+    /// Models could not be composed
+    #[error("cannot stitch models: {0}")]
+    ModelStitching(String),
+    /// A model or its settings exceed what the circuit can support
+    #[error("limit exceeded: {0}")]
+    LimitExceeded(String),
+    /// A custom op was used without registering an implementation for it
+    #[error("custom op {0} is not registered, register it with Model::register_custom_op")]
+    MissingCustomOp(String),
+    /// A model could not be encrypted or decrypted
+    #[error("model encryption error: {0}")]
+    Encryption(String),
+    /// A tabular dataset could not be mapped to the model inputs
+    #[error("failed to load dataset: {0}")]
+    Dataset(String),
+    /// Outputs could not be sealed to, or unsealed with, an external key
+    #[error("output sealing error: {0}")]
+    Sealing(String),
+    /// A tensor has a zero length dimension
+    #[error("zero length dimensions are not supported: {0}")]
+    ZeroLengthDim(String),
+    /// Untrusted json exceeded the limits it is parsed with
+    #[error("rejected untrusted json: {0}")]
+    UntrustedJson(String),
 }
 
 ///
@@ -179,6 +263,24 @@ pub struct GraphWitness {
     pub min_lookup_inputs: i128,
     /// max range check size
     pub max_range_size: i128,
+    /// min and max inputs of each lookup op, which calibration picks their domains from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lookup_input_ranges: Vec<(LookupOp, Range)>,
+    /// non fatal issues raised while generating the witness
+    #[serde(default)]
+    pub warnings: Vec<crate::warnings::Warning>,
+    /// The outputs encrypted to an external key, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_outputs: Option<sealed::SealedOutputs>,
+    /// The address proofs are bound to, see [crate::RunArgs::bind_caller]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<Fp>,
+    /// The outputs the original model is expected to produce, carried over from the input data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_outputs: Option<Vec<Vec<f64>>>,
+    /// Proofs of the storage backing on-chain inputs, if the data source requested them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_proof: Option<input::BlockStateProof>,
 }
 
 impl GraphWitness {
@@ -207,6 +309,12 @@ impl GraphWitness {
             max_lookup_inputs: 0,
             min_lookup_inputs: 0,
             max_range_size: 0,
+            lookup_input_ranges: vec![],
+            warnings: vec![],
+            sealed_outputs: None,
+            caller: None,
+            expected_outputs: None,
+            state_proof: None,
         }
     }
 
@@ -315,13 +423,21 @@ impl GraphWitness {
         Ok(serialized)
     }
 
-    /// Load the model input from a file
+    /// Load the witness from a file, within the default [sanitize::JsonLimits]
     pub fn from_path(path: std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let file = std::fs::File::open(path.clone())
-            .map_err(|_| format!("failed to load {}", path.display()))?;
+        let limits = sanitize::JsonLimits::default();
+        let bytes = sanitize::read_json(&path, &limits)?;
+        Self::from_untrusted(&bytes, &limits)
+    }
 
-        let reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
-        serde_json::from_reader(reader).map_err(|e| e.into())
+    /// Parse an untrusted witness, rejecting payloads exceeding the `limits` or repeating keys
+    /// before they are deserialized, see [sanitize]
+    pub fn from_untrusted(
+        bytes: &[u8],
+        limits: &sanitize::JsonLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        sanitize::check_json(bytes, limits)?;
+        Ok(serde_json::from_slice(bytes)?)
     }
 
     /// Save the model input to a file
@@ -440,6 +556,48 @@ fn insert_polycommit_pydict(pydict: &PyDict, commits: &Vec<Vec<G1Affine>>) -> Re
     Ok(())
 }
 
+/// A contiguous run of elements in the flattened public instances of a proof.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct InstanceSegment {
+    /// name of the segment, e.g `input_0`, `param_hashes` or `output_hash_1`
+    pub name: String,
+    /// shape of the segment
+    pub shape: Vec<usize>,
+    /// fixed point scale of the elements, `None` for hashes
+    pub scale: Option<crate::Scale>,
+    /// index of the first element of the segment in the flattened instances
+    pub offset: usize,
+}
+
+impl InstanceSegment {
+    /// number of field elements in the segment
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// whether the segment is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A lookup table the circuit requires.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequiredLookup {
+    /// the op the table is for
+    pub op: LookupOp,
+    /// the input range of the table, `None` for the shared [crate::RunArgs::lookup_range].
+    /// Ops with the same domain share the input columns of their tables, see [domains]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<Range>,
+}
+
+impl From<LookupOp> for RequiredLookup {
+    fn from(op: LookupOp) -> Self {
+        RequiredLookup { op, domain: None }
+    }
+}
+
 /// model parameters
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct GraphSettings {
@@ -468,20 +626,43 @@ pub struct GraphSettings {
     /// the of instance cells used by modules
     pub module_sizes: ModuleSizes,
     /// required_lookups
-    pub required_lookups: Vec<LookupOp>,
+    pub required_lookups: Vec<RequiredLookup>,
     /// required range_checks
     pub required_range_checks: Vec<Range>,
     /// check mode
     pub check_mode: CheckMode,
     /// ezkl version used
     pub version: String,
+    /// schema version of the serialized settings, see [migrate::SETTINGS_SCHEMA_VERSION]
+    #[serde(default)]
+    pub schema_version: u32,
     /// num blinding factors
     pub num_blinding_factors: Option<usize>,
     /// unix time timestamp
     pub timestamp: Option<u128>,
+    /// non fatal issues raised while loading the model and generating settings
+    #[serde(default)]
+    pub warnings: Vec<crate::warnings::Warning>,
+    /// float corrections added to bias constants (keyed by node index) when the circuit is
+    /// compiled, found by calibrating with `--bias-correction`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bias_corrections: BTreeMap<usize, Vec<f32>>,
+    /// the Poseidon hash of the params, expected as the `model_hash` instance of proofs when
+    /// [crate::RunArgs::expose_model_hash] is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_hash: Option<Fp>,
 }
 
 impl GraphSettings {
+    /// The input range of the lookup table for `op`
+    pub fn lookup_domain(&self, op: &LookupOp) -> Range {
+        self.required_lookups
+            .iter()
+            .find(|l| &l.op == op)
+            .and_then(|l| l.domain)
+            .unwrap_or(self.run_args.lookup_range)
+    }
+
     fn model_constraint_logrows(&self) -> u32 {
         (self.num_rows as f64 + RESERVED_BLINDING_ROWS as f64)
             .log2()
@@ -516,6 +697,12 @@ impl GraphSettings {
             .map(|x| x.iter().product())
             .collect();
         instances.extend(self.module_sizes.num_instances());
+        if self.run_args.bind_caller {
+            instances.push(1);
+        }
+        if self.run_args.seal_outputs {
+            instances.push(self.module_sizes.sealed_instances());
+        }
 
         instances
     }
@@ -543,12 +730,29 @@ impl GraphSettings {
         // buf reader
         let reader =
             std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::open(path)?);
-        serde_json::from_reader(reader).map_err(|e| {
+        let value: serde_json::Value = serde_json::from_reader(reader).map_err(|e| {
+            error!("failed to load settings file at {}", e);
+            std::io::Error::new(std::io::ErrorKind::Other, e)
+        })?;
+        Self::from_value(value).map_err(|e| {
             error!("failed to load settings file at {}", e);
             std::io::Error::new(std::io::ErrorKind::Other, e)
         })
     }
 
+    /// Deserialize settings, migrating them from older schema versions if necessary
+    pub fn from_value(value: serde_json::Value) -> Result<Self, serde_json::Error> {
+        let version = migrate::settings_schema_version(&value);
+        let value = migrate::migrate_settings(value).map_err(serde::de::Error::custom)?;
+        if version < SETTINGS_SCHEMA_VERSION {
+            warn!(
+                "settings were written with schema v{}, migrated to v{}. run `ezkl migrate` to update the file",
+                version, SETTINGS_SCHEMA_VERSION
+            );
+        }
+        serde_json::from_value(value)
+    }
+
     /// Export the ezkl configuration as json
     pub fn as_json(&self) -> Result<String, Box<dyn std::error::Error>> {
         let serialized = match serde_json::to_string(&self) {
@@ -561,13 +765,164 @@ impl GraphSettings {
     }
     /// Parse an ezkl configuration from a json
     pub fn from_json(arg_json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str(arg_json)
+        Self::from_value(serde_json::from_str(arg_json)?)
     }
 
     fn set_num_blinding_factors(&mut self, num_blinding_factors: usize) {
         self.num_blinding_factors = Some(num_blinding_factors);
     }
 
+    /// Describes how the public instances of a proof are laid out, in the order they are
+    /// produced by [GraphCircuit::prepare_public_inputs]. Each segment is only present when its
+    /// visibility calls for it:
+    ///
+    /// 1. `input_{i}` or `input_hash_{i}`, the public inputs or their hashes
+    /// 2. `param_hashes`, or the single `model_hash` when [crate::RunArgs::expose_model_hash] is
+    ///    set, which is therefore the first instance of proofs with private inputs
+    /// 3. `output_{i}`, the public outputs
+    /// 4. `valid_count`, see [crate::RunArgs::mask_output]
+    /// 5. `output_hash_{i}`, the hashes of hashed public outputs
+    /// 6. `caller`, see [crate::RunArgs::bind_caller]
+    /// 7. `sealing_public_key`, `sealing_c1` and `sealed_outputs`, the limbs of the public key
+    ///    and of the ephemeral key followed by the masked outputs, see
+    ///    [crate::RunArgs::seal_outputs]
+    pub fn instance_layout(&self) -> Vec<InstanceSegment> {
+        let run_args = &self.run_args;
+        let num_inputs = self.model_input_scales.len();
+        let num_outputs = self.model_output_scales.len();
+        // the outputs hashed, all of them unless the visibility names some
+        let hashed_outputs = match run_args.output_visibility.overwrites_inputs() {
+            outlets if outlets.is_empty() => (0..num_outputs).collect(),
+            outlets => outlets,
+        };
+
+        let mut segments: Vec<InstanceSegment> = vec![];
+        let mut push = |name: String, shape: Vec<usize>, scale: Option<crate::Scale>| {
+            let offset = segments.last().map(|s| s.offset + s.len()).unwrap_or(0);
+            segments.push(InstanceSegment {
+                name,
+                shape,
+                scale,
+                offset,
+            });
+        };
+
+        // public model inputs and outputs share `model_instance_shapes`, inputs first
+        let mut model_shapes = self.model_instance_shapes.iter();
+
+        if run_args.input_visibility.is_public() {
+            for (i, scale) in self.model_input_scales.iter().enumerate() {
+                if let Some(shape) = model_shapes.next() {
+                    push(format!("input_{}", i), shape.clone(), Some(*scale));
+                }
+            }
+        } else if run_args.input_visibility.is_hashed_public() {
+            for i in 0..num_inputs {
+                push(format!("input_hash_{}", i), vec![1], None);
+            }
+        }
+
+        if run_args.param_visibility.is_hashed_public() {
+            // one hash per hashed tensor, params account for whatever the inputs and outputs don't
+            let num_hashes = self.module_sizes.num_instances().iter().sum::<usize>();
+            let mut num_param_hashes = num_hashes;
+            if run_args.input_visibility.is_hashed() {
+                num_param_hashes = num_param_hashes.saturating_sub(num_inputs);
+            }
+            if run_args.output_visibility.is_hashed() {
+                num_param_hashes = num_param_hashes.saturating_sub(hashed_outputs.len());
+            }
+            // the params are flattened and hashed as one, so this is the hash of the model
+            let name = if run_args.expose_model_hash {
+                "model_hash"
+            } else {
+                "param_hashes"
+            };
+            push(name.to_string(), vec![num_param_hashes], None);
+        }
+
+        for (i, scale) in self.model_output_scales.iter().enumerate() {
+            if run_args.output_is_public(i) {
+                if let Some(shape) = model_shapes.next() {
+                    push(format!("output_{}", i), shape.clone(), Some(*scale));
+                }
+            }
+        }
+
+        if run_args.mask_output.is_some() {
+            push("valid_count".to_string(), vec![1], None);
+        }
+
+        if run_args.output_visibility.is_hashed_public() {
+            for i in hashed_outputs {
+                push(format!("output_hash_{}", i), vec![1], None);
+            }
+        }
+
+        if run_args.bind_caller {
+            push("caller".to_string(), vec![1], None);
+        }
+
+        if run_args.seal_outputs {
+            let limbs = 2 * crate::circuit::modules::elgamal::LIMBS;
+            push("sealing_public_key".to_string(), vec![limbs], None);
+            push("sealing_c1".to_string(), vec![limbs], None);
+            push(
+                "sealed_outputs".to_string(),
+                vec![self.module_sizes.sealed_instances() - 2 * limbs],
+                None,
+            );
+        }
+
+        segments
+    }
+
+    /// The number of valid slots marked by the validity mask, read from the public instances of a
+    /// proof, see [crate::RunArgs::mask_output]. The valid slots are the first `count` slots of the
+    /// flattened padded outputs.
+    pub fn valid_count(
+        &self,
+        instances: &[Fp],
+    ) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        let Some(segment) = self
+            .instance_layout()
+            .into_iter()
+            .find(|s| s.name == "valid_count")
+        else {
+            return Ok(None);
+        };
+        let count = instances
+            .get(segment.offset)
+            .ok_or("the proof has fewer instances than the circuit")?;
+        Ok(Some(felt_to_i128(*count) as usize))
+    }
+
+    /// Checks the `model_hash` instance of a proof is the hash of the params these settings were
+    /// generated with, see [crate::RunArgs::expose_model_hash].
+    pub fn check_model_hash(&self, instances: &[Fp]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(expected) = self.model_hash else {
+            return Ok(());
+        };
+        let Some(segment) = self
+            .instance_layout()
+            .into_iter()
+            .find(|s| s.name == "model_hash")
+        else {
+            return Ok(());
+        };
+        let found = instances
+            .get(segment.offset)
+            .ok_or("the proof has fewer instances than the circuit")?;
+        if *found != expected {
+            return Err(format!(
+                "the proof was generated for model hash {:?} but the settings expect {:?}",
+                found, expected
+            )
+            .into());
+        }
+        Ok(())
+    }
+
     ///
     pub fn available_col_size(&self) -> usize {
         let base = 2u32;
@@ -575,7 +930,10 @@ impl GraphSettings {
             base.pow(self.run_args.logrows) as usize - num_blinding_factors - 1
         } else {
             log::error!("num_blinding_factors not set");
-            log::warn!("using default available_col_size");
+            crate::warnings::emit(
+                crate::warnings::WarningKind::DefaultUsed,
+                "num_blinding_factors not set, using default available_col_size",
+            );
             base.pow(self.run_args.logrows) as usize - ASSUMED_BLINDING_FACTORS - 1
         }
     }
@@ -590,6 +948,7 @@ impl GraphSettings {
         self.run_args.input_visibility.is_hashed()
             || self.run_args.output_visibility.is_hashed()
             || self.run_args.param_visibility.is_hashed()
+            || self.run_args.seal_outputs
     }
 
     /// requires dynamic lookup
@@ -610,6 +969,36 @@ impl GraphSettings {
     }
 }
 
+impl KeyParams for GraphSettings {
+    fn logrows(&self) -> Option<u32> {
+        Some(self.run_args.logrows)
+    }
+
+    /// The SHA256 hash of the settings, leaving out provenance and runtime options that don't
+    /// change the circuit.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn settings_hash(&self) -> Option<String> {
+        let mut settings = self.clone();
+        settings.version = String::new();
+        settings.timestamp = None;
+        settings.warnings = vec![];
+        settings.num_blinding_factors = None;
+        settings.check_mode = CheckMode::default();
+        // a mismatched commitment is caught by halo2 when the keys are read
+        settings.run_args.commitment = Default::default();
+        settings.run_args.num_threads = None;
+        settings.run_args.assignment_order = Default::default();
+        // a circuit whose params are hashed serves any weights of the same shapes
+        settings.model_hash = None;
+        serde_json::to_vec(&settings).ok().map(sha256::digest)
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    fn settings_hash(&self) -> Option<String> {
+        None
+    }
+}
+
 /// Configuration for a computational graph / model loaded from a `.onnx` file.
 #[derive(Clone, Debug)]
 pub struct GraphConfig {
@@ -634,6 +1023,9 @@ pub struct GraphCircuit {
     pub core: CoreCircuit,
     /// The witness data for the model.
     pub graph_witness: GraphWitness,
+    /// Whether the witness is zeroized when the circuit is dropped, see [GraphCircuit::scrub]
+    #[serde(skip)]
+    pub scrub: bool,
 }
 
 impl GraphCircuit {
@@ -661,13 +1053,89 @@ impl GraphCircuit {
     pub fn load(path: std::path::PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         // read bytes from file
         let f = std::fs::File::open(path)?;
-        let reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
-        let result: GraphCircuit = bincode::deserialize_from(reader)?;
+        let mut reader = std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
+        #[cfg(not(target_arch = "wasm32"))]
+        if encryption::is_encrypted(std::io::BufRead::fill_buf(&mut reader)?) {
+            return Err(GraphError::Encryption(
+                "the compiled circuit is encrypted, pass its key with --encryption-key".to_string(),
+            )
+            .into());
+        }
+        let result: GraphCircuit = bincode::deserialize_from(reader).map_err(|e| {
+            format!(
+                "failed to load compiled circuit ({}), it may have been produced by a different ezkl release. re-run `ezkl compile-circuit` with this version",
+                e
+            )
+        })?;
+
+        Ok(result)
+    }
+
+    /// Saves the compiled circuit (which embeds the model's constants) encrypted with `key`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_encrypted(
+        &self,
+        path: std::path::PathBuf,
+        key: &encryption::ModelKey,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let serialized = bincode::serialize(&self)?;
+        std::fs::write(path, encryption::encrypt(&serialized, key)?)?;
+        Ok(())
+    }
 
+    /// Loads a compiled circuit, decrypting it with `key` if one is passed. Encrypted circuits
+    /// are only decrypted in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_with_key(
+        path: std::path::PathBuf,
+        key: Option<&encryption::ModelKey>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let key = match key {
+            Some(key) => key,
+            None => return Self::load(path),
+        };
+        let buffer = encryption::decrypt(&std::fs::read(path)?, key)?;
+        let result: GraphCircuit = bincode::deserialize(&buffer)?;
         Ok(result)
     }
 }
 
+/// Reshapes the data supplied for a model input, with scalars supplied as a single element.
+fn reshape_input(
+    t: &mut Tensor<Fp>,
+    shape: &[usize],
+    input_idx: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shape = normalize_dims(shape, &format!("input {}", input_idx))?;
+    let expected = shape.iter().product::<usize>();
+    if t.len() != expected {
+        return Err(Box::new(GraphError::InvalidDims(
+            input_idx,
+            format!(
+                "input {} has {} elements but the model expects shape {:?}",
+                input_idx,
+                t.len(),
+                shape
+            ),
+        )));
+    }
+    t.reshape(&shape)?;
+    Ok(())
+}
+
+/// The number of valid slots marked by a validity mask, see [crate::RunArgs::mask_output]. Errors
+/// if the mask isn't boolean or a valid slot follows the padding, as the circuit would reject it.
+pub fn count_valid_slots(mask: &[Fp]) -> Result<usize, Box<dyn std::error::Error>> {
+    if mask.iter().any(|x| *x != Fp::zero() && *x != Fp::one()) {
+        return Err("the validity mask must only hold 0s and 1s".into());
+    }
+    let count = mask.iter().take_while(|x| **x == Fp::one()).count();
+    if mask[count..].iter().any(|x| *x == Fp::one()) {
+        return Err("the valid slots of the validity mask must come before its padding".into());
+    }
+    Ok(count)
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, PartialOrd)]
 /// The data source for a test
 pub enum TestDataSource {
@@ -696,7 +1164,10 @@ impl From<String> for TestDataSource {
             "on-chain" => TestDataSource::OnChain,
             _ => {
                 error!("invalid data source: {}", value);
-                warn!("using default data source: on-chain");
+                crate::warnings::emit(
+                    crate::warnings::WarningKind::DefaultUsed,
+                    "using default data source: on-chain",
+                );
                 TestDataSource::default()
             }
         }
@@ -729,6 +1200,8 @@ impl GraphCircuit {
         model: Model,
         run_args: &RunArgs,
     ) -> Result<GraphCircuit, Box<dyn std::error::Error>> {
+        let run_args = &run_args.resolve_visibility();
+
         // // placeholder dummy inputs - must call prepare_public_inputs to load data afterwards
         let mut inputs: Vec<Vec<Fp>> = vec![];
         for shape in model.graph.input_shapes()? {
@@ -736,9 +1209,15 @@ impl GraphCircuit {
             inputs.push(t);
         }
 
+        run_args.validate()?;
+
         // dummy module settings, must load from GraphData after
         let mut settings = model.gen_params(run_args, run_args.check_mode)?;
 
+        if run_args.expose_model_hash {
+            settings.model_hash = model.params_hash(run_args.hash_chunk_size)?;
+        }
+
         let mut num_params = 0;
         if !model.const_shapes().is_empty() {
             for shape in model.const_shapes() {
@@ -751,6 +1230,8 @@ impl GraphCircuit {
             vec![vec![num_params]],
             model.graph.output_shapes()?,
             VarVisibility::from_args(run_args)?,
+            run_args.hash_chunk_size,
+            run_args.seal_outputs,
         );
 
         // number of instances used by modules
@@ -767,6 +1248,7 @@ impl GraphCircuit {
         Ok(GraphCircuit {
             core,
             graph_witness: GraphWitness::new(inputs, vec![]),
+            scrub: false,
         })
     }
 
@@ -795,6 +1277,7 @@ impl GraphCircuit {
         Ok(GraphCircuit {
             core,
             graph_witness: GraphWitness::new(inputs, vec![]),
+            scrub: false,
         })
     }
 
@@ -826,10 +1309,38 @@ impl GraphCircuit {
             public_inputs.extend(processed_params.get_instances().into_iter().flatten());
         }
 
-        if self.settings().run_args.output_visibility.is_public() {
-            public_inputs.extend(self.graph_witness.outputs.clone().into_iter().flatten());
-        } else if let Some(processed_outputs) = &data.processed_outputs {
-            public_inputs.extend(processed_outputs.get_instances().into_iter().flatten());
+        for (i, output) in self.graph_witness.outputs.iter().enumerate() {
+            if self.settings().run_args.output_is_public(i) {
+                public_inputs.extend(output.iter().cloned());
+            }
+        }
+
+        if let Some(idx) = self.settings().run_args.mask_output {
+            let output = data.outputs.get(idx).ok_or(format!(
+                "the witness has no output {} to use as a validity mask",
+                idx
+            ))?;
+            public_inputs.push(Fp::from(count_valid_slots(output)? as u64));
+        }
+
+        if !self.settings().run_args.output_visibility.is_public() {
+            if let Some(processed_outputs) = &data.processed_outputs {
+                public_inputs.extend(processed_outputs.get_instances().into_iter().flatten());
+            }
+        }
+
+        // the caller isn't constrained by any gate, the transcript alone binds the proof to it
+        if self.settings().run_args.bind_caller {
+            public_inputs.push(data.caller.ok_or(
+                "the circuit binds proofs to a caller, set one with gen-witness --caller",
+            )?);
+        }
+
+        if self.settings().run_args.seal_outputs {
+            let sealed = data.sealed_outputs.as_ref().ok_or(
+                "the circuit seals its outputs, set a public key with gen-witness --seal-outputs-to",
+            )?;
+            public_inputs.extend(sealed.instances()?);
         }
 
         if public_inputs.len() < 11 {
@@ -851,7 +1362,10 @@ impl GraphCircuit {
         // as they are configured in that order as Column<Instances>
 
         if data.pretty_elements.is_none() {
-            warn!("no rescaled elements found in witness data");
+            crate::warnings::emit(
+                crate::warnings::WarningKind::DefaultUsed,
+                "no rescaled elements found in witness data",
+            );
             return Ok(None);
         }
 
@@ -869,10 +1383,18 @@ impl GraphCircuit {
             public_inputs.processed_params = elements.processed_params.clone();
         }
 
-        if self.settings().run_args.output_visibility.is_public() {
-            public_inputs.rescaled_outputs = elements.rescaled_outputs.clone();
-            public_inputs.outputs = elements.outputs.clone();
-        } else if data.processed_outputs.is_some() {
+        let run_args = &self.settings().run_args;
+        let public = |outputs: &Vec<Vec<String>>| {
+            outputs
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| run_args.output_is_public(*i))
+                .map(|(_, o)| o.clone())
+                .collect::<Vec<_>>()
+        };
+        public_inputs.rescaled_outputs = public(&elements.rescaled_outputs);
+        public_inputs.outputs = public(&elements.outputs);
+        if !run_args.output_visibility.is_public() && data.processed_outputs.is_some() {
             public_inputs.processed_outputs = elements.processed_outputs.clone();
         }
 
@@ -959,6 +1481,7 @@ impl GraphCircuit {
         input_types: Vec<InputType>,
     ) -> Result<Vec<Tensor<Fp>>, Box<dyn std::error::Error>> {
         match &data {
+            #[cfg(feature = "evm")]
             DataSource::OnChain(source) => {
                 let mut per_item_scale = vec![];
                 for (i, shape) in shapes.iter().enumerate() {
@@ -967,6 +1490,8 @@ impl GraphCircuit {
                 self.load_on_chain_data(source.clone(), &shapes, per_item_scale)
                     .await
             }
+            #[cfg(not(feature = "evm"))]
+            DataSource::OnChain(_) => Err("on-chain data sources require the evm feature".into()),
             DataSource::File(file_data) => {
                 self.load_file_data(file_data, &shapes, scales, input_types)
             }
@@ -978,18 +1503,104 @@ impl GraphCircuit {
     }
 
     /// Prepare on chain test data
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
     pub async fn load_on_chain_data(
         &mut self,
-        source: OnChainSource,
+        mut source: OnChainSource,
         shapes: &Vec<Vec<usize>>,
         scales: Vec<crate::Scale>,
     ) -> Result<Vec<Tensor<Fp>>, Box<dyn std::error::Error>> {
-        use crate::eth::{evm_quantize, read_on_chain_inputs, setup_eth_backend};
-        let (_, client) = setup_eth_backend(Some(&source.rpc), None).await?;
-        let inputs = read_on_chain_inputs(client.clone(), client.address(), &source.calls).await?;
-        // quantize the supplied data using the provided scale + QuantizeData.sol
-        let quantized_evm_inputs = evm_quantize(client, scales, &inputs).await?;
+        use crate::eth::{
+            evm_quantize, read_on_chain_inputs_with_quorum, setup_eth_backend, QuorumRead,
+        };
+        use input::{OnChainSnapshot, SnapshotCall};
+
+        let snapshot_path = source.snapshot.as_ref().map(std::path::PathBuf::from);
+        let replay = snapshot_path
+            .as_ref()
+            .filter(|p| source.replay_snapshot && p.exists());
+
+        let quantized_evm_inputs = if let Some(path) = replay {
+            let snapshot = OnChainSnapshot::from_path(path)?;
+            // the snapshot is matched on the calls made, so replaying it needs no rpc to resolve
+            // the decimals of the contract calls
+            snapshot.check(&source.encoded_calls()?, &scales)?;
+            if let Some(block) = source.block_number.filter(|b| *b != snapshot.block_number) {
+                return Err(format!(
+                    "on-chain snapshot was read at block {}, but the reads are pinned to block {}",
+                    snapshot.block_number, block
+                )
+                .into());
+            }
+            warn!(
+                "replaying on-chain reads made at block {} from snapshot {}",
+                snapshot.block_number,
+                path.display()
+            );
+            snapshot.quantized
+        } else {
+            source.resolve_contract_calls().await?;
+            // every read is pinned to the same block so the rpcs and the snapshot are consistent
+            let QuorumRead {
+                rpc,
+                client,
+                block_number,
+                inputs,
+            } = read_on_chain_inputs_with_quorum(
+                &source.rpcs(),
+                &source.calls,
+                source.block_number.map(Into::into),
+                source.quorum,
+                source.retries,
+                std::time::Duration::from_millis(source.retry_backoff_ms),
+            )
+            .await?;
+            // websocket rpcs are only read from, so their reads are quantized on a local anvil
+            let (_anvil, client) = match client {
+                Some(client) => (None, client),
+                None => {
+                    let (anvil, client) = setup_eth_backend(None, None).await?;
+                    (Some(anvil), client)
+                }
+            };
+            // quantize the supplied data using the provided scale + QuantizeData.sol
+            let is_unsigned = source
+                .calls
+                .iter()
+                .flat_map(|account| account.unsigned_calls())
+                .collect::<Vec<_>>();
+            let quantized = evm_quantize(client, scales.clone(), &inputs, &is_unsigned).await?;
+
+            if let Some(path) = &snapshot_path {
+                let mut calls = vec![];
+                let mut results = inputs.0.iter();
+                for account in &source.calls {
+                    for ((call_data, decimals), is_unsigned) in
+                        account.call_data.iter().zip(account.unsigned_calls())
+                    {
+                        calls.push(SnapshotCall {
+                            address: account.address.clone(),
+                            call_data: call_data.clone(),
+                            call_hash: OnChainSnapshot::call_hash(&account.address, call_data)?,
+                            decimals: *decimals,
+                            is_unsigned,
+                            result: hex::encode(results.next().ok_or("missing on-chain read")?),
+                        });
+                    }
+                }
+                OnChainSnapshot {
+                    rpc,
+                    block_number: block_number.as_u64(),
+                    calls,
+                    scales,
+                    quantized: quantized.clone(),
+                    state_proof: None,
+                }
+                .save(path)?;
+                debug!("snapshotted on-chain reads to {}", path.display());
+            }
+            quantized
+        };
         // on-chain data has already been quantized at this point. Just need to reshape it and push into tensor vector
         let mut inputs: Vec<Tensor<Fp>> = vec![];
         for (input, shape) in [quantized_evm_inputs].iter().zip(shapes) {
@@ -1003,7 +1614,7 @@ impl GraphCircuit {
 
     ///
     pub fn load_file_data(
-        &mut self,
+        &self,
         file_data: &FileSource,
         shapes: &Vec<Vec<usize>>,
         scales: Vec<crate::Scale>,
@@ -1027,7 +1638,7 @@ impl GraphCircuit {
                 .collect();
 
             let mut t: Tensor<Fp> = t.into_iter().into();
-            t.reshape(shape)?;
+            reshape_input(&mut t, shape, data.len())?;
 
             data.push(t);
         }
@@ -1044,7 +1655,7 @@ impl GraphCircuit {
         let mut data: Vec<Tensor<Fp>> = vec![];
         for (d, shape) in file_data.iter().zip(shapes) {
             let mut t: Tensor<Fp> = d.clone().into_iter().into();
-            t.reshape(shape)?;
+            reshape_input(&mut t, shape, data.len())?;
             data.push(t);
         }
         Ok(data)
@@ -1241,12 +1852,26 @@ impl GraphCircuit {
         vk: Option<&VerifyingKey<G1Affine>>,
         srs: Option<&Scheme::ParamsProver>,
         throw_range_check_error: bool,
+    ) -> Result<GraphWitness, Box<dyn std::error::Error>> {
+        let context = stream::ForwardContext::new::<Scheme>(self, vk, srs)?;
+        self.forward_with_context::<Scheme>(&context, inputs, vk, srs, throw_range_check_error)
+    }
+
+    /// Runs the forward pass with the input independent state of `context`, see [Self::forward].
+    pub(crate) fn forward_with_context<Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>>(
+        &self,
+        context: &stream::ForwardContext,
+        inputs: &mut [Tensor<Fp>],
+        vk: Option<&VerifyingKey<G1Affine>>,
+        srs: Option<&Scheme::ParamsProver>,
+        throw_range_check_error: bool,
     ) -> Result<GraphWitness, Box<dyn std::error::Error>> {
         let original_inputs = inputs.to_vec();
 
-        let visibility = VarVisibility::from_args(&self.settings().run_args)?;
+        let visibility = context.visibility.clone();
+        let hash_chunk_size = self.settings().run_args.hash_chunk_size;
         let mut processed_inputs = None;
-        let mut processed_params = None;
+        let processed_params = context.processed_params.clone();
         let mut processed_outputs = None;
 
         if visibility.input.requires_processing() {
@@ -1256,8 +1881,13 @@ impl GraphCircuit {
                 for outlet in &module_outlets {
                     module_inputs.push(inputs[*outlet].clone());
                 }
-                let res =
-                    GraphModules::forward::<Scheme>(&module_inputs, &visibility.input, vk, srs)?;
+                let res = GraphModules::forward::<Scheme>(
+                    &module_inputs,
+                    &visibility.input,
+                    hash_chunk_size,
+                    vk,
+                    srs,
+                )?;
                 processed_inputs = Some(res.clone());
                 let module_results = res.get_result(visibility.input.clone());
 
@@ -1268,19 +1898,7 @@ impl GraphCircuit {
                 processed_inputs = Some(GraphModules::forward::<Scheme>(
                     inputs,
                     &visibility.input,
-                    vk,
-                    srs,
-                )?);
-            }
-        }
-
-        if visibility.params.requires_processing() {
-            let params = self.model().get_all_params();
-            if !params.is_empty() {
-                let flattened_params = Tensor::new(Some(&params), &[params.len()])?.combine()?;
-                processed_params = Some(GraphModules::forward::<Scheme>(
-                    &[flattened_params],
-                    &visibility.params,
+                    hash_chunk_size,
                     vk,
                     srs,
                 )?);
@@ -1298,8 +1916,13 @@ impl GraphCircuit {
                 for outlet in &module_outlets {
                     module_inputs.push(model_results.outputs[*outlet].clone());
                 }
-                let res =
-                    GraphModules::forward::<Scheme>(&module_inputs, &visibility.output, vk, srs)?;
+                let res = GraphModules::forward::<Scheme>(
+                    &module_inputs,
+                    &visibility.output,
+                    hash_chunk_size,
+                    vk,
+                    srs,
+                )?;
                 processed_outputs = Some(res.clone());
                 let module_results = res.get_result(visibility.output.clone());
 
@@ -1311,6 +1934,7 @@ impl GraphCircuit {
                 processed_outputs = Some(GraphModules::forward::<Scheme>(
                     &model_results.outputs,
                     &visibility.output,
+                    hash_chunk_size,
                     vk,
                     srs,
                 )?);
@@ -1334,14 +1958,46 @@ impl GraphCircuit {
             max_lookup_inputs: model_results.max_lookup_inputs,
             min_lookup_inputs: model_results.min_lookup_inputs,
             max_range_size: model_results.max_range_size,
+            lookup_input_ranges: model_results.lookup_input_ranges.clone(),
+            warnings: vec![],
+            sealed_outputs: None,
+            caller: None,
+            expected_outputs: None,
+            state_proof: None,
         };
 
         witness.generate_rescaled_elements(
-            self.model().graph.get_input_scales(),
-            self.model().graph.get_output_scales()?,
+            context.input_scales.clone(),
+            context.output_scales.clone(),
             visibility,
         );
 
+        let (min_lookup, max_lookup) = self.settings().run_args.lookup_range;
+        if witness.min_lookup_inputs < min_lookup || witness.max_lookup_inputs > max_lookup {
+            crate::warnings::emit(
+                crate::warnings::WarningKind::Clamped,
+                format!(
+                    "lookup inputs [{}, {}] fall outside of the lookup range [{}, {}], consider re-calibrating",
+                    witness.min_lookup_inputs, witness.max_lookup_inputs, min_lookup, max_lookup
+                ),
+            );
+        }
+        for (op, (min, max)) in &witness.lookup_input_ranges {
+            let (min_domain, max_domain) = self.settings().lookup_domain(op);
+            if (*min < min_domain || *max > max_domain)
+                && (min_domain, max_domain) != (min_lookup, max_lookup)
+            {
+                crate::warnings::emit(
+                    crate::warnings::WarningKind::Clamped,
+                    format!(
+                        "inputs [{}, {}] of {} fall outside of its lookup domain [{}, {}], consider re-calibrating",
+                        min, max, crate::circuit::ops::Op::<Fp>::as_string(op), min_domain, max_domain
+                    ),
+                );
+            }
+        }
+        witness.warnings = crate::warnings::take();
+
         #[cfg(not(target_arch = "wasm32"))]
         log::trace!(
             "witness: \n {}",
@@ -1352,29 +2008,43 @@ impl GraphCircuit {
     }
 
     /// Create a new circuit from a set of input data and [RunArgs].
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     pub fn from_run_args(
         run_args: &RunArgs,
         model_path: &std::path::Path,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        let run_args = &run_args.resolve_visibility();
         let model = Model::from_run_args(run_args, model_path)?;
         Self::new(model, run_args)
     }
 
+    /// Create a new circuit from an ordered list of models stitched end to end, see [Model::new_composite].
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    pub fn from_composite(
+        run_args: &RunArgs,
+        model_paths: &[std::path::PathBuf],
+        submodel_scales: Option<Vec<VarScales>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let run_args = &run_args.resolve_visibility();
+        let model = Model::from_composite_paths(run_args, model_paths, submodel_scales)?;
+        Self::new(model, run_args)
+    }
+
     /// Create a new circuit from a set of input data and [GraphSettings].
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     pub fn from_settings(
         params: &GraphSettings,
         model_path: &std::path::Path,
         check_mode: CheckMode,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         params.run_args.validate()?;
-        let model = Model::from_run_args(&params.run_args, model_path)?;
+        let mut model = Model::from_run_args(&params.run_args, model_path)?;
+        model.apply_bias_corrections(&params.bias_corrections)?;
         Self::new_from_settings(model, params.clone(), check_mode)
     }
 
     ///
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
     pub async fn populate_on_chain_test_data(
         &mut self,
         data: &mut GraphData,
@@ -1558,7 +2228,8 @@ impl Circuit<Fp> for GraphCircuit {
         debug!(
             "degree: {}, log2_ceil of degrees: {:?}",
             cs.degree(),
-            (cs.degree() as f32).log2().ceil()
+            // integer math, as the verifier contracts can't hold float instructions
+            cs.degree().next_power_of_two().trailing_zeros()
         );
 
         let circuit_size = CircuitSize::from_cs(cs, params.run_args.logrows);
@@ -1599,10 +2270,12 @@ impl Circuit<Fp> for GraphCircuit {
             return Err(PlonkError::Synthesis);
         }
 
+        crate::pfsys::profile::start("witness generation");
+        let synthesis_start = instant::Instant::now();
         trace!("Setting input in synthesize");
         let input_vis = &self.settings().run_args.input_visibility;
         let output_vis = &self.settings().run_args.output_visibility;
-        let mut graph_modules = GraphModules::new();
+        let mut graph_modules = GraphModules::new(self.settings().run_args.hash_chunk_size);
 
         let mut config = config.clone();
 
@@ -1730,6 +2403,8 @@ impl Circuit<Fp> for GraphCircuit {
                 log::error!("{}", e);
                 PlonkError::Synthesis
             })?;
+        // the sealing encrypts the outputs themselves, not their hashes
+        let model_outputs = outputs.clone();
         trace!("running output module layout");
 
         let output_visibility = &self.settings().run_args.output_visibility;
@@ -1765,6 +2440,22 @@ impl Circuit<Fp> for GraphCircuit {
             )?;
         }
 
+        if self.settings().run_args.seal_outputs {
+            trace!("running sealing module layout");
+            // the caller sits between the output hashes and the sealing
+            if self.settings().run_args.bind_caller {
+                instance_offset += 1;
+            }
+            GraphModules::layout_sealing(
+                &mut layouter,
+                &config.module_configs,
+                &model_outputs,
+                self.graph_witness.sealed_outputs.as_ref(),
+                &mut instance_offset,
+            )?;
+        }
+
+        crate::pfsys::profile::record("witness generation", synthesis_start.elapsed());
         Ok(())
     }
 }
@@ -1,15 +1,20 @@
 use super::extract_const_quantized_values;
+use super::modules::poseidon_hash;
 use super::node::*;
 use super::scale_to_multiplier;
 use super::vars::*;
 use super::GraphError;
 use super::GraphSettings;
+use super::RequiredLookup;
 use crate::circuit::hybrid::HybridOp;
+use crate::circuit::poly::PolyOp;
 use crate::circuit::region::RegionCtx;
 use crate::circuit::table::Range;
+use crate::circuit::Constant;
 use crate::circuit::Input;
 use crate::circuit::InputType;
 use crate::circuit::Unknown;
+use crate::fieldutils::i128_to_felt;
 use crate::tensor::ValType;
 use crate::{
     circuit::{lookup::LookupOp, BaseConfig as PolyConfig, CheckMode, Op},
@@ -18,8 +23,9 @@ use crate::{
 };
 use halo2curves::bn256::Fr as Fp;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use super::input::GraphData;
+use clap::ValueEnum;
 #[cfg(not(target_arch = "wasm32"))]
 use colored::Colorize;
 use halo2_proofs::{
@@ -29,11 +35,14 @@ use halo2_proofs::{
 use halo2curves::ff::Field;
 use itertools::Itertools;
 use log::error;
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+use log::warn;
 use log::{debug, info, trace};
+use maybe_rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::BTreeMap;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::error::Error;
@@ -41,22 +50,23 @@ use std::fs;
 use std::io::Read;
 use std::path::PathBuf;
 #[cfg(not(target_arch = "wasm32"))]
-use tabled::Table;
-#[cfg(not(target_arch = "wasm32"))]
+use tabled::{Table, Tabled};
+use tosubcommand::ToFlags;
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::prelude::{
-    Framework, Graph, InferenceFact, InferenceModelExt, SymbolValues, TypedFact, TypedOp,
+    Framework, Graph, InferenceFact, InferenceModelExt, OutletId, SymbolValues, TypedFact, TypedOp,
 };
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::tract_core::internal::DatumType;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::tract_hir::ops::scan::Scan;
 use unzip_n::unzip_n;
 
 unzip_n!(pub 3);
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 type TractResult = (Graph<TypedFact, Box<dyn TypedOp>>, SymbolValues);
 /// The result of a forward pass.
 #[derive(Clone, Debug)]
@@ -69,6 +79,8 @@ pub struct ForwardResult {
     pub min_lookup_inputs: i128,
     /// The max range check size
     pub max_range_size: i128,
+    /// The min and max inputs of each lookup operation.
+    pub lookup_input_ranges: Vec<(LookupOp, Range)>,
 }
 
 impl From<DummyPassRes> for ForwardResult {
@@ -78,6 +90,7 @@ impl From<DummyPassRes> for ForwardResult {
             max_lookup_inputs: res.max_lookup_inputs,
             min_lookup_inputs: res.min_lookup_inputs,
             max_range_size: res.max_range_size,
+            lookup_input_ranges: res.lookup_input_ranges,
         }
     }
 }
@@ -121,10 +134,129 @@ pub struct DummyPassRes {
     pub min_lookup_inputs: i128,
     /// min range check
     pub max_range_size: i128,
+    /// min and max inputs of each lookup op
+    pub lookup_input_ranges: Vec<(LookupOp, Range)>,
     /// outputs
     pub outputs: Vec<Tensor<Fp>>,
 }
 
+/// The estimated cost of a single node of a model, see [Model::report].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct NodeReport {
+    /// the node's unique identifier
+    pub idx: usize,
+    /// the operation of the node
+    pub op: String,
+    /// the shapes of the node's outputs
+    pub out_dims: Vec<Vec<usize>>,
+    /// the scales of the node's outputs
+    pub out_scales: Vec<crate::Scale>,
+    /// the rows the node takes up when laid out on its own
+    pub num_rows: usize,
+    /// the advice cells the node assigns
+    pub total_assignments: usize,
+    /// the constants the node assigns
+    pub total_const_size: usize,
+    /// the lookup tables the node needs
+    pub lookups: Vec<String>,
+    /// the range checks the node needs
+    pub range_checks: Vec<Range>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Tabled for NodeReport {
+    const LENGTH: usize = 9;
+
+    fn headers() -> Vec<std::borrow::Cow<'static, str>> {
+        [
+            "idx",
+            "op",
+            "out_dims",
+            "out_scales",
+            "rows",
+            "assignments",
+            "constants",
+            "lookups",
+            "range_checks",
+        ]
+        .into_iter()
+        .map(std::borrow::Cow::Borrowed)
+        .collect()
+    }
+
+    fn fields(&self) -> Vec<std::borrow::Cow<'_, str>> {
+        [
+            self.idx.to_string(),
+            self.op.clone(),
+            format!("{:?}", self.out_dims),
+            format!("{:?}", self.out_scales),
+            self.num_rows.to_string(),
+            self.total_assignments.to_string(),
+            self.total_const_size.to_string(),
+            self.lookups.join(", "),
+            format!("{:?}", self.range_checks),
+        ]
+        .into_iter()
+        .map(std::borrow::Cow::Owned)
+        .collect()
+    }
+}
+
+/// What the nodes of a [Model::report] are sorted by, the costliest first
+#[derive(
+    ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq, Deserialize, Serialize, PartialOrd,
+)]
+pub enum ReportSort {
+    /// the order of the nodes in the graph
+    #[default]
+    Idx,
+    /// the rows each node takes up
+    Rows,
+    /// the advice cells each node assigns
+    Assignments,
+    /// the constants each node assigns
+    Constants,
+    /// the number of lookup tables each node needs
+    Lookups,
+}
+
+impl std::fmt::Display for ReportSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ReportSort::Idx => "idx",
+                ReportSort::Rows => "rows",
+                ReportSort::Assignments => "assignments",
+                ReportSort::Constants => "constants",
+                ReportSort::Lookups => "lookups",
+            }
+        )
+    }
+}
+
+impl ToFlags for ReportSort {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl ReportSort {
+    /// Sorts `reports`, keeping the order of the graph between nodes of the same cost.
+    pub fn sort(&self, reports: &mut [NodeReport]) {
+        use std::cmp::Reverse;
+        reports.sort_by_key(|r| r.idx);
+        match self {
+            ReportSort::Idx => {}
+            ReportSort::Rows => reports.sort_by_key(|r| Reverse(r.num_rows)),
+            ReportSort::Assignments => reports.sort_by_key(|r| Reverse(r.total_assignments)),
+            ReportSort::Constants => reports.sort_by_key(|r| Reverse(r.total_const_size)),
+            ReportSort::Lookups => reports.sort_by_key(|r| Reverse(r.lookups.len())),
+        }
+    }
+}
+
 /// A struct for loading from an Onnx file and converting a computational graph to a circuit.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct Model {
@@ -411,6 +543,16 @@ impl ParsedNodes {
         Ok(inputs)
     }
 
+    /// Returns the indices of the nodes feeding each of the graph's inputs.
+    pub fn input_nodes(&self) -> &[usize] {
+        &self.inputs
+    }
+
+    /// Returns the (node, slot) each of the graph's outputs is read from.
+    pub fn output_outlets(&self) -> &[Outlet] {
+        &self.outputs
+    }
+
     /// Returns the number of the computational graph's outputs
     pub fn num_outputs(&self) -> usize {
         let output_nodes = self.outputs.iter();
@@ -461,18 +603,108 @@ impl ParsedNodes {
             })
             .collect::<Result<Vec<_>, GraphError>>()
     }
+
+    /// Appends `next` to this graph, feeding our outputs into its inputs in order.
+    /// The input nodes of `next` are dropped and its node indices are offset past ours.
+    pub fn stitch(mut self, next: ParsedNodes) -> Result<ParsedNodes, GraphError> {
+        if self.outputs.len() != next.inputs.len() {
+            return Err(GraphError::ModelStitching(format!(
+                "{} outputs cannot feed {} inputs",
+                self.outputs.len(),
+                next.inputs.len()
+            )));
+        }
+
+        let output_shapes = self.output_shapes()?;
+        for (i, (output, input)) in output_shapes.iter().zip(next.inputs.iter()).enumerate() {
+            let input_dims = next
+                .nodes
+                .get(input)
+                .ok_or(GraphError::MissingNode(*input))?
+                .out_dims();
+            if input_dims.first() != Some(output) {
+                return Err(GraphError::ModelStitching(format!(
+                    "output {} has shape {:?} but the next model expects {:?}",
+                    i,
+                    output,
+                    input_dims.first()
+                )));
+            }
+        }
+
+        let offset = self.nodes.keys().max().map(|k| k + 1).unwrap_or(0);
+        let remap: BTreeMap<usize, Outlet> = next
+            .inputs
+            .iter()
+            .cloned()
+            .zip(self.outputs.iter().cloned())
+            .collect();
+        let rewrite = |outlet: &Outlet| match remap.get(&outlet.0) {
+            Some(prev) => *prev,
+            None => (outlet.0 + offset, outlet.1),
+        };
+
+        for (idx, mut node) in next.nodes {
+            if remap.contains_key(&idx) {
+                continue;
+            }
+
+            let node_inputs = match &mut node {
+                NodeType::Node(n) => {
+                    n.idx += offset;
+                    &mut n.inputs
+                }
+                NodeType::SubGraph { inputs, idx, .. } => {
+                    *idx += offset;
+                    inputs
+                }
+            };
+            for input in node_inputs.iter_mut() {
+                if let Some(prev) = remap.get(&input.0) {
+                    if let Some(NodeType::Node(n)) = self.nodes.get_mut(&prev.0) {
+                        n.num_uses += 1;
+                    }
+                }
+                *input = rewrite(input);
+            }
+
+            self.nodes.insert(idx + offset, node);
+        }
+
+        Ok(ParsedNodes {
+            nodes: self.nodes,
+            inputs: self.inputs,
+            outputs: next.outputs.iter().map(rewrite).collect(),
+        })
+    }
 }
 
 impl Model {
+    /// Registers an implementation for onnx nodes of op type `name`, e.g ops from a custom domain.
+    /// Must be called before loading a model (or compiled circuit) that uses the op.
+    pub fn register_custom_op(name: &str, factory: Box<dyn super::custom::GraphOpFactory>) {
+        super::custom::register_custom_op(name, factory)
+    }
+
     /// Creates a `Model` from a specified path to an Onnx file.
     /// # Arguments
     /// * `reader` - A reader for an Onnx file.
     /// * `run_args` - [RunArgs]
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     pub fn new(reader: &mut dyn std::io::Read, run_args: &RunArgs) -> Result<Self, Box<dyn Error>> {
+        Self::new_in_dir(reader, run_args, None)
+    }
+
+    /// Creates a `Model` from an Onnx file whose external data (if any) lives in `model_dir`.
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    fn new_in_dir(
+        reader: &mut dyn std::io::Read,
+        run_args: &RunArgs,
+        model_dir: Option<&std::path::Path>,
+    ) -> Result<Self, Box<dyn Error>> {
         let visibility = VarVisibility::from_args(run_args)?;
 
-        let graph = Self::load_onnx_model(reader, run_args, &visibility)?;
+        let graph = Self::load_onnx_model(reader, run_args, &visibility, model_dir)?;
 
         let om = Model { graph, visibility };
 
@@ -481,6 +713,175 @@ impl Model {
         Ok(om)
     }
 
+    /// Downloads an Onnx file over https, checks it against a pinned SHA256 hash and loads it.
+    /// # Arguments
+    /// * `url` - https url of the Onnx file.
+    /// * `sha256` - expected hex SHA256 hash of the file.
+    /// * `run_args` - [RunArgs]
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub async fn from_url(
+        url: &str,
+        sha256: &str,
+        run_args: &RunArgs,
+    ) -> Result<Self, Box<dyn Error>> {
+        let path = Self::fetch_onnx(url, sha256).await?;
+        Self::from_run_args(run_args, &path)
+    }
+
+    /// Fetches an Onnx file into the local model cache (`$EZKL_REPO_PATH/models`) and returns its path.
+    /// Files are cached by hash, so a pinned model is only downloaded once.
+    #[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+    pub async fn fetch_onnx(url: &str, sha256: &str) -> Result<PathBuf, Box<dyn Error>> {
+        if !url.starts_with("https://") {
+            return Err(format!("refusing to fetch model over insecure url {}", url).into());
+        }
+        let expected = sha256.trim().to_lowercase();
+        if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid SHA256 pin {}", sha256).into());
+        }
+
+        let cache_dir = PathBuf::from(&*crate::execute::EZKL_MODELS_REPO_PATH);
+        fs::create_dir_all(&cache_dir)?;
+        let path = cache_dir.join(format!("{}.onnx", expected));
+
+        if path.exists() {
+            if sha256::digest(fs::read(&path)?) == expected {
+                debug!("using cached model at {}", path.display());
+                return Ok(path);
+            }
+            warn!(
+                "cached model at {} is corrupted, re-downloading",
+                path.display()
+            );
+            fs::remove_file(&path)?;
+        }
+
+        info!("downloading model from {}", url);
+        let bytes = reqwest::get(url)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec();
+
+        let hash = sha256::digest(bytes.clone());
+        if hash != expected {
+            return Err(format!(
+                "model hash {} does not match the pinned hash {}. the remote file may have been tampered with",
+                hash, expected
+            )
+            .into());
+        }
+
+        // write to a temporary file first so an interrupted download never poisons the cache
+        let partial = path.with_extension("part");
+        fs::write(&partial, &bytes)?;
+        fs::rename(&partial, &path)?;
+
+        Ok(path)
+    }
+
+    /// Creates a single `Model` from an ordered list of Onnx files, the outputs of each model
+    /// feeding the inputs of the next.
+    /// # Arguments
+    /// * `readers` - Readers for the Onnx files, in execution order.
+    /// * `run_args` - [RunArgs]
+    /// * `submodel_scales` - Optional [VarScales] for each model, defaults to the scales in `run_args`.
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    pub fn new_composite(
+        readers: &mut [&mut dyn std::io::Read],
+        run_args: &RunArgs,
+        submodel_scales: Option<Vec<VarScales>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut sources = readers
+            .iter_mut()
+            .map(|r| (&mut **r, None))
+            .collect::<Vec<_>>();
+        Self::load_composite(&mut sources, run_args, submodel_scales)
+    }
+
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    fn load_composite(
+        readers: &mut [(&mut dyn std::io::Read, Option<&std::path::Path>)],
+        run_args: &RunArgs,
+        submodel_scales: Option<Vec<VarScales>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let visibility = VarVisibility::from_args(run_args)?;
+
+        let submodel_scales = match submodel_scales {
+            Some(scales) => scales,
+            None => vec![VarScales::from_args(run_args)?; readers.len()],
+        };
+        if submodel_scales.len() != readers.len() {
+            return Err(Box::new(GraphError::ModelStitching(format!(
+                "got scales for {} models but {} models",
+                submodel_scales.len(),
+                readers.len()
+            ))));
+        }
+
+        let mut graph: Option<ParsedNodes> = None;
+        for ((reader, model_dir), scales) in readers.iter_mut().zip(submodel_scales.iter()) {
+            let (model, symbol_values) =
+                Self::load_onnx_using_tract(&mut **reader, run_args, *model_dir)?;
+
+            // inputs of a downstream model take on the scales of the outputs feeding them
+            let input_scales = match &graph {
+                Some(prev) => Some(prev.get_output_scales()?),
+                None => None,
+            };
+
+            let nodes = Self::nodes_from_graph(
+                &model,
+                run_args,
+                scales,
+                &visibility,
+                &symbol_values,
+                input_scales,
+                None,
+            )?;
+
+            let next = ParsedNodes {
+                nodes,
+                inputs: model.inputs.iter().map(|o| o.node).collect(),
+                outputs: model.outputs.iter().map(|o| (o.node, o.slot)).collect(),
+            };
+
+            graph = Some(match graph {
+                Some(prev) => prev.stitch(next)?,
+                None => next,
+            });
+        }
+
+        let graph = graph.ok_or(GraphError::ModelStitching("no models provided".to_string()))?;
+        let om = Model { graph, visibility };
+
+        debug!("\n {}", om.table_nodes());
+
+        Ok(om)
+    }
+
+    /// Creates a composite `Model` from paths to Onnx files, see [Model::new_composite].
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    pub fn from_composite_paths(
+        run_args: &RunArgs,
+        models: &[PathBuf],
+        submodel_scales: Option<Vec<VarScales>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut files = models
+            .iter()
+            .map(|m| {
+                std::fs::File::open(m).map_err(|_| format!("failed to load {}", m.display()).into())
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let mut sources = files
+            .iter_mut()
+            .zip(models.iter())
+            .map(|(f, m)| (f as &mut dyn std::io::Read, m.parent()))
+            .collect::<Vec<_>>();
+        Model::load_composite(&mut sources, run_args, submodel_scales)
+    }
+
     ///
     pub fn save(&self, path: PathBuf) -> Result<(), Box<dyn Error>> {
         let f = std::fs::File::create(path)?;
@@ -500,20 +901,32 @@ impl Model {
         Ok(result)
     }
 
-    /// Generate model parameters for the circuit
-    pub fn gen_params(
+    /// Saves the model encrypted with `key`, so its weights aren't readable on disk.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_encrypted(
         &self,
-        run_args: &RunArgs,
-        check_mode: CheckMode,
-    ) -> Result<GraphSettings, Box<dyn Error>> {
-        let instance_shapes = self.instance_shapes()?;
-        #[cfg(not(target_arch = "wasm32"))]
-        debug!(
-            "{} {} {}",
-            "model has".blue(),
-            instance_shapes.len().to_string().blue(),
-            "instances".blue()
-        );
+        path: PathBuf,
+        key: &super::encryption::ModelKey,
+    ) -> Result<(), Box<dyn Error>> {
+        let serialized = bincode::serialize(&self)?;
+        fs::write(path, super::encryption::encrypt(&serialized, key)?)?;
+        Ok(())
+    }
+
+    /// Loads a model saved with [Model::save_encrypted].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_encrypted(
+        path: PathBuf,
+        key: &super::encryption::ModelKey,
+    ) -> Result<Self, Box<dyn Error>> {
+        let buffer = super::encryption::decrypt(&fs::read(path)?, key)?;
+        let result = bincode::deserialize(&buffer)?;
+        Ok(result)
+    }
+
+    /// Inputs of unknown values, or of constants for fixed inputs, to lay the model out with
+    /// without data.
+    fn dummy_inputs(&self) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
         // this is the total number of variables we will need to allocate
         // for the circuit
         let default_value = if !self.visibility.input.is_fixed() {
@@ -522,8 +935,7 @@ impl Model {
             ValType::Constant(Fp::ONE)
         };
 
-        let inputs: Vec<ValTensor<Fp>> = self
-            .graph
+        self.graph
             .input_shapes()?
             .iter()
             .map(|shape| {
@@ -532,9 +944,79 @@ impl Model {
                 t.reshape(shape)?;
                 Ok(t)
             })
-            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+            .collect()
+    }
+
+    /// The estimated cost of each node of the model, in the order of the graph: the rows, advice
+    /// cells and constants it takes up when laid out on its own, and the lookup tables and range
+    /// checks it needs. Each node is laid out from the start of a fresh region, so with several
+    /// inner columns the rows of the nodes may not add up exactly to those of the model.
+    pub fn report(&self, run_args: &RunArgs) -> Result<Vec<NodeReport>, Box<dyn Error>> {
+        let mut results = BTreeMap::<usize, Vec<ValTensor<Fp>>>::new();
+        for (input_idx, input) in self.graph.inputs.iter().zip(self.dummy_inputs()?) {
+            results.insert(*input_idx, vec![input]);
+        }
+
+        let mut config = ModelConfig {
+            base: PolyConfig::dummy(run_args.logrows as usize, run_args.num_inner_cols),
+            vars: ModelVars::new_dummy(),
+        };
 
-        let res = self.dummy_layout(run_args, &inputs, false)?;
+        let mut reports = vec![];
+        for (idx, node) in self.graph.nodes.iter() {
+            let mut region = RegionCtx::new_dummy(0, run_args.num_inner_cols, false);
+            let outputs = self.layout_node(&mut config, &mut region, &results, *idx, node)?;
+            reports.push(NodeReport {
+                idx: *idx,
+                op: node.as_str(),
+                out_dims: node.out_dims(),
+                out_scales: node.out_scales(),
+                num_rows: region.row(),
+                total_assignments: region.linear_coord(),
+                total_const_size: region.total_constants(),
+                lookups: region
+                    .used_lookups()
+                    .iter()
+                    .map(Op::<Fp>::as_string)
+                    .sorted()
+                    .collect(),
+                range_checks: region.used_range_checks().into_iter().sorted().collect(),
+            });
+            if let Some(outputs) = outputs {
+                results.insert(*idx, outputs);
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Generate model parameters for the circuit
+    pub fn gen_params(
+        &self,
+        run_args: &RunArgs,
+        check_mode: CheckMode,
+    ) -> Result<GraphSettings, Box<dyn Error>> {
+        let mut instance_shapes = self.instance_shapes(run_args)?;
+        if let Some(idx) = run_args.mask_output {
+            if idx >= self.graph.num_outputs() {
+                return Err(format!(
+                    "mask_output {} is out of range, the model has {} outputs",
+                    idx,
+                    self.graph.num_outputs()
+                )
+                .into());
+            }
+            // the count of valid slots of the masked output
+            instance_shapes.push(vec![1]);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        debug!(
+            "{} {} {}",
+            "model has".blue(),
+            instance_shapes.len().to_string().blue(),
+            "instances".blue()
+        );
+        let inputs = self.dummy_inputs()?;
+        let res = self.dummy_layout(run_args, &inputs, false, false)?;
 
         // if we're using percentage tolerance, we need to add the necessary range check ops for it.
 
@@ -544,7 +1026,11 @@ impl Model {
             module_sizes: crate::graph::modules::ModuleSizes::default(),
             num_rows: res.num_rows,
             total_assignments: res.linear_coord,
-            required_lookups: res.lookup_ops.into_iter().collect(),
+            required_lookups: res
+                .lookup_ops
+                .into_iter()
+                .map(RequiredLookup::from)
+                .collect(),
             required_range_checks: res.range_checks.into_iter().collect(),
             model_output_scales: self.graph.get_output_scales()?,
             model_input_scales: self.graph.get_input_scales(),
@@ -555,6 +1041,10 @@ impl Model {
             total_const_size: res.total_const_size,
             check_mode,
             version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: super::SETTINGS_SCHEMA_VERSION,
+            warnings: crate::warnings::take(),
+            bias_corrections: BTreeMap::new(),
+            model_hash: None,
             num_blinding_factors: None,
             // unix time timestamp
             #[cfg(not(target_arch = "wasm32"))]
@@ -568,7 +1058,7 @@ impl Model {
         })
     }
 
-    /// Runs a forward pass on sample data !
+    /// Runs a forward pass on sample data ! Independent nodes are evaluated in parallel.
     /// # Arguments
     /// * `reader` - A reader for an Onnx file.
     /// * `model_inputs` - A vector of [Tensor]s to use as inputs to the model.
@@ -583,29 +1073,87 @@ impl Model {
             .iter()
             .map(|x| x.map(|elem| ValType::Value(Value::known(elem))).into())
             .collect();
-        let res = self.dummy_layout(run_args, &valtensor_inputs, throw_range_check_error)?;
+        let res = self.dummy_layout(run_args, &valtensor_inputs, throw_range_check_error, true)?;
         Ok(res.into())
     }
 
+    /// Runs a forward pass and returns the quantized (integer) output of each of `nodes`, rather
+    /// than the model outputs. Range checks are not enforced.
+    pub fn forward_nodes(
+        &self,
+        model_inputs: &[Tensor<Fp>],
+        run_args: &RunArgs,
+        nodes: &[usize],
+    ) -> Result<Vec<Tensor<i128>>, Box<dyn Error>> {
+        let mut results = BTreeMap::<usize, Vec<ValTensor<Fp>>>::new();
+        for (i, input_idx) in self.graph.inputs.iter().enumerate() {
+            let input = model_inputs[i].map(|elem| ValType::Value(Value::known(elem)));
+            results.insert(*input_idx, vec![input.into()]);
+        }
+
+        let mut model_config = ModelConfig {
+            base: PolyConfig::dummy(run_args.logrows as usize, run_args.num_inner_cols),
+            vars: ModelVars::new_dummy(),
+        };
+        let mut region = RegionCtx::new_dummy(0, run_args.num_inner_cols, false);
+        self.layout_nodes(&mut model_config, &mut region, &mut results, false)?;
+
+        nodes
+            .iter()
+            .map(|idx| match results.get(idx).and_then(|r| r.first()) {
+                Some(output) => output.get_int_evals(),
+                None => Err(format!("node {} has no output", idx).into()),
+            })
+            .collect()
+    }
+
     /// Loads an Onnx model from a specified path.
     /// # Arguments
     /// * `reader` - A reader for an Onnx file.
     /// * `scale` - The scale to use for quantization.
     /// * `public_params` - Whether to make the params public.
-    #[cfg(not(target_arch = "wasm32"))]
+    /// * `model_dir` - Directory external data locations are relative to, defaults to the working directory.
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     fn load_onnx_using_tract(
         reader: &mut dyn std::io::Read,
         run_args: &RunArgs,
+        model_dir: Option<&std::path::Path>,
     ) -> Result<TractResult, Box<dyn Error>> {
         use tract_onnx::{
             tract_core::internal::IntoArcTensor, tract_hir::internal::GenericFactoid,
         };
 
-        let mut model = tract_onnx::onnx().model_for_read(reader).map_err(|e| {
+        let mut proto = tract_onnx::onnx()
+            .proto_model_for_read(reader)
+            .map_err(|e| {
+                error!("Error loading model: {}", e);
+                GraphError::ModelLoad
+            })?;
+
+        // inline tensors stored in external data files before handing the proto to tract
+        if let Some(graph) = proto.graph.as_mut() {
+            super::utilities::load_external_data(
+                graph,
+                model_dir.unwrap_or(std::path::Path::new(".")),
+            )?;
+        }
+        // newer opsets move some attributes to inputs, rewrite them to the form the node mappings expect
+        super::opset::normalize_opset(&mut proto);
+
+        let mut onnx = tract_onnx::onnx();
+        super::custom::register_tract_parsers(&mut onnx);
+        let mut model = onnx.model_for_proto_model(&proto).map_err(|e| {
             error!("Error loading model: {}", e);
             GraphError::ModelLoad
         })?;
 
+        // prune the graph to the subgraph feeding the selected outputs, decluttering drops the rest
+        if !run_args.output_names.is_empty() {
+            model
+                .set_output_names(&run_args.output_names)
+                .map_err(|e| format!("invalid output names {:?}: {}", run_args.output_names, e))?;
+        }
+
         let variables: std::collections::HashMap<String, usize> =
             std::collections::HashMap::from_iter(run_args.variables.clone());
 
@@ -670,15 +1218,16 @@ impl Model {
     /// * `reader` - A reader for an Onnx file.
     /// * `scale` - The scale to use for quantization.
     /// * `public_params` - Whether to make the params public.
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     fn load_onnx_model(
         reader: &mut dyn std::io::Read,
         run_args: &RunArgs,
         visibility: &VarVisibility,
+        model_dir: Option<&std::path::Path>,
     ) -> Result<ParsedNodes, Box<dyn Error>> {
         let start_time = instant::Instant::now();
 
-        let (model, symbol_values) = Self::load_onnx_using_tract(reader, run_args)?;
+        let (model, symbol_values) = Self::load_onnx_using_tract(reader, run_args, model_dir)?;
 
         let scales = VarScales::from_args(run_args)?;
         let nodes = Self::nodes_from_graph(
@@ -748,7 +1297,7 @@ impl Model {
     /// * `visibility` - Which inputs to the model are public and private (params, inputs, outputs) using [VarVisibility].
     /// * `input_scales` - The scales of the model's inputs.
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     pub fn nodes_from_graph(
         graph: &Graph<TypedFact, Box<dyn TypedOp>>,
         run_args: &RunArgs,
@@ -762,6 +1311,7 @@ impl Model {
 
         let mut nodes = BTreeMap::<usize, NodeType>::new();
         let mut input_idx = 0;
+        let mut num_folded = 0;
         for (i, n) in graph.nodes.iter().enumerate() {
             // Extract the slope layer hyperparams
             match n.op().downcast_ref::<Scan>() {
@@ -895,6 +1445,7 @@ impl Model {
                         i,
                         symbol_values,
                         run_args.div_rebasing,
+                        run_args.shift_rebasing,
                         run_args.rebase_frac_zero_constants,
                     )?;
                     if let Some(ref scales) = override_input_scales {
@@ -918,6 +1469,7 @@ impl Model {
                                     n.out_scale,
                                     1,
                                     run_args.div_rebasing,
+                                    run_args.shift_rebasing,
                                 )
                             } else {
                                 RebaseScale::rebase_up(
@@ -925,23 +1477,50 @@ impl Model {
                                     scales[&i],
                                     n.out_scale,
                                     run_args.div_rebasing,
+                                    run_args.shift_rebasing,
                                 )
                             };
                             n.out_scale = scales[&i];
                         }
                     }
                     nodes.insert(i, NodeType::Node(n));
+                    // fold shape computations as they are loaded, their consumers may need constants
+                    if super::utilities::fold_node(&mut nodes, i) {
+                        num_folded += 1;
+                    }
                 }
             }
         }
+        if num_folded > 0 {
+            debug!("folded {} constant nodes", num_folded);
+        }
+        // subgraph outputs may have their scales overridden, so only the top level graph is fused
+        if run_args.fuse_attention && override_output_scales.is_none() {
+            let outputs: Vec<usize> = graph.outputs.iter().map(|o| o.node).collect();
+            let num_fused = super::fusion::fuse_attention(
+                &mut nodes,
+                &outputs,
+                run_args.div_rebasing,
+                run_args.shift_rebasing,
+            )?;
+            if num_fused > 0 {
+                debug!("fused {} attention blocks", num_fused);
+            }
+        }
+        if run_args.shuffle_permutations {
+            let num_replaced = super::shuffle::shuffle_permutations(&mut nodes)?;
+            if num_replaced > 0 {
+                debug!("laid out {} gathers in the shuffle columns", num_replaced);
+            }
+        }
         Self::remove_unused_nodes(&mut nodes);
 
         Ok(nodes)
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
     /// Removes all nodes that are consts with 0 uses
     fn remove_unused_nodes(nodes: &mut BTreeMap<usize, NodeType>) {
+        let num_nodes = nodes.len();
         // remove all nodes that are consts with 0 uses now
         nodes.retain(|_, n| match n {
             NodeType::Node(n) => match &mut n.opkind {
@@ -956,24 +1535,50 @@ impl Model {
                 true
             }
         });
+
+        if nodes.len() < num_nodes {
+            crate::warnings::emit(
+                crate::warnings::WarningKind::PrunedNode,
+                format!("pruned {} unused nodes", num_nodes - nodes.len()),
+            );
+        }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     /// Run tract onnx model on sample data !
     pub fn run_onnx_predictions(
         run_args: &RunArgs,
         model_path: &std::path::Path,
         data_chunks: &[GraphData],
         input_shapes: Vec<Vec<usize>>,
+    ) -> Result<Vec<Vec<Tensor<f32>>>, Box<dyn Error>> {
+        Self::run_onnx_node_predictions(run_args, model_path, data_chunks, input_shapes, None)
+    }
+
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    /// Run tract onnx model on sample data, returning the first output of each of `nodes` rather
+    /// than the model outputs. Node indices are the same as the ones of the quantized [Model].
+    pub fn run_onnx_node_predictions(
+        run_args: &RunArgs,
+        model_path: &std::path::Path,
+        data_chunks: &[GraphData],
+        input_shapes: Vec<Vec<usize>>,
+        nodes: Option<&[usize]>,
     ) -> Result<Vec<Vec<Tensor<f32>>>, Box<dyn Error>> {
         use tract_onnx::tract_core::internal::IntoArcTensor;
 
-        let (model, _) = Model::load_onnx_using_tract(
+        let (mut model, _) = Model::load_onnx_using_tract(
             &mut std::fs::File::open(model_path)
                 .map_err(|_| format!("failed to load {}", model_path.display()))?,
             run_args,
+            model_path.parent(),
         )?;
 
+        if let Some(nodes) = nodes {
+            let outlets = nodes.iter().map(|n| OutletId::new(*n, 0)).collect_vec();
+            model.set_output_outlets(&outlets)?;
+        }
+
         let datum_types: Vec<DatumType> = model
             .input_outlets()?
             .iter()
@@ -999,15 +1604,16 @@ impl Model {
     /// Creates a `Model` from parsed run_args
     /// # Arguments
     /// * `params` - A [GraphSettings] struct holding parsed CLI arguments.
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     pub fn from_run_args(
         run_args: &RunArgs,
         model: &std::path::Path,
     ) -> Result<Self, Box<dyn Error>> {
-        Model::new(
+        Model::new_in_dir(
             &mut std::fs::File::open(model)
                 .map_err(|_| format!("failed to load {}", model.display()))?,
             run_args,
+            model.parent(),
         )
     }
 
@@ -1038,8 +1644,10 @@ impl Model {
         let input = &vars.advices[0];
         let output = &vars.advices[2];
         let index = &vars.advices[1];
-        for op in required_lookups {
-            base_gate.configure_lookup(meta, input, output, index, lookup_range, logrows, &op)?;
+        for lookup in required_lookups {
+            // ops without a domain of their own share the global lookup range
+            let domain = lookup.domain.unwrap_or(lookup_range);
+            base_gate.configure_lookup(meta, input, output, index, domain, logrows, &lookup.op)?;
         }
 
         for range in required_range_checks {
@@ -1112,17 +1720,19 @@ impl Model {
             || "model",
             |region| {
                 let mut thread_safe_region = RegionCtx::new(region, 0, run_args.num_inner_cols);
+                thread_safe_region.set_assignment_order(run_args.assignment_order);
                 // we need to do this as this loop is called multiple times
                 vars.set_instance_idx(instance_idx);
 
                 let outputs = self
-                    .layout_nodes(&mut config, &mut thread_safe_region, &mut results)
+                    .layout_nodes(&mut config, &mut thread_safe_region, &mut results, false)
                     .map_err(|e| {
                         error!("{}", e);
                         halo2_proofs::plonk::Error::Synthesis
                     })?;
 
-                if run_args.output_visibility.is_public() || run_args.output_visibility.is_fixed() {
+                let public_outputs = (0..outputs.len()).any(|i| run_args.output_is_public(i));
+                if public_outputs || run_args.output_visibility.is_fixed() {
                     let output_scales = self.graph.get_output_scales().map_err(|e| {
                         error!("{}", e);
                         halo2_proofs::plonk::Error::Synthesis
@@ -1130,11 +1740,12 @@ impl Model {
                     let res = outputs
                         .iter()
                         .enumerate()
+                        .filter(|(i, _)| run_args.output_is_public(*i) || run_args.output_visibility.is_fixed())
                         .map(|(i, output)| {
                             let mut tolerance = run_args.tolerance;
                             tolerance.scale = scale_to_multiplier(output_scales[i]).into();
 
-                            let comparators = if run_args.output_visibility == Visibility::Public {
+                            let comparators = if run_args.output_is_public(i) {
                                 let res = vars.instance.as_ref().ok_or("no instance")?.clone();
                                 vars.increment_instance_idx();
                                 res
@@ -1162,6 +1773,25 @@ impl Model {
                     thread_safe_region.increment_total_constants(output.num_constants());
                     }
                 }
+
+                if run_args.mask_output.is_some() {
+                    let comparator = vars.instance.clone().ok_or_else(|| {
+                        error!("no instance for the count of valid slots");
+                        halo2_proofs::plonk::Error::Synthesis
+                    })?;
+                    vars.increment_instance_idx();
+                    Self::layout_valid_count(
+                        &config.base,
+                        &mut thread_safe_region,
+                        run_args,
+                        &outputs,
+                        comparator,
+                    )
+                    .map_err(|e| {
+                        error!("{}", e);
+                        halo2_proofs::plonk::Error::Synthesis
+                    })?;
+                }
                 num_rows = thread_safe_region.row();
                 linear_coord = thread_safe_region.linear_coord();
                 total_const_size = thread_safe_region.total_constants();
@@ -1187,11 +1817,14 @@ impl Model {
         Ok(outputs)
     }
 
+    /// Lays out the nodes of the graph in order. If `parallel` is set and the region is a dummy
+    /// one the nodes are laid out level by level instead, see [Self::layout_levels].
     fn layout_nodes(
         &self,
         config: &mut ModelConfig,
         region: &mut RegionCtx<Fp>,
         results: &mut BTreeMap<usize, Vec<ValTensor<Fp>>>,
+        parallel: bool,
     ) -> Result<Vec<ValTensor<Fp>>, Box<dyn Error>> {
         // index over results to get original inputs
         let orig_inputs: BTreeMap<usize, _> = results
@@ -1200,187 +1833,290 @@ impl Model {
             .filter(|(idx, _)| self.graph.inputs.contains(idx))
             .collect();
 
+        if parallel && region.is_dummy() {
+            self.layout_levels(config, region, results)?;
+        } else {
+            for (idx, node) in self.graph.nodes.iter() {
+                if let Some(outputs) = self.layout_node(config, region, results, *idx, node)? {
+                    results.insert(*idx, outputs);
+                }
+            }
+        }
+
+        // we do this so we can support multiple passes of the same model and have deterministic results (Non-assigned inputs etc... etc...)
+        results.extend(orig_inputs);
+
+        let output_nodes = self.graph.outputs.iter();
+        debug!(
+            "model outputs are nodes: {:?}",
+            output_nodes.clone().collect_vec()
+        );
+        let outputs = output_nodes
+            .map(|(idx, outlet)| {
+                Ok(results.get(idx).ok_or(GraphError::MissingResults)?[*outlet].clone())
+            })
+            .collect::<Result<Vec<_>, GraphError>>()?;
+
+        Ok(outputs)
+    }
+
+    /// Groups the nodes into topological levels: every node only depends on nodes of earlier
+    /// levels, so the nodes of a level are independent of one another.
+    pub(crate) fn topological_levels(&self) -> Vec<Vec<usize>> {
+        let mut depths = BTreeMap::<usize, usize>::new();
+        let mut levels: Vec<Vec<usize>> = vec![];
+        // nodes are indexed in topological order
         for (idx, node) in self.graph.nodes.iter() {
-            let mut values: Vec<ValTensor<Fp>> = if !node.is_input() {
-                node.inputs()
-                    .iter()
-                    .map(|(idx, outlet)| {
-                        Ok(results.get(idx).ok_or(GraphError::MissingResults)?[*outlet].clone())
-                    })
-                    .collect::<Result<Vec<_>, GraphError>>()?
-            } else {
-                // we re-assign inputs, always from the 0 outlet
-                vec![results.get(idx).ok_or(GraphError::MissingResults)?[0].clone()]
-            };
+            let depth = node
+                .inputs()
+                .iter()
+                .filter_map(|(input, _)| depths.get(input).map(|d| d + 1))
+                .max()
+                .unwrap_or(0);
+            depths.insert(*idx, depth);
+            if levels.len() <= depth {
+                levels.resize(depth + 1, vec![]);
+            }
+            levels[depth].push(*idx);
+        }
+        levels
+    }
 
-            debug!(
-                "laying out {}: {}, row:{}, coord:{}, total_constants: {}, max_lookup_inputs: {}, min_lookup_inputs: {}",
-                idx,
-                node.as_str(),
-                region.row(),
-                region.linear_coord(),
-                region.total_constants(),
-                region.max_lookup_inputs(),
-                region.min_lookup_inputs()
-            );
-            debug!("dims: {:?}", node.out_dims());
-            debug!(
-                "input_dims {:?}",
-                values.iter().map(|v| v.dims()).collect_vec()
-            );
-            debug!("output scales: {:?}", node.out_scales());
-            debug!("input indices: {:?}", node.inputs());
-            debug!(
-                "input scales: {:?}",
-                node.inputs()
-                    .iter()
-                    .map(|(idx, outlet)| self.graph.nodes[idx].out_scales()[*outlet])
-                    .collect_vec()
-            );
+    /// Lays out the nodes level by level in a dummy region, laying out the independent nodes of a
+    /// level in parallel. Each node gets its own dummy region, whose usage is then added to
+    /// `region` in the same way as [RegionCtx::dummy_loop]. Subgraphs are laid out serially.
+    ///
+    /// The values computed are the same as when laying out the nodes in order, but rows are counted
+    /// from the start of each node rather than carried over, so the row count may differ slightly
+    /// when there are several inner columns. This is for witness generation, not for settings.
+    fn layout_levels(
+        &self,
+        config: &ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        results: &mut BTreeMap<usize, Vec<ValTensor<Fp>>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let num_inner_cols = region.num_inner_cols();
+        let throw_range_check_error = region.throw_range_check_error();
+
+        for level in self.topological_levels() {
+            let (nodes, subgraphs): (Vec<usize>, Vec<usize>) = level
+                .into_iter()
+                .partition(|idx| matches!(self.graph.nodes[idx], NodeType::Node(_)));
+
+            let shared_results = &*results;
+            let laid_out = nodes
+                .par_iter()
+                .map(|idx| {
+                    let mut local_config = config.clone();
+                    let mut local_region =
+                        RegionCtx::new_dummy(0, num_inner_cols, throw_range_check_error);
+                    let outputs = self
+                        .layout_node(
+                            &mut local_config,
+                            &mut local_region,
+                            shared_results,
+                            *idx,
+                            &self.graph.nodes[idx],
+                        )
+                        .map_err(|e| e.to_string())?;
+                    Ok((*idx, outputs, local_region.usage()))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
 
-            match &node {
-                NodeType::Node(n) => {
-                    let res = if node.is_constant() && node.num_uses() == 1 {
-                        log::debug!("node {} is a constant with 1 use", n.idx);
-                        let mut node = n.clone();
-                        let c = node.opkind.get_mutable_constant().ok_or("no constant")?;
-                        Some(c.quantized_values.clone().try_into()?)
-                    } else {
-                        config
-                            .base
-                            .layout(region, &values, n.opkind.clone_dyn())
-                            .map_err(|e| {
-                                error!("{}", e);
-                                halo2_proofs::plonk::Error::Synthesis
-                            })?
-                    };
+            for (idx, outputs, usage) in laid_out {
+                region.add_usage(&usage);
+                if let Some(outputs) = outputs {
+                    results.insert(idx, outputs);
+                }
+            }
 
-                    if let Some(mut vt) = res {
-                        vt.reshape(&node.out_dims()[0])?;
-                        // we get the max as for fused nodes this corresponds to the node output
-                        results.insert(*idx, vec![vt.clone()]);
-                        //only use with mock prover
-                        debug!("------------ output node {:?}: {:?}", idx, vt.show());
-                    }
+            let mut config = config.clone();
+            for idx in subgraphs {
+                let node = &self.graph.nodes[&idx];
+                if let Some(outputs) = self.layout_node(&mut config, region, results, idx, node)? {
+                    results.insert(idx, outputs);
                 }
-                NodeType::SubGraph {
-                    model,
-                    inputs,
-                    output_mappings,
-                    input_mappings,
-                    ..
-                } => {
-                    let original_values = values.clone();
-                    let input_mappings = input_mappings.clone();
-
-                    let input_dims = values.iter().map(|inp| inp.dims());
-                    let num_iter = number_of_iterations(&input_mappings, input_dims.collect());
-
-                    debug!(
-                        "{} iteration(s) in a subgraph with inputs {:?} and sources {:?}",
-                        num_iter, inputs, model.graph.inputs
-                    );
+            }
+        }
+        Ok(())
+    }
 
-                    let mut full_results: Vec<ValTensor<Fp>> = vec![];
-
-                    for i in 0..num_iter {
-                        debug!(" -------------- subgraph iteration: {}", i);
-                        // replace the Stacked input with the current chunk iter
-                        for ((mapping, inp), og_inp) in
-                            input_mappings.iter().zip(&mut values).zip(&original_values)
-                        {
-                            if let InputMapping::Stacked { axis, chunk } = mapping {
-                                let start = i * chunk;
-                                let end = (i + 1) * chunk;
-                                let mut sliced_input = og_inp.clone();
-                                sliced_input.slice(axis, &start, &end)?;
-                                *inp = sliced_input;
-                            }
+    /// Lays out a single node, returning its outputs if it has any. The outputs of the nodes it
+    /// depends on must be in `results`.
+    fn layout_node(
+        &self,
+        config: &mut ModelConfig,
+        region: &mut RegionCtx<Fp>,
+        results: &BTreeMap<usize, Vec<ValTensor<Fp>>>,
+        idx: usize,
+        node: &NodeType,
+    ) -> Result<Option<Vec<ValTensor<Fp>>>, Box<dyn Error>> {
+        let mut values: Vec<ValTensor<Fp>> = if !node.is_input() {
+            node.inputs()
+                .iter()
+                .map(|(idx, outlet)| {
+                    Ok(results.get(idx).ok_or(GraphError::MissingResults)?[*outlet].clone())
+                })
+                .collect::<Result<Vec<_>, GraphError>>()?
+        } else {
+            // we re-assign inputs, always from the 0 outlet
+            vec![results.get(&idx).ok_or(GraphError::MissingResults)?[0].clone()]
+        };
+
+        debug!(
+            "laying out {}: {}, row:{}, coord:{}, total_constants: {}, max_lookup_inputs: {}, min_lookup_inputs: {}",
+            idx,
+            node.as_str(),
+            region.row(),
+            region.linear_coord(),
+            region.total_constants(),
+            region.max_lookup_inputs(),
+            region.min_lookup_inputs()
+        );
+        debug!("dims: {:?}", node.out_dims());
+        debug!(
+            "input_dims {:?}",
+            values.iter().map(|v| v.dims()).collect_vec()
+        );
+        debug!("output scales: {:?}", node.out_scales());
+        debug!("input indices: {:?}", node.inputs());
+        debug!(
+            "input scales: {:?}",
+            node.inputs()
+                .iter()
+                .map(|(idx, outlet)| self.graph.nodes[idx].out_scales()[*outlet])
+                .collect_vec()
+        );
+
+        match &node {
+            NodeType::Node(n) => {
+                let res = if node.is_constant() && node.num_uses() == 1 {
+                    log::debug!("node {} is a constant with 1 use", n.idx);
+                    let mut node = n.clone();
+                    let c = node.opkind.get_mutable_constant().ok_or("no constant")?;
+                    Some(c.quantized_values.clone().try_into()?)
+                } else {
+                    config
+                        .base
+                        .layout(region, &values, n.opkind.clone_dyn())
+                        .map_err(|e| {
+                            error!("{}", e);
+                            halo2_proofs::plonk::Error::Synthesis
+                        })?
+                };
+
+                if let Some(mut vt) = res {
+                    vt.reshape(&node.out_dims()[0])?;
+                    //only use with mock prover
+                    debug!("------------ output node {:?}: {:?}", idx, vt.show());
+                    // we get the max as for fused nodes this corresponds to the node output
+                    return Ok(Some(vec![vt]));
+                }
+                Ok(None)
+            }
+            NodeType::SubGraph {
+                model,
+                inputs,
+                output_mappings,
+                input_mappings,
+                ..
+            } => {
+                let original_values = values.clone();
+                let input_mappings = input_mappings.clone();
+
+                let input_dims = values.iter().map(|inp| inp.dims());
+                let num_iter = number_of_iterations(&input_mappings, input_dims.collect());
+
+                debug!(
+                    "{} iteration(s) in a subgraph with inputs {:?} and sources {:?}",
+                    num_iter, inputs, model.graph.inputs
+                );
+
+                let mut full_results: Vec<ValTensor<Fp>> = vec![];
+
+                for i in 0..num_iter {
+                    debug!(" -------------- subgraph iteration: {}", i);
+                    // replace the Stacked input with the current chunk iter
+                    for ((mapping, inp), og_inp) in
+                        input_mappings.iter().zip(&mut values).zip(&original_values)
+                    {
+                        if let InputMapping::Stacked { axis, chunk } = mapping {
+                            let start = i * chunk;
+                            let end = (i + 1) * chunk;
+                            let mut sliced_input = og_inp.clone();
+                            sliced_input.slice(axis, &start, &end)?;
+                            *inp = sliced_input;
                         }
+                    }
 
-                        let mut subgraph_results = BTreeMap::from_iter(
-                            model
-                                .graph
-                                .inputs
-                                .clone()
-                                .into_iter()
-                                .zip(values.clone().into_iter().map(|v| vec![v])),
-                        );
+                    let mut subgraph_results = BTreeMap::from_iter(
+                        model
+                            .graph
+                            .inputs
+                            .clone()
+                            .into_iter()
+                            .zip(values.clone().into_iter().map(|v| vec![v])),
+                    );
 
-                        let res = model.layout_nodes(config, region, &mut subgraph_results)?;
+                    let res = model.layout_nodes(config, region, &mut subgraph_results, false)?;
 
-                        let mut outlets = BTreeMap::new();
+                    let mut outlets = BTreeMap::new();
 
-                        for (mappings, outlet_res) in output_mappings.iter().zip(res) {
-                            for mapping in mappings {
-                                match mapping {
-                                    OutputMapping::Single { outlet, .. } => {
+                    for (mappings, outlet_res) in output_mappings.iter().zip(res) {
+                        for mapping in mappings {
+                            match mapping {
+                                OutputMapping::Single { outlet, .. } => {
+                                    outlets.insert(outlet, outlet_res.clone());
+                                }
+                                OutputMapping::Stacked { outlet, axis, .. } => {
+                                    if !full_results.is_empty() {
+                                        let stacked_res = full_results[*outlet]
+                                            .clone()
+                                            .concat_axis(outlet_res.clone(), axis)?;
+
+                                        outlets.insert(outlet, stacked_res);
+                                    } else {
                                         outlets.insert(outlet, outlet_res.clone());
                                     }
-                                    OutputMapping::Stacked { outlet, axis, .. } => {
-                                        if !full_results.is_empty() {
-                                            let stacked_res = full_results[*outlet]
-                                                .clone()
-                                                .concat_axis(outlet_res.clone(), axis)?;
-
-                                            outlets.insert(outlet, stacked_res);
-                                        } else {
-                                            outlets.insert(outlet, outlet_res.clone());
-                                        }
-                                    }
                                 }
                             }
                         }
+                    }
 
-                        full_results = outlets.into_values().collect_vec();
+                    full_results = outlets.into_values().collect_vec();
 
-                        let output_states = output_state_idx(output_mappings);
-                        let input_states = input_state_idx(&input_mappings);
+                    let output_states = output_state_idx(output_mappings);
+                    let input_states = input_state_idx(&input_mappings);
 
-                        assert_eq!(input_states.len(), output_states.len());
+                    assert_eq!(input_states.len(), output_states.len());
 
-                        for (input_idx, output_idx) in input_states.iter().zip(output_states) {
-                            values[*input_idx] = full_results[output_idx].clone();
-                        }
+                    for (input_idx, output_idx) in input_states.iter().zip(output_states) {
+                        values[*input_idx] = full_results[output_idx].clone();
                     }
+                }
 
-                    //only use with mock prover
-                    trace!(
-                        "------------ output subgraph node {:?}: {:?}",
-                        idx,
-                        full_results.iter().map(|x| x.show()).collect_vec()
-                    );
+                //only use with mock prover
+                trace!(
+                    "------------ output subgraph node {:?}: {:?}",
+                    idx,
+                    full_results.iter().map(|x| x.show()).collect_vec()
+                );
 
-                    results.insert(*idx, full_results);
-                }
+                Ok(Some(full_results))
             }
         }
-
-        // we do this so we can support multiple passes of the same model and have deterministic results (Non-assigned inputs etc... etc...)
-        results.extend(orig_inputs);
-
-        let output_nodes = self.graph.outputs.iter();
-        debug!(
-            "model outputs are nodes: {:?}",
-            output_nodes.clone().collect_vec()
-        );
-        let outputs = output_nodes
-            .map(|(idx, outlet)| {
-                Ok(results.get(idx).ok_or(GraphError::MissingResults)?[*outlet].clone())
-            })
-            .collect::<Result<Vec<_>, GraphError>>()?;
-
-        Ok(outputs)
     }
 
     /// Assigns dummy values to the regions created when calling `configure`.
     /// # Arguments
     /// * `input_shapes` - The shapes of the inputs to the model.
+    /// * `parallel` - Whether to lay out independent nodes in parallel, see [Self::layout_levels].
     pub fn dummy_layout(
         &self,
         run_args: &RunArgs,
         inputs: &[ValTensor<Fp>],
         throw_range_check_error: bool,
+        parallel: bool,
     ) -> Result<DummyPassRes, Box<dyn Error>> {
         debug!("calculating num of constraints using dummy model layout...");
 
@@ -1401,9 +2137,10 @@ impl Model {
 
         let mut region = RegionCtx::new_dummy(0, run_args.num_inner_cols, throw_range_check_error);
 
-        let outputs = self.layout_nodes(&mut model_config, &mut region, &mut results)?;
+        let outputs = self.layout_nodes(&mut model_config, &mut region, &mut results, parallel)?;
 
-        if self.visibility.output.is_public() || self.visibility.output.is_fixed() {
+        let public_outputs = (0..outputs.len()).any(|i| run_args.output_is_public(i));
+        if public_outputs || self.visibility.output.is_fixed() {
             let default_value = if !self.visibility.output.is_fixed() {
                 ValType::Value(Value::<Fp>::unknown())
             } else {
@@ -1414,6 +2151,7 @@ impl Model {
             let res = outputs
                 .iter()
                 .enumerate()
+                .filter(|(i, _)| run_args.output_is_public(*i) || self.visibility.output.is_fixed())
                 .map(|(i, output)| {
                     let mut tolerance = run_args.tolerance;
                     tolerance.scale = scale_to_multiplier(output_scales[i]).into();
@@ -1436,6 +2174,11 @@ impl Model {
             }
         }
 
+        if run_args.mask_output.is_some() {
+            let comparator: ValTensor<Fp> = vec![ValType::Value(Value::<Fp>::unknown())].into();
+            Self::layout_valid_count(&dummy_config, &mut region, run_args, &outputs, comparator)?;
+        }
+
         let duration = start_time.elapsed();
         trace!("dummy model layout took: {:?}", duration);
 
@@ -1467,6 +2210,11 @@ impl Model {
             max_lookup_inputs: region.max_lookup_inputs(),
             min_lookup_inputs: region.min_lookup_inputs(),
             max_range_size: region.max_range_size(),
+            lookup_input_ranges: region
+                .lookup_input_ranges()
+                .into_iter()
+                .sorted_by(|a, b| a.0.cmp(&b.0))
+                .collect(),
             num_dynamic_lookups: region.dynamic_lookup_index(),
             dynamic_lookup_col_coord: region.dynamic_lookup_col_coord(),
             num_shuffles: region.shuffle_index(),
@@ -1477,6 +2225,40 @@ impl Model {
         Ok(res)
     }
 
+    /// Shifts the quantized values of bias constants by the float corrections found during
+    /// calibration (see [GraphSettings::bias_corrections]), quantized at each constant's scale.
+    pub fn apply_bias_corrections(
+        &mut self,
+        corrections: &BTreeMap<usize, Vec<f32>>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (idx, deltas) in corrections {
+            let constant = match self.graph.nodes.get_mut(idx) {
+                Some(NodeType::Node(n)) => {
+                    let multiplier = scale_to_multiplier(n.out_scale);
+                    n.opkind.get_mutable_constant().map(|c| (c, multiplier))
+                }
+                _ => None,
+            };
+            let (constant, multiplier) = constant.ok_or(format!(
+                "bias correction targets node {} which is not a constant",
+                idx
+            ))?;
+            if constant.quantized_values.len() != deltas.len() {
+                return Err(format!(
+                    "bias correction for node {} has {} elements, expected {}",
+                    idx,
+                    deltas.len(),
+                    constant.quantized_values.len()
+                )
+                .into());
+            }
+            for (q, delta) in constant.quantized_values.iter_mut().zip(deltas) {
+                *q += i128_to_felt::<Fp>((*delta as f64 * multiplier).round() as i128);
+            }
+        }
+        Ok(())
+    }
+
     /// Retrieves all constants from the model.
     pub fn get_all_params(&self) -> Vec<Tensor<Fp>> {
         let mut params = vec![];
@@ -1513,6 +2295,19 @@ impl Model {
         const_shapes
     }
 
+    /// The Poseidon hash of the params, flattened in the order of [Self::get_all_params], as
+    /// computed in-circuit when the params are hashed with `hash_chunk_size` elements at each
+    /// node of the hash tree. `None` if the model has no params.
+    pub fn params_hash(&self, hash_chunk_size: usize) -> Result<Option<Fp>, Box<dyn Error>> {
+        let params = self.get_all_params();
+        if params.is_empty() {
+            return Ok(None);
+        }
+        let flattened = Tensor::new(Some(&params), &[params.len()])?.combine()?;
+        let hash = poseidon_hash(flattened.to_vec(), hash_chunk_size)?;
+        Ok(hash.first().and_then(|h| h.first()).copied())
+    }
+
     /// Replaces all constants in the model with the provided values (in order of indexing), returns the number of consts
     pub fn replace_consts(&mut self, consts: &[ValTensor<Fp>]) -> usize {
         let mut const_idx = 0;
@@ -1539,15 +2334,465 @@ impl Model {
         const_idx
     }
 
-    /// Shapes of the computational graph's public inputs (if any)
-    pub fn instance_shapes(&self) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    /// Constrains the number of valid slots marked by the validity mask output, see
+    /// [RunArgs::mask_output], to equal `comparator`.
+    fn layout_valid_count(
+        config: &PolyConfig<Fp>,
+        region: &mut RegionCtx<Fp>,
+        run_args: &RunArgs,
+        outputs: &[ValTensor<Fp>],
+        comparator: ValTensor<Fp>,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(idx) = run_args.mask_output else {
+            return Ok(());
+        };
+        let output = outputs.get(idx).ok_or(format!(
+            "mask_output {} is out of range, the model has {} outputs",
+            idx,
+            outputs.len()
+        ))?;
+        let count = crate::circuit::layouts::valid_count(config, region, &[output.clone()])?;
+        crate::circuit::layouts::enforce_equality(config, region, &[count, comparator])?;
+        Ok(())
+    }
+
+    /// Shapes of the computational graph's public inputs (if any), and of the outputs `run_args`
+    /// make public
+    pub fn instance_shapes(&self, run_args: &RunArgs) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
         let mut instance_shapes = vec![];
         if self.visibility.input.is_public() {
             instance_shapes.extend(self.graph.input_shapes()?);
         }
-        if self.visibility.output.is_public() {
-            instance_shapes.extend(self.graph.output_shapes()?);
+        for (i, shape) in self.graph.output_shapes()?.into_iter().enumerate() {
+            if run_args.output_is_public(i) {
+                instance_shapes.push(shape);
+            }
         }
         Ok(instance_shapes)
     }
 }
+
+/// Programmatically assembles a [Model] without going through an Onnx file.
+///
+/// Nodes are quantized and rescaled using the same rules as the Onnx loader, so a graph built
+/// here produces the same [Model] as the equivalent Onnx graph would.
+///
+/// ```ignore
+/// let mut builder = GraphBuilder::new(&run_args)?;
+/// let x = builder.add_input(vec![1, 4], InputType::F32)?;
+/// let w = builder.add_const(weights)?;
+/// let y = builder.add_matmul(x, w)?;
+/// let y = builder.add_relu(y)?;
+/// let model = builder.finalize(&[y])?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct GraphBuilder {
+    run_args: RunArgs,
+    scales: VarScales,
+    visibility: VarVisibility,
+    nodes: BTreeMap<usize, NodeType>,
+    inputs: Vec<usize>,
+}
+
+impl GraphBuilder {
+    /// Creates an empty builder.
+    /// # Arguments
+    /// * `run_args` - [RunArgs] used to derive scales and visibility.
+    pub fn new(run_args: &RunArgs) -> Result<Self, Box<dyn Error>> {
+        Ok(GraphBuilder {
+            run_args: run_args.clone(),
+            scales: VarScales::from_args(run_args)?,
+            visibility: VarVisibility::from_args(run_args)?,
+            nodes: BTreeMap::new(),
+            inputs: vec![],
+        })
+    }
+
+    /// Adds a model input of the given shape.
+    pub fn add_input(
+        &mut self,
+        shape: Vec<usize>,
+        datum_type: InputType,
+    ) -> Result<Outlet, Box<dyn Error>> {
+        let scale = if datum_type.is_integer() {
+            0
+        } else {
+            self.scales.input
+        };
+        let idx = self.insert(
+            SupportedOp::Input(Input { scale, datum_type }),
+            vec![],
+            shape,
+        )?;
+        self.inputs.push(idx);
+        Ok((idx, 0))
+    }
+
+    /// Adds a constant (e.g a weight matrix), quantized at the param scale.
+    pub fn add_const(&mut self, raw_value: Tensor<f32>) -> Result<Outlet, Box<dyn Error>> {
+        let mut scale = self.scales.params;
+        if self.run_args.rebase_frac_zero_constants && raw_value.iter().all(|x| x.fract() == 0.0) {
+            scale = 0;
+        }
+        let quantized_value =
+            super::quantize_tensor(raw_value.clone(), scale, &self.run_args.param_visibility)?;
+        let shape = raw_value.dims().to_vec();
+        let op = SupportedOp::Constant(Constant::new(quantized_value, raw_value));
+        let idx = self.insert(op, vec![], shape)?;
+        Ok((idx, 0))
+    }
+
+    /// Adds a matrix multiplication `a @ b`, where `a` may carry leading batch dimensions and `b` is 2D.
+    pub fn add_matmul(&mut self, a: Outlet, b: Outlet) -> Result<Outlet, Box<dyn Error>> {
+        let idx = self.next_idx();
+        let a_dims = self.out_dims(a)?;
+        let b_dims = self.out_dims(b)?;
+        if a_dims.len() < 2 || b_dims.len() != 2 || a_dims[a_dims.len() - 1] != b_dims[0] {
+            return Err(Box::new(GraphError::InvalidDims(idx, "matmul".to_string())));
+        }
+
+        let batch: String = ('a'..='h').take(a_dims.len() - 2).collect();
+        let equation = format!("{}mk,kn->{}mn", batch, batch);
+
+        let mut out_dims = a_dims[..a_dims.len() - 1].to_vec();
+        out_dims.push(b_dims[1]);
+
+        let op = SupportedOp::Linear(PolyOp::Einsum { equation });
+        let idx = self.insert(op, vec![a, b], out_dims)?;
+        Ok((idx, 0))
+    }
+
+    /// Adds an elementwise ReLU.
+    pub fn add_relu(&mut self, a: Outlet) -> Result<Outlet, Box<dyn Error>> {
+        let out_dims = self.out_dims(a)?;
+        let idx = self.insert(SupportedOp::Nonlinear(LookupOp::ReLU), vec![a], out_dims)?;
+        Ok((idx, 0))
+    }
+
+    /// Consumes the builder and returns a [Model] whose outputs are `outputs`.
+    pub fn finalize(mut self, outputs: &[Outlet]) -> Result<Model, Box<dyn Error>> {
+        for (idx, _) in outputs {
+            if !self.nodes.contains_key(idx) {
+                return Err(Box::new(GraphError::MissingNode(*idx)));
+            }
+        }
+
+        // mirror the onnx loader, where inputs and outputs without successors count as a single
+        // use and nodes that don't reach an output have been pruned before we see them
+        let outputs_and_inputs = outputs
+            .iter()
+            .map(|(idx, _)| *idx)
+            .chain(self.inputs.iter().copied())
+            .collect::<HashSet<_>>();
+        for idx in &outputs_and_inputs {
+            if let Some(NodeType::Node(n)) = self.nodes.get_mut(idx) {
+                n.num_uses = std::cmp::max(n.num_uses, 1);
+            }
+        }
+
+        // dead nodes are removed last to first, so that removing one releases its own inputs
+        let candidates = self.nodes.keys().rev().copied().collect::<Vec<_>>();
+        for idx in candidates {
+            let dead = match self.nodes.get(&idx) {
+                Some(NodeType::Node(n)) => n.num_uses == 0,
+                _ => false,
+            };
+            if !dead {
+                continue;
+            }
+            if let Some(node) = self.nodes.remove(&idx) {
+                for (input, _) in node.inputs() {
+                    if let Some(input_node) = self.nodes.get_mut(&input) {
+                        input_node.decrement_use();
+                    }
+                }
+            }
+        }
+
+        Model::remove_unused_nodes(&mut self.nodes);
+
+        let om = Model {
+            graph: ParsedNodes {
+                nodes: self.nodes,
+                inputs: self.inputs,
+                outputs: outputs.to_vec(),
+            },
+            visibility: self.visibility,
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        debug!("\n {}", om.table_nodes());
+
+        Ok(om)
+    }
+
+    fn next_idx(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn out_dims(&self, outlet: Outlet) -> Result<Vec<usize>, Box<dyn Error>> {
+        let node = self
+            .nodes
+            .get(&outlet.0)
+            .ok_or(GraphError::MissingNode(outlet.0))?;
+        Ok(node.out_dims()[outlet.1].clone())
+    }
+
+    /// Inserts a node, rebasing its output scale the same way [Node::new] does.
+    fn insert(
+        &mut self,
+        opkind: SupportedOp,
+        inputs: Vec<Outlet>,
+        mut out_dims: Vec<usize>,
+    ) -> Result<usize, Box<dyn Error>> {
+        let idx = self.next_idx();
+
+        let mut in_scales = vec![];
+        for (input, outlet) in &inputs {
+            let input_node = self
+                .nodes
+                .get_mut(input)
+                .ok_or(GraphError::MissingNode(*input))?;
+            in_scales.push(input_node.out_scales()[*outlet]);
+            if let NodeType::Node(n) = input_node {
+                n.num_uses += 1;
+            }
+        }
+
+        let out_scale = opkind.out_scale(in_scales.clone())?;
+        let opkind = RebaseScale::rebase(
+            opkind,
+            self.scales.get_max(),
+            out_scale,
+            self.scales.rebase_multiplier,
+            self.run_args.div_rebasing,
+            self.run_args.shift_rebasing,
+        );
+        let out_scale = opkind.out_scale(in_scales)?;
+
+        if !opkind.is_constant() {
+            out_dims = super::normalize_dims(&out_dims, &format!("node {}", idx))?;
+        } else if out_dims.is_empty() {
+            out_dims = vec![1];
+        }
+
+        self.nodes.insert(
+            idx,
+            NodeType::Node(Node {
+                idx,
+                opkind,
+                inputs,
+                out_dims,
+                out_scale,
+                num_uses: 0,
+            }),
+        );
+
+        Ok(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_levels() {
+        let run_args = RunArgs::default();
+        let mut builder = GraphBuilder::new(&run_args).unwrap();
+        let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+        let a = builder.add_relu(x).unwrap();
+        let b = builder.add_relu(x).unwrap();
+        let w = builder
+            .add_const(Tensor::new(Some(&[0.5; 8]), &[4, 2]).unwrap())
+            .unwrap();
+        let y = builder.add_matmul(a, w).unwrap();
+        let z = builder.add_matmul(b, w).unwrap();
+        let model = builder.finalize(&[y, z]).unwrap();
+        assert_eq!(
+            model.topological_levels(),
+            vec![vec![0, 3], vec![1, 2], vec![4, 5]]
+        );
+
+        let input: ValTensor<Fp> = Tensor::new(Some(&[128, -256, 384, -512]), &[1, 4])
+            .unwrap()
+            .map(|v| ValType::Value(Value::known(i128_to_felt(v))))
+            .into();
+        let serial = model
+            .dummy_layout(&run_args, &[input.clone()], false, false)
+            .unwrap();
+        let parallel = model
+            .dummy_layout(&run_args, &[input], false, true)
+            .unwrap();
+        assert_eq!(serial.outputs, parallel.outputs);
+        assert_eq!(serial.max_lookup_inputs, parallel.max_lookup_inputs);
+        assert_eq!(serial.min_lookup_inputs, parallel.min_lookup_inputs);
+        assert_eq!(serial.lookup_ops, parallel.lookup_ops);
+        assert_eq!(serial.total_const_size, parallel.total_const_size);
+    }
+
+    #[test]
+    fn test_builder_num_uses() {
+        let run_args = RunArgs::default();
+        let mut builder = GraphBuilder::new(&run_args).unwrap();
+        let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+        let w = builder
+            .add_const(Tensor::new(Some(&[0.5; 8]), &[4, 2]).unwrap())
+            .unwrap();
+        // a dead branch, which the onnx loader would never see
+        let dead = builder.add_relu(x).unwrap();
+        let _ = builder.add_relu(dead).unwrap();
+        let y = builder.add_matmul(x, w).unwrap();
+        let z = builder.add_relu(y).unwrap();
+        let model = builder.finalize(&[y, z]).unwrap();
+
+        let nodes = &model.graph.nodes;
+        assert_eq!(nodes.keys().copied().collect::<Vec<_>>(), vec![0, 1, 4, 5]);
+        // the input feeds only the matmul once the dead branch is gone
+        assert_eq!(nodes[&x.0].num_uses(), 1);
+        assert_eq!(nodes[&w.0].num_uses(), 1);
+        // an output that also feeds another node counts its successors only
+        assert_eq!(nodes[&y.0].num_uses(), 1);
+        assert_eq!(nodes[&z.0].num_uses(), 1);
+    }
+
+    #[test]
+    fn test_stitch() {
+        let run_args = RunArgs::default();
+        let first = {
+            let mut builder = GraphBuilder::new(&run_args).unwrap();
+            let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+            let w = builder
+                .add_const(
+                    Tensor::new(Some(&[0.5, -0.5, 1.0, 0.25, 0.0, 1.0, -1.0, 0.5]), &[4, 2])
+                        .unwrap(),
+                )
+                .unwrap();
+            let y = builder.add_matmul(x, w).unwrap();
+            let y = builder.add_relu(y).unwrap();
+            builder.finalize(&[y]).unwrap()
+        };
+        let second = |rows: usize| {
+            let mut builder = GraphBuilder::new(&run_args).unwrap();
+            let x = builder.add_input(vec![1, rows], InputType::F32).unwrap();
+            let w = builder
+                .add_const(Tensor::new(Some(&vec![0.75; rows * 3]), &[rows, 3]).unwrap())
+                .unwrap();
+            let y = builder.add_matmul(x, w).unwrap();
+            builder.finalize(&[y]).unwrap()
+        };
+
+        let stitched = first.graph.clone().stitch(second(2).graph).unwrap();
+        // the input of the second model is dropped and the output of the first feeds its matmul
+        assert_eq!(stitched.inputs, first.graph.inputs);
+        assert_eq!(stitched.nodes.len(), 6);
+        let relu = first.graph.outputs[0];
+        assert_eq!(stitched.nodes[&relu.0].num_uses(), 2);
+        let (out, _) = stitched.outputs[0];
+        assert!(stitched.nodes[&out].inputs().contains(&relu));
+
+        let stitched = Model {
+            graph: stitched,
+            visibility: first.visibility.clone(),
+        };
+        let input = Tensor::new(Some(&[128, -256, 384, -512]), &[1, 4])
+            .unwrap()
+            .map(i128_to_felt::<Fp>);
+        let intermediate = first.forward(&[input.clone()], &run_args, false).unwrap();
+        let expected = second(2)
+            .forward(&intermediate.outputs, &run_args, false)
+            .unwrap();
+        let res = stitched.forward(&[input], &run_args, false).unwrap();
+        assert_eq!(res.outputs, expected.outputs);
+
+        // the second model must take what the first one outputs
+        assert!(matches!(
+            first.graph.clone().stitch(second(3).graph),
+            Err(GraphError::ModelStitching(_))
+        ));
+    }
+
+    #[test]
+    fn test_report() {
+        let run_args = RunArgs::default();
+        let mut builder = GraphBuilder::new(&run_args).unwrap();
+        let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+        let w = builder
+            .add_const(Tensor::new(Some(&[0.5; 8]), &[4, 2]).unwrap())
+            .unwrap();
+        let y = builder.add_matmul(x, w).unwrap();
+        let z = builder.add_relu(y).unwrap();
+        let model = builder.finalize(&[z]).unwrap();
+
+        let mut reports = model.report(&run_args).unwrap();
+        assert_eq!(
+            reports.iter().map(|r| r.idx).collect::<Vec<_>>(),
+            model.graph.nodes.keys().copied().collect::<Vec<_>>()
+        );
+        let relu = reports.iter().find(|r| r.idx == z.0).unwrap();
+        assert_eq!(relu.out_dims, vec![vec![1, 2]]);
+        assert!(relu.total_assignments > 0);
+
+        // the cells of the nodes laid out on their own are among those of the model
+        let settings = model.gen_params(&run_args, CheckMode::UNSAFE).unwrap();
+        let assignments = reports.iter().map(|r| r.total_assignments).sum::<usize>();
+        assert!(assignments <= settings.total_assignments);
+
+        ReportSort::Assignments.sort(&mut reports);
+        assert!(reports
+            .windows(2)
+            .all(|w| w[0].total_assignments >= w[1].total_assignments));
+    }
+
+    #[test]
+    fn test_model_hash() {
+        let run_args = RunArgs {
+            param_visibility: Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![],
+            },
+            expose_model_hash: true,
+            ..RunArgs::default()
+        };
+        let model = |weight: f32| {
+            let mut builder = GraphBuilder::new(&run_args).unwrap();
+            let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+            let w = builder
+                .add_const(Tensor::new(Some(&[weight; 8]), &[4, 2]).unwrap())
+                .unwrap();
+            let y = builder.add_matmul(x, w).unwrap();
+            builder.finalize(&[y]).unwrap()
+        };
+
+        let circuit = crate::graph::GraphCircuit::new(model(0.5), &run_args).unwrap();
+        let settings = circuit.settings();
+        let hash = settings.model_hash.unwrap();
+        let chunk_size = run_args.hash_chunk_size;
+        assert_eq!(model(0.5).params_hash(chunk_size).unwrap(), Some(hash));
+        assert_ne!(model(0.25).params_hash(chunk_size).unwrap(), Some(hash));
+
+        let segment = settings
+            .instance_layout()
+            .into_iter()
+            .find(|s| s.name == "model_hash")
+            .unwrap();
+        let mut instances = vec![Fp::from(0); segment.offset + 1];
+        instances[segment.offset] = hash;
+        assert!(settings.check_model_hash(&instances).is_ok());
+        instances[segment.offset] = Fp::from(1);
+        assert!(settings.check_model_hash(&instances).is_err());
+
+        // private params are hashed for the model hash, which is then the first instance
+        let private_args = RunArgs {
+            expose_model_hash: true,
+            ..RunArgs::default()
+        };
+        let circuit = crate::graph::GraphCircuit::new(model(0.5), &private_args).unwrap();
+        let settings = circuit.settings();
+        assert!(settings.run_args.param_visibility.is_hashed_public());
+        assert_eq!(settings.model_hash, Some(hash));
+        let layout = settings.instance_layout();
+        assert_eq!(layout[0].name, "model_hash");
+        assert_eq!(layout[0].offset, 0);
+    }
+}
@@ -1,28 +1,75 @@
+use crate::circuit::modules::elgamal::{self, ElGamalChip, ElGamalConfig};
 use crate::circuit::modules::polycommit::{PolyCommitChip, PolyCommitConfig};
 use crate::circuit::modules::poseidon::spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH};
 use crate::circuit::modules::poseidon::{PoseidonChip, PoseidonConfig};
 use crate::circuit::modules::Module;
 use crate::tensor::{Tensor, ValTensor};
-use halo2_proofs::circuit::Layouter;
+use halo2_proofs::circuit::{Layouter, Value};
 use halo2_proofs::plonk::{Column, ConstraintSystem, Error, Instance, VerifyingKey};
 use halo2_proofs::poly::commitment::CommitmentScheme;
 use halo2curves::bn256::{Fr as Fp, G1Affine};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+use super::sealed::SealedOutputs;
 use super::{VarVisibility, Visibility};
 
 /// poseidon len to hash in tree
 pub const POSEIDON_LEN_GRAPH: usize = 32;
+/// The number of elements the poseidon module can hash together at each node of its hash tree,
+/// see [crate::RunArgs::hash_chunk_size]
+pub const POSEIDON_CHUNK_SIZES: [usize; 4] = [8, 16, 32, 64];
 /// Poseidon number of instancess
 pub const POSEIDON_INSTANCES: usize = 1;
 
+/// Poseidon module type, hashing `L` elements at each node of its hash tree
+pub type ModulePoseidonChunked<const L: usize> =
+    PoseidonChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, L>;
 /// Poseidon module type
-pub type ModulePoseidon =
-    PoseidonChip<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>;
+pub type ModulePoseidon = ModulePoseidonChunked<POSEIDON_LEN_GRAPH>;
 /// Poseidon module config
 pub type ModulePoseidonConfig = PoseidonConfig<POSEIDON_WIDTH, POSEIDON_RATE>;
 
+/// Evaluates `$body` with `$chip` naming the poseidon module type hashing `$chunk_size` elements
+/// at each node. Sizes outside of [POSEIDON_CHUNK_SIZES] fall back to [POSEIDON_LEN_GRAPH].
+macro_rules! with_poseidon_chunk_size {
+    ($chunk_size:expr, $chip:ident => $body:expr) => {
+        match $chunk_size {
+            8 => {
+                type $chip = ModulePoseidonChunked<8>;
+                $body
+            }
+            16 => {
+                type $chip = ModulePoseidonChunked<16>;
+                $body
+            }
+            64 => {
+                type $chip = ModulePoseidonChunked<64>;
+                $body
+            }
+            _ => {
+                type $chip = ModulePoseidon;
+                $body
+            }
+        }
+    };
+}
+
+/// The poseidon hash of `message` computed by the poseidon module, hashing `chunk_size` elements
+/// at each node of the hash tree. The nodes of each level are hashed in parallel.
+pub fn poseidon_hash(
+    message: Vec<Fp>,
+    chunk_size: usize,
+) -> Result<Vec<Vec<Fp>>, Box<dyn std::error::Error>> {
+    with_poseidon_chunk_size!(chunk_size, Chip => Chip::run(message))
+}
+
+/// The number of rows the poseidon module takes to hash `len` elements, hashing `chunk_size`
+/// elements at each node of the hash tree.
+pub fn poseidon_num_rows(len: usize, chunk_size: usize) -> usize {
+    with_poseidon_chunk_size!(chunk_size, Chip => Chip::num_rows(len))
+}
+
 ///
 #[derive(Clone, Debug, Default)]
 pub struct ModuleConfigs {
@@ -30,6 +77,8 @@ pub struct ModuleConfigs {
     polycommit: Vec<PolyCommitConfig>,
     /// Poseidon
     poseidon: Option<ModulePoseidonConfig>,
+    /// ElGamal sealing of the outputs
+    elgamal: Option<ElGamalConfig>,
     /// Instance
     pub instance: Option<Column<Instance>>,
 }
@@ -59,6 +108,16 @@ impl ModuleConfigs {
         visibility: VarVisibility,
         module_size: ModuleSizes,
     ) {
+        // the main gate of the sealing brings its own instance column, which everything else
+        // shares
+        if module_size.elgamal > 0 {
+            let elgamal = ElGamalChip::configure(cs);
+            if self.instance.is_none() {
+                self.instance = Some(elgamal.instance());
+            }
+            self.elgamal = Some(elgamal);
+        }
+
         if (visibility.input.is_hashed()
             || visibility.output.is_hashed()
             || visibility.params.is_hashed())
@@ -127,6 +186,9 @@ impl ModuleForwardResult {
 pub struct ModuleSizes {
     polycommit: Vec<usize>,
     poseidon: (usize, Vec<usize>),
+    /// number of sealed output elements, see [crate::RunArgs::seal_outputs]
+    #[serde(default)]
+    elgamal: usize,
 }
 
 impl ModuleSizes {
@@ -138,29 +200,47 @@ impl ModuleSizes {
                 0,
                 vec![0; crate::circuit::modules::poseidon::NUM_INSTANCE_COLUMNS],
             ),
+            elgamal: 0,
         }
     }
 
     /// Get the number of constraints
     pub fn max_constraints(&self) -> usize {
-        self.poseidon.0
+        if self.elgamal > 0 {
+            std::cmp::max(self.poseidon.0, elgamal::num_rows(self.elgamal))
+        } else {
+            self.poseidon.0
+        }
     }
     /// Get the number of instances
     pub fn num_instances(&self) -> Vec<usize> {
         // concat
         self.poseidon.1.clone()
     }
+    /// Get the number of instances exposed by the sealing of the outputs, which come after every
+    /// other instance
+    pub fn sealed_instances(&self) -> usize {
+        if self.elgamal > 0 {
+            elgamal::num_instances(self.elgamal)
+        } else {
+            0
+        }
+    }
 }
 
 /// Graph modules that can process inputs, params and outputs beyond the basic operations
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GraphModules {
     polycommit_idx: usize,
+    hash_chunk_size: usize,
 }
 impl GraphModules {
-    ///
-    pub fn new() -> GraphModules {
-        GraphModules { polycommit_idx: 0 }
+    /// Modules hashing `hash_chunk_size` elements at each node of their hash trees
+    pub fn new(hash_chunk_size: usize) -> GraphModules {
+        GraphModules {
+            polycommit_idx: 0,
+            hash_chunk_size,
+        }
     }
 
     ///
@@ -173,16 +253,22 @@ impl GraphModules {
     fn num_constraint_given_shapes(
         visibility: Visibility,
         shapes: Vec<Vec<usize>>,
+        hash_chunk_size: usize,
         sizes: &mut ModuleSizes,
     ) {
-        for shape in shapes {
+        // a hashed visibility naming outlets only hashes those
+        let outlets = visibility.overwrites_inputs();
+        for (i, shape) in shapes.into_iter().enumerate() {
+            if !outlets.is_empty() && !outlets.contains(&i) {
+                continue;
+            }
             let total_len = shape.iter().product::<usize>();
             if total_len > 0 {
                 if visibility.is_polycommit() {
                     // 1 constraint for each polycommit commitment
                     sizes.polycommit.push(total_len);
                 } else if visibility.is_hashed() {
-                    sizes.poseidon.0 += ModulePoseidon::num_rows(total_len);
+                    sizes.poseidon.0 += poseidon_num_rows(total_len, hash_chunk_size);
                     // 1 constraints for hash
                     sizes.poseidon.1[0] += 1;
                 }
@@ -195,12 +281,30 @@ impl GraphModules {
         params_shapes: Vec<Vec<usize>>,
         output_shapes: Vec<Vec<usize>>,
         visibility: VarVisibility,
+        hash_chunk_size: usize,
+        seal_outputs: bool,
     ) -> ModuleSizes {
         let mut module_sizes = ModuleSizes::new();
 
-        Self::num_constraint_given_shapes(visibility.input, input_shapes, &mut module_sizes);
-        Self::num_constraint_given_shapes(visibility.params, params_shapes, &mut module_sizes);
-        Self::num_constraint_given_shapes(visibility.output, output_shapes, &mut module_sizes);
+        if seal_outputs {
+            module_sizes.elgamal = output_shapes
+                .iter()
+                .map(|shape| shape.iter().product::<usize>())
+                .sum();
+        }
+
+        for (visibility, shapes) in [
+            (visibility.input, input_shapes),
+            (visibility.params, params_shapes),
+            (visibility.output, output_shapes),
+        ] {
+            Self::num_constraint_given_shapes(
+                visibility,
+                shapes,
+                hash_chunk_size,
+                &mut module_sizes,
+            );
+        }
 
         module_sizes
     }
@@ -264,13 +368,14 @@ impl GraphModules {
             if let Some(config) = &mut configs.poseidon {
                 // reserve module 0 for poseidon modules
                 layouter.assign_region(|| "_enter_module_0", |_| Ok(()))?;
-                // create the module
-                let chip = ModulePoseidon::new(config.clone());
                 // concat values and sk to get the inputs
                 let mut inputs = values.iter_mut().map(|x| vec![x.clone()]).collect_vec();
-                // layout the module
-                inputs.iter_mut().for_each(|x| {
-                    Self::layout_module(&chip, layouter, x, instance_offset).unwrap();
+                // create and layout the module
+                with_poseidon_chunk_size!(self.hash_chunk_size, Chip => {
+                    let chip = Chip::new(config.clone());
+                    inputs.iter_mut().for_each(|x| {
+                        Self::layout_module(&chip, layouter, x, instance_offset).unwrap();
+                    });
                 });
                 // replace the inputs with the outputs
                 values.iter_mut().enumerate().for_each(|(i, x)| {
@@ -286,10 +391,42 @@ impl GraphModules {
         Ok(())
     }
 
+    /// Seals the model outputs to the public key of `sealed`, exposing the ciphertext at
+    /// `instance_offset`. Without a witness (e.g during keygen) the key and randomness are unknown.
+    pub fn layout_sealing(
+        layouter: &mut impl Layouter<Fp>,
+        configs: &ModuleConfigs,
+        outputs: &[ValTensor<Fp>],
+        sealed: Option<&SealedOutputs>,
+        instance_offset: &mut usize,
+    ) -> Result<(), Error> {
+        let Some(config) = &configs.elgamal else {
+            log::error!("ElGamal config not initialized");
+            return Err(Error::Synthesis);
+        };
+        // reserve module 1 for the sealing
+        layouter.assign_region(|| "_enter_module_1", |_| Ok(()))?;
+        let (public_key, randomness) = match sealed {
+            Some(sealed) => (
+                Value::known(sealed.public_key),
+                Value::known(sealed.randomness),
+            ),
+            None => (Value::unknown(), Value::unknown()),
+        };
+        ElGamalChip::new(config.clone()).layout(
+            layouter,
+            outputs,
+            public_key,
+            randomness,
+            instance_offset,
+        )
+    }
+
     /// Run forward pass
     pub fn forward<Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>>(
         inputs: &[Tensor<Scheme::Scalar>],
         element_visibility: &Visibility,
+        hash_chunk_size: usize,
         vk: Option<&VerifyingKey<G1Affine>>,
         srs: Option<&Scheme::ParamsProver>,
     ) -> Result<ModuleForwardResult, Box<dyn std::error::Error>> {
@@ -298,7 +435,7 @@ impl GraphModules {
 
         if element_visibility.is_hashed() {
             let field_elements = inputs.iter().fold(vec![], |mut acc, x| {
-                let res = ModulePoseidon::run(x.to_vec()).unwrap()[0].clone();
+                let res = poseidon_hash(x.to_vec(), hash_chunk_size).unwrap()[0].clone();
                 acc.extend(res);
                 acc
             });
@@ -308,6 +445,8 @@ impl GraphModules {
         if element_visibility.is_polycommit() {
             if let Some(vk) = vk {
                 if let Some(srs) = srs {
+                    crate::pfsys::profile::start("commitment");
+                    let commit_start = instant::Instant::now();
                     let commitments = inputs.iter().fold(vec![], |mut acc, x| {
                         let res = PolyCommitChip::commit::<Scheme>(
                             x.to_vec(),
@@ -318,6 +457,7 @@ impl GraphModules {
                         acc.push(res);
                         acc
                     });
+                    crate::pfsys::profile::record("commitment", commit_start.elapsed());
                     polycommit = Some(commitments);
                 } else {
                     log::warn!("no srs provided for polycommit. processed value will be none");
@@ -335,3 +475,25 @@ impl GraphModules {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poseidon_chunk_sizes() {
+        let message = (0..100).map(|i| Fp::from(i as u64)).collect_vec();
+        let default = poseidon_hash(message.clone(), POSEIDON_LEN_GRAPH).unwrap();
+        assert_eq!(default, ModulePoseidon::run(message.clone()).unwrap());
+
+        let hashes = POSEIDON_CHUNK_SIZES
+            .iter()
+            .map(|size| poseidon_hash(message.clone(), *size).unwrap()[0][0])
+            .collect_vec();
+        assert!(hashes.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(
+            poseidon_num_rows(100, POSEIDON_LEN_GRAPH),
+            ModulePoseidon::num_rows(100)
+        );
+    }
+}
@@ -1,9 +1,11 @@
 use super::scale_to_multiplier;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use super::utilities::node_output_shapes;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+use super::utilities::normalize_dims;
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use super::VarScales;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use super::Visibility;
 use crate::circuit::hybrid::HybridOp;
 use crate::circuit::lookup::LookupOp;
@@ -12,23 +14,23 @@ use crate::circuit::Constant;
 use crate::circuit::Input;
 use crate::circuit::Op;
 use crate::circuit::Unknown;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use crate::graph::new_op_from_onnx;
 use crate::tensor::Tensor;
 use crate::tensor::TensorError;
 use halo2curves::bn256::Fr as Fp;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use log::trace;
 use serde::Deserialize;
 use serde::Serialize;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use std::collections::BTreeMap;
 use std::error::Error;
 #[cfg(not(target_arch = "wasm32"))]
 use std::fmt;
 #[cfg(not(target_arch = "wasm32"))]
 use tabled::Tabled;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::{
     self,
     prelude::{Node as OnnxNode, SymbolValues, TypedFact, TypedOp},
@@ -136,6 +138,7 @@ impl RebaseScale {
         op_out_scale: crate::Scale,
         scale_rebase_multiplier: u32,
         div_rebasing: bool,
+        shift_rebasing: bool,
     ) -> SupportedOp {
         if (op_out_scale > (global_scale * scale_rebase_multiplier as i32))
             && !inner.is_constant()
@@ -152,6 +155,7 @@ impl RebaseScale {
                     rebase_op: HybridOp::Div {
                         denom: crate::circuit::utils::F32((multiplier) as f32),
                         use_range_check_for_int: !div_rebasing,
+                        use_shift_for_pow2: shift_rebasing,
                     },
                     original_scale: op.original_scale,
                 })
@@ -163,6 +167,7 @@ impl RebaseScale {
                     rebase_op: HybridOp::Div {
                         denom: crate::circuit::utils::F32(multiplier as f32),
                         use_range_check_for_int: !div_rebasing,
+                        use_shift_for_pow2: shift_rebasing,
                     },
                     original_scale: op_out_scale,
                 })
@@ -178,6 +183,7 @@ impl RebaseScale {
         target_scale: crate::Scale,
         op_out_scale: crate::Scale,
         div_rebasing: bool,
+        shift_rebasing: bool,
     ) -> SupportedOp {
         if (op_out_scale < (target_scale)) && !inner.is_constant() && !inner.is_input() {
             let multiplier = scale_to_multiplier(op_out_scale - target_scale);
@@ -191,6 +197,7 @@ impl RebaseScale {
                     rebase_op: HybridOp::Div {
                         denom: crate::circuit::utils::F32((multiplier) as f32),
                         use_range_check_for_int: !div_rebasing,
+                        use_shift_for_pow2: shift_rebasing,
                     },
                 })
             } else {
@@ -202,6 +209,7 @@ impl RebaseScale {
                     rebase_op: HybridOp::Div {
                         denom: crate::circuit::utils::F32(multiplier as f32),
                         use_range_check_for_int: !div_rebasing,
+                        use_shift_for_pow2: shift_rebasing,
                     },
                 })
             }
@@ -273,6 +281,8 @@ pub enum SupportedOp {
     Rescaled(Rescaled),
     ///
     RebaseScale(RebaseScale),
+    /// An op supplied through [crate::graph::custom::register_custom_op].
+    Custom(crate::graph::custom::CustomOp),
 }
 
 impl SupportedOp {
@@ -284,6 +294,28 @@ impl SupportedOp {
             _ => false,
         }
     }
+
+    /// Returns true if the op is a lookup that only approximates its floating point counterpart,
+    /// as opposed to e.g a ReLU or a comparison, which are exact on fixed point values.
+    pub fn is_approximation(&self) -> bool {
+        match self {
+            SupportedOp::Nonlinear(op) => !matches!(
+                op,
+                LookupOp::Abs
+                    | LookupOp::ReLU
+                    | LookupOp::Max { .. }
+                    | LookupOp::Min { .. }
+                    | LookupOp::GreaterThan { .. }
+                    | LookupOp::LessThan { .. }
+                    | LookupOp::GreaterThanEqual { .. }
+                    | LookupOp::LessThanEqual { .. }
+                    | LookupOp::Sign
+                    | LookupOp::KroneckerDelta
+            ),
+            SupportedOp::RebaseScale(op) => op.inner.is_approximation(),
+            _ => false,
+        }
+    }
     ///
     pub fn get_input(&self) -> Option<Input> {
         match self {
@@ -324,7 +356,7 @@ impl SupportedOp {
         }
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     fn homogenous_rescale(
         &self,
         in_scales: Vec<crate::Scale>,
@@ -346,6 +378,7 @@ impl SupportedOp {
             SupportedOp::Unknown(op) => op,
             SupportedOp::Rescaled(op) => op,
             SupportedOp::RebaseScale(op) => op,
+            SupportedOp::Custom(op) => op,
         }
     }
 }
@@ -381,6 +414,12 @@ impl From<Box<dyn Op<Fp>>> for SupportedOp {
         if let Some(op) = value.as_any().downcast_ref::<RebaseScale>() {
             return SupportedOp::RebaseScale(op.clone());
         };
+        if let Some(op) = value
+            .as_any()
+            .downcast_ref::<crate::graph::custom::CustomOp>()
+        {
+            return SupportedOp::Custom(op.clone());
+        };
 
         log::error!("Unsupported op type");
         log::warn!("defaulting to Unknown");
@@ -496,7 +535,7 @@ impl Node {
     /// * `other_nodes` - [BTreeMap] of other previously initialized [Node]s in the computational graph.
     /// * `public_params` - flag if parameters of model are public
     /// * `idx` - The node's unique identifier.
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         node: OnnxNode<TypedFact, Box<dyn TypedOp>>,
@@ -506,6 +545,7 @@ impl Node {
         idx: usize,
         symbol_values: &SymbolValues,
         div_rebasing: bool,
+        shift_rebasing: bool,
         rebase_frac_zero_constants: bool,
     ) -> Result<Self, Box<dyn Error>> {
         trace!("Create {:?}", node);
@@ -614,17 +654,34 @@ impl Node {
             out_scale,
             scales.rebase_multiplier,
             div_rebasing,
+            shift_rebasing,
         );
 
         out_scale = opkind.out_scale(in_scales)?;
 
+        if opkind.is_approximation() {
+            crate::warnings::emit(
+                crate::warnings::WarningKind::Approximation,
+                format!(
+                    "{} is approximated through a lookup table at scale {}",
+                    opkind.as_string(),
+                    out_scale
+                ),
+            );
+        }
+
         // get the output shape
         let out_dims = node_output_shapes(&node, symbol_values)?;
         // nodes vs subgraphs always have a single output
         let mut out_dims = out_dims[0].clone();
 
-        if out_dims.is_empty() {
-            out_dims = vec![1];
+        // constants (e.g empty roi / scales inputs) are consumed at load time, so they may be empty
+        if opkind.is_constant() {
+            if out_dims.is_empty() {
+                out_dims = vec![1];
+            }
+        } else {
+            out_dims = normalize_dims(&out_dims, &format!("node {}", idx))?;
         }
 
         Ok(Node {
@@ -638,7 +695,7 @@ impl Node {
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 fn rescale_const_with_single_use(
     constant: &mut Constant<Fp>,
     in_scales: Vec<crate::Scale>,
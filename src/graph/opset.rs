@@ -0,0 +1,279 @@
+use log::debug;
+use std::collections::HashMap;
+use tract_onnx::pb::attribute_proto::AttributeType;
+use tract_onnx::pb::tensor_proto::DataType;
+use tract_onnx::pb::{AttributeProto, GraphProto, ModelProto, NodeProto, TensorProto};
+
+/// The opset the node mappings in [super::utilities::new_op_from_onnx] were written against.
+pub const TARGET_OPSET: i64 = 12;
+
+/// Reduce ops whose `axes` moved from an attribute to an input in opset 18.
+const REDUCE_OPS_18: [&str; 9] = [
+    "ReduceMax",
+    "ReduceMin",
+    "ReduceMean",
+    "ReduceProd",
+    "ReduceL1",
+    "ReduceL2",
+    "ReduceLogSum",
+    "ReduceLogSumExp",
+    "ReduceSumSquare",
+];
+
+/// Ops (and the opset they changed in) whose newer signatures have no opset 12 equivalent that
+/// this pass knows how to produce. Models containing them are loaded as is.
+const UNSHIMMABLE_OPS: [(&str, i64); 6] = [
+    ("Resize", 13),
+    ("Pad", 18),
+    ("ScatterND", 16),
+    ("ScatterElements", 16),
+    ("Shape", 15),
+    ("LayerNormalization", 17),
+];
+
+/// Returns the version of the default onnx domain a model was exported with.
+fn default_opset(model: &ModelProto) -> Option<i64> {
+    model
+        .opset_import
+        .iter()
+        .find(|o| o.domain.is_empty() || o.domain == "ai.onnx")
+        .map(|o| o.version)
+}
+
+/// Rewrites nodes exported at opsets newer than [TARGET_OPSET] into their opset 12 form, e.g
+/// moving the `axes` input of Squeeze / Unsqueeze / Reduce* and the `split` input of Split back
+/// into attributes, then downgrades the model's opset.
+/// If any node can't be rewritten the model is left untouched and a warning is emitted.
+pub fn normalize_opset(model: &mut ModelProto) {
+    let version = match default_opset(model) {
+        Some(v) if v > TARGET_OPSET => v,
+        _ => return,
+    };
+    let mut graph = match model.graph.clone() {
+        Some(g) => g,
+        None => return,
+    };
+
+    match shim_graph(&mut graph, version, &HashMap::new()) {
+        Ok(()) => {
+            debug!(
+                "downgraded model from opset {} to opset {}",
+                version, TARGET_OPSET
+            );
+            model.graph = Some(graph);
+            for opset in model.opset_import.iter_mut() {
+                if opset.domain.is_empty() || opset.domain == "ai.onnx" {
+                    opset.version = TARGET_OPSET;
+                }
+            }
+        }
+        Err(reason) => crate::warnings::emit(
+            crate::warnings::WarningKind::UnsupportedOp,
+            format!(
+                "keeping model at opset {}, cannot downgrade to opset {}: {}",
+                version, TARGET_OPSET, reason
+            ),
+        ),
+    }
+}
+
+/// Decodes an int64 tensor, as used for `axes` and `split` inputs.
+fn tensor_to_i64s(tensor: &TensorProto) -> Option<Vec<i64>> {
+    if tensor.data_type != DataType::Int64 as i32 {
+        return None;
+    }
+    if !tensor.int64_data.is_empty() {
+        return Some(tensor.int64_data.clone());
+    }
+    Some(
+        tensor
+            .raw_data
+            .chunks_exact(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+fn get_attr<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute.iter().find(|a| a.name == name)
+}
+
+fn remove_attr(node: &mut NodeProto, name: &str) {
+    node.attribute.retain(|a| a.name != name);
+}
+
+/// Replaces the constant input at `idx` by an ints attribute. An empty tensor (or a missing
+/// optional input) maps to a missing attribute, which has the same meaning in opset 12.
+fn input_to_attr(
+    node: &mut NodeProto,
+    idx: usize,
+    name: &str,
+    constants: &HashMap<String, Vec<i64>>,
+) -> Result<(), String> {
+    let input = match node.input.get(idx) {
+        Some(input) if !input.is_empty() => input.clone(),
+        _ => return Ok(()),
+    };
+    let values = constants
+        .get(&input)
+        .ok_or(format!(
+            "{} of {} node {} is not constant",
+            name, node.op_type, node.name
+        ))?
+        .clone();
+
+    node.input.remove(idx);
+    remove_attr(node, name);
+    if !values.is_empty() {
+        node.attribute.push(AttributeProto {
+            name: name.to_string(),
+            r#type: AttributeType::Ints as i32,
+            ints: values,
+            ..Default::default()
+        });
+    }
+    Ok(())
+}
+
+fn shim_node(
+    node: &mut NodeProto,
+    version: i64,
+    constants: &HashMap<String, Vec<i64>>,
+) -> Result<(), String> {
+    if !(node.domain.is_empty() || node.domain == "ai.onnx") {
+        return Ok(());
+    }
+
+    let op = node.op_type.clone();
+    match op.as_str() {
+        "Squeeze" | "Unsqueeze" if version >= 13 => input_to_attr(node, 1, "axes", constants)?,
+        "ReduceSum" if version >= 13 => shim_reduce(node, constants)?,
+        o if REDUCE_OPS_18.contains(&o) && version >= 18 => shim_reduce(node, constants)?,
+        "Split" if version >= 13 => {
+            input_to_attr(node, 1, "split", constants)?;
+            // opset 12 splits evenly across the outputs, which is what num_outputs describes
+            remove_attr(node, "num_outputs");
+        }
+        "Softmax" | "LogSoftmax" | "Hardmax" if version >= 13 => {
+            // opset 12 coerces the input to 2D around `axis`, which only matches the opset 13
+            // behaviour when normalizing over the last axis
+            match get_attr(node, "axis").map(|a| a.i) {
+                None => node.attribute.push(AttributeProto {
+                    name: "axis".to_string(),
+                    r#type: AttributeType::Int as i32,
+                    i: -1,
+                    ..Default::default()
+                }),
+                Some(-1) => {}
+                Some(axis) => return Err(format!("{} node {} over axis {}", op, node.name, axis)),
+            }
+        }
+        "Reshape" if version >= 14 => {
+            if get_attr(node, "allowzero").map(|a| a.i) == Some(1) {
+                return Err(format!("Reshape node {} uses allowzero", node.name));
+            }
+            remove_attr(node, "allowzero");
+        }
+        o => {
+            if let Some((_, since)) = UNSHIMMABLE_OPS.iter().find(|(name, _)| *name == o) {
+                if version >= *since {
+                    return Err(format!("{} node {} (opset {})", o, node.name, since));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn shim_reduce(node: &mut NodeProto, constants: &HashMap<String, Vec<i64>>) -> Result<(), String> {
+    let noop = get_attr(node, "noop_with_empty_axes").map(|a| a.i) == Some(1);
+    let has_axes = match node.input.get(1) {
+        Some(input) if !input.is_empty() => constants.get(input).map(|v| !v.is_empty()),
+        _ => Some(false),
+    };
+    if noop && has_axes == Some(false) {
+        return Err(format!(
+            "{} node {} is a no-op without axes",
+            node.op_type, node.name
+        ));
+    }
+    input_to_attr(node, 1, "axes", constants)?;
+    remove_attr(node, "noop_with_empty_axes");
+    Ok(())
+}
+
+/// Rewrites every node of a graph (and its subgraphs, which can reference the constants of the
+/// enclosing scopes).
+fn shim_graph(
+    graph: &mut GraphProto,
+    version: i64,
+    outer: &HashMap<String, Vec<i64>>,
+) -> Result<(), String> {
+    let mut constants = outer.clone();
+    for tensor in graph.initializer.iter() {
+        if let Some(values) = tensor_to_i64s(tensor) {
+            constants.insert(tensor.name.clone(), values);
+        }
+    }
+    for node in graph.node.iter().filter(|n| n.op_type == "Constant") {
+        let value = get_attr(node, "value").and_then(|a| a.t.as_ref());
+        if let (Some(value), Some(output)) = (value.and_then(tensor_to_i64s), node.output.first()) {
+            constants.insert(output.clone(), value);
+        }
+    }
+
+    for node in graph.node.iter_mut() {
+        shim_node(node, version, &constants)?;
+        for attr in node.attribute.iter_mut() {
+            if let Some(subgraph) = attr.g.as_mut() {
+                shim_graph(subgraph, version, &constants)?;
+            }
+            for subgraph in attr.graphs.iter_mut() {
+                shim_graph(subgraph, version, &constants)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tract_onnx::pb::OperatorSetIdProto;
+
+    #[test]
+    fn test_squeeze_axes_input_to_attribute() {
+        let axes = TensorProto {
+            name: "axes".to_string(),
+            data_type: DataType::Int64 as i32,
+            dims: vec![1],
+            int64_data: vec![0],
+            ..Default::default()
+        };
+        let squeeze = NodeProto {
+            op_type: "Squeeze".to_string(),
+            input: vec!["x".to_string(), "axes".to_string()],
+            output: vec!["y".to_string()],
+            ..Default::default()
+        };
+        let mut model = ModelProto {
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: 13,
+            }],
+            graph: Some(GraphProto {
+                node: vec![squeeze],
+                initializer: vec![axes],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        normalize_opset(&mut model);
+
+        assert_eq!(default_opset(&model), Some(TARGET_OPSET));
+        let node = &model.graph.unwrap().node[0];
+        assert_eq!(node.input, vec!["x".to_string()]);
+        assert_eq!(get_attr(node, "axes").unwrap().ints, vec![0]);
+    }
+}
@@ -0,0 +1,183 @@
+//! A compact, proof-carrying response for inference APIs.
+//!
+//! An API serving a model returns [InferenceResponse]s built with [respond]: the outputs as
+//! floats, the proof, its public instances and how they are laid out, and the hash of the
+//! circuit settings the proof is for. A client holding the settings checks the envelope is
+//! consistent with [validate_response], i.e that the outputs it reads are the ones the proof
+//! commits to, then verifies the proof itself, see [InferenceResponse::to_snark].
+//!
+//! Only public outputs are bound by the proof. The outputs of a circuit with private or hashed
+//! outputs are returned as is and can't be checked against the proof.
+
+use super::{GraphSettings, GraphWitness, InstanceSegment};
+use crate::fieldutils::dequantize;
+use crate::pfsys::key_header::KeyParams;
+use crate::pfsys::{Snark, TranscriptType};
+use crate::Commitments;
+use halo2curves::bn256::{Fr as Fp, G1Affine};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// The current version of the response format.
+pub const RESPONSE_FORMAT_VERSION: u32 = 1;
+
+/// The payload an inference API returns to clients who want to verify its outputs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InferenceResponse {
+    /// The version of the response format
+    pub format_version: u32,
+    /// The outputs of the model, dequantized to floats
+    pub outputs: Vec<Vec<f64>>,
+    /// The proof, as a 0x prefixed hex string
+    pub proof: String,
+    /// The public instances of the proof, flattened
+    pub instances: Vec<Fp>,
+    /// How the instances are laid out, see [GraphSettings::instance_layout]
+    pub instance_layout: Vec<InstanceSegment>,
+    /// The hash of the circuit settings the proof was generated with
+    pub settings_hash: Option<String>,
+    /// The transcript the proof was generated with
+    pub transcript_type: TranscriptType,
+    /// The commitment scheme the proof was generated with
+    pub commitment: Option<Commitments>,
+}
+
+impl InferenceResponse {
+    /// The proof of the response, to be verified against the circuit's verification key.
+    pub fn to_snark(&self) -> Result<Snark<Fp, G1Affine>, Box<dyn Error>> {
+        let proof = hex::decode(self.proof.trim_start_matches("0x"))
+            .map_err(|e| format!("the proof is not valid hex: {}", e))?;
+        let mut snark = Snark::new(
+            None,
+            vec![self.instances.clone()],
+            proof,
+            Some(self.proof.clone()),
+            self.transcript_type,
+            None,
+            None,
+            self.commitment,
+        );
+        snark.settings_hash = self.settings_hash.clone();
+        Ok(snark)
+    }
+}
+
+/// Builds the response to an inference from its `witness` and `proof`, laying out the instances
+/// per the `settings` of the circuit.
+pub fn respond(
+    witness: &GraphWitness,
+    proof: &Snark<Fp, G1Affine>,
+    settings: &GraphSettings,
+) -> InferenceResponse {
+    let outputs = witness
+        .outputs
+        .iter()
+        .zip(settings.model_output_scales.iter())
+        .map(|(output, scale)| output.iter().map(|x| dequantize(*x, *scale, 0.0)).collect())
+        .collect();
+    InferenceResponse {
+        format_version: RESPONSE_FORMAT_VERSION,
+        outputs,
+        proof: format!("0x{}", hex::encode(&proof.proof)),
+        instances: proof.instances.concat(),
+        instance_layout: settings.instance_layout(),
+        settings_hash: proof.settings_hash.clone(),
+        transcript_type: proof.transcript_type,
+        commitment: proof.commitment,
+    }
+}
+
+/// Checks a response is consistent with the `settings` of the circuit it claims to be for: same
+/// settings hash and instance layout, public outputs matching the proven instances and, when
+/// exposed, the expected model hash. Doesn't verify the proof.
+pub fn validate_response(
+    response: &InferenceResponse,
+    settings: &GraphSettings,
+) -> Result<(), Box<dyn Error>> {
+    if response.format_version > RESPONSE_FORMAT_VERSION {
+        return Err(format!(
+            "response format version {} is newer than the supported version {}",
+            response.format_version, RESPONSE_FORMAT_VERSION
+        )
+        .into());
+    }
+    if let (Some(expected), Some(found)) = (settings.settings_hash(), &response.settings_hash) {
+        if &expected != found {
+            return Err("the response was generated with different circuit settings".into());
+        }
+    }
+
+    let layout = settings.instance_layout();
+    if response.instance_layout != layout {
+        return Err("the response instance layout doesn't match the circuit's".into());
+    }
+    let num_instances = layout.last().map_or(0, |s| s.offset + s.len());
+    if response.instances.len() != num_instances {
+        return Err(format!(
+            "the response has {} instances but the circuit has {}",
+            response.instances.len(),
+            num_instances
+        )
+        .into());
+    }
+
+    for (i, scale) in settings.model_output_scales.iter().enumerate() {
+        let name = format!("output_{}", i);
+        let Some(segment) = layout.iter().find(|s| s.name == name) else {
+            continue;
+        };
+        let proven = response.instances[segment.offset..segment.offset + segment.len()]
+            .iter()
+            .map(|x| dequantize(*x, *scale, 0.0))
+            .collect::<Vec<_>>();
+        if response.outputs.get(i) != Some(&proven) {
+            return Err(format!("output {} doesn't match the proven instances", i).into());
+        }
+    }
+
+    settings.check_model_hash(&response.instances)?;
+    response.to_snark()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respond() {
+        let settings = GraphSettings {
+            model_output_scales: vec![1],
+            model_instance_shapes: vec![vec![2]],
+            ..Default::default()
+        };
+        // outputs at scale 1, i.e 1.0 and -1.5
+        let outputs = vec![vec![Fp::from(2), -Fp::from(3)]];
+        let witness = GraphWitness::new(vec![], outputs.clone());
+        let mut proof = Snark::new(
+            None,
+            outputs,
+            vec![1, 2, 3],
+            None,
+            TranscriptType::EVM,
+            None,
+            None,
+            None,
+        );
+        proof.settings_hash = settings.settings_hash();
+
+        let response = respond(&witness, &proof, &settings);
+        assert_eq!(response.outputs, vec![vec![1.0, -1.5]]);
+        assert_eq!(response.proof, "0x010203");
+        validate_response(&response, &settings).unwrap();
+        assert_eq!(response.to_snark().unwrap().proof, vec![1, 2, 3]);
+
+        let mut tampered = response.clone();
+        tampered.outputs[0][1] = 1.5;
+        assert!(validate_response(&tampered, &settings).is_err());
+
+        let mut tampered = response;
+        tampered.instances.pop();
+        assert!(validate_response(&tampered, &settings).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! Limits on untrusted json inputs and witnesses.
+//!
+//! Deserializing json straight into a [super::GraphData] or [super::GraphWitness] allocates as
+//! it goes, so a deeply nested or enormous payload can exhaust memory before it is ever checked
+//! against the model. Untrusted payloads are first walked without being materialized, rejecting
+//! any that exceed the [JsonLimits] or repeat a key within an object.
+
+use super::{GraphCircuit, GraphError};
+use crate::EZKL_BUF_CAPACITY;
+use serde::de::{DeserializeSeed, Deserializer, Error as _, MapAccess, SeqAccess, Visitor};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+
+/// The default size of a json payload, in bytes.
+pub const DEFAULT_MAX_JSON_BYTES: usize = 1 << 31;
+/// The default nesting depth of a json payload. Inputs are nested at most once per axis.
+pub const DEFAULT_MAX_JSON_DEPTH: usize = 64;
+/// The default number of scalar values in a json payload.
+pub const DEFAULT_MAX_JSON_ELEMENTS: usize = 1 << 28;
+/// Scalars allowed on top of the elements of the model's tensors, e.g the fields of on-chain
+/// sources or of the witness's hashes.
+const ELEMENT_SLACK: usize = 1 << 16;
+
+/// Hard limits on a json payload. The defaults can be overridden with the
+/// `EZKL_MAX_JSON_BYTES`, `EZKL_MAX_JSON_DEPTH` and `EZKL_MAX_JSON_ELEMENTS` env variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// The size of the payload, in bytes
+    pub max_bytes: usize,
+    /// How deeply arrays and objects can be nested
+    pub max_depth: usize,
+    /// The number of scalar values, i.e numbers, strings, booleans and nulls
+    pub max_elements: usize,
+}
+
+fn env_limit(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: env_limit("EZKL_MAX_JSON_BYTES", DEFAULT_MAX_JSON_BYTES),
+            max_depth: env_limit("EZKL_MAX_JSON_DEPTH", DEFAULT_MAX_JSON_DEPTH),
+            max_elements: env_limit("EZKL_MAX_JSON_ELEMENTS", DEFAULT_MAX_JSON_ELEMENTS),
+        }
+    }
+}
+
+impl JsonLimits {
+    /// Tightens the element count to what the data of a model with these input and output
+    /// shapes can hold: its inputs, and its outputs twice over for the output data and the
+    /// expected outputs.
+    pub fn for_shapes(mut self, input_shapes: &[Vec<usize>], output_shapes: &[Vec<usize>]) -> Self {
+        let len = |shapes: &[Vec<usize>]| -> usize {
+            shapes.iter().map(|s| s.iter().product::<usize>()).sum()
+        };
+        let max_elements = len(input_shapes)
+            .saturating_add(len(output_shapes).saturating_mul(2))
+            .saturating_add(ELEMENT_SLACK);
+        self.max_elements = self.max_elements.min(max_elements);
+        self
+    }
+}
+
+impl GraphCircuit {
+    /// The default [JsonLimits], with the element count tightened to the circuit's input and
+    /// output shapes, see [JsonLimits::for_shapes].
+    pub fn json_limits(&self) -> Result<JsonLimits, Box<dyn Error>> {
+        let graph = &self.model().graph;
+        Ok(JsonLimits::default().for_shapes(&graph.input_shapes()?, &graph.output_shapes()?))
+    }
+}
+
+/// Walks a json value without materializing it, see [check_json].
+#[derive(Clone, Copy)]
+struct Walker<'a> {
+    limits: &'a JsonLimits,
+    depth: usize,
+    elements: &'a Cell<usize>,
+}
+
+impl<'a> Walker<'a> {
+    fn leaf<E: serde::de::Error>(self) -> Result<(), E> {
+        let elements = self.elements.get() + 1;
+        if elements > self.limits.max_elements {
+            return Err(E::custom(format!(
+                "more than {} elements",
+                self.limits.max_elements
+            )));
+        }
+        self.elements.set(elements);
+        Ok(())
+    }
+
+    fn nested<E: serde::de::Error>(self) -> Result<Self, E> {
+        if self.depth >= self.limits.max_depth {
+            return Err(E::custom(format!(
+                "nested deeper than {} levels",
+                self.limits.max_depth
+            )));
+        }
+        Ok(Self {
+            depth: self.depth + 1,
+            ..self
+        })
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for Walker<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for Walker<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a json value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, _: bool) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, _: i64) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, _: u64) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, _: f64) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_str<E: serde::de::Error>(self, _: &str) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<(), E> {
+        self.leaf()
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let child = self.nested()?;
+        while seq.next_element_seed(child)?.is_some() {}
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let child = self.nested()?;
+        let mut keys = HashSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if !keys.insert(key.clone()) {
+                return Err(A::Error::custom(format!("duplicate key {:?}", key)));
+            }
+            map.next_value_seed(child)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks an untrusted json payload against the `limits`, and that no object repeats a key,
+/// before it is deserialized.
+pub fn check_json(bytes: &[u8], limits: &JsonLimits) -> Result<(), GraphError> {
+    if bytes.len() > limits.max_bytes {
+        return Err(GraphError::UntrustedJson(format!(
+            "payload of {} bytes exceeds {} bytes",
+            bytes.len(),
+            limits.max_bytes
+        )));
+    }
+    let elements = Cell::new(0);
+    let walker = Walker {
+        limits,
+        depth: 0,
+        elements: &elements,
+    };
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    walker
+        .deserialize(&mut deserializer)
+        .and_then(|_| deserializer.end())
+        .map_err(|e| GraphError::UntrustedJson(e.to_string()))
+}
+
+/// Reads a json file, rejecting it before it is read if it exceeds the size in the `limits`.
+pub fn read_json(path: &Path, limits: &JsonLimits) -> Result<Vec<u8>, Box<dyn Error>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+    let len = file.metadata()?.len();
+    if len > limits.max_bytes as u64 {
+        return Err(GraphError::UntrustedJson(format!(
+            "{} is {} bytes, exceeding {} bytes",
+            path.display(),
+            len,
+            limits.max_bytes
+        ))
+        .into());
+    }
+    let mut bytes = Vec::with_capacity(len as usize);
+    std::io::BufReader::with_capacity(*EZKL_BUF_CAPACITY, file).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphData;
+
+    #[test]
+    fn test_check_json() {
+        let limits = JsonLimits {
+            max_bytes: 1024,
+            max_depth: 4,
+            max_elements: 6,
+        };
+        let data = br#"{"input_data": [[1.0, 2.0, 3.0]]}"#;
+        check_json(data, &limits).unwrap();
+        GraphData::from_untrusted(data, &limits).unwrap();
+
+        let nested = br#"{"input_data": [[[[1.0]]]]}"#;
+        assert!(check_json(nested, &limits).is_err());
+
+        let duplicate = br#"{"input_data": [[1.0]], "input_data": [[2.0]]}"#;
+        assert!(check_json(duplicate, &limits).is_err());
+
+        let large = br#"{"input_data": [[1, 2, 3, 4, 5, 6, 7]]}"#;
+        assert!(check_json(large, &limits).is_err());
+        let limits = limits.for_shapes(&[vec![7]], &[]);
+        assert_eq!(limits.max_elements, 6);
+
+        assert!(check_json(&[b' '; 2048], &limits).is_err());
+    }
+}
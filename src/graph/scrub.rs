@@ -0,0 +1,147 @@
+//! Best effort erasure of private witness material once it has been proven.
+//!
+//! In-memory buffers are zeroized and witness files overwritten before being unlinked. This covers
+//! the witness loaded for proving and the copy held by the circuit (see [GraphCircuit::scrub]), but
+//! not the buffers the prover assigns internally. Swap, copy on write filesystems and SSD wear
+//! levelling can still keep older copies around, so this narrows the window in which a witness
+//! can be recovered rather than closing it.
+
+use super::modules::ModuleForwardResult;
+use super::{GraphCircuit, GraphWitness};
+use crate::pfsys::PrettyElements;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{compiler_fence, Ordering};
+use zeroize::Zeroize;
+
+/// A field element or curve point to zeroize in place. halo2curves doesn't implement [Zeroize], so
+/// the value is overwritten with its default (zero, or the identity) and the write is kept
+/// observable so it can't be elided.
+struct Scrub<'a, T: Copy + Default>(&'a mut T);
+
+impl<T: Copy + Default> Zeroize for Scrub<'_, T> {
+    fn zeroize(&mut self) {
+        *self.0 = T::default();
+        std::hint::black_box(&*self.0);
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+fn zeroize_all<Z: Zeroize>(values: impl IntoIterator<Item = Z>) {
+    for mut value in values {
+        value.zeroize();
+    }
+}
+
+fn zeroize_flat<T: Copy + Default>(values: &mut [T]) {
+    zeroize_all(values.iter_mut().map(Scrub));
+}
+
+fn zeroize_nested<T: Copy + Default>(values: &mut Vec<Vec<T>>) {
+    for v in values.iter_mut() {
+        zeroize_flat(v);
+    }
+    values.clear();
+}
+
+fn scrub_module_result(result: &mut Option<ModuleForwardResult>) {
+    if let Some(result) = result {
+        if let Some(hash) = &mut result.poseidon_hash {
+            zeroize_flat(hash);
+        }
+        if let Some(polycommit) = &mut result.polycommit {
+            zeroize_nested(polycommit);
+        }
+    }
+    *result = None;
+}
+
+fn scrub_pretty_elements(pretty: &mut PrettyElements) {
+    for elements in [
+        &mut pretty.rescaled_inputs,
+        &mut pretty.inputs,
+        &mut pretty.processed_inputs,
+        &mut pretty.processed_params,
+        &mut pretty.processed_outputs,
+        &mut pretty.rescaled_outputs,
+        &mut pretty.outputs,
+    ] {
+        elements.zeroize();
+    }
+}
+
+/// Zeroizes the inputs, outputs and intermediate results held by a witness. Sealed outputs are
+/// encrypted and left as they are.
+pub fn scrub_witness(witness: &mut GraphWitness) {
+    zeroize_nested(&mut witness.inputs);
+    zeroize_nested(&mut witness.outputs);
+    if let Some(pretty) = &mut witness.pretty_elements {
+        scrub_pretty_elements(pretty);
+    }
+    witness.pretty_elements = None;
+    scrub_module_result(&mut witness.processed_inputs);
+    scrub_module_result(&mut witness.processed_params);
+    scrub_module_result(&mut witness.processed_outputs);
+    if let Some(caller) = &mut witness.caller {
+        zeroize_flat(std::slice::from_mut(caller));
+    }
+    witness.caller = None;
+    witness.expected_outputs = None;
+}
+
+impl GraphCircuit {
+    /// Zeroizes the witness loaded into the circuit once it is dropped, including in the copies
+    /// the prover makes of it.
+    pub fn scrub(&mut self) {
+        self.scrub = true;
+    }
+}
+
+impl Drop for GraphCircuit {
+    fn drop(&mut self) {
+        if self.scrub {
+            scrub_witness(&mut self.graph_witness);
+        }
+    }
+}
+
+/// Overwrites a file with zeros, flushes it to disk and removes it.
+pub fn shred_file(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 4096];
+    let mut remaining = file.metadata()?.len();
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::Fr as Fp;
+
+    #[test]
+    fn test_scrub() {
+        let mut witness = GraphWitness::new(vec![vec![Fp::from(3)]], vec![vec![Fp::from(5)]]);
+        witness.caller = Some(Fp::from(7));
+        scrub_witness(&mut witness);
+        assert!(witness.inputs.is_empty() && witness.outputs.is_empty());
+        assert!(witness.caller.is_none());
+
+        let mut values = vec![Fp::from(11), Fp::from(13)];
+        zeroize_flat(&mut values);
+        assert_eq!(values, vec![Fp::zero(); 2]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("witness.json");
+        std::fs::write(&path, vec![1u8; 10_000]).unwrap();
+        shred_file(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,189 @@
+use super::GraphError;
+use crate::circuit::modules::elgamal;
+use halo2curves::bn256::{Fr as Fp, G1Affine};
+use halo2curves::ff::{Field, PrimeField};
+use halo2curves::group::prime::PrimeCurveAffine;
+use halo2curves::group::{Curve, GroupEncoding};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Model outputs encrypted to an external public key (e.g a committee or timelock service key)
+/// with hashed ElGamal: `c1 = r·G` and every output element is masked by a Poseidon pad derived
+/// from the shared point `r·pk`.
+///
+/// Circuits generated with [crate::RunArgs::seal_outputs] constrain the ciphertext to encrypt the
+/// model outputs and expose it as public instances, see [crate::circuit::modules::elgamal].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SealedOutputs {
+    /// The public key the outputs are sealed to
+    pub public_key: G1Affine,
+    /// The ephemeral key `r·G`
+    pub c1: G1Affine,
+    /// The masked outputs, with the same shapes as the witness outputs
+    pub c2: Vec<Vec<Fp>>,
+    /// The randomness `r`, which the prover needs to lay out the sealing. Anyone holding it can
+    /// unseal the outputs, so it must not leave the witness
+    #[serde(default)]
+    pub randomness: Fp,
+}
+
+/// Parses a hex encoded compressed G1 point.
+pub fn public_key_from_hex(hex_key: &str) -> Result<G1Affine, GraphError> {
+    let bytes = hex::decode(hex_key.trim().trim_start_matches("0x"))
+        .map_err(|e| GraphError::Sealing(format!("invalid hex public key: {}", e)))?;
+    let mut repr = <G1Affine as GroupEncoding>::Repr::default();
+    if bytes.len() != repr.as_ref().len() {
+        return Err(GraphError::Sealing(format!(
+            "expected a {} byte compressed public key, got {} bytes",
+            repr.as_ref().len(),
+            bytes.len()
+        )));
+    }
+    repr.as_mut().copy_from_slice(&bytes);
+    let public_key: G1Affine = Option::from(G1Affine::from_bytes(&repr)).ok_or(
+        GraphError::Sealing("public key is not on the curve".to_string()),
+    )?;
+    // every shared point would be the identity, whose pads anyone can derive
+    if bool::from(public_key.is_identity()) {
+        return Err(GraphError::Sealing(
+            "public key is the identity".to_string(),
+        ));
+    }
+    Ok(public_key)
+}
+
+/// Hex encodes a public key as a compressed G1 point.
+pub fn public_key_to_hex(pk: &G1Affine) -> String {
+    hex::encode(pk.to_bytes())
+}
+
+/// Parses a hex encoded (little endian) secret key.
+pub fn secret_key_from_hex(hex_key: &str) -> Result<Fp, GraphError> {
+    let bytes = hex::decode(hex_key.trim().trim_start_matches("0x"))
+        .map_err(|e| GraphError::Sealing(format!("invalid hex secret key: {}", e)))?;
+    let repr: [u8; 32] = bytes.as_slice().try_into().map_err(|_| {
+        GraphError::Sealing(format!(
+            "expected a 32 byte secret key, got {} bytes",
+            bytes.len()
+        ))
+    })?;
+    Option::from(Fp::from_repr(repr)).ok_or(GraphError::Sealing(
+        "secret key is not a valid scalar".to_string(),
+    ))
+}
+
+/// Generates a secret key and its public key. Meant for tests and local setups, a committee or
+/// timelock service would derive its own.
+pub fn gen_keypair(rng: impl RngCore) -> (Fp, G1Affine) {
+    let sk = Fp::random(rng);
+    (sk, (G1Affine::generator() * sk).to_affine())
+}
+
+fn mask(
+    outputs: &[Vec<Fp>],
+    shared: &G1Affine,
+    op: impl Fn(Fp, Fp) -> Fp,
+) -> Result<Vec<Vec<Fp>>, GraphError> {
+    let len = outputs.iter().map(|output| output.len()).sum();
+    let pads = elgamal::pads(shared, len)
+        .map_err(|e| GraphError::Sealing(e.to_string()))?
+        .ok_or(GraphError::Sealing(
+            "the shared point is the identity".to_string(),
+        ))?;
+    let mut pads = pads.into_iter();
+    Ok(outputs
+        .iter()
+        .map(|output| {
+            output
+                .iter()
+                .zip(pads.by_ref())
+                .map(|(x, p)| op(*x, p))
+                .collect()
+        })
+        .collect())
+}
+
+/// Seals the outputs to `public_key` under fresh randomness.
+pub fn seal(
+    outputs: &[Vec<Fp>],
+    public_key: &G1Affine,
+    mut rng: impl RngCore,
+) -> Result<SealedOutputs, GraphError> {
+    if bool::from(public_key.is_identity()) {
+        return Err(GraphError::Sealing(
+            "cannot seal to the identity".to_string(),
+        ));
+    }
+    let r = loop {
+        let r = Fp::random(&mut rng);
+        if r != Fp::ZERO {
+            break r;
+        }
+    };
+    let c1 = (G1Affine::generator() * r).to_affine();
+    let shared = (*public_key * r).to_affine();
+    Ok(SealedOutputs {
+        public_key: *public_key,
+        c1,
+        c2: mask(outputs, &shared, |x, p| x + p)?,
+        randomness: r,
+    })
+}
+
+impl SealedOutputs {
+    /// Recovers the outputs with the secret key matching [SealedOutputs::public_key].
+    pub fn unseal(&self, secret_key: &Fp) -> Result<Vec<Vec<Fp>>, GraphError> {
+        if (G1Affine::generator() * secret_key).to_affine() != self.public_key {
+            return Err(GraphError::Sealing(
+                "the secret key does not match the public key the outputs were sealed to"
+                    .to_string(),
+            ));
+        }
+        let shared = (self.c1 * secret_key).to_affine();
+        mask(&self.c2, &shared, |x, p| x - p)
+    }
+
+    /// The public instances the sealing exposes: the limbs of the public key and of `c1`,
+    /// followed by the flattened masked outputs.
+    pub fn instances(&self) -> Result<Vec<Fp>, GraphError> {
+        let limbs = |point: &G1Affine| {
+            elgamal::point_to_limbs(point).ok_or(GraphError::Sealing(
+                "cannot expose the identity".to_string(),
+            ))
+        };
+        let mut instances = limbs(&self.public_key)?;
+        instances.extend(limbs(&self.c1)?);
+        instances.extend(self.c2.iter().flatten());
+        Ok(instances)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let (sk, pk) = gen_keypair(OsRng);
+        let pk = public_key_from_hex(&public_key_to_hex(&pk)).unwrap();
+        let outputs = vec![vec![Fp::from(3), Fp::from(3)], vec![Fp::from(7)]];
+
+        let sealed = seal(&outputs, &pk, OsRng).unwrap();
+        // every element gets its own pad, so equal outputs don't leak
+        assert_ne!(sealed.c2[0][0], sealed.c2[0][1]);
+        assert_eq!(sealed.unseal(&sk).unwrap(), outputs);
+
+        let (wrong_sk, _) = gen_keypair(OsRng);
+        assert!(sealed.unseal(&wrong_sk).is_err());
+
+        assert_eq!(sealed.instances().unwrap().len(), elgamal::num_instances(3));
+    }
+
+    #[test]
+    fn test_reject_identity() {
+        let identity = public_key_to_hex(&G1Affine::identity());
+        assert!(public_key_from_hex(&identity).is_err());
+        assert!(seal(&[vec![Fp::ONE]], &G1Affine::identity(), OsRng).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use super::GraphSettings;
+use crate::pfsys::key_header::{KeyHeader, KeyParams};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+
+/// A field of the settings in which two settings, or settings and a key, differ.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettingsDifference {
+    /// The path of the field, e.g `run_args.logrows` or `required_lookups[2]`
+    pub field: String,
+    /// The field in the first settings, unset if it is missing there
+    pub left: Option<Value>,
+    /// The field in the second settings or key, unset if it is missing there
+    pub right: Option<Value>,
+    /// Whether keys generated from one settings can't be used with the other
+    pub requires_new_keys: bool,
+}
+
+impl std::fmt::Display for SettingsDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |v: &Option<Value>| v.as_ref().map_or("<missing>".into(), |v| v.to_string());
+        write!(
+            f,
+            "{}: {} != {} ({})",
+            self.field,
+            show(&self.left),
+            show(&self.right),
+            if self.requires_new_keys {
+                "changes the circuit, keys have to be regenerated"
+            } else {
+                "doesn't change the circuit, keys are compatible"
+            }
+        )
+    }
+}
+
+/// A step into a json value.
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+fn field_name(path: &[Step]) -> String {
+    let mut name = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) if name.is_empty() => name.push_str(key),
+            Step::Key(key) => name.push_str(&format!(".{}", key)),
+            Step::Index(i) => name.push_str(&format!("[{}]", i)),
+        }
+    }
+    name
+}
+
+/// The leaves at which two json values differ, descending into objects and into arrays of the
+/// same length.
+fn diff_values(
+    path: &mut Vec<Step>,
+    left: Option<&Value>,
+    right: Option<&Value>,
+) -> Vec<Vec<Step>> {
+    match (left, right) {
+        (Some(l), Some(r)) if l == r => vec![],
+        (Some(Value::Object(l)), Some(Value::Object(r))) => {
+            let mut keys = l.keys().chain(r.keys()).cloned().collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            let mut paths = vec![];
+            for key in keys {
+                path.push(Step::Key(key.clone()));
+                paths.extend(diff_values(path, l.get(&key), r.get(&key)));
+                path.pop();
+            }
+            paths
+        }
+        (Some(Value::Array(l)), Some(Value::Array(r))) if l.len() == r.len() => {
+            let mut paths = vec![];
+            for (i, (l, r)) in l.iter().zip(r).enumerate() {
+                path.push(Step::Index(i));
+                paths.extend(diff_values(path, Some(l), Some(r)));
+                path.pop();
+            }
+            paths
+        }
+        _ => vec![path.clone()],
+    }
+}
+
+fn get<'a>(value: &'a Value, path: &[Step]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, step| match step {
+        Step::Key(key) => value.get(key),
+        Step::Index(i) => value.get(i),
+    })
+}
+
+/// Sets the field at `path` of `value` to `field`, removing it if unset.
+fn set(value: &mut Value, path: &[Step], field: Option<Value>) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let Some(parent) = parents.iter().try_fold(value, |value, step| match step {
+        Step::Key(key) => value.get_mut(key),
+        Step::Index(i) => value.get_mut(i),
+    }) else {
+        return;
+    };
+    match (last, parent, field) {
+        (Step::Key(key), Value::Object(object), Some(field)) => {
+            object.insert(key.clone(), field);
+        }
+        (Step::Key(key), Value::Object(object), None) => {
+            object.remove(key);
+        }
+        (Step::Index(i), Value::Array(array), Some(field)) if *i < array.len() => {
+            array[*i] = field;
+        }
+        _ => {}
+    }
+}
+
+/// Whether changing the field at `path` of `left` to its value in `right` changes the circuit.
+/// Fields are judged by whether they change [KeyParams::settings_hash], which keys are checked
+/// against when loaded, except for the commitment scheme: it is left out of the hash because
+/// halo2 rejects keys of another scheme on its own.
+fn requires_new_keys(left: &Value, right: &Value, path: &[Step], hash: &Option<String>) -> bool {
+    if field_name(path) == "run_args.commitment" {
+        return true;
+    }
+    let mut changed = left.clone();
+    set(&mut changed, path, get(right, path).cloned());
+    match serde_json::from_value::<GraphSettings>(changed) {
+        Ok(changed) => &changed.settings_hash() != hash,
+        // a field that can't change on its own, e.g an element of a list of another shape
+        Err(_) => true,
+    }
+}
+
+/// Compares two settings field by field, telling for each field that differs whether keys
+/// generated from one settings can be used with the other.
+pub fn diff_settings(
+    left: &GraphSettings,
+    right: &GraphSettings,
+) -> Result<Vec<SettingsDifference>, Box<dyn Error>> {
+    let (left, right) = (serde_json::to_value(left)?, serde_json::to_value(right)?);
+    let hash = serde_json::from_value::<GraphSettings>(left.clone())?.settings_hash();
+    Ok(diff_values(&mut vec![], Some(&left), Some(&right))
+        .into_iter()
+        .map(|path| SettingsDifference {
+            field: field_name(&path),
+            left: get(&left, &path).cloned(),
+            right: get(&right, &path).cloned(),
+            requires_new_keys: requires_new_keys(&left, &right, &path, &hash),
+        })
+        .collect())
+}
+
+/// Compares settings with the header of a key generated from them. Headers only record the
+/// logrows and hash of the settings, so when the hash differs the settings the key was generated
+/// from are needed to tell which fields changed.
+pub fn diff_key_header(settings: &GraphSettings, header: &KeyHeader) -> Vec<SettingsDifference> {
+    let mut differences = vec![];
+    if settings.run_args.logrows != header.logrows {
+        differences.push(SettingsDifference {
+            field: "run_args.logrows".into(),
+            left: Some(settings.run_args.logrows.into()),
+            right: Some(header.logrows.into()),
+            requires_new_keys: true,
+        });
+    }
+    let hash = settings.settings_hash();
+    if let (Some(expected), Some(found)) = (&hash, &header.settings_hash) {
+        if expected != found {
+            differences.push(SettingsDifference {
+                field: "settings_hash".into(),
+                left: Some(expected.clone().into()),
+                right: Some(found.clone().into()),
+                requires_new_keys: true,
+            });
+        }
+    }
+    if settings.version != header.ezkl_version {
+        differences.push(SettingsDifference {
+            field: "version".into(),
+            left: Some(settings.version.clone().into()),
+            right: Some(header.ezkl_version.clone().into()),
+            requires_new_keys: false,
+        });
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_settings() {
+        let left = GraphSettings::default();
+        assert!(diff_settings(&left, &left).unwrap().is_empty());
+
+        let mut right = left.clone();
+        right.run_args.logrows += 1;
+        right.version = "1.0.0".into();
+        right.num_rows = 42;
+        let differences = diff_settings(&left, &right).unwrap();
+
+        let difference = |field: &str| differences.iter().find(|d| d.field == field).unwrap();
+        assert_eq!(differences.len(), 3);
+        assert!(difference("run_args.logrows").requires_new_keys);
+        assert!(difference("num_rows").requires_new_keys);
+        assert!(!difference("version").requires_new_keys);
+        assert_eq!(difference("num_rows").right, Some(42.into()));
+
+        let header = KeyHeader::new::<()>(left.run_args.logrows, &left);
+        assert!(diff_key_header(&left, &header)
+            .iter()
+            .all(|d| !d.requires_new_keys));
+        let differences = diff_key_header(&right, &header);
+        assert!(differences
+            .iter()
+            .any(|d| d.field == "settings_hash" && d.requires_new_keys));
+    }
+}
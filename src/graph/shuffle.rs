@@ -0,0 +1,104 @@
+//! Gathers with constant indices laid out in the shuffle columns.
+//!
+//! A gather whose indices are constants only rearranges the cells of its input, which every
+//! consumer then copies out of wherever they were assigned. With
+//! [crate::RunArgs::shuffle_permutations] such gathers are replaced by a [PolyOp::Rearrange]
+//! reading the same elements, whose output is assigned afresh and looked up among the input
+//! elements, each tagged with its position, see [crate::circuit::layouts::rearrange].
+
+use super::model::NodeType;
+use super::node::SupportedOp;
+use crate::circuit::hybrid::HybridOp;
+use crate::circuit::poly::PolyOp;
+use crate::tensor::{ops, Tensor, TensorError};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// The flat index of the input element each output element of `op` reads, if `op` is a gather
+/// with constant indices over an input of `input_dims`.
+pub fn rearrangement(
+    op: &SupportedOp,
+    input_dims: &[usize],
+) -> Option<Result<Tensor<usize>, TensorError>> {
+    let positions = || -> Result<Tensor<usize>, TensorError> {
+        let mut positions = Tensor::from(0..input_dims.iter().product::<usize>());
+        positions.reshape(input_dims)?;
+        Ok(positions)
+    };
+    Some(match op {
+        SupportedOp::Hybrid(HybridOp::Gather {
+            dim,
+            constant_idx: Some(idx),
+        }) => positions().and_then(|p| ops::gather(&p, idx, *dim)),
+        SupportedOp::Linear(PolyOp::GatherElements {
+            dim,
+            constant_idx: Some(idx),
+        }) => positions().and_then(|p| ops::gather_elements(&p, idx, *dim)),
+        SupportedOp::Linear(PolyOp::GatherND {
+            batch_dims,
+            indices: Some(idx),
+        }) => positions().and_then(|p| ops::gather_nd(&p, idx, *batch_dims)),
+        _ => return None,
+    })
+}
+
+/// Replaces the gathers with constant indices of `nodes` with [PolyOp::Rearrange], returning the
+/// number of ops replaced.
+pub fn shuffle_permutations(
+    nodes: &mut BTreeMap<usize, NodeType>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut num_replaced = 0;
+    let candidates: Vec<usize> = nodes.keys().copied().collect();
+    for idx in candidates {
+        let Some(NodeType::Node(n)) = nodes.get(&idx) else {
+            continue;
+        };
+        let Some(input) = n.inputs.first() else {
+            continue;
+        };
+        let input_dims = nodes
+            .get(&input.0)
+            .and_then(|i| i.out_dims().get(input.1).cloned())
+            .ok_or("input not found")?;
+        let Some(indices) = rearrangement(&n.opkind, &input_dims) else {
+            continue;
+        };
+        let indices = indices?;
+        if let Some(NodeType::Node(n)) = nodes.get_mut(&idx) {
+            n.opkind = SupportedOp::Linear(PolyOp::Rearrange { indices });
+            num_replaced += 1;
+        }
+    }
+    Ok(num_replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::Op;
+    use halo2curves::bn256::Fr as Fp;
+
+    #[test]
+    fn test_rearrangement() {
+        let op = SupportedOp::Hybrid(HybridOp::Gather {
+            dim: 1,
+            constant_idx: Some(Tensor::new(Some(&[2, 0]), &[2]).unwrap()),
+        });
+        let indices = rearrangement(&op, &[2, 3]).unwrap().unwrap();
+        assert_eq!(indices, Tensor::new(Some(&[2, 0, 5, 3]), &[2, 2]).unwrap());
+
+        // the rearrangement reads the same elements as the gather
+        let x = Tensor::new(Some(&(1..=6u64).map(Fp::from).collect::<Vec<_>>()), &[2, 3]).unwrap();
+        let rearrange = PolyOp::Rearrange { indices };
+        assert_eq!(
+            Op::<Fp>::f(&rearrange, &[x.clone()]).unwrap().output,
+            Op::<Fp>::f(&op, &[x]).unwrap().output
+        );
+
+        let op = SupportedOp::Linear(PolyOp::GatherElements {
+            dim: 0,
+            constant_idx: None,
+        });
+        assert!(rearrangement(&op, &[2, 3]).is_none());
+    }
+}
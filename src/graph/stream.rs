@@ -0,0 +1,194 @@
+//! Witness generation over streams of inputs, for high throughput proving pipelines.
+//!
+//! [GraphCircuit::gen_witnesses] computes the parts of a forward pass that don't depend on the
+//! inputs (visibilities, scales and the hashes or commitments of the params) once, and reuses them
+//! for every input of the stream. Witnesses are generated lazily as the stream is consumed, so a
+//! pipeline only holds as many inputs and witnesses in memory as it pulls at a time.
+
+use super::input::{DataSource, GraphData};
+use super::modules::{GraphModules, ModuleForwardResult};
+use super::vars::VarVisibility;
+use super::{GraphCircuit, GraphWitness};
+use crate::circuit::InputType;
+use crate::tensor::Tensor;
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::commitment::CommitmentScheme;
+use halo2curves::bn256::{Fr as Fp, G1Affine};
+use std::error::Error;
+
+/// The state of a forward pass that doesn't depend on its inputs.
+#[derive(Clone, Debug)]
+pub(crate) struct ForwardContext {
+    pub(crate) visibility: VarVisibility,
+    pub(crate) input_scales: Vec<crate::Scale>,
+    pub(crate) output_scales: Vec<crate::Scale>,
+    /// the hashes or commitments of the params, if they are processed
+    pub(crate) processed_params: Option<ModuleForwardResult>,
+}
+
+impl ForwardContext {
+    pub(crate) fn new<Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>>(
+        circuit: &GraphCircuit,
+        vk: Option<&VerifyingKey<G1Affine>>,
+        srs: Option<&Scheme::ParamsProver>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let visibility = VarVisibility::from_args(&circuit.settings().run_args)?;
+
+        let mut processed_params = None;
+        if visibility.params.requires_processing() {
+            let params = circuit.model().get_all_params();
+            if !params.is_empty() {
+                let flattened_params = Tensor::new(Some(&params), &[params.len()])?.combine()?;
+                processed_params = Some(GraphModules::forward::<Scheme>(
+                    &[flattened_params],
+                    &visibility.params,
+                    circuit.settings().run_args.hash_chunk_size,
+                    vk,
+                    srs,
+                )?);
+            }
+        }
+
+        Ok(Self {
+            input_scales: circuit.model().graph.get_input_scales(),
+            output_scales: circuit.model().graph.get_output_scales()?,
+            visibility,
+            processed_params,
+        })
+    }
+}
+
+/// The witnesses of a stream of inputs, see [GraphCircuit::gen_witnesses].
+pub struct WitnessStream<'a, Scheme: CommitmentScheme, I> {
+    circuit: &'a GraphCircuit,
+    inputs: I,
+    context: ForwardContext,
+    shapes: Vec<Vec<usize>>,
+    input_types: Vec<InputType>,
+    vk: Option<&'a VerifyingKey<G1Affine>>,
+    srs: Option<&'a Scheme::ParamsProver>,
+}
+
+impl<'a, Scheme, I> WitnessStream<'a, Scheme, I>
+where
+    Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>,
+{
+    fn witness(&self, data: &GraphData) -> Result<GraphWitness, Box<dyn Error>> {
+        let DataSource::File(file_data) = &data.input_data else {
+            return Err("only file data sources can be streamed".into());
+        };
+        let mut inputs = self.circuit.load_file_data(
+            file_data,
+            &self.shapes,
+            self.context.input_scales.clone(),
+            self.input_types.clone(),
+        )?;
+        self.circuit.forward_with_context::<Scheme>(
+            &self.context,
+            &mut inputs,
+            self.vk,
+            self.srs,
+            false,
+        )
+    }
+}
+
+impl<'a, Scheme, I> Iterator for WitnessStream<'a, Scheme, I>
+where
+    Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>,
+    I: Iterator<Item = GraphData>,
+{
+    type Item = Result<GraphWitness, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.inputs.next()?;
+        Some(self.witness(&data))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+impl GraphCircuit {
+    /// Generates the witness of each of `inputs`, lazily as the returned stream is consumed.
+    ///
+    /// Each witness is the one [GraphCircuit::forward] generates for that input, but the input
+    /// independent work, such as hashing the params, is only done once for the whole stream. Only
+    /// file data sources are supported, on-chain and database sources have to be fetched
+    /// asynchronously and loaded with [GraphCircuit::load_graph_input].
+    pub fn gen_witnesses<'a, Scheme, I>(
+        &'a self,
+        inputs: I,
+        vk: Option<&'a VerifyingKey<G1Affine>>,
+        srs: Option<&'a Scheme::ParamsProver>,
+    ) -> Result<WitnessStream<'a, Scheme, I::IntoIter>, Box<dyn Error>>
+    where
+        Scheme: CommitmentScheme<Scalar = Fp, Curve = G1Affine>,
+        I: IntoIterator<Item = GraphData>,
+    {
+        Ok(WitnessStream {
+            circuit: self,
+            inputs: inputs.into_iter(),
+            context: ForwardContext::new::<Scheme>(self, vk, srs)?,
+            shapes: self.model().graph.input_shapes()?,
+            input_types: self.model().graph.get_input_types()?,
+            vk,
+            srs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::input::FileSourceInner;
+    use crate::graph::model::GraphBuilder;
+    use crate::RunArgs;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::Bn256;
+
+    #[test]
+    fn test_gen_witnesses() {
+        let run_args = RunArgs::default();
+        let mut builder = GraphBuilder::new(&run_args).unwrap();
+        let x = builder.add_input(vec![1, 4], InputType::F32).unwrap();
+        let y = builder.add_relu(x).unwrap();
+        let model = builder.finalize(&[y]).unwrap();
+        let circuit = GraphCircuit::new(model, &run_args).unwrap();
+
+        let data = |values: [f64; 4]| {
+            GraphData::new(DataSource::File(vec![values
+                .iter()
+                .map(|v| FileSourceInner::Float(*v))
+                .collect()]))
+        };
+        let inputs = vec![data([1.0, -2.0, 3.0, -4.0]), data([-1.0, 2.0, -3.0, 4.0])];
+
+        let witnesses = circuit
+            .gen_witnesses::<KZGCommitmentScheme<Bn256>, _>(inputs.clone(), None, None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(witnesses.len(), 2);
+
+        for (data, witness) in inputs.iter().zip(witnesses) {
+            let DataSource::File(file_data) = &data.input_data else {
+                unreachable!()
+            };
+            let mut input = circuit
+                .load_file_data(
+                    file_data,
+                    &circuit.model().graph.input_shapes().unwrap(),
+                    circuit.model().graph.get_input_scales(),
+                    circuit.model().graph.get_input_types().unwrap(),
+                )
+                .unwrap();
+            let expected = circuit
+                .forward::<KZGCommitmentScheme<Bn256>>(&mut input, None, None, false)
+                .unwrap();
+            assert_eq!(witness.inputs, expected.inputs);
+            assert_eq!(witness.outputs, expected.outputs);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Small ready-made models for common tasks.
+//!
+//! Proving a simple formula, e.g a linear regression, shouldn't require exporting a model from
+//! pytorch. A [Template] is an onnx model for a [TemplateTask] over `num_features` inputs, along
+//! with example input data and the [RunArgs] it is best proven with. Its weights are seeded
+//! placeholders stored as initializers named `weight*` and `bias*`, to be replaced by the
+//! trained values.
+
+use super::input::{DataSource, GraphData};
+use super::Visibility;
+use crate::RunArgs;
+use prost::Message;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use tosubcommand::ToFlags;
+use tract_onnx::pb::attribute_proto::AttributeType;
+use tract_onnx::pb::tensor_proto::DataType;
+use tract_onnx::pb::tensor_shape_proto::{dimension, Dimension};
+use tract_onnx::pb::type_proto::{Tensor as TensorType, Value as TypeValue};
+use tract_onnx::pb::{
+    AttributeProto, GraphProto, ModelProto, NodeProto, OperatorSetIdProto, TensorProto,
+    TensorShapeProto, TypeProto, ValueInfoProto,
+};
+
+/// The opset templates are exported at, see [super::opset::TARGET_OPSET].
+const TEMPLATE_OPSET: i64 = super::opset::TARGET_OPSET;
+/// The onnx ir version matching [TEMPLATE_OPSET].
+const TEMPLATE_IR_VERSION: i64 = 7;
+
+/// The task a [Template] model performs on a row of features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum TemplateTask {
+    /// `x · w + b`
+    LinearRegression,
+    /// `sigmoid(x · w + b)`
+    Logistic,
+    /// `relu(x · W1 + b1) · w2 + b2`, with a single hidden layer
+    Mlp,
+    /// `Σ (x - μ)²`, the squared distance of the features from their expected values
+    AnomalyScore,
+}
+
+impl FromStr for TemplateTask {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear-regression" => Ok(TemplateTask::LinearRegression),
+            "logistic" => Ok(TemplateTask::Logistic),
+            "mlp" => Ok(TemplateTask::Mlp),
+            "anomaly-score" => Ok(TemplateTask::AnomalyScore),
+            _ => Err(format!(
+                "invalid task {}, expected one of linear-regression, logistic, mlp, anomaly-score",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for TemplateTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateTask::LinearRegression => write!(f, "linear-regression"),
+            TemplateTask::Logistic => write!(f, "logistic"),
+            TemplateTask::Mlp => write!(f, "mlp"),
+            TemplateTask::AnomalyScore => write!(f, "anomaly-score"),
+        }
+    }
+}
+
+impl TemplateTask {
+    /// The scale of the inputs and weights of the task's template. The rescalings of the linear
+    /// tasks are range checked over tables of `2^scale` rows, the nonlinearities of the others are
+    /// lookup tables whose rows grow with the scale and the number of features, so the linear
+    /// tasks afford more precision.
+    fn scale(&self) -> crate::Scale {
+        match self {
+            TemplateTask::LinearRegression => 12,
+            TemplateTask::AnomalyScore => 10,
+            TemplateTask::Logistic | TemplateTask::Mlp => 8,
+        }
+    }
+
+    /// The run args the template of the task over `num_features` features is best proven with.
+    ///
+    /// The inputs, weights and biases of the templates are in `[-1, 1)`, so the outputs of a
+    /// dense layer are less than `num_features + 1` in magnitude. The lookup range covers them at
+    /// the task's scale for the sigmoid and relu lookups, and the logrows fit the largest table,
+    /// with as many rows again for the layout and the blinding factors.
+    pub fn run_args(&self, num_features: usize) -> RunArgs {
+        let scale = self.scale();
+        let lookup_range = match self {
+            TemplateTask::Logistic | TemplateTask::Mlp => {
+                let bound = (num_features as i128 + 1) << scale;
+                (-bound, bound)
+            }
+            // the linear tasks have no lookups
+            TemplateTask::LinearRegression | TemplateTask::AnomalyScore => (0, 0),
+        };
+        let table_len = ((lookup_range.1 - lookup_range.0 + 1) as u64).max(1 << scale);
+        RunArgs {
+            input_scale: scale,
+            param_scale: scale,
+            lookup_range,
+            logrows: table_len.next_power_of_two().trailing_zeros() + 1,
+            // the weights are part of the model, so they are baked into the circuit rather than
+            // witnessed, and the prediction is what gets proven
+            param_visibility: Visibility::Fixed,
+            ..RunArgs::default()
+        }
+    }
+}
+
+impl ToFlags for TemplateTask {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+/// A model for a [TemplateTask], with example input data and the run args to prove it with.
+#[derive(Clone, Debug)]
+pub struct Template {
+    /// The onnx model
+    pub model: ModelProto,
+    /// Example input data for the model
+    pub data: GraphData,
+    /// The run args the model is best proven with
+    pub run_args: RunArgs,
+}
+
+/// Builds the graph of a template, naming and seeding its tensors.
+struct Builder {
+    rng: StdRng,
+    graph: GraphProto,
+}
+
+impl Builder {
+    fn float_info(name: &str, dims: &[usize]) -> ValueInfoProto {
+        let dim = dims
+            .iter()
+            .map(|d| Dimension {
+                value: Some(dimension::Value::DimValue(*d as i64)),
+                ..Default::default()
+            })
+            .collect();
+        ValueInfoProto {
+            name: name.to_string(),
+            r#type: Some(TypeProto {
+                value: Some(TypeValue::TensorType(TensorType {
+                    elem_type: DataType::Float as i32,
+                    shape: Some(TensorShapeProto { dim }),
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Adds an initializer of `dims` with seeded values in `[-1, 1)`.
+    fn weight(&mut self, name: &str, dims: &[usize]) -> String {
+        let len = dims.iter().product::<usize>();
+        let float_data = (0..len).map(|_| self.rng.gen_range(-1.0..1.0)).collect();
+        self.graph.initializer.push(TensorProto {
+            name: name.to_string(),
+            data_type: DataType::Float as i32,
+            dims: dims.iter().map(|d| *d as i64).collect(),
+            float_data,
+            ..Default::default()
+        });
+        name.to_string()
+    }
+
+    fn node(&mut self, op_type: &str, inputs: &[&str], attribute: Vec<AttributeProto>) -> String {
+        let output = format!("{}_{}", op_type.to_lowercase(), self.graph.node.len());
+        self.graph.node.push(NodeProto {
+            name: output.clone(),
+            op_type: op_type.to_string(),
+            input: inputs.iter().map(|i| i.to_string()).collect(),
+            output: vec![output.clone()],
+            attribute,
+            ..Default::default()
+        });
+        output
+    }
+
+    fn int_attr(name: &str, value: i64) -> AttributeProto {
+        AttributeProto {
+            name: name.to_string(),
+            r#type: AttributeType::Int as i32,
+            i: value,
+            ..Default::default()
+        }
+    }
+
+    /// A dense layer from `input` of `num_in` features to `num_out`, in the form pytorch exports
+    /// `nn.Linear`.
+    fn dense(&mut self, input: &str, num_in: usize, num_out: usize, layer: usize) -> String {
+        let weight = self.weight(&format!("weight{}", layer), &[num_out, num_in]);
+        let bias = self.weight(&format!("bias{}", layer), &[num_out]);
+        self.node(
+            "Gemm",
+            &[input, &weight, &bias],
+            vec![Self::int_attr("transB", 1)],
+        )
+    }
+}
+
+impl Template {
+    /// The template for `task` over `num_features` features, with `hidden_size` hidden units for
+    /// [TemplateTask::Mlp], its placeholder weights and example data drawn from `seed`.
+    pub fn new(
+        task: TemplateTask,
+        num_features: usize,
+        hidden_size: usize,
+        seed: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        if num_features == 0 || hidden_size == 0 {
+            return Err("templates need at least one feature and one hidden unit".into());
+        }
+        let mut builder = Builder {
+            rng: StdRng::seed_from_u64(seed),
+            graph: GraphProto {
+                name: task.to_string(),
+                input: vec![Builder::float_info("input", &[1, num_features])],
+                ..Default::default()
+            },
+        };
+
+        let output = match task {
+            TemplateTask::LinearRegression => builder.dense("input", num_features, 1, 0),
+            TemplateTask::Logistic => {
+                let logits = builder.dense("input", num_features, 1, 0);
+                builder.node("Sigmoid", &[&logits], vec![])
+            }
+            TemplateTask::Mlp => {
+                let hidden = builder.dense("input", num_features, hidden_size, 0);
+                let hidden = builder.node("Relu", &[&hidden], vec![]);
+                builder.dense(&hidden, hidden_size, 1, 1)
+            }
+            TemplateTask::AnomalyScore => {
+                let mean = builder.weight("bias0", &[1, num_features]);
+                let diff = builder.node("Sub", &["input", &mean], vec![]);
+                let squared = builder.node("Mul", &[&diff, &diff], vec![]);
+                builder.node(
+                    "ReduceSum",
+                    &[&squared],
+                    vec![
+                        AttributeProto {
+                            name: "axes".to_string(),
+                            r#type: AttributeType::Ints as i32,
+                            ints: vec![1],
+                            ..Default::default()
+                        },
+                        Builder::int_attr("keepdims", 1),
+                    ],
+                )
+            }
+        };
+        builder
+            .graph
+            .output
+            .push(Builder::float_info(&output, &[1, 1]));
+
+        let input = (0..num_features)
+            .map(|_| builder.rng.gen_range(-1.0..1.0))
+            .collect::<Vec<f64>>();
+
+        let model = ModelProto {
+            ir_version: TEMPLATE_IR_VERSION,
+            producer_name: "ezkl".to_string(),
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: TEMPLATE_OPSET,
+            }],
+            graph: Some(builder.graph),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            model,
+            data: GraphData::new(DataSource::from(vec![input])),
+            run_args: task.run_args(num_features),
+        })
+    }
+
+    /// Writes the onnx model to `model_path` and the example data to `data_path`.
+    pub fn save(&self, model_path: &Path, data_path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(model_path, self.model.encode_to_vec())?;
+        self.data.save(data_path.to_path_buf())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphCircuit;
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use halo2curves::bn256::Bn256;
+
+    #[test]
+    fn test_templates_tuned() {
+        let linear = TemplateTask::LinearRegression.run_args(3);
+        assert_eq!((linear.input_scale, linear.lookup_range), (12, (0, 0)));
+        assert_eq!(linear.logrows, 13);
+
+        // the lookup range covers the logits of 3 features at scale 8
+        let logistic = TemplateTask::Logistic.run_args(3);
+        assert_eq!(logistic.lookup_range, (-1024, 1024));
+        assert_eq!(logistic.logrows, 13);
+        assert!(TemplateTask::Logistic.run_args(100).logrows > logistic.logrows);
+    }
+
+    #[test]
+    fn test_templates_mock_prove() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("network.onnx");
+        let data_path = dir.path().join("input.json");
+        for task in [
+            TemplateTask::LinearRegression,
+            TemplateTask::Logistic,
+            TemplateTask::Mlp,
+            TemplateTask::AnomalyScore,
+        ] {
+            let template = Template::new(task, 3, 4, 0).unwrap();
+            template.save(&model_path, &data_path).unwrap();
+
+            let mut circuit = GraphCircuit::from_run_args(&template.run_args, &model_path).unwrap();
+            let mut inputs = circuit
+                .load_graph_from_file_exclusively(&template.data)
+                .unwrap();
+            let witness = circuit
+                .forward::<KZGCommitmentScheme<Bn256>>(&mut inputs, None, None, true)
+                .unwrap();
+            circuit.load_graph_witness(&witness).unwrap();
+            let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+
+            let prover =
+                MockProver::run(template.run_args.logrows, &circuit, vec![public_inputs]).unwrap();
+            assert!(prover.verify().is_ok(), "{}", task);
+        }
+    }
+
+    #[test]
+    fn test_templates_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("network.onnx");
+        let data_path = dir.path().join("input.json");
+        for task in ["linear-regression", "logistic", "mlp", "anomaly-score"] {
+            let task = TemplateTask::from_str(task).unwrap();
+            let template = Template::new(task, 3, 4, 0).unwrap();
+            template.save(&model_path, &data_path).unwrap();
+
+            let circuit = GraphCircuit::from_run_args(&template.run_args, &model_path).unwrap();
+            assert_eq!(
+                circuit.model().graph.input_shapes().unwrap(),
+                vec![vec![1, 3]]
+            );
+            GraphData::from_path(data_path.clone()).unwrap();
+        }
+    }
+}
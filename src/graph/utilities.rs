@@ -1,13 +1,13 @@
 #[cfg(not(target_arch = "wasm32"))]
 use super::GraphError;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use super::VarScales;
 use super::{Rescaled, SupportedOp, Visibility};
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use crate::circuit::hybrid::HybridOp;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use crate::circuit::lookup::LookupOp;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use crate::circuit::poly::PolyOp;
 use crate::circuit::Op;
 use crate::tensor::{Tensor, TensorError, TensorType};
@@ -15,13 +15,13 @@ use halo2curves::bn256::Fr as Fp;
 use halo2curves::ff::PrimeField;
 use itertools::Itertools;
 #[cfg(not(target_arch = "wasm32"))]
-use log::{debug, warn};
+use log::debug;
 use std::error::Error;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use std::sync::Arc;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::prelude::{DatumType, Node as OnnxNode, TypedFact, TypedOp};
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::tract_core::ops::{
     array::{
         Gather, GatherElements, GatherNd, MultiBroadcastTo, OneHot, ScatterElements, ScatterNd,
@@ -34,7 +34,7 @@ use tract_onnx::tract_core::ops::{
     nn::{LeakyRelu, Reduce, Softmax},
     Downsample,
 };
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::tract_hir::{
     internal::DimLike,
     ops::array::{Pad, PadMode, TypedConcat},
@@ -45,50 +45,10 @@ use tract_onnx::tract_hir::{
     tract_core::ops::cnn::{conv::KernelFormat, MaxPool, PaddingSpec, SumPool},
 };
 
-/// Quantizes an iterable of f32s to a [Tensor] of i32s using a fixed point representation.
-/// Arguments
-///
-/// * `vec` - the vector to quantize.
-/// * `dims` - the dimensionality of the resulting [Tensor].
-/// * `shift` - offset used in the fixed point representation.
-/// * `scale` - `2^scale` used in the fixed point representation.
-pub fn quantize_float(elem: &f64, shift: f64, scale: crate::Scale) -> Result<i128, TensorError> {
-    let mult = scale_to_multiplier(scale);
-    let max_value = ((i128::MAX as f64 - shift) / mult).round(); // the maximum value that can be represented w/o sig bit truncation
-
-    if *elem > max_value {
-        return Err(TensorError::SigBitTruncationError);
-    }
-
-    // we parallelize the quantization process as it seems to be quite slow at times
-    let scaled = (mult * *elem + shift).round() as i128;
-
-    Ok(scaled)
-}
-
-/// Dequantizes a field element to a f64 using a fixed point representation.
-/// Arguments
-/// * `felt` - the field element to dequantize.
-/// * `scale` - `2^scale` used in the fixed point representation.
-/// * `shift` - offset used in the fixed point representation.
-pub fn dequantize(felt: Fp, scale: crate::Scale, shift: f64) -> f64 {
-    let int_rep = crate::fieldutils::felt_to_i128(felt);
-    let multiplier = scale_to_multiplier(scale);
-    int_rep as f64 / multiplier - shift
-}
-
-/// Converts a scale (log base 2) to a fixed point multiplier.
-pub fn scale_to_multiplier(scale: crate::Scale) -> f64 {
-    f64::powf(2., scale as f64)
-}
-
-/// Converts a scale (log base 2) to a fixed point multiplier.
-pub fn multiplier_to_scale(mult: f64) -> crate::Scale {
-    mult.log2().round() as crate::Scale
-}
+pub use crate::fieldutils::{dequantize, multiplier_to_scale, quantize_float, scale_to_multiplier};
 
 /// Gets the shape of a onnx node's outlets.
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 pub fn node_output_shapes(
     node: &OnnxNode<TypedFact, Box<dyn TypedOp>>,
     symbol_values: &SymbolValues,
@@ -103,9 +63,26 @@ pub fn node_output_shapes(
     }
     Ok(shapes)
 }
-#[cfg(not(target_arch = "wasm32"))]
+
+/// Canonical form of the shape of a (non constant) tensor in the graph: scalars (rank 0) are
+/// represented as `[1]`, and shapes with a zero length dimension are rejected, as the circuit
+/// cannot assign empty tensors.
+pub fn normalize_dims(dims: &[usize], context: &str) -> Result<Vec<usize>, GraphError> {
+    if dims.is_empty() {
+        return Ok(vec![1]);
+    }
+    if dims.contains(&0) {
+        return Err(GraphError::ZeroLengthDim(format!(
+            "{} has shape {:?}",
+            context, dims
+        )));
+    }
+    Ok(dims.to_vec())
+}
+
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 use tract_onnx::prelude::SymbolValues;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 /// Extracts the raw values from a tensor.
 pub fn extract_tensor_value(
     input: Arc<tract_onnx::prelude::Tensor>,
@@ -214,7 +191,7 @@ pub fn extract_tensor_value(
     Ok(const_value)
 }
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 fn load_op<C: tract_onnx::prelude::Op + Clone>(
     op: &dyn tract_onnx::prelude::Op,
     idx: usize,
@@ -238,7 +215,7 @@ fn load_op<C: tract_onnx::prelude::Op + Clone>(
 /// * `param_visibility` - [Visibility] of the node.
 /// * `node` - the [OnnxNode] to be matched.
 /// * `inputs` - the node's inputs.
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
 pub fn new_op_from_onnx(
     idx: usize,
     scales: &VarScales,
@@ -1059,9 +1036,96 @@ pub fn new_op_from_onnx(
             };
 
             let axes = &op.axes;
-            SupportedOp::Linear(PolyOp::Einsum {
-                equation: axes.to_string(),
-            })
+            if op.q_params.is_none() {
+                SupportedOp::Linear(PolyOp::Einsum {
+                    equation: axes.to_string(),
+                })
+            } else {
+                // quantized einsum (e.g QLinearMatMul), inputs are
+                // a, b, bias, a_zero_point, a_scale, b_zero_point, b_scale, c_zero_point, c_scale
+                if inputs.len() != 9 {
+                    return Err(Box::new(GraphError::InvalidDims(
+                        idx,
+                        "quantized einsum".to_string(),
+                    )));
+                }
+
+                let mut q_params = vec![];
+                for input in inputs.iter().skip(3) {
+                    let raw = match input.opkind().get_constant() {
+                        Some(c) if c.raw_values.len() == 1 => c.raw_values[0],
+                        _ => {
+                            return Err(Box::new(GraphError::MisformedParams(
+                                "quantized einsum only supports constant per-tensor zero points and scales"
+                                    .to_string(),
+                            )))
+                        }
+                    };
+                    q_params.push(raw);
+                }
+
+                let (output_zero_point, output_scale) = (q_params[4], q_params[5]);
+                let bias_is_zero = match inputs[2].opkind().get_constant() {
+                    Some(c) => c.raw_values.iter().all(|x| *x == 0.0),
+                    None => false,
+                };
+                if !bias_is_zero || output_zero_point != 0.0 {
+                    return Err(Box::new(GraphError::MisformedParams(
+                        "quantized einsum only supports a zero bias and output zero point"
+                            .to_string(),
+                    )));
+                }
+
+                // the output is requantized, q_c = real / c_scale, and einsum is bilinear, so the
+                // requantization is folded into the first constant operand
+                let requantized = (0..2).find(|i| inputs[*i].opkind().get_constant().is_some());
+                if requantized.is_none() && output_scale != 1.0 {
+                    return Err(Box::new(GraphError::MisformedParams(
+                        "quantized einsum needs a constant operand to requantize its output"
+                            .to_string(),
+                    )));
+                }
+
+                // dequantize constant operands in place: real = scale * (q - zero_point).
+                // non-constant operands come out of a QuantizeLinear, which we treat as the identity,
+                // so they are already real valued.
+                for (operand, zero_point, q_scale) in
+                    [(0, q_params[0], q_params[1]), (1, q_params[2], q_params[3])]
+                {
+                    let q_scale = if requantized == Some(operand) {
+                        q_scale / output_scale
+                    } else {
+                        q_scale
+                    };
+                    let opkind = inputs[operand].opkind();
+                    if let Some(c) = opkind.get_constant() {
+                        let raw = c.raw_values.map(|x| (x - zero_point) * q_scale);
+                        let quantized =
+                            quantize_tensor(raw.clone(), scales.params, param_visibility)?;
+                        inputs[operand].replace_opkind(SupportedOp::Constant(
+                            crate::circuit::ops::Constant::new(quantized, raw),
+                        ));
+                        inputs[operand].bump_scale(scales.params);
+                    }
+                }
+
+                // the bias and quantization params are folded away
+                for (i, input) in inputs.iter_mut().enumerate().skip(2) {
+                    input.decrement_use();
+                    deleted_indices.push(i);
+                }
+
+                // only keep the a and b operands of the equation
+                let equation = axes.to_string();
+                let (operands, output) = equation
+                    .split_once("->")
+                    .ok_or(GraphError::MisformedParams(equation.clone()))?;
+                let operands = operands.split(',').take(2).collect::<Vec<_>>().join(",");
+
+                SupportedOp::Linear(PolyOp::Einsum {
+                    equation: format!("{}->{}", operands, output),
+                })
+            }
         }
         "Softmax" => {
             // Extract the slope layer hyperparams
@@ -1521,9 +1585,12 @@ pub fn new_op_from_onnx(
         "RmAxis" | "Reshape" | "AddAxis" => {
             // Extract the slope layer hyperparams
             let shapes = node_output_shapes(&node, symbol_values)?;
-            let mut output_shape = shapes[0].clone();
-            if output_shape.is_empty() {
-                output_shape = vec![1];
+            let output_shape = normalize_dims(&shapes[0], &format!("reshape node {}", idx))?;
+
+            // the target shape is known from the output fact, a (folded) shape operand isn't needed
+            if inputs.len() == 2 && inputs[1].opkind().is_constant() {
+                inputs[1].decrement_use();
+                deleted_indices.push(1);
             }
 
             SupportedOp::Linear(PolyOp::Reshape(output_shape))
@@ -1533,14 +1600,121 @@ pub fn new_op_from_onnx(
             SupportedOp::Linear(PolyOp::Flatten(new_dims))
         }
         c => {
-            warn!("Unknown op: {}", c);
-            SupportedOp::Unknown(crate::circuit::ops::Unknown)
+            if let Some(op) = node.op_as::<super::custom::tract::CustomTypedOp>() {
+                SupportedOp::Custom(super::custom::CustomOp::new(
+                    op.name.clone(),
+                    op.attributes.clone(),
+                ))
+            } else {
+                crate::warnings::emit(
+                    crate::warnings::WarningKind::UnsupportedOp,
+                    format!("Unknown op: {}", c),
+                );
+                SupportedOp::Unknown(crate::circuit::ops::Unknown)
+            }
         }
     };
 
     Ok((node, deleted_indices))
 }
 
+/// Inlines the tensors of an onnx graph (and its subgraphs) that are stored as external data,
+/// honouring the `offset` and `length` entries. Locations must be relative to `model_dir`.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub fn load_external_data(
+    graph: &mut tract_onnx::pb::GraphProto,
+    model_dir: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for tensor in graph.initializer.iter_mut() {
+        load_external_tensor(tensor, model_dir)?;
+    }
+    for node in graph.node.iter_mut() {
+        for attr in node.attribute.iter_mut() {
+            if let Some(tensor) = attr.t.as_mut() {
+                load_external_tensor(tensor, model_dir)?;
+            }
+            for tensor in attr.tensors.iter_mut() {
+                load_external_tensor(tensor, model_dir)?;
+            }
+            if let Some(subgraph) = attr.g.as_mut() {
+                load_external_data(subgraph, model_dir)?;
+            }
+            for subgraph in attr.graphs.iter_mut() {
+                load_external_data(subgraph, model_dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+fn load_external_tensor(
+    tensor: &mut tract_onnx::pb::TensorProto,
+    model_dir: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Seek, SeekFrom};
+    use tract_onnx::pb::tensor_proto::DataLocation;
+
+    if tensor.data_location != DataLocation::External as i32 {
+        return Ok(());
+    }
+
+    let mut location = None;
+    let mut offset = 0;
+    let mut length = None;
+    for entry in tensor.external_data.iter() {
+        match entry.key.as_str() {
+            "location" => location = Some(entry.value.clone()),
+            "offset" => offset = entry.value.parse::<u64>()?,
+            "length" => length = Some(entry.value.parse::<usize>()?),
+            _ => {}
+        }
+    }
+
+    let location = location.ok_or(GraphError::MissingParams(format!(
+        "external data location for tensor {}",
+        tensor.name
+    )))?;
+    let relative = std::path::Path::new(&location);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(Box::new(GraphError::MisformedParams(format!(
+            "external data location {} must be relative to the model directory",
+            location
+        ))));
+    }
+
+    let path = model_dir.join(relative);
+    let mut file = std::fs::File::open(&path)
+        .map_err(|e| format!("failed to open external data {}: {}", path.display(), e))?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut raw_data = vec![];
+    match length {
+        Some(length) => {
+            raw_data.resize(length, 0);
+            file.read_exact(&mut raw_data)?;
+        }
+        None => {
+            file.read_to_end(&mut raw_data)?;
+        }
+    }
+    debug!(
+        "loaded {} bytes of external data for tensor {} from {}",
+        raw_data.len(),
+        tensor.name,
+        path.display()
+    );
+
+    tensor.raw_data = raw_data;
+    tensor.external_data.clear();
+    tensor.data_location = DataLocation::Default as i32;
+    Ok(())
+}
+
 /// Extracts the raw values from a [crate::circuit::ops::Constant] op.
 pub fn extract_const_raw_values(op: SupportedOp) -> Option<Tensor<f32>> {
     match op {
@@ -1578,6 +1752,100 @@ pub fn quantize_tensor<F: PrimeField + TensorType + PartialOrd>(
     Ok(value)
 }
 
+/// Evaluates nodes whose inputs are all constants (e.g Shape -> Gather -> Concat -> Reshape chains)
+/// at load time and replaces them with constants, so they don't become circuit nodes.
+/// Nodes are visited in topological order so whole constant subgraphs collapse in one pass.
+/// Constants that are public or hashed are left alone as folding them would change the instances.
+/// Returns the number of folded nodes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fold_constants(nodes: &mut std::collections::BTreeMap<usize, super::NodeType>) -> usize {
+    let indices = nodes.keys().cloned().collect_vec();
+    indices
+        .into_iter()
+        .filter(|idx| fold_node(nodes, *idx))
+        .count()
+}
+
+/// Folds the node at `idx` into a constant if all its inputs are constants, see [fold_constants].
+/// Called as each node is loaded, so ops that need constant operands (e.g the end of a `Range` or
+/// the indices of a `Gather`) see the result of shape computations rather than the ops computing
+/// them. Returns whether the node was folded.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fold_node(
+    nodes: &mut std::collections::BTreeMap<usize, super::NodeType>,
+    idx: usize,
+) -> bool {
+    let node = match nodes.get(&idx) {
+        Some(super::NodeType::Node(n)) => n,
+        _ => return false,
+    };
+    if node.inputs.is_empty()
+        || matches!(
+            node.opkind,
+            SupportedOp::Input(_) | SupportedOp::Constant(_) | SupportedOp::Unknown(_)
+        )
+    {
+        return false;
+    }
+
+    let inputs = node
+        .inputs
+        .iter()
+        .map(|(i, _)| match nodes.get(i) {
+            Some(super::NodeType::Node(n)) => {
+                n.opkind.get_constant().map(|c| c.quantized_values.clone())
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let inputs = match inputs {
+        Some(inputs) => inputs,
+        None => return false,
+    };
+
+    let visibility = inputs[0].visibility();
+    if inputs.iter().any(|t| t.visibility() != visibility)
+        || visibility
+            .as_ref()
+            .map(|v| v.is_public() || v.requires_processing())
+            .unwrap_or(false)
+    {
+        return false;
+    }
+
+    let mut output = match node.opkind.f(&inputs) {
+        Ok(res) => res.output,
+        Err(e) => {
+            debug!("could not fold node {}: {}", idx, e);
+            return false;
+        }
+    };
+    let out_scale = node.out_scale;
+    let raw_values = output.map(|x| dequantize(x, out_scale, 0.0) as f32);
+    output.set_scale(out_scale);
+    if let Some(visibility) = &visibility {
+        output.set_visibility(visibility);
+    }
+
+    let node_inputs = node.inputs.clone();
+    for (i, _) in node_inputs {
+        if let Some(super::NodeType::Node(n)) = nodes.get_mut(&i) {
+            n.num_uses = n.num_uses.saturating_sub(1);
+        }
+    }
+    if let Some(super::NodeType::Node(n)) = nodes.get_mut(&idx) {
+        debug!(
+            "folded node {} ({}) into a constant",
+            idx,
+            n.opkind.as_string()
+        );
+        n.opkind = SupportedOp::Constant(crate::circuit::ops::Constant::new(output, raw_values));
+        n.inputs = vec![];
+        return true;
+    }
+    false
+}
+
 use crate::tensor::ValTensor;
 /// Split a [ValTensor] into a vector of [ValTensor]s.
 pub(crate) fn split_valtensor(
@@ -1651,6 +1919,17 @@ pub fn homogenize_input_scales(
 pub mod tests {
 
     use super::*;
+    use crate::circuit::poly::PolyOp;
+
+    #[test]
+    fn test_normalize_dims() {
+        assert_eq!(normalize_dims(&[], "scalar").unwrap(), vec![1]);
+        assert_eq!(normalize_dims(&[2, 3], "matrix").unwrap(), vec![2, 3]);
+        assert!(matches!(
+            normalize_dims(&[2, 0], "empty"),
+            Err(GraphError::ZeroLengthDim(_))
+        ));
+    }
 
     #[test]
     fn test_flatten_valtensors() {
@@ -1679,4 +1958,52 @@ pub mod tests {
         assert_eq!(split[2].dims(), vec![5, 2]);
         assert_eq!(split[2].len(), 10);
     }
+
+    #[test]
+    fn test_fold_constants() {
+        use super::super::{Node, NodeType};
+        use std::collections::BTreeMap;
+
+        let constant = |idx: usize, values: &[f32]| {
+            let raw = Tensor::from(values.iter().cloned());
+            let quantized = quantize_tensor(raw.clone(), 0, &Visibility::Fixed).unwrap();
+            NodeType::Node(Node {
+                opkind: SupportedOp::Constant(crate::circuit::ops::Constant::new(quantized, raw)),
+                out_scale: 0,
+                inputs: vec![],
+                out_dims: vec![values.len()],
+                idx,
+                num_uses: 1,
+            })
+        };
+
+        let mut nodes = BTreeMap::new();
+        nodes.insert(0, constant(0, &[1.0, 2.0]));
+        nodes.insert(1, constant(1, &[3.0, 4.0]));
+        nodes.insert(
+            2,
+            NodeType::Node(Node {
+                opkind: SupportedOp::Linear(PolyOp::Add),
+                out_scale: 0,
+                inputs: vec![(0, 0), (1, 0)],
+                out_dims: vec![2],
+                idx: 2,
+                num_uses: 1,
+            }),
+        );
+
+        assert_eq!(fold_constants(&mut nodes), 1);
+        match &nodes[&2] {
+            NodeType::Node(n) => {
+                let c = n.opkind.get_constant().unwrap();
+                assert_eq!(c.raw_values, Tensor::from([4.0f32, 6.0].into_iter()));
+                assert!(n.inputs.is_empty());
+            }
+            _ => panic!("expected a node"),
+        }
+        match &nodes[&0] {
+            NodeType::Node(n) => assert_eq!(n.num_uses, 0),
+            _ => panic!("expected a node"),
+        }
+    }
 }
@@ -96,7 +96,10 @@ impl<'a> From<&'a str> for Visibility {
             },
             _ => {
                 log::error!("Invalid value for Visibility: {}", s);
-                log::warn!("Defaulting to private");
+                crate::warnings::emit(
+                    crate::warnings::WarningKind::DefaultUsed,
+                    format!("invalid visibility {}, defaulting to private", s),
+                );
                 Visibility::Private
             }
         }
@@ -173,6 +176,68 @@ impl<'source> FromPyObject<'source> for Visibility {
     }
 }
 
+/// Where a single model output goes, see [crate::RunArgs::output_destinations].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum OutputDestination {
+    /// The output is a public instance
+    Public,
+    /// The Poseidon hash of the output is a public instance
+    Hashed,
+    /// The output stays private to the prover
+    Dropped,
+}
+
+impl std::str::FromStr for OutputDestination {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "public" => Ok(OutputDestination::Public),
+            "hashed" => Ok(OutputDestination::Hashed),
+            "dropped" => Ok(OutputDestination::Dropped),
+            _ => Err(format!(
+                "invalid output destination {}, expected one of public, hashed, dropped",
+                s
+            )),
+        }
+    }
+}
+
+impl Display for OutputDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutputDestination::Public => write!(f, "public"),
+            OutputDestination::Hashed => write!(f, "hashed"),
+            OutputDestination::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
+impl ToFlags for OutputDestination {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Converts OutputDestination into a PyObject (Required for OutputDestination to be compatible with Python)
+impl IntoPy<PyObject> for OutputDestination {
+    fn into_py(self, py: Python) -> PyObject {
+        self.to_string().to_object(py)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains OutputDestination from PyObject, rejecting unknown destinations
+impl<'source> FromPyObject<'source> for OutputDestination {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let trystr = <PyString as PyTryFrom>::try_from(ob)?;
+        trystr
+            .to_string()
+            .parse()
+            .map_err(|e: String| PyValueError::new_err(e))
+    }
+}
+
 impl Visibility {
     #[allow(missing_docs)]
     pub fn is_fixed(&self) -> bool {
@@ -472,3 +537,75 @@ impl<F: PrimeField + TensorType + PartialOrd> ModelVars<F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RunArgs;
+
+    #[test]
+    fn test_output_destinations() {
+        let run_args = RunArgs {
+            output_destinations: "public,hashed,dropped"
+                .split(',')
+                .map(|d| d.parse().unwrap())
+                .collect(),
+            ..RunArgs::default()
+        };
+        let resolved = run_args.resolve_visibility();
+        assert_eq!(
+            resolved.output_visibility,
+            Visibility::Hashed {
+                hash_is_public: true,
+                outlets: vec![1],
+            }
+        );
+        assert!(resolved.output_is_public(0));
+        assert!(!resolved.output_is_public(1));
+        assert!(!resolved.output_is_public(2));
+        assert!("elsewhere".parse::<OutputDestination>().is_err());
+    }
+
+    #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_output_destinations_mock_prove() {
+        use crate::graph::{GraphCircuit, GraphData};
+        use halo2_proofs::dev::MockProver;
+        use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+        use halo2curves::bn256::{Bn256, Fr};
+
+        // the model has three outputs of 18 elements each
+        let run_args = RunArgs {
+            output_destinations: vec![
+                OutputDestination::Public,
+                OutputDestination::Hashed,
+                OutputDestination::Dropped,
+            ],
+            ..RunArgs::default()
+        };
+        let model_path = std::path::Path::new("examples/onnx/rounding_ops/network.onnx");
+        let data = GraphData::from_path("examples/onnx/rounding_ops/input.json".into()).unwrap();
+
+        let mut circuit = GraphCircuit::from_run_args(&run_args, model_path).unwrap();
+        let mut inputs = circuit.load_graph_from_file_exclusively(&data).unwrap();
+        let witness = circuit
+            .forward::<KZGCommitmentScheme<Bn256>>(&mut inputs, None, None, true)
+            .unwrap();
+        circuit.load_graph_witness(&witness).unwrap();
+        let public_inputs = circuit.prepare_public_inputs(&witness).unwrap();
+        // the public output and the hash of the hashed one
+        assert_eq!(public_inputs.len(), 1 + 18);
+
+        let logrows = circuit.settings().run_args.logrows;
+        let prover = MockProver::run(logrows, &circuit, vec![public_inputs.clone()]).unwrap();
+        assert!(prover.verify().is_ok());
+
+        // neither the public output nor the hash can be swapped out
+        for i in [0, public_inputs.len() - 1] {
+            let mut tampered = public_inputs.clone();
+            tampered[i] += Fr::from(1);
+            let prover = MockProver::run(logrows, &circuit, vec![tampered]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
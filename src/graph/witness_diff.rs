@@ -0,0 +1,186 @@
+use super::model::Model;
+use super::modules::ModuleForwardResult;
+use super::GraphWitness;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// A single element at which two witnesses disagree.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WitnessDivergence {
+    /// The witness field the element belongs to, e.g `outputs` or `processed_inputs.poseidon_hash`
+    pub section: String,
+    /// The index of the tensor within the section
+    pub tensor: usize,
+    /// The flat index of the element within the tensor
+    pub element: usize,
+    /// The element in the first witness, unset if the tensor is shorter there
+    pub left: Option<String>,
+    /// The element in the second witness, unset if the tensor is shorter there
+    pub right: Option<String>,
+    /// The graph node the tensor was read from, if a compiled circuit was provided
+    pub node: Option<String>,
+}
+
+impl std::fmt::Display for WitnessDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |v: &Option<String>| v.as_deref().unwrap_or("<missing>").to_string();
+        write!(
+            f,
+            "{}[{}][{}]: {} != {}",
+            self.section,
+            self.tensor,
+            self.element,
+            show(&self.left),
+            show(&self.right)
+        )?;
+        if let Some(node) = &self.node {
+            write!(f, " ({})", node)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two sets of tensors element-wise, a missing tensor or element is reported as a
+/// divergence against `None`.
+fn diff_tensors<T: PartialEq + Debug>(
+    section: &str,
+    left: &[Vec<T>],
+    right: &[Vec<T>],
+    provenance: impl Fn(usize) -> Option<String>,
+) -> Vec<WitnessDivergence> {
+    let mut divergences = vec![];
+    for tensor in 0..left.len().max(right.len()) {
+        let (l, r) = (left.get(tensor), right.get(tensor));
+        let len = l.map_or(0, |t| t.len()).max(r.map_or(0, |t| t.len()));
+        for element in 0..len {
+            let l = l.and_then(|t| t.get(element));
+            let r = r.and_then(|t| t.get(element));
+            if l != r {
+                divergences.push(WitnessDivergence {
+                    section: section.to_string(),
+                    tensor,
+                    element,
+                    left: l.map(|v| format!("{:?}", v)),
+                    right: r.map(|v| format!("{:?}", v)),
+                    node: provenance(tensor),
+                });
+            }
+        }
+    }
+    divergences
+}
+
+fn diff_module_results(
+    section: &str,
+    left: &Option<ModuleForwardResult>,
+    right: &Option<ModuleForwardResult>,
+) -> Vec<WitnessDivergence> {
+    let hashes = |r: &Option<ModuleForwardResult>| {
+        r.as_ref()
+            .and_then(|r| r.poseidon_hash.clone())
+            .map_or(vec![], |h| vec![h])
+    };
+    let commits = |r: &Option<ModuleForwardResult>| {
+        r.as_ref()
+            .and_then(|r| r.polycommit.clone())
+            .unwrap_or_default()
+    };
+
+    let mut divergences = diff_tensors(
+        &format!("{}.poseidon_hash", section),
+        &hashes(left),
+        &hashes(right),
+        |_| None,
+    );
+    divergences.extend(diff_tensors(
+        &format!("{}.polycommit", section),
+        &commits(left),
+        &commits(right),
+        |_| None,
+    ));
+    divergences
+}
+
+/// Compares two witnesses element-wise, in the order the circuit consumes them: inputs, outputs,
+/// then the hashes / commitments of the inputs, params and outputs.
+/// When `model` is provided each input and output divergence is tagged with the node it
+/// originates from.
+pub fn diff_witnesses(
+    left: &GraphWitness,
+    right: &GraphWitness,
+    model: Option<&Model>,
+) -> Vec<WitnessDivergence> {
+    let describe = |idx: usize| {
+        model
+            .and_then(|m| m.graph.nodes.get(&idx))
+            .map(|n| n.as_str())
+    };
+    let input_provenance = |i: usize| {
+        let idx = *model?.graph.input_nodes().get(i)?;
+        Some(format!(
+            "input {} at node {}: {}",
+            i,
+            idx,
+            describe(idx).unwrap_or_default()
+        ))
+    };
+    let output_provenance = |i: usize| {
+        let (idx, slot) = *model?.graph.output_outlets().get(i)?;
+        Some(format!(
+            "output {} at node {} (slot {}): {}",
+            i,
+            idx,
+            slot,
+            describe(idx).unwrap_or_default()
+        ))
+    };
+
+    let mut divergences = diff_tensors("inputs", &left.inputs, &right.inputs, input_provenance);
+    divergences.extend(diff_tensors(
+        "outputs",
+        &left.outputs,
+        &right.outputs,
+        output_provenance,
+    ));
+    divergences.extend(diff_module_results(
+        "processed_inputs",
+        &left.processed_inputs,
+        &right.processed_inputs,
+    ));
+    divergences.extend(diff_module_results(
+        "processed_params",
+        &left.processed_params,
+        &right.processed_params,
+    ));
+    divergences.extend(diff_module_results(
+        "processed_outputs",
+        &left.processed_outputs,
+        &right.processed_outputs,
+    ));
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::Fr as Fp;
+
+    #[test]
+    fn test_first_divergence() {
+        let left = GraphWitness::new(
+            vec![vec![Fp::from(1), Fp::from(2)]],
+            vec![vec![Fp::from(3), Fp::from(4)]],
+        );
+        let mut right = left.clone();
+        assert!(diff_witnesses(&left, &right, None).is_empty());
+
+        right.outputs[0][1] = Fp::from(5);
+        right.outputs[0].push(Fp::from(6));
+        let divergences = diff_witnesses(&left, &right, None);
+
+        assert_eq!(divergences.len(), 2);
+        assert_eq!(divergences[0].section, "outputs");
+        assert_eq!((divergences[0].tensor, divergences[0].element), (0, 1));
+        assert_eq!(divergences[1].left, None);
+    }
+}
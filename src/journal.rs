@@ -0,0 +1,394 @@
+//! Journals of ezkl invocations, to reproduce and audit a pipeline.
+//!
+//! When `EZKL_JOURNAL` points to a file, every command that succeeds appends an entry with its
+//! resolved arguments and the hashes of the files it read and wrote. `ezkl replay` re-runs the
+//! journal, checking each step finds the same inputs and reproduces the same outputs.
+
+use crate::commands::Commands;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The environment variable holding the path of the journal to record commands to.
+pub const JOURNAL_ENV: &str = "EZKL_JOURNAL";
+
+/// The journal commands are recorded to, if any.
+pub fn journal_path() -> Option<PathBuf> {
+    std::env::var(JOURNAL_ENV)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The name of a command, e.g `Prove`.
+pub fn command_name(command: &Commands) -> String {
+    match serde_json::to_value(command) {
+        Ok(serde_json::Value::Object(map)) => map.keys().next().cloned().unwrap_or_default(),
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// The name of the subcommand of a command, e.g `gen-settings` for `GenSettings`.
+fn subcommand_name(command: &Commands) -> String {
+    let mut name = String::new();
+    for (i, c) in command_name(command).chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            name.push('-');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// The values of the path-typed arguments of a command.
+fn command_paths(command: &Commands) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    use clap::CommandFactory;
+
+    fn collect(
+        value: &serde_json::Value,
+        ids: &BTreeSet<String>,
+        is_path: bool,
+        paths: &mut Vec<PathBuf>,
+    ) {
+        match value {
+            serde_json::Value::String(s) if is_path => paths.push(PathBuf::from(s)),
+            serde_json::Value::Array(values) => {
+                values.iter().for_each(|v| collect(v, ids, is_path, paths))
+            }
+            serde_json::Value::Object(map) => map
+                .iter()
+                .for_each(|(k, v)| collect(v, ids, ids.contains(k), paths)),
+            _ => {}
+        }
+    }
+
+    let name = subcommand_name(command);
+    let cli = crate::commands::Cli::command();
+    let subcommand = cli
+        .find_subcommand(&name)
+        .ok_or_else(|| format!("unknown command {}", name))?;
+    let ids = subcommand
+        .get_arguments()
+        .filter(|arg| arg.get_value_parser().type_id() == std::any::TypeId::of::<PathBuf>())
+        .map(|arg| arg.get_id().to_string())
+        .collect::<BTreeSet<_>>();
+
+    let mut paths = vec![];
+    collect(&serde_json::to_value(command)?, &ids, false, &mut paths);
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// The fields of a file that change every time it is written, e.g the creation time of a proof or
+/// of settings, and are left out of its hash.
+const VOLATILE_FIELDS: [&str; 1] = ["timestamp"];
+
+fn strip_volatile(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_volatile),
+        serde_json::Value::Object(map) => {
+            for field in VOLATILE_FIELDS {
+                map.remove(field);
+            }
+            map.values_mut().for_each(strip_volatile);
+        }
+        _ => {}
+    }
+}
+
+/// The SHA256 hash of a file. JSON and CBOR files, e.g settings and proofs, are hashed without
+/// their [VOLATILE_FIELDS], so that reproducing them reproduces their hash.
+fn hash_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    let value = if crate::pfsys::is_cbor_path(path) {
+        ciborium::from_reader::<serde_json::Value, _>(bytes.as_slice()).ok()
+    } else {
+        serde_json::from_slice::<serde_json::Value>(&bytes).ok()
+    };
+    match value {
+        Some(mut value) => {
+            strip_volatile(&mut value);
+            Ok(sha256::digest(serde_json::to_vec(&value)?.as_slice()))
+        }
+        None => Ok(sha256::digest(bytes.as_slice())),
+    }
+}
+
+/// The state of a file at some point of a command.
+#[derive(Clone, Debug, PartialEq)]
+struct FileState {
+    hash: String,
+    modified: Option<SystemTime>,
+}
+
+/// The state of the files among `paths` that exist.
+fn snapshot(paths: &[PathBuf]) -> Result<BTreeMap<PathBuf, FileState>, Box<dyn Error>> {
+    let mut states = BTreeMap::new();
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let state = FileState {
+            hash: hash_file(path)?,
+            modified: std::fs::metadata(path)?.modified().ok(),
+        };
+        states.insert(path.clone(), state);
+    }
+    Ok(states)
+}
+
+/// The size and modification time of the files among `paths` that exist.
+fn stat(paths: &[PathBuf]) -> Result<BTreeMap<PathBuf, (u64, Option<SystemTime>)>, Box<dyn Error>> {
+    let mut states = BTreeMap::new();
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let metadata = std::fs::metadata(path)?;
+        states.insert(path.clone(), (metadata.len(), metadata.modified().ok()));
+    }
+    Ok(states)
+}
+
+/// Splits the files seen by a command into those it read and those it (re)wrote, with their hashes.
+fn classify(
+    before: BTreeMap<PathBuf, FileState>,
+    after: BTreeMap<PathBuf, FileState>,
+) -> (BTreeMap<PathBuf, String>, BTreeMap<PathBuf, String>) {
+    let mut inputs = BTreeMap::new();
+    let mut outputs = BTreeMap::new();
+    for (path, state) in after {
+        if before.get(&path) == Some(&state) {
+            inputs.insert(path, state.hash);
+        } else {
+            outputs.insert(path, state.hash);
+        }
+    }
+    (inputs, outputs)
+}
+
+/// Checks the files still have the given hashes.
+fn check_hashes(files: &BTreeMap<PathBuf, String>, kind: &str) -> Result<(), Box<dyn Error>> {
+    for (path, expected) in files {
+        let found = hash_file(path)
+            .map_err(|e| format!("failed to hash {} {}: {}", kind, path.display(), e))?;
+        if &found != expected {
+            return Err(format!(
+                "{} {} has hash {} but {} was journaled",
+                kind,
+                path.display(),
+                found,
+                expected
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// A single command of a journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// The command, with every argument resolved
+    pub command: Commands,
+    /// The SHA256 hashes of the files the command read
+    pub inputs: BTreeMap<PathBuf, String>,
+    /// The SHA256 hashes of the files the command wrote
+    pub outputs: BTreeMap<PathBuf, String>,
+}
+
+impl JournalEntry {
+    /// Checks the inputs of the command are those that were journaled.
+    pub fn check_inputs(&self) -> Result<(), Box<dyn Error>> {
+        check_hashes(&self.inputs, "input")
+    }
+
+    /// Checks the command reproduced the outputs that were journaled.
+    pub fn check_outputs(&self) -> Result<(), Box<dyn Error>> {
+        check_hashes(&self.outputs, "output")
+    }
+}
+
+/// Records the files a command reads and writes, see [Recording::start].
+pub struct Recording {
+    command: Commands,
+    paths: Vec<PathBuf>,
+    before: BTreeMap<PathBuf, FileState>,
+}
+
+impl Recording {
+    /// Hashes the files among the arguments of `command` before it runs.
+    pub fn start(command: &Commands) -> Result<Self, Box<dyn Error>> {
+        let paths = command_paths(command)?;
+        let before = snapshot(&paths)?;
+        Ok(Self {
+            command: command.clone(),
+            paths,
+            before,
+        })
+    }
+
+    /// Hashes the files again once the command has run. Files that were created or modified are
+    /// its outputs, the others its inputs.
+    pub fn finish(self) -> Result<JournalEntry, Box<dyn Error>> {
+        let after = snapshot(&self.paths)?;
+        let (inputs, outputs) = classify(self.before, after);
+        Ok(JournalEntry {
+            command: self.command,
+            inputs,
+            outputs,
+        })
+    }
+}
+
+/// Watches the files among the arguments of a command for those it writes. Unlike a [Recording] it
+/// tells them apart by their size and modification time rather than their hashes, so that
+/// watching a command doesn't read its keys and SRS.
+pub struct Watch {
+    paths: Vec<PathBuf>,
+    before: BTreeMap<PathBuf, (u64, Option<SystemTime>)>,
+}
+
+impl Watch {
+    /// Stats the files among the arguments of `command` before it runs.
+    pub fn start(command: &Commands) -> Result<Self, Box<dyn Error>> {
+        let paths = command_paths(command)?;
+        let before = stat(&paths)?;
+        Ok(Self { paths, before })
+    }
+
+    /// The files that were created or modified since the command started.
+    pub fn finish(self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        Ok(stat(&self.paths)?
+            .into_iter()
+            .filter(|(path, state)| self.before.get(path) != Some(state))
+            .map(|(path, _)| path)
+            .collect())
+    }
+}
+
+/// A journal of commands, in the order they ran.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+    /// The ezkl version the journal was recorded with
+    pub version: String,
+    /// The commands that ran
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: vec![],
+        }
+    }
+}
+
+impl Journal {
+    /// Load the journal from a .json file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open journal {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*crate::EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the journal to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer =
+            BufWriter::with_capacity(*crate::EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+
+    /// Appends an entry to the journal at `path`, creating it if it doesn't exist.
+    pub fn append(path: &Path, entry: JournalEntry) -> Result<(), Box<dyn Error>> {
+        let mut journal = if path.exists() {
+            Self::load(path)?
+        } else {
+            Self::default()
+        };
+        journal.entries.push(entry);
+        journal.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_ignore_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        std::fs::write(&path, r#"{"logrows": 17, "timestamp": 1}"#).unwrap();
+        let hash = hash_file(&path).unwrap();
+        std::fs::write(&path, r#"{"logrows": 17, "timestamp": 2}"#).unwrap();
+        assert_eq!(hash_file(&path).unwrap(), hash);
+        std::fs::write(&path, r#"{"logrows": 18, "timestamp": 2}"#).unwrap();
+        assert_ne!(hash_file(&path).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_command_paths() {
+        let command = Commands::GetModel {
+            url: "https://example.com/model.onnx".into(),
+            sha256: "00".into(),
+            model: "model.onnx".into(),
+        };
+        assert_eq!(subcommand_name(&command), "get-model");
+        assert_eq!(
+            command_paths(&command).unwrap(),
+            vec![PathBuf::from("model.onnx")]
+        );
+    }
+
+    #[test]
+    fn test_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = dir.path().join("model.onnx");
+        std::fs::write(&model, "model").unwrap();
+        let command = Commands::GetModel {
+            url: "https://example.com/model.onnx".into(),
+            sha256: "00".into(),
+            model: model.clone(),
+        };
+        let watch = Watch::start(&command).unwrap();
+        assert!(Watch::start(&command).unwrap().finish().unwrap().is_empty());
+        std::fs::write(&model, "another model").unwrap();
+        assert_eq!(watch.finish().unwrap(), vec![model]);
+    }
+
+    #[test]
+    fn test_classify() {
+        let state = |hash: &str, secs: u64| FileState {
+            hash: hash.into(),
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs)),
+        };
+        let before = BTreeMap::from([
+            ("model.onnx".into(), state("a", 1)),
+            ("settings.json".into(), state("b", 1)),
+            ("pk.key".into(), state("c", 1)),
+        ]);
+        let after = BTreeMap::from([
+            ("model.onnx".into(), state("a", 1)),
+            // rewritten with the same content, still an output
+            ("settings.json".into(), state("b", 2)),
+            ("pk.key".into(), state("d", 2)),
+            ("vk.key".into(), state("e", 2)),
+        ]);
+        let (inputs, outputs) = classify(before, after);
+        assert_eq!(
+            inputs.keys().collect::<Vec<_>>(),
+            vec![&PathBuf::from("model.onnx")]
+        );
+        assert_eq!(
+            outputs.keys().collect::<Vec<_>>(),
+            vec![
+                &PathBuf::from("pk.key"),
+                &PathBuf::from("settings.json"),
+                &PathBuf::from("vk.key")
+            ]
+        );
+    }
+}
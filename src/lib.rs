@@ -32,50 +32,109 @@ use std::str::FromStr;
 
 use circuit::{table::Range, CheckMode, Tolerance};
 use clap::Args;
-use graph::Visibility;
+use graph::{OutputDestination, Visibility};
 use halo2_proofs::poly::{
     ipa::commitment::IPACommitmentScheme, kzg::commitment::KZGCommitmentScheme,
 };
 use halo2curves::bn256::{Bn256, G1Affine};
 use serde::{Deserialize, Serialize};
+use tensor::AssignmentOrder;
 use tosubcommand::ToFlags;
 
+/// The types and OpenAPI document of the proving service.
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod api;
+/// End-to-end benchmarks of a model
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod bench;
 /// Methods for configuring tensor operations and assigning values to them in a Halo2 circuit.
 pub mod circuit;
 /// CLI commands.
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 pub mod commands;
+/// Which releases can verify an artifact, from the versions of its format
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod compat;
+/// Lockfiles of everything affecting proof bytes, to check environments are interoperable
 #[cfg(not(target_arch = "wasm32"))]
+pub mod env_lock;
+#[cfg(all(feature = "evm", not(target_arch = "wasm32")))]
 // abigen doesn't generate docs for this module
 #[allow(missing_docs)]
 /// Utility functions for contracts
 pub mod eth;
 /// Command execution
 ///
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
 pub mod execute;
-/// Utilities for converting from Halo2 Field types to integers (and vice-versa).
+/// A C ABI for hosts embedding the cdylib, with callbacks for log lines and proving progress.
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+/// Stable conversions between integers, fixed point floats and Halo2 Field types.
 pub mod fieldutils;
+/// Randomized testing of compiled circuits against the mock prover
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod fuzz;
 /// Methods for loading onnx format models and automatically laying them out in
-/// a Halo2 circuit.
-#[cfg(feature = "onnx")]
+/// a Halo2 circuit. Without the `onnx` feature only compiled circuits and settings can be loaded.
+#[cfg(feature = "verify")]
 pub mod graph;
+/// Journals of the commands run, to replay and audit a pipeline
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod journal;
+/// Memory trimming around the prover and peak memory measurements
+#[cfg(not(target_arch = "wasm32"))]
+pub mod memory;
+#[cfg(feature = "verify")]
+pub use graph::limits::limits;
+#[cfg(feature = "verify")]
+pub use graph::response::{respond, validate_response};
 /// beautiful logging
 #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 pub mod logger;
+/// Generation of model specific npm verifier packages
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod npm;
+/// Machine readable results of commands
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod output;
 /// Tools for proofs and verification used by cli
 pub mod pfsys;
+/// Pipelines running every stage from a model to a verified proof, caching their artifacts
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod pipeline;
 /// Python bindings
 #[cfg(feature = "python-bindings")]
 pub mod python;
+/// Retention of the artifacts of the proving service's jobs
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod retention;
+/// The long-running proving service of `ezkl serve`
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod serve;
 /// srs sha hashes
 #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
 pub mod srs_sha;
 /// An implementation of multi-dimensional tensors.
 pub mod tensor;
+/// Thread pools for witness generation and proving
+#[cfg(not(target_arch = "wasm32"))]
+pub mod threads;
+/// Resumable, checksummed transfers of large artifacts over http
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod transfer;
+/// Structured non fatal warnings collected across pipeline stages
+pub mod warnings;
 /// wasm prover and verifier
-#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[cfg(all(
+    target_arch = "wasm32",
+    target_os = "unknown",
+    not(feature = "contract")
+))]
 pub mod wasm;
+/// Generation of model specific verifier contracts for non-EVM chains
+#[cfg(all(feature = "ezkl", not(target_arch = "wasm32")))]
+pub mod wasm_contracts;
 
 #[cfg(not(target_arch = "wasm32"))]
 use lazy_static::lazy_static;
@@ -168,6 +227,45 @@ impl From<String> for Commitments {
     }
 }
 
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Default, Copy,
+)]
+/// Hardware the prover offloads MSMs and FFTs to
+pub enum Accelerator {
+    #[default]
+    /// Multithreaded CPU
+    Cpu,
+    /// NVIDIA GPUs through icicle, requires the `icicle` feature
+    Cuda,
+}
+
+impl FromStr for Accelerator {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Ok(Accelerator::Cpu),
+            "cuda" => Ok(Accelerator::Cuda),
+            _ => Err(format!("unknown accelerator {}, expected cpu or cuda", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Accelerator::Cpu => write!(f, "cpu"),
+            Accelerator::Cuda => write!(f, "cuda"),
+        }
+    }
+}
+
+impl ToFlags for Accelerator {
+    /// Convert the struct to a subcommand string
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
 /// Parameters specific to a proving run
 #[derive(Debug, Args, Deserialize, Serialize, Clone, PartialEq, PartialOrd, ToFlags)]
 pub struct RunArgs {
@@ -207,6 +305,12 @@ pub struct RunArgs {
     #[arg(long, default_value = "false")]
     /// Rebase the scale using lookup table for division instead of using a range check
     pub div_rebasing: bool,
+    /// Rebase the scale by shifting when the rebase divisor is a power of two, which takes fewer rows
+    /// than a range checked division but rounds negative values down rather than to the nearest
+    /// integer (ignored with --div-rebasing)
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub shift_rebasing: bool,
     /// Should constants with 0.0 fraction be rebased to scale 0
     #[arg(long, default_value = "false")]
     pub rebase_frac_zero_constants: bool,
@@ -216,6 +320,78 @@ pub struct RunArgs {
     /// commitment scheme
     #[arg(long, default_value = "kzg")]
     pub commitment: Commitments,
+    /// Names of the model outputs to keep, the graph is pruned to the nodes feeding them (defaults to all outputs)
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub output_names: Vec<String>,
+    /// Number of threads used for witness generation and proving, defaults to the cpus available
+    /// to the process (respecting cgroup limits)
+    #[arg(long)]
+    #[serde(default)]
+    pub num_threads: Option<usize>,
+    /// Order in which witness cells are written during synthesis: row-major, column-major or auto,
+    /// which goes column-major for large tensors spread over several inner columns. Only changes
+    /// the speed of witness generation and proving, never the circuit
+    #[arg(long, default_value = "row-major")]
+    #[serde(default)]
+    pub assignment_order: AssignmentOrder,
+    /// Appends a public instance binding proofs to a caller address, which the generated evm
+    /// verifier checks against `msg.sender`
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub bind_caller: bool,
+    /// Fuses attention blocks (QK^T, scaling, softmax and the product with V) into a single op
+    /// that shares the rescaling of its intermediates
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub fuse_attention: bool,
+    /// Index of the output that is the validity mask of outputs padded to a fixed number of slots
+    /// (e.g detections), holding 1 for each valid slot and 0 for each padded one. The mask is
+    /// constrained to be boolean with its valid slots first, and its count of valid slots is
+    /// exposed as a public instance
+    #[arg(long)]
+    #[serde(default)]
+    pub mask_output: Option<usize>,
+    /// Names the hash of the params a `model_hash` public instance and records the expected hash
+    /// in the settings, so verifiers can check which weights a proof was generated with rather
+    /// than relying on the verifying key alone. Private params are hashed for it, keeping the
+    /// params themselves private, see [RunArgs::resolve_visibility]. The instance sits where the
+    /// param hashes would, after the public inputs or their hashes, see
+    /// [graph::GraphSettings::instance_layout]
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub expose_model_hash: bool,
+    /// Lays out gathers with constant indices in the shuffle columns, looking up each output
+    /// element, tagged with the position it reads, among the input elements tagged with their
+    /// positions, rather than rearranging the input cells for every consumer to copy. Changes the
+    /// circuit, so keys have to be regenerated
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub shuffle_permutations: bool,
+    /// Number of elements hashed together at each node of the Poseidon hash tree of hashed
+    /// inputs, params and outputs (8, 16, 32 or 64). The nodes of each level are hashed in
+    /// parallel during witness generation (see [RunArgs::num_threads]); smaller chunks make for a
+    /// deeper tree of shorter sponges, larger ones for fewer hashes. Changes the hashes
+    #[arg(long, default_value = "32")]
+    #[serde(default = "default_hash_chunk_size")]
+    pub hash_chunk_size: usize,
+    /// Where each model output goes, overriding output_visibility: `public` outputs are public
+    /// instances, the Poseidon hashes of `hashed` ones are, and `dropped` ones stay private, e.g
+    /// `public,hashed,dropped`. Outputs past the end of the list are dropped
+    #[arg(long, value_delimiter = ',')]
+    #[serde(default)]
+    pub output_destinations: Vec<OutputDestination>,
+    /// Constrains the outputs to be sealed to the public key given to `gen-witness
+    /// --seal-outputs-to` and exposes the sealed outputs, the public key and the ephemeral key as
+    /// public instances, see [graph::sealed]. The range checks of the sealing need at least 18
+    /// logrows. Changes the circuit, so keys have to be regenerated
+    #[arg(long, default_value = "false")]
+    #[serde(default)]
+    pub seal_outputs: bool,
+}
+
+fn default_hash_chunk_size() -> usize {
+    graph::modules::POSEIDON_LEN_GRAPH
 }
 
 impl Default for RunArgs {
@@ -233,9 +409,21 @@ impl Default for RunArgs {
             output_visibility: Visibility::Public,
             param_visibility: Visibility::Private,
             div_rebasing: false,
+            shift_rebasing: false,
             rebase_frac_zero_constants: false,
             check_mode: CheckMode::UNSAFE,
             commitment: Commitments::KZG,
+            output_names: vec![],
+            num_threads: None,
+            assignment_order: AssignmentOrder::default(),
+            bind_caller: false,
+            fuse_attention: false,
+            mask_output: None,
+            expose_model_hash: false,
+            shuffle_permutations: false,
+            hash_chunk_size: default_hash_chunk_size(),
+            output_destinations: vec![],
+            seal_outputs: false,
         }
     }
 }
@@ -255,12 +443,86 @@ impl RunArgs {
         if self.num_inner_cols < 1 {
             return Err("num_inner_cols must be >= 1".into());
         }
-        if self.tolerance.val > 0.0 && self.output_visibility != Visibility::Public {
+        if !self.output_destinations.is_empty()
+            && !self
+                .output_destinations
+                .contains(&OutputDestination::Public)
+            && !self
+                .output_destinations
+                .contains(&OutputDestination::Hashed)
+        {
+            return Err("output_destinations drops every output".into());
+        }
+        if self.tolerance.val > 0.0
+            && self.output_visibility != Visibility::Public
+            && !self
+                .output_destinations
+                .contains(&OutputDestination::Public)
+        {
             return Err("tolerance > 0.0 requires output_visibility to be public".into());
         }
+        if self.expose_model_hash
+            && !(self.param_visibility.is_private() || self.param_visibility.is_hashed_public())
+        {
+            return Err(
+                "expose_model_hash requires param_visibility to be private or hashed/public".into(),
+            );
+        }
+        if !graph::modules::POSEIDON_CHUNK_SIZES.contains(&self.hash_chunk_size) {
+            return Err(format!(
+                "hash_chunk_size must be one of {:?}",
+                graph::modules::POSEIDON_CHUNK_SIZES
+            )
+            .into());
+        }
         Ok(())
     }
 
+    /// The run args circuits are built with. When [RunArgs::expose_model_hash] is set, otherwise
+    /// private params are hashed with a public hash, which becomes the `model_hash` instance. When
+    /// [RunArgs::output_destinations] is set, the output visibility hashes the `hashed` outputs.
+    pub fn resolve_visibility(&self) -> RunArgs {
+        let mut run_args = self.clone();
+        if run_args.expose_model_hash && run_args.param_visibility.is_private() {
+            let outlets = match &run_args.param_visibility {
+                Visibility::Hashed { outlets, .. } => outlets.clone(),
+                _ => vec![],
+            };
+            run_args.param_visibility = Visibility::Hashed {
+                hash_is_public: true,
+                outlets,
+            };
+        }
+        // the hashed outputs are laid out by the modules, and the public ones by the model
+        if !run_args.output_destinations.is_empty() {
+            let hashed = run_args
+                .output_destinations
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| **d == OutputDestination::Hashed)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            run_args.output_visibility = if !hashed.is_empty() {
+                Visibility::Hashed {
+                    hash_is_public: true,
+                    outlets: hashed,
+                }
+            } else {
+                Visibility::Public
+            };
+        }
+        run_args
+    }
+
+    /// Whether output `idx` is a public instance, see [RunArgs::output_destinations]
+    pub fn output_is_public(&self, idx: usize) -> bool {
+        if self.output_destinations.is_empty() {
+            self.output_visibility.is_public()
+        } else {
+            self.output_destinations.get(idx) == Some(&OutputDestination::Public)
+        }
+    }
+
     /// Export the ezkl configuration as json
     pub fn as_json(&self) -> Result<String, Box<dyn std::error::Error>> {
         let serialized = match serde_json::to_string(&self) {
@@ -67,6 +67,11 @@ pub fn format(buf: &mut Formatter, record: &Record<'_>) -> Result<(), std::fmt::
 
 /// initializes the logger
 pub fn init_logger() {
+    init_logger_to(env_logger::Target::Stdout)
+}
+
+/// initializes the logger, writing to `target`
+pub fn init_logger_to(target: env_logger::Target) {
     let mut builder = Builder::new();
 
     builder.format(move |buf, record| {
@@ -84,7 +89,7 @@ pub fn init_logger() {
                 .replace('\n', &format!("\n{} ", " | ".white().bold()))
         )
     });
-    builder.target(env_logger::Target::Stdout);
+    builder.target(target);
     builder.filter(None, LevelFilter::Info);
     if env::var("RUST_LOG").is_ok() {
         builder.parse_filters(&env::var("RUST_LOG").unwrap());
@@ -0,0 +1,65 @@
+//! Memory trimming around the prover (`prove --trim-memory`), trading proving time for a smaller
+//! footprint.
+//!
+//! This is not a low-memory prover: the pinned halo2 prover allocates its extended domain
+//! evaluations whole and has no switch to chunk the quotient computation or recompute them, so the
+//! peak they make up, and the circuit size a machine can prove, are unchanged. Trimming only
+//! shrinks what is around them:
+//!
+//! - the prover runs on a quarter of the cpus unless `num_threads` is set, bounding the scratch
+//!   space its MSMs and FFTs take per thread,
+//! - the heap is trimmed back to the OS once the proving key and SRS are loaded, releasing the
+//!   buffers they were read through.
+
+use crate::threads::available_cpus;
+
+/// The number of threads to prove on when trimming memory, `num_threads` when set and a quarter of
+/// the available cpus otherwise.
+pub fn trimmed_threads(num_threads: Option<usize>) -> usize {
+    num_threads.unwrap_or_else(|| (available_cpus() / 4).max(1))
+}
+
+/// Releases the free memory of the heap back to the OS. A no-op outside of linux glibc.
+pub fn trim_heap() {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    // SAFETY: malloc_trim only releases memory that is already free
+    #[allow(unsafe_code)]
+    unsafe {
+        libc::malloc_trim(0);
+    }
+}
+
+/// Parses the peak resident set size, in bytes, out of the contents of `/proc/self/status`.
+fn parse_peak_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// The peak resident set size of the process in bytes, where the OS reports it.
+pub fn peak_rss() -> Option<u64> {
+    parse_peak_rss(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+/// Resets the peak reported by [peak_rss] to the current resident set size, so that the peak of
+/// a stage can be measured on its own. Returns whether the OS supports it, only linux does.
+pub fn reset_peak_rss() -> bool {
+    // writing 5 to clear_refs resets VmHWM, see proc(5)
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_memory() {
+        let status =
+            "Name:\tezkl\nVmPeak:\t  204800 kB\nVmHWM:\t    1024 kB\nVmRSS:\t     512 kB\n";
+        assert_eq!(parse_peak_rss(status), Some(1024 * 1024));
+        assert_eq!(parse_peak_rss("Name:\tezkl\n"), None);
+
+        assert_eq!(trimmed_threads(Some(3)), 3);
+        assert!(trimmed_threads(None) >= 1);
+    }
+}
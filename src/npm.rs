@@ -0,0 +1,237 @@
+use crate::graph::{GraphSettings, InstanceSegment};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// The version of `@ezkljs/engine` the generated packages depend on
+pub const ENGINE_VERSION: &str = "^9.4.4";
+
+/// Name of the directory the artifacts are copied to inside the package
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// Nested TS array type for a segment, e.g `number[][]` for a 2D segment.
+fn ts_type(segment: &InstanceSegment) -> String {
+    let base = if segment.scale.is_some() {
+        "number"
+    } else {
+        "string"
+    };
+    let rank = segment.shape.len().max(1);
+    format!("{}{}", base, "[]".repeat(rank))
+}
+
+/// Generates the `index.d.ts` declarations for a model's public instances.
+pub fn typescript_declarations(layout: &[InstanceSegment]) -> String {
+    let mut fields = String::new();
+    for segment in layout {
+        fields.push_str(&format!(
+            "  /** shape {:?}{} */\n  {}: {};\n",
+            segment.shape,
+            segment
+                .scale
+                .map(|s| format!(", scale {}", s))
+                .unwrap_or_default(),
+            segment.name,
+            ts_type(segment)
+        ));
+    }
+
+    format!(
+        r#"export interface PublicInstances {{
+{fields}}}
+
+export interface Proof {{
+  instances: string[][];
+  [key: string]: unknown;
+}}
+
+/** Verifies a proof against the verifying key, settings and SRS bundled with this package. */
+export declare function verify(proof: Proof): Promise<boolean>;
+
+/** Decodes the public instances of a proof into typed, dequantized values. */
+export declare function publicInstances(proof: Proof): PublicInstances;
+
+export declare const layout: {{
+  name: string;
+  shape: number[];
+  scale: number | null;
+  offset: number;
+}}[];
+"#
+    )
+}
+
+/// Generates the `index.js` module wrapping `@ezkljs/engine`.
+pub fn javascript_module(layout: &[InstanceSegment]) -> Result<String, Box<dyn Error>> {
+    let layout = serde_json::to_string_pretty(layout)?;
+
+    Ok(format!(
+        r#"import {{ verify as engineVerify, feltToFloat }} from "@ezkljs/engine";
+
+export const layout = {layout};
+
+const encoder = new TextEncoder();
+
+async function load(name) {{
+  const res = await fetch(new URL("./{ARTIFACTS_DIR}/" + name, import.meta.url));
+  return new Uint8ClampedArray(await res.arrayBuffer());
+}}
+
+function reshape(flat, shape) {{
+  if (shape.length <= 1) return flat;
+  const stride = flat.length / shape[0];
+  const out = [];
+  for (let i = 0; i < shape[0]; i++) {{
+    out.push(reshape(flat.slice(i * stride, (i + 1) * stride), shape.slice(1)));
+  }}
+  return out;
+}}
+
+export async function verify(proof) {{
+  const [vk, settings, srs] = await Promise.all([
+    load("vk.key"),
+    load("settings.json"),
+    load("params.srs"),
+  ]);
+  const proofBytes = new Uint8ClampedArray(encoder.encode(JSON.stringify(proof)));
+  return engineVerify(proofBytes, vk, settings, srs);
+}}
+
+export function publicInstances(proof) {{
+  const flat = proof.instances.flat();
+  const result = {{}};
+  for (const segment of layout) {{
+    const len = segment.shape.reduce((a, b) => a * b, 1);
+    let values = flat.slice(segment.offset, segment.offset + len);
+    if (segment.scale !== null) {{
+      values = values.map((felt) =>
+        feltToFloat(new Uint8ClampedArray(encoder.encode(JSON.stringify(felt))), segment.scale)
+      );
+    }}
+    result[segment.name] = reshape(values, segment.shape);
+  }}
+  return result;
+}}
+"#
+    ))
+}
+
+/// Generates the `package.json` manifest.
+pub fn package_manifest(name: &str) -> Result<String, Box<dyn Error>> {
+    let manifest = serde_json::json!({
+        "name": name,
+        "version": "0.1.0",
+        "type": "module",
+        "main": "index.js",
+        "types": "index.d.ts",
+        "files": ["index.js", "index.d.ts", ARTIFACTS_DIR],
+        "dependencies": {
+            "@ezkljs/engine": ENGINE_VERSION,
+        },
+    });
+    Ok(serde_json::to_string_pretty(&manifest)?)
+}
+
+/// Writes an npm package that verifies proofs for a single model in the browser.
+/// # Arguments
+/// * `output_dir` - directory the package is written to, created if missing.
+/// * `name` - npm package name.
+/// * `settings_path` - circuit settings of the model.
+/// * `vk_path` - verifying key of the model.
+/// * `srs_path` - SRS used to verify.
+pub fn create_package(
+    output_dir: &Path,
+    name: &str,
+    settings_path: &Path,
+    vk_path: &Path,
+    srs_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path.to_path_buf())?;
+    let layout = settings.instance_layout();
+
+    let artifacts = output_dir.join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts)?;
+    fs::copy(vk_path, artifacts.join("vk.key"))?;
+    fs::copy(srs_path, artifacts.join("params.srs"))?;
+    // re-save rather than copy so the bundled settings are on the current schema
+    settings.save(&artifacts.join("settings.json"))?;
+
+    fs::write(output_dir.join("package.json"), package_manifest(name)?)?;
+    fs::write(output_dir.join("index.js"), javascript_module(&layout)?)?;
+    fs::write(
+        output_dir.join("index.d.ts"),
+        typescript_declarations(&layout),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Visibility;
+    use crate::RunArgs;
+
+    #[test]
+    fn test_create_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = GraphSettings {
+            run_args: RunArgs {
+                input_visibility: Visibility::Public,
+                output_visibility: Visibility::Public,
+                ..RunArgs::default()
+            },
+            model_instance_shapes: vec![vec![1, 3], vec![2]],
+            model_input_scales: vec![7],
+            model_output_scales: vec![4],
+            ..GraphSettings::default()
+        };
+        let settings_path = dir.path().join("settings.json");
+        settings.save(&settings_path).unwrap();
+        let vk_path = dir.path().join("vk.key");
+        fs::write(&vk_path, [1u8, 2, 3]).unwrap();
+        let srs_path = dir.path().join("kzg.srs");
+        fs::write(&srs_path, [4u8, 5]).unwrap();
+
+        let package = dir.path().join("package");
+        create_package(&package, "my-verifier", &settings_path, &vk_path, &srs_path).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(package.join("package.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["name"], "my-verifier");
+        assert_eq!(manifest["dependencies"]["@ezkljs/engine"], ENGINE_VERSION);
+        assert_eq!(
+            fs::read(package.join(ARTIFACTS_DIR).join("vk.key")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            fs::read(package.join(ARTIFACTS_DIR).join("params.srs")).unwrap(),
+            vec![4, 5]
+        );
+        let bundled =
+            GraphSettings::load(&package.join(ARTIFACTS_DIR).join("settings.json")).unwrap();
+        assert_eq!(
+            bundled.model_instance_shapes,
+            settings.model_instance_shapes
+        );
+
+        // the typed fields follow the instance layout, inputs first
+        let declarations = fs::read_to_string(package.join("index.d.ts")).unwrap();
+        assert!(declarations.contains("/** shape [1, 3], scale 7 */\n  input_0: number[][];"));
+        assert!(declarations.contains("/** shape [2], scale 4 */\n  output_0: number[];"));
+        let module = fs::read_to_string(package.join("index.js")).unwrap();
+        let layout: Vec<InstanceSegment> = serde_json::from_str(
+            module
+                .split("export const layout = ")
+                .nth(1)
+                .unwrap()
+                .split(";\n")
+                .next()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(layout, settings.instance_layout());
+        assert_eq!(layout[1].offset, 3);
+    }
+}
@@ -0,0 +1,129 @@
+//! Machine readable results of commands, see `--format json`.
+//!
+//! By default commands report what they did through their logs. With `--format json` the logs go
+//! to stderr instead, and a single [CommandReport] is printed to stdout once the command is done:
+//! whether it succeeded, how long it took, the files it wrote and the result it returned. The
+//! files are told apart by their size and modification time, and are only hashed when the
+//! command is journaled, see [crate::journal].
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tosubcommand::ToFlags;
+
+/// How the results of a command are printed
+#[derive(
+    ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq, Deserialize, Serialize, PartialOrd,
+)]
+pub enum OutputFormat {
+    /// log lines on stdout, with the reports of e.g `bench` and `inspect` as tables
+    #[default]
+    #[value(alias = "table")]
+    Human,
+    /// a [CommandReport] on stdout, the log lines on stderr
+    Json,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OutputFormat::Human => "human",
+                OutputFormat::Json => "json",
+            }
+        )
+    }
+}
+
+impl ToFlags for OutputFormat {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+/// Whether a command succeeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandStatus {
+    /// the command succeeded
+    Succeeded,
+    /// the command failed, see [CommandReport::error]
+    Failed,
+}
+
+/// The result of a command, printed by `--format json`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommandReport {
+    /// the name of the command, e.g `Prove`
+    pub command: String,
+    /// whether the command succeeded
+    pub status: CommandStatus,
+    /// how long the command took, in seconds
+    pub seconds: f64,
+    /// the files among the arguments of the command that it created or modified
+    pub artifacts: Vec<PathBuf>,
+    /// what the command returned, e.g the proof of `prove` or whether `verify` succeeded, as json
+    /// where it is json
+    pub result: Option<serde_json::Value>,
+    /// why the command failed
+    pub error: Option<String>,
+}
+
+impl CommandReport {
+    /// The report of `command`, which returned `result` after `elapsed`.
+    pub fn new(
+        command: String,
+        elapsed: Duration,
+        artifacts: Vec<PathBuf>,
+        result: Result<String, String>,
+    ) -> Self {
+        let (status, result, error) = match result {
+            Ok(result) if result.is_empty() => (CommandStatus::Succeeded, None, None),
+            Ok(result) => (
+                CommandStatus::Succeeded,
+                Some(
+                    serde_json::from_str(&result)
+                        .unwrap_or_else(|_| serde_json::Value::String(result)),
+                ),
+                None,
+            ),
+            Err(error) => (CommandStatus::Failed, None, Some(error)),
+        };
+        Self {
+            command,
+            status,
+            seconds: elapsed.as_secs_f64(),
+            artifacts,
+            result,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_report() {
+        let elapsed = Duration::from_millis(1500);
+        let report = CommandReport::new("Verify".into(), elapsed, vec![], Ok("true".into()));
+        assert_eq!(report.status, CommandStatus::Succeeded);
+        assert_eq!(report.result, Some(serde_json::Value::Bool(true)));
+        assert_eq!(report.seconds, 1.5);
+
+        let report = CommandReport::new("Mock".into(), elapsed, vec![], Ok("".into()));
+        assert_eq!(report.result, None);
+        let report = CommandReport::new("Table".into(), elapsed, vec![], Ok("a b".into()));
+        assert_eq!(report.result, Some("a b".into()));
+
+        let report = CommandReport::new("Prove".into(), elapsed, vec![], Err("no".into()));
+        assert_eq!(report.status, CommandStatus::Failed);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "failed");
+        assert_eq!(json["error"], "no");
+    }
+}
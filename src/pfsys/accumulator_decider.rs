@@ -0,0 +1,35 @@
+//! The accumulator decider of aggregated proofs, see [ezkl_accumulator_decider]. It lives in its
+//! own `no_std` crate so it can be built for targets without std.
+
+pub use ezkl_accumulator_decider::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pfsys::evm::aggregation_kzg::AggregationCircuit;
+    use halo2curves::bn256::{Fr, G1Affine, G2Affine};
+    use halo2curves::group::prime::PrimeCurveAffine;
+    use halo2curves::group::Curve;
+    use snark_verifier::util::arithmetic::fe_to_limbs;
+
+    #[test]
+    fn test_decide_accumulator() {
+        assert_eq!(ACCUMULATOR_LIMBS, AggregationCircuit::num_limbs());
+        assert_eq!(ACCUMULATOR_LIMB_BITS, AggregationCircuit::num_bits());
+
+        // a valid accumulator for a toy trapdoor s: lhs = s·P and rhs = P
+        let s = Fr::from(42);
+        let p = (G1Affine::generator() * Fr::from(7)).to_affine();
+        let lhs = (p * s).to_affine();
+        let g2 = G2Affine::generator();
+        let s_g2 = (g2 * s).to_affine();
+
+        let encode = |a: &G1Affine, b: &G1Affine| {
+            [a.x, a.y, b.x, b.y]
+                .map(fe_to_limbs::<_, _, ACCUMULATOR_LIMBS, ACCUMULATOR_LIMB_BITS>)
+                .concat()
+        };
+        assert!(verify_accumulator(&encode(&lhs, &p), &g2, &s_g2));
+        assert!(!verify_accumulator(&encode(&p, &lhs), &g2, &s_g2));
+    }
+}
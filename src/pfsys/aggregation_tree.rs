@@ -0,0 +1,430 @@
+use super::evm::aggregation_kzg::{AggregationCircuit, PoseidonTranscript};
+use super::{create_keys, create_proof_circuit, load_pk, save_pk, Snark, TranscriptType};
+use crate::circuit::CheckMode;
+use crate::{Commitments, EZKL_BUF_CAPACITY};
+use halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::{AccumulatorStrategy, SingleStrategy};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use log::info;
+use serde::{Deserialize, Serialize};
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use snark_verifier::system::halo2::{compile, Config};
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+/// The smallest fan-in that still shrinks each layer of the tree.
+pub const MIN_FAN_IN: usize = 2;
+
+/// The verifying key digest and instance counts of each snark of a chunk.
+pub type ChunkShape = Vec<(Option<Fr>, Vec<usize>)>;
+
+/// The verifying key digest and number of instances of each snark in a chunk, which fully
+/// determine the shape of the aggregation circuit over that chunk. Snarks of distinct circuits
+/// can share a number of instances, so the digest keeps their chunks from sharing keys.
+fn chunk_shape(chunk: &[Snark<Fr, G1Affine>]) -> ChunkShape {
+    chunk
+        .iter()
+        .map(|s| {
+            (
+                s.protocol.as_ref().and_then(|p| p.transcript_initial_state),
+                s.instances.iter().map(|i| i.len()).collect(),
+            )
+        })
+        .collect()
+}
+
+/// Aggregates a single chunk of snarks. Intermediate nodes of the tree are proven with a poseidon
+/// transcript and carry a protocol that exposes their accumulator, so that the next layer folds it
+/// in rather than treating it as plain public inputs.
+fn aggregate_chunk(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: AggregationCircuit,
+    transcript: TranscriptType,
+    check_mode: CheckMode,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let public_inputs = circuit.instances();
+    match transcript {
+        TranscriptType::EVM => create_proof_circuit::<
+            KZGCommitmentScheme<Bn256>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit,
+            vec![public_inputs],
+            params,
+            pk,
+            check_mode,
+            Commitments::KZG,
+            transcript,
+            None,
+            None,
+            None,
+        ),
+        TranscriptType::Blake2b => {
+            Err("aggregated proofs are only proven with evm or poseidon transcripts".into())
+        }
+        TranscriptType::Poseidon => {
+            let protocol = Some(compile(
+                params,
+                pk.get_vk(),
+                Config::kzg()
+                    .with_num_instance(vec![public_inputs.len()])
+                    .with_accumulator_indices(Some(AggregationCircuit::accumulator_indices())),
+            ));
+            create_proof_circuit::<
+                KZGCommitmentScheme<Bn256>,
+                _,
+                ProverSHPLONK<_>,
+                VerifierSHPLONK<_>,
+                AccumulatorStrategy<_>,
+                _,
+                PoseidonTranscript<NativeLoader, _>,
+                PoseidonTranscript<NativeLoader, _>,
+            >(
+                circuit,
+                vec![public_inputs],
+                params,
+                pk,
+                check_mode,
+                Commitments::KZG,
+                transcript,
+                None,
+                protocol,
+                None,
+            )
+        }
+    }
+}
+
+/// Recursively aggregates snarks into a single proof, `fan_in` proofs at a time. Each layer of the
+/// tree is proven chunk by chunk and only the proving key of the current chunk shape is kept
+/// around, so peak memory is bounded by a single aggregation of `fan_in` proofs rather than
+/// growing with the number of inner proofs.
+///
+/// Only the root is proven with `transcript`, inner layers always use a poseidon transcript so
+/// they can be verified in-circuit. Returns the root snark along with its verifying key.
+pub fn aggregate_tree(
+    params: &ParamsKZG<Bn256>,
+    snarks: Vec<Snark<Fr, G1Affine>>,
+    fan_in: usize,
+    transcript: TranscriptType,
+    check_mode: CheckMode,
+    disable_selector_compression: bool,
+) -> Result<(Snark<Fr, G1Affine>, VerifyingKey<G1Affine>), Box<dyn Error>> {
+    if fan_in < MIN_FAN_IN {
+        return Err(format!("fan-in must be at least {}, got {}", MIN_FAN_IN, fan_in).into());
+    }
+    if snarks.is_empty() {
+        return Err("no snarks to aggregate".into());
+    }
+    if snarks.iter().any(|s| s.protocol.is_none()) {
+        return Err("snarks must be generated with --proof-type=for-aggr to be aggregated".into());
+    }
+
+    let svk = ParamsProver::<G1Affine>::get_g(params)[0].into();
+    let mut layer = snarks;
+    let mut depth = 0;
+    loop {
+        let is_root = layer.len() <= fan_in;
+        let layer_transcript = if is_root {
+            transcript
+        } else {
+            TranscriptType::Poseidon
+        };
+        let num_proofs = layer.len();
+
+        let mut next = Vec::with_capacity(num_proofs.div_ceil(fan_in));
+        let mut keys: Option<(ChunkShape, ProvingKey<G1Affine>)> = None;
+        while !layer.is_empty() {
+            let chunk: Vec<_> = layer.drain(..fan_in.min(layer.len())).collect();
+            let shape = chunk_shape(&chunk);
+            let circuit = AggregationCircuit::new(&svk, chunk, false)?;
+
+            // the last chunk of a layer can be smaller, and every layer adds the accumulator to
+            // the instances, so keys are only reused across chunks of the same shape
+            if keys.as_ref().map(|(s, _)| s != &shape).unwrap_or(true) {
+                let pk = create_keys::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(
+                    &circuit,
+                    params,
+                    disable_selector_compression,
+                )?;
+                keys = Some((shape, pk));
+            }
+            let (_, pk) = keys.as_ref().unwrap();
+
+            next.push(aggregate_chunk(
+                params,
+                pk,
+                circuit,
+                layer_transcript,
+                check_mode,
+            )?);
+        }
+
+        info!(
+            "aggregation layer {}: {} proofs -> {}",
+            depth,
+            num_proofs,
+            next.len()
+        );
+
+        if is_root {
+            let (_, pk) = keys.unwrap();
+            return Ok((next.pop().unwrap(), pk.get_vk().clone()));
+        }
+        layer = next;
+        depth += 1;
+    }
+}
+
+/// The running state of the aggregation of a stream of proofs. `layers[i]` holds the
+/// proofs that each already aggregate `fan_in^i` inner proofs and are waiting for siblings: once a
+/// layer fills up it is aggregated into a single proof of the next layer, so the state never holds
+/// more than `fan_in - 1` proofs per layer.
+///
+/// Every inference is still fully proven and aggregated recursively, there is no folding scheme:
+/// the state only spreads the aggregation over the stream. The proving key of each layer is
+/// generated once and saved next to the state, see [AggregationState::pk_path].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AggregationState {
+    /// The number of proofs aggregated together
+    pub fan_in: usize,
+    /// The logrows of the aggregation circuits
+    pub logrows: u32,
+    /// The SRS the proving keys of the layers were generated with
+    pub srs_path: PathBuf,
+    /// The number of inner proofs pushed so far
+    pub num_proofs: usize,
+    /// The pending proofs of each layer
+    pub layers: Vec<Vec<Snark<Fr, G1Affine>>>,
+    /// The shape of the chunks aggregated at each layer, which their saved proving keys are for
+    #[serde(default)]
+    pub layer_shapes: Vec<ChunkShape>,
+    /// Where the state is saved, the proving keys of the layers are saved next to it
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl AggregationState {
+    /// A new state saved at `path`, aggregating `fan_in` proofs at a time in circuits of
+    /// `logrows` rows proven against the SRS at `srs_path`.
+    pub fn new(
+        path: PathBuf,
+        fan_in: usize,
+        logrows: u32,
+        srs_path: PathBuf,
+    ) -> Result<Self, Box<dyn Error>> {
+        if fan_in < MIN_FAN_IN {
+            return Err(format!("fan-in must be at least {}, got {}", MIN_FAN_IN, fan_in).into());
+        }
+        Ok(Self {
+            fan_in,
+            logrows,
+            srs_path,
+            num_proofs: 0,
+            layers: vec![],
+            layer_shapes: vec![],
+            path,
+        })
+    }
+
+    /// Load the state from a .json file
+    pub fn load(path: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open aggregation state {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        let mut state: Self = serde_json::from_reader(reader)?;
+        state.path = path.clone();
+        Ok(state)
+    }
+
+    /// Checks the state was created with the given settings. Unset settings match anything, as
+    /// they are only needed to create a state.
+    pub fn check(
+        &self,
+        fan_in: Option<usize>,
+        logrows: Option<u32>,
+        srs_path: Option<&PathBuf>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mismatch = |name: &str, stored: String, given: String| -> Box<dyn Error> {
+            format!(
+                "the aggregation state at {} was created with {} {}, got {}",
+                self.path.display(),
+                name,
+                stored,
+                given
+            )
+            .into()
+        };
+        if let Some(fan_in) = fan_in.filter(|f| *f != self.fan_in) {
+            return Err(mismatch(
+                "fan-in",
+                self.fan_in.to_string(),
+                fan_in.to_string(),
+            ));
+        }
+        if let Some(logrows) = logrows.filter(|l| *l != self.logrows) {
+            return Err(mismatch(
+                "logrows",
+                self.logrows.to_string(),
+                logrows.to_string(),
+            ));
+        }
+        if let Some(srs_path) = srs_path.filter(|p| **p != self.srs_path) {
+            return Err(mismatch(
+                "srs",
+                self.srs_path.display().to_string(),
+                srs_path.display().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Save the state to its .json file
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let writer =
+            BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(&self.path)?);
+        serde_json::to_writer(writer, &self)?;
+        Ok(())
+    }
+
+    /// The path of the proving key aggregating the chunks of layer `depth`
+    pub fn pk_path(&self, depth: usize) -> PathBuf {
+        self.path.with_extension(format!("layer{}.pk", depth))
+    }
+
+    /// The proving key of layer `depth`, loaded from next to the state or generated and saved
+    /// there the first time the layer fills up.
+    fn layer_pk(
+        &mut self,
+        params: &ParamsKZG<Bn256>,
+        depth: usize,
+        circuit: &AggregationCircuit,
+        shape: ChunkShape,
+        disable_selector_compression: bool,
+    ) -> Result<ProvingKey<G1Affine>, Box<dyn Error>> {
+        let pk_path = self.pk_path(depth);
+        if let Some(stored) = self.layer_shapes.get(depth) {
+            if stored != &shape {
+                return Err(format!(
+                    "the proofs of layer {} don't match the proofs the aggregation state was started with",
+                    depth
+                )
+                .into());
+            }
+            if pk_path.exists() {
+                return load_pk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(
+                    pk_path,
+                    (),
+                    false,
+                );
+            }
+        }
+
+        let pk = create_keys::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(
+            circuit,
+            params,
+            disable_selector_compression,
+        )?;
+        save_pk::<G1Affine>(&pk_path, &pk, &())?;
+        self.layer_shapes.truncate(depth);
+        self.layer_shapes.push(shape);
+        info!(
+            "saved the proving key of layer {} to {}",
+            depth,
+            pk_path.display()
+        );
+        Ok(pk)
+    }
+
+    /// Adds a proof to the stream, aggregating every layer that fills up along the way.
+    pub fn push(
+        &mut self,
+        params: &ParamsKZG<Bn256>,
+        snark: Snark<Fr, G1Affine>,
+        check_mode: CheckMode,
+        disable_selector_compression: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if snark.protocol.is_none() {
+            return Err(
+                "snarks must be generated with --proof-type=for-aggr to be aggregated".into(),
+            );
+        }
+        self.num_proofs += 1;
+
+        let svk = ParamsProver::<G1Affine>::get_g(params)[0].into();
+        let mut carry = snark;
+        for depth in 0.. {
+            if self.layers.len() <= depth {
+                self.layers.push(vec![]);
+            }
+            self.layers[depth].push(carry);
+            if self.layers[depth].len() < self.fan_in {
+                break;
+            }
+
+            let chunk = std::mem::take(&mut self.layers[depth]);
+            let shape = chunk_shape(&chunk);
+            let circuit = AggregationCircuit::new(&svk, chunk, false)?;
+            let pk = self.layer_pk(params, depth, &circuit, shape, disable_selector_compression)?;
+            carry = aggregate_chunk(params, &pk, circuit, TranscriptType::Poseidon, check_mode)?;
+            info!("aggregated layer {} into a single proof", depth);
+        }
+        Ok(())
+    }
+
+    /// Produces the final proof over every proof pushed so far, aggregating the pending proofs of
+    /// all layers with [aggregate_tree].
+    pub fn finalize(
+        self,
+        params: &ParamsKZG<Bn256>,
+        transcript: TranscriptType,
+        check_mode: CheckMode,
+        disable_selector_compression: bool,
+    ) -> Result<(Snark<Fr, G1Affine>, VerifyingKey<G1Affine>), Box<dyn Error>> {
+        let pending = self.layers.into_iter().flatten().collect();
+        aggregate_tree(
+            params,
+            pending,
+            self.fan_in,
+            transcript,
+            check_mode,
+            disable_selector_compression,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregation_state_rejects_changed_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let srs_path = dir.path().join("kzg23.srs");
+        let state = AggregationState::new(path.clone(), 2, 23, srs_path.clone()).unwrap();
+        state.save().unwrap();
+
+        let state = AggregationState::load(&path).unwrap();
+        assert_eq!(state.pk_path(1), dir.path().join("state.layer1.pk"));
+        assert!(state.check(None, None, None).is_ok());
+        assert!(state.check(Some(2), Some(23), Some(&srs_path)).is_ok());
+        assert!(state.check(Some(4), None, None).is_err());
+        assert!(state.check(None, Some(20), None).is_err());
+        assert!(state
+            .check(None, None, Some(&dir.path().join("kzg.srs")))
+            .is_err());
+    }
+}
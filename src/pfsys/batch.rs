@@ -0,0 +1,380 @@
+use super::{create_single_proof, verify_proof_circuit, ProofSplitCommit, Snark, TranscriptType};
+use crate::circuit::CheckMode;
+use crate::pfsys::evm::aggregation_kzg::PoseidonTranscript;
+use crate::{Commitments, EZKL_BUF_CAPACITY};
+use halo2_proofs::plonk::{Circuit, ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::{CommitmentScheme, Prover, Verifier};
+use halo2_proofs::poly::VerificationStrategy;
+use halo2_proofs::transcript::{Blake2bRead, Challenge255};
+use halo2curves::bn256::{Fr, G1Affine};
+use log::{info, warn};
+use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// A circuit loaded with one witness, ready to be proven as part of a batch.
+pub struct BatchItem<C, X> {
+    /// The circuit, with the witness loaded
+    pub circuit: C,
+    /// The public instances of the proof
+    pub instances: Vec<Vec<Fr>>,
+    /// The split of the proof's polycommits, if any
+    pub split: Option<ProofSplitCommit>,
+    /// Anything the caller needs to finish the proof, see [prove_batch]
+    pub context: X,
+}
+
+/// Proves every job against the same proving key and SRS, which are only loaded once.
+///
+/// Jobs are spread across the current rayon pool: `prepare` loads a job's witness into a circuit,
+/// the circuit is proven and `finish` consumes the proof (e.g writing it out), so only as many
+/// circuits as there are threads are held in memory at once. A failing job doesn't stop the
+/// others, its error is returned in its place. `seed` is passed on to [create_single_proof].
+#[allow(clippy::too_many_arguments)]
+pub fn prove_batch<
+    'params,
+    Scheme: CommitmentScheme<Scalar = Fr, Curve = G1Affine>,
+    C: Circuit<Fr>,
+    P: Prover<'params, Scheme>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+    J: Send,
+    X,
+    R: Send,
+>(
+    jobs: Vec<J>,
+    prepare: impl Fn(J) -> Result<BatchItem<C, X>, Box<dyn Error>> + Sync,
+    finish: impl Fn(Snark<Fr, G1Affine>, X) -> Result<R, Box<dyn Error>> + Sync,
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<G1Affine>,
+    check_mode: CheckMode,
+    commitment: Commitments,
+    transcript_type: TranscriptType,
+    seed: Option<u64>,
+) -> Vec<Result<R, String>>
+where
+    Scheme::ParamsVerifier: 'params,
+    Scheme::ParamsProver: Sync,
+{
+    let num_jobs = jobs.len();
+    info!("proving a batch of {} witnesses", num_jobs);
+    jobs.into_par_iter()
+        .map(|job| {
+            let item = prepare(job)?;
+            let snark = create_single_proof::<Scheme, C, P, V, Strategy>(
+                item.circuit,
+                item.instances,
+                params,
+                pk,
+                check_mode,
+                commitment,
+                transcript_type,
+                item.split,
+                seed,
+            )?;
+            finish(snark, item.context)
+        })
+        .map(|res| res.map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Verifies every proof against the same verification key and SRS.
+///
+/// Proofs are spread across the current rayon pool, each read with the transcript it was proven
+/// with. `orig_n` is the number of rows of the circuit, as passed to [verify_proof_circuit]. A
+/// failing proof doesn't stop the others, its error is returned in its place.
+pub fn verify_batch<
+    'params,
+    Scheme: CommitmentScheme<Scalar = Fr, Curve = G1Affine>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+>(
+    snarks: &[Snark<Fr, G1Affine>],
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<G1Affine>,
+    orig_n: u64,
+) -> Vec<Result<(), String>>
+where
+    Scheme::ParamsVerifier: Sync,
+{
+    info!("verifying a batch of {} proofs", snarks.len());
+    snarks
+        .par_iter()
+        .map(|snark| {
+            let strategy = Strategy::new(params);
+            let res = match snark.transcript_type {
+                TranscriptType::EVM => verify_proof_circuit::<
+                    V,
+                    Scheme,
+                    Strategy,
+                    _,
+                    EvmTranscript<G1Affine, _, _, _>,
+                >(snark, params, vk, strategy, orig_n),
+                TranscriptType::Poseidon => verify_proof_circuit::<
+                    V,
+                    Scheme,
+                    Strategy,
+                    _,
+                    PoseidonTranscript<NativeLoader, _>,
+                >(snark, params, vk, strategy, orig_n),
+                TranscriptType::Blake2b => verify_proof_circuit::<
+                    V,
+                    Scheme,
+                    Strategy,
+                    _,
+                    Blake2bRead<_, _, Challenge255<_>>,
+                >(snark, params, vk, strategy, orig_n),
+            };
+            res.map(|_| ()).map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// The outcome of proving a single witness of a batch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BatchEntry {
+    /// The witness file
+    pub witness: PathBuf,
+    /// The proof file, if proving succeeded
+    pub proof: Option<PathBuf>,
+    /// Why proving failed, if it did
+    pub error: Option<String>,
+}
+
+/// The manifest of a batch of proofs, listing the proof of each witness.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchManifest {
+    /// One entry per witness, in the order they were listed
+    pub entries: Vec<BatchEntry>,
+}
+
+impl BatchManifest {
+    /// Builds the manifest from the witnesses of a batch and the results of [prove_batch].
+    pub fn new(witnesses: Vec<PathBuf>, results: Vec<Result<PathBuf, String>>) -> Self {
+        let entries = witnesses
+            .into_iter()
+            .zip(results)
+            .map(|(witness, res)| match res {
+                Ok(proof) => BatchEntry {
+                    witness,
+                    proof: Some(proof),
+                    error: None,
+                },
+                Err(error) => {
+                    warn!("failed to prove {}: {}", witness.display(), error);
+                    BatchEntry {
+                        witness,
+                        proof: None,
+                        error: Some(error),
+                    }
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// The number of witnesses that failed to prove
+    pub fn num_failed(&self) -> usize {
+        self.entries.iter().filter(|e| e.error.is_some()).count()
+    }
+
+    /// Load the manifest from a .json file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open manifest {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the manifest to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(writer, &self)?;
+        Ok(())
+    }
+}
+
+/// The outcome of verifying a single proof of a batch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerificationEntry {
+    /// The proof file
+    pub proof: PathBuf,
+    /// Whether the proof verified
+    pub verified: bool,
+    /// Why the proof could not be verified, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The per-proof results of verifying a batch of proofs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// One entry per proof, in the order they were listed
+    pub entries: Vec<VerificationEntry>,
+}
+
+impl VerificationReport {
+    /// Builds the report from the proofs of a batch and the results of [verify_batch].
+    pub fn new(proofs: Vec<PathBuf>, results: Vec<Result<(), String>>) -> Self {
+        let entries = proofs
+            .into_iter()
+            .zip(results)
+            .map(|(proof, res)| {
+                if let Err(e) = &res {
+                    warn!("failed to verify {}: {}", proof.display(), e);
+                }
+                VerificationEntry {
+                    proof,
+                    verified: res.is_ok(),
+                    error: res.err(),
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// The number of proofs that failed to verify
+    pub fn num_failed(&self) -> usize {
+        self.entries.iter().filter(|e| !e.verified).count()
+    }
+
+    /// Errors, listing the proofs that failed to verify, unless every proof verified.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        let failed = self
+            .entries
+            .iter()
+            .filter(|e| !e.verified)
+            .map(|e| e.proof.display().to_string())
+            .collect::<Vec<_>>();
+        if failed.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "{} of {} proofs failed to verify: {}",
+            failed.len(),
+            self.entries.len(),
+            failed.join(", ")
+        )
+        .into())
+    }
+}
+
+/// The path in `proof_dir` of the proof of each witness, named after the witness's file stem. Stems
+/// that collide, compared case insensitively for case insensitive file systems, or that would
+/// overwrite the manifest of the batch get a numeric suffix, so that no proof overwrites another.
+pub fn proof_paths(proof_dir: &Path, witnesses: &[PathBuf]) -> Vec<PathBuf> {
+    let mut taken = HashSet::from(["manifest".to_string()]);
+    witnesses
+        .iter()
+        .map(|witness| {
+            let stem = witness
+                .file_stem()
+                .map_or("proof".into(), |s| s.to_string_lossy().into_owned());
+            let mut name = stem.clone();
+            let mut suffix = 1;
+            while !taken.insert(name.to_lowercase()) {
+                name = format!("{}_{}", stem, suffix);
+                suffix += 1;
+            }
+            proof_dir.join(format!("{}.json", name))
+        })
+        .collect()
+}
+
+/// Every file in `dir` with one of the `extensions`, sorted by name.
+fn list_files(dir: &Path, kind: &str, extensions: &[&str]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = std::fs::read_dir(dir)
+        .map_err(|e| format!("failed to read {} dir {}: {}", kind, dir.display(), e))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>, std::io::Error>>()?;
+    files.retain(|p| {
+        p.is_file()
+            && p.extension().map_or(false, |e| {
+                extensions.iter().any(|ext| e.eq_ignore_ascii_case(ext))
+            })
+    });
+    files.sort();
+    Ok(files)
+}
+
+/// The witness files in `dir`, i.e every `.json` file, sorted by name.
+pub fn list_witnesses(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    list_files(dir, "witness", &["json"])
+}
+
+/// The proof files in `dir`, i.e every `.json` or `.cbor` file but the manifest of a batch, sorted
+/// by name.
+pub fn list_proofs(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut proofs = list_files(dir, "proof", &["json", "cbor"])?;
+    proofs.retain(|p| p.file_name().map_or(true, |f| f != "manifest.json"));
+    Ok(proofs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_manifest() {
+        let manifest = BatchManifest::new(
+            vec!["a.json".into(), "b.json".into()],
+            vec![Ok("proofs/a.json".into()), Err("unsatisfied".into())],
+        );
+        assert_eq!(manifest.num_failed(), 1);
+        assert_eq!(manifest.entries[0].proof, Some("proofs/a.json".into()));
+        assert_eq!(manifest.entries[1].error.as_deref(), Some("unsatisfied"));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("a.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let witnesses = list_witnesses(dir.path()).unwrap();
+        assert_eq!(
+            witnesses,
+            vec![dir.path().join("a.json"), dir.path().join("b.json")]
+        );
+
+        // the manifest of a batch sits next to its proofs but isn't one, CBOR proofs are
+        std::fs::write(dir.path().join("manifest.json"), "{}").unwrap();
+        assert_eq!(list_proofs(dir.path()).unwrap(), witnesses);
+        std::fs::write(dir.path().join("c.cbor"), "").unwrap();
+        assert_eq!(
+            list_proofs(dir.path()).unwrap(),
+            vec![
+                dir.path().join("a.json"),
+                dir.path().join("b.json"),
+                dir.path().join("c.cbor")
+            ]
+        );
+        assert_eq!(list_witnesses(dir.path()).unwrap(), witnesses);
+
+        // proofs never overwrite each other or the manifest
+        let proofs = proof_paths(
+            Path::new("proofs"),
+            &[
+                "a/x.json".into(),
+                "b/x.json".into(),
+                "X.json".into(),
+                "manifest.json".into(),
+            ],
+        );
+        assert_eq!(
+            proofs,
+            ["x", "x_1", "X_2", "manifest_1"]
+                .map(|name| Path::new("proofs").join(format!("{}.json", name)))
+        );
+
+        let report = VerificationReport::new(witnesses, vec![Ok(()), Err("bad proof".into())]);
+        assert_eq!(report.num_failed(), 1);
+        assert!(report.entries[0].verified);
+        assert_eq!(report.entries[1].error.as_deref(), Some("bad proof"));
+        assert!(report.check().is_err());
+        assert!(VerificationReport::new(vec!["a.json".into()], vec![Ok(())])
+            .check()
+            .is_ok());
+    }
+}
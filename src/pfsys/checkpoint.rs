@@ -0,0 +1,198 @@
+//! Checkpoints of the stages of setup and proving, see `--checkpoint-dir`.
+//!
+//! A [Checkpoint] keeps the artifact of each stage of a command that completed, e.g the verifying
+//! and proving keys of setup, along with a fingerprint of the inputs of the command. When the
+//! command is rerun with `--resume` on the same inputs, the stages whose artifacts were kept are
+//! loaded rather than recomputed. halo2 can't be interrupted within key generation or proof
+//! creation, nor be handed the fixed and permutation polynomials or the witness assignment it
+//! computes within them, so the keys and the proof are the finest stages there are. Checkpoints are
+//! removed once their command succeeds.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// The stages of a command completed so far, and the inputs they were completed for.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointManifest {
+    /// the fingerprint of the inputs of the command, see [fingerprint]
+    pub fingerprint: String,
+    /// the stages completed, in order
+    pub completed: Vec<String>,
+}
+
+/// The checkpoint of a command in a directory.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    dir: PathBuf,
+    command: String,
+    manifest: CheckpointManifest,
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint of `command` in `dir`. Its completed stages are kept if `resume` is
+    /// set and they were completed for the same `fingerprint`, otherwise the command starts over.
+    pub fn open(
+        dir: &Path,
+        command: &str,
+        fingerprint: String,
+        resume: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        let mut checkpoint = Self {
+            dir: dir.to_path_buf(),
+            command: command.to_string(),
+            manifest: CheckpointManifest::default(),
+        };
+        let previous = match std::fs::read(checkpoint.manifest_path()) {
+            Ok(manifest) => Some(serde_json::from_slice::<CheckpointManifest>(&manifest)?),
+            Err(_) => None,
+        };
+        match previous {
+            Some(previous) if resume && previous.fingerprint == fingerprint => {
+                info!(
+                    "resuming {} from {}, after {}",
+                    command,
+                    dir.display(),
+                    previous.completed.join(", ")
+                );
+                checkpoint.manifest = previous;
+                return Ok(checkpoint);
+            }
+            Some(previous) => {
+                if resume {
+                    warn!(
+                        "the checkpoint of {} in {} is for other inputs, starting over",
+                        command,
+                        dir.display()
+                    );
+                }
+                for stage in &previous.completed {
+                    let _ = std::fs::remove_file(checkpoint.artifact(stage));
+                }
+            }
+            None if resume => {
+                info!(
+                    "no checkpoint of {} in {}, starting over",
+                    command,
+                    dir.display()
+                );
+            }
+            None => {}
+        }
+        checkpoint.manifest.fingerprint = fingerprint;
+        checkpoint.save()?;
+        Ok(checkpoint)
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.checkpoint.json", self.command))
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        // written then renamed, so that a crash never leaves a partial manifest
+        let tmp = self
+            .dir
+            .join(format!("{}.checkpoint.json.tmp", self.command));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&self.manifest)?)?;
+        std::fs::rename(tmp, self.manifest_path())?;
+        Ok(())
+    }
+
+    /// The file the artifact of `stage` is written to.
+    pub fn artifact(&self, stage: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}.checkpoint", self.command, stage))
+    }
+
+    /// Whether `stage` completed and its artifact is still there.
+    pub fn is_complete(&self, stage: &str) -> bool {
+        self.manifest.completed.iter().any(|s| s == stage) && self.artifact(stage).is_file()
+    }
+
+    /// Records that `stage` completed, once its artifact has been written.
+    pub fn complete(&mut self, stage: &str) -> Result<(), Box<dyn Error>> {
+        if !self.manifest.completed.iter().any(|s| s == stage) {
+            self.manifest.completed.push(stage.to_string());
+        }
+        self.save()
+    }
+
+    /// Removes the checkpoint once its command succeeded.
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        for stage in &self.manifest.completed {
+            let _ = std::fs::remove_file(self.artifact(stage));
+        }
+        std::fs::remove_file(self.manifest_path())?;
+        Ok(())
+    }
+}
+
+/// The SHA256 fingerprint of the inputs of a command: the contents of `files`, the size and
+/// modification time of `large_files` (e.g keys and SRS, too large to hash on every run) and
+/// `args`.
+pub fn fingerprint(
+    files: &[&Path],
+    large_files: &[&Path],
+    args: serde_json::Value,
+) -> Result<String, Box<dyn Error>> {
+    let mut inputs = vec![];
+    for path in files {
+        let contents =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        inputs.push(serde_json::json!([
+            path,
+            sha256::digest(contents.as_slice())
+        ]));
+    }
+    for path in large_files {
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos());
+        inputs.push(serde_json::json!([path, metadata.len(), modified]));
+    }
+    let fingerprint = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "inputs": inputs,
+        "args": args,
+    });
+    Ok(sha256::digest(serde_json::to_vec(&fingerprint)?.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input");
+        std::fs::write(&input, "circuit").unwrap();
+        let args = serde_json::json!({ "logrows": 17 });
+        let fp = fingerprint(&[input.as_path()], &[], args.clone()).unwrap();
+
+        let mut checkpoint = Checkpoint::open(dir.path(), "setup", fp.clone(), false).unwrap();
+        assert!(!checkpoint.is_complete("vk"));
+        std::fs::write(checkpoint.artifact("vk"), "vk").unwrap();
+        checkpoint.complete("vk").unwrap();
+
+        let checkpoint = Checkpoint::open(dir.path(), "setup", fp.clone(), true).unwrap();
+        assert!(checkpoint.is_complete("vk"));
+
+        // other inputs start over, removing the stale artifacts
+        std::fs::write(&input, "another circuit").unwrap();
+        let other = fingerprint(&[input.as_path()], &[], args).unwrap();
+        assert_ne!(fp, other);
+        let checkpoint = Checkpoint::open(dir.path(), "setup", other, true).unwrap();
+        assert!(!checkpoint.is_complete("vk"));
+        assert!(!checkpoint.artifact("vk").exists());
+
+        checkpoint.finish().unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,268 @@
+//! Compact proofs, with the points of the transcript compressed and repeated instances stored once.
+//!
+//! Evm transcripts write points as their uncompressed `(x, y)` coordinates, two 32 byte words each,
+//! so that they can be read as is on-chain. [Snark::compress] replaces every such point with its 32
+//! byte compressed encoding and stores each distinct instance once, with the instances of each
+//! column as indices into them. [Snark::from_reader] decompresses proofs as they are loaded, so
+//! every verifier path takes compressed proofs without changes.
+//!
+//! Compression is lossless for the proof bytes and instances. The hex proof is recreated when the
+//! proof is decompressed, but the pretty public inputs, which are derived from the witness, are
+//! dropped.
+
+use super::{Snark, TranscriptType, SNARK_FORMAT_VERSION};
+use halo2curves::ff::{FromUniformBytes, PrimeField};
+use halo2curves::group::GroupEncoding;
+use halo2curves::serde::SerdeObject;
+use halo2curves::CurveAffine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+/// The format version of compressed proofs.
+pub const COMPRESSED_SNARK_FORMAT_VERSION: u32 = 3;
+
+/// The size of the words of an evm transcript.
+const WORD_SIZE: usize = 32;
+
+/// How a compressed proof was compressed, see [Snark::compress].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Compression<F: PrimeField + SerdeObject> {
+    /// The words of the compressed proof that are compressed points
+    pub points: Vec<usize>,
+    /// The distinct instances, in the order they first appear
+    pub instance_values: Vec<F>,
+    /// The instances of each column, as indices into `instance_values`
+    pub instance_indices: Vec<Vec<usize>>,
+}
+
+/// Reads a base field element from a big endian word.
+fn coordinate_from_word<C: CurveAffine>(word: &[u8]) -> Option<C::Base> {
+    let mut repr = <C::Base as PrimeField>::Repr::default();
+    if repr.as_ref().len() != word.len() {
+        return None;
+    }
+    for (dst, src) in repr.as_mut().iter_mut().zip(word.iter().rev()) {
+        *dst = *src;
+    }
+    Option::from(C::Base::from_repr(repr))
+}
+
+/// The point whose uncompressed coordinates are the words `x` and `y`, if they are on the curve.
+fn point_from_words<C: CurveAffine>(x: &[u8], y: &[u8]) -> Option<C> {
+    let (x, y) = (coordinate_from_word::<C>(x)?, coordinate_from_word::<C>(y)?);
+    Option::from(C::from_xy(x, y))
+}
+
+/// The uncompressed coordinates of a point as big endian words, as an evm transcript writes them.
+fn point_to_words<C: CurveAffine>(point: &C) -> Option<Vec<u8>> {
+    let coordinates = point.coordinates();
+    if bool::from(coordinates.is_none()) {
+        return None;
+    }
+    let coordinates = coordinates.unwrap();
+    let mut words = vec![];
+    for coordinate in [coordinates.x(), coordinates.y()] {
+        words.extend(coordinate.to_repr().as_ref().iter().rev());
+    }
+    Some(words)
+}
+
+/// Compresses the points of an evm transcript, returning the compressed bytes and the words of
+/// them that are compressed points.
+fn compress_points<C: CurveAffine>(proof: &[u8]) -> (Vec<u8>, Vec<usize>) {
+    if proof.len() % WORD_SIZE != 0 || C::Repr::default().as_ref().len() != WORD_SIZE {
+        return (proof.to_vec(), vec![]);
+    }
+    let words: Vec<&[u8]> = proof.chunks_exact(WORD_SIZE).collect();
+    let mut compressed = Vec::with_capacity(proof.len());
+    let mut points = vec![];
+    let mut i = 0;
+    while i < words.len() {
+        // a pair of words is only taken for a point if the point encodes back to the same words,
+        // which keeps the compression lossless whatever the words are
+        let point = words
+            .get(i + 1)
+            .and_then(|y| point_from_words::<C>(words[i], y))
+            .filter(|p| {
+                point_to_words(p).as_deref() == Some(&proof[i * WORD_SIZE..(i + 2) * WORD_SIZE])
+            });
+        match point {
+            Some(point) => {
+                points.push(compressed.len() / WORD_SIZE);
+                compressed.extend(point.to_bytes().as_ref());
+                i += 2;
+            }
+            None => {
+                compressed.extend(words[i]);
+                i += 1;
+            }
+        }
+    }
+    (compressed, points)
+}
+
+/// Reverses [compress_points].
+fn decompress_points<C: CurveAffine>(
+    compressed: &[u8],
+    points: &[usize],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if compressed.len() % WORD_SIZE != 0 {
+        return Err("compressed proof is not a whole number of words".into());
+    }
+    let points: BTreeSet<usize> = points.iter().copied().collect();
+    let mut proof = Vec::with_capacity(compressed.len() + points.len() * WORD_SIZE);
+    for (i, word) in compressed.chunks_exact(WORD_SIZE).enumerate() {
+        if points.contains(&i) {
+            let mut repr = C::Repr::default();
+            if repr.as_ref().len() != WORD_SIZE {
+                return Err("unsupported point encoding".into());
+            }
+            repr.as_mut().copy_from_slice(word);
+            let point: Option<C> = Option::from(C::from_bytes(&repr));
+            let point = point
+                .ok_or_else(|| format!("word {} of the compressed proof is not a point", i))?;
+            proof.extend(point_to_words(&point).ok_or("the identity can't be decompressed")?);
+        } else {
+            proof.extend(word);
+        }
+    }
+    Ok(proof)
+}
+
+impl<
+        F: PrimeField + SerdeObject + Serialize + FromUniformBytes<64> + DeserializeOwned,
+        C: CurveAffine + Serialize + DeserializeOwned,
+    > Snark<F, C>
+where
+    C::Scalar: Serialize + DeserializeOwned,
+    C::ScalarExt: Serialize + DeserializeOwned,
+{
+    /// Whether the proof is compressed, see [Self::compress].
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    /// Compresses the points of an evm transcript and stores each distinct instance once. The
+    /// hex proof and pretty public inputs, which duplicate the proof and instances, are dropped.
+    pub fn compress(&mut self) {
+        if self.is_compressed() {
+            return;
+        }
+
+        let mut points = vec![];
+        if self.transcript_type == TranscriptType::EVM {
+            let (compressed, compressed_points) = compress_points::<C>(&self.proof);
+            self.proof = compressed;
+            points = compressed_points;
+        }
+
+        let mut instance_values = vec![];
+        let mut seen = HashMap::new();
+        let instance_indices = self
+            .instances
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|value| {
+                        *seen
+                            .entry(value.to_repr().as_ref().to_vec())
+                            .or_insert_with(|| {
+                                instance_values.push(*value);
+                                instance_values.len() - 1
+                            })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        self.instances = vec![];
+        self.hex_proof = None;
+        self.pretty_public_inputs = None;
+        self.format_version = COMPRESSED_SNARK_FORMAT_VERSION;
+        self.compression = Some(Compression {
+            points,
+            instance_values,
+            instance_indices,
+        });
+    }
+
+    /// Reverses [Self::compress], leaving uncompressed proofs as they are.
+    pub fn decompress(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(compression) = self.compression.take() else {
+            return Ok(());
+        };
+
+        if !compression.points.is_empty() {
+            self.proof = decompress_points::<C>(&self.proof, &compression.points)?;
+        }
+        self.instances = compression
+            .instance_indices
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|i| {
+                        compression
+                            .instance_values
+                            .get(*i)
+                            .copied()
+                            .ok_or_else(|| format!("instance index {} is out of range", i))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.format_version = SNARK_FORMAT_VERSION;
+        if self.transcript_type == TranscriptType::EVM {
+            self.create_hex_proof();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::{Fr, G1Affine};
+    use halo2curves::group::prime::PrimeCurveAffine;
+    use halo2curves::group::Curve;
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let point = (G1Affine::generator() * Fr::from(5)).to_affine();
+        // a scalar word, a point and another scalar word, as an evm transcript lays them out
+        let mut proof = Fr::from(3).to_repr().as_ref().to_vec();
+        proof.extend(point_to_words(&point).unwrap());
+        proof.extend([0u8; WORD_SIZE]);
+
+        let mut snark = Snark::<Fr, G1Affine>::new(
+            None,
+            vec![
+                vec![Fr::from(0), Fr::from(7), Fr::from(0)],
+                vec![Fr::from(7)],
+            ],
+            proof.clone(),
+            None,
+            TranscriptType::EVM,
+            None,
+            None,
+            None,
+        );
+        let instances = snark.instances.clone();
+
+        snark.compress();
+        assert_eq!(snark.proof.len(), 3 * WORD_SIZE);
+        let compression = snark.compression.as_ref().unwrap();
+        assert_eq!(compression.points, vec![1]);
+        assert_eq!(compression.instance_values.len(), 2);
+
+        let json = serde_json::to_string(&snark).unwrap();
+        let loaded = Snark::<Fr, G1Affine>::from_reader(&mut json.as_bytes()).unwrap();
+        assert!(!loaded.is_compressed());
+        assert_eq!(loaded.proof, proof);
+        assert_eq!(loaded.instances, instances);
+        assert_eq!(loaded.format_version, SNARK_FORMAT_VERSION);
+    }
+}
@@ -0,0 +1,225 @@
+//! Verification of proofs encoded as the Solidity verifier takes them: the raw bytes of a proof
+//! with an EVM transcript and its flattened instances as 32 byte big endian words. The verifier
+//! contracts generated for non-EVM chains (see `ezkl create-wasm-verifier`) bundle a model's
+//! settings (see [encode_settings]), verifying key and verifier params and call
+//! [verify_encoded], so proofs and instances are passed to them exactly as they are to a
+//! Halo2Verifier.
+
+use crate::circuit::lookup::LookupOp;
+use crate::circuit::table::Range;
+use crate::circuit::CheckMode;
+use crate::graph::modules::ModuleSizes;
+use crate::graph::{GraphCircuit, GraphSettings, RequiredLookup};
+use crate::pfsys::key_header::read_key_header;
+use halo2_proofs::plonk::{verify_proof, VerifyingKey};
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::transcript::TranscriptReadBuffer;
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2curves::ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use std::error::Error;
+use std::io::Cursor;
+
+/// The part of [GraphSettings] a verifier rebuilds the constraint system of a model from. The
+/// contracts decode it with bincode rather than json: float parsing would pull float instructions
+/// in, which CosmWasm rejects, and bincode can't skip the fields [GraphSettings] leaves out when
+/// empty.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct VerifierSettings {
+    run_args: crate::RunArgs,
+    num_rows: usize,
+    total_assignments: usize,
+    total_const_size: usize,
+    total_dynamic_col_size: usize,
+    num_dynamic_lookups: usize,
+    num_shuffles: usize,
+    total_shuffle_col_size: usize,
+    model_instance_shapes: Vec<Vec<usize>>,
+    model_output_scales: Vec<crate::Scale>,
+    model_input_scales: Vec<crate::Scale>,
+    module_sizes: ModuleSizes,
+    required_lookups: Vec<(LookupOp, Option<Range>)>,
+    required_range_checks: Vec<Range>,
+    check_mode: CheckMode,
+    num_blinding_factors: Option<usize>,
+    model_hash: Option<Fr>,
+}
+
+impl From<&GraphSettings> for VerifierSettings {
+    fn from(settings: &GraphSettings) -> Self {
+        let settings = settings.clone();
+        VerifierSettings {
+            run_args: settings.run_args,
+            num_rows: settings.num_rows,
+            total_assignments: settings.total_assignments,
+            total_const_size: settings.total_const_size,
+            total_dynamic_col_size: settings.total_dynamic_col_size,
+            num_dynamic_lookups: settings.num_dynamic_lookups,
+            num_shuffles: settings.num_shuffles,
+            total_shuffle_col_size: settings.total_shuffle_col_size,
+            model_instance_shapes: settings.model_instance_shapes,
+            model_output_scales: settings.model_output_scales,
+            model_input_scales: settings.model_input_scales,
+            module_sizes: settings.module_sizes,
+            required_lookups: settings
+                .required_lookups
+                .into_iter()
+                .map(|lookup| (lookup.op, lookup.domain))
+                .collect(),
+            required_range_checks: settings.required_range_checks,
+            check_mode: settings.check_mode,
+            num_blinding_factors: settings.num_blinding_factors,
+            model_hash: settings.model_hash,
+        }
+    }
+}
+
+impl From<VerifierSettings> for GraphSettings {
+    fn from(settings: VerifierSettings) -> Self {
+        GraphSettings {
+            run_args: settings.run_args,
+            num_rows: settings.num_rows,
+            total_assignments: settings.total_assignments,
+            total_const_size: settings.total_const_size,
+            total_dynamic_col_size: settings.total_dynamic_col_size,
+            num_dynamic_lookups: settings.num_dynamic_lookups,
+            num_shuffles: settings.num_shuffles,
+            total_shuffle_col_size: settings.total_shuffle_col_size,
+            model_instance_shapes: settings.model_instance_shapes,
+            model_output_scales: settings.model_output_scales,
+            model_input_scales: settings.model_input_scales,
+            module_sizes: settings.module_sizes,
+            required_lookups: settings
+                .required_lookups
+                .into_iter()
+                .map(|(op, domain)| RequiredLookup { op, domain })
+                .collect(),
+            required_range_checks: settings.required_range_checks,
+            check_mode: settings.check_mode,
+            num_blinding_factors: settings.num_blinding_factors,
+            model_hash: settings.model_hash,
+            ..Default::default()
+        }
+    }
+}
+
+/// Encodes the settings [verify_encoded] takes.
+pub fn encode_settings(settings: &GraphSettings) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(bincode::serialize(&VerifierSettings::from(settings))?)
+}
+
+/// Encodes instances as the 32 byte big endian words of the `uint256[]` of the Solidity verifier.
+pub fn instances_to_words(instances: &[Fr]) -> Vec<[u8; 32]> {
+    instances
+        .iter()
+        .map(|felt| {
+            let mut word = felt.to_repr();
+            word.reverse();
+            word
+        })
+        .collect()
+}
+
+/// Decodes 32 byte big endian words into instances, failing on words that aren't field elements.
+pub fn words_to_instances(words: &[[u8; 32]]) -> Result<Vec<Fr>, Box<dyn Error>> {
+    words
+        .iter()
+        .map(|word| {
+            let mut repr = *word;
+            repr.reverse();
+            Option::from(Fr::from_repr(repr))
+                .ok_or_else(|| "instance is not a field element".into())
+        })
+        .collect()
+}
+
+/// Verifies a KZG proof with an EVM transcript, given as the Solidity verifier takes it, against
+/// the settings (see [encode_settings]), verifying key and verifier params of a model. The params
+/// only need `G_0`, so an SRS downsized to `k = 1` does. Returns `Ok(false)` for proofs that don't
+/// verify and errors only on malformed artifacts. The key header isn't checked against the
+/// settings, which is left to whoever bundles them.
+pub fn verify_encoded(
+    proof: &[u8],
+    instances: &[[u8; 32]],
+    settings: &[u8],
+    vk: &[u8],
+    srs: &[u8],
+) -> Result<bool, Box<dyn Error>> {
+    let settings: GraphSettings = bincode::deserialize::<VerifierSettings>(settings)?.into();
+    let num_instances = settings.total_instances().iter().sum::<usize>();
+    if instances.len() != num_instances {
+        return Err(format!(
+            "expected {} instances but got {}",
+            num_instances,
+            instances.len()
+        )
+        .into());
+    }
+    let instances = words_to_instances(instances)?;
+
+    let mut reader = std::io::BufReader::new(vk);
+    read_key_header::<G1Affine, _>(&mut reader, "verification key", &(), false)?;
+    let orig_n = 1 << settings.run_args.logrows;
+    let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
+        &mut reader,
+        halo2_proofs::SerdeFormat::RawBytes,
+        settings,
+    )?;
+
+    let params: ParamsKZG<Bn256> = Params::<'_, G1Affine>::read(&mut &srs[..])?;
+    let strategy = SingleStrategy::new(params.verifier_params());
+    let mut transcript = EvmTranscript::<G1Affine, _, _, _>::init(Cursor::new(proof.to_vec()));
+    let instances: &[&[&[Fr]]] = &[&[&instances]];
+    let result = verify_proof::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, _, _, _>(
+        params.verifier_params(),
+        &vk,
+        strategy,
+        instances,
+        &mut transcript,
+        orig_n,
+    );
+    Ok(result.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_words() {
+        let instances = vec![Fr::from(1), -Fr::from(2)];
+        let words = instances_to_words(&instances);
+        // big endian, as the uint256 of the Solidity verifier
+        assert_eq!(words[0][31], 1);
+        assert_eq!(words[0][..31], [0; 31]);
+        assert_eq!(words_to_instances(&words).unwrap(), instances);
+        assert!(words_to_instances(&[[0xff; 32]]).is_err());
+    }
+
+    #[test]
+    fn test_encode_settings() {
+        let mut settings = GraphSettings {
+            num_rows: 42,
+            model_instance_shapes: vec![vec![1, 3]],
+            ..Default::default()
+        };
+        // lookups with and without a domain, which the json of the settings leaves out
+        settings.required_lookups = vec![
+            LookupOp::Div { denom: 2.0.into() }.into(),
+            RequiredLookup {
+                op: LookupOp::Sigmoid { scale: 4.0.into() },
+                domain: Some((-8, 8)),
+            },
+        ];
+
+        let decoded: GraphSettings =
+            bincode::deserialize::<VerifierSettings>(&encode_settings(&settings).unwrap())
+                .unwrap()
+                .into();
+        assert_eq!(decoded, settings);
+    }
+}
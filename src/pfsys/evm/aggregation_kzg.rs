@@ -18,6 +18,7 @@ use halo2curves::ff::PrimeField;
 use itertools::Itertools;
 use log::trace;
 use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
 use snark_verifier::loader::native::NativeLoader;
 use snark_verifier::loader::EcPointLoader;
 use snark_verifier::{
@@ -30,9 +31,10 @@ use snark_verifier::{
         AccumulationScheme, AccumulationSchemeProver,
     },
     system,
-    util::arithmetic::fe_to_limbs,
-    verifier::{self, SnarkVerifier},
+    util::{arithmetic::fe_to_limbs, transcript::Transcript},
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
 };
+use std::ops::Range;
 use std::rc::Rc;
 use thiserror::Error;
 
@@ -70,6 +72,64 @@ pub enum AggregationError {
     /// proof creation errors
     #[error("Failed to create proof")]
     ProofCreate,
+    /// a snark was generated without the protocol needed to aggregate it
+    #[error("snark {0} has no protocol, generate it with --proof-type=for-aggr")]
+    MissingProtocol(usize),
+    /// a snark's protocol has no verifying key digest to key a universal verifier by
+    #[error("snark {0} has no verifying key digest")]
+    MissingVkDigest(usize),
+}
+
+/// The hash a universal verifier keys an inner proof's verifying key by: a poseidon hash of the
+/// key's digest, i.e the initial state of its transcript, and of its preprocessed commitments.
+///
+/// The digest alone isn't enough, as a universal verifier loads the preprocessed commitments
+/// as witnesses and the digest doesn't bind them.
+pub fn vk_hash(protocol: &PlonkProtocol<G1Affine>) -> Option<Fr> {
+    let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::<u8>::new());
+    transcript
+        .common_scalar(protocol.transcript_initial_state.as_ref()?)
+        .ok()?;
+    for point in protocol.preprocessed.iter() {
+        transcript.common_ec_point(point).ok()?;
+    }
+    Some(transcript.squeeze_challenge())
+}
+
+/// Where the instances of one of the aggregated snarks sit among the instances of the aggregate.
+///
+/// Snarks of distinct circuits can be aggregated together, so the instances following the
+/// accumulator limbs are only meaningful along with the layout of each snark.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InnerProofLayout {
+    /// The digest of the snark's verifying key, i.e the initial state of its transcript
+    pub vk_digest: Option<Fr>,
+    /// The offset of the snark's first instance in the aggregate's instances
+    pub offset: usize,
+    /// The number of instances in each of the snark's instance columns
+    pub num_instances: Vec<usize>,
+}
+
+impl InnerProofLayout {
+    /// The range of the aggregate's instances holding the snark's instances
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.num_instances.iter().sum::<usize>()
+    }
+
+    /// The snark's instances, column by column, out of the instances of the aggregate.
+    pub fn instances<'a>(&self, aggregate: &'a [Fr]) -> Option<Vec<&'a [Fr]>> {
+        let mut instances = aggregate.get(self.range())?;
+        Some(
+            self.num_instances
+                .iter()
+                .map(|len| {
+                    let (column, rest) = instances.split_at(*len);
+                    instances = rest;
+                    column
+                })
+                .collect(),
+        )
+    }
 }
 
 type AggregationResult<'a> = (
@@ -77,6 +137,8 @@ type AggregationResult<'a> = (
     KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>>,
     // the set of assigned cells
     Vec<Vec<AssignedCell<Fr, Fr>>>,
+    // the vk hash of each snark, for universal verifiers
+    Vec<AssignedCell<Fr, Fr>>,
 );
 
 type LoadedProof<'a> = verifier::plonk::PlonkProof<
@@ -91,13 +153,51 @@ type LoadedProof<'a> = verifier::plonk::PlonkProof<
     KzgAs<Bn256, Bdfg21>,
 >;
 
-/// Aggregate one or more application snarks of the same shape into a KzgAccumulator
+/// Loads the digest and preprocessed commitments of `protocol` as witnesses rather than the
+/// constants of `native`, returning their [vk_hash] computed in-circuit.
+fn load_vk<'a>(
+    loader: &Rc<Halo2Loader<'a>>,
+    protocol: &mut PlonkProtocol<G1Affine, Rc<Halo2Loader<'a>>>,
+    native: &PlonkProtocol<G1Affine>,
+) -> Result<AssignedCell<Fr, Fr>, plonk::Error> {
+    let digest = native
+        .transcript_initial_state
+        .ok_or(plonk::Error::Synthesis)?;
+    let digest = loader.assign_scalar(Value::known(digest));
+    let preprocessed = native
+        .preprocessed
+        .iter()
+        .map(|point| loader.assign_ec_point(Value::known(*point)))
+        .collect_vec();
+
+    let mut transcript =
+        PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, Value::<&[u8]>::unknown());
+    transcript
+        .common_scalar(&digest)
+        .map_err(|_| plonk::Error::Synthesis)?;
+    for point in preprocessed.iter() {
+        transcript
+            .common_ec_point(point)
+            .map_err(|_| plonk::Error::Synthesis)?;
+    }
+    let hash = transcript.squeeze_challenge().into_assigned();
+
+    protocol.transcript_initial_state = Some(digest);
+    protocol.preprocessed = preprocessed;
+    Ok(hash)
+}
+
+/// Aggregate one or more application snarks, each verified against its own protocol, into a KzgAccumulator.
+///
+/// With `universal`, the verifying key of each snark is a witness rather than baked into the
+/// circuit, and its [vk_hash] is returned so it can be exposed.
 pub fn aggregate<'a>(
     svk: &Svk,
     loader: &Rc<Halo2Loader<'a>>,
     snarks: &[SnarkWitness<Fr, G1Affine>],
     as_proof: Value<&'_ [u8]>,
     split_proofs: bool,
+    universal: bool,
 ) -> Result<AggregationResult<'a>, plonk::Error> {
     let assign_instances = |instances: &[Vec<Value<Fr>>]| {
         instances
@@ -113,10 +213,15 @@ pub fn aggregate<'a>(
 
     let mut accumulators = vec![];
     let mut snark_instances = vec![];
+    let mut vk_hashes = vec![];
     let mut proofs: Vec<LoadedProof<'_>> = vec![];
 
     for snark in snarks.iter() {
-        let protocol = snark.protocol.as_ref().unwrap().loaded(loader);
+        let native = snark.protocol.as_ref().unwrap();
+        let mut protocol = native.loaded(loader);
+        if universal {
+            vk_hashes.push(load_vk(loader, &mut protocol, native)?);
+        }
         let instances = assign_instances(&snark.instances);
 
         // get assigned cells
@@ -173,7 +278,7 @@ pub fn aggregate<'a>(
         let proof = As::read_proof(&Default::default(), &accumulators, &mut transcript).unwrap();
         As::verify(&Default::default(), &accumulators, &proof).map_err(|_| plonk::Error::Synthesis)
     }?;
-    Ok((accumulator, snark_instances))
+    Ok((accumulator, snark_instances, vk_hashes))
 }
 
 /// The Halo2 Config for the aggregation circuit
@@ -226,6 +331,9 @@ pub struct AggregationCircuit {
     instances: Vec<Fr>,
     as_proof: Value<Vec<u8>>,
     split_proof: bool,
+    universal: bool,
+    // the vk hash of each snark, exposed by universal verifiers
+    vk_hashes: Vec<Fr>,
 }
 
 impl AggregationCircuit {
@@ -236,6 +344,9 @@ impl AggregationCircuit {
         split_proof: bool,
     ) -> Result<Self, AggregationError> {
         let snarks = snarks.into_iter().collect_vec();
+        if let Some(idx) = snarks.iter().position(|s| s.protocol.is_none()) {
+            return Err(AggregationError::MissingProtocol(idx));
+        }
 
         let mut accumulators = vec![];
 
@@ -283,9 +394,44 @@ impl AggregationCircuit {
             instances,
             as_proof: Value::known(as_proof),
             split_proof,
+            universal: false,
+            vk_hashes: vec![],
+        })
+    }
+
+    /// Create a universal verifier over `snarks`: the verifying keys of the snarks are witnesses
+    /// rather than constants, and their [vk_hash]es are exposed after the snarks' instances. A
+    /// single verifying key for the circuit then verifies proofs of any model with the same
+    /// circuit shape, e.g the same architecture with different weights, and the exposed hashes
+    /// tell which, see [crate::pfsys::registry].
+    pub fn universal(
+        svk: &KzgSuccinctVerifyingKey<G1Affine>,
+        snarks: impl IntoIterator<Item = Snark<Fr, G1Affine>>,
+    ) -> Result<Self, AggregationError> {
+        let snarks = snarks.into_iter().collect_vec();
+        let vk_hashes = snarks
+            .iter()
+            .enumerate()
+            .map(|(idx, snark)| {
+                let protocol = snark
+                    .protocol
+                    .as_ref()
+                    .ok_or(AggregationError::MissingProtocol(idx))?;
+                vk_hash(protocol).ok_or(AggregationError::MissingVkDigest(idx))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            universal: true,
+            vk_hashes,
+            ..Self::new(svk, snarks, false)?
         })
     }
 
+    /// Whether the circuit is a universal verifier, see [Self::universal]
+    pub fn is_universal(&self) -> bool {
+        self.universal
+    }
+
     ///
     pub fn num_limbs() -> usize {
         LIMBS
@@ -306,6 +452,35 @@ impl AggregationCircuit {
         vec![accumulation_instances + orginal_circuit_instances]
     }
 
+    /// Where the instances of each snark sit among the instances of the aggregation circuit over
+    /// `snarks`, see [Self::instances].
+    pub fn inner_layout(snarks: &[Snark<Fr, G1Affine>]) -> Vec<InnerProofLayout> {
+        let mut offset = 4 * LIMBS;
+        snarks
+            .iter()
+            .map(|snark| {
+                let num_instances = snark.instances.iter().map(|i| i.len()).collect_vec();
+                let layout = InnerProofLayout {
+                    vk_digest: snark
+                        .protocol
+                        .as_ref()
+                        .and_then(|p| p.transcript_initial_state),
+                    offset,
+                    num_instances,
+                };
+                offset = layout.range().end;
+                layout
+            })
+            .collect()
+    }
+
+    /// The vk hashes a universal verifier over snarks laid out as `layout` exposes, out of its
+    /// `instances`, see [Self::universal].
+    pub fn vk_hashes<'a>(layout: &[InnerProofLayout], instances: &'a [Fr]) -> Option<&'a [Fr]> {
+        let offset = layout.last().map_or(4 * LIMBS, |l| l.range().end);
+        instances.get(offset..offset + layout.len())
+    }
+
     /// Instance variables for the aggregation circuit, fed to verifier.
     pub fn instances(&self) -> Vec<Fr> {
         // also get snark instances here
@@ -327,6 +502,8 @@ impl AggregationCircuit {
             }
         }
 
+        instances.extend(self.vk_hashes.iter().copied());
+
         instances
     }
 
@@ -351,6 +528,8 @@ impl Circuit<Fr> for AggregationCircuit {
             instances: Vec::new(),
             as_proof: Value::unknown(),
             split_proof: self.split_proof,
+            universal: self.universal,
+            vk_hashes: self.vk_hashes.clone(),
         }
     }
 
@@ -372,19 +551,20 @@ impl Circuit<Fr> for AggregationCircuit {
 
         range_chip.load_table(&mut layouter)?;
 
-        let (accumulator_limbs, snark_instances) = layouter.assign_region(
+        let (accumulator_limbs, snark_instances, vk_hashes) = layouter.assign_region(
             || "",
             |region| {
                 let ctx = RegionCtx::new(region, 0);
 
                 let ecc_chip = config.ecc_chip();
                 let loader = Halo2Loader::new(ecc_chip, ctx);
-                let (accumulator, snark_instances) = aggregate(
+                let (accumulator, snark_instances, vk_hashes) = aggregate(
                     &self.svk,
                     &loader,
                     &self.snarks,
                     self.as_proof(),
                     self.split_proof,
+                    self.universal,
                 )?;
 
                 let accumulator_limbs = [accumulator.lhs, accumulator.rhs]
@@ -398,7 +578,7 @@ impl Circuit<Fr> for AggregationCircuit {
                     .into_iter()
                     .flatten();
 
-                Ok((accumulator_limbs, snark_instances))
+                Ok((accumulator_limbs, snark_instances, vk_hashes))
             },
         )?;
 
@@ -415,6 +595,62 @@ impl Circuit<Fr> for AggregationCircuit {
             }
         }
 
+        for vk_hash in vk_hashes.into_iter() {
+            main_gate.expose_public(layouter.namespace(|| ""), vk_hash, instance_offset)?;
+            instance_offset += 1;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pfsys::TranscriptType;
+    use halo2curves::group::prime::PrimeCurveAffine;
+
+    #[test]
+    fn test_inner_layout() {
+        let snark = |instances: Vec<Vec<Fr>>| {
+            Snark::new(
+                None,
+                instances,
+                vec![],
+                None,
+                TranscriptType::Poseidon,
+                None,
+                None,
+                None,
+            )
+        };
+        // a snark with two instance columns followed by one with a single column
+        let snarks = vec![
+            snark(vec![vec![Fr::from(1), Fr::from(2)], vec![Fr::from(3)]]),
+            snark(vec![vec![Fr::from(4); 3]]),
+        ];
+        let layout = AggregationCircuit::inner_layout(&snarks);
+        assert_eq!(layout[0].range(), 4 * LIMBS..4 * LIMBS + 3);
+        assert_eq!(layout[1].range(), 4 * LIMBS + 3..4 * LIMBS + 6);
+
+        let mut aggregate = vec![Fr::from(0); 4 * LIMBS];
+        aggregate.extend(snarks.iter().flat_map(|s| s.instances.concat()));
+        let inner = layout[0].instances(&aggregate).unwrap();
+        assert_eq!(
+            inner,
+            vec![&snarks[0].instances[0][..], &snarks[0].instances[1][..]]
+        );
+        assert_eq!(
+            layout[1].instances(&aggregate).unwrap()[0],
+            &snarks[1].instances[0][..]
+        );
+        assert!(layout[1].instances(&aggregate[..4 * LIMBS + 4]).is_none());
+
+        // a universal verifier can't key a snark without a protocol by its vk hash
+        let svk: Svk = G1Affine::generator().into();
+        assert!(matches!(
+            AggregationCircuit::universal(&svk, snarks),
+            Err(AggregationError::MissingProtocol(0))
+        ));
+    }
+}
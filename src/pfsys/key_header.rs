@@ -0,0 +1,210 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// Magic bytes opening key files that carry a [KeyHeader]. Files without them are legacy raw halo2
+/// serializations and are loaded without checks.
+pub const KEY_HEADER_MAGIC: &[u8; 8] = b"ezkl-key";
+/// Version of the header layout, bumped when fields are removed or change meaning.
+pub const KEY_HEADER_VERSION: u32 = 1;
+
+/// Circuit params that can be pinned in the header of key files.
+pub trait KeyParams {
+    /// The logrows of the circuit, if known from the params
+    fn logrows(&self) -> Option<u32>;
+    /// A hash of the params that shape the circuit, if any
+    fn settings_hash(&self) -> Option<String>;
+}
+
+impl KeyParams for () {
+    fn logrows(&self) -> Option<u32> {
+        None
+    }
+
+    fn settings_hash(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Provenance of a proving or verifying key, written ahead of the halo2 serialization.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyHeader {
+    /// The layout version of the header
+    pub header_version: u32,
+    /// The ezkl version the key was generated with
+    pub ezkl_version: String,
+    /// The curve the key is over
+    pub curve: String,
+    /// The logrows of the circuit
+    pub logrows: u32,
+    /// The hash of the circuit settings the key was generated from, see [KeyParams]
+    pub settings_hash: Option<String>,
+}
+
+impl KeyHeader {
+    /// The header of a key over `C` with `2^logrows` rows, generated from `params`.
+    pub fn new<C>(logrows: u32, params: &impl KeyParams) -> Self {
+        Self {
+            header_version: KEY_HEADER_VERSION,
+            ezkl_version: env!("CARGO_PKG_VERSION").to_string(),
+            curve: std::any::type_name::<C>().to_string(),
+            logrows,
+            settings_hash: params.settings_hash(),
+        }
+    }
+
+    /// Writes the magic bytes, the length of the header and the header itself.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let header = serde_json::to_vec(self)?;
+        writer.write_all(KEY_HEADER_MAGIC)?;
+        writer.write_all(&(header.len() as u32).to_le_bytes())?;
+        writer.write_all(&header)
+    }
+
+    /// Reads the header at the start of `reader`, leaving it at the halo2 serialization. Returns
+    /// `None` (without consuming anything) for legacy keys.
+    pub fn read<R: BufRead>(reader: &mut R) -> io::Result<Option<Self>> {
+        if !reader.fill_buf()?.starts_with(KEY_HEADER_MAGIC) {
+            return Ok(None);
+        }
+        reader.consume(KEY_HEADER_MAGIC.len());
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let mut header = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut header)?;
+        Ok(Some(serde_json::from_slice(&header)?))
+    }
+
+    /// Checks a key over `C` with this header can be loaded with `params`. With `force` (`--force`),
+    /// keys generated from different settings load with a warning instead of failing; keys of a
+    /// different size or curve still fail to load.
+    pub fn check<C>(
+        &self,
+        kind: &str,
+        params: &impl KeyParams,
+        force: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let regenerate = "regenerate the keys with `ezkl setup`, or load them with the settings they were generated from";
+        if self.header_version > KEY_HEADER_VERSION {
+            return Err(format!(
+                "the {} was written by a newer ezkl ({}), upgrade ezkl to load it",
+                kind, self.ezkl_version
+            )
+            .into());
+        }
+        let curve = std::any::type_name::<C>();
+        if self.curve != curve {
+            return Err(format!(
+                "the {} is over {} but {} was expected",
+                kind, self.curve, curve
+            )
+            .into());
+        }
+        if let Some(logrows) = params.logrows() {
+            if logrows != self.logrows {
+                return Err(format!(
+                    "the {} was generated for logrows {} but the settings have logrows {}: {}",
+                    kind, self.logrows, logrows, regenerate
+                )
+                .into());
+            }
+        }
+        if let (Some(expected), Some(found)) = (params.settings_hash(), &self.settings_hash) {
+            if &expected != found {
+                let msg = format!(
+                    "the {} was generated from different circuit settings (digest {} but the settings have {})",
+                    kind, found, expected
+                );
+                if !force {
+                    return Err(format!("{}: {}, or pass --force", msg, regenerate).into());
+                }
+                warn!("{}, loading it anyway as --force is set", msg);
+            }
+        }
+        if self.ezkl_version != env!("CARGO_PKG_VERSION") {
+            warn!(
+                "the {} was generated with ezkl {}, this is ezkl {}",
+                kind,
+                self.ezkl_version,
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Reads and checks the header of a key, if it has one, see [KeyHeader::check].
+pub fn read_key_header<C, R: BufRead>(
+    reader: &mut R,
+    kind: &str,
+    params: &impl KeyParams,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    match KeyHeader::read(reader)? {
+        Some(header) => header.check::<C>(kind, params, force),
+        None => {
+            warn!(
+                "the {} has no header, it can't be checked against the settings",
+                kind
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2curves::bn256::G1Affine;
+
+    struct Params(u32, &'static str);
+
+    impl KeyParams for Params {
+        fn logrows(&self) -> Option<u32> {
+            Some(self.0)
+        }
+
+        fn settings_hash(&self) -> Option<String> {
+            Some(self.1.to_string())
+        }
+    }
+
+    #[test]
+    fn test_key_header_roundtrip() {
+        let mut bytes = vec![];
+        KeyHeader::new::<G1Affine>(17, &Params(17, "abc"))
+            .write(&mut bytes)
+            .unwrap();
+        bytes.extend([1, 2, 3]);
+
+        let mut reader = &bytes[..];
+        let header = KeyHeader::read(&mut reader).unwrap().unwrap();
+        // the reader is left at the key itself
+        assert_eq!(reader, &[1, 2, 3]);
+
+        assert!(header
+            .check::<G1Affine>("vk", &Params(17, "abc"), false)
+            .is_ok());
+        assert!(header
+            .check::<G1Affine>("vk", &Params(18, "abc"), false)
+            .is_err());
+        assert!(header
+            .check::<G1Affine>("vk", &Params(17, "def"), false)
+            .is_err());
+        assert!(header.check::<G1Affine>("vk", &(), false).is_ok());
+
+        // forcing loads keys from stale settings, but not keys of another size
+        assert!(header
+            .check::<G1Affine>("vk", &Params(17, "def"), true)
+            .is_ok());
+        assert!(header
+            .check::<G1Affine>("vk", &Params(18, "abc"), true)
+            .is_err());
+
+        // legacy keys are left untouched
+        let mut legacy = &[1u8, 2, 3][..];
+        assert!(KeyHeader::read(&mut legacy).unwrap().is_none());
+        assert_eq!(legacy, &[1, 2, 3]);
+    }
+}
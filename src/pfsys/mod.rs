@@ -1,13 +1,44 @@
+/// Recursive aggregation of many proofs in a tree of bounded fan-in
+pub mod aggregation_tree;
+
+/// Proving many witnesses of the same circuit against a single proving key, and verifying many
+/// proofs against a single verification key
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+
+/// Checkpoints of the stages of setup and proving, to resume them after a crash
+#[cfg(not(target_arch = "wasm32"))]
+pub mod checkpoint;
+
+/// Compact proofs, with compressed points and deduplicated instances
+pub mod compress;
+
 /// EVM related proving and verification
 pub mod evm;
 
+/// Verification of proofs and instances encoded as the Solidity verifier takes them
+pub mod encoded;
+
+/// Versioned headers of proving and verifying key files
+pub mod key_header;
+
+/// Timing and size reports of proofs, for capacity planning
+pub mod profile;
+
+/// The models a universal verifier accepts, keyed by vk hash
+pub mod registry;
+
 /// SRS generation, processing, verification and downloading
 pub mod srs;
 
+/// The KZG accumulator decider of aggregated proofs, kept std independent for embedded targets
+pub mod accumulator_decider;
+
 use crate::circuit::CheckMode;
 use crate::graph::GraphWitness;
 use crate::pfsys::evm::aggregation_kzg::PoseidonTranscript;
-use crate::{Commitments, EZKL_BUF_CAPACITY, EZKL_KEY_FORMAT};
+use crate::pfsys::key_header::{read_key_header, KeyHeader, KeyParams};
+use crate::{Accelerator, Commitments, EZKL_BUF_CAPACITY, EZKL_KEY_FORMAT};
 use clap::ValueEnum;
 use halo2_proofs::circuit::Value;
 use halo2_proofs::plonk::{
@@ -17,16 +48,19 @@ use halo2_proofs::poly::commitment::{CommitmentScheme, Params, ParamsProver, Pro
 use halo2_proofs::poly::ipa::commitment::IPACommitmentScheme;
 use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
 use halo2_proofs::poly::VerificationStrategy;
-use halo2_proofs::transcript::{EncodedChallenge, TranscriptReadBuffer, TranscriptWriterBuffer};
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptReadBuffer,
+    TranscriptWriterBuffer,
+};
 use halo2curves::ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
 use halo2curves::serde::SerdeObject;
 use halo2curves::CurveAffine;
 use instant::Instant;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 #[cfg(not(feature = "det-prove"))]
 use rand::rngs::OsRng;
-#[cfg(feature = "det-prove")]
 use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use snark_verifier::loader::native::NativeLoader;
@@ -177,9 +211,14 @@ pub enum PfSysError {
     ValueEnum, Default, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize, PartialOrd,
 )]
 pub enum TranscriptType {
+    /// Poseidon, cheap to verify in-circuit so used for proofs that get aggregated
     Poseidon,
+    /// Keccak, cheap to verify on-chain
     #[default]
+    #[value(alias = "keccak")]
     EVM,
+    /// Blake2b, fastest to prove and verify natively
+    Blake2b,
 }
 
 impl std::fmt::Display for TranscriptType {
@@ -190,6 +229,7 @@ impl std::fmt::Display for TranscriptType {
             match self {
                 TranscriptType::Poseidon => "poseidon",
                 TranscriptType::EVM => "evm",
+                TranscriptType::Blake2b => "blake2b",
             }
         )
     }
@@ -207,6 +247,7 @@ impl ToPyObject for TranscriptType {
         match self {
             TranscriptType::Poseidon => "Poseidon".to_object(py),
             TranscriptType::EVM => "EVM".to_object(py),
+            TranscriptType::Blake2b => "Blake2b".to_object(py),
         }
     }
 }
@@ -293,6 +334,34 @@ where
     pub timestamp: Option<u128>,
     /// commitment
     pub commitment: Option<Commitments>,
+    /// non fatal issues raised while generating the witness and proof
+    #[serde(default)]
+    pub warnings: Vec<crate::warnings::Warning>,
+    /// the version of the proof format, proofs predating versioning are version 1
+    #[serde(default = "legacy_snark_format")]
+    pub format_version: u32,
+    /// the hash of the circuit settings the proof was generated with, see [KeyParams]
+    #[serde(default)]
+    pub settings_hash: Option<String>,
+    /// how the proof was compressed, if it was, see [Snark::compress]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<compress::Compression<F>>,
+    /// for aggregate proofs, where the instances of each aggregated proof sit among its instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_proofs: Option<Vec<evm::aggregation_kzg::InnerProofLayout>>,
+}
+
+/// The current version of the proof format.
+pub const SNARK_FORMAT_VERSION: u32 = 2;
+
+fn legacy_snark_format() -> u32 {
+    1
+}
+
+/// Whether proofs at `path` are CBOR rather than JSON encoded.
+pub fn is_cbor_path(path: &std::path::Path) -> bool {
+    path.extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("cbor"))
 }
 
 #[cfg(feature = "python-bindings")]
@@ -354,6 +423,11 @@ where
                     .as_millis(),
             ),
             commitment,
+            warnings: vec![],
+            format_version: SNARK_FORMAT_VERSION,
+            settings_hash: None,
+            compression: None,
+            inner_proofs: None,
         }
     }
 
@@ -363,15 +437,22 @@ where
         self.hex_proof = Some(format!("0x{}", hex_proof));
     }
 
-    /// Saves the Proof to a specified `proof_path`.
+    /// Saves the Proof to a specified `proof_path`, as CBOR if it has a `.cbor` extension and as
+    /// JSON otherwise.
     pub fn save(&self, proof_path: &PathBuf) -> Result<(), Box<dyn Error>> {
         let file = std::fs::File::create(proof_path)?;
         let mut writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, file);
-        serde_json::to_writer(&mut writer, &self)?;
+        if is_cbor_path(proof_path) {
+            ciborium::into_writer(&self, &mut writer)?;
+        } else {
+            serde_json::to_writer(&mut writer, &self)?;
+        }
+        writer.flush()?;
         Ok(())
     }
 
-    /// Load a json serialized proof from the provided path.
+    /// Load a JSON or CBOR serialized proof from the provided path. The encoding is detected from
+    /// the contents, so proofs of every format version load regardless of their extension.
     pub fn load<Scheme: CommitmentScheme<Curve = C, Scalar = F>>(
         proof_path: &PathBuf,
     ) -> Result<Self, Box<dyn Error>>
@@ -379,11 +460,45 @@ where
         <C as CurveAffine>::ScalarExt: FromUniformBytes<64>,
     {
         trace!("reading proof");
-        let file = std::fs::File::open(proof_path)?;
-        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
-        let proof: Self = serde_json::from_reader(reader)?;
+        let file = std::fs::File::open(proof_path)
+            .map_err(|e| format!("failed to open proof {}: {}", proof_path.display(), e))?;
+        let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        Self::from_reader(&mut reader)
+    }
+
+    /// Reads a JSON or CBOR serialized proof, decompressing it if it is compressed.
+    pub fn from_reader<R: std::io::BufRead>(reader: &mut R) -> Result<Self, Box<dyn Error>> {
+        // a json proof is an object, whereas a cbor map never starts with whitespace or `{`
+        let is_json = reader
+            .fill_buf()?
+            .iter()
+            .find(|b| !b.is_ascii_whitespace())
+            .map_or(true, |b| *b == b'{');
+        let mut proof: Self = if is_json {
+            serde_json::from_reader(reader)?
+        } else {
+            ciborium::from_reader(reader)?
+        };
+        if proof.format_version > compress::COMPRESSED_SNARK_FORMAT_VERSION {
+            return Err(format!(
+                "proof format version {} is newer than the supported version {}, upgrade ezkl to load it",
+                proof.format_version, compress::COMPRESSED_SNARK_FORMAT_VERSION
+            )
+            .into());
+        }
+        proof.decompress()?;
         Ok(proof)
     }
+
+    /// Checks the proof was generated with `settings`, when both record a settings hash.
+    pub fn check_settings(&self, settings: &impl KeyParams) -> Result<(), Box<dyn Error>> {
+        if let (Some(expected), Some(found)) = (settings.settings_hash(), &self.settings_hash) {
+            if &expected != found {
+                return Err("the proof was generated with different circuit settings than the ones it is being checked against".into());
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -395,12 +510,12 @@ pub struct ProofSplitCommit {
     end: usize,
 }
 
-impl From<GraphWitness> for Option<ProofSplitCommit> {
-    fn from(witness: GraphWitness) -> Self {
+impl From<&GraphWitness> for Option<ProofSplitCommit> {
+    fn from(witness: &GraphWitness) -> Self {
         let mut elem_offset = 0;
 
-        if let Some(input) = witness.processed_inputs {
-            if let Some(polycommit) = input.polycommit {
+        if let Some(input) = &witness.processed_inputs {
+            if let Some(polycommit) = &input.polycommit {
                 // flatten and count number of elements
                 let num_elements = polycommit
                     .iter()
@@ -411,8 +526,8 @@ impl From<GraphWitness> for Option<ProofSplitCommit> {
             }
         }
 
-        if let Some(params) = witness.processed_params {
-            if let Some(polycommit) = params.polycommit {
+        if let Some(params) = &witness.processed_params {
+            if let Some(polycommit) = &params.polycommit {
                 // flatten and count number of elements
                 let num_elements = polycommit
                     .iter()
@@ -423,8 +538,8 @@ impl From<GraphWitness> for Option<ProofSplitCommit> {
             }
         }
 
-        if let Some(output) = witness.processed_outputs {
-            if let Some(polycommit) = output.polycommit {
+        if let Some(output) = &witness.processed_outputs {
+            if let Some(polycommit) = &output.polycommit {
                 // flatten and count number of elements
                 let num_elements = polycommit
                     .iter()
@@ -491,6 +606,30 @@ where
     }
 }
 
+/// Checks that the prover will offload its MSMs and FFTs to `accelerator`. The pinned halo2
+/// prover has no argument to pick its backend: built with the `icicle` feature, it goes to CUDA
+/// whenever the `ENABLE_ICICLE_GPU` environment variable is set, reading it on every MSM and FFT.
+/// Setting the variable from here would race with the threads reading the environment, so this
+/// only fails when the environment doesn't match `accelerator`.
+pub fn check_accelerator(accelerator: Accelerator) -> Result<(), Box<dyn Error>> {
+    let gpu_enabled = cfg!(feature = "icicle") && std::env::var("ENABLE_ICICLE_GPU").is_ok();
+    match accelerator {
+        Accelerator::Cpu if gpu_enabled => {
+            Err("ENABLE_ICICLE_GPU is set, unset it to prove on the cpu".into())
+        }
+        Accelerator::Cuda if !cfg!(feature = "icicle") => {
+            Err("the cuda accelerator requires ezkl to be built with the `icicle` feature".into())
+        }
+        Accelerator::Cuda if !gpu_enabled => {
+            Err("the cuda accelerator requires ENABLE_ICICLE_GPU to be set".into())
+        }
+        _ => {
+            info!("offloading MSMs and FFTs to {}", accelerator);
+            Ok(())
+        }
+    }
+}
+
 /// Creates a [VerifyingKey] and [ProvingKey] for a [crate::graph::GraphCircuit] (`circuit`) with specific [CommitmentScheme] parameters (`params`).
 pub fn create_keys<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
     circuit: &C,
@@ -501,17 +640,43 @@ where
     C: Circuit<Scheme::Scalar>,
     <Scheme as CommitmentScheme>::Scalar: FromUniformBytes<64>,
 {
-    //	Real proof
+    let vk = create_vk::<Scheme, C>(circuit, params, disable_selector_compression)?;
+    create_pk::<Scheme, C>(circuit, params, vk)
+}
+
+/// Generates the verifying key of a circuit, the first stage of [create_keys]
+pub fn create_vk<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
+    circuit: &C,
+    params: &'_ Scheme::ParamsProver,
+    disable_selector_compression: bool,
+) -> Result<VerifyingKey<Scheme::Curve>, halo2_proofs::plonk::Error>
+where
+    C: Circuit<Scheme::Scalar>,
+    <Scheme as CommitmentScheme>::Scalar: FromUniformBytes<64>,
+{
     let empty_circuit = <C as Circuit<Scheme::Scalar>>::without_witnesses(circuit);
 
-    // Initialize verifying key
     let now = Instant::now();
     trace!("preparing VK");
     let vk = keygen_vk_custom(params, &empty_circuit, !disable_selector_compression)?;
     let elapsed = now.elapsed();
     info!("VK took {}.{}", elapsed.as_secs(), elapsed.subsec_millis());
+    Ok(vk)
+}
+
+/// Generates the proving key of a circuit from its verifying key, the second stage of
+/// [create_keys]
+pub fn create_pk<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
+    circuit: &C,
+    params: &'_ Scheme::ParamsProver,
+    vk: VerifyingKey<Scheme::Curve>,
+) -> Result<ProvingKey<Scheme::Curve>, halo2_proofs::plonk::Error>
+where
+    C: Circuit<Scheme::Scalar>,
+    <Scheme as CommitmentScheme>::Scalar: FromUniformBytes<64>,
+{
+    let empty_circuit = <C as Circuit<Scheme::Scalar>>::without_witnesses(circuit);
 
-    // Initialize the proving key
     let now = Instant::now();
     let pk = keygen_pk(params, vk, &empty_circuit)?;
     let elapsed = now.elapsed();
@@ -520,6 +685,10 @@ where
 }
 
 /// a wrapper around halo2's create_proof
+///
+/// `seed` seeds the rng the proof is blinded with, making proofs byte-identical across runs. It is
+/// only meant for test fixtures: anyone knowing the seed can recover the blinding, so seeded proofs
+/// are not zero knowledge.
 #[allow(clippy::too_many_arguments)]
 pub fn create_proof_circuit<
     'params,
@@ -541,6 +710,7 @@ pub fn create_proof_circuit<
     transcript_type: TranscriptType,
     split: Option<ProofSplitCommit>,
     protocol: Option<PlonkProtocol<Scheme::Curve>>,
+    seed: Option<u64>,
 ) -> Result<Snark<Scheme::Scalar, Scheme::Curve>, Box<dyn Error>>
 where
     Scheme::ParamsVerifier: 'params,
@@ -554,10 +724,21 @@ where
 {
     let strategy = Strategy::new(params.verifier_params());
     let mut transcript = TranscriptWriterBuffer::<_, Scheme::Curve, _>::init(vec![]);
+    let mut seeded = seed.map(|seed| {
+        warn!(
+            "proving with the fixed seed {}, the proof is NOT zero knowledge and must only be used for tests",
+            seed
+        );
+        StdRng::seed_from_u64(seed)
+    });
     #[cfg(feature = "det-prove")]
-    let mut rng = <StdRng as rand::SeedableRng>::from_seed([0u8; 32]);
+    let mut unseeded = StdRng::from_seed([0u8; 32]);
     #[cfg(not(feature = "det-prove"))]
-    let mut rng = OsRng;
+    let mut unseeded = OsRng;
+    let rng: &mut (dyn RngCore + Send) = match &mut seeded {
+        Some(rng) => rng,
+        None => &mut unseeded,
+    };
 
     let pi_inner = instances
         .iter()
@@ -571,18 +752,13 @@ where
     );
 
     info!("proof started...");
+    profile::start("proving");
     // not wasm32 unknown
     let now = Instant::now();
 
-    create_proof::<Scheme, P, _, _, TW, _>(
-        params,
-        pk,
-        &[circuit],
-        pi_inner,
-        &mut rng,
-        &mut transcript,
-    )?;
+    create_proof::<Scheme, P, _, _, TW, _>(params, pk, &[circuit], pi_inner, rng, &mut transcript)?;
     let proof = transcript.finalize();
+    profile::record("proving", now.elapsed());
     let hex_proof = format!("0x{}", hex::encode(&proof));
 
     let checkable_pf = Snark::new(
@@ -599,6 +775,8 @@ where
     // sanity check that the generated proof is valid
     if check_mode == CheckMode::SAFE {
         debug!("verifying generated proof");
+        profile::start("verification");
+        let verify_start = Instant::now();
         let verifier_params = params.verifier_params();
         verify_proof_circuit::<V, Scheme, Strategy, E, TR>(
             &checkable_pf,
@@ -607,6 +785,7 @@ where
             strategy,
             verifier_params.n(),
         )?;
+        profile::record("verification", verify_start.elapsed());
     }
     let elapsed = now.elapsed();
     info!(
@@ -618,6 +797,98 @@ where
     Ok(checkable_pf)
 }
 
+/// [create_proof_circuit] for proofs verified on their own, with the transcript hash picked at
+/// runtime. Proofs to be aggregated need a protocol and a poseidon transcript so are created with
+/// [create_proof_circuit] directly.
+#[allow(clippy::too_many_arguments)]
+pub fn create_single_proof<
+    'params,
+    Scheme: CommitmentScheme<Scalar = Fr, Curve = G1Affine>,
+    C: Circuit<Fr>,
+    P: Prover<'params, Scheme>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+>(
+    circuit: C,
+    instances: Vec<Vec<Fr>>,
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<G1Affine>,
+    check_mode: CheckMode,
+    commitment: Commitments,
+    transcript_type: TranscriptType,
+    split: Option<ProofSplitCommit>,
+    seed: Option<u64>,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>>
+where
+    Scheme::ParamsVerifier: 'params,
+{
+    match transcript_type {
+        TranscriptType::EVM => create_proof_circuit::<
+            Scheme,
+            C,
+            P,
+            V,
+            Strategy,
+            _,
+            EvmTranscript<_, _, _, _>,
+            EvmTranscript<_, _, _, _>,
+        >(
+            circuit,
+            instances,
+            params,
+            pk,
+            check_mode,
+            commitment,
+            transcript_type,
+            split,
+            None,
+            seed,
+        ),
+        TranscriptType::Poseidon => create_proof_circuit::<
+            Scheme,
+            C,
+            P,
+            V,
+            Strategy,
+            _,
+            PoseidonTranscript<NativeLoader, _>,
+            PoseidonTranscript<NativeLoader, _>,
+        >(
+            circuit,
+            instances,
+            params,
+            pk,
+            check_mode,
+            commitment,
+            transcript_type,
+            split,
+            None,
+            seed,
+        ),
+        TranscriptType::Blake2b => create_proof_circuit::<
+            Scheme,
+            C,
+            P,
+            V,
+            Strategy,
+            _,
+            Blake2bWrite<_, _, Challenge255<_>>,
+            Blake2bRead<_, _, Challenge255<_>>,
+        >(
+            circuit,
+            instances,
+            params,
+            pk,
+            check_mode,
+            commitment,
+            transcript_type,
+            split,
+            None,
+            seed,
+        ),
+    }
+}
+
 /// Swaps the proof commitments to a new set in the proof
 pub fn swap_proof_commitments<
     Scheme: CommitmentScheme,
@@ -673,6 +944,11 @@ pub fn swap_proof_commitments_polycommit(
                 _,
                 PoseidonTranscript<NativeLoader, _>,
             >(snark, commitments)?,
+            TranscriptType::Blake2b => swap_proof_commitments::<
+                KZGCommitmentScheme<Bn256>,
+                _,
+                Blake2bWrite<_, G1Affine, Challenge255<_>>,
+            >(snark, commitments)?,
         },
         Some(Commitments::IPA) => match snark.transcript_type {
             TranscriptType::EVM => swap_proof_commitments::<
@@ -685,6 +961,11 @@ pub fn swap_proof_commitments_polycommit(
                 _,
                 PoseidonTranscript<NativeLoader, _>,
             >(snark, commitments)?,
+            TranscriptType::Blake2b => swap_proof_commitments::<
+                IPACommitmentScheme<G1Affine>,
+                _,
+                Blake2bWrite<_, G1Affine, Challenge255<_>>,
+            >(snark, commitments)?,
         },
         None => {
             return Err("commitment scheme not found".into());
@@ -730,13 +1011,16 @@ where
     verify_proof::<Scheme, V, _, TR, _>(params, vk, strategy, instances, &mut transcript, orig_n)
 }
 
-/// Loads a [VerifyingKey] at `path`.
+/// Loads a [VerifyingKey] at `path`, checking its header (if any) against `params`. `force` loads keys
+/// generated from different settings with a warning, see [KeyHeader::check].
 pub fn load_vk<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
     path: PathBuf,
     params: <C as Circuit<Scheme::Scalar>>::Params,
+    force: bool,
 ) -> Result<VerifyingKey<Scheme::Curve>, Box<dyn Error>>
 where
     C: Circuit<Scheme::Scalar>,
+    C::Params: KeyParams,
     Scheme::Curve: SerdeObject + CurveAffine,
     Scheme::Scalar: PrimeField + SerdeObject + FromUniformBytes<64>,
 {
@@ -744,6 +1028,7 @@ where
     let f =
         File::open(path.clone()).map_err(|_| format!("failed to load vk at {}", path.display()))?;
     let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
+    read_key_header::<Scheme::Curve, _>(&mut reader, "verification key", &params, force)?;
     let vk = VerifyingKey::<Scheme::Curve>::read::<_, C>(
         &mut reader,
         serde_format_from_str(&EZKL_KEY_FORMAT),
@@ -753,13 +1038,16 @@ where
     Ok(vk)
 }
 
-/// Loads a [ProvingKey] at `path`.
+/// Loads a [ProvingKey] at `path`, checking its header (if any) against `params`. `force` loads keys
+/// generated from different settings with a warning, see [KeyHeader::check].
 pub fn load_pk<Scheme: CommitmentScheme, C: Circuit<Scheme::Scalar>>(
     path: PathBuf,
     params: <C as Circuit<Scheme::Scalar>>::Params,
+    force: bool,
 ) -> Result<ProvingKey<Scheme::Curve>, Box<dyn Error>>
 where
     C: Circuit<Scheme::Scalar>,
+    C::Params: KeyParams,
     Scheme::Curve: SerdeObject + CurveAffine,
     Scheme::Scalar: PrimeField + SerdeObject + FromUniformBytes<64>,
 {
@@ -767,6 +1055,7 @@ where
     let f =
         File::open(path.clone()).map_err(|_| format!("failed to load pk at {}", path.display()))?;
     let mut reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, f);
+    read_key_header::<Scheme::Curve, _>(&mut reader, "proving key", &params, force)?;
     let pk = ProvingKey::<Scheme::Curve>::read::<_, C>(
         &mut reader,
         serde_format_from_str(&EZKL_KEY_FORMAT),
@@ -776,10 +1065,11 @@ where
     Ok(pk)
 }
 
-/// Saves a [ProvingKey] to `path`.
+/// Saves a [ProvingKey] to `path`, behind a [KeyHeader] recording the `params` it was generated from.
 pub fn save_pk<C: SerdeObject + CurveAffine>(
     path: &PathBuf,
     pk: &ProvingKey<C>,
+    params: &impl KeyParams,
 ) -> Result<(), io::Error>
 where
     C::ScalarExt: FromUniformBytes<64> + SerdeObject,
@@ -787,16 +1077,18 @@ where
     info!("saving proving key 💾");
     let f = File::create(path)?;
     let mut writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, f);
+    KeyHeader::new::<C>(pk.get_vk().get_domain().k(), params).write(&mut writer)?;
     pk.write(&mut writer, serde_format_from_str(&EZKL_KEY_FORMAT))?;
     writer.flush()?;
     info!("done saving proving key ✅");
     Ok(())
 }
 
-/// Saves a [VerifyingKey] to `path`.
+/// Saves a [VerifyingKey] to `path`, behind a [KeyHeader] recording the `params` it was generated from.
 pub fn save_vk<C: CurveAffine + SerdeObject>(
     path: &PathBuf,
     vk: &VerifyingKey<C>,
+    params: &impl KeyParams,
 ) -> Result<(), io::Error>
 where
     C::ScalarExt: FromUniformBytes<64> + SerdeObject,
@@ -804,6 +1096,7 @@ where
     info!("saving verification key 💾");
     let f = File::create(path)?;
     let mut writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, f);
+    KeyHeader::new::<C>(vk.get_domain().k(), params).write(&mut writer)?;
     vk.write(&mut writer, serde_format_from_str(&EZKL_KEY_FORMAT))?;
     writer.flush()?;
     info!("done saving verification key ✅");
@@ -857,6 +1150,11 @@ mod tests {
             pretty_public_inputs: None,
             timestamp: None,
             commitment: None,
+            warnings: vec![],
+            format_version: SNARK_FORMAT_VERSION,
+            settings_hash: Some("abc".into()),
+            compression: None,
+            inner_proofs: None,
         };
 
         snark
@@ -869,5 +1167,25 @@ mod tests {
         assert_eq!(snark.instances, snark2.instances);
         assert_eq!(snark.proof, snark2.proof);
         assert_eq!(snark.transcript_type, snark2.transcript_type);
+
+        snark
+            .save(&"test_snark_serialization_roundtrip.cbor".into())
+            .unwrap();
+        let snark3 = Snark::<Fr, G1Affine>::load::<KZGCommitmentScheme<Bn256>>(
+            &"test_snark_serialization_roundtrip.cbor".into(),
+        )
+        .unwrap();
+        assert_eq!(snark.instances, snark3.instances);
+        assert_eq!(snark.proof, snark3.proof);
+        assert_eq!(snark.settings_hash, snark3.settings_hash);
+
+        // proofs predating the format version load as version 1
+        let mut legacy = serde_json::to_value(&snark).unwrap();
+        legacy.as_object_mut().unwrap().remove("format_version");
+        legacy.as_object_mut().unwrap().remove("settings_hash");
+        let legacy = legacy.to_string();
+        let snark4 = Snark::<Fr, G1Affine>::from_reader(&mut legacy.as_bytes()).unwrap();
+        assert_eq!(snark4.format_version, 1);
+        assert_eq!(snark4.settings_hash, None);
     }
 }
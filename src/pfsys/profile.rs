@@ -0,0 +1,247 @@
+//! Timing and size reports of proofs, for capacity planning.
+//!
+//! Each stage of proving [record]s how long it took into the [Profile] [scope]d on its thread, and
+//! a [ProofReport] collects the stages once the proof is done. A profile only sees the stages of
+//! the call that opened it, so concurrent proofs don't mix, and [crate::threads::install] carries
+//! it onto the pool a stage runs on. halo2 synthesizes the circuit (`witness generation`) and
+//! commits to every phase within a single call, so the commitments of the proof are timed
+//! together as `proving`, which includes the synthesis. The polycommit commitments to the inputs,
+//! params and outputs are timed as `commitment` when the witness is generated.
+//!
+//! A [Listener] is notified as each stage starts and finishes whether or not a profile is open, so
+//! embedders can surface the progress of proving.
+
+use super::{Snark, TranscriptType};
+use crate::Commitments;
+use halo2curves::bn256::{Fr, G1Affine};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+static LISTENER: RwLock<Option<Listener>> = RwLock::new(None);
+
+thread_local! {
+    static CURRENT: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Notified of the [Progress] of proving, see [set_listener].
+pub type Listener = Box<dyn Fn(Progress<'_>) + Send + Sync>;
+
+/// A stage of proving starting or finishing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Progress<'a> {
+    /// the stage with this name started
+    Started(&'a str),
+    /// the stage finished
+    Finished(&'a Phase),
+}
+
+/// Sets the listener notified of the progress of proving, replacing any set before. `None`
+/// removes it.
+pub fn set_listener(listener: Option<Listener>) {
+    if let Ok(mut current) = LISTENER.write() {
+        *current = listener;
+    }
+}
+
+fn notify(progress: Progress<'_>) {
+    if let Ok(listener) = LISTENER.read() {
+        if let Some(listener) = listener.as_ref() {
+            listener(progress);
+        }
+    }
+}
+
+/// A stage of proving and how long it took.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Phase {
+    /// the name of the stage
+    pub name: String,
+    /// how long it took, in seconds
+    pub seconds: f64,
+}
+
+/// Where the stages of a [Profile] are recorded, shared by the threads it is [Recorder::enter]ed on.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    phases: Arc<Mutex<Vec<Phase>>>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Records the stages run on this thread into this recorder until the returned profile drops.
+    pub fn enter(&self) -> Profile {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        Profile {
+            recorder: self.clone(),
+            previous,
+        }
+    }
+}
+
+/// The stages recorded on the threads it is entered on, until it drops, see [scope].
+#[derive(Debug)]
+pub struct Profile {
+    recorder: Recorder,
+    previous: Option<Recorder>,
+}
+
+impl Profile {
+    /// The stages recorded so far, in the order they finished.
+    pub fn phases(&self) -> Vec<Phase> {
+        match self.recorder.phases.lock() {
+            Ok(phases) => phases.clone(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// The time since the profile was opened.
+    pub fn elapsed(&self) -> Duration {
+        self.recorder.started.elapsed()
+    }
+}
+
+impl Drop for Profile {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Opens a profile recording the stages run on this thread, until it drops.
+pub fn scope() -> Profile {
+    Recorder {
+        phases: Arc::new(Mutex::new(vec![])),
+        started: Instant::now(),
+    }
+    .enter()
+}
+
+/// The recorder of the profile open on this thread, if any, to [Recorder::enter] on another.
+pub fn current() -> Option<Recorder> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Notifies the listener that the stage `name` started.
+pub fn start(name: &str) {
+    notify(Progress::Started(name));
+}
+
+/// Records that the stage `name` took `elapsed` into the profile open on this thread, if any, and
+/// notifies the listener that it finished.
+pub fn record(name: &str, elapsed: Duration) {
+    let phase = Phase {
+        name: name.to_string(),
+        seconds: elapsed.as_secs_f64(),
+    };
+    notify(Progress::Finished(&phase));
+    if let Some(recorder) = current() {
+        if let Ok(mut phases) = recorder.phases.lock() {
+            phases.push(phase);
+        }
+    }
+}
+
+/// The resources a proof took, written next to the proof by `prove --profile`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofReport {
+    /// the logrows of the circuit
+    pub logrows: u32,
+    /// the number of public instances
+    pub num_instances: usize,
+    /// the size of the proof, in bytes
+    pub proof_size_bytes: usize,
+    /// the stages of proving, in the order they ran
+    pub phases: Vec<Phase>,
+    /// the time taken from the start of the first stage to the report, in seconds. Stages can nest
+    /// (e.g `witness generation` runs within `proving`), so this isn't their sum
+    pub total_seconds: f64,
+    /// the peak resident set size of the process in bytes, where the OS reports it
+    pub peak_rss_bytes: Option<u64>,
+    /// the transcript the proof was generated with
+    pub transcript_type: TranscriptType,
+    /// the commitment scheme the proof was generated with
+    pub commitment: Option<Commitments>,
+}
+
+impl ProofReport {
+    /// The report of `snark`, over a circuit of `logrows`, proven within `profile`.
+    pub fn new(
+        snark: &Snark<Fr, G1Affine>,
+        logrows: u32,
+        profile: &Profile,
+        peak_rss_bytes: Option<u64>,
+    ) -> Self {
+        Self {
+            logrows,
+            num_instances: snark.instances.iter().map(|i| i.len()).sum(),
+            proof_size_bytes: snark.proof.len(),
+            total_seconds: profile.elapsed().as_secs_f64(),
+            phases: profile.phases(),
+            peak_rss_bytes,
+            transcript_type: snark.transcript_type,
+            commitment: snark.commitment,
+        }
+    }
+
+    /// Where the report of the proof at `proof_path` is written, e.g `proof.profile.json` for
+    /// `proof.json`.
+    pub fn path_for(proof_path: &Path) -> PathBuf {
+        proof_path.with_extension("profile.json")
+    }
+
+    /// Saves the report to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_report() {
+        let snark = Snark::new(
+            None,
+            vec![vec![Fr::from(1), Fr::from(2)]],
+            vec![0; 32],
+            None,
+            TranscriptType::EVM,
+            None,
+            None,
+            None,
+        );
+        record("unscoped", Duration::from_secs(1));
+        let profile = scope();
+        record("proving", Duration::from_millis(1500));
+        // stages run on another thread are recorded into the profile they enter, and nothing
+        // recorded outside of it leaks in
+        let recorder = current().unwrap();
+        std::thread::spawn(move || {
+            record("elsewhere", Duration::from_secs(1));
+            let _profile = recorder.enter();
+            record("verification", Duration::from_millis(500));
+        })
+        .join()
+        .unwrap();
+        let report = ProofReport::new(&snark, 17, &profile, None);
+        let names: Vec<_> = report.phases.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["proving", "verification"]);
+        assert!(report.total_seconds < 2.0);
+        assert_eq!((report.num_instances, report.proof_size_bytes), (2, 32));
+
+        drop(profile);
+        assert!(current().is_none());
+        assert_eq!(scope().phases(), vec![]);
+
+        assert_eq!(
+            ProofReport::path_for(Path::new("out/proof.json")),
+            PathBuf::from("out/proof.profile.json")
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! The models a universal verifier accepts, keyed by the [vk_hash] of their verifying keys.
+//!
+//! A universal verifier, see [AggregationCircuit::universal], verifies proofs of any model with
+//! the same circuit shape and exposes the vk hash of each. The hashes on their own say nothing
+//! about which models were proven, so whoever consumes the verifier's proofs keeps a
+//! [VkRegistry] of the models they trust and resolves the hashes against it.
+
+use super::evm::aggregation_kzg::{vk_hash, AggregationCircuit};
+use super::{field_to_string, Snark};
+use crate::EZKL_BUF_CAPACITY;
+use halo2curves::bn256::{Fr, G1Affine};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+/// The registered models, by the hex encoded vk hash of their verifying keys.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VkRegistry {
+    /// The name of each registered model, by vk hash
+    pub models: BTreeMap<String, String>,
+}
+
+impl VkRegistry {
+    /// Registers the model `name` proofs such as `snark` are generated for, returning its vk hash.
+    /// The snark needs its protocol, i.e to be generated with `--proof-type=for-aggr`.
+    pub fn register(
+        &mut self,
+        name: &str,
+        snark: &Snark<Fr, G1Affine>,
+    ) -> Result<Fr, Box<dyn Error>> {
+        let Some(hash) = snark.protocol.as_ref().and_then(vk_hash) else {
+            return Err("the snark has no protocol, generate it with --proof-type=for-aggr".into());
+        };
+        let key = field_to_string(&hash);
+        if let Some(existing) = self.models.get(&key) {
+            if existing != name {
+                return Err(
+                    format!("vk hash {} is already registered as {}", key, existing).into(),
+                );
+            }
+        }
+        self.models.insert(key, name.to_string());
+        Ok(hash)
+    }
+
+    /// The model registered with `vk_hash`, if any
+    pub fn lookup(&self, vk_hash: &Fr) -> Option<&str> {
+        self.models
+            .get(&field_to_string(vk_hash))
+            .map(String::as_str)
+    }
+
+    /// The models proven by a universal verifier's `proof`, in the order they were aggregated.
+    /// Fails if any of them isn't registered.
+    pub fn resolve(&self, proof: &Snark<Fr, G1Affine>) -> Result<Vec<String>, Box<dyn Error>> {
+        let layout = proof
+            .inner_proofs
+            .as_ref()
+            .ok_or("the proof doesn't record the layout of its inner proofs")?;
+        let instances = proof.instances.concat();
+        let hashes = AggregationCircuit::vk_hashes(layout, &instances)
+            .ok_or("the proof doesn't expose the vk hashes of its inner proofs")?;
+        hashes
+            .iter()
+            .map(|hash| {
+                self.lookup(hash).map(str::to_string).ok_or_else(|| {
+                    Box::<dyn Error>::from(format!(
+                        "vk hash {} isn't registered",
+                        field_to_string(hash)
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Load the registry from a .json file
+    pub fn load(path: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open vk registry {}: {}", path.display(), e))?;
+        let reader = BufReader::with_capacity(*EZKL_BUF_CAPACITY, file);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Save the registry to a .json file
+    pub fn save(&self, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::with_capacity(*EZKL_BUF_CAPACITY, std::fs::File::create(path)?);
+        serde_json::to_writer(writer, &self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pfsys::evm::aggregation_kzg::InnerProofLayout;
+    use crate::pfsys::TranscriptType;
+
+    #[test]
+    fn test_resolve() {
+        let mut registry = VkRegistry::default();
+        let hash = Fr::from(7);
+        registry
+            .models
+            .insert(field_to_string(&hash), "model".to_string());
+        assert_eq!(registry.lookup(&hash), Some("model"));
+
+        // an accumulator, one inner proof with two instances, then its vk hash
+        let mut instances = vec![Fr::from(0); 16];
+        instances.extend([Fr::from(1), Fr::from(2), hash]);
+        let mut proof = Snark::new(
+            None,
+            vec![instances],
+            vec![],
+            None,
+            TranscriptType::EVM,
+            None,
+            None,
+            None,
+        );
+        proof.inner_proofs = Some(vec![InnerProofLayout {
+            vk_digest: None,
+            offset: 16,
+            num_instances: vec![2],
+        }]);
+        assert_eq!(registry.resolve(&proof).unwrap(), vec!["model"]);
+
+        proof.instances[0][18] = Fr::from(8);
+        assert!(registry.resolve(&proof).is_err());
+    }
+}
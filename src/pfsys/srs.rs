@@ -1,11 +1,30 @@
+#[cfg(not(target_arch = "wasm32"))]
+use halo2_proofs::arithmetic::g_to_lagrange;
 use halo2_proofs::poly::commitment::CommitmentScheme;
 use halo2_proofs::poly::commitment::Params;
 use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2curves::bn256::Bn256;
+#[cfg(not(target_arch = "wasm32"))]
+use halo2curves::ff::PrimeField;
+#[cfg(not(target_arch = "wasm32"))]
+use halo2curves::serde::SerdeObject;
+#[cfg(not(target_arch = "wasm32"))]
+use halo2curves::CurveAffine;
 use log::info;
+#[cfg(not(target_arch = "wasm32"))]
+use log::warn;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The seed the toxic waste of [gen_unsafe_srs] is drawn from.
+pub const UNSAFE_SRS_SEED: u64 = 0x657a6b6c;
 
 /// for now we use the urls of the powers of tau ceremony from <https://github.com/han0110/halo2-kzg-srs>
 pub const PUBLIC_SRS_URL: &str =
@@ -16,6 +35,43 @@ pub fn gen_srs<Scheme: CommitmentScheme>(k: u32) -> Scheme::ParamsProver {
     Scheme::ParamsProver::new(k)
 }
 
+/// Generates a KZG SRS from toxic waste drawn from [UNSAFE_SRS_SEED], so the same SRS on every
+/// run. As the toxic waste is public anyone can forge proofs against it: only use it for local
+/// development, see [flag_unsafe_srs].
+pub fn gen_unsafe_srs(k: u32) -> ParamsKZG<Bn256> {
+    ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(UNSAFE_SRS_SEED))
+}
+
+/// The file flagging the SRS at `path` as generated by [gen_unsafe_srs].
+fn unsafe_srs_flag(path: &Path) -> PathBuf {
+    let mut flag = path.as_os_str().to_owned();
+    flag.push(".unsafe");
+    PathBuf::from(flag)
+}
+
+/// Flags the SRS at `path` as generated by [gen_unsafe_srs], so it is warned about whenever it is
+/// loaded and never mistaken for a public SRS.
+pub fn flag_unsafe_srs(path: &Path) -> Result<(), Box<dyn Error>> {
+    std::fs::write(
+        unsafe_srs_flag(path),
+        "generated by `ezkl gen-srs --unsafe` from publicly known toxic waste, for local development only\n",
+    )?;
+    Ok(())
+}
+
+/// Removes the flag of an SRS at `path`, if any, see [flag_unsafe_srs].
+pub fn clear_unsafe_srs_flag(path: &Path) -> Result<(), Box<dyn Error>> {
+    match std::fs::remove_file(unsafe_srs_flag(path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the SRS at `path` was generated by [gen_unsafe_srs].
+pub fn is_unsafe_srs(path: &Path) -> bool {
+    unsafe_srs_flag(path).exists()
+}
+
 /// Loads the [CommitmentScheme::ParamsVerifier] at `path`.
 pub fn load_srs_verifier<Scheme: CommitmentScheme>(
     path: PathBuf,
@@ -37,3 +93,158 @@ pub fn load_srs_prover<Scheme: CommitmentScheme>(
     let mut reader = BufReader::new(f);
     Params::<'_, Scheme::Curve>::read(&mut reader).map_err(Box::<dyn Error>::from)
 }
+
+/// Size in bytes of the `k` header of a serialized SRS.
+#[cfg(not(target_arch = "wasm32"))]
+const SRS_HEADER_BYTES: usize = 4;
+
+/// Size in bytes of a raw serialized curve point.
+#[cfg(not(target_arch = "wasm32"))]
+fn raw_point_bytes<C: CurveAffine>() -> usize {
+    2 * <C::Base as PrimeField>::Repr::default().as_ref().len()
+}
+
+/// Memory maps the SRS at `path` and reads it trimmed to `2^k` rows.
+///
+/// A serialized SRS is laid out as `k`, the `2^k` powers `g`, their lagrange basis `g_lagrange`
+/// and a fixed size tail (`g2, s_g2` for KZG, `w, u` for IPA). When the file holds more rows than
+/// needed only the first `2^k` powers and the tail are paged in and the lagrange basis is
+/// recomputed for the smaller domain, so the rest of the file is never materialized.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_trimmed<'params, C, P>(path: &PathBuf, k: u32) -> Result<P, Box<dyn Error>>
+where
+    C: CurveAffine + SerdeObject,
+    P: Params<'params, C>,
+{
+    info!("mapping srs from {:?}", path);
+    if is_unsafe_srs(path) {
+        warn!(
+            "the srs at {} was generated with gen-srs --unsafe, anyone can forge proofs against it: only use it for local development",
+            path.display()
+        );
+    }
+    let f = File::open(path).map_err(|_| format!("failed to load srs at {}", path.display()))?;
+    // safety: the mapping is only read, and srs files are not expected to change under us
+    #[allow(unsafe_code)]
+    let mmap = unsafe { memmap2::Mmap::map(&f)? };
+
+    let header: [u8; SRS_HEADER_BYTES] = mmap
+        .get(..SRS_HEADER_BYTES)
+        .and_then(|h| h.try_into().ok())
+        .ok_or(format!("srs at {} is empty", path.display()))?;
+    let file_k = u32::from_le_bytes(header);
+    if k >= file_k {
+        return Ok(P::read(&mut &mmap[..])?);
+    }
+
+    let point_bytes = raw_point_bytes::<C>();
+    let powers_end = SRS_HEADER_BYTES + (1usize << k) * point_bytes;
+    let tail_start = SRS_HEADER_BYTES + 2 * (1usize << file_k) * point_bytes;
+    if mmap.len() < tail_start {
+        return Err(format!(
+            "srs at {} is truncated: expected at least {} bytes for logrows {}, got {}",
+            path.display(),
+            tail_start,
+            file_k,
+            mmap.len()
+        )
+        .into());
+    }
+    info!("trimming srs from {} to {} logrows", file_k, k);
+
+    let powers = &mmap[SRS_HEADER_BYTES..powers_end];
+    // points are checked when the params are read below
+    let g: Vec<C::Curve> = powers
+        .chunks_exact(point_bytes)
+        .map(|mut p| C::read_raw_unchecked(&mut p).to_curve())
+        .collect();
+    let mut lagrange = Vec::with_capacity(powers.len());
+    for p in g_to_lagrange::<C>(g, k) {
+        p.write_raw(&mut lagrange)?;
+    }
+
+    let k_bytes = k.to_le_bytes();
+    let mut reader = (&k_bytes[..])
+        .chain(powers)
+        .chain(&lagrange[..])
+        .chain(&mmap[tail_start..]);
+    Ok(P::read(&mut reader)?)
+}
+
+/// Loads the [CommitmentScheme::ParamsProver] at `path` with `2^k` rows, only reading the powers
+/// needed when the file holds a larger SRS.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_srs_prover_trimmed<Scheme: CommitmentScheme>(
+    path: PathBuf,
+    k: u32,
+) -> Result<Scheme::ParamsProver, Box<dyn Error>>
+where
+    Scheme::Curve: SerdeObject,
+{
+    read_trimmed::<Scheme::Curve, Scheme::ParamsProver>(&path, k)
+}
+
+/// Loads the [CommitmentScheme::ParamsVerifier] at `path` with `2^k` rows, only reading the powers
+/// needed when the file holds a larger SRS.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_srs_verifier_trimmed<Scheme: CommitmentScheme>(
+    path: PathBuf,
+    k: u32,
+) -> Result<Scheme::ParamsVerifier, Box<dyn Error>>
+where
+    Scheme::Curve: SerdeObject,
+{
+    read_trimmed::<Scheme::Curve, Scheme::ParamsVerifier>(&path, k)
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::pfsys::save_params;
+    use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+    use tempfile::Builder;
+
+    #[test]
+    fn test_trimmed_srs_matches_downsize() {
+        let tmp_dir = Builder::new().prefix("srs").tempdir().unwrap();
+        let fname = tmp_dir.path().join("kzg5.srs");
+        let srs = gen_srs::<KZGCommitmentScheme<Bn256>>(5);
+        save_params::<KZGCommitmentScheme<Bn256>>(&fname, &srs).unwrap();
+
+        let trimmed: ParamsKZG<Bn256> =
+            load_srs_prover_trimmed::<KZGCommitmentScheme<Bn256>>(fname.clone(), 3).unwrap();
+        let mut downsized = srs.clone();
+        downsized.downsize(3);
+
+        let serialize = |p: &ParamsKZG<Bn256>| {
+            let mut buf = vec![];
+            p.write(&mut buf).unwrap();
+            buf
+        };
+        assert_eq!(trimmed.k(), 3);
+        assert_eq!(serialize(&trimmed), serialize(&downsized));
+
+        // asking for as many rows as the file holds reads it whole
+        let full: ParamsKZG<Bn256> =
+            load_srs_prover_trimmed::<KZGCommitmentScheme<Bn256>>(fname, 5).unwrap();
+        assert_eq!(serialize(&full), serialize(&srs));
+    }
+
+    #[test]
+    fn test_unsafe_srs() {
+        let tmp_dir = Builder::new().prefix("srs").tempdir().unwrap();
+        let fname = tmp_dir.path().join("kzg3.srs");
+
+        let srs = gen_unsafe_srs(3);
+        assert_eq!(srs.s_g2(), gen_unsafe_srs(3).s_g2());
+        save_params::<KZGCommitmentScheme<Bn256>>(&fname, &srs).unwrap();
+        assert!(!is_unsafe_srs(&fname));
+
+        flag_unsafe_srs(&fname).unwrap();
+        assert!(is_unsafe_srs(&fname));
+        clear_unsafe_srs_flag(&fname).unwrap();
+        clear_unsafe_srs_flag(&fname).unwrap();
+        assert!(!is_unsafe_srs(&fname));
+    }
+}
@@ -0,0 +1,443 @@
+//! Pipelines taking a model from its .onnx file to a verified proof, see `ezkl run`.
+//!
+//! A [PipelineConfig] names the model, its data and the arguments of each stage, and the stages
+//! write their artifacts to the work directory of the pipeline. The [PipelineCache] records the
+//! fingerprint of each stage that ran, from its arguments and the hashes of the files it read, so
+//! that a stage whose fingerprint hasn't changed and whose outputs are still there is skipped.
+//! Keys and SRS are too large to hash on every run, and are fingerprinted by their size and
+//! modification time instead.
+//! As the files a stage reads are the outputs of the stages before it, changing the model reruns
+//! every stage while changing the data only reruns calibration and those after it.
+
+use crate::commands::CalibrationTarget;
+use crate::RunArgs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// The name of the file the cache of a pipeline is saved to, in its work directory.
+pub const CACHE_FILE: &str = "cache.json";
+
+/// The arguments of the calibration stage, as those of `ezkl calibrate-settings`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalibrationConfig {
+    /// what to calibrate for, `resources`, `resources/col-overflow` or `accuracy`
+    pub target: String,
+    /// the factor the range of lookups is widened by
+    pub lookup_safety_margin: i128,
+    /// the scales to try, all the default ones if unset
+    pub scales: Option<Vec<crate::Scale>>,
+    /// the scale rebase multipliers to try
+    pub scale_rebase_multiplier: Vec<u32>,
+    /// the largest logrows to calibrate to
+    pub max_logrows: Option<u32>,
+    /// whether to only range check rebases
+    pub only_range_check_rebase: bool,
+    /// whether to correct the quantized biases of conv and dense layers
+    pub bias_correction: bool,
+    /// the max number of domains to give lookup tables
+    pub max_lookup_domains: usize,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            target: "resources".into(),
+            lookup_safety_margin: 2,
+            scales: None,
+            scale_rebase_multiplier: vec![1, 2, 10],
+            max_logrows: None,
+            only_range_check_rebase: false,
+            bias_correction: false,
+            max_lookup_domains: 1,
+        }
+    }
+}
+
+impl CalibrationConfig {
+    /// The target to calibrate for.
+    pub fn target(&self) -> Result<CalibrationTarget, Box<dyn Error>> {
+        match self.target.as_str() {
+            "resources" | "resources/col-overflow" | "accuracy" => Ok(self.target.as_str().into()),
+            target => Err(format!("unknown calibration target {}", target).into()),
+        }
+    }
+}
+
+fn default_work_dir() -> PathBuf {
+    PathBuf::from("pipeline")
+}
+
+/// The config of `ezkl run`, a .toml or .json file. Relative paths are relative to the file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// the .onnx model
+    pub model: PathBuf,
+    /// the .json input data, which calibration also runs on
+    pub data: PathBuf,
+    /// the directory the artifacts and the cache are written to
+    #[serde(default = "default_work_dir")]
+    pub work_dir: PathBuf,
+    /// the SRS, the default one for the logrows of the circuit if unset
+    #[serde(default)]
+    pub srs_path: Option<PathBuf>,
+    /// the run args settings are generated with, the defaults of `ezkl gen-settings` for those
+    /// left out
+    #[serde(default)]
+    pub run_args: serde_json::Map<String, Value>,
+    /// the arguments of calibration, which is skipped if unset
+    #[serde(default)]
+    pub calibration: Option<CalibrationConfig>,
+}
+
+impl PipelineConfig {
+    /// Load the config from a .toml file, or from a .json file for any other extension.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read pipeline {}: {}", path.display(), e))?;
+        let mut config: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&contents)?,
+            _ => serde_json::from_str(&contents)?,
+        };
+        if let Some(dir) = path.parent() {
+            config.resolve(dir);
+        }
+        Ok(config)
+    }
+
+    /// Makes the relative paths of the config relative to `dir`.
+    fn resolve(&mut self, dir: &Path) {
+        for path in [&mut self.model, &mut self.data, &mut self.work_dir]
+            .into_iter()
+            .chain(self.srs_path.as_mut())
+        {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+    }
+
+    /// The run args settings are generated with.
+    pub fn run_args(&self) -> Result<RunArgs, Box<dyn Error>> {
+        let mut run_args = serde_json::to_value(RunArgs::default())?;
+        if let Value::Object(defaults) = &mut run_args {
+            defaults.extend(self.run_args.clone());
+        }
+        let run_args: RunArgs = serde_json::from_value(run_args)?;
+        run_args.validate()?;
+        Ok(run_args)
+    }
+
+    /// The settings generated from the model, before calibration.
+    pub fn generated_settings_path(&self) -> PathBuf {
+        self.work_dir.join("settings.json")
+    }
+
+    /// The settings the circuit is compiled with, calibrated if calibration is configured.
+    pub fn settings_path(&self) -> PathBuf {
+        match self.calibration {
+            Some(_) => self.work_dir.join("settings.calibrated.json"),
+            None => self.generated_settings_path(),
+        }
+    }
+
+    /// The compiled circuit.
+    pub fn compiled_circuit_path(&self) -> PathBuf {
+        self.work_dir.join("model.compiled")
+    }
+
+    /// The verifying key.
+    pub fn vk_path(&self) -> PathBuf {
+        self.work_dir.join("vk.key")
+    }
+
+    /// The proving key.
+    pub fn pk_path(&self) -> PathBuf {
+        self.work_dir.join("pk.key")
+    }
+
+    /// The witness of the data.
+    pub fn witness_path(&self) -> PathBuf {
+        self.work_dir.join("witness.json")
+    }
+
+    /// The proof of the witness.
+    pub fn proof_path(&self) -> PathBuf {
+        self.work_dir.join("proof.json")
+    }
+
+    /// The cache of the fingerprints of the stages that ran.
+    pub fn cache_path(&self) -> PathBuf {
+        self.work_dir.join(CACHE_FILE)
+    }
+
+    /// The stages of the pipeline, in the order they run. Verification isn't one of them, as it
+    /// has no artifacts to cache and always runs.
+    pub fn stages(&self) -> Result<Vec<Stage>, Box<dyn Error>> {
+        let mut stages = vec![Stage {
+            kind: StageKind::GenSettings,
+            inputs: vec![self.model.clone()],
+            large_inputs: vec![],
+            args: serde_json::to_value(self.run_args()?)?,
+            outputs: vec![self.generated_settings_path()],
+        }];
+        if let Some(calibration) = &self.calibration {
+            stages.push(Stage {
+                kind: StageKind::Calibrate,
+                inputs: vec![
+                    self.model.clone(),
+                    self.data.clone(),
+                    self.generated_settings_path(),
+                ],
+                large_inputs: vec![],
+                args: serde_json::to_value(calibration)?,
+                outputs: vec![self.settings_path()],
+            });
+        }
+        stages.extend([
+            Stage {
+                kind: StageKind::Compile,
+                inputs: vec![self.model.clone(), self.settings_path()],
+                large_inputs: vec![],
+                args: Value::Null,
+                outputs: vec![self.compiled_circuit_path()],
+            },
+            Stage {
+                kind: StageKind::Setup,
+                inputs: vec![self.compiled_circuit_path()],
+                // without an srs_path, the default SRS is only known once the settings are written,
+                // see [Stage::with_default_srs]
+                large_inputs: self.srs_path.clone().into_iter().collect(),
+                args: Value::Null,
+                outputs: vec![self.vk_path(), self.pk_path()],
+            },
+            Stage {
+                kind: StageKind::Witness,
+                inputs: vec![
+                    self.compiled_circuit_path(),
+                    self.data.clone(),
+                    self.vk_path(),
+                ],
+                large_inputs: vec![],
+                args: Value::Null,
+                outputs: vec![self.witness_path()],
+            },
+            Stage {
+                kind: StageKind::Prove,
+                inputs: vec![self.compiled_circuit_path(), self.witness_path()],
+                large_inputs: vec![self.pk_path()],
+                args: Value::Null,
+                outputs: vec![self.proof_path()],
+            },
+        ]);
+        Ok(stages)
+    }
+}
+
+/// A stage of a pipeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageKind {
+    /// `ezkl gen-settings`
+    GenSettings,
+    /// `ezkl calibrate-settings`
+    Calibrate,
+    /// `ezkl compile-circuit`
+    Compile,
+    /// `ezkl setup`
+    Setup,
+    /// `ezkl gen-witness`
+    Witness,
+    /// `ezkl prove`
+    Prove,
+    /// `ezkl verify`
+    Verify,
+}
+
+impl std::fmt::Display for StageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                StageKind::GenSettings => "gen-settings",
+                StageKind::Calibrate => "calibrate",
+                StageKind::Compile => "compile",
+                StageKind::Setup => "setup",
+                StageKind::Witness => "witness",
+                StageKind::Prove => "prove",
+                StageKind::Verify => "verify",
+            }
+        )
+    }
+}
+
+/// A stage along with the files it reads and writes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stage {
+    /// which stage this is
+    pub kind: StageKind,
+    /// the files the stage reads
+    pub inputs: Vec<PathBuf>,
+    /// the files the stage reads that are too large to hash, e.g keys and SRS
+    pub large_inputs: Vec<PathBuf>,
+    /// the arguments of the stage that aren't files
+    pub args: Value,
+    /// the files the stage writes
+    pub outputs: Vec<PathBuf>,
+}
+
+impl Stage {
+    /// The SHA256 hash of the stage, its arguments, the contents of its inputs, the size and
+    /// modification time of its large inputs and the version of ezkl, which is the same as long as
+    /// the stage would write the same outputs, see [crate::pfsys::checkpoint::fingerprint].
+    pub fn fingerprint(&self) -> Result<String, Box<dyn Error>> {
+        let inputs = self.inputs.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+        let large_inputs = self
+            .large_inputs
+            .iter()
+            .map(PathBuf::as_path)
+            .collect::<Vec<_>>();
+        crate::pfsys::checkpoint::fingerprint(
+            &inputs,
+            &large_inputs,
+            serde_json::json!({
+                "stage": self.kind,
+                "args": self.args,
+            }),
+        )
+    }
+
+    /// Adds the SRS the setup reads when the pipeline has no srs_path, that of the logrows and
+    /// commitment of `settings`, to the large inputs of the setup stage.
+    pub fn with_default_srs(mut self, settings: &crate::graph::GraphSettings) -> Self {
+        if self.kind == StageKind::Setup && self.large_inputs.is_empty() {
+            let run_args = &settings.run_args;
+            let srs = crate::execute::get_srs_path(run_args.logrows, None, run_args.commitment);
+            // an SRS that is yet to be fetched doesn't have a size, and the setup reruns once it is
+            self.large_inputs
+                .extend(Some(srs).filter(|srs| srs.exists()));
+        }
+        self
+    }
+}
+
+/// The fingerprints of the stages of a pipeline that last ran successfully.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineCache {
+    /// the fingerprint of each stage, see [Stage::fingerprint]
+    pub stages: BTreeMap<StageKind, String>,
+}
+
+impl PipelineCache {
+    /// Load the cache from a .json file, empty if there is none yet.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    /// Save the cache to a .json file
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether `stage` last ran with the same `fingerprint` and its outputs are still there.
+    pub fn is_fresh(&self, stage: &Stage, fingerprint: &str) -> bool {
+        self.stages.get(&stage.kind).map(String::as_str) == Some(fingerprint)
+            && stage.outputs.iter().all(|path| path.is_file())
+    }
+
+    /// Records that `stage` ran with `fingerprint`.
+    pub fn record(&mut self, stage: &Stage, fingerprint: String) {
+        self.stages.insert(stage.kind, fingerprint);
+    }
+
+    /// Forgets `stage`, so that it runs again if it fails midway.
+    pub fn forget(&mut self, stage: &Stage) {
+        self.stages.remove(&stage.kind);
+    }
+}
+
+/// How a stage of a pipeline went.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StageOutcome {
+    /// the stage
+    pub stage: StageKind,
+    /// whether the stage was skipped, its outputs being up to date
+    pub cached: bool,
+    /// how long the stage took, in seconds
+    pub seconds: f64,
+}
+
+/// The report of `ezkl run`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipelineReport {
+    /// the stages, in the order they ran
+    pub stages: Vec<StageOutcome>,
+    /// the proof of the data
+    pub proof_path: PathBuf,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("pipeline.toml");
+        std::fs::write(
+            &config_path,
+            "model = \"network.onnx\"\ndata = \"input.json\"\n\n[run_args]\nlogrows = 12\n\n[calibration]\ntarget = \"accuracy\"\n",
+        )
+        .unwrap();
+        let config = PipelineConfig::load(&config_path).unwrap();
+        assert_eq!(config.model, dir.path().join("network.onnx"));
+        assert_eq!(config.work_dir, dir.path().join("pipeline"));
+        assert_eq!(config.run_args().unwrap().logrows, 12);
+        assert_eq!(config.run_args().unwrap().param_scale, 7);
+        let calibration = config.calibration.clone().unwrap();
+        assert_eq!(calibration.target().unwrap(), CalibrationTarget::Accuracy);
+        assert_eq!(calibration.lookup_safety_margin, 2);
+
+        let stages = config.stages().unwrap();
+        assert_eq!(stages.len(), 6);
+        assert_eq!(stages[2].inputs[1], config.settings_path());
+        assert_eq!(stages[5].kind, StageKind::Prove);
+        assert_eq!(stages[5].large_inputs, vec![config.pk_path()]);
+
+        std::fs::write(&config.model, "model").unwrap();
+        std::fs::create_dir_all(&config.work_dir).unwrap();
+        let stage = &stages[0];
+        let fingerprint = stage.fingerprint().unwrap();
+        let mut cache = PipelineCache::default();
+        assert!(!cache.is_fresh(stage, &fingerprint));
+        cache.record(stage, fingerprint.clone());
+        // the settings haven't been written yet
+        assert!(!cache.is_fresh(stage, &fingerprint));
+        std::fs::write(config.generated_settings_path(), "{}").unwrap();
+        assert!(cache.is_fresh(stage, &fingerprint));
+
+        cache.save(&config.cache_path()).unwrap();
+        let cache = PipelineCache::load(&config.cache_path()).unwrap();
+        std::fs::write(&config.model, "another model").unwrap();
+        assert!(!cache.is_fresh(stage, &stage.fingerprint().unwrap()));
+
+        // large inputs are fingerprinted without being read, by their size
+        let prove = &stages[5];
+        std::fs::write(config.compiled_circuit_path(), "circuit").unwrap();
+        std::fs::write(config.witness_path(), "witness").unwrap();
+        std::fs::write(config.pk_path(), "pk").unwrap();
+        let fingerprint = prove.fingerprint().unwrap();
+        std::fs::write(config.pk_path(), "another pk").unwrap();
+        assert_ne!(prove.fingerprint().unwrap(), fingerprint);
+    }
+}
@@ -1,28 +1,25 @@
 use crate::circuit::modules::polycommit::PolyCommitChip;
-use crate::circuit::modules::poseidon::{
-    spec::{PoseidonSpec, POSEIDON_RATE, POSEIDON_WIDTH},
-    PoseidonChip,
-};
-use crate::circuit::modules::Module;
 use crate::circuit::{CheckMode, Tolerance};
 use crate::commands::*;
 use crate::fieldutils::{felt_to_i128, i128_to_felt};
 use crate::graph::modules::POSEIDON_LEN_GRAPH;
 use crate::graph::TestDataSource;
 use crate::graph::{
-    quantize_float, scale_to_multiplier, GraphCircuit, GraphSettings, Model, Visibility,
+    quantize_float, scale_to_multiplier, GraphCircuit, GraphSettings, Model, OutputDestination,
+    Visibility,
 };
 use crate::pfsys::evm::aggregation_kzg::AggregationCircuit;
 use crate::pfsys::{
     load_pk, load_vk, save_params, save_vk, srs::gen_srs as ezkl_gen_srs, srs::load_srs_prover,
     ProofType, TranscriptType,
 };
-use crate::Commitments;
+use crate::tensor::AssignmentOrder;
 use crate::RunArgs;
+use crate::{Accelerator, Commitments};
 use halo2_proofs::poly::ipa::commitment::IPACommitmentScheme;
 use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
 use halo2curves::bn256::{Bn256, Fq, Fr, G1Affine, G1};
-use pyo3::exceptions::{PyIOError, PyRuntimeError};
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
 use pyo3_log;
@@ -163,11 +160,35 @@ struct PyRunArgs {
     #[pyo3(get, set)]
     pub div_rebasing: bool,
     #[pyo3(get, set)]
+    pub shift_rebasing: bool,
+    #[pyo3(get, set)]
     pub rebase_frac_zero_constants: bool,
     #[pyo3(get, set)]
     pub check_mode: CheckMode,
     #[pyo3(get, set)]
     pub commitment: PyCommitments,
+    #[pyo3(get, set)]
+    pub output_names: Vec<String>,
+    #[pyo3(get, set)]
+    pub num_threads: Option<usize>,
+    #[pyo3(get, set)]
+    pub assignment_order: AssignmentOrder,
+    #[pyo3(get, set)]
+    pub bind_caller: bool,
+    #[pyo3(get, set)]
+    pub fuse_attention: bool,
+    #[pyo3(get, set)]
+    pub mask_output: Option<usize>,
+    #[pyo3(get, set)]
+    pub expose_model_hash: bool,
+    #[pyo3(get, set)]
+    pub shuffle_permutations: bool,
+    #[pyo3(get, set)]
+    pub hash_chunk_size: usize,
+    #[pyo3(get, set)]
+    pub output_destinations: Vec<OutputDestination>,
+    #[pyo3(get, set)]
+    pub seal_outputs: bool,
 }
 
 /// default instantiation of PyRunArgs
@@ -195,9 +216,21 @@ impl From<PyRunArgs> for RunArgs {
             param_visibility: py_run_args.param_visibility,
             variables: py_run_args.variables,
             div_rebasing: py_run_args.div_rebasing,
+            shift_rebasing: py_run_args.shift_rebasing,
             rebase_frac_zero_constants: py_run_args.rebase_frac_zero_constants,
             check_mode: py_run_args.check_mode,
             commitment: py_run_args.commitment.into(),
+            output_names: py_run_args.output_names,
+            num_threads: py_run_args.num_threads,
+            assignment_order: py_run_args.assignment_order,
+            bind_caller: py_run_args.bind_caller,
+            fuse_attention: py_run_args.fuse_attention,
+            mask_output: py_run_args.mask_output,
+            expose_model_hash: py_run_args.expose_model_hash,
+            shuffle_permutations: py_run_args.shuffle_permutations,
+            hash_chunk_size: py_run_args.hash_chunk_size,
+            output_destinations: py_run_args.output_destinations,
+            seal_outputs: py_run_args.seal_outputs,
         }
     }
 }
@@ -217,9 +250,40 @@ impl Into<PyRunArgs> for RunArgs {
             param_visibility: self.param_visibility,
             variables: self.variables,
             div_rebasing: self.div_rebasing,
+            shift_rebasing: self.shift_rebasing,
             rebase_frac_zero_constants: self.rebase_frac_zero_constants,
             check_mode: self.check_mode,
             commitment: self.commitment.into(),
+            output_names: self.output_names,
+            num_threads: self.num_threads,
+            assignment_order: self.assignment_order,
+            bind_caller: self.bind_caller,
+            fuse_attention: self.fuse_attention,
+            mask_output: self.mask_output,
+            expose_model_hash: self.expose_model_hash,
+            shuffle_permutations: self.shuffle_permutations,
+            hash_chunk_size: self.hash_chunk_size,
+            output_destinations: self.output_destinations,
+            seal_outputs: self.seal_outputs,
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Debug, Clone)]
+/// Pyclass marking the hardware the prover offloads to
+pub enum PyAccelerator {
+    /// Multithreaded CPU
+    Cpu,
+    /// NVIDIA GPUs
+    Cuda,
+}
+
+impl From<PyAccelerator> for Accelerator {
+    fn from(py_accelerator: PyAccelerator) -> Self {
+        match py_accelerator {
+            PyAccelerator::Cpu => Accelerator::Cpu,
+            PyAccelerator::Cuda => Accelerator::Cuda,
         }
     }
 }
@@ -371,17 +435,15 @@ fn buffer_to_felts(buffer: Vec<u8>) -> PyResult<Vec<String>> {
 /// Generate a poseidon hash.
 #[pyfunction(signature = (
     message,
+    chunk_size=POSEIDON_LEN_GRAPH,
     ))]
-fn poseidon_hash(message: Vec<PyFelt>) -> PyResult<Vec<PyFelt>> {
+fn poseidon_hash(message: Vec<PyFelt>, chunk_size: usize) -> PyResult<Vec<PyFelt>> {
     let message: Vec<Fr> = message
         .iter()
         .map(crate::pfsys::string_to_field::<Fr>)
         .collect::<Vec<_>>();
 
-    let output =
-        PoseidonChip::<PoseidonSpec, POSEIDON_WIDTH, POSEIDON_RATE, POSEIDON_LEN_GRAPH>::run(
-            message.clone(),
-        )
+    let output = crate::graph::modules::poseidon_hash(message.clone(), chunk_size)
         .map_err(|_| PyIOError::new_err("Failed to run poseidon"))?;
 
     let hash = output[0]
@@ -418,7 +480,7 @@ fn kzg_commit(
     let srs = load_srs_prover::<KZGCommitmentScheme<Bn256>>(srs_path)
         .map_err(|_| PyIOError::new_err("Failed to load srs"))?;
 
-    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, settings)
+    let vk = load_vk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(vk_path, settings, false)
         .map_err(|_| PyIOError::new_err("Failed to load vk"))?;
 
     let output = PolyCommitChip::commit::<KZGCommitmentScheme<Bn256>>(
@@ -458,7 +520,7 @@ fn ipa_commit(
     let srs = load_srs_prover::<IPACommitmentScheme<G1Affine>>(srs_path)
         .map_err(|_| PyIOError::new_err("Failed to load srs"))?;
 
-    let vk = load_vk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(vk_path, settings)
+    let vk = load_vk::<IPACommitmentScheme<G1Affine>, GraphCircuit>(vk_path, settings, false)
         .map_err(|_| PyIOError::new_err("Failed to load vk"))?;
 
     let output = PolyCommitChip::commit::<IPACommitmentScheme<G1Affine>>(
@@ -483,6 +545,23 @@ fn swap_proof_commitments(proof_path: PathBuf, witness_path: PathBuf) -> PyResul
     Ok(())
 }
 
+/// Compresses a proof, or decompresses it if `decompress` is set
+#[pyfunction(signature = (
+    proof_path=PathBuf::from(DEFAULT_PROOF),
+    output_path=None,
+    decompress=DEFAULT_DECOMPRESS.parse::<bool>().unwrap(),
+    ))]
+fn compress_proof(
+    proof_path: PathBuf,
+    output_path: Option<PathBuf>,
+    decompress: bool,
+) -> PyResult<()> {
+    crate::execute::compress_proof(proof_path, output_path, decompress)
+        .map_err(|e| PyIOError::new_err(format!("Failed to compress proof: {}", e)))?;
+
+    Ok(())
+}
+
 /// Generates a vk from a pk for a model circuit and saves it to a file
 #[pyfunction(signature = (
     path_to_pk=PathBuf::from(DEFAULT_PK),
@@ -497,13 +576,14 @@ fn gen_vk_from_pk_single(
     let settings = GraphSettings::load(&circuit_settings_path)
         .map_err(|_| PyIOError::new_err("Failed to load circuit settings"))?;
 
-    let pk = load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(path_to_pk, settings)
-        .map_err(|_| PyIOError::new_err("Failed to load pk"))?;
+    let pk =
+        load_pk::<KZGCommitmentScheme<Bn256>, GraphCircuit>(path_to_pk, settings.clone(), false)
+            .map_err(|_| PyIOError::new_err("Failed to load pk"))?;
 
     let vk = pk.get_vk();
 
     // now save
-    save_vk::<G1Affine>(&vk_output_path, vk)
+    save_vk::<G1Affine>(&vk_output_path, vk, &settings)
         .map_err(|_| PyIOError::new_err("Failed to save vk"))?;
 
     Ok(true)
@@ -515,13 +595,13 @@ fn gen_vk_from_pk_single(
     vk_output_path=PathBuf::from(DEFAULT_VK_AGGREGATED),
     ))]
 fn gen_vk_from_pk_aggr(path_to_pk: PathBuf, vk_output_path: PathBuf) -> PyResult<bool> {
-    let pk = load_pk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(path_to_pk, ())
+    let pk = load_pk::<KZGCommitmentScheme<Bn256>, AggregationCircuit>(path_to_pk, (), false)
         .map_err(|_| PyIOError::new_err("Failed to load pk"))?;
 
     let vk = pk.get_vk();
 
     // now save
-    save_vk::<G1Affine>(&vk_output_path, vk)
+    save_vk::<G1Affine>(&vk_output_path, vk, &())
         .map_err(|_| PyIOError::new_err("Failed to save vk"))?;
 
     Ok(true)
@@ -619,6 +699,9 @@ fn gen_settings(
     scale_rebase_multiplier = DEFAULT_SCALE_REBASE_MULTIPLIERS.split(",").map(|x| x.parse().unwrap()).collect(),
     max_logrows = None,
     only_range_check_rebase = DEFAULT_ONLY_RANGE_CHECK_REBASE.parse().unwrap(),
+    dataset_spec = None,
+    bias_correction = DEFAULT_BIAS_CORRECTION.parse().unwrap(),
+    max_lookup_domains = DEFAULT_MAX_LOOKUP_DOMAINS.parse().unwrap(),
 ))]
 fn calibrate_settings(
     data: PathBuf,
@@ -630,6 +713,9 @@ fn calibrate_settings(
     scale_rebase_multiplier: Vec<u32>,
     max_logrows: Option<u32>,
     only_range_check_rebase: bool,
+    dataset_spec: Option<PathBuf>,
+    bias_correction: bool,
+    max_lookup_domains: usize,
 ) -> Result<bool, PyErr> {
     crate::execute::calibrate(
         model,
@@ -641,6 +727,9 @@ fn calibrate_settings(
         scale_rebase_multiplier,
         only_range_check_rebase,
         max_logrows,
+        dataset_spec,
+        bias_correction,
+        max_lookup_domains,
     )
     .map_err(|e| {
         let err_str = format!("Failed to calibrate settings: {}", e);
@@ -657,6 +746,11 @@ fn calibrate_settings(
     output=PathBuf::from(DEFAULT_WITNESS),
     vk_path=None,
     srs_path=None,
+    encryption_key=None,
+    seal_outputs_to=None,
+    caller=None,
+    user_op=None,
+    full_assignment=None,
 ))]
 fn gen_witness(
     data: PathBuf,
@@ -664,11 +758,29 @@ fn gen_witness(
     output: Option<PathBuf>,
     vk_path: Option<PathBuf>,
     srs_path: Option<PathBuf>,
+    encryption_key: Option<PathBuf>,
+    seal_outputs_to: Option<PathBuf>,
+    caller: Option<&str>,
+    user_op: Option<&str>,
+    full_assignment: Option<PathBuf>,
 ) -> PyResult<PyObject> {
+    let user_op = user_op
+        .map(UserOpFlag::from_str)
+        .transpose()
+        .map_err(PyValueError::new_err)?;
     let output = Runtime::new()
         .unwrap()
         .block_on(crate::execute::gen_witness(
-            model, data, output, vk_path, srs_path,
+            model,
+            data,
+            output,
+            vk_path,
+            srs_path,
+            encryption_key,
+            seal_outputs_to,
+            caller.map(H160Flag::from),
+            user_op,
+            full_assignment,
         ))
         .map_err(|e| {
             let err_str = format!("Failed to run generate witness: {}", e);
@@ -681,9 +793,10 @@ fn gen_witness(
 #[pyfunction(signature = (
     witness=PathBuf::from(DEFAULT_WITNESS),
     model=PathBuf::from(DEFAULT_MODEL),
+    encryption_key=None,
 ))]
-fn mock(witness: PathBuf, model: PathBuf) -> PyResult<bool> {
-    crate::execute::mock(model, witness).map_err(|e| {
+fn mock(witness: PathBuf, model: PathBuf, encryption_key: Option<PathBuf>) -> PyResult<bool> {
+    crate::execute::mock(model, witness, encryption_key).map_err(|e| {
         let err_str = format!("Failed to run mock: {}", e);
         PyRuntimeError::new_err(err_str)
     })?;
@@ -695,16 +808,20 @@ fn mock(witness: PathBuf, model: PathBuf) -> PyResult<bool> {
     aggregation_snarks=vec![PathBuf::from(DEFAULT_PROOF)],
     logrows=DEFAULT_AGGREGATED_LOGROWS.parse().unwrap(),
     split_proofs = false,
+    universal = DEFAULT_UNIVERSAL.parse().unwrap(),
 ))]
 fn mock_aggregate(
     aggregation_snarks: Vec<PathBuf>,
     logrows: u32,
     split_proofs: bool,
+    universal: bool,
 ) -> PyResult<bool> {
-    crate::execute::mock_aggregate(aggregation_snarks, logrows, split_proofs).map_err(|e| {
-        let err_str = format!("Failed to run mock: {}", e);
-        PyRuntimeError::new_err(err_str)
-    })?;
+    crate::execute::mock_aggregate(aggregation_snarks, logrows, split_proofs, universal).map_err(
+        |e| {
+            let err_str = format!("Failed to run mock: {}", e);
+            PyRuntimeError::new_err(err_str)
+        },
+    )?;
 
     Ok(true)
 }
@@ -717,6 +834,10 @@ fn mock_aggregate(
     srs_path=None,
     witness_path = None,
     disable_selector_compression=DEFAULT_DISABLE_SELECTOR_COMPRESSION.parse().unwrap(),
+    encryption_key=None,
+    commitment=None,
+    checkpoint_dir=None,
+    resume=DEFAULT_RESUME.parse::<bool>().unwrap(),
 ))]
 fn setup(
     model: PathBuf,
@@ -725,6 +846,10 @@ fn setup(
     srs_path: Option<PathBuf>,
     witness_path: Option<PathBuf>,
     disable_selector_compression: bool,
+    encryption_key: Option<PathBuf>,
+    commitment: Option<PyCommitments>,
+    checkpoint_dir: Option<PathBuf>,
+    resume: bool,
 ) -> Result<bool, PyErr> {
     crate::execute::setup(
         model,
@@ -733,6 +858,10 @@ fn setup(
         pk_path,
         witness_path,
         disable_selector_compression,
+        encryption_key,
+        commitment.map(|c| c.into()),
+        checkpoint_dir,
+        resume,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run setup: {}", e);
@@ -750,6 +879,19 @@ fn setup(
     proof_path=None,
     proof_type=ProofType::default(),
     srs_path=None,
+    encryption_key=None,
+    commitment=None,
+    transcript=None,
+    accelerator=None,
+    from_assignment=None,
+    scrub=DEFAULT_SCRUB.parse::<bool>().unwrap(),
+    seed=None,
+    trim_memory=DEFAULT_TRIM_MEMORY.parse::<bool>().unwrap(),
+    profile=DEFAULT_PROFILE.parse::<bool>().unwrap(),
+    shadow_model=None,
+    force=DEFAULT_FORCE.parse::<bool>().unwrap(),
+    checkpoint_dir=None,
+    resume=DEFAULT_RESUME.parse::<bool>().unwrap(),
 ))]
 fn prove(
     witness: PathBuf,
@@ -758,6 +900,19 @@ fn prove(
     proof_path: Option<PathBuf>,
     proof_type: ProofType,
     srs_path: Option<PathBuf>,
+    encryption_key: Option<PathBuf>,
+    commitment: Option<PyCommitments>,
+    transcript: Option<TranscriptType>,
+    accelerator: Option<PyAccelerator>,
+    from_assignment: Option<PathBuf>,
+    scrub: bool,
+    seed: Option<u64>,
+    trim_memory: bool,
+    profile: bool,
+    shadow_model: Option<PathBuf>,
+    force: bool,
+    checkpoint_dir: Option<PathBuf>,
+    resume: bool,
 ) -> PyResult<PyObject> {
     let snark = crate::execute::prove(
         witness,
@@ -767,6 +922,19 @@ fn prove(
         srs_path,
         proof_type,
         CheckMode::UNSAFE,
+        encryption_key,
+        commitment.map(|c| c.into()),
+        transcript,
+        accelerator.map(|a| a.into()),
+        from_assignment,
+        scrub,
+        seed,
+        trim_memory,
+        profile,
+        shadow_model,
+        checkpoint_dir,
+        resume,
+        force,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run prove: {}", e);
@@ -783,6 +951,8 @@ fn prove(
     vk_path=PathBuf::from(DEFAULT_VK),
     srs_path=None,
     non_reduced_srs=DEFAULT_USE_REDUCED_SRS_FOR_VERIFICATION.parse::<bool>().unwrap(),
+    commitment=None,
+    force=DEFAULT_FORCE.parse::<bool>().unwrap(),
 ))]
 fn verify(
     proof_path: PathBuf,
@@ -790,6 +960,8 @@ fn verify(
     vk_path: PathBuf,
     srs_path: Option<PathBuf>,
     non_reduced_srs: bool,
+    commitment: Option<PyCommitments>,
+    force: bool,
 ) -> Result<bool, PyErr> {
     crate::execute::verify(
         proof_path,
@@ -797,6 +969,8 @@ fn verify(
         vk_path,
         srs_path,
         non_reduced_srs,
+        commitment.map(|c| c.into()),
+        force,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run verify: {}", e);
@@ -815,6 +989,7 @@ fn verify(
     srs_path = None,
     disable_selector_compression=DEFAULT_DISABLE_SELECTOR_COMPRESSION.parse().unwrap(),
     commitment=DEFAULT_COMMITMENT.parse().unwrap(),
+    universal = DEFAULT_UNIVERSAL.parse().unwrap(),
 ))]
 fn setup_aggregate(
     sample_snarks: Vec<PathBuf>,
@@ -825,6 +1000,7 @@ fn setup_aggregate(
     srs_path: Option<PathBuf>,
     disable_selector_compression: bool,
     commitment: PyCommitments,
+    universal: bool,
 ) -> Result<bool, PyErr> {
     crate::execute::setup_aggregate(
         sample_snarks,
@@ -833,6 +1009,7 @@ fn setup_aggregate(
         srs_path,
         logrows,
         split_proofs,
+        universal,
         disable_selector_compression,
         commitment.into(),
     )
@@ -848,16 +1025,19 @@ fn setup_aggregate(
     model=PathBuf::from(DEFAULT_MODEL),
     compiled_circuit=PathBuf::from(DEFAULT_COMPILED_CIRCUIT),
     settings_path=PathBuf::from(DEFAULT_SETTINGS),
+    encryption_key=None,
 ))]
 fn compile_circuit(
     model: PathBuf,
     compiled_circuit: PathBuf,
     settings_path: PathBuf,
+    encryption_key: Option<PathBuf>,
 ) -> Result<bool, PyErr> {
-    crate::execute::compile_circuit(model, compiled_circuit, settings_path).map_err(|e| {
-        let err_str = format!("Failed to setup aggregate: {}", e);
-        PyRuntimeError::new_err(err_str)
-    })?;
+    crate::execute::compile_circuit(model, compiled_circuit, settings_path, encryption_key)
+        .map_err(|e| {
+            let err_str = format!("Failed to setup aggregate: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
 
     Ok(true)
 }
@@ -873,6 +1053,7 @@ fn compile_circuit(
     split_proofs = false,
     srs_path=None,
     commitment=DEFAULT_COMMITMENT.parse().unwrap(),
+    universal = DEFAULT_UNIVERSAL.parse().unwrap(),
 ))]
 fn aggregate(
     aggregation_snarks: Vec<PathBuf>,
@@ -884,6 +1065,7 @@ fn aggregate(
     split_proofs: bool,
     srs_path: Option<PathBuf>,
     commitment: PyCommitments,
+    universal: bool,
 ) -> Result<bool, PyErr> {
     // the K used for the aggregation circuit
     crate::execute::aggregate(
@@ -895,6 +1077,7 @@ fn aggregate(
         logrows,
         check_mode,
         split_proofs,
+        universal,
         commitment.into(),
     )
     .map_err(|e| {
@@ -946,6 +1129,8 @@ fn verify_aggr(
     abi_path=PathBuf::from(DEFAULT_VERIFIER_ABI),
     srs_path=None,
     render_vk_seperately = DEFAULT_RENDER_VK_SEPERATELY.parse().unwrap(),
+    commitments_path=None,
+    target_chain=DEFAULT_TARGET_CHAIN,
 ))]
 fn create_evm_verifier(
     vk_path: PathBuf,
@@ -954,6 +1139,8 @@ fn create_evm_verifier(
     abi_path: PathBuf,
     srs_path: Option<PathBuf>,
     render_vk_seperately: bool,
+    commitments_path: Option<PathBuf>,
+    target_chain: &str,
 ) -> Result<bool, PyErr> {
     crate::execute::create_evm_verifier(
         vk_path,
@@ -962,6 +1149,8 @@ fn create_evm_verifier(
         sol_code_path,
         abi_path,
         render_vk_seperately,
+        commitments_path,
+        target_chain.parse().map_err(PyValueError::new_err)?,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run create_evm_verifier: {}", e);
@@ -993,6 +1182,25 @@ fn create_evm_data_attestation(
     Ok(true)
 }
 
+// creates a Solidity library quantizing on-chain fixed point values as ezkl does at a given scale
+#[pyfunction(signature = (
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_QUANTIZER),
+    scale=7,
+    bits=128,
+))]
+fn create_evm_quantizer(
+    sol_code_path: PathBuf,
+    scale: crate::Scale,
+    bits: u32,
+) -> Result<bool, PyErr> {
+    crate::execute::create_evm_quantizer(sol_code_path, scale, bits).map_err(|e| {
+        let err_str = format!("Failed to run create_evm_quantizer: {}", e);
+        PyRuntimeError::new_err(err_str)
+    })?;
+
+    Ok(true)
+}
+
 #[pyfunction(signature = (
     data_path,
     compiled_circuit_path,
@@ -1033,6 +1241,7 @@ fn setup_test_evm_witness(
     rpc_url=None,
     optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
     private_key=None,
+    create2_vk_path=None,
 ))]
 fn deploy_evm(
     addr_path: PathBuf,
@@ -1040,6 +1249,7 @@ fn deploy_evm(
     rpc_url: Option<String>,
     optimizer_runs: usize,
     private_key: Option<String>,
+    create2_vk_path: Option<PathBuf>,
 ) -> Result<bool, PyErr> {
     Runtime::new()
         .unwrap()
@@ -1050,6 +1260,7 @@ fn deploy_evm(
             optimizer_runs,
             private_key,
             "Halo2Verifier",
+            create2_vk_path,
         ))
         .map_err(|e| {
             let err_str = format!("Failed to run deploy_evm: {}", e);
@@ -1065,6 +1276,7 @@ fn deploy_evm(
     rpc_url=None,
     optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
     private_key=None,
+    create2_vk_path=None,
 ))]
 fn deploy_vk_evm(
     addr_path: PathBuf,
@@ -1072,6 +1284,7 @@ fn deploy_vk_evm(
     rpc_url: Option<String>,
     optimizer_runs: usize,
     private_key: Option<String>,
+    create2_vk_path: Option<PathBuf>,
 ) -> Result<bool, PyErr> {
     Runtime::new()
         .unwrap()
@@ -1082,6 +1295,7 @@ fn deploy_vk_evm(
             optimizer_runs,
             private_key,
             "Halo2VerifyingKey",
+            create2_vk_path,
         ))
         .map_err(|e| {
             let err_str = format!("Failed to run deploy_evm: {}", e);
@@ -1091,6 +1305,26 @@ fn deploy_vk_evm(
     Ok(true)
 }
 
+/// predicts the address deploy_evm or deploy_vk_evm deploy a contract to with create2_vk_path
+#[pyfunction(signature = (
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE),
+    vk_path=PathBuf::from(DEFAULT_VK),
+    optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
+    contract_name=DEFAULT_VERIFIER_CONTRACT_NAME.to_string(),
+))]
+fn predict_evm_address(
+    sol_code_path: PathBuf,
+    vk_path: PathBuf,
+    optimizer_runs: usize,
+    contract_name: String,
+) -> PyResult<String> {
+    crate::execute::predict_evm_address(sol_code_path, vk_path, optimizer_runs, &contract_name)
+        .map_err(|e| {
+            let err_str = format!("Failed to run predict_evm_address: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })
+}
+
 #[pyfunction(signature = (
     addr_path,
     input_data,
@@ -1127,6 +1361,264 @@ fn deploy_da_evm(
 
     Ok(true)
 }
+// creates an ERC-4337 paymaster sponsoring user operations carrying a valid proof, you will need solc installed in your environment to run this
+#[pyfunction(signature = (
+    settings_path=PathBuf::from(DEFAULT_SETTINGS),
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_PAYMASTER),
+    abi_path=PathBuf::from(DEFAULT_PAYMASTER_ABI),
+    verify_gas_limit=DEFAULT_PAYMASTER_VERIFY_GAS_LIMIT.parse().unwrap(),
+))]
+fn create_evm_paymaster(
+    settings_path: PathBuf,
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+    verify_gas_limit: u64,
+) -> Result<bool, PyErr> {
+    crate::execute::create_evm_paymaster(settings_path, sol_code_path, abi_path, verify_gas_limit)
+        .map_err(|e| {
+            let err_str = format!("Failed to run create_evm_paymaster: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    Ok(true)
+}
+
+#[pyfunction(signature = (
+    addr_verifier,
+    addr_path,
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_PAYMASTER),
+    entry_point=DEFAULT_ENTRY_POINT,
+    rpc_url=None,
+    optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
+    private_key=None,
+))]
+fn deploy_evm_paymaster(
+    addr_verifier: &str,
+    addr_path: PathBuf,
+    sol_code_path: PathBuf,
+    entry_point: &str,
+    rpc_url: Option<String>,
+    optimizer_runs: usize,
+    private_key: Option<String>,
+) -> Result<bool, PyErr> {
+    Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::deploy_evm_paymaster(
+            sol_code_path,
+            H160Flag::from(addr_verifier),
+            H160Flag::from(entry_point),
+            rpc_url,
+            addr_path,
+            optimizer_runs,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run deploy_evm_paymaster: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    Ok(true)
+}
+
+// creates a contract recording proofs posted as EIP-4844 blobs, you will need solc installed in your environment to run this
+#[pyfunction(signature = (
+    settings_path=PathBuf::from(DEFAULT_SETTINGS),
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_BLOB),
+    abi_path=PathBuf::from(DEFAULT_VERIFIER_BLOB_ABI),
+))]
+fn create_evm_blob_verifier(
+    settings_path: PathBuf,
+    sol_code_path: PathBuf,
+    abi_path: PathBuf,
+) -> Result<bool, PyErr> {
+    crate::execute::create_evm_blob_verifier(settings_path, sol_code_path, abi_path).map_err(
+        |e| {
+            let err_str = format!("Failed to run create_evm_blob_verifier: {}", e);
+            PyRuntimeError::new_err(err_str)
+        },
+    )?;
+
+    Ok(true)
+}
+
+#[pyfunction(signature = (
+    addr_verifier,
+    addr_path,
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_BLOB),
+    rpc_url=None,
+    optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
+    private_key=None,
+))]
+fn deploy_evm_blob_verifier(
+    addr_verifier: &str,
+    addr_path: PathBuf,
+    sol_code_path: PathBuf,
+    rpc_url: Option<String>,
+    optimizer_runs: usize,
+    private_key: Option<String>,
+) -> Result<bool, PyErr> {
+    Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::deploy_evm_blob_verifier(
+            sol_code_path,
+            H160Flag::from(addr_verifier),
+            rpc_url,
+            addr_path,
+            optimizer_runs,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run deploy_evm_blob_verifier: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    Ok(true)
+}
+
+// posts a proof as an EIP-4844 blob to a blob verifier, returning the versioned hash of the blob
+#[pyfunction(signature = (
+    addr_verifier,
+    proof_path=PathBuf::from(DEFAULT_PROOF),
+    trusted_setup_path=PathBuf::from(DEFAULT_TRUSTED_SETUP),
+    rpc_url=None,
+    private_key=None,
+))]
+fn post_proof_blob(
+    addr_verifier: &str,
+    proof_path: PathBuf,
+    trusted_setup_path: PathBuf,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<String, PyErr> {
+    Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::post_proof_blob(
+            proof_path,
+            H160Flag::from(addr_verifier),
+            trusted_setup_path,
+            rpc_url,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run post_proof_blob: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })
+}
+
+// creates a verifier registry whose address stays the same across model updates, you will need solc installed in your environment to run this
+#[pyfunction(signature = (
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_REGISTRY),
+    abi_path=PathBuf::from(DEFAULT_VERIFIER_REGISTRY_ABI),
+))]
+fn create_evm_registry(sol_code_path: PathBuf, abi_path: PathBuf) -> Result<bool, PyErr> {
+    crate::execute::create_evm_registry(sol_code_path, abi_path).map_err(|e| {
+        let err_str = format!("Failed to run create_evm_registry: {}", e);
+        PyRuntimeError::new_err(err_str)
+    })?;
+
+    Ok(true)
+}
+
+#[pyfunction(signature = (
+    addr_verifier,
+    addr_vk,
+    addr_path,
+    sol_code_path=PathBuf::from(DEFAULT_SOL_CODE_REGISTRY),
+    rpc_url=None,
+    optimizer_runs=DEFAULT_OPTIMIZER_RUNS.parse().unwrap(),
+    private_key=None,
+))]
+fn deploy_evm_registry(
+    addr_verifier: &str,
+    addr_vk: &str,
+    addr_path: PathBuf,
+    sol_code_path: PathBuf,
+    rpc_url: Option<String>,
+    optimizer_runs: usize,
+    private_key: Option<String>,
+) -> Result<bool, PyErr> {
+    Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::deploy_evm_registry(
+            sol_code_path,
+            H160Flag::from(addr_verifier),
+            H160Flag::from(addr_vk),
+            rpc_url,
+            addr_path,
+            optimizer_runs,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run deploy_evm_registry: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    Ok(true)
+}
+
+/// points a verifier registry at a new verifying key, returning the version proofs are now verified against
+#[pyfunction(signature = (
+    addr_registry,
+    addr_vk,
+    addr_verifier=None,
+    rpc_url=None,
+    private_key=None,
+))]
+fn update_evm_vk(
+    addr_registry: &str,
+    addr_vk: &str,
+    addr_verifier: Option<&str>,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<u64, PyErr> {
+    let version = Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::update_evm_vk(
+            H160Flag::from(addr_registry),
+            H160Flag::from(addr_vk),
+            addr_verifier.map(H160Flag::from),
+            rpc_url,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run update_evm_vk: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    version
+        .parse()
+        .map_err(|e| PyRuntimeError::new_err(format!("Failed to run update_evm_vk: {}", e)))
+}
+
+/// revokes a past version of a verifier registry, so proofs no longer verify against its verifying key
+#[pyfunction(signature = (
+    addr_registry,
+    version,
+    rpc_url=None,
+    private_key=None,
+))]
+fn revoke_evm_vk(
+    addr_registry: &str,
+    version: u64,
+    rpc_url: Option<String>,
+    private_key: Option<String>,
+) -> Result<bool, PyErr> {
+    Runtime::new()
+        .unwrap()
+        .block_on(crate::execute::revoke_evm_vk(
+            H160Flag::from(addr_registry),
+            version,
+            rpc_url,
+            private_key,
+        ))
+        .map_err(|e| {
+            let err_str = format!("Failed to run revoke_evm_vk: {}", e);
+            PyRuntimeError::new_err(err_str)
+        })?;
+
+    Ok(true)
+}
+
 /// verifies an evm compatible proof, you will need solc installed in your environment to run this
 #[pyfunction(signature = (
     addr_verifier,
@@ -1182,6 +1674,7 @@ fn verify_evm(
     logrows=DEFAULT_AGGREGATED_LOGROWS.parse().unwrap(),
     srs_path=None,
     render_vk_seperately = DEFAULT_RENDER_VK_SEPERATELY.parse().unwrap(),
+    forward_instances = DEFAULT_FORWARD_INSTANCES.parse().unwrap(),
 ))]
 fn create_evm_verifier_aggr(
     aggregation_settings: Vec<PathBuf>,
@@ -1191,6 +1684,7 @@ fn create_evm_verifier_aggr(
     logrows: u32,
     srs_path: Option<PathBuf>,
     render_vk_seperately: bool,
+    forward_instances: bool,
 ) -> Result<bool, PyErr> {
     crate::execute::create_evm_aggregate_verifier(
         vk_path,
@@ -1200,6 +1694,7 @@ fn create_evm_verifier_aggr(
         aggregation_settings,
         logrows,
         render_vk_seperately,
+        forward_instances,
     )
     .map_err(|e| {
         let err_str = format!("Failed to run create_evm_verifier_aggr: {}", e);
@@ -1217,6 +1712,7 @@ fn ezkl(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyG1>()?;
     m.add_class::<PyTestDataSource>()?;
     m.add_class::<PyCommitments>()?;
+    m.add_class::<PyAccelerator>()?;
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     m.add_function(wrap_pyfunction!(felt_to_big_endian, m)?)?;
     m.add_function(wrap_pyfunction!(felt_to_int, m)?)?;
@@ -1224,6 +1720,7 @@ fn ezkl(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(kzg_commit, m)?)?;
     m.add_function(wrap_pyfunction!(ipa_commit, m)?)?;
     m.add_function(wrap_pyfunction!(swap_proof_commitments, m)?)?;
+    m.add_function(wrap_pyfunction!(compress_proof, m)?)?;
     m.add_function(wrap_pyfunction!(poseidon_hash, m)?)?;
     m.add_function(wrap_pyfunction!(float_to_felt, m)?)?;
     m.add_function(wrap_pyfunction!(buffer_to_felts, m)?)?;
@@ -1247,11 +1744,22 @@ fn ezkl(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(create_evm_verifier, m)?)?;
     m.add_function(wrap_pyfunction!(deploy_evm, m)?)?;
     m.add_function(wrap_pyfunction!(deploy_vk_evm, m)?)?;
+    m.add_function(wrap_pyfunction!(predict_evm_address, m)?)?;
     m.add_function(wrap_pyfunction!(deploy_da_evm, m)?)?;
     m.add_function(wrap_pyfunction!(verify_evm, m)?)?;
     m.add_function(wrap_pyfunction!(setup_test_evm_witness, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_verifier_aggr, m)?)?;
     m.add_function(wrap_pyfunction!(create_evm_data_attestation, m)?)?;
+    m.add_function(wrap_pyfunction!(create_evm_quantizer, m)?)?;
+    m.add_function(wrap_pyfunction!(create_evm_paymaster, m)?)?;
+    m.add_function(wrap_pyfunction!(deploy_evm_paymaster, m)?)?;
+    m.add_function(wrap_pyfunction!(create_evm_blob_verifier, m)?)?;
+    m.add_function(wrap_pyfunction!(deploy_evm_blob_verifier, m)?)?;
+    m.add_function(wrap_pyfunction!(post_proof_blob, m)?)?;
+    m.add_function(wrap_pyfunction!(create_evm_registry, m)?)?;
+    m.add_function(wrap_pyfunction!(deploy_evm_registry, m)?)?;
+    m.add_function(wrap_pyfunction!(update_evm_vk, m)?)?;
+    m.add_function(wrap_pyfunction!(revoke_evm_vk, m)?)?;
 
     Ok(())
 }
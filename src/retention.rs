@@ -0,0 +1,253 @@
+//! Retention of the artifacts of proving service jobs.
+//!
+//! Each job keeps its witnesses, proofs and logs in its own directory under the service's jobs
+//! directory, next to a [JOB_RECORD] describing it. Finished jobs are collected once they are
+//! older than the [RetentionPolicy] allows, or, oldest first, while the jobs directory is larger
+//! than it allows. Queued and running jobs are never collected. [purge] removes every finished
+//! job of a model or tenant at once, as the service's admin endpoint does.
+
+use crate::api::{JobState, JobStatus, PurgeReport, PurgeRequest};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The file in a job's directory describing the job.
+pub const JOB_RECORD: &str = "job.json";
+
+/// What a job's directory records about it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    /// The status of the job
+    pub status: JobStatus,
+    /// The model the job ran against
+    pub model: Option<String>,
+    /// The tenant the job was submitted by
+    pub tenant: Option<String>,
+    /// When the job finished, in seconds since the unix epoch
+    pub finished_at: Option<u64>,
+}
+
+impl JobRecord {
+    /// Whether the job finished, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.state, JobState::Done | JobState::Failed)
+    }
+}
+
+/// How long the artifacts of finished jobs are kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Finished jobs older than this many seconds are collected
+    pub max_age: Option<u64>,
+    /// Finished jobs are collected, oldest first, while the jobs directory is larger than this many bytes
+    pub max_bytes: Option<u64>,
+}
+
+/// A finished job found in the jobs directory.
+#[derive(Clone, Debug)]
+struct FinishedJob {
+    dir: PathBuf,
+    record: JobRecord,
+    finished_at: u64,
+    bytes: u64,
+}
+
+/// The size of the files under `path`.
+fn dir_size(path: &Path) -> Result<u64, Box<dyn Error>> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// The current time, in seconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The finished jobs under `jobs_dir`, oldest first, and the size of the directory. Directories
+/// without a readable [JOB_RECORD] are left alone.
+fn scan(jobs_dir: &Path) -> Result<(Vec<FinishedJob>, u64), Box<dyn Error>> {
+    let mut jobs = vec![];
+    let mut total_bytes = 0;
+    for entry in std::fs::read_dir(jobs_dir)? {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        let bytes = dir_size(&dir)?;
+        total_bytes += bytes;
+
+        let record = std::fs::read_to_string(dir.join(JOB_RECORD))
+            .ok()
+            .and_then(|s| serde_json::from_str::<JobRecord>(&s).ok());
+        match record {
+            Some(record) if record.is_finished() => {
+                let finished_at = record.finished_at.unwrap_or_default();
+                jobs.push(FinishedJob {
+                    dir,
+                    record,
+                    finished_at,
+                    bytes,
+                });
+            }
+            Some(_) => {}
+            None => log::warn!("skipping {}, it has no {}", dir.display(), JOB_RECORD),
+        }
+    }
+    jobs.sort_by_key(|j| j.finished_at);
+    Ok((jobs, total_bytes))
+}
+
+fn remove(job: &FinishedJob, report: &mut PurgeReport) -> Result<(), Box<dyn Error>> {
+    std::fs::remove_dir_all(&job.dir)?;
+    log::debug!("removed job {}", job.record.status.id);
+    report.removed_jobs.push(job.record.status.id.clone());
+    report.freed_bytes += job.bytes;
+    Ok(())
+}
+
+/// Removes the finished jobs under `jobs_dir` that `policy` no longer retains.
+pub fn collect_garbage(
+    jobs_dir: &Path,
+    policy: &RetentionPolicy,
+) -> Result<PurgeReport, Box<dyn Error>> {
+    let (jobs, mut total_bytes) = scan(jobs_dir)?;
+    let now = now();
+    let mut report = PurgeReport::default();
+    for job in &jobs {
+        let age = now.saturating_sub(job.finished_at);
+        let expired = policy.max_age.is_some_and(|max_age| age > max_age);
+        let over_budget = policy
+            .max_bytes
+            .is_some_and(|max_bytes| total_bytes > max_bytes);
+        if !expired && !over_budget {
+            // jobs are sorted oldest first, so the later ones are neither expired nor over budget
+            break;
+        }
+        remove(job, &mut report)?;
+        total_bytes -= job.bytes;
+    }
+    Ok(report)
+}
+
+/// Removes the finished jobs under `jobs_dir` of the model and tenant of `request`. At least one
+/// of them has to be set.
+pub fn purge(jobs_dir: &Path, request: &PurgeRequest) -> Result<PurgeReport, Box<dyn Error>> {
+    if request.model.is_none() && request.tenant.is_none() {
+        return Err("purging needs a model or a tenant".into());
+    }
+    let matches = |wanted: &Option<String>, actual: &Option<String>| match wanted {
+        Some(wanted) => actual.as_ref() == Some(wanted),
+        None => true,
+    };
+
+    let (jobs, _) = scan(jobs_dir)?;
+    let mut report = PurgeReport::default();
+    for job in jobs.iter().filter(|j| {
+        matches(&request.model, &j.record.model) && matches(&request.tenant, &j.record.tenant)
+    }) {
+        remove(job, &mut report)?;
+    }
+    Ok(report)
+}
+
+/// Collects the garbage of `jobs_dir` every `interval` on a background thread, for as long as the
+/// process runs. Failed passes are logged and retried at the next interval.
+pub fn spawn_collector(
+    jobs_dir: PathBuf,
+    policy: RetentionPolicy,
+    interval: Duration,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || loop {
+        match collect_garbage(&jobs_dir, &policy) {
+            Ok(report) if !report.removed_jobs.is_empty() => log::info!(
+                "collected {} jobs, freeing {} bytes",
+                report.removed_jobs.len(),
+                report.freed_bytes
+            ),
+            Ok(_) => {}
+            Err(e) => log::error!(
+                "failed to collect the jobs in {}: {}",
+                jobs_dir.display(),
+                e
+            ),
+        }
+        std::thread::sleep(interval);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_job(jobs_dir: &Path, id: &str, state: JobState, tenant: &str, finished_at: u64) {
+        let dir = jobs_dir.join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("proof.json"), vec![0u8; 100]).unwrap();
+        let record = JobRecord {
+            status: JobStatus {
+                id: id.to_string(),
+                state,
+                error: None,
+                artifacts: vec!["proof.json".to_string()],
+            },
+            model: Some("model".to_string()),
+            tenant: Some(tenant.to_string()),
+            finished_at: Some(finished_at),
+        };
+        std::fs::write(
+            dir.join(JOB_RECORD),
+            serde_json::to_string(&record).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_retention() {
+        let jobs_dir = tempfile::tempdir().unwrap();
+        let jobs_dir = jobs_dir.path();
+        let now = now();
+        add_job(jobs_dir, "old", JobState::Done, "a", now - 1000);
+        add_job(jobs_dir, "running", JobState::Running, "a", 0);
+        add_job(jobs_dir, "recent", JobState::Failed, "b", now - 10);
+        add_job(jobs_dir, "newest", JobState::Done, "a", now);
+
+        let policy = RetentionPolicy {
+            max_age: Some(100),
+            max_bytes: None,
+        };
+        let report = collect_garbage(jobs_dir, &policy).unwrap();
+        assert_eq!(report.removed_jobs, vec!["old".to_string()]);
+
+        // the running job is kept even though the directory stays over budget
+        let policy = RetentionPolicy {
+            max_age: None,
+            max_bytes: Some(1),
+        };
+        let report = collect_garbage(jobs_dir, &policy).unwrap();
+        assert_eq!(report.removed_jobs, vec!["recent", "newest"]);
+        assert!(jobs_dir.join("running").exists());
+
+        add_job(jobs_dir, "other", JobState::Done, "b", now);
+        add_job(jobs_dir, "mine", JobState::Done, "a", now);
+        let request = PurgeRequest {
+            model: None,
+            tenant: Some("a".to_string()),
+        };
+        let report = purge(jobs_dir, &request).unwrap();
+        assert_eq!(report.removed_jobs, vec!["mine".to_string()]);
+        assert!(jobs_dir.join("other").exists());
+    }
+}
@@ -0,0 +1,796 @@
+//! The proving service of `ezkl serve`.
+//!
+//! The compiled circuit, proving key and SRS of a model are loaded once, then witness generation
+//! and proving requests are queued as jobs and run by a fixed number of worker threads, so that
+//! callers don't pay the cost of loading them for every proof. The queue is bounded and requests
+//! beyond it are turned away with a 503. Verification is quick enough to be answered directly.
+//!
+//! Each job keeps its [JOB_RECORD] and artifacts in its own directory under the jobs directory,
+//! the layout [crate::retention] and `ezkl gc-jobs` expect. The endpoints are those of
+//! [crate::api::endpoints], and the OpenAPI document is served at [OPENAPI_PATH].
+//!
+//! A job that panics is failed with the panic message rather than taking its worker down, and a
+//! worker that panics between jobs is restarted, so that jobs aren't accepted without a worker to
+//! run them.
+
+use crate::api::{
+    openapi, JobState, JobStatus, ProveRequest, PurgeRequest, VerifyRequest, VerifyResponse,
+    WitnessSubmission, OPENAPI_PATH,
+};
+use crate::circuit::CheckMode;
+use crate::graph::input::GraphData;
+use crate::graph::{GraphCircuit, GraphWitness};
+use crate::pfsys::key_header::KeyParams;
+use crate::pfsys::{
+    create_single_proof, verify_proof_circuit, ProofSplitCommit, Snark, TranscriptType,
+};
+use crate::retention::{now, spawn_collector, JobRecord, RetentionPolicy, JOB_RECORD};
+use crate::threads::panic_message;
+use crate::Commitments;
+use halo2_proofs::plonk::ProvingKey;
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{error, info};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The header requests name their tenant in, recorded with their jobs so they can be purged.
+pub const TENANT_HEADER: &str = "x-ezkl-tenant";
+
+/// The artifact of a witness generation job.
+pub const WITNESS_ARTIFACT: &str = "witness.json";
+
+/// The artifact of a proving job.
+pub const PROOF_ARTIFACT: &str = "proof.json";
+
+/// The largest request body the service reads.
+pub const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// The environment variable the bearer token of the admin endpoints is read from when no file
+/// holds it.
+pub const ADMIN_TOKEN_ENV: &str = "EZKL_ADMIN_TOKEN";
+
+/// How often the retention policy is applied.
+const COLLECT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What the service loads once at startup.
+pub struct LoadedModel {
+    /// The compiled circuit, cloned for each job
+    pub circuit: GraphCircuit,
+    /// The proving key of the circuit, whose verifying key proofs are verified against
+    pub pk: ProvingKey<G1Affine>,
+    /// The SRS, trimmed to the logrows of the circuit
+    pub params: ParamsKZG<Bn256>,
+}
+
+/// How the service runs.
+#[derive(Clone, Debug)]
+pub struct ServeConfig {
+    /// The address to listen on
+    pub addr: SocketAddr,
+    /// The directory holding one directory per job
+    pub jobs_dir: PathBuf,
+    /// The name of the model, recorded with each job
+    pub model: Option<String>,
+    /// The number of jobs run at once
+    pub workers: usize,
+    /// The number of jobs that can wait for a worker
+    pub max_queue: usize,
+    /// The checks run when proving
+    pub check_mode: CheckMode,
+    /// How long the artifacts of finished jobs are kept
+    pub retention: RetentionPolicy,
+    /// The bearer token of the admin endpoints, which are disabled without one
+    pub admin_token: Option<String>,
+}
+
+/// An endpoint of [crate::api::endpoints], with its path parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Route {
+    OpenApi,
+    Witness,
+    Prove,
+    Verify,
+    Job(String),
+    Artifact(String, String),
+    Purge,
+}
+
+/// Whether `id` can name a job directory, ruling out paths.
+fn is_job_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn route(method: &Method, path: &str) -> Option<Route> {
+    let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+    let route = match (method, segments.as_slice()) {
+        (&Method::GET, _) if path == OPENAPI_PATH => Route::OpenApi,
+        (&Method::POST, ["witness"]) => Route::Witness,
+        (&Method::POST, ["prove"]) => Route::Prove,
+        (&Method::POST, ["verify"]) => Route::Verify,
+        (&Method::GET, ["jobs", id]) => Route::Job(id.to_string()),
+        (&Method::GET, ["jobs", id, "artifacts", name]) => {
+            Route::Artifact(id.to_string(), name.to_string())
+        }
+        (&Method::POST, ["admin", "purge"]) => Route::Purge,
+        _ => return None,
+    };
+    match &route {
+        Route::Job(id) | Route::Artifact(id, _) if !is_job_id(id) => None,
+        _ => Some(route),
+    }
+}
+
+/// The work of a job.
+enum Task {
+    Witness(GraphData),
+    Prove(GraphWitness),
+}
+
+struct Job {
+    record: JobRecord,
+    task: Task,
+}
+
+/// The service answering requests, see [start].
+pub struct Service {
+    model: Arc<LoadedModel>,
+    config: ServeConfig,
+    queue: SyncSender<Job>,
+    /// The number of workers running, jobs are turned away when none are
+    workers: Arc<AtomicUsize>,
+}
+
+/// Reads the bearer token of the admin endpoints from `file`, or else from [ADMIN_TOKEN_ENV], rather
+/// than from the arguments, which other users can see in the process list.
+pub fn read_admin_token(file: Option<PathBuf>) -> Result<Option<String>, Box<dyn Error>> {
+    match file {
+        Some(path) => {
+            let token = std::fs::read_to_string(&path)?.trim().to_string();
+            if token.is_empty() {
+                return Err(format!("{} holds no admin token", path.display()).into());
+            }
+            Ok(Some(token))
+        }
+        None => Ok(std::env::var(ADMIN_TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.is_empty())),
+    }
+}
+
+/// Compares `a` and `b` in a time that doesn't depend on where they differ, so that the admin token
+/// can't be recovered a byte at a time from the response times.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn write_record(jobs_dir: &Path, record: &JobRecord) -> Result<(), Box<dyn Error>> {
+    let dir = jobs_dir.join(&record.status.id);
+    // written then renamed, so that readers never see a partial record
+    let tmp = dir.join(format!("{}.tmp", JOB_RECORD));
+    std::fs::write(&tmp, serde_json::to_vec(record)?)?;
+    std::fs::rename(tmp, dir.join(JOB_RECORD))?;
+    Ok(())
+}
+
+fn read_record(jobs_dir: &Path, id: &str) -> Option<JobRecord> {
+    let record = std::fs::read(jobs_dir.join(id).join(JOB_RECORD)).ok()?;
+    serde_json::from_slice(&record).ok()
+}
+
+/// Fails the jobs a previous run of the service left queued or running.
+fn fail_interrupted_jobs(jobs_dir: &Path) -> Result<(), Box<dyn Error>> {
+    for entry in std::fs::read_dir(jobs_dir)? {
+        let id = entry?.file_name().to_string_lossy().to_string();
+        if let Some(mut record) = read_record(jobs_dir, &id) {
+            if !record.is_finished() {
+                record.status.state = JobState::Failed;
+                record.status.error = Some("the service stopped before the job finished".into());
+                record.finished_at = Some(now());
+                write_record(jobs_dir, &record)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn generate_witness(
+    model: &LoadedModel,
+    data: &GraphData,
+) -> Result<GraphWitness, Box<dyn Error>> {
+    let mut circuit = model.circuit.clone();
+    let mut input = circuit.load_graph_input(data).await?;
+    let settings = circuit.settings().clone();
+    let srs = settings
+        .module_requires_polycommit()
+        .then_some(&model.params);
+    let mut witness =
+        crate::threads::install(settings.run_args.num_threads, "witness generation", || {
+            circuit.forward::<KZGCommitmentScheme<Bn256>>(
+                &mut input,
+                Some(model.pk.get_vk()),
+                srs,
+                false,
+            )
+        })?;
+    witness.expected_outputs = data.expected_outputs.clone();
+    // the warnings of loading the input, witness generation takes its own on the pool it runs on
+    witness.warnings.extend(crate::warnings::take());
+    Ok(witness)
+}
+
+fn prove(
+    model: &LoadedModel,
+    witness: &GraphWitness,
+    check_mode: CheckMode,
+) -> Result<Snark<Fr, G1Affine>, Box<dyn Error>> {
+    let mut circuit = model.circuit.clone();
+    circuit.load_graph_witness(witness)?;
+    let pretty_public_inputs = circuit.pretty_public_inputs(witness)?;
+    let public_inputs = circuit.prepare_public_inputs(witness)?;
+    let settings = circuit.settings().clone();
+    let proof_split_commits: Option<ProofSplitCommit> = witness.into();
+
+    let mut snark = crate::threads::install(settings.run_args.num_threads, "proving", || {
+        create_single_proof::<
+            KZGCommitmentScheme<Bn256>,
+            _,
+            ProverSHPLONK<_>,
+            VerifierSHPLONK<_>,
+            SingleStrategy<_>,
+        >(
+            circuit,
+            vec![public_inputs],
+            &model.params,
+            &model.pk,
+            check_mode,
+            Commitments::KZG,
+            TranscriptType::EVM,
+            proof_split_commits,
+            None,
+        )
+    })?;
+    snark.pretty_public_inputs = pretty_public_inputs;
+    snark.settings_hash = settings.settings_hash();
+    snark.warnings = witness.warnings.clone();
+    snark.warnings.extend(crate::warnings::take());
+    Ok(snark)
+}
+
+fn verify(model: &LoadedModel, proof: serde_json::Value) -> Result<bool, Box<dyn Error>> {
+    let snark: Snark<Fr, G1Affine> = serde_json::from_value(proof)?;
+    let settings = model.circuit.settings();
+    settings.check_model_hash(&snark.instances.concat())?;
+    if snark.transcript_type != TranscriptType::EVM {
+        return Err("only proofs with an EVM transcript are verified by the service".into());
+    }
+    let strategy = SingleStrategy::new(model.params.verifier_params());
+    let result = verify_proof_circuit::<
+        VerifierSHPLONK<'_, Bn256>,
+        KZGCommitmentScheme<Bn256>,
+        _,
+        _,
+        EvmTranscript<G1Affine, _, _, _>,
+    >(
+        &snark,
+        model.params.verifier_params(),
+        model.pk.get_vk(),
+        strategy,
+        1 << settings.run_args.logrows,
+    );
+    Ok(result.is_ok())
+}
+
+/// Runs the job, returning the artifacts it wrote to `dir`.
+fn run_job(
+    model: &LoadedModel,
+    runtime: &tokio::runtime::Runtime,
+    task: Task,
+    dir: &Path,
+    check_mode: CheckMode,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    match task {
+        Task::Witness(data) => {
+            let witness = runtime.block_on(generate_witness(model, &data))?;
+            witness.save(dir.join(WITNESS_ARTIFACT))?;
+            Ok(vec![WITNESS_ARTIFACT.to_string()])
+        }
+        Task::Prove(witness) => {
+            let snark = prove(model, &witness, check_mode)?;
+            snark.save(&dir.join(PROOF_ARTIFACT))?;
+            Ok(vec![PROOF_ARTIFACT.to_string()])
+        }
+    }
+}
+
+/// Runs the jobs of the queue until the service stops. Witness generation can read inputs from
+/// a chain, so each worker has its own runtime to block on.
+fn run_worker(
+    model: &LoadedModel,
+    queue: &Mutex<Receiver<Job>>,
+    jobs_dir: &Path,
+    check_mode: CheckMode,
+) {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            error!("failed to start a worker: {}", e);
+            return;
+        }
+    };
+    loop {
+        // the receiver is left consistent by a panic, so a poisoned lock is still usable
+        let job = queue.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let Ok(Job { mut record, task }) = job else {
+            return;
+        };
+        let id = record.status.id.clone();
+        record.status.state = JobState::Running;
+        if let Err(e) = write_record(jobs_dir, &record) {
+            error!("failed to record job {}: {}", id, e);
+        }
+
+        info!("running job {}", id);
+        // each job only sees its own warnings
+        let _warnings = crate::warnings::scope();
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            run_job(model, &runtime, task, &jobs_dir.join(&id), check_mode)
+        }))
+        .unwrap_or_else(|panic| Err(format!("panicked: {}", panic_message(&*panic)).into()));
+        match result {
+            Ok(artifacts) => {
+                record.status.state = JobState::Done;
+                record.status.artifacts = artifacts;
+            }
+            Err(e) => {
+                error!("job {} failed: {}", id, e);
+                record.status.state = JobState::Failed;
+                record.status.error = Some(e.to_string());
+            }
+        }
+        record.finished_at = Some(now());
+        if let Err(e) = write_record(jobs_dir, &record) {
+            error!("failed to record job {}: {}", id, e);
+        }
+    }
+}
+
+/// Runs a worker, restarting it if it panics, until the queue closes or its runtime can't start.
+/// It was counted in `workers` when spawned, and is discounted once it stops.
+fn supervise_worker(
+    model: Arc<LoadedModel>,
+    queue: Arc<Mutex<Receiver<Job>>>,
+    jobs_dir: PathBuf,
+    check_mode: CheckMode,
+    workers: Arc<AtomicUsize>,
+) {
+    while let Err(panic) = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        run_worker(&model, &queue, &jobs_dir, check_mode)
+    })) {
+        error!("worker panicked, restarting it: {}", panic_message(&*panic));
+    }
+    workers.fetch_sub(1, Ordering::SeqCst);
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    match serde_json::to_vec(body) {
+        Ok(body) => Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap_or_default(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+fn error_response(status: StatusCode, message: impl std::fmt::Display) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "text/plain")
+        .body(Body::from(message.to_string()))
+        .unwrap_or_default()
+}
+
+/// Reads and parses a json body of at most [MAX_BODY_BYTES].
+async fn read_json<T: DeserializeOwned>(mut body: Body) -> Result<T, Response<Body>> {
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error_response(StatusCode::BAD_REQUEST, e))?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("the body exceeds {} bytes", MAX_BODY_BYTES),
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&bytes).map_err(|e| error_response(StatusCode::BAD_REQUEST, e))
+}
+
+impl Service {
+    /// Queues a job, recording it first so its status can be polled as soon as it is returned.
+    fn submit(&self, tenant: Option<String>, task: Task) -> Response<Body> {
+        if self.workers.load(Ordering::SeqCst) == 0 {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "no worker is running");
+        }
+        let id = format!("{:032x}", rand::random::<u128>());
+        let record = JobRecord {
+            status: JobStatus {
+                id: id.clone(),
+                state: JobState::Queued,
+                error: None,
+                artifacts: vec![],
+            },
+            model: self.config.model.clone(),
+            tenant,
+            finished_at: None,
+        };
+        let dir = self.config.jobs_dir.join(&id);
+        if let Err(e) = std::fs::create_dir_all(&dir)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|_| write_record(&self.config.jobs_dir, &record))
+        {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, e);
+        }
+
+        let status = record.status.clone();
+        match self.queue.try_send(Job { record, task }) {
+            Ok(()) => json_response(StatusCode::ACCEPTED, &status),
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                match e {
+                    TrySendError::Full(_) => {
+                        error_response(StatusCode::SERVICE_UNAVAILABLE, "the job queue is full")
+                    }
+                    TrySendError::Disconnected(_) => error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "the workers have stopped",
+                    ),
+                }
+            }
+        }
+    }
+
+    fn witness_of(&self, request: ProveRequest) -> Result<GraphWitness, Response<Body>> {
+        match (request.witness_job, request.witness) {
+            (Some(id), None) => {
+                let done = is_job_id(&id)
+                    && read_record(&self.config.jobs_dir, &id)
+                        .is_some_and(|r| r.status.artifacts.iter().any(|a| a == WITNESS_ARTIFACT));
+                if !done {
+                    return Err(error_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("job {} has no witness", id),
+                    ));
+                }
+                let path = self.config.jobs_dir.join(id).join(WITNESS_ARTIFACT);
+                GraphWitness::from_path(path)
+                    .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e))
+            }
+            (None, Some(witness)) => Ok(witness),
+            _ => Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "exactly one of witness_job and witness must be set",
+            )),
+        }
+    }
+
+    fn is_admin(&self, request: &Request<Body>) -> bool {
+        let Some(token) = &self.config.admin_token else {
+            return false;
+        };
+        request
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|v| constant_time_eq(v.as_bytes(), token.as_bytes()))
+    }
+
+    /// Answers a request to one of the endpoints of [crate::api::endpoints].
+    pub async fn handle(self: Arc<Self>, request: Request<Body>) -> Response<Body> {
+        let Some(route) = route(request.method(), request.uri().path()) else {
+            return error_response(StatusCode::NOT_FOUND, "no such endpoint");
+        };
+        let tenant = request
+            .headers()
+            .get(TENANT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        if route == Route::Purge && !self.is_admin(&request) {
+            return error_response(StatusCode::FORBIDDEN, "not an admin");
+        }
+        let body = request.into_body();
+
+        let response = match route {
+            Route::OpenApi => Ok(json_response(StatusCode::OK, &openapi())),
+            Route::Witness => read_json::<WitnessSubmission>(body)
+                .await
+                .map(|submission| self.submit(tenant, Task::Witness(submission.data))),
+            Route::Prove => match read_json::<ProveRequest>(body).await {
+                Ok(request) => self
+                    .witness_of(request)
+                    .map(|witness| self.submit(tenant, Task::Prove(witness))),
+                Err(response) => Err(response),
+            },
+            Route::Verify => match read_json::<VerifyRequest>(body).await {
+                Ok(request) => {
+                    let model = self.model.clone();
+                    let verified = tokio::task::spawn_blocking(move || {
+                        verify(&model, request.proof).map_err(|e| e.to_string())
+                    })
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|verified| verified);
+                    let response = match verified {
+                        Ok(verified) => VerifyResponse {
+                            verified,
+                            error: None,
+                        },
+                        Err(e) => VerifyResponse {
+                            verified: false,
+                            error: Some(e),
+                        },
+                    };
+                    Ok(json_response(StatusCode::OK, &response))
+                }
+                Err(response) => Err(response),
+            },
+            Route::Job(id) => match read_record(&self.config.jobs_dir, &id) {
+                Some(record) => Ok(json_response(StatusCode::OK, &record.status)),
+                None => Err(error_response(StatusCode::NOT_FOUND, "no such job")),
+            },
+            Route::Artifact(id, name) => match read_record(&self.config.jobs_dir, &id) {
+                // only the recorded artifacts are served, so the name can't escape the job's directory
+                Some(record) if record.status.artifacts.contains(&name) => {
+                    match std::fs::read(self.config.jobs_dir.join(&id).join(&name)) {
+                        Ok(bytes) => Ok(Response::builder()
+                            .header("content-type", "application/json")
+                            .body(Body::from(bytes))
+                            .unwrap_or_default()),
+                        Err(e) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, e)),
+                    }
+                }
+                _ => Err(error_response(StatusCode::NOT_FOUND, "no such artifact")),
+            },
+            Route::Purge => match read_json::<PurgeRequest>(body).await {
+                Ok(request) => crate::retention::purge(&self.config.jobs_dir, &request)
+                    .map(|report| json_response(StatusCode::OK, &report))
+                    .map_err(|e| error_response(StatusCode::BAD_REQUEST, e)),
+                Err(response) => Err(response),
+            },
+        };
+        response.unwrap_or_else(|response| response)
+    }
+}
+
+/// Starts the workers and the retention of the service, without listening, so that embedders can
+/// pass it requests through [Service::handle].
+pub fn start(model: LoadedModel, config: ServeConfig) -> Result<Arc<Service>, Box<dyn Error>> {
+    std::fs::create_dir_all(&config.jobs_dir)?;
+    fail_interrupted_jobs(&config.jobs_dir)?;
+    if config.retention != RetentionPolicy::default() {
+        spawn_collector(
+            config.jobs_dir.clone(),
+            config.retention.clone(),
+            COLLECT_INTERVAL,
+        );
+    }
+
+    let model = Arc::new(model);
+    let (sender, receiver) = sync_channel(config.max_queue);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers = Arc::new(AtomicUsize::new(0));
+    for i in 0..config.workers.max(1) {
+        let model = model.clone();
+        let receiver = receiver.clone();
+        let jobs_dir = config.jobs_dir.clone();
+        let check_mode = config.check_mode;
+        let workers = workers.clone();
+        // counted before it starts, so that jobs submitted right away aren't turned away
+        workers.fetch_add(1, Ordering::SeqCst);
+        std::thread::Builder::new()
+            .name(format!("ezkl-worker-{}", i))
+            .spawn(move || supervise_worker(model, receiver, jobs_dir, check_mode, workers))?;
+    }
+
+    Ok(Arc::new(Service {
+        model,
+        config,
+        queue: sender,
+        workers,
+    }))
+}
+
+/// Serves `model` until the process is stopped.
+pub async fn serve(model: LoadedModel, config: ServeConfig) -> Result<(), Box<dyn Error>> {
+    let addr = config.addr;
+    let service = start(model, config)?;
+    let make_service = make_service_fn(move |_| {
+        let service = service.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let service = service.clone();
+                async move { Ok::<_, Infallible>(service.handle(request).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    info!("serving on http://{}", server.local_addr());
+    server.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::templates::{Template, TemplateTask};
+    use crate::pfsys::{create_keys, srs::gen_srs};
+
+    async fn request<T: DeserializeOwned>(
+        service: &Arc<Service>,
+        method: Method,
+        path: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, T) {
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap();
+        let response = service.clone().handle(request).await;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn wait_for(service: &Arc<Service>, id: &str) -> JobStatus {
+        loop {
+            let path = format!("/jobs/{}", id);
+            let (_, status) =
+                request::<JobStatus>(service, Method::GET, &path, serde_json::Value::Null).await;
+            if matches!(status.state, JobState::Done | JobState::Failed) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_prove_and_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("network.onnx");
+        let template = Template::new(TemplateTask::LinearRegression, 3, 4, 0).unwrap();
+        template
+            .save(&model_path, &dir.path().join("input.json"))
+            .unwrap();
+        let circuit = GraphCircuit::from_run_args(&template.run_args, &model_path).unwrap();
+        let params = gen_srs::<KZGCommitmentScheme<Bn256>>(template.run_args.logrows);
+        let pk = create_keys::<KZGCommitmentScheme<Bn256>, GraphCircuit>(&circuit, &params, false)
+            .unwrap();
+        let config = ServeConfig {
+            addr: ([127, 0, 0, 1], 0).into(),
+            jobs_dir: dir.path().join("jobs"),
+            model: Some("linear-regression".into()),
+            workers: 1,
+            max_queue: 4,
+            check_mode: CheckMode::SAFE,
+            retention: RetentionPolicy::default(),
+            admin_token: None,
+        };
+        let service = start(
+            LoadedModel {
+                circuit,
+                pk,
+                params,
+            },
+            config,
+        )
+        .unwrap();
+
+        let submission = serde_json::json!({ "data": template.data });
+        let (status, job) =
+            request::<JobStatus>(&service, Method::POST, "/witness", submission).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let witness_job = wait_for(&service, &job.id).await;
+        assert_eq!(witness_job.state, JobState::Done, "{:?}", witness_job.error);
+
+        let prove = serde_json::json!({ "witness_job": witness_job.id });
+        let (status, job) = request::<JobStatus>(&service, Method::POST, "/prove", prove).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let proving_job = wait_for(&service, &job.id).await;
+        assert_eq!(proving_job.state, JobState::Done, "{:?}", proving_job.error);
+        assert_eq!(proving_job.artifacts, vec![PROOF_ARTIFACT.to_string()]);
+
+        let path = format!("/jobs/{}/artifacts/{}", proving_job.id, PROOF_ARTIFACT);
+        let (status, proof) =
+            request::<serde_json::Value>(&service, Method::GET, &path, serde_json::Value::Null)
+                .await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, response) = request::<VerifyResponse>(
+            &service,
+            Method::POST,
+            "/verify",
+            serde_json::json!({ "proof": proof }),
+        )
+        .await;
+        assert_eq!(
+            response,
+            VerifyResponse {
+                verified: true,
+                error: None
+            }
+        );
+
+        // a proof of other outputs doesn't verify
+        let mut tampered: Snark<Fr, G1Affine> = serde_json::from_value(proof).unwrap();
+        tampered.instances[0][0] += Fr::from(1);
+        let (_, response) = request::<VerifyResponse>(
+            &service,
+            Method::POST,
+            "/verify",
+            serde_json::json!({ "proof": tampered }),
+        )
+        .await;
+        assert!(!response.verified);
+    }
+
+    #[test]
+    fn test_admin_token() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "secret\n").unwrap();
+        assert_eq!(
+            read_admin_token(Some(path.clone())).unwrap(),
+            Some("secret".to_string())
+        );
+        std::fs::write(&path, "\n").unwrap();
+        assert!(read_admin_token(Some(path)).is_err());
+    }
+
+    #[test]
+    fn test_routes() {
+        // every endpoint of the OpenAPI document is served
+        for endpoint in crate::api::endpoints() {
+            let method = endpoint.method.to_uppercase().parse::<Method>().unwrap();
+            let path = endpoint
+                .path
+                .replace("{id}", "abc123")
+                .replace("{name}", "proof.json");
+            assert!(route(&method, &path).is_some(), "{}", endpoint.path);
+        }
+        assert_eq!(route(&Method::GET, OPENAPI_PATH), Some(Route::OpenApi));
+        assert_eq!(
+            route(&Method::GET, "/jobs/abc123/artifacts/proof.json"),
+            Some(Route::Artifact("abc123".into(), "proof.json".into()))
+        );
+
+        assert_eq!(route(&Method::GET, "/prove"), None);
+        assert_eq!(route(&Method::GET, "/jobs/.."), None);
+        assert_eq!(route(&Method::GET, "/jobs/../artifacts/job.json"), None);
+    }
+}
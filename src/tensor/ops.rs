@@ -1959,6 +1959,50 @@ pub fn topk_axes<T: TensorType + PartialOrd + Send + Sync>(
     Ok(res)
 }
 
+/// Samples a token from the top K logits: the token is the index of the `(randomness mod k)`-th
+/// largest logit. Ties resolve to the first index holding the selected value.
+/// # Arguments
+///
+/// * `a` - Tensor of logits
+/// * `randomness` - Randomness the sample is drawn under
+/// * `k` - Number of candidates to sample from
+/// # Examples
+/// ```
+/// use ezkl::tensor::Tensor;
+/// use ezkl::tensor::ops::sample_topk;
+/// let x = Tensor::<i128>::new(
+///     Some(&[2, 15, 2, 1, 7, 0]),
+///     &[6],
+/// ).unwrap();
+/// let result = sample_topk(&x, 4, 3).unwrap();
+/// let expected = Tensor::<i128>::new(Some(&[4]), &[1]).unwrap();
+/// assert_eq!(result, expected);
+/// ```
+pub fn sample_topk(
+    a: &Tensor<i128>,
+    randomness: i128,
+    k: usize,
+) -> Result<Tensor<i128>, TensorError> {
+    if k == 0 || k > a.len() {
+        return Err(TensorError::DimError(format!(
+            "cannot sample from the top {} of {} logits",
+            k,
+            a.len()
+        )));
+    }
+    let mut logits = a.clone();
+    logits.flatten();
+
+    let candidates = topk(&logits, k, true)?;
+    let selected = candidates[randomness.rem_euclid(k as i128) as usize];
+    let token = logits
+        .iter()
+        .position(|x| *x == selected)
+        .ok_or(TensorError::DimError("sampled logit not found".to_string()))?;
+
+    Tensor::new(Some(&[token as i128]), &[1])
+}
+
 /// Sums a tensor along specific axes.
 /// # Arguments
 ///
@@ -4232,6 +4276,32 @@ pub mod nonlinearities {
         .unwrap()
     }
 
+    /// Elementwise divides a tensor by `2^log_denom`, rounding halves up (unlike [const_div], which rounds them away from zero).
+    /// # Arguments
+    ///
+    /// * `a` - Tensor
+    /// * `log_denom` - The log2 of the denominator
+    /// # Examples
+    /// ```
+    /// use ezkl::tensor::Tensor;
+    /// use ezkl::tensor::ops::nonlinearities::shift_div;
+    /// let x = Tensor::<i128>::new(
+    ///     Some(&[2, 1, -2, 7, -1, -3]),
+    ///     &[2, 3],
+    /// ).unwrap();
+    /// let result = shift_div(&x, 1);
+    /// let expected = Tensor::<i128>::new(Some(&[1, 1, -1, 4, 0, -1]), &[2, 3]).unwrap();
+    /// assert_eq!(result, expected);
+    /// ```
+    pub fn shift_div(a: &Tensor<i128>, log_denom: u32) -> Tensor<i128> {
+        if log_denom == 0 {
+            return a.clone();
+        }
+        let half = 1_i128 << (log_denom - 1);
+        a.par_enum_map(|_, a_i| Ok::<_, TensorError>((a_i + half) >> log_denom))
+            .unwrap()
+    }
+
     /// Elementwise inverse.
     /// # Arguments
     ///
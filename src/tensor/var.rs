@@ -1,10 +1,96 @@
 use std::collections::HashSet;
 
 use log::{debug, error, warn};
+use tosubcommand::ToFlags;
 
 use crate::circuit::CheckMode;
 
 use super::*;
+
+/// Order in which the cells of a [ValTensor] are written to a [VarTensor] during synthesis, set
+/// with [crate::RunArgs::assignment_order]. This only changes the order of the assignment loop,
+/// each value always lands in the same cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssignmentOrder {
+    /// Follow the flattened order of the tensor, which cycles through the inner columns on every row
+    #[default]
+    RowMajor,
+    /// Fill each column top to bottom before moving to the next, which keeps writes contiguous
+    ColumnMajor,
+    /// Pick per assignment based on the number of inner columns and the size of the tensor
+    Auto,
+}
+
+/// Tensors at least this large are assigned column-major under [AssignmentOrder::Auto], below
+/// it the cost of ordering the coordinates outweighs the better cache behaviour.
+pub const AUTO_COLUMN_MAJOR_MIN_LEN: usize = 1 << 12;
+
+impl AssignmentOrder {
+    /// Resolves [AssignmentOrder::Auto] for a tensor of `len` elements.
+    pub fn resolve(self, num_inner_cols: usize, len: usize) -> Self {
+        match self {
+            AssignmentOrder::Auto if num_inner_cols > 1 && len >= AUTO_COLUMN_MAJOR_MIN_LEN => {
+                AssignmentOrder::ColumnMajor
+            }
+            AssignmentOrder::Auto => AssignmentOrder::RowMajor,
+            order => order,
+        }
+    }
+}
+
+impl std::fmt::Display for AssignmentOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssignmentOrder::RowMajor => write!(f, "row-major"),
+            AssignmentOrder::ColumnMajor => write!(f, "column-major"),
+            AssignmentOrder::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+impl ToFlags for AssignmentOrder {
+    /// Convert the struct to a subcommand string
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+impl std::str::FromStr for AssignmentOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "row-major" => Ok(AssignmentOrder::RowMajor),
+            "column-major" => Ok(AssignmentOrder::ColumnMajor),
+            "auto" => Ok(AssignmentOrder::Auto),
+            _ => Err(format!(
+                "unknown assignment order {}, expected row-major, column-major or auto",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Converts AssignmentOrder into a PyObject (Required for AssignmentOrder to be compatible with Python)
+impl pyo3::IntoPy<pyo3::PyObject> for AssignmentOrder {
+    fn into_py(self, py: pyo3::Python) -> pyo3::PyObject {
+        pyo3::ToPyObject::to_object(&self.to_string(), py)
+    }
+}
+
+#[cfg(feature = "python-bindings")]
+/// Obtains AssignmentOrder from PyObject (Required for AssignmentOrder to be compatible with Python)
+impl<'source> pyo3::FromPyObject<'source> for AssignmentOrder {
+    fn extract(ob: &'source pyo3::PyAny) -> pyo3::PyResult<Self> {
+        let strval = ob.extract::<String>()?;
+        strval
+            .parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+}
+
 /// A wrapper around Halo2's `Column<Fixed>` or `Column<Advice>`.
 /// Typically assign [ValTensor]s to [VarTensor]s when laying out a circuit.
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
@@ -339,12 +425,13 @@ impl VarTensor {
         Ok(res)
     }
 
-    /// Assigns [ValTensor] to the columns of the inner tensor.
+    /// Assigns [ValTensor] to the columns of the inner tensor, writing the cells in `order`.
     pub fn assign<F: PrimeField + TensorType + PartialOrd>(
         &self,
         region: &mut Region<F>,
         offset: usize,
         values: &ValTensor<F>,
+        order: AssignmentOrder,
     ) -> Result<ValTensor<F>, halo2_proofs::plonk::Error> {
         let mut res: ValTensor<F> = match values {
             ValTensor::Instance {
@@ -380,8 +467,8 @@ impl VarTensor {
                     Err(halo2_proofs::plonk::Error::Synthesis)
                 }
             },
-            ValTensor::Value { inner: v, .. } => Ok(v
-                .enum_map(|coord, k| {
+            ValTensor::Value { inner: v, .. } => {
+                let mut assign_cell = |coord: usize, k: ValType<F>| {
                     let cell = self.assign_value(region, offset, k.clone(), coord)?;
                     match k {
                         ValType::Constant(f) => Ok::<ValType<F>, halo2_proofs::plonk::Error>(
@@ -390,8 +477,24 @@ impl VarTensor {
                         ValType::AssignedConstant(_, f) => Ok(ValType::AssignedConstant(cell, f)),
                         _ => Ok(ValType::PrevAssigned(cell)),
                     }
-                })?
-                .into()),
+                };
+
+                match order.resolve(self.num_inner_cols(), v.len()) {
+                    AssignmentOrder::ColumnMajor => {
+                        let mut coords = (0..v.len()).collect::<Vec<_>>();
+                        coords.sort_by_key(|coord| self.cartesian_coord(offset + coord));
+                        let mut assigned = vec![None; v.len()];
+                        for coord in coords {
+                            assigned[coord] = Some(assign_cell(coord, v[coord].clone())?);
+                        }
+                        let assigned = assigned.into_iter().flatten().collect::<Vec<_>>();
+                        Ok(Tensor::new(Some(&assigned), v.dims())
+                            .map_err(|_| halo2_proofs::plonk::Error::Synthesis)?
+                            .into())
+                    }
+                    _ => Ok(v.enum_map(|coord, k| assign_cell(coord, k))?.into()),
+                }
+            }
         }?;
         res.set_scale(values.scale());
         Ok(res)
@@ -0,0 +1,115 @@
+//! Sizing and scoping of the rayon thread pools used by each pipeline stage.
+//!
+//! By default everything runs on rayon's global pool, which is sized from the host's core count
+//! and shared by witness generation, the halo2 prover and anything else linking rayon in the same
+//! process. Running each stage in its own pool bounds how many cores it can take, and lets a
+//! co-located service keep the rest.
+
+use rayon::ThreadPoolBuilder;
+use std::any::Any;
+use std::error::Error;
+use std::path::Path;
+
+/// Parses a cgroup v2 `cpu.max` file, e.g `200000 100000` for two cpus or `max 100000` for no limit.
+fn parse_cpu_max(contents: &str) -> Option<usize> {
+    let mut fields = contents.split_whitespace();
+    let quota = fields.next()?.parse::<f64>().ok()?;
+    let period = fields.next()?.parse::<f64>().ok()?;
+    cpus_from_quota(quota, period)
+}
+
+fn cpus_from_quota(quota: f64, period: f64) -> Option<usize> {
+    if quota <= 0.0 || period <= 0.0 {
+        return None;
+    }
+    Some(((quota / period).ceil() as usize).max(1))
+}
+
+fn read(path: impl AsRef<Path>) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+/// The cpu quota of the cgroup the process runs in, if any. Checks cgroup v2 and falls back to
+/// the v1 cfs quota.
+fn cgroup_cpu_limit() -> Option<usize> {
+    if let Some(limit) = read("/sys/fs/cgroup/cpu.max").and_then(|c| parse_cpu_max(&c)) {
+        return Some(limit);
+    }
+    let quota = read("/sys/fs/cgroup/cpu/cpu.cfs_quota_us")?
+        .trim()
+        .parse()
+        .ok()?;
+    let period = read("/sys/fs/cgroup/cpu/cpu.cfs_period_us")?
+        .trim()
+        .parse()
+        .ok()?;
+    cpus_from_quota(quota, period)
+}
+
+/// The number of cpus the process can actually use: the host's parallelism (which already
+/// accounts for the affinity mask), capped by the cgroup cpu quota when running in a container.
+pub fn available_cpus() -> usize {
+    let host = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    match cgroup_cpu_limit() {
+        Some(limit) => host.min(limit),
+        None => host,
+    }
+}
+
+/// Runs `op` on a dedicated pool of `num_threads` threads, or of [available_cpus] threads when
+/// unset. Every rayon parallel iterator called from `op`, including the ones in the halo2 prover,
+/// runs on that pool rather than on the global one, and records its stages into the caller's
+/// [crate::pfsys::profile::Profile]. The pool is torn down when `op` returns.
+pub fn install<T: Send>(
+    num_threads: Option<usize>,
+    stage: &str,
+    op: impl FnOnce() -> Result<T, Box<dyn Error>> + Send,
+) -> Result<T, Box<dyn Error>> {
+    let num_threads = match num_threads {
+        Some(0) => return Err("num_threads must be at least 1".into()),
+        Some(n) => n,
+        None => available_cpus(),
+    };
+    log::debug!("running {} on {} threads", stage, num_threads);
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name({
+            let stage = stage.replace(' ', "-");
+            move |i| format!("ezkl-{}-{}", stage, i)
+        })
+        .build()?;
+    // the stages run on the pool are recorded into the profile of the caller
+    let recorder = crate::pfsys::profile::current();
+    // the error is flattened to a string as boxed errors can't be sent back across threads
+    Ok(pool.install(|| {
+        let _profile = recorder.as_ref().map(|recorder| recorder.enter());
+        op().map_err(|e| e.to_string())
+    })?)
+}
+
+/// The message a thread panicked with, for reporting a panic that was caught.
+pub fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cgroup_quota() {
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2));
+        assert_eq!(parse_cpu_max("150000 100000"), Some(2));
+        assert_eq!(parse_cpu_max("max 100000"), None);
+        assert_eq!(cpus_from_quota(-1.0, 100000.0), None);
+
+        let threads = install(Some(3), "test", || Ok(rayon::current_num_threads())).unwrap();
+        assert_eq!(threads, 3);
+    }
+}
@@ -0,0 +1,350 @@
+//! Resumable, checksummed transfers of large artifacts (proving keys, SRS) over http.
+//!
+//! Downloads stream the artifact a chunk at a time with range requests into a `.part` file next to
+//! it, which is only renamed into place once complete, so an interrupted download resumes from the
+//! last byte written. Uploads PUT the artifact a
+//! chunk at a time with `Content-Range` headers, as resumable upload endpoints expect, and record
+//! their progress next to the artifact. Failed chunks are retried with exponential backoff, and a
+//! server pushing back with `429` or `503` is waited on for as long as its `Retry-After` asks.
+//! Artifacts are checked against their SHA256 hash once they have fully moved.
+
+use crate::execute::init_bar;
+use log::{info, warn};
+use reqwest::header::{HeaderMap, CONTENT_RANGE, RANGE, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The size of the chunks artifacts are transferred in by default, 64 MiB.
+pub const DEFAULT_CHUNK_SIZE: u64 = 64 << 20;
+/// The header carrying the SHA256 hash (hex) of the whole artifact on uploads.
+pub const SHA256_HEADER: &str = "x-content-sha256";
+/// How many times a chunk is retried before the transfer is given up.
+const MAX_RETRIES: u32 = 8;
+/// The delay before the first retry, doubled on each following retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// The longest a retry is delayed, whatever the server asks for.
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+/// The status resumable upload endpoints answer chunks that leave the upload incomplete with.
+const RESUME_INCOMPLETE: u16 = 308;
+
+/// The SHA256 hash (hex) of a file, read a chunk at a time.
+pub fn hash_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    Ok(sha256::try_digest(path)?)
+}
+
+/// `path` with `suffix` appended to its file name, e.g `pk.key.part`.
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// The total size out of a `Content-Range: bytes start-end/total` header.
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// The number of bytes an upload endpoint holds, out of its `Range: bytes=0-end` header.
+fn received_bytes(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get(RANGE)?.to_str().ok()?;
+    let end: u64 = range.rsplit('-').next()?.trim().parse().ok()?;
+    Some(end + 1)
+}
+
+/// The delay before the `attempt`th retry, unless the server asked for another one.
+fn backoff(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after
+        .unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Sends the request built by `build`, retrying connection failures and transient statuses with
+/// exponential backoff. The body is left to the caller to stream.
+async fn request(build: impl Fn() -> RequestBuilder) -> Result<Response, Box<dyn Error>> {
+    let mut attempt = 0;
+    loop {
+        let (error, retry_after) = match build().send().await {
+            Ok(resp)
+                if resp.status() == StatusCode::REQUEST_TIMEOUT
+                    || resp.status() == StatusCode::TOO_MANY_REQUESTS
+                    || resp.status().is_server_error() =>
+            {
+                let retry_after = resp
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                (format!("server answered {}", resp.status()), retry_after)
+            }
+            Ok(resp) => return Ok(resp),
+            Err(e) => (e.to_string(), None),
+        };
+        if attempt == MAX_RETRIES {
+            return Err(format!("transfer failed after {} retries: {}", MAX_RETRIES, error).into());
+        }
+        let wait = backoff(attempt, retry_after);
+        attempt += 1;
+        warn!("{}, retrying in {:.1}s", error, wait.as_secs_f32());
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Writes the body of `resp` to `file` as it arrives. Returns the number of bytes written and, if
+/// the body was cut short, why; the bytes written before are kept.
+async fn stream_body(
+    mut resp: Response,
+    file: &mut File,
+    pb: &indicatif::ProgressBar,
+) -> Result<(u64, Option<String>), Box<dyn Error>> {
+    let mut written = 0;
+    loop {
+        match resp.chunk().await {
+            Ok(Some(bytes)) => {
+                file.write_all(&bytes)?;
+                written += bytes.len() as u64;
+                pb.inc(bytes.len() as u64);
+            }
+            Ok(None) => return Ok((written, None)),
+            Err(e) => return Ok((written, Some(e.to_string()))),
+        }
+    }
+}
+
+/// Downloads the artifact at `uri` to `path` in chunks of `chunk_size` bytes, streamed into a
+/// `.part` file and resuming from the one an interrupted download left behind. The artifact is
+/// only moved into place once complete and its hash matches `expected_hash`, when given. Returns
+/// the hash of the artifact.
+pub async fn download(
+    uri: &str,
+    path: &Path,
+    expected_hash: Option<&str>,
+    chunk_size: u64,
+) -> Result<String, Box<dyn Error>> {
+    if chunk_size == 0 {
+        return Err("the chunk size must be positive".into());
+    }
+    let part = sidecar(path, ".part");
+    let mut file = OpenOptions::new().create(true).append(true).open(&part)?;
+    let mut offset = file.metadata()?.len();
+    if offset > 0 {
+        info!("resuming download of {} from byte {}", uri, offset);
+    }
+
+    let client = reqwest::Client::new();
+    let pb = init_bar(0);
+    pb.set_position(offset);
+    pb.set_message(format!("Downloading {} ...", uri));
+    let mut total = None;
+    // consecutive chunks whose body was cut short before any byte of it landed
+    let mut stalled = 0;
+    while total.map_or(true, |t| offset < t) {
+        let range = format!("bytes={}-{}", offset, offset + chunk_size - 1);
+        let resp = request(|| client.get(uri).header(RANGE, range.clone())).await?;
+        let (written, interrupted) = match resp.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                total = content_range_total(resp.headers()).or(total);
+                if let Some(total) = total {
+                    pb.set_length(total);
+                }
+                let (written, interrupted) = stream_body(resp, &mut file, &pb).await?;
+                if interrupted.is_none() && written == 0 {
+                    return Err(format!("{} sent an empty chunk at byte {}", uri, offset).into());
+                }
+                offset += written;
+                // without a total size the last chunk is the first that comes back short
+                if total.is_none() && interrupted.is_none() && written < chunk_size {
+                    break;
+                }
+                (written, interrupted)
+            }
+            // the part file already holds the whole artifact
+            StatusCode::RANGE_NOT_SATISFIABLE => break,
+            // servers that ignore ranges send the whole artifact back
+            status if status.is_success() => {
+                file.set_len(0)?;
+                pb.set_position(0);
+                let (written, interrupted) = stream_body(resp, &mut file, &pb).await?;
+                offset = written;
+                if interrupted.is_none() {
+                    total = Some(offset);
+                }
+                (written, interrupted)
+            }
+            status => return Err(format!("failed to download {}: {}", uri, status).into()),
+        };
+        if let Some(error) = interrupted {
+            stalled = if written > 0 { 0 } else { stalled + 1 };
+            if stalled > MAX_RETRIES {
+                return Err(
+                    format!("download failed after {} retries: {}", MAX_RETRIES, error).into(),
+                );
+            }
+            let wait = backoff(stalled, None);
+            warn!("{}, resuming in {:.1}s", error, wait.as_secs_f32());
+            tokio::time::sleep(wait).await;
+        }
+    }
+    file.flush()?;
+    drop(file);
+    pb.finish_with_message("Downloaded.");
+
+    let hash = hash_file(&part)?;
+    if let Some(expected) = expected_hash {
+        if hash != expected {
+            warn!("removing download at {}", part.display());
+            std::fs::remove_file(&part)?;
+            return Err(format!(
+                "the hash {} of {} does not match the expected hash {}, the remote artifact may have been tampered with",
+                hash, uri, expected
+            )
+            .into());
+        }
+    }
+    std::fs::rename(&part, path)?;
+    Ok(hash)
+}
+
+/// The progress of an upload, recorded next to the artifact so an interrupted upload resumes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct UploadState {
+    uri: String,
+    sha256: String,
+    offset: u64,
+}
+
+/// Uploads the artifact at `path` to `uri` in chunks of `chunk_size` bytes. Returns the hash of
+/// the artifact.
+///
+/// Each chunk is PUT with a `Content-Range: bytes start-end/total` header and the hash of the whole
+/// artifact in [SHA256_HEADER], so the server can check it once the last chunk lands. The server
+/// answers `308` to chunks that leave the upload incomplete, along with a `Range: bytes=0-end`
+/// header of what it holds, and a success status once the upload is complete. An interrupted
+/// upload of the same artifact to the same `uri` asks the server what it holds and resumes from
+/// there.
+pub async fn upload(path: &Path, uri: &str, chunk_size: u64) -> Result<String, Box<dyn Error>> {
+    if chunk_size == 0 {
+        return Err("the chunk size must be positive".into());
+    }
+    let total = std::fs::metadata(path)?.len();
+    if total == 0 {
+        return Err(format!("{} is empty", path.display()).into());
+    }
+    let hash = hash_file(path)?;
+    let state_path = sidecar(path, ".upload");
+    let recorded = std::fs::read(&state_path)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<UploadState>(&b).ok())
+        .filter(|s| s.uri == uri && s.sha256 == hash);
+
+    let client = reqwest::Client::new();
+    let mut offset = 0;
+    if let Some(state) = recorded {
+        // the last chunk may or may not have landed, so ask the server what it holds
+        let reply = request(|| {
+            client
+                .put(uri)
+                .header(CONTENT_RANGE, format!("bytes */{}", total))
+                .header(SHA256_HEADER, hash.clone())
+        })
+        .await?;
+        offset = match reply.status().as_u16() {
+            RESUME_INCOMPLETE => received_bytes(reply.headers()).unwrap_or(0),
+            _ if reply.status().is_success() => total,
+            _ => state.offset,
+        };
+        info!("resuming upload of {} from byte {}", path.display(), offset);
+    }
+
+    let mut file = File::open(path)?;
+    let pb = init_bar(total);
+    pb.set_position(offset);
+    pb.set_message(format!("Uploading {} ...", path.display()));
+    while offset < total {
+        let len = chunk_size.min(total - offset);
+        let mut chunk = vec![0u8; len as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut chunk)?;
+
+        let content_range = format!("bytes {}-{}/{}", offset, offset + len - 1, total);
+        let reply = request(|| {
+            client
+                .put(uri)
+                .header(CONTENT_RANGE, content_range.clone())
+                .header(SHA256_HEADER, hash.clone())
+                .body(chunk.clone())
+        })
+        .await?;
+        offset = match reply.status().as_u16() {
+            RESUME_INCOMPLETE => received_bytes(reply.headers()).unwrap_or(offset + len),
+            _ if reply.status().is_success() => offset + len,
+            _ => {
+                return Err(format!(
+                    "failed to upload {} to {}: {}",
+                    path.display(),
+                    uri,
+                    reply.status()
+                )
+                .into())
+            }
+        };
+
+        let state = UploadState {
+            uri: uri.to_string(),
+            sha256: hash.clone(),
+            offset,
+        };
+        std::fs::write(&state_path, serde_json::to_vec(&state)?)?;
+        pb.set_position(offset);
+    }
+    pb.finish_with_message("Uploaded.");
+
+    if state_path.exists() {
+        std::fs::remove_file(&state_path)?;
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_transfer_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(content_range_total(&headers), None);
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-99/1000"));
+        assert_eq!(content_range_total(&headers), Some(1000));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 0-99/*"));
+        assert_eq!(content_range_total(&headers), None);
+
+        headers.insert(RANGE, HeaderValue::from_static("bytes=0-524287"));
+        assert_eq!(received_bytes(&headers), Some(524288));
+
+        assert_eq!(
+            sidecar(Path::new("keys/pk.key"), ".part"),
+            PathBuf::from("keys/pk.key.part")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifact");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
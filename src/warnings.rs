@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+/// Classes of non fatal issues, so pipelines can gate on them without parsing logs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WarningKind {
+    /// An operation is computed approximately, e.g a nonlinearity evaluated through a lookup table
+    Approximation,
+    /// Values were clamped or fall outside of the range the circuit supports
+    Clamped,
+    /// Nodes were removed from the graph
+    PrunedNode,
+    /// An invalid or missing value was replaced by a default
+    DefaultUsed,
+    /// An op could not be mapped and was replaced by a placeholder
+    UnsupportedOp,
+}
+
+/// A single non fatal issue.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Warning {
+    /// the class of the issue
+    pub kind: WarningKind,
+    /// human readable description
+    pub message: String,
+}
+
+thread_local! {
+    /// the warnings of the open [WarningScope]s of this thread, innermost last. The first entry
+    /// collects the warnings emitted outside of any scope.
+    static SCOPES: RefCell<Vec<Vec<Warning>>> = RefCell::new(vec![vec![]]);
+}
+
+/// Records a warning in the innermost [WarningScope] of this thread and mirrors it to the logs.
+pub fn emit(kind: WarningKind, message: impl Into<String>) {
+    let message = message.into();
+    log::warn!("{}", message);
+    let warning = Warning { kind, message };
+    SCOPES.with(|scopes| {
+        if let Some(warnings) = scopes.borrow_mut().last_mut() {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    });
+}
+
+/// Drains the warnings recorded in the innermost [WarningScope] since the last call, this is
+/// called at the end of each pipeline stage.
+pub fn take() -> Vec<Warning> {
+    SCOPES.with(|scopes| {
+        scopes
+            .borrow_mut()
+            .last_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    })
+}
+
+/// Scopes the warnings to a single call, so that concurrent calls (on other threads) and earlier
+/// calls (on this one) don't leak into its settings, witness or proof. Warnings emitted while the
+/// scope is open are dropped with it unless they were taken.
+pub struct WarningScope {
+    depth: usize,
+    // the scope lives in thread local storage, so the guard mustn't move threads
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Opens a [WarningScope] on this thread.
+pub fn scope() -> WarningScope {
+    let depth = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(vec![]);
+        scopes.len()
+    });
+    WarningScope {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for WarningScope {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.depth - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_scopes() {
+        emit(WarningKind::PrunedNode, "outer");
+        {
+            let _scope = scope();
+            emit(WarningKind::Clamped, "inner");
+            emit(WarningKind::Clamped, "inner");
+            // another thread has its own warnings
+            std::thread::spawn(|| {
+                emit(WarningKind::DefaultUsed, "other thread");
+                assert_eq!(take().len(), 1);
+            })
+            .join()
+            .unwrap();
+            let inner = take();
+            assert_eq!(inner.len(), 1);
+            assert_eq!(inner[0].message, "inner");
+            emit(WarningKind::Clamped, "dropped with the scope");
+        }
+        let outer = take();
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer[0].message, "outer");
+    }
+}
@@ -9,6 +9,7 @@ use crate::graph::scale_to_multiplier;
 use crate::graph::{GraphCircuit, GraphSettings};
 use crate::pfsys::create_proof_circuit;
 use crate::pfsys::evm::aggregation_kzg::PoseidonTranscript;
+use crate::pfsys::key_header::{read_key_header, KeyHeader};
 use crate::pfsys::verify_proof_circuit;
 use crate::pfsys::TranscriptType;
 use crate::tensor::TensorType;
@@ -28,6 +29,7 @@ use halo2_proofs::poly::kzg::{
     strategy::SingleStrategy as KZGSingleStrategy,
 };
 use halo2_proofs::poly::VerificationStrategy;
+use halo2_proofs::transcript::{Blake2bRead, Challenge255};
 use halo2_solidity_verifier::encode_calldata;
 use halo2curves::bn256::{Bn256, Fr, G1Affine};
 use halo2curves::ff::{FromUniformBytes, PrimeField};
@@ -60,7 +62,7 @@ pub fn encodeVerifierCalldata(
     proof: wasm_bindgen::Clamped<Vec<u8>>,
     vk_address: Option<Vec<u8>>,
 ) -> Result<Vec<u8>, JsError> {
-    let snark: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof[..])
+    let snark = crate::pfsys::Snark::<Fr, G1Affine>::from_reader(&mut &proof[..])
         .map_err(|e| JsError::new(&format!("Failed to deserialize proof: {}", e)))?;
 
     let vk_address: Option<[u8; 20]> = if let Some(vk_address) = vk_address {
@@ -269,6 +271,9 @@ pub fn genVk(
     .map_err(|e| JsError::new(&format!("Failed to create verifying key: {}", e)))?;
 
     let mut serialized_vk = Vec::new();
+    KeyHeader::new::<G1Affine>(vk.get_domain().k(), circuit.settings())
+        .write(&mut serialized_vk)
+        .map_err(|e| JsError::new(&format!("Failed to serialize vk header: {}", e)))?;
     vk.write(&mut serialized_vk, halo2_proofs::SerdeFormat::RawBytes)
         .map_err(|e| JsError::new(&format!("Failed to serialize vk: {}", e)))?;
 
@@ -294,6 +299,8 @@ pub fn genPk(
 
     // Read in verifying key
     let mut reader = std::io::BufReader::new(&vk[..]);
+    read_key_header::<G1Affine, _>(&mut reader, "verification key", circuit.settings())
+        .map_err(|e| JsError::new(&format!("Incompatible verification key: {}", e)))?;
     let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -306,6 +313,9 @@ pub fn genPk(
         .map_err(|e| JsError::new(&format!("Failed to create proving key: {}", e)))?;
 
     let mut serialized_pk = Vec::new();
+    KeyHeader::new::<G1Affine>(pk.get_vk().get_domain().k(), circuit.settings())
+        .write(&mut serialized_pk)
+        .map_err(|e| JsError::new(&format!("Failed to serialize pk header: {}", e)))?;
     pk.write(&mut serialized_pk, halo2_proofs::SerdeFormat::RawBytes)
         .map_err(|e| JsError::new(&format!("Failed to serialize pk: {}", e)))?;
 
@@ -321,12 +331,18 @@ pub fn verify(
     srs: wasm_bindgen::Clamped<Vec<u8>>,
 ) -> Result<bool, JsError> {
     let circuit_settings: GraphSettings = serde_json::from_slice(&settings[..])
+        .and_then(GraphSettings::from_value)
         .map_err(|e| JsError::new(&format!("Failed to deserialize settings: {}", e)))?;
 
-    let proof: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof_js[..])
+    let proof = crate::pfsys::Snark::<Fr, G1Affine>::from_reader(&mut &proof_js[..])
         .map_err(|e| JsError::new(&format!("Failed to deserialize proof: {}", e)))?;
+    proof
+        .check_settings(&circuit_settings)
+        .map_err(|e| JsError::new(&format!("Incompatible proof: {}", e)))?;
 
     let mut reader = std::io::BufReader::new(&vk[..]);
+    read_key_header::<G1Affine, _>(&mut reader, "verification key", &circuit_settings)
+        .map_err(|e| JsError::new(&format!("Incompatible verification key: {}", e)))?;
     let vk = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -361,6 +377,13 @@ pub fn verify(
                         PoseidonTranscript<NativeLoader, _>,
                     >(&proof, &params, &vk, strategy, orig_n)
                 }
+                TranscriptType::Blake2b => verify_proof_circuit::<
+                    VerifierSHPLONK<'_, Bn256>,
+                    KZGCommitmentScheme<Bn256>,
+                    KZGSingleStrategy<_>,
+                    _,
+                    Blake2bRead<_, G1Affine, Challenge255<_>>,
+                >(&proof, &params, &vk, strategy, orig_n),
             }
         }
         Commitments::IPA => {
@@ -385,6 +408,13 @@ pub fn verify(
                         PoseidonTranscript<NativeLoader, _>,
                     >(&proof, &params, &vk, strategy, orig_n)
                 }
+                TranscriptType::Blake2b => verify_proof_circuit::<
+                    VerifierIPA<_>,
+                    IPACommitmentScheme<G1Affine>,
+                    IPASingleStrategy<_>,
+                    _,
+                    Blake2bRead<_, G1Affine, Challenge255<_>>,
+                >(&proof, &params, &vk, strategy, orig_n),
             }
         }
     };
@@ -418,6 +448,8 @@ pub fn prove(
 
     // read in proving key
     let mut reader = std::io::BufReader::new(&pk[..]);
+    read_key_header::<G1Affine, _>(&mut reader, "proving key", circuit.settings())
+        .map_err(|e| JsError::new(&format!("Incompatible proving key: {}", e)))?;
     let pk = ProvingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -432,7 +464,7 @@ pub fn prove(
     let public_inputs = circuit
         .prepare_public_inputs(&data)
         .map_err(|e| JsError::new(&format!("{}", e)))?;
-    let proof_split_commits: Option<crate::pfsys::ProofSplitCommit> = data.into();
+    let proof_split_commits: Option<crate::pfsys::ProofSplitCommit> = (&data).into();
 
     // read in kzg params
     let mut reader = std::io::BufReader::new(&srs[..]);
@@ -462,6 +494,7 @@ pub fn prove(
                 TranscriptType::EVM,
                 proof_split_commits,
                 None,
+                None,
             )
         }
         Commitments::IPA => {
@@ -488,6 +521,7 @@ pub fn prove(
                 TranscriptType::EVM,
                 proof_split_commits,
                 None,
+                None,
             )
         }
     }
@@ -533,7 +567,7 @@ pub fn inputValidation(input: wasm_bindgen::Clamped<Vec<u8>>) -> Result<bool, Js
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 pub fn proofValidation(proof: wasm_bindgen::Clamped<Vec<u8>>) -> Result<bool, JsError> {
-    let _: crate::pfsys::Snark<Fr, G1Affine> = serde_json::from_slice(&proof[..])
+    let _ = crate::pfsys::Snark::<Fr, G1Affine>::from_reader(&mut &proof[..])
         .map_err(|e| JsError::new(&format!("Failed to deserialize proof: {}", e)))?;
 
     Ok(true)
@@ -546,8 +580,11 @@ pub fn vkValidation(
     settings: wasm_bindgen::Clamped<Vec<u8>>,
 ) -> Result<bool, JsError> {
     let circuit_settings: GraphSettings = serde_json::from_slice(&settings[..])
+        .and_then(GraphSettings::from_value)
         .map_err(|e| JsError::new(&format!("Failed to deserialize settings: {}", e)))?;
     let mut reader = std::io::BufReader::new(&vk[..]);
+    read_key_header::<G1Affine, _>(&mut reader, "verification key", &circuit_settings)
+        .map_err(|e| JsError::new(&format!("Incompatible verification key: {}", e)))?;
     let _ = VerifyingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -565,8 +602,11 @@ pub fn pkValidation(
     settings: wasm_bindgen::Clamped<Vec<u8>>,
 ) -> Result<bool, JsError> {
     let circuit_settings: GraphSettings = serde_json::from_slice(&settings[..])
+        .and_then(GraphSettings::from_value)
         .map_err(|e| JsError::new(&format!("Failed to deserialize settings: {}", e)))?;
     let mut reader = std::io::BufReader::new(&pk[..]);
+    read_key_header::<G1Affine, _>(&mut reader, "proving key", &circuit_settings)
+        .map_err(|e| JsError::new(&format!("Incompatible proving key: {}", e)))?;
     let _ = ProvingKey::<G1Affine>::read::<_, GraphCircuit>(
         &mut reader,
         halo2_proofs::SerdeFormat::RawBytes,
@@ -581,6 +621,7 @@ pub fn pkValidation(
 #[allow(non_snake_case)]
 pub fn settingsValidation(settings: wasm_bindgen::Clamped<Vec<u8>>) -> Result<bool, JsError> {
     let _: GraphSettings = serde_json::from_slice(&settings[..])
+        .and_then(GraphSettings::from_value)
         .map_err(|e| JsError::new(&format!("Failed to deserialize settings: {}", e)))?;
 
     Ok(true)
@@ -0,0 +1,304 @@
+use crate::graph::{GraphSettings, InstanceSegment};
+use crate::pfsys::encoded::encode_settings;
+use crate::pfsys::key_header::read_key_header;
+use crate::pfsys::srs::load_srs_verifier_trimmed;
+use clap::ValueEnum;
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2curves::bn256::{Bn256, G1Affine};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tosubcommand::ToFlags;
+
+/// The repository the generated contracts pull the verifier from
+const EZKL_REPOSITORY: &str = "https://github.com/zkonduit/ezkl";
+
+/// Version of `cosmwasm-std` the generated CosmWasm contracts depend on
+pub const COSMWASM_VERSION: &str = "1.5";
+
+/// Name of the directory the artifacts are copied to inside the contract crate
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// The smart contract platform a verifier contract is generated for. There is no ink! target, as
+/// ezkl's verifier needs std while pallet-contracts only runs no_std wasm
+#[derive(
+    ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq, Deserialize, Serialize, PartialOrd,
+)]
+pub enum ContractTarget {
+    /// CosmWasm, for Cosmos SDK chains
+    #[default]
+    #[value(name = "cosmwasm")]
+    CosmWasm,
+}
+
+impl std::fmt::Display for ContractTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ContractTarget::CosmWasm => "cosmwasm",
+            }
+        )
+    }
+}
+
+impl ToFlags for ContractTarget {
+    fn to_flags(&self) -> Vec<String> {
+        vec![format!("{}", self)]
+    }
+}
+
+/// The ezkl dependency of the generated crates: verification only, with the lookup argument the
+/// verifying key was generated with.
+fn ezkl_dependency() -> String {
+    let source = match env!("CARGO_PKG_VERSION") {
+        "0.0.0" => "branch = \"main\"".to_string(),
+        version => format!("tag = \"v{}\"", version),
+    };
+    let mut features = vec!["\"contract\""];
+    if cfg!(feature = "mv-lookup") {
+        features.push("\"mv-lookup\"");
+    }
+    format!(
+        "ezkl = {{ git = \"{}\", {}, default-features = false, features = [{}] }}",
+        EZKL_REPOSITORY,
+        source,
+        features.join(", ")
+    )
+}
+
+/// Generates the `Cargo.toml` of the contract crate.
+pub fn cargo_manifest(target: ContractTarget, name: &str) -> String {
+    let (dependencies, dev_dependencies) = match target {
+        ContractTarget::CosmWasm => (
+            format!(
+                "cosmwasm-std = \"{COSMWASM_VERSION}\"\ncosmwasm-schema = \"{COSMWASM_VERSION}\"\n"
+            ),
+            format!("cosmwasm-vm = \"{COSMWASM_VERSION}\"\n"),
+        ),
+    };
+    format!(
+        r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[dependencies]
+{ezkl}
+{dependencies}
+[dev-dependencies]
+{dev_dependencies}
+[profile.release]
+opt-level = "z"
+lto = true
+codegen-units = 1
+panic = "abort"
+"#,
+        ezkl = ezkl_dependency(),
+    )
+}
+
+/// Generates the `src/lib.rs` of a CosmWasm contract, whose `VerifyProof` query takes a proof and
+/// its instances as the Solidity verifier does.
+pub fn cosmwasm_contract(layout: &[InstanceSegment]) -> Result<String, Box<dyn Error>> {
+    let layout = serde_json::to_string(layout)?;
+
+    Ok(format!(
+        r##"use cosmwasm_schema::{{cw_serde, QueryResponses}};
+use cosmwasm_std::{{
+    entry_point, from_json, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, Uint256,
+}};
+
+const SETTINGS: &[u8] = include_bytes!("../{ARTIFACTS_DIR}/settings.bin");
+const VK: &[u8] = include_bytes!("../{ARTIFACTS_DIR}/vk.key");
+const SRS: &[u8] = include_bytes!("../{ARTIFACTS_DIR}/params.srs");
+const LAYOUT: &str = r#"{layout}"#;
+
+#[cw_serde]
+pub struct InstantiateMsg {{}}
+
+/// Where a public input, output or hash sits among the instances of a proof
+#[cw_serde]
+pub struct InstanceSegment {{
+    pub name: String,
+    pub shape: Vec<u64>,
+    /// fixed point scale of the elements, none for hashes
+    pub scale: Option<i32>,
+    pub offset: u64,
+}}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {{
+    /// Verifies a proof, given as the Solidity verifier takes it: the proof bytes and the flattened instances
+    #[returns(bool)]
+    VerifyProof {{
+        proof: Binary,
+        instances: Vec<Uint256>,
+    }},
+    /// The layout of the instances
+    #[returns(Vec<InstanceSegment>)]
+    Layout {{}},
+}}
+
+#[entry_point]
+pub fn instantiate(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> StdResult<Response> {{
+    Ok(Response::default())
+}}
+
+#[entry_point]
+pub fn query(_deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {{
+    match msg {{
+        QueryMsg::VerifyProof {{ proof, instances }} => {{
+            let instances = instances
+                .iter()
+                .map(|instance| instance.to_be_bytes())
+                .collect::<Vec<_>>();
+            let verified =
+                ezkl::pfsys::encoded::verify_encoded(&proof, &instances, SETTINGS, VK, SRS)
+                    .map_err(|e| StdError::generic_err(e.to_string()))?;
+            to_json_binary(&verified)
+        }}
+        QueryMsg::Layout {{}} => to_json_binary(&from_json::<Vec<InstanceSegment>>(LAYOUT)?),
+    }}
+}}
+"##
+    ))
+}
+
+/// Generates the `tests/integration.rs` of a CosmWasm contract, which instantiates the contract
+/// built for wasm32 in the CosmWasm VM. Compiling it there also runs the checks chains run on
+/// upload, which reject e.g float instructions.
+pub fn cosmwasm_test(name: &str, num_instances: usize) -> String {
+    let lib = name.replace('-', "_");
+
+    format!(
+        r#"//! Instantiates the contract in the CosmWasm VM. Build it for wasm32 first with
+//! `cargo build --release --lib --target wasm32-unknown-unknown`.
+
+use cosmwasm_std::{{from_json, Binary, Empty, Response, Uint256}};
+use cosmwasm_vm::testing::{{
+    instantiate, mock_env, mock_info, mock_instance_with_gas_limit, query,
+}};
+use {lib}::{{InstanceSegment, InstantiateMsg, QueryMsg}};
+
+static WASM: &[u8] = include_bytes!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/target/wasm32-unknown-unknown/release/{lib}.wasm"
+));
+
+const NUM_INSTANCES: usize = {num_instances};
+
+#[test]
+fn instantiate_and_query() {{
+    let mut deps = mock_instance_with_gas_limit(WASM, u64::MAX);
+    let info = mock_info("creator", &[]);
+    let res: Response<Empty> = instantiate(&mut deps, mock_env(), info, InstantiateMsg {{}}).unwrap();
+    assert!(res.messages.is_empty());
+
+    let layout = query(&mut deps, mock_env(), QueryMsg::Layout {{}}).unwrap();
+    from_json::<Vec<InstanceSegment>>(&layout).unwrap();
+
+    // a proof that doesn't verify is rejected, rather than failing the query
+    let msg = QueryMsg::VerifyProof {{
+        proof: Binary::from(vec![0u8; 32]),
+        instances: vec![Uint256::zero(); NUM_INSTANCES],
+    }};
+    let verified = query(&mut deps, mock_env(), msg).unwrap();
+    assert!(!from_json::<bool>(&verified).unwrap());
+}}
+"#
+    )
+}
+
+/// Writes a contract crate that verifies proofs for a single model on a non-EVM chain.
+/// # Arguments
+/// * `target` - the smart contract platform of the chain.
+/// * `output_dir` - directory the crate is written to, created if missing.
+/// * `name` - crate name.
+/// * `settings_path` - circuit settings of the model.
+/// * `vk_path` - verifying key of the model.
+/// * `srs_path` - SRS the verifying key was generated with. Only its verifier params are bundled
+///   in the contract, so its size doesn't grow with logrows.
+pub fn create_contract(
+    target: ContractTarget,
+    output_dir: &Path,
+    name: &str,
+    settings_path: &Path,
+    vk_path: &Path,
+    srs_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let settings = GraphSettings::load(&settings_path.to_path_buf())?;
+    let layout = settings.instance_layout();
+    // the contract can't tell a stale verifying key from a bad proof, so check it here
+    let vk = fs::read(vk_path)?;
+    read_key_header::<G1Affine, _>(&mut &vk[..], "verification key", &settings, false)?;
+    // only need G_0 for the verification with shplonk
+    let params: ParamsKZG<Bn256> =
+        load_srs_verifier_trimmed::<KZGCommitmentScheme<Bn256>>(srs_path.to_path_buf(), 1)?;
+
+    let artifacts = output_dir.join(ARTIFACTS_DIR);
+    fs::create_dir_all(&artifacts)?;
+    fs::create_dir_all(output_dir.join("src"))?;
+    fs::create_dir_all(output_dir.join("tests"))?;
+    fs::write(artifacts.join("vk.key"), vk)?;
+    params.write(&mut fs::File::create(artifacts.join("params.srs"))?)?;
+    fs::write(artifacts.join("settings.bin"), encode_settings(&settings)?)?;
+
+    fs::write(output_dir.join("Cargo.toml"), cargo_manifest(target, name))?;
+    let (contract, test) = match target {
+        ContractTarget::CosmWasm => (
+            cosmwasm_contract(&layout)?,
+            cosmwasm_test(name, settings.total_instances().iter().sum()),
+        ),
+    };
+    fs::write(output_dir.join("src").join("lib.rs"), contract)?;
+    fs::write(output_dir.join("tests").join("integration.rs"), test)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_sources() {
+        let layout = vec![
+            InstanceSegment {
+                name: "input_0".to_string(),
+                shape: vec![1, 3],
+                scale: Some(7),
+                offset: 0,
+            },
+            InstanceSegment {
+                name: "output_hash_0".to_string(),
+                shape: vec![1],
+                scale: None,
+                offset: 3,
+            },
+        ];
+
+        let cosmwasm = cosmwasm_contract(&layout).unwrap();
+        assert!(cosmwasm.contains(r#""name":"output_hash_0","shape":[1],"scale":null,"offset":3"#));
+        let test = cosmwasm_test("ezkl-verifier", 4);
+        assert!(test.contains("use ezkl_verifier::{"));
+        assert!(test.contains("release/ezkl_verifier.wasm"));
+
+        let manifest = cargo_manifest(ContractTarget::CosmWasm, "verifier");
+        assert!(manifest.contains("features = [\"contract\""));
+        assert!(manifest.contains("[dev-dependencies]\ncosmwasm-vm"));
+    }
+}
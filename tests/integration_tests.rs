@@ -28,6 +28,7 @@ mod native_tests {
         GrayGlacier,
         Paris,
         Shanghai,
+        Cancun,
         Latest,
     }
     lazy_static! {
@@ -53,6 +54,7 @@ mod native_tests {
             Hardfork::London => args.push("--hardfork=london"),
             Hardfork::Latest => {}
             Hardfork::Shanghai => args.push("--hardfork=shanghai"),
+            Hardfork::Cancun => args.push("--hardfork=cancun"),
             Hardfork::ArrowGlacier => args.push("--hardfork=arrowGlacier"),
             Hardfork::GrayGlacier => args.push("--hardfork=grayGlacier"),
         }
@@ -451,7 +453,16 @@ mod native_tests {
                 crate::native_tests::init_binary();
                 let test_dir = TempDir::new(test).unwrap();
                 let path = test_dir.path().to_str().unwrap(); crate::native_tests::mv_test_(path, test);
-                kzg_aggr_mock_prove_and_verify(path, test.to_string());
+                kzg_aggr_mock_prove_and_verify(path, test.to_string(), false);
+                test_dir.close().unwrap();
+            }
+
+            #(#[test_case(TESTS_AGGR[N])])*
+            fn kzg_aggr_universal_mock_prove_and_verify_(test: &str) {
+                crate::native_tests::init_binary();
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap(); crate::native_tests::mv_test_(path, test);
+                kzg_aggr_mock_prove_and_verify(path, test.to_string(), true);
                 test_dir.close().unwrap();
             }
 
@@ -508,6 +519,8 @@ mod native_tests {
             use crate::native_tests::run_js_tests;
             use crate::native_tests::render_circuit;
             use crate::native_tests::model_serialization_different_binaries;
+            #[cfg(feature = "onnxruntime")]
+            use crate::native_tests::prove_with_shadow_model;
             use rand::Rng;
             use tempdir::TempDir;
             use ezkl::Commitments;
@@ -523,6 +536,33 @@ mod native_tests {
                 test_dir.close().unwrap();
             }
 
+            #[test]
+            fn cosmwasm_verifier_() {
+                crate::native_tests::init_binary();
+                let test = "1l_mlp";
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap();
+                crate::native_tests::mv_test_(path, test);
+                prove_and_verify(path, test.to_string(), "safe", "private", "private", "public", 1, None, false, "single", Commitments::KZG, 2);
+                crate::native_tests::cosmwasm_verifier(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
+            #[cfg(feature = "onnxruntime")]
+            #[test]
+            fn kzg_prove_shadow_model_() {
+                crate::native_tests::init_binary();
+                let test = "1l_relu";
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap();
+                crate::native_tests::mv_test_(path, test);
+                crate::native_tests::mv_test_(path, "1l_sigmoid");
+                assert!(prove_with_shadow_model(path, test.to_string(), test));
+                // the outputs of a different model stop the proof
+                assert!(!prove_with_shadow_model(path, test.to_string(), "1l_sigmoid"));
+                test_dir.close().unwrap();
+            }
+
             seq!(N in 0..=5 {
             #(#[test_case(ACCURACY_CAL_TESTS[N])])*
             fn mock_accuracy_cal_tests(test: &str) {
@@ -968,6 +1008,354 @@ mod native_tests {
             use crate::native_tests::Hardfork;
             use crate::native_tests::run_js_tests;
 
+            #[test]
+            fn kzg_evm_paymaster_prove_and_verify_() {
+                let test = "1l_mlp";
+                crate::native_tests::init_binary();
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap(); crate::native_tests::mv_test_(path, test);
+                let _anvil_child = crate::native_tests::start_anvil(false, Hardfork::Latest);
+                crate::native_tests::kzg_evm_paymaster(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
+            #[cfg(feature = "blobs")]
+            #[test]
+            fn kzg_evm_blob_prove_and_verify_() {
+                let test = "1l_mlp";
+                crate::native_tests::init_binary();
+                let test_dir = TempDir::new(test).unwrap();
+                let path = test_dir.path().to_str().unwrap(); crate::native_tests::mv_test_(path, test);
+                let _anvil_child = crate::native_tests::start_anvil(false, Hardfork::Cancun);
+                kzg_evm_prove_and_verify(2, path, test.to_string(), "private", "private", "public");
+                crate::native_tests::kzg_evm_blob(path, test.to_string());
+                test_dir.close().unwrap();
+            }
+
+            #[tokio::test]
+            async fn evm_quantizer_matches_quantize_float_() {
+                // integers and their decimals, including ties, which round away from zero
+                let values: Vec<(i128, u32)> = vec![
+                    (0, 0), (3, 0), (6, 0), (-6, 0), (5, 1), (-5, 1), (15, 1), (-25, 1),
+                    (1, 1), (-1, 1), (125, 3), (123456, 3), (-123456, 3), (1000, 0),
+                ];
+                for scale in [0, 7, -2] {
+                    for bits in [16, 128] {
+                        let quantized = ezkl::eth::quantize_via_solidity(scale, bits, &values, None)
+                            .await
+                            .unwrap();
+                        for ((value, decimals), quantized) in values.iter().zip(quantized) {
+                            let float = *value as f64 / 10f64.powi(*decimals as i32);
+                            let expected = ezkl::fieldutils::quantize_float(&float, 0.0, scale)
+                                .ok()
+                                .filter(|q| {
+                                    let bound = 1u128 << (bits - 1);
+                                    q.unsigned_abs() < bound || (*q < 0 && q.unsigned_abs() == bound)
+                                });
+                            assert_eq!(quantized, expected, "{} / 10^{} at scale {} in {} bits", value, decimals, scale, bits);
+                        }
+                    }
+                }
+            }
+
+            #[tokio::test]
+            async fn evm_quantizer_is_exact_beyond_f64_() {
+                // value / 10^decimals * 2^scale, rounded half away from zero, in integers
+                let exact = |value: i128, decimals: u32, scale: i32| {
+                    let (numerator, denominator) = if scale >= 0 {
+                        (1i128 << scale, 10i128.pow(decimals))
+                    } else {
+                        (1, 10i128.pow(decimals) << -scale)
+                    };
+                    let q = (2 * value.abs() * numerator + denominator) / (2 * denominator);
+                    if value < 0 { -q } else { q }
+                };
+                // above 2^53, and just under or over ties that aren't exact in f64
+                let values: Vec<(i128, u32)> = vec![
+                    (9007199254740993, 0), (-9007199254740993, 0), (123456789012345678, 0),
+                    (2499999999999999999, 18), (-2499999999999999999, 18), (2500000000000000001, 18),
+                    (49999999999999999, 17), (15000000000000001, 16),
+                ];
+                for scale in [0, 7, -2] {
+                    let quantized = ezkl::eth::quantize_via_solidity(scale, 128, &values, None)
+                        .await
+                        .unwrap();
+                    for ((value, decimals), quantized) in values.iter().zip(quantized) {
+                        assert_eq!(quantized, Some(exact(*value, *decimals, scale)), "{} / 10^{} at scale {}", value, decimals, scale);
+                    }
+                }
+                // where f64 isn't exact, quantize_float diverges from the library
+                let float = 2499999999999999999f64 / 1e18;
+                assert_eq!(ezkl::fieldutils::quantize_float(&float, 0.0, 0).unwrap(), 3);
+                assert_eq!(exact(2499999999999999999, 18, 0), 2);
+                let float = 9007199254740993f64;
+                assert_eq!(ezkl::fieldutils::quantize_float(&float, 0.0, 0).unwrap(), 9007199254740992);
+            }
+
+            #[tokio::test]
+            async fn evm_quantize_unsigned_() {
+                use ethers::abi::Token;
+                use ethers::types::{Bytes, I256, U256};
+                use halo2curves::bn256::Fr;
+                use halo2curves::ff::PrimeField;
+
+                let (_anvil, client) = ezkl::eth::setup_eth_backend(None, None).await.unwrap();
+                // an infinite approval is above int256 max, and stays positive when decoded as a uint
+                let data = vec![
+                    Bytes::from(ethers::abi::encode(&[Token::Uint(U256::MAX)])),
+                    Bytes::from(ethers::abi::encode(&[Token::Int(I256::from(-5).into_raw())])),
+                ];
+                let decimals = vec![18, 1];
+                let quantized =
+                    ezkl::eth::evm_quantize(client, vec![0, 0], &(data, decimals), &[true, false])
+                        .await
+                        .unwrap();
+
+                let denom = U256::exp10(18);
+                let mut expected = U256::MAX / denom;
+                if (U256::MAX % denom) * 2 >= denom {
+                    expected += U256::one();
+                }
+                assert_eq!(quantized[0], Fr::from_str_vartime(&expected.to_string()).unwrap());
+                // -0.5 rounds away from zero
+                assert_eq!(quantized[1], -Fr::one());
+            }
+
+            #[tokio::test]
+            async fn evm_registry_revokes_versions_() {
+                use ethers::abi::{ParamType, Token};
+                use ethers::providers::Middleware;
+                use ethers::types::transaction::eip2718::TypedTransaction;
+                use ethers::types::{TransactionRequest, U256};
+
+                let (anvil, client) = ezkl::eth::setup_eth_backend(None, None).await.unwrap();
+                let rpc = anvil.endpoint();
+                let test_dir = TempDir::new("registry").unwrap();
+                // a verifier accepting the proofs whose first instance is the id of the vk
+                let mocks = r#"
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.17;
+
+contract MockVk {
+    uint256 public immutable id;
+
+    constructor(uint256 _id) {
+        id = _id;
+    }
+}
+
+contract MockVerifier {
+    function verifyProof(address vk, bytes calldata, uint256[] calldata instances) external view returns (bool) {
+        return instances[0] == MockVk(vk).id();
+    }
+}
+"#;
+                let mocks_path = test_dir.path().join("Mocks.sol");
+                std::fs::write(&mocks_path, mocks).unwrap();
+                let verifier = ezkl::eth::deploy_contract_via_solidity(mocks_path.clone(), Some(&rpc), 0, None, "MockVerifier")
+                    .await
+                    .unwrap();
+                let (abi, bytecode, _) = ezkl::eth::get_contract_artifacts(mocks_path, "MockVk", 0).unwrap();
+                let mut vks = vec![];
+                for id in [1u64, 2] {
+                    let factory = ethers::contract::ContractFactory::new(abi.clone(), bytecode.clone(), client.clone());
+                    vks.push(factory.deploy(U256::from(id)).unwrap().send().await.unwrap().address());
+                }
+
+                let registry_path = test_dir.path().join("Registry.sol");
+                std::fs::write(&registry_path, ezkl::eth::VERIFIER_REGISTRY_SOL).unwrap();
+                let registry = ezkl::eth::deploy_verifier_registry(registry_path, Some(&rpc), 0, None, verifier, vks[0])
+                    .await
+                    .unwrap();
+
+                let verify = |signature: &'static str, mut args: Vec<Token>, id: u64| {
+                    args.extend([Token::Bytes(vec![]), Token::Array(vec![Token::Uint(id.into())])]);
+                    let data = [&ethers::utils::id(signature)[..], &ethers::abi::encode(&args)].concat();
+                    let tx: TypedTransaction = TransactionRequest::default().to(registry).data(data).into();
+                    let client = client.clone();
+                    async move {
+                        let result = client.call(&tx, None).await.ok()?;
+                        ethers::abi::decode(&[ParamType::Bool], &result).ok()?.pop()?.into_bool()
+                    }
+                };
+                let verify_at = |version: u64, id: u64| verify("verifyProofAt(uint256,bytes,uint256[])", vec![Token::Uint(version.into())], id);
+                assert_eq!(verify("verifyProof(bytes,uint256[])", vec![], 1).await, Some(true));
+
+                // pushing a different vk moves the current version to it, the first stays verifiable
+                let version = ezkl::eth::update_registry_vk(registry, None, vks[1], Some(&rpc), None).await.unwrap();
+                assert_eq!(version, 1);
+                assert_eq!(verify("verifyProof(bytes,uint256[])", vec![], 1).await, Some(false));
+                assert_eq!(verify("verifyProof(bytes,uint256[])", vec![], 2).await, Some(true));
+                assert_eq!(verify_at(0, 1).await, Some(true));
+
+                // until the owner revokes it
+                let other = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+                assert!(ezkl::eth::revoke_registry_version(registry, 0, Some(&rpc), Some(other)).await.is_err());
+                ezkl::eth::revoke_registry_version(registry, 0, Some(&rpc), None).await.unwrap();
+                assert_eq!(verify_at(0, 1).await, None);
+                assert_eq!(verify_at(1, 2).await, Some(true));
+                // the current version can't be revoked
+                assert!(ezkl::eth::revoke_registry_version(registry, 1, Some(&rpc), None).await.is_err());
+                drop(anvil);
+            }
+
+            #[tokio::test]
+            async fn evm_multicall_keeps_msg_sender_() {
+                use ethers::providers::Middleware;
+                use ezkl::graph::input::CallsToAccount;
+
+                let (anvil, client) = ezkl::eth::setup_eth_backend(None, None).await.unwrap();
+                let rpc = anvil.endpoint();
+                let test_dir = TempDir::new("multicall").unwrap();
+                let sol = r#"
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.17;
+
+contract Caller {
+    function caller() external view returns (address) {
+        return msg.sender;
+    }
+}
+
+contract Multicall3 {
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        bytes callData;
+    }
+
+    struct Result {
+        bool success;
+        bytes returnData;
+    }
+
+    function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory results) {
+        results = new Result[](calls.length);
+        for (uint256 i = 0; i < calls.length; i++) {
+            (bool success, bytes memory data) = calls[i].target.call(calls[i].callData);
+            require(success || calls[i].allowFailure, "call failed");
+            results[i] = Result(success, data);
+        }
+    }
+}
+"#;
+                let path = test_dir.path().join("Caller.sol");
+                std::fs::write(&path, sol).unwrap();
+                let caller = ezkl::eth::deploy_contract_via_solidity(path.clone(), Some(&rpc), 0, None, "Caller")
+                    .await
+                    .unwrap();
+                let calls = vec![CallsToAccount {
+                    call_data: vec![(hex::encode(ethers::utils::id("caller()")), 0); 2],
+                    address: hex::encode(caller.as_bytes()),
+                    is_unsigned: vec![],
+                }];
+                let from: ethers::types::H160 = "0x00000000000000000000000000000000000000aa".parse().unwrap();
+                let read = || ezkl::eth::read_on_chain_inputs(client.clone(), from, &calls, None);
+
+                // without Multicall3 the calls are made one by one
+                let (results, _) = read().await.unwrap();
+                for result in &results {
+                    assert_eq!(ethers::types::H160::from_slice(&result[12..]), from);
+                }
+
+                // with Multicall3 they are batched, still from `from`
+                let multicall = ezkl::eth::deploy_contract_via_solidity(path, Some(&rpc), 0, None, "Multicall3")
+                    .await
+                    .unwrap();
+                let code = client.get_code(multicall, None).await.unwrap();
+                let address: ethers::types::H160 = format!("0x{}", ezkl::eth::MULTICALL3_ADDRESS).parse().unwrap();
+                client
+                    .provider()
+                    .request::<_, ()>("anvil_setCode", (address, code))
+                    .await
+                    .unwrap();
+                let (batched, _) = read().await.unwrap();
+                assert_eq!(batched, results);
+                drop(anvil);
+            }
+
+            #[tokio::test]
+            async fn evm_rpc_signer_() {
+                use ezkl::graph::input::FileSourceInner;
+
+                // anvil's accounts are unlocked, so it signs the transactions it's sent
+                let (anvil, client) = ezkl::eth::setup_eth_backend(None, Some("rpc")).await.unwrap();
+                assert!(matches!(client.signer(), ezkl::eth::EthSigner::Rpc(_)));
+                assert_eq!(client.address(), anvil.addresses()[0]);
+                // deploying the contract sends a transaction through eth_sendTransaction
+                let data = vec![vec![FileSourceInner::Float(1.5)]];
+                let calls = ezkl::eth::test_on_chain_data(client.clone(), &data).await.unwrap();
+                assert_eq!(calls[0].call_data.len(), 1);
+                drop(anvil);
+            }
+
+            #[tokio::test]
+            async fn evm_block_state_proof_() {
+                use ethers::providers::Middleware;
+                use ezkl::graph::input::{FileSourceInner, OnChainSnapshot, OnChainSource};
+
+                let (anvil, client) = ezkl::eth::setup_eth_backend(None, None).await.unwrap();
+                let data = vec![vec![FileSourceInner::Float(1.5), FileSourceInner::Float(-2.25)]];
+                let calls = ezkl::eth::test_on_chain_data(client.clone(), &data).await.unwrap();
+                let block = client.get_block_number().await.unwrap().as_u64();
+
+                let mut source = OnChainSource::new(calls, anvil.endpoint());
+                source.block_number = Some(block);
+                source.prove_storage = true;
+                let proof = ezkl::eth::fetch_block_state_proof(&source).await.unwrap();
+                assert_eq!(proof.block_number, block);
+                // reading arr(i) reads the length of arr and its element i
+                let account = proof
+                    .accounts
+                    .iter()
+                    .find(|a| a.address.trim_start_matches("0x") == source.calls[0].address)
+                    .unwrap();
+                assert_eq!(account.storage.len(), 3);
+                assert!(account.storage.iter().any(|slot| slot.value == "0x2"));
+
+                let mut tampered = proof.clone();
+                tampered.accounts[0].storage[0].value = "0x3".to_string();
+                assert!(ezkl::eth::verify_block_state_proof(&tampered).is_err());
+                let mut tampered = proof.clone();
+                tampered.accounts[0].balance = "0x1".to_string();
+                assert!(ezkl::eth::verify_block_state_proof(&tampered).is_err());
+
+                // replaying the snapshot doesn't query the rpc
+                let test_dir = TempDir::new("block_state_proof").unwrap();
+                let snapshot_path = test_dir.path().join("snapshot.json");
+                OnChainSnapshot {
+                    rpc: source.rpc.clone(),
+                    block_number: block,
+                    calls: vec![],
+                    scales: vec![],
+                    quantized: vec![],
+                    state_proof: Some(proof.clone()),
+                }
+                .save(&snapshot_path)
+                .unwrap();
+                source.rpc = "http://127.0.0.1:1".to_string();
+                source.snapshot = Some(snapshot_path.to_str().unwrap().to_string());
+                source.replay_snapshot = true;
+                assert_eq!(ezkl::eth::fetch_block_state_proof(&source).await.unwrap(), proof);
+                drop(anvil);
+            }
+
+            #[test]
+            fn evm_bind_caller_compiles_() {
+                let test_dir = TempDir::new("bind_caller").unwrap();
+                for params in ["", "address vk, "] {
+                    let verifier = format!("// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\ncontract Halo2Verifier {{\n    function verifyProof({}bytes calldata proof, uint256[] calldata instances) public returns (bool) {{\n        return proof.length > 0 && instances.length > 0;\n    }}\n}}\n", params);
+                    let bound = ezkl::eth::bind_caller_sol(&verifier).unwrap();
+                    let path = test_dir.path().join("Verifier.sol");
+                    std::fs::write(&path, bound).unwrap();
+                    let (abi, _, _) = ezkl::eth::get_contract_artifacts(path.clone(), "Halo2Verifier", 0).unwrap();
+                    assert!(abi.function("verifyProof").is_ok());
+                    assert_eq!(abi.function("verifyProofFor").unwrap().inputs[0].name, "caller");
+                    assert!(abi.function("verifier").is_ok());
+                    assert!(ezkl::eth::get_contract_artifacts(path, "Halo2VerifierCore", 0).is_ok());
+                }
+            }
+
             /// Currently only on chain inputs that return a non-negative value are supported.
             const TESTS_ON_CHAIN_INPUT: [&str; 17] = [
                 "1l_mlp",
@@ -1205,6 +1593,55 @@ mod native_tests {
     test_func_evm!();
     test_func_examples!();
 
+    // generates the CosmWasm verifier of a proven model, builds it for wasm32 against this tree and
+    // runs its tests, which instantiate it in the CosmWasm VM
+    fn cosmwasm_verifier(test_dir: &str, example_name: String) {
+        let contract_dir = format!("{}/{}/contract", test_dir, example_name);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "create-wasm-verifier",
+                "--settings-path",
+                &format!("{}/{}/settings.json", test_dir, example_name),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, example_name),
+                "--output-dir",
+                &contract_dir,
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let manifest_path = format!("{}/Cargo.toml", contract_dir);
+        let mut manifest = std::fs::read_to_string(&manifest_path).unwrap();
+        manifest.push_str(&format!(
+            "\n[patch.\"https://github.com/zkonduit/ezkl\"]\nezkl = {{ path = {:?} }}\n",
+            env!("CARGO_MANIFEST_DIR")
+        ));
+        std::fs::write(&manifest_path, manifest).unwrap();
+
+        let status = Command::new("cargo")
+            .args([
+                "build",
+                "--release",
+                "--lib",
+                "--target",
+                "wasm32-unknown-unknown",
+            ])
+            .current_dir(&contract_dir)
+            .env_remove("CARGO_TARGET_DIR")
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let status = Command::new("cargo")
+            .args(["test", "--release"])
+            .current_dir(&contract_dir)
+            .env_remove("CARGO_TARGET_DIR")
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+    }
+
     fn model_serialization_different_binaries(test_dir: &str, example_name: String) {
         let status = Command::new("cargo")
             .args([
@@ -1666,7 +2103,7 @@ mod native_tests {
     }
 
     // prove-serialize-verify, the usual full path
-    fn kzg_aggr_mock_prove_and_verify(test_dir: &str, example_name: String) {
+    fn kzg_aggr_mock_prove_and_verify(test_dir: &str, example_name: String, universal: bool) {
         prove_and_verify(
             test_dir,
             example_name.clone(),
@@ -1687,6 +2124,7 @@ mod native_tests {
                 "--logrows=23",
                 "--aggregation-snarks",
                 &format!("{}/{}/proof.pf", test_dir, example_name),
+                &format!("--universal={}", universal),
             ])
             .status()
             .expect("failed to execute process");
@@ -1987,6 +2425,68 @@ mod native_tests {
         assert!(status.success());
     }
 
+    // proves with --shadow-model and --scrub, returning whether the proof was emitted. The witness
+    // is scrubbed either way
+    #[cfg(feature = "onnxruntime")]
+    fn prove_with_shadow_model(test_dir: &str, example_name: String, shadow_name: &str) -> bool {
+        gen_circuit_settings_and_witness(
+            test_dir,
+            example_name.clone(),
+            "private",
+            "private",
+            "public",
+            1,
+            "resources",
+            None,
+            1,
+            false,
+            &mut 0.0,
+            Commitments::KZG,
+            2,
+        );
+
+        let settings_path = format!("{}/{}/settings.json", test_dir, example_name);
+        init_params(settings_path.into());
+
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "setup",
+                "-M",
+                &format!("{}/{}/network.compiled", test_dir, example_name),
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, example_name),
+                "--vk-path",
+                &format!("{}/{}/key.vk", test_dir, example_name),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let witness_path = format!("{}/{}/witness.json", test_dir, example_name);
+        let proof_path = format!("{}/{}/shadow.pf", test_dir, example_name);
+        let _ = std::fs::remove_file(&proof_path);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "prove",
+                "-W",
+                &witness_path,
+                "-M",
+                &format!("{}/{}/network.compiled", test_dir, example_name),
+                "--proof-path",
+                &proof_path,
+                "--pk-path",
+                &format!("{}/{}/key.pk", test_dir, example_name),
+                "--shadow-model",
+                &format!("{}/{}/network.onnx", test_dir, shadow_name),
+                "--scrub",
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(!std::path::Path::new(&witness_path).exists());
+        assert_eq!(std::path::Path::new(&proof_path).exists(), status.success());
+        status.success()
+    }
+
     // prove-serialize-verify, the usual full path
     fn kzg_evm_prove_and_verify(
         num_inner_columns: usize,
@@ -2213,6 +2713,500 @@ mod native_tests {
             .status()
             .expect("failed to execute process");
         assert!(!status.success());
+
+        // verify through a registry pointed at the verifier and the vk, at a stable address
+        let sol_arg_registry =
+            format!("--sol-code-path={}/{}/registry.sol", test_dir, example_name);
+        let abi_arg_registry = format!("--abi-path={}/{}/registry.abi", test_dir, example_name);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "create-evm-registry",
+                sol_arg_registry.as_str(),
+                abi_arg_registry.as_str(),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let addr_path_arg_registry = format!(
+            "--addr-path={}/{}/addr_registry.txt",
+            test_dir, example_name
+        );
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "deploy-evm-registry",
+                rpc_arg.as_str(),
+                sol_arg_registry.as_str(),
+                addr_path_arg_registry.as_str(),
+                deployed_addr_arg.as_str(),
+                deployed_addr_arg_vk.as_str(),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+
+        let addr_registry =
+            std::fs::read_to_string(format!("{}/{}/addr_registry.txt", test_dir, example_name))
+                .expect("failed to read address file");
+        let verify_via_registry = || {
+            Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+                .args([
+                    "verify-evm",
+                    "--proof-path",
+                    pf_arg.as_str(),
+                    rpc_arg.as_str(),
+                    &format!("--addr-verifier={}", addr_registry),
+                ])
+                .status()
+                .expect("failed to execute process")
+        };
+        assert!(verify_via_registry().success());
+
+        // pushing a vk keeps the registry's address and its verifier
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "update-evm-vk",
+                rpc_arg.as_str(),
+                &format!("--addr-registry={}", addr_registry),
+                deployed_addr_arg_vk.as_str(),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+        assert!(verify_via_registry().success());
+
+        // revoking the first version leaves the current one, which can't be revoked
+        let revoke = |version: &str| {
+            Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+                .args([
+                    "revoke-evm-vk",
+                    rpc_arg.as_str(),
+                    &format!("--addr-registry={}", addr_registry),
+                    &format!("--version={}", version),
+                ])
+                .status()
+                .expect("failed to execute process")
+        };
+        assert!(revoke("0").success());
+        assert!(!revoke("0").success());
+        assert!(!revoke("1").success());
+        assert!(verify_via_registry().success());
+    }
+
+    // the EntryPoint v0.7 of @account-abstraction/contracts, installed by pnpm install
+    fn entry_point_artifacts() -> (ethers::abi::Abi, ethers::types::Bytes) {
+        use ethers::solc::{Artifact, Project, ProjectPathsConfig, Solc, SolcConfig};
+        let root = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let node_modules = root.join("node_modules");
+        let paths = ProjectPathsConfig::builder()
+            .root(&root)
+            .sources(node_modules.join("@account-abstraction/contracts/core"))
+            .lib(&node_modules)
+            .remapping(
+                format!(
+                    "@openzeppelin/={}/",
+                    node_modules.join("@openzeppelin").display()
+                )
+                .parse()
+                .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let mut settings = ethers::solc::artifacts::Settings::default();
+        settings.optimizer.enabled = Some(true);
+        settings.optimizer.runs = Some(1_000_000);
+        // the entry point needs solc 0.8.23, installed with svm next to the default solc
+        let solc = Solc::find_svm_installed_version("0.8.23")
+            .unwrap()
+            .expect("solc 0.8.23 isn't installed");
+        let project = Project::builder()
+            .paths(paths)
+            .solc(solc)
+            .solc_config(SolcConfig::builder().settings(settings).build())
+            .no_auto_detect()
+            .ephemeral()
+            .no_artifacts()
+            .build()
+            .unwrap();
+        let output = project.compile().unwrap();
+        assert!(!output.has_compiler_errors(), "{}", output);
+        let (abi, bytecode, _) = output
+            .find_first("EntryPoint")
+            .unwrap()
+            .clone()
+            .into_parts_or_default();
+        (abi, bytecode)
+    }
+
+    // sponsors a user operation carrying a proof bound to it with a paymaster, through the
+    // handleOps of an EntryPoint v0.7
+    fn kzg_evm_paymaster(test_dir: &str, example_name: String) {
+        use ethers::abi::Token;
+        use ethers::contract::{Contract, ContractFactory};
+        use ethers::types::{H160, H256, U256};
+
+        let ezkl = |args: &[&str]| {
+            let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+                .args(args)
+                .status()
+                .expect("failed to execute process");
+            assert!(status.success());
+        };
+        let file = |name: &str| format!("{}/{}/{}", test_dir, example_name, name);
+        let settings_arg = format!("--settings-path={}", file("settings.json"));
+        let rpc_arg = format!("--rpc-url={}", *ANVIL_URL);
+
+        // the proof of an operation is bound to it through the caller instance
+        ezkl(&[
+            "gen-settings",
+            "-M",
+            &file("network.onnx"),
+            &settings_arg,
+            "--output-visibility=public",
+            "--bind-caller",
+        ]);
+        ezkl(&[
+            "calibrate-settings",
+            "--data",
+            &file("input.json"),
+            "-M",
+            &file("network.onnx"),
+            &settings_arg,
+        ]);
+        ezkl(&[
+            "compile-circuit",
+            "-M",
+            &file("network.onnx"),
+            "--compiled-circuit",
+            &file("network.compiled"),
+            &settings_arg,
+        ]);
+        init_params(file("settings.json").into());
+        ezkl(&[
+            "setup",
+            "-M",
+            &file("network.compiled"),
+            "--pk-path",
+            &file("key.pk"),
+            "--vk-path",
+            &file("key.vk"),
+            "--disable-selector-compression",
+        ]);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (_anvil, client) = runtime
+            .block_on(ezkl::eth::setup_eth_backend(Some(ANVIL_URL.as_str()), None))
+            .unwrap();
+        let (abi, bytecode) = entry_point_artifacts();
+        let entry_point = runtime.block_on(async {
+            let factory = ContractFactory::new(abi, bytecode, client.clone());
+            factory.deploy(()).unwrap().send().await.unwrap()
+        });
+        // an account accepting any operation, whose call counts the operations it executed
+        let account_sol = format!("{}/{}/Account.sol", test_dir, example_name);
+        std::fs::write(
+            &account_sol,
+            r#"
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.17;
+
+struct PackedUserOperation {
+    address sender;
+    uint256 nonce;
+    bytes initCode;
+    bytes callData;
+    bytes32 accountGasLimits;
+    uint256 preVerificationGas;
+    bytes32 gasFees;
+    bytes paymasterAndData;
+    bytes signature;
+}
+
+contract Account {
+    uint256 public pings;
+
+    function validateUserOp(PackedUserOperation calldata, bytes32, uint256) external pure returns (uint256) {
+        return 0;
+    }
+
+    function ping() external {
+        pings += 1;
+    }
+}
+"#,
+        )
+        .unwrap();
+        let (account_abi, account_bytecode, _) =
+            ezkl::eth::get_contract_artifacts(account_sol.into(), "Account", 0).unwrap();
+        let account = runtime.block_on(async {
+            let factory = ContractFactory::new(account_abi, account_bytecode, client.clone());
+            factory.deploy(()).unwrap().send().await.unwrap()
+        });
+        let call_data = ethers::utils::id("ping()").to_vec();
+
+        ezkl(&[
+            "gen-witness",
+            "-D",
+            &file("input.json"),
+            "-M",
+            &file("network.compiled"),
+            "-O",
+            &file("witness.json"),
+            &format!(
+                "--user-op={:#x}:0:0x{}",
+                account.address(),
+                hex::encode(&call_data)
+            ),
+        ]);
+        ezkl(&[
+            "prove",
+            "-W",
+            &file("witness.json"),
+            "-M",
+            &file("network.compiled"),
+            "--proof-path",
+            &file("proof.pf"),
+            "--pk-path",
+            &file("key.pk"),
+        ]);
+        ezkl(&[
+            "create-evm-verifier",
+            "--vk-path",
+            &file("key.vk"),
+            &settings_arg,
+            "--sol-code-path",
+            &file("kzg.sol"),
+        ]);
+        ezkl(&[
+            "deploy-evm-verifier",
+            &rpc_arg,
+            "--sol-code-path",
+            &file("kzg.sol"),
+            &format!("--addr-path={}", file("addr.txt")),
+        ]);
+        ezkl(&[
+            "create-evm-paymaster",
+            &settings_arg,
+            &format!("--sol-code-path={}", file("paymaster.sol")),
+            &format!("--abi-path={}", file("paymaster.abi")),
+        ]);
+        let verifier =
+            std::fs::read_to_string(file("addr.txt")).expect("failed to read address file");
+        ezkl(&[
+            "deploy-evm-paymaster",
+            &rpc_arg,
+            &format!("--sol-code-path={}", file("paymaster.sol")),
+            &format!("--addr-verifier={}", verifier),
+            &format!("--entry-point={:#x}", entry_point.address()),
+            &format!("--addr-path={}", file("addr_paymaster.txt")),
+        ]);
+        let paymaster: H160 = std::fs::read_to_string(file("addr_paymaster.txt"))
+            .expect("failed to read address file")
+            .trim()
+            .parse()
+            .unwrap();
+        let paymaster_abi: ethers::abi::Abi =
+            serde_json::from_reader(std::fs::File::open(file("paymaster.abi")).unwrap()).unwrap();
+        let paymaster = Contract::new(paymaster, paymaster_abi, client.clone());
+        let proof = ezkl::pfsys::Snark::load::<
+            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<halo2curves::bn256::Bn256>,
+        >(&file("proof.pf").into())
+        .unwrap();
+
+        // two 128 bit values packed in 32 bytes, as the gas limits and fees of operations
+        let pack = |high: u128, low: u128| [high.to_be_bytes(), low.to_be_bytes()].concat();
+        let user_op = |sender: H160, nonce: u64| {
+            Token::Tuple(vec![
+                Token::Address(sender),
+                Token::Uint(nonce.into()),
+                Token::Bytes(vec![]),
+                Token::Bytes(call_data.clone()),
+                Token::FixedBytes(pack(1_000_000, 100_000)),
+                Token::Uint(100_000.into()),
+                Token::FixedBytes(pack(1_000_000_000, 10_000_000_000)),
+                Token::Bytes(
+                    [
+                        paymaster.address().as_bytes(),
+                        &pack(3_000_000, 100_000),
+                        &ezkl::eth::paymaster_data(&proof),
+                    ]
+                    .concat(),
+                ),
+                Token::Bytes(vec![]),
+            ])
+        };
+        let handle_ops = |op: Token| {
+            entry_point
+                .method::<_, ()>("handleOps", (Token::Array(vec![op]), client.address()))
+                .unwrap()
+                .gas(15_000_000u64)
+        };
+
+        runtime.block_on(async {
+            let ether = U256::exp10(18);
+            for call in [
+                paymaster.method::<_, ()>("deposit", ()).unwrap(),
+                paymaster.method::<_, ()>("addStake", 1u32).unwrap(),
+            ] {
+                let receipt = call
+                    .value(ether)
+                    .send()
+                    .await
+                    .unwrap()
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(receipt.status, Some(1.into()));
+            }
+
+            // the proof is copied to another account's operation, or to a later one of the account
+            let other = H160::from_low_u64_be(0xaa);
+            assert!(handle_ops(user_op(other, 0)).call().await.is_err());
+            assert!(handle_ops(user_op(account.address(), 1))
+                .call()
+                .await
+                .is_err());
+
+            let receipt = handle_ops(user_op(account.address(), 0))
+                .send()
+                .await
+                .unwrap()
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(receipt.status, Some(1.into()));
+            let pings: U256 = account.method("pings", ()).unwrap().call().await.unwrap();
+            assert_eq!(pings, 1.into());
+            // postOp emitted the public outputs
+            let validated = ethers::utils::keccak256("ProofValidated(address,bytes32,uint256[])");
+            assert!(
+                receipt
+                    .logs
+                    .iter()
+                    .any(|log| log.address == paymaster.address()
+                        && log.topics[0] == validated.into())
+            );
+            let nullified: bool = paymaster
+                .method(
+                    "nullified",
+                    H256::from(ethers::utils::keccak256(&proof.proof)),
+                )
+                .unwrap()
+                .call()
+                .await
+                .unwrap();
+            assert!(nullified);
+            // the account's nonce moved on, so the operation can't be replayed either
+            assert!(handle_ops(user_op(account.address(), 0))
+                .call()
+                .await
+                .is_err());
+        });
+    }
+
+    // posts the proof of kzg_evm_prove_and_verify as a blob to a blob verifier, with the trusted
+    // setup of Ethereum's KZG ceremony in trusted_setup.txt
+    #[cfg(feature = "blobs")]
+    fn kzg_evm_blob(test_dir: &str, example_name: String) {
+        use ethers::abi::{ParamType, Token};
+        use ethers::providers::Middleware;
+        use ethers::types::transaction::eip2718::TypedTransaction;
+        use ethers::types::{TransactionRequest, H160, U256};
+        use halo2curves::ff::PrimeField;
+
+        let file = |name: &str| format!("{}/{}/{}", test_dir, example_name, name);
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "create-evm-blob-verifier",
+                &format!("--settings-path={}", file("settings.json")),
+                &format!("--sol-code-path={}", file("blob.sol")),
+                &format!("--abi-path={}", file("blob.abi")),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+        let verifier =
+            std::fs::read_to_string(file("addr.txt")).expect("failed to read address file");
+        let status = Command::new(format!("{}/release/ezkl", *CARGO_TARGET_DIR))
+            .args([
+                "deploy-evm-blob-verifier",
+                &format!("--rpc-url={}", *ANVIL_URL),
+                &format!("--sol-code-path={}", file("blob.sol")),
+                &format!("--addr-verifier={}", verifier),
+                &format!("--addr-path={}", file("addr_blob.txt")),
+            ])
+            .status()
+            .expect("failed to execute process");
+        assert!(status.success());
+        let blob_verifier: H160 = std::fs::read_to_string(file("addr_blob.txt"))
+            .expect("failed to read address file")
+            .trim()
+            .parse()
+            .unwrap();
+
+        let proof = ezkl::pfsys::Snark::load::<
+            halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme<halo2curves::bn256::Bn256>,
+        >(&file("proof.pf").into())
+        .unwrap();
+        let post = |proof| {
+            ezkl::eth::post_proof_blob(
+                proof,
+                blob_verifier,
+                std::path::Path::new("trusted_setup.txt"),
+                Some(ANVIL_URL.as_str()),
+                Some(ANVIL_DEFAULT_PRIVATE_KEY.as_str()),
+            )
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let versioned_hash = post(proof.clone()).await.unwrap();
+            let instances = proof
+                .instances
+                .iter()
+                .flatten()
+                .map(|i| Token::Uint(U256::from_little_endian(i.to_repr().as_ref())))
+                .collect::<Vec<_>>();
+            let (_anvil, client) = ezkl::eth::setup_eth_backend(Some(ANVIL_URL.as_str()), None)
+                .await
+                .unwrap();
+            let is_posted = |instances: Vec<Token>| {
+                let data = [
+                    &ethers::utils::id("isPosted(bytes32,uint256[])")[..],
+                    &ethers::abi::encode(&[
+                        Token::FixedBytes(versioned_hash.as_bytes().to_vec()),
+                        Token::Array(instances),
+                    ]),
+                ]
+                .concat();
+                let tx: TypedTransaction = TransactionRequest::default()
+                    .to(blob_verifier)
+                    .data(data)
+                    .into();
+                let client = client.clone();
+                async move {
+                    let result = client.call(&tx, None).await.unwrap();
+                    ethers::abi::decode(&[ParamType::Bool], &result).unwrap()[0]
+                        .clone()
+                        .into_bool()
+                        .unwrap()
+                }
+            };
+            assert!(is_posted(instances.clone()).await);
+            let mut other = instances;
+            other[0] = Token::Uint(U256::from(7));
+            assert!(!is_posted(other).await);
+
+            // an invalid proof isn't recorded, whatever blob carries it
+            let mut invalid = proof;
+            invalid.proof[0] ^= 1;
+            assert!(post(invalid).await.is_err());
+        });
     }
 
     // run js browser evm verify tests for a given example
@@ -2517,6 +3511,15 @@ mod native_tests {
         ];
         #[cfg(not(feature = "icicle"))]
         let args = ["build", "--release", "--bin", "ezkl"];
+        #[cfg(feature = "onnxruntime")]
+        let args = [
+            "build",
+            "--release",
+            "--bin",
+            "ezkl",
+            "--features",
+            "onnxruntime",
+        ];
         #[cfg(not(feature = "mv-lookup"))]
         let args = [
             "build",